@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::auth::Identity;
+use crate::services::versions::ApiVersion;
+
+/// How often tallies are written to `api_usage`. Usage shows up in the
+/// `apiUsage` query this long after the calls.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Days of usage kept.
+const RETENTION_DAYS: i32 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct UsageKey {
+    bucket: DateTime<Utc>,
+    client_id: String,
+    api_version: &'static str,
+    operation_type: &'static str,
+    operation: String,
+}
+
+#[derive(Debug, Default)]
+struct UsageTally {
+    client: String,
+    user_id: Option<Uuid>,
+    api_key_id: Option<Uuid>,
+    calls: i64,
+    errors: i64,
+    deprecated_calls: i64,
+    total_ms: i64,
+    max_ms: i32,
+    last_called_at: Option<DateTime<Utc>>,
+}
+
+/// A finished GraphQL call, as seen by the usage extension.
+pub struct Call<'a> {
+    pub identity: Option<&'a Identity>,
+    pub api_version: ApiVersion,
+    pub operation_type: &'static str,
+    pub operation: String,
+    pub elapsed: Duration,
+    pub failed: bool,
+    /// Used a deprecated field
+    pub deprecated: bool,
+}
+
+/// Per-client, per-operation call counts since the last flush. Recording
+/// only touches memory, so a client polling every second costs one map
+/// update per call rather than a database write.
+#[derive(Clone, Default)]
+pub struct ApiUsage {
+    pending: Arc<Mutex<HashMap<UsageKey, UsageTally>>>,
+}
+
+impl ApiUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, call: Call<'_>) {
+        let now = Utc::now();
+        let (client_id, client) = match call.identity {
+            Some(identity) => match identity.api_key_id {
+                Some(key_id) => (format!("api_key:{}", key_id), identity.username.clone()),
+                None => (
+                    format!("user:{}", identity.user_id),
+                    identity.username.clone(),
+                ),
+            },
+            None => ("anonymous".to_string(), "anonymous".to_string()),
+        };
+
+        let key = UsageKey {
+            bucket: now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now),
+            client_id,
+            api_version: call.api_version.as_str(),
+            operation_type: call.operation_type,
+            operation: call.operation.chars().take(255).collect(),
+        };
+        let elapsed_ms = i32::try_from(call.elapsed.as_millis()).unwrap_or(i32::MAX);
+
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        let tally = pending.entry(key).or_default();
+        tally.client = client;
+        tally.user_id = call.identity.map(|identity| identity.user_id);
+        tally.api_key_id = call.identity.and_then(|identity| identity.api_key_id);
+        tally.calls += 1;
+        tally.errors += i64::from(call.failed);
+        tally.deprecated_calls += i64::from(call.deprecated);
+        tally.total_ms += i64::from(elapsed_ms);
+        tally.max_ms = tally.max_ms.max(elapsed_ms);
+        tally.last_called_at = Some(now);
+    }
+
+    fn take(&self) -> HashMap<UsageKey, UsageTally> {
+        self.pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+}
+
+/// Background loop that adds the tallies into `api_usage` and drops hours
+/// past retention.
+pub async fn run(pool: PgPool, usage: ApiUsage) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = flush(&pool, &usage).await {
+            tracing::error!("❌ Failed to record API usage: {}", e);
+        }
+    }
+}
+
+async fn flush(pool: &PgPool, usage: &ApiUsage) -> Result<(), sqlx::Error> {
+    let pending = usage.take();
+
+    for (key, tally) in pending {
+        let Some(last_called_at) = tally.last_called_at else {
+            continue;
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO api_usage
+                (bucket, client_id, client, user_id, api_key_id, api_version, operation_type,
+                 operation, calls, errors, deprecated_calls, total_ms, max_ms, last_called_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            ON CONFLICT (bucket, client_id, api_version, operation_type, operation) DO UPDATE SET
+                client = EXCLUDED.client,
+                calls = api_usage.calls + EXCLUDED.calls,
+                errors = api_usage.errors + EXCLUDED.errors,
+                deprecated_calls = api_usage.deprecated_calls + EXCLUDED.deprecated_calls,
+                total_ms = api_usage.total_ms + EXCLUDED.total_ms,
+                max_ms = GREATEST(api_usage.max_ms, EXCLUDED.max_ms),
+                last_called_at = GREATEST(api_usage.last_called_at, EXCLUDED.last_called_at)
+            "#,
+            key.bucket,
+            key.client_id,
+            tally.client,
+            tally.user_id,
+            tally.api_key_id,
+            key.api_version,
+            key.operation_type,
+            key.operation,
+            tally.calls,
+            tally.errors,
+            tally.deprecated_calls,
+            tally.total_ms,
+            tally.max_ms,
+            last_called_at
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    sqlx::query!(
+        "DELETE FROM api_usage WHERE bucket < NOW() - make_interval(days => $1)",
+        RETENTION_DAYS
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::sync::{RwLock, broadcast};
+
+use crate::models::{AvailabilityBand, ProductAvailability};
+
+/// How often stock is checked for band changes. This is also the throttle:
+/// a product publishes at most one change per interval, however fast it sells.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Changes kept for subscribers that fall behind before older ones are dropped.
+const AVAILABILITY_BROADCAST_CAPACITY: usize = 256;
+
+/// Latest availability band of every catalog product, plus the channel that
+/// band changes are published on.
+#[derive(Clone)]
+pub struct AvailabilityFeed {
+    current: Arc<RwLock<HashMap<uuid::Uuid, ProductAvailability>>>,
+    changes: broadcast::Sender<ProductAvailability>,
+}
+
+impl AvailabilityFeed {
+    pub fn new() -> Self {
+        let (changes, _) = broadcast::channel(AVAILABILITY_BROADCAST_CAPACITY);
+        Self {
+            current: Arc::default(),
+            changes,
+        }
+    }
+
+    /// Current bands, by product name.
+    pub async fn snapshot(&self) -> Vec<ProductAvailability> {
+        let mut products: Vec<_> = self.current.read().await.values().cloned().collect();
+        products.sort_by(|a, b| a.name.cmp(&b.name));
+        products
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ProductAvailability> {
+        self.changes.subscribe()
+    }
+}
+
+impl Default for AvailabilityFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Background loop that recomputes catalog availability bands and publishes
+/// the ones that changed.
+pub async fn run(pool: PgPool, feed: AvailabilityFeed) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = refresh(&pool, &feed).await {
+            tracing::error!("❌ Failed to refresh product availability: {}", e);
+        }
+    }
+}
+
+fn band(available: &BigDecimal, reorder_point: &BigDecimal) -> AvailabilityBand {
+    if *available <= BigDecimal::from(0) {
+        AvailabilityBand::SoldOut
+    } else if available <= reorder_point {
+        AvailabilityBand::Low
+    } else {
+        AvailabilityBand::InStock
+    }
+}
+
+async fn refresh(pool: &PgPool, feed: &AvailabilityFeed) -> Result<(), sqlx::Error> {
+    // Same product set as the catalog query
+    let products = sqlx::query!(
+        r#"
+        SELECT
+            i.id, i.name,
+            i.available_stock as "available_stock!",
+            i.reorder_point
+        FROM inventory i
+        WHERE i.is_active = true
+            AND i.discontinued_at IS NULL
+            AND (i.category = 'finished_product'
+                OR EXISTS (SELECT 1 FROM pack_sizes ps WHERE ps.packaged_inventory_id = i.id))
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let now = Utc::now();
+    let mut current = feed.current.write().await;
+    let first_run = current.is_empty();
+    let mut seen = HashMap::with_capacity(products.len());
+
+    for product in products {
+        let band = band(&product.available_stock, &product.reorder_point);
+
+        let availability = match current.remove(&product.id) {
+            Some(previous) if previous.band == band => ProductAvailability {
+                name: product.name,
+                ..previous
+            },
+            previous => {
+                let availability = ProductAvailability {
+                    inventory_id: product.id,
+                    name: product.name,
+                    band,
+                    changed_at: now,
+                };
+                // Nobody needs a change event for the initial load
+                if previous.is_some() || !first_run {
+                    // No subscribers is not an error
+                    let _ = feed.changes.send(availability.clone());
+                }
+                availability
+            }
+        };
+
+        seen.insert(product.id, availability);
+    }
+
+    // Products dropped from the catalog are no longer for sale
+    for (_, mut gone) in current.drain() {
+        if gone.band != AvailabilityBand::SoldOut {
+            gone.band = AvailabilityBand::SoldOut;
+            gone.changed_at = now;
+            let _ = feed.changes.send(gone);
+        }
+    }
+
+    *current = seen;
+    Ok(())
+}
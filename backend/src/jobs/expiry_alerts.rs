@@ -0,0 +1,55 @@
+use sqlx::PgPool;
+
+use crate::services::lots;
+use crate::services::notifications::{Alert, Notifier};
+
+/// Lots expiring within this many days are included in the alert.
+const ALERT_WINDOW_DAYS: i32 = 7;
+
+/// Alert on lots that are expired or about to expire. Run by the scheduler
+/// (`expiry_scan`); returns what it found.
+pub async fn check(pool: &PgPool, notifier: &Notifier) -> Result<String, sqlx::Error> {
+    let lots = lots::expiring(pool, ALERT_WINDOW_DAYS).await?;
+
+    if lots.is_empty() {
+        return Ok(format!(
+            "No lots expiring within {} days",
+            ALERT_WINDOW_DAYS
+        ));
+    }
+
+    let expired = lots.iter().filter(|lot| lot.days_until_expiry < 0).count();
+    let message = format!(
+        "{} lot(s) expiring within {} days ({} already expired)",
+        lots.len(),
+        ALERT_WINDOW_DAYS,
+        expired
+    );
+    let summary = lots
+        .iter()
+        .map(|lot| {
+            format!(
+                "{} {} {} ({})",
+                lot.remaining_quantity,
+                lot.unit,
+                lot.item_name,
+                if lot.days_until_expiry < 0 {
+                    format!("expired {}", lot.expiry_date)
+                } else {
+                    format!("expires {}", lot.expiry_date)
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    notifier
+        .send(&Alert {
+            kind: "expiring_items".to_string(),
+            message: format!("{}: {}", message, summary),
+            data: serde_json::to_value(&lots).unwrap_or_default(),
+        })
+        .await;
+
+    Ok(message)
+}
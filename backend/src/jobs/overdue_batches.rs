@@ -0,0 +1,67 @@
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+
+use crate::models::BatchAlert;
+use crate::services::batches;
+use crate::services::notifications::{Alert, Notifier};
+
+/// Alert on batches still in progress past their estimated completion date.
+/// When `OVERDUE_BATCH_FLAG_DAYS` is set, batches that many days over are
+/// also flagged with an 'overdue' batch alert, published to `batchAlerts`
+/// subscribers. Run by the scheduler (`overdue_batch_check`); returns what it
+/// found.
+pub async fn check(
+    pool: &PgPool,
+    notifier: &Notifier,
+    batch_alerts: &broadcast::Sender<BatchAlert>,
+) -> Result<String, sqlx::Error> {
+    let batches = batches::overdue(pool, 0).await?;
+
+    if batches.is_empty() {
+        return Ok("No batches past their estimated completion".to_string());
+    }
+
+    let mut message = format!(
+        "{} batch(es) still in progress past their estimated completion",
+        batches.len()
+    );
+    let summary = batches
+        .iter()
+        .map(|batch| {
+            format!(
+                "{} {} (due {}, {} day(s) over)",
+                batch.batch_number,
+                batch.product_name,
+                batch.estimated_completion_date.date_naive(),
+                batch.days_overdue
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    notifier
+        .send(&Alert {
+            kind: "overdue_batches".to_string(),
+            message: format!("{}: {}", message, summary),
+            data: serde_json::to_value(&batches).unwrap_or_default(),
+        })
+        .await;
+
+    let flag_days = std::env::var("OVERDUE_BATCH_FLAG_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse::<i32>().ok());
+
+    if let Some(flag_days) = flag_days {
+        let flagged = batches::flag_overdue(pool, flag_days).await?;
+        if !flagged.is_empty() {
+            message = format!("{}; flagged {}", message, flagged.len());
+        }
+
+        for alert in flagged {
+            // No subscribers is fine; the alert is already persisted
+            let _ = batch_alerts.send(alert);
+        }
+    }
+
+    Ok(message)
+}
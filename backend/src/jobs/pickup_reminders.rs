@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::notifications::{Alert, Notifier};
+
+/// How often the job looks for pickups to remind customers about.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Customers who have missed this many pickups are flagged for a call ahead.
+const REPEAT_NO_SHOWS: i64 = 2;
+
+/// A booking to remind a customer about, as sent to the alert webhook.
+#[derive(Debug, Serialize)]
+struct PickupReminder {
+    booking_id: Uuid,
+    sale_number: String,
+    customer_name: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    pickup_date: chrono::NaiveDate,
+    start_time: chrono::NaiveTime,
+    end_time: chrono::NaiveTime,
+    no_show_count: i64,
+    /// Missed REPEAT_NO_SHOWS or more pickups before
+    call_ahead: bool,
+    /// Agreed to texted reminders
+    #[serde(skip)]
+    sms_opt_in: bool,
+    /// Whether the customer was texted the reminder
+    texted: bool,
+}
+
+/// Background loop that sends a reminder alert for pickups booked for today
+/// or tomorrow. Each booking is reminded once. Customers who opted in to texts
+/// are texted directly when an SMS provider is configured; a webhook receiver
+/// (Zapier, n8n) can reach the rest from the alert data.
+pub async fn run(pool: PgPool, notifier: Notifier) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = remind(&pool, &notifier).await {
+            tracing::error!("❌ Failed to send pickup reminders: {}", e);
+        }
+    }
+}
+
+async fn remind(pool: &PgPool, notifier: &Notifier) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let rows = sqlx::query!(
+        r#"
+        UPDATE pickup_bookings b
+        SET reminded_at = NOW()
+        FROM pickup_windows w, sales s
+        WHERE w.id = b.pickup_window_id
+            AND s.id = b.sale_id
+            AND b.status = 'booked'
+            AND b.reminded_at IS NULL
+            AND w.pickup_date BETWEEN CURRENT_DATE AND CURRENT_DATE + 1
+        RETURNING
+            b.id, b.customer_id, s.sale_number, w.pickup_date, w.start_time, w.end_time
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let mut reminders = Vec::with_capacity(rows.len());
+    for row in rows {
+        let customer = sqlx::query!(
+            r#"
+            SELECT
+                name, email, phone, sms_opt_in,
+                (SELECT COUNT(*) FROM pickup_bookings
+                 WHERE customer_id = c.id AND status = 'no_show') AS "no_shows!"
+            FROM customers c
+            WHERE id = $1
+            "#,
+            row.customer_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let no_show_count = customer.as_ref().map_or(0, |c| c.no_shows);
+        reminders.push(PickupReminder {
+            booking_id: row.id,
+            sale_number: row.sale_number,
+            customer_name: customer.as_ref().map(|c| c.name.clone()),
+            email: customer.as_ref().and_then(|c| c.email.clone()),
+            phone: customer.as_ref().and_then(|c| c.phone.clone()),
+            pickup_date: row.pickup_date,
+            start_time: row.start_time,
+            end_time: row.end_time,
+            no_show_count,
+            call_ahead: no_show_count >= REPEAT_NO_SHOWS,
+            sms_opt_in: customer.as_ref().is_some_and(|c| c.sms_opt_in),
+            texted: false,
+        });
+    }
+
+    tx.commit().await?;
+
+    let mut texted = 0;
+    for reminder in &mut reminders {
+        let Some(ref phone) = reminder.phone else {
+            continue;
+        };
+        if !reminder.sms_opt_in {
+            continue;
+        }
+
+        let body = format!(
+            "Your order {} is ready for pickup {} between {} and {}. Reply or call if you can't make it.",
+            reminder.sale_number,
+            reminder.pickup_date.format("%a %b %-d"),
+            reminder.start_time.format("%-I:%M%P"),
+            reminder.end_time.format("%-I:%M%P")
+        );
+
+        reminder.texted = notifier.text(phone, &body).await;
+        if reminder.texted {
+            texted += 1;
+        }
+    }
+
+    let call_ahead = reminders.iter().filter(|r| r.call_ahead).count();
+    notifier
+        .send(&Alert {
+            kind: "pickup_reminders".to_string(),
+            message: format!(
+                "{} pickup(s) booked for today or tomorrow ({} texted, {} with repeat no-shows to call ahead)",
+                reminders.len(),
+                texted,
+                call_ahead
+            ),
+            data: serde_json::to_value(&reminders).unwrap_or_default(),
+        })
+        .await;
+
+    Ok(())
+}
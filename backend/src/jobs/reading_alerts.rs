@@ -0,0 +1,67 @@
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::models::{BatchAlert, BatchReading};
+use crate::services::notifications::{Alert, Notifier};
+use crate::services::readings;
+
+/// Alerts kept for subscribers that fall behind before older ones are dropped.
+const ALERT_BROADCAST_CAPACITY: usize = 100;
+
+/// Handle for queueing new readings for threshold evaluation.
+///
+/// Recording a reading (mutation or sensor ingest) only enqueues it, so a slow
+/// alert webhook never holds up the request that stored the reading.
+#[derive(Clone)]
+pub struct ReadingQueue(mpsc::UnboundedSender<BatchReading>);
+
+impl ReadingQueue {
+    pub fn submit(&self, reading: BatchReading) {
+        if self.0.send(reading).is_err() {
+            tracing::error!("❌ Reading alert evaluator is not running; reading was not evaluated");
+        }
+    }
+}
+
+/// Create the reading queue and the broadcast channel alerts are published on.
+pub fn channels() -> (
+    ReadingQueue,
+    mpsc::UnboundedReceiver<BatchReading>,
+    broadcast::Sender<BatchAlert>,
+) {
+    let (queue, readings) = mpsc::unbounded_channel();
+    let (alerts, _) = broadcast::channel(ALERT_BROADCAST_CAPACITY);
+    (ReadingQueue(queue), readings, alerts)
+}
+
+/// Background loop that evaluates queued readings against recipe thresholds,
+/// persists alerts, publishes them to subscribers, and notifies the webhook.
+pub async fn run(
+    pool: PgPool,
+    notifier: Notifier,
+    mut readings: mpsc::UnboundedReceiver<BatchReading>,
+    alerts: broadcast::Sender<BatchAlert>,
+) {
+    while let Some(reading) = readings.recv().await {
+        let batch_alerts = match readings::evaluate(&pool, &reading).await {
+            Ok(batch_alerts) => batch_alerts,
+            Err(e) => {
+                tracing::error!("❌ Failed to evaluate reading {}: {}", reading.id, e);
+                continue;
+            }
+        };
+
+        for alert in batch_alerts {
+            notifier
+                .send(&Alert {
+                    kind: "reading_out_of_range".to_string(),
+                    message: alert.message.clone(),
+                    data: serde_json::to_value(&alert).unwrap_or_default(),
+                })
+                .await;
+
+            // No subscribers is fine; the alert is already persisted
+            let _ = alerts.send(alert);
+        }
+    }
+}
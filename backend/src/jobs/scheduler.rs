@@ -0,0 +1,336 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+
+use crate::jobs::{expiry_alerts, overdue_batches, stock_alerts, webhook_dispatch};
+use crate::models::{BatchAlert, MutationError, Rejection, ScheduledJob};
+use crate::services::cron::Schedule;
+use crate::services::notifications::Notifier;
+
+/// How often the scheduler looks for due jobs.
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A run that started this long ago without finishing is taken to have died
+/// with its server, and the job may be claimed again.
+const STALE_RUN_SECS: f64 = 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy)]
+enum Job {
+    ExpiryScan,
+    LowStockCheck,
+    OverdueBatchCheck,
+    WebhookRetrySweep,
+}
+
+struct JobSpec {
+    job: Job,
+    name: &'static str,
+    description: &'static str,
+    /// Cron expression (UTC) used unless `JOB_SCHEDULE_<NAME>` overrides it
+    default_schedule: &'static str,
+}
+
+/// Every job the scheduler knows. Daily checks run at 11:00 UTC, early
+/// morning in Frederick.
+const JOBS: &[JobSpec] = &[
+    JobSpec {
+        job: Job::ExpiryScan,
+        name: "expiry_scan",
+        description: "Alert on lots expired or expiring within a week",
+        default_schedule: "0 11 * * *",
+    },
+    JobSpec {
+        job: Job::LowStockCheck,
+        name: "low_stock_check",
+        description: "Alert on active items at or below their reorder point",
+        default_schedule: "0 11 * * *",
+    },
+    JobSpec {
+        job: Job::OverdueBatchCheck,
+        name: "overdue_batch_check",
+        description: "Alert on batches still in progress past their estimated completion, and flag long-overdue ones",
+        default_schedule: "0 11 * * *",
+    },
+    JobSpec {
+        job: Job::WebhookRetrySweep,
+        name: "webhook_retry_sweep",
+        description: "Requeue failed webhook deliveries once their receiver is taking deliveries again",
+        default_schedule: "15 * * * *",
+    },
+];
+
+/// Runs the registered jobs on their cron schedules, and on demand through
+/// `runJobNow`. Each job's schedule comes from `JOB_SCHEDULE_<NAME>` (e.g.
+/// `JOB_SCHEDULE_EXPIRY_SCAN="30 10 * * 1-5"`, or `off`), falling back to its
+/// default. Runs are claimed in `scheduled_jobs`, so with several servers each
+/// due run happens once.
+#[derive(Clone)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    pool: PgPool,
+    notifier: Notifier,
+    batch_alerts: broadcast::Sender<BatchAlert>,
+    schedules: Vec<(&'static JobSpec, Option<Schedule>)>,
+}
+
+impl Scheduler {
+    pub fn from_env(
+        pool: PgPool,
+        notifier: Notifier,
+        batch_alerts: broadcast::Sender<BatchAlert>,
+    ) -> Self {
+        let schedules = JOBS
+            .iter()
+            .map(|spec| {
+                let var = format!("JOB_SCHEDULE_{}", spec.name.to_uppercase());
+                let default = || spec.default_schedule.parse::<Schedule>().ok();
+
+                let schedule = match std::env::var(&var) {
+                    Ok(value) if value.trim().eq_ignore_ascii_case("off") => None,
+                    Ok(value) => value.parse::<Schedule>().map(Some).unwrap_or_else(|e| {
+                        tracing::error!(
+                            "❌ Invalid {}: {}; using \"{}\"",
+                            var,
+                            e,
+                            spec.default_schedule
+                        );
+                        default()
+                    }),
+                    Err(_) => default(),
+                };
+
+                (spec, schedule)
+            })
+            .collect();
+
+        Self {
+            inner: Arc::new(Inner {
+                pool,
+                notifier,
+                batch_alerts,
+                schedules,
+            }),
+        }
+    }
+
+    /// Record each job and its schedule. A job keeps its next run across
+    /// restarts (so a run missed while the server was down happens on
+    /// start), unless its schedule changed.
+    pub async fn register(&self) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+
+        for (spec, schedule) in &self.inner.schedules {
+            sqlx::query!(
+                r#"
+                INSERT INTO scheduled_jobs (name, description, schedule, next_run_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name) DO UPDATE
+                SET description = EXCLUDED.description,
+                    next_run_at = CASE
+                        WHEN scheduled_jobs.schedule IS NOT DISTINCT FROM EXCLUDED.schedule
+                            AND scheduled_jobs.next_run_at IS NOT NULL
+                        THEN scheduled_jobs.next_run_at
+                        ELSE EXCLUDED.next_run_at
+                    END,
+                    schedule = EXCLUDED.schedule,
+                    updated_at = NOW()
+                "#,
+                spec.name,
+                spec.description,
+                schedule.as_ref().map(Schedule::to_string),
+                schedule.as_ref().and_then(|s| s.next_after(now))
+            )
+            .execute(&self.inner.pool)
+            .await?;
+        }
+
+        let names: Vec<String> = JOBS.iter().map(|spec| spec.name.to_string()).collect();
+        sqlx::query!("DELETE FROM scheduled_jobs WHERE name <> ALL($1)", &names)
+            .execute(&self.inner.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Run a job straight away, whatever its schedule. Its next scheduled run
+    /// stays as it was.
+    pub async fn run_now(
+        &self,
+        name: &str,
+    ) -> Result<Result<ScheduledJob, Rejection>, sqlx::Error> {
+        let Some((spec, _)) = self
+            .inner
+            .schedules
+            .iter()
+            .find(|(spec, _)| spec.name == name)
+        else {
+            return Ok(Err(Rejection::new(
+                format!("No scheduled job named {}", name),
+                MutationError::not_found("Scheduled job"),
+            )));
+        };
+
+        if !self.execute(spec, None).await? {
+            return Ok(Err(format!("{} is already running", name).into()));
+        }
+
+        Ok(job(&self.inner.pool, name)
+            .await?
+            .ok_or_else(|| format!("No scheduled job named {}", name).into()))
+    }
+
+    /// Claim the job, run it and record the outcome. Scheduled runs (with
+    /// their schedule) only claim a due job and move its next run on; manual
+    /// runs claim it whenever it isn't already running. Returns false when
+    /// the job wasn't claimed.
+    async fn execute(
+        &self,
+        spec: &JobSpec,
+        schedule: Option<&Schedule>,
+    ) -> Result<bool, sqlx::Error> {
+        let pool = &self.inner.pool;
+        let scheduled = schedule.is_some();
+
+        let claimed = sqlx::query_scalar!(
+            r#"
+            UPDATE scheduled_jobs
+            SET running_since = NOW(), last_started_at = NOW(), last_trigger = $2,
+                updated_at = NOW()
+            WHERE name = $1
+                AND (NOT $3 OR next_run_at <= NOW())
+                AND (running_since IS NULL OR running_since < NOW() - make_interval(secs => $4))
+            RETURNING name
+            "#,
+            spec.name,
+            if scheduled { "schedule" } else { "manual" },
+            scheduled,
+            STALE_RUN_SECS
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if claimed.is_none() {
+            return Ok(false);
+        }
+
+        let notifier = &self.inner.notifier;
+        let outcome = async {
+            match spec.job {
+                Job::ExpiryScan => expiry_alerts::check(pool, notifier).await,
+                Job::LowStockCheck => stock_alerts::check(pool, notifier).await,
+                Job::OverdueBatchCheck => {
+                    overdue_batches::check(pool, notifier, &self.inner.batch_alerts).await
+                }
+                Job::WebhookRetrySweep => webhook_dispatch::retry_failed(pool)
+                    .await
+                    .map(|requeued| format!("Requeued {} failed webhook delivery(ies)", requeued)),
+            }
+        }
+        .instrument(tracing::info_span!(
+            "scheduled_job",
+            job = spec.name,
+            trigger = if scheduled { "schedule" } else { "manual" }
+        ))
+        .await;
+
+        let (status, message) = match outcome {
+            Ok(message) => {
+                tracing::info!("⏰ {}: {}", spec.name, message);
+                ("succeeded", message)
+            }
+            Err(e) => {
+                tracing::error!("❌ Scheduled job {} failed: {}", spec.name, e);
+                ("failed", e.to_string())
+            }
+        };
+        let next_run_at = schedule.and_then(|s| s.next_after(Utc::now()));
+
+        sqlx::query!(
+            r#"
+            UPDATE scheduled_jobs
+            SET running_since = NULL,
+                last_finished_at = NOW(),
+                last_status = $2::varchar,
+                last_message = $3,
+                run_count = run_count + 1,
+                failure_count = failure_count + CASE WHEN $2::varchar = 'failed' THEN 1 ELSE 0 END,
+                next_run_at = CASE WHEN $4 THEN $5 ELSE next_run_at END,
+                updated_at = NOW()
+            WHERE name = $1
+            "#,
+            spec.name,
+            status,
+            message,
+            scheduled,
+            next_run_at
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(true)
+    }
+}
+
+/// Background loop that runs each job when it comes due.
+pub async fn run(scheduler: Scheduler) {
+    if let Err(e) = scheduler.register().await {
+        tracing::error!("❌ Failed to register scheduled jobs: {}", e);
+    }
+
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        for (spec, schedule) in &scheduler.inner.schedules {
+            let Some(schedule) = schedule else {
+                continue;
+            };
+
+            if let Err(e) = scheduler.execute(spec, Some(schedule)).await {
+                tracing::error!("❌ Failed to run scheduled job {}: {}", spec.name, e);
+            }
+        }
+    }
+}
+
+/// Every scheduled job, by name.
+pub async fn jobs<'e>(executor: impl PgExecutor<'e>) -> Result<Vec<ScheduledJob>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduledJob,
+        r#"
+        SELECT name, description, schedule, next_run_at, running_since, last_started_at,
+            last_finished_at, last_status, last_message, last_trigger, run_count, failure_count
+        FROM scheduled_jobs
+        ORDER BY name
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// A scheduled job by name.
+pub async fn job<'e>(
+    executor: impl PgExecutor<'e>,
+    name: &str,
+) -> Result<Option<ScheduledJob>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduledJob,
+        r#"
+        SELECT name, description, schedule, next_run_at, running_since, last_started_at,
+            last_finished_at, last_status, last_message, last_trigger, run_count, failure_count
+        FROM scheduled_jobs
+        WHERE name = $1
+        "#,
+        name
+    )
+    .fetch_optional(executor)
+    .await
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::Notify;
+
+use crate::services::http::HttpClient;
+use crate::services::response_cache::ResponseCache;
+use crate::services::secrets::SecretStore;
+use crate::services::shopify::{self, SyncError};
+
+/// How often stock is pushed to Shopify and orders are pulled when no webhook
+/// arrives. Short, since stock sold here stays on sale online until then.
+const SYNC_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+/// Wakes the Shopify sync early, when a Shopify webhook says an order changed.
+#[derive(Clone, Default)]
+pub struct ShopifySync {
+    wake: Arc<Notify>,
+}
+
+impl ShopifySync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the sync now instead of at the next interval.
+    pub fn trigger(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Background loop that turns paid Shopify orders into sales and pushes
+/// available stock to Shopify. Does nothing until Shopify is connected.
+pub async fn run(
+    pool: PgPool,
+    http: HttpClient,
+    secrets: SecretStore,
+    sync: ShopifySync,
+    cache: ResponseCache,
+) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    let mut connected = true;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = sync.wake.notified() => {}
+        }
+
+        match shopify::sync(&pool, &http, &secrets).await {
+            Ok(summary) => {
+                connected = true;
+                if summary.imported > 0 {
+                    cache.invalidate();
+                }
+                if summary.fetched > 0 || summary.pushed > 0 || summary.conflicts > 0 {
+                    tracing::info!(
+                        "🛍️  Shopify: {} new order(s), {} imported, {} stock level(s) pushed, {} conflict(s)",
+                        summary.fetched,
+                        summary.imported,
+                        summary.pushed,
+                        summary.conflicts
+                    );
+                }
+            }
+            // Not connected is the normal state for installs without Shopify;
+            // say so once rather than every interval
+            Err(e @ SyncError::NotConnected) => {
+                if connected {
+                    tracing::info!("ℹ️  {}", e);
+                }
+                connected = false;
+            }
+            Err(e) => tracing::error!("❌ Shopify sync failed: {}", e),
+        }
+    }
+}
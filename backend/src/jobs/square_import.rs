@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::Notify;
+
+use crate::services::http::HttpClient;
+use crate::services::response_cache::ResponseCache;
+use crate::services::secrets::SecretStore;
+use crate::services::square::{self, SyncError};
+
+/// How often Square is checked for completed orders when no webhook arrives.
+const IMPORT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Wakes the Square import early, when a Square webhook says an order or
+/// payment changed.
+#[derive(Clone, Default)]
+pub struct SquareImport {
+    wake: Arc<Notify>,
+}
+
+impl SquareImport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the import now instead of at the next interval.
+    pub fn trigger(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Background loop that pulls completed Square orders and turns them into
+/// sales. Does nothing until Square is connected.
+pub async fn run(
+    pool: PgPool,
+    http: HttpClient,
+    secrets: SecretStore,
+    import: SquareImport,
+    cache: ResponseCache,
+) {
+    let mut interval = tokio::time::interval(IMPORT_INTERVAL);
+    let mut connected = true;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = import.wake.notified() => {}
+        }
+
+        match square::sync(&pool, &http, &secrets).await {
+            Ok(summary) => {
+                connected = true;
+                if summary.imported > 0 {
+                    cache.invalidate();
+                }
+                if summary.imported > 0 || summary.fetched > 0 {
+                    tracing::info!(
+                        "🟩 Square: {} new order(s), {} imported, {} waiting for a mapping",
+                        summary.fetched,
+                        summary.imported,
+                        summary.unmapped
+                    );
+                }
+            }
+            // Not connected is the normal state for installs without Square;
+            // say so once rather than every interval
+            Err(e @ SyncError::NotConnected) => {
+                if connected {
+                    tracing::info!("ℹ️  {}", e);
+                }
+                connected = false;
+            }
+            Err(e) => tracing::error!("❌ Square import failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use sqlx::PgPool;
+
+use crate::services::notifications::{Alert, Notifier};
+
+/// Alert on active items at or below their reorder point. Unlike the
+/// lowStock webhook, which fires once as an item crosses it, this lists every
+/// item still low each time it runs. Run by the scheduler (`low_stock_check`);
+/// returns what it found.
+pub async fn check(pool: &PgPool, notifier: &Notifier) -> Result<String, sqlx::Error> {
+    let items = sqlx::query!(
+        r#"
+        SELECT id, name, unit, available_stock AS "available_stock!", reorder_point
+        FROM inventory
+        WHERE is_active AND reorder_point > 0 AND available_stock <= reorder_point
+        ORDER BY available_stock / reorder_point, name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if items.is_empty() {
+        return Ok("No items at or below their reorder point".to_string());
+    }
+
+    let message = format!("{} item(s) at or below their reorder point", items.len());
+    let summary = items
+        .iter()
+        .map(|item| {
+            format!(
+                "{} ({} {} left, reorder at {})",
+                item.name, item.available_stock, item.unit, item.reorder_point
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    let data = items
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "inventory_id": item.id,
+                "name": item.name,
+                "unit": item.unit,
+                "available_stock": item.available_stock,
+                "reorder_point": item.reorder_point,
+            })
+        })
+        .collect();
+
+    notifier
+        .send(&Alert {
+            kind: "low_stock".to_string(),
+            message: format!("{}: {}", message, summary),
+            data: serde_json::Value::Array(data),
+        })
+        .await;
+
+    Ok(message)
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+/// How often the scheduler checks for new or due storage moves.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Background loop that turns storage transition rules into move tasks and
+/// reports moves that are due.
+pub async fn run(pool: PgPool) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match generate_move_tasks(&pool).await {
+            Ok(0) => {}
+            Ok(created) => tracing::info!("📦 Scheduled {} new batch move task(s)", created),
+            Err(e) => tracing::error!("❌ Failed to generate batch move tasks: {}", e),
+        }
+
+        if let Err(e) = report_due_moves(&pool).await {
+            tracing::error!("❌ Failed to check due batch moves: {}", e);
+        }
+    }
+}
+
+/// Create a pending move task for every in-progress batch whose recipe has
+/// storage transition rules. Moves that would fall on a production closure in
+/// the business calendar are pushed to the next open day. Existing tasks are
+/// left untouched, so this is safe to run repeatedly.
+pub async fn generate_move_tasks(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO batch_move_tasks (
+            batch_id, rule_id, from_location, to_location, due_date, notes
+        )
+        SELECT
+            b.id, r.id, r.from_location, r.to_location,
+            due.at + make_interval(
+                days => COALESCE(next_open_day(due.at::date, 'production'), due.at::date) - due.at::date
+            ),
+            r.notes
+        FROM production_batches b
+        JOIN storage_transition_rules r ON r.recipe_template_id = b.recipe_template_id
+        CROSS JOIN LATERAL (
+            SELECT b.start_date + make_interval(days => r.after_days) AS at
+        ) due
+        WHERE b.status = 'in_progress'
+        ON CONFLICT (batch_id, rule_id) DO NOTHING
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Log a reminder for every pending move that is due.
+async fn report_due_moves(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let due = sqlx::query!(
+        r#"
+        SELECT b.batch_number, t.to_location, t.due_date
+        FROM batch_move_tasks t
+        JOIN production_batches b ON b.id = t.batch_id
+        WHERE t.status = 'pending' AND t.due_date <= NOW()
+        ORDER BY t.due_date
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for task in due {
+        tracing::info!(
+            "⏰ Batch {} is due to move to {} (since {})",
+            task.batch_number,
+            task.to_location,
+            task.due_date.format("%Y-%m-%d")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::services::changes::Cursor;
+use crate::services::events;
+use crate::services::http::HttpClient;
+use crate::services::secrets::SecretStore;
+use crate::services::webhooks;
+
+/// How often due deliveries are sent and stock is checked for lowStock events.
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Attempts per delivery, including the first, before it is marked failed.
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Delay before the first retry; doubles after each failed attempt, so the
+/// last retry comes a little over two hours after the event.
+const INITIAL_BACKOFF_SECS: i64 = 60;
+
+/// Longest error text kept on a delivery.
+const MAX_ERROR_LENGTH: usize = 500;
+
+/// Failed deliveries for events older than this are left for `retryWebhookDelivery`.
+const RETRY_SWEEP_DAYS: i32 = 3;
+
+/// Background loop that posts queued webhook deliveries to their
+/// subscribers, signed with each subscription's secret, retrying failures
+/// with exponential backoff.
+pub async fn run(pool: PgPool, http: HttpClient, secrets: SecretStore) {
+    let mut interval = tokio::time::interval(DISPATCH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = events::detect_low_stock(&pool).await {
+            tracing::error!("❌ Failed to check stock for lowStock webhooks: {}", e);
+        }
+
+        loop {
+            match dispatch_next(&pool, &http, &secrets).await {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => {
+                    tracing::error!("❌ Failed to dispatch webhook delivery: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Send the oldest due delivery, if there is one. The row stays locked while
+/// it is sent, so several servers never post the same delivery twice.
+async fn dispatch_next(
+    pool: &PgPool,
+    http: &HttpClient,
+    secrets: &SecretStore,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let delivery = sqlx::query!(
+        r#"
+        SELECT d.id, d.subscription_id, d.attempts, e.id AS event_id, e.txid, e.event_type,
+            e.payload, e.occurred_at, s.url
+        FROM webhook_deliveries d
+        JOIN domain_events e ON e.id = d.event_id
+        JOIN webhook_subscriptions s ON s.id = d.subscription_id
+        WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND s.is_active
+        ORDER BY d.next_attempt_at
+        LIMIT 1
+        FOR UPDATE OF d SKIP LOCKED
+        "#
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(delivery) = delivery else {
+        return Ok(false);
+    };
+
+    let body = serde_json::json!({
+        "id": delivery.id,
+        "event": delivery.event_type,
+        "cursor": Cursor {
+            txid: delivery.txid,
+            id: delivery.event_id,
+        }
+        .to_string(),
+        "created_at": delivery.occurred_at,
+        "data": delivery.payload,
+    })
+    .to_string();
+
+    let outcome: Result<i32, (Option<i32>, String)> = match secrets
+        .get(&webhooks::secret_name(delivery.subscription_id))
+        .await
+    {
+        Ok(Some(secret)) => {
+            let timestamp = Utc::now().timestamp();
+            let sent = http
+                .client()
+                .post(&delivery.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Webhook-Event", &delivery.event_type)
+                .header("X-Webhook-Delivery", delivery.id.to_string())
+                .header("X-Webhook-Timestamp", timestamp.to_string())
+                .header(
+                    "X-Webhook-Signature",
+                    format!("sha256={}", webhooks::sign(&secret, timestamp, &body)),
+                )
+                .body(body)
+                .send()
+                .await;
+
+            match sent {
+                Ok(response) if response.status().is_success() => {
+                    Ok(response.status().as_u16() as i32)
+                }
+                Ok(response) => Err((
+                    Some(response.status().as_u16() as i32),
+                    format!("Receiver answered {}", response.status()),
+                )),
+                Err(e) => Err((None, e.to_string())),
+            }
+        }
+        Ok(None) => Err((None, "Signing secret is missing".to_string())),
+        Err(e) => Err((None, e.to_string())),
+    };
+
+    let attempts = delivery.attempts + 1;
+
+    match outcome {
+        Ok(status_code) => {
+            sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = 'delivered', attempts = $2, delivered_at = NOW(),
+                    last_status_code = $3, last_error = NULL
+                WHERE id = $1
+                "#,
+                delivery.id,
+                attempts,
+                status_code
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        Err((status_code, error)) => {
+            let gave_up = attempts >= MAX_ATTEMPTS;
+            let backoff = INITIAL_BACKOFF_SECS << (attempts - 1).min(20);
+            let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+
+            sqlx::query!(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = CASE WHEN $3 THEN 'failed' ELSE 'pending' END,
+                    attempts = $2,
+                    next_attempt_at = NOW() + make_interval(secs => $4),
+                    last_status_code = $5,
+                    last_error = $6,
+                    failed_at = CASE WHEN $3 THEN NOW() END
+                WHERE id = $1
+                "#,
+                delivery.id,
+                attempts,
+                gave_up,
+                backoff as f64,
+                status_code,
+                error
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if gave_up {
+                tracing::error!(
+                    "❌ Gave up on {} webhook delivery {} to {} after {} attempts: {}",
+                    delivery.event_type,
+                    delivery.id,
+                    delivery.url,
+                    attempts,
+                    error
+                );
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Requeue recent deliveries that gave up, to subscribers who have taken a
+/// delivery since (so the receiver is back), for a fresh round of attempts.
+/// Returns how many were requeued.
+pub async fn retry_failed(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let requeued = sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries d
+        SET status = 'pending', attempts = 0, next_attempt_at = NOW()
+        FROM webhook_subscriptions s
+        WHERE s.id = d.subscription_id
+            AND s.is_active
+            AND d.status = 'failed'
+            AND d.created_at > NOW() - make_interval(days => $1)
+            AND EXISTS (
+                SELECT 1 FROM webhook_deliveries ok
+                WHERE ok.subscription_id = d.subscription_id
+                    AND ok.status = 'delivered'
+                    AND ok.delivered_at > d.failed_at
+            )
+        "#,
+        RETRY_SWEEP_DAYS
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(requeued)
+}
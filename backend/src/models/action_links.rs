@@ -0,0 +1,58 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A short-lived, single-use link that lets one staff member confirm one
+/// batch transition from a phone without signing in. The token itself is
+/// only shown when the link is made.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ActionLink {
+    pub id: Uuid,
+    pub action: String, // 'advance_stage', 'complete_move'
+    pub batch_id: Uuid,
+    /// The move task confirmed by a 'complete_move' link
+    pub move_task_id: Option<Uuid>,
+    /// What confirming does, e.g. "Move batch FF-2024-052 to cold storage"
+    pub description: String,
+    /// Who confirms; the transition is recorded as theirs
+    pub user_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for making an action link.
+#[derive(Debug, InputObject)]
+pub struct CreateActionLinkInput {
+    /// 'advance_stage' (move the batch to its next recipe stage) or
+    /// 'complete_move' (confirm a scheduled storage move)
+    pub action: String,
+    /// Batch to advance ('advance_stage')
+    pub batch_id: Option<Uuid>,
+    /// Pending move task to confirm ('complete_move')
+    pub move_task_id: Option<Uuid>,
+    /// Staff member who will confirm (defaults to the signed-in user)
+    pub user_id: Option<Uuid>,
+    /// Minutes until the link stops working (default 30, at most 1440)
+    pub expires_in_minutes: Option<i32>,
+}
+
+/// Result from making or revoking an action link.
+#[derive(Debug, SimpleObject)]
+pub struct ActionLinkResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub link: Option<ActionLink>,
+    /// Address to open on the phone, shown only once when the link is made
+    pub url: Option<String>,
+    /// `url` as a QR code (SVG), shown only once when the link is made
+    pub qr_svg: Option<String>,
+}
@@ -0,0 +1,64 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A notice for app users: a "what's new" entry, a planned downtime banner,
+/// or a general notice. Shown from `publish_at` until `expires_at`.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct Announcement {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub kind: String, // 'whats_new', 'downtime', 'notice'
+    pub publish_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for posting an announcement.
+#[derive(Debug, InputObject)]
+pub struct CreateAnnouncementInput {
+    pub title: String,
+    pub body: String,
+    /// 'whats_new', 'downtime' or 'notice' (default)
+    pub kind: Option<String>,
+    /// When to start showing it (default: now)
+    pub publish_at: Option<DateTime<Utc>>,
+    /// When to stop showing it (default: until removed)
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Input for editing an announcement.
+#[derive(Debug, InputObject)]
+pub struct UpdateAnnouncementInput {
+    /// ID of the announcement to update
+    pub id: Uuid,
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub kind: Option<String>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Input for removing an announcement.
+#[derive(Debug, InputObject)]
+pub struct DeleteAnnouncementInput {
+    /// ID of the announcement to remove
+    pub id: Uuid,
+}
+
+/// Result from posting or editing an announcement.
+#[derive(Debug, SimpleObject)]
+pub struct AnnouncementResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub announcement: Option<Announcement>,
+}
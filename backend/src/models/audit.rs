@@ -0,0 +1,23 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// One row changed by a signed-in user or API key.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Who made the change (for API keys, the owner who created the key)
+    pub user_id: Uuid,
+    /// Username, or "<key name> (API key)"
+    pub actor: String,
+    /// Table the row is in (e.g. "inventory")
+    pub entity: String,
+    pub entity_id: Uuid,
+    pub operation: String, // 'insert', 'update', 'delete'
+    /// Changed columns as `{"column": {"old": ..., "new": ...}}`; the whole
+    /// row for inserts and deletes
+    pub diff: serde_json::Value,
+    pub changed_at: DateTime<Utc>,
+}
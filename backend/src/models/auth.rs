@@ -0,0 +1,160 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// Someone who can sign in to the app. The password hash never leaves the
+/// database layer.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: String, // 'owner', 'production', 'sales', 'read_only'
+    pub is_active: bool,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for signing in.
+#[derive(Debug, InputObject)]
+pub struct LoginInput {
+    pub username: String,
+    pub password: String,
+}
+
+/// Input for trading a refresh token for new tokens, or revoking it on sign out.
+#[derive(Debug, InputObject)]
+pub struct RefreshTokenInput {
+    pub refresh_token: String,
+}
+
+/// Input for adding a user (owner only).
+#[derive(Debug, InputObject)]
+pub struct CreateUserInput {
+    pub username: String,
+    /// At least 10 characters
+    pub password: String,
+    pub display_name: Option<String>,
+    /// 'owner', 'production', 'sales' or 'read_only' (default)
+    pub role: Option<String>,
+}
+
+/// Input for changing a user's role or access (owner only).
+#[derive(Debug, InputObject)]
+pub struct UpdateUserInput {
+    /// ID of the user to update
+    pub id: Uuid,
+    pub display_name: Option<String>,
+    /// 'owner', 'production', 'sales' or 'read_only'
+    pub role: Option<String>,
+    /// False signs the user out everywhere and blocks sign-in
+    pub is_active: Option<bool>,
+    /// Set a new password (at least 10 characters)
+    pub password: Option<String>,
+}
+
+/// Result from signing in or refreshing. Send the access token as
+/// `Authorization: Bearer <token>`; when it expires, call `refreshToken`.
+#[derive(Debug, SimpleObject)]
+pub struct AuthResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub access_token: Option<String>,
+    pub access_token_expires_at: Option<DateTime<Utc>>,
+    /// Single use: each refresh returns a new one
+    pub refresh_token: Option<String>,
+    pub user: Option<User>,
+}
+
+/// Result from adding or updating a user.
+#[derive(Debug, SimpleObject)]
+pub struct UserResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub user: Option<User>,
+}
+
+/// A key a device or script sends as `Authorization: Bearer ffk_...` instead
+/// of signing in. Only its hash is stored.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// Start of the key, to tell keys apart
+    pub prefix: String,
+    pub scope: String, // 'read_only', 'ingest', 'full'
+    pub created_by: Uuid,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for creating an API key (owner only).
+#[derive(Debug, InputObject)]
+pub struct CreateApiKeyInput {
+    /// What uses it (e.g. "walk-in probe", "weekly report script")
+    pub name: String,
+    /// 'read_only' (queries and exports), 'ingest' (posting readings only)
+    /// or 'full' (everything the owner can do)
+    pub scope: String,
+}
+
+/// Input for revoking an API key.
+#[derive(Debug, InputObject)]
+pub struct RevokeApiKeyInput {
+    /// ID of the API key to revoke
+    pub id: Uuid,
+}
+
+/// Result from creating or revoking an API key.
+#[derive(Debug, SimpleObject)]
+pub struct ApiKeyResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub api_key: Option<ApiKey>,
+    /// The key itself, shown only once when it's created
+    pub key: Option<String>,
+}
+
+/// How the signed-in user is notified. Everyone gets alerts through the alert
+/// webhook; these settings add texts.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct NotificationSettings {
+    /// E.164 phone number texts go to
+    pub phone: Option<String>,
+    /// Alert kinds texted to the phone
+    pub sms_alert_kinds: Vec<String>,
+    /// Alert kinds that can be texted
+    pub available_sms_alert_kinds: Vec<String>,
+    /// Whether an SMS provider is set up; texts aren't sent until it is
+    pub sms_configured: bool,
+}
+
+/// Input for changing the signed-in user's notification settings.
+#[derive(Debug, InputObject)]
+pub struct UpdateNotificationSettingsInput {
+    /// Phone number for texts (an empty string removes it)
+    pub phone: Option<String>,
+    /// Alert kinds to text; replaces the current list
+    pub sms_alert_kinds: Option<Vec<String>>,
+}
+
+/// Result from updating notification settings.
+#[derive(Debug, SimpleObject)]
+pub struct NotificationSettingsResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub settings: Option<NotificationSettings>,
+}
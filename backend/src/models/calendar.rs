@@ -0,0 +1,95 @@
+use async_graphql::*;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A market day, holiday, or production blackout on the business calendar.
+///
+/// Entries span one or more whole days. Holidays close both production and
+/// deliveries, blackouts (e.g. the week of the fair) close production only,
+/// and market days close nothing unless told to; the flags can be overridden
+/// per entry.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub id: Uuid,
+    pub kind: String, // 'market', 'holiday', 'blackout'
+    pub name: String,
+    pub start_date: NaiveDate,
+    /// Last day of the entry (inclusive)
+    pub end_date: NaiveDate,
+    pub blocks_production: bool,
+    pub blocks_deliveries: bool,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What the calendar says about a single day.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct BusinessDay {
+    pub date: NaiveDate,
+    /// Calendar entries covering the day
+    pub events: Vec<CalendarEvent>,
+    pub production_open: bool,
+    pub deliveries_open: bool,
+}
+
+/// Input for adding a calendar entry.
+#[derive(Debug, InputObject)]
+pub struct CreateCalendarEventInput {
+    /// 'market', 'holiday', or 'blackout'
+    pub kind: String,
+    /// Display name (e.g. "Great Frederick Fair")
+    pub name: String,
+    /// First day of the entry
+    pub start_date: NaiveDate,
+    /// Last day of the entry (defaults to start_date)
+    pub end_date: Option<NaiveDate>,
+    /// Whether production is closed (defaults to true for holidays and blackouts)
+    pub blocks_production: Option<bool>,
+    /// Whether deliveries are closed (defaults to true for holidays)
+    pub blocks_deliveries: Option<bool>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for updating a calendar entry.
+#[derive(Debug, InputObject)]
+pub struct UpdateCalendarEventInput {
+    /// ID of the entry to update
+    pub id: Uuid,
+    /// Optional new kind ('market', 'holiday', 'blackout')
+    pub kind: Option<String>,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Optional new first day
+    pub start_date: Option<NaiveDate>,
+    /// Optional new last day
+    pub end_date: Option<NaiveDate>,
+    /// Optional new production flag
+    pub blocks_production: Option<bool>,
+    /// Optional new deliveries flag
+    pub blocks_deliveries: Option<bool>,
+    /// Optional new notes
+    pub notes: Option<String>,
+}
+
+/// Input for removing a calendar entry.
+#[derive(Debug, InputObject)]
+pub struct DeleteCalendarEventInput {
+    /// ID of the entry to remove
+    pub id: Uuid,
+}
+
+/// Result from creating or updating a calendar entry.
+#[derive(Debug, SimpleObject)]
+pub struct CalendarEventResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub event: Option<CalendarEvent>,
+}
@@ -0,0 +1,153 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// Locale used when a product has no translation for the requested one.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Customer-facing text for a product in one locale.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ProductTranslation {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    /// Locale code (e.g. "en", "es", "es-MX")
+    pub locale: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Text printed on the jar label
+    pub label_text: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A product as shown in the catalog or on a label, in the requested locale.
+///
+/// Falls back from the exact locale to its base language (es-MX -> es),
+/// then to English, then to the inventory item's own name.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct LocalizedProduct {
+    pub inventory_id: Uuid,
+    /// Locale the text actually came from (None if no translation exists)
+    pub locale: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub label_text: Option<String>,
+    pub unit: String,
+    pub available_stock: BigDecimal,
+}
+
+/// Input for adding or replacing a product translation.
+#[derive(Debug, InputObject)]
+pub struct UpsertProductTranslationInput {
+    /// ID of the inventory item being translated
+    pub inventory_id: Uuid,
+    /// Locale code (e.g. "es" or "es-MX")
+    pub locale: String,
+    /// Translated product name
+    pub name: String,
+    /// Optional translated description
+    pub description: Option<String>,
+    /// Optional translated label text
+    pub label_text: Option<String>,
+}
+
+/// Input for deleting a product translation.
+#[derive(Debug, InputObject)]
+pub struct DeleteProductTranslationInput {
+    /// ID of the translation to delete
+    pub id: Uuid,
+}
+
+/// Result from adding or replacing a product translation.
+#[derive(Debug, SimpleObject)]
+pub struct ProductTranslationResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The saved translation (if successful)
+    pub translation: Option<ProductTranslation>,
+}
+
+/// How much of a catalog product is left, as a band rather than a quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+pub enum AvailabilityBand {
+    InStock,
+    /// At or below the item's reorder point
+    Low,
+    SoldOut,
+}
+
+/// Public availability of a catalog product. Exact stock is never exposed.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ProductAvailability {
+    pub inventory_id: Uuid,
+    pub name: String,
+    pub band: AvailabilityBand,
+    /// When the product entered this band (as observed by the server)
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Sell-through status of a discontinued product.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct DiscontinuationReport {
+    pub inventory_id: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub discontinued_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    /// Stock left to sell through
+    pub current_stock: BigDecimal,
+    pub reserved_stock: BigDecimal,
+    /// Planned size of batches still in progress (they can finish and be sold)
+    pub in_production: BigDecimal,
+    /// Quantity sold since it was discontinued
+    pub sold_since: BigDecimal,
+    pub revenue_since: BigDecimal,
+    pub last_sold_at: Option<DateTime<Utc>>,
+    /// Whether nothing is left on hand or in production
+    pub sold_through: bool,
+    /// Kits that still list it as a component
+    pub kits: Vec<String>,
+    /// Upcoming workshops whose materials kit includes it
+    pub workshops: Vec<String>,
+    /// Packaged SKUs of it, discontinued along with it
+    pub pack_sizes: Vec<String>,
+    /// Open customer orders (drafts and confirmed) for it or its packaged
+    /// SKUs, e.g. "ORD-20261015-001 for Test Bistro on 2026-10-18". Drafts
+    /// can no longer be confirmed; confirmed ones still hold their stock.
+    pub orders: Vec<String>,
+}
+
+/// Input for discontinuing a product.
+#[derive(Debug, InputObject)]
+pub struct DiscontinueProductInput {
+    pub inventory_id: Uuid,
+    /// Optional reason (e.g. "Seasonal flavor retired")
+    pub reason: Option<String>,
+    /// Deactivate the recipes that make it (default: true)
+    pub deactivate_recipes: Option<bool>,
+}
+
+/// Input for bringing a discontinued product back.
+#[derive(Debug, InputObject)]
+pub struct ReinstateProductInput {
+    pub inventory_id: Uuid,
+}
+
+/// Result from discontinuing or reinstating a product.
+#[derive(Debug, SimpleObject)]
+pub struct DiscontinueProductResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub report: Option<DiscontinuationReport>,
+}
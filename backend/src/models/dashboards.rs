@@ -0,0 +1,85 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A saved dashboard layout. Each user keeps their own (e.g. the owner's
+/// revenue view, the production lead's batch view).
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Dashboard {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// Widgets in display order
+    pub widgets: Vec<DashboardWidget>,
+    /// Opened first; at most one per user
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One widget on a dashboard.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct DashboardWidget {
+    /// What the widget shows (e.g. "revenue_summary", "active_batches")
+    pub kind: String,
+    /// Heading shown instead of the widget's usual one
+    pub title: Option<String>,
+    /// Grid columns the widget spans (1-4)
+    pub width: i32,
+    /// Widget-specific filters (e.g. {"channel": "wholesale", "days": 30})
+    pub filters: serde_json::Value,
+}
+
+/// A widget to save on a dashboard.
+#[derive(Debug, InputObject)]
+pub struct DashboardWidgetInput {
+    pub kind: String,
+    /// Optional custom heading
+    pub title: Option<String>,
+    /// Grid columns to span (1-4, defaults to 1)
+    pub width: Option<i32>,
+    /// Optional filters, as a JSON object
+    pub filters: Option<serde_json::Value>,
+}
+
+/// Input for saving a new dashboard for the signed-in user.
+#[derive(Debug, InputObject)]
+pub struct CreateDashboardInput {
+    /// Name, unique per user (e.g. "Revenue")
+    pub name: String,
+    /// Widgets in display order
+    pub widgets: Vec<DashboardWidgetInput>,
+    /// Open this dashboard first (defaults to true for a user's first dashboard)
+    pub is_default: Option<bool>,
+}
+
+/// Input for updating one of the signed-in user's dashboards.
+#[derive(Debug, InputObject)]
+pub struct UpdateDashboardInput {
+    pub id: Uuid,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Replaces the widget list when given
+    pub widgets: Option<Vec<DashboardWidgetInput>>,
+    /// Set true to open this dashboard first
+    pub is_default: Option<bool>,
+}
+
+/// Input for deleting one of the signed-in user's dashboards.
+#[derive(Debug, InputObject)]
+pub struct DeleteDashboardInput {
+    pub id: Uuid,
+}
+
+/// Result from saving a dashboard.
+#[derive(Debug, SimpleObject)]
+pub struct DashboardResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub dashboard: Option<Dashboard>,
+}
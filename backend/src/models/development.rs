@@ -0,0 +1,169 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+use crate::services::development;
+
+/// A product in development, moving through the pipeline
+/// concept → trials → costing → compliance → launched (or shelved).
+/// Each step forward passes a stage gate; see `blockers`.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct ProductDevelopment {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub stage: String, // 'concept', 'trials', 'costing', 'compliance', 'launched', 'shelved'
+    /// Candidate recipe under trial
+    pub recipe_template_id: Option<Uuid>,
+    pub recipe_name: Option<String>,
+    /// Product it launches as
+    pub product_inventory_id: Option<Uuid>,
+    pub target_launch_date: Option<NaiveDate>,
+    /// Ingredient cost per unit when it passed the costing gate
+    pub costed_unit_cost: Option<BigDecimal>,
+    /// When the label and compliance review was signed off
+    pub label_approved_at: Option<DateTime<Utc>>,
+    pub launched_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub stage_changed_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl ProductDevelopment {
+    /// Trial batches made for this product, oldest first
+    async fn trial_batches(&self, ctx: &Context<'_>) -> Result<Vec<TrialBatch>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let batches = sqlx::query_as!(
+            TrialBatch,
+            r#"
+            SELECT
+                id AS batch_id, batch_number, batch_size, unit, status,
+                start_date, completion_date, yield_percentage, quality_notes
+            FROM production_batches
+            WHERE development_id = $1
+            ORDER BY start_date
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// Stage changes, oldest first
+    async fn stage_history(&self, ctx: &Context<'_>) -> Result<Vec<DevelopmentStageChange>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let history = sqlx::query_as!(
+            DevelopmentStageChange,
+            r#"
+            SELECT id, from_stage, to_stage, notes, created_at
+            FROM product_development_stages
+            WHERE development_id = $1
+            ORDER BY created_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    /// What has to happen before this product can move to its next stage
+    /// (empty when it's ready, or launched/shelved)
+    async fn blockers(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(development::blockers(pool, self).await?)
+    }
+}
+
+/// An experimental batch made for a product in development.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct TrialBatch {
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    pub batch_size: BigDecimal,
+    pub unit: String,
+    pub status: String,
+    pub start_date: DateTime<Utc>,
+    pub completion_date: Option<DateTime<Utc>>,
+    pub yield_percentage: Option<BigDecimal>,
+    pub quality_notes: Option<String>,
+}
+
+/// One move of a product in development between stages.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct DevelopmentStageChange {
+    pub id: Uuid,
+    pub from_stage: Option<String>,
+    pub to_stage: String,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for starting a product in development.
+#[derive(Debug, InputObject)]
+pub struct CreateProductDevelopmentInput {
+    /// Working name (e.g. "Gochugaru Kimchi")
+    pub name: String,
+    /// Optional description of the idea
+    pub description: Option<String>,
+    /// Optional candidate recipe
+    pub recipe_template_id: Option<Uuid>,
+    /// Optional target launch date
+    pub target_launch_date: Option<NaiveDate>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for updating a product in development.
+#[derive(Debug, InputObject)]
+pub struct UpdateProductDevelopmentInput {
+    /// ID of the product in development
+    pub id: Uuid,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Optional new description
+    pub description: Option<String>,
+    /// Optional new candidate recipe
+    pub recipe_template_id: Option<Uuid>,
+    /// Optional product it launches as
+    pub product_inventory_id: Option<Uuid>,
+    /// Optional new target launch date
+    pub target_launch_date: Option<NaiveDate>,
+    /// Set true to sign off the label and compliance review
+    pub label_approved: Option<bool>,
+    /// Optional new notes
+    pub notes: Option<String>,
+}
+
+/// Input for moving a product in development to its next stage, or shelving it.
+#[derive(Debug, InputObject)]
+pub struct AdvanceProductDevelopmentInput {
+    /// ID of the product in development
+    pub id: Uuid,
+    /// Set true to shelve the product instead of advancing it
+    pub shelve: Option<bool>,
+    /// Optional notes on the decision
+    pub notes: Option<String>,
+}
+
+/// Result from changing a product in development.
+#[derive(Debug, SimpleObject)]
+pub struct ProductDevelopmentResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub development: Option<ProductDevelopment>,
+}
@@ -0,0 +1,205 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MutationError, ProductionBatch};
+
+/// A crock, fermenter, or brite tank that production batches ferment in.
+///
+/// A vessel holds at most one in-progress batch. Starting a batch in a vessel
+/// marks it 'in_use'; completing or failing the batch moves it to 'cleaning',
+/// and recording a cleaning makes it 'available' again.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct Vessel {
+    pub id: Uuid,
+    pub name: String,
+    pub vessel_type: String, // 'crock', 'fermenter', 'brite_tank', etc.
+    pub capacity: BigDecimal,
+    pub capacity_unit: String,
+    pub status: String, // 'available', 'in_use', 'cleaning', 'out_of_service'
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl Vessel {
+    /// The in-progress batch currently occupying this vessel
+    async fn current_batch(&self, ctx: &Context<'_>) -> Result<Option<ProductionBatch>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let batch = sqlx::query_as!(
+            ProductionBatch,
+            r#"
+            SELECT
+                id, batch_number, product_inventory_id, recipe_template_id,
+                batch_size, unit, start_date, estimated_completion_date,
+                completion_date, production_date, status,
+                production_time_hours, yield_percentage, actual_yield,
+                quality_notes, storage_location_id,
+                (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                vessel_id, last_moved_at, notes,
+                created_at, updated_at
+            FROM production_batches
+            WHERE vessel_id = $1 AND status = 'in_progress'
+            "#,
+            self.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(batch)
+    }
+}
+
+/// How much of a period a vessel spent holding batches.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct VesselUtilization {
+    pub vessel_id: Uuid,
+    pub name: String,
+    pub vessel_type: String,
+    pub status: String,
+    /// Batches that occupied the vessel at some point in the period
+    pub batch_count: i64,
+    /// Hours the vessel held a batch during the period
+    pub occupied_hours: BigDecimal,
+    /// Occupied hours as a percentage of the period
+    pub utilization_percent: BigDecimal,
+}
+
+/// Input for creating a vessel.
+#[derive(Debug, InputObject)]
+pub struct CreateVesselInput {
+    /// Unique display name (e.g. "Crock 3")
+    pub name: String,
+    /// Kind of vessel (e.g. "crock", "fermenter", "brite_tank")
+    pub vessel_type: String,
+    /// Working capacity
+    pub capacity: BigDecimal,
+    /// Unit of the capacity (e.g. "gallons", "liters")
+    pub capacity_unit: String,
+    /// Optional physical location
+    pub location: Option<String>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for updating a vessel.
+#[derive(Debug, InputObject)]
+pub struct UpdateVesselInput {
+    /// ID of the vessel to update
+    pub id: Uuid,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Optional new vessel type
+    pub vessel_type: Option<String>,
+    /// Optional new capacity
+    pub capacity: Option<BigDecimal>,
+    /// Optional new capacity unit
+    pub capacity_unit: Option<String>,
+    /// Optional new status ('available', 'cleaning', 'out_of_service')
+    pub status: Option<String>,
+    /// Optional new location
+    pub location: Option<String>,
+    /// Optional new notes
+    pub notes: Option<String>,
+}
+
+/// Input for retiring a vessel.
+#[derive(Debug, InputObject)]
+pub struct DeleteVesselInput {
+    /// ID of the vessel to retire
+    pub id: Uuid,
+}
+
+/// Input for moving a batch into a vessel (or out of one).
+#[derive(Debug, InputObject)]
+pub struct AssignBatchVesselInput {
+    /// ID of the in-progress batch
+    pub batch_id: Uuid,
+    /// Vessel to assign; omit to take the batch out of its current vessel
+    pub vessel_id: Option<Uuid>,
+}
+
+/// A recorded cleaning of a vessel.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SanitationLog {
+    pub id: Uuid,
+    pub vessel_id: Uuid,
+    /// Sanitizer used (e.g. "Star San", "chlorine")
+    pub chemical: String,
+    pub concentration: BigDecimal,
+    pub concentration_unit: String, // 'ppm', 'percent', 'ml_per_gallon', etc.
+    /// Who did the cleaning
+    pub operator: String,
+    pub cleaned_at: DateTime<Utc>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// When a vessel was last cleaned, and with what.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct VesselSanitationStatus {
+    pub vessel_id: Uuid,
+    pub name: String,
+    pub status: String,
+    /// Most recent cleaning (None if never cleaned)
+    pub last_cleaned_at: Option<DateTime<Utc>>,
+    pub last_chemical: Option<String>,
+    pub last_concentration: Option<BigDecimal>,
+    pub last_concentration_unit: Option<String>,
+    pub last_operator: Option<String>,
+    /// Whole days since the last cleaning
+    pub days_since_cleaned: Option<i32>,
+}
+
+/// Input for recording a vessel cleaning.
+#[derive(Debug, InputObject)]
+pub struct RecordCleaningInput {
+    /// ID of the vessel that was cleaned
+    pub vessel_id: Uuid,
+    /// Sanitizer used
+    pub chemical: String,
+    /// Sanitizer concentration
+    pub concentration: BigDecimal,
+    /// Unit of the concentration (defaults to "ppm")
+    pub concentration_unit: Option<String>,
+    /// Who did the cleaning
+    pub operator: String,
+    /// When the cleaning was done (defaults to now)
+    pub cleaned_at: Option<DateTime<Utc>>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Result from recording a vessel cleaning.
+#[derive(Debug, SimpleObject)]
+pub struct SanitationLogResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The recorded cleaning (if successful)
+    pub log: Option<SanitationLog>,
+}
+
+/// Result from creating or updating a vessel.
+#[derive(Debug, SimpleObject)]
+pub struct VesselResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The created or updated vessel (if successful)
+    pub vessel: Option<Vessel>,
+}
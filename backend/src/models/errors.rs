@@ -0,0 +1,119 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Why a mutation was turned down, for clients that act on failures in code
+/// instead of matching on `message`. Set on every result whose `success` is
+/// false.
+#[derive(Debug, Clone, Union, Serialize, Deserialize)]
+pub enum MutationError {
+    NotFound(NotFound),
+    NameConflict(NameConflict),
+    InsufficientStock(InsufficientStock),
+    StaleEdit(StaleEdit),
+    InvalidInput(InvalidInput),
+}
+
+/// The record the mutation refers to doesn't exist (or is inactive).
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct NotFound {
+    /// What was looked up (e.g. "Customer")
+    pub entity: String,
+}
+
+/// Another record already uses the name.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct NameConflict {
+    pub entity: String,
+    pub name: String,
+}
+
+/// There isn't enough of an item in stock.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct InsufficientStock {
+    pub item_id: Uuid,
+    pub item_name: String,
+    pub needed: BigDecimal,
+    pub available: BigDecimal,
+}
+
+/// The record changed since the client loaded it.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct StaleEdit {
+    /// When it was last changed
+    pub updated_at: DateTime<Utc>,
+    /// Who changed it, from the audit log
+    pub updated_by: Option<String>,
+}
+
+/// The input broke a rule; `message` says which.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct InvalidInput {
+    /// Input field at fault, when there's a single one
+    pub field: Option<String>,
+}
+
+impl MutationError {
+    pub fn not_found(entity: &str) -> Self {
+        MutationError::NotFound(NotFound {
+            entity: entity.to_string(),
+        })
+    }
+
+    pub fn name_conflict(entity: &str, name: &str) -> Self {
+        MutationError::NameConflict(NameConflict {
+            entity: entity.to_string(),
+            name: name.to_string(),
+        })
+    }
+
+    pub fn insufficient_stock(
+        item_id: Uuid,
+        item_name: &str,
+        needed: &BigDecimal,
+        available: &BigDecimal,
+    ) -> Self {
+        MutationError::InsufficientStock(InsufficientStock {
+            item_id,
+            item_name: item_name.to_string(),
+            needed: needed.clone(),
+            available: available.clone(),
+        })
+    }
+
+    pub fn stale_edit(updated_at: DateTime<Utc>, updated_by: Option<String>) -> Self {
+        MutationError::StaleEdit(StaleEdit {
+            updated_at,
+            updated_by,
+        })
+    }
+
+    pub fn invalid() -> Self {
+        MutationError::InvalidInput(InvalidInput { field: None })
+    }
+}
+
+/// A validation failure from a service, carrying both the message for the
+/// result and its typed error. Plain messages become `InvalidInput`.
+#[derive(Debug)]
+pub struct Rejection {
+    pub message: String,
+    pub error: MutationError,
+}
+
+impl Rejection {
+    pub fn new(message: String, error: MutationError) -> Self {
+        Rejection { message, error }
+    }
+}
+
+impl From<String> for Rejection {
+    fn from(message: String) -> Self {
+        Rejection {
+            message,
+            error: MutationError::invalid(),
+        }
+    }
+}
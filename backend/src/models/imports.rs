@@ -0,0 +1,130 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A source row from the legacy spreadsheets and what became of it.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct LegacyImportRow {
+    pub id: Uuid,
+    /// 'batches', 'sales', or 'purchases'
+    pub record_type: String,
+    /// Original batch or sale number, or a fingerprint of a purchase row
+    pub source_key: String,
+    /// 'imported' or 'rejected'
+    pub status: String,
+    /// Batch, sale, or purchase lot created from the row
+    pub entity_id: Option<Uuid>,
+    /// Inconsistencies found (warnings if imported, errors if rejected)
+    pub issues: serde_json::Value,
+    /// Source row(s) as exported from the sheet
+    pub raw: serde_json::Value,
+    pub imported_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for importing historical records from the legacy spreadsheets.
+#[derive(Debug, InputObject)]
+pub struct LegacyImportInput {
+    /// 'batches', 'sales', or 'purchases'
+    pub record_type: String,
+    /// Sheet rows as a JSON array of objects keyed by column header
+    pub rows: serde_json::Value,
+    /// Optional column mapping as {"field": "Column Header"}; unmapped fields
+    /// use the default headers (see `legacyImportMapping`)
+    pub mapping: Option<serde_json::Value>,
+    /// Validate and report without writing anything
+    pub dry_run: Option<bool>,
+}
+
+/// Outcome of one imported record. Sales spanning several rows report once.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LegacyImportOutcome {
+    /// Position of the (first) source row in the input
+    pub row_index: i32,
+    pub source_key: Option<String>,
+    /// 'imported', 'skipped' (already imported), or 'rejected'
+    pub status: String,
+    /// Batch, sale, or purchase lot created from the row
+    pub entity_id: Option<Uuid>,
+    /// Warnings if imported, errors if rejected
+    pub issues: Vec<String>,
+}
+
+/// Result from a legacy import.
+#[derive(Debug, SimpleObject)]
+pub struct LegacyImportResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub imported: i32,
+    pub skipped: i32,
+    pub rejected: i32,
+    /// Imported records that had warnings
+    pub flagged: i32,
+    pub outcomes: Vec<LegacyImportOutcome>,
+}
+
+/// A column the importer reads and the sheet header it reads it from by default.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LegacyImportField {
+    pub field: String,
+    pub default_column: String,
+    pub required: bool,
+}
+
+/// Input for importing inventory items or suppliers from a CSV file.
+#[derive(Debug, InputObject)]
+pub struct CsvImportInput {
+    /// 'inventory' or 'suppliers'
+    pub entity: String,
+    /// The CSV file, base64 encoded, header row first
+    pub file: String,
+    /// Optional column mapping as {"field": "Column Header"}; unmapped fields
+    /// use the default headers (see `csvImportFields`)
+    pub mapping: Option<serde_json::Value>,
+    /// Update records whose name matches a row instead of rejecting the row
+    pub upsert: Option<bool>,
+    /// Validate and report without writing anything
+    pub dry_run: Option<bool>,
+}
+
+/// Outcome of one CSV row.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CsvImportOutcome {
+    /// Row in the file, counting the header as row 1 and skipping blank lines
+    pub row_number: i32,
+    pub name: Option<String>,
+    /// 'created', 'updated', or 'rejected'
+    pub status: String,
+    /// Item or supplier created or updated from the row
+    pub entity_id: Option<Uuid>,
+    /// Warnings if imported, errors if rejected
+    pub issues: Vec<String>,
+}
+
+/// Result from a CSV import.
+#[derive(Debug, SimpleObject)]
+pub struct CsvImportResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub created: i32,
+    pub updated: i32,
+    pub rejected: i32,
+    pub outcomes: Vec<CsvImportOutcome>,
+}
+
+/// A column the CSV importer reads and the header it reads it from by default.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct CsvImportField {
+    pub field: String,
+    pub default_column: String,
+    pub required: bool,
+}
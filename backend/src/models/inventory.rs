@@ -2,9 +2,12 @@ use async_graphql::*;
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
+use crate::models::{CreateProductionBatchInput, MutationError, ProductionBatch};
+use crate::services::qr::QrCode;
+
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
 pub struct InventoryItem {
     pub id: Uuid,
@@ -19,7 +22,9 @@ pub struct InventoryItem {
     pub default_supplier_id: Option<Uuid>,    // NULL allowed
     pub shelf_life_days: Option<i32>,         // NULL allowed
     pub storage_requirements: Option<String>, // NULL allowed
+    pub product_code: Option<String>,         // Used in batch numbers
     pub is_active: bool,                      // NOT NULL
+    pub version: i32,                         // Bumped by edits, not stock movements
     pub created_at: DateTime<Utc>,            // NOT NULL
     pub updated_at: DateTime<Utc>,            // NOT NULL
 }
@@ -38,6 +43,9 @@ pub struct Supplier {
     pub latitude: Option<BigDecimal>,
     pub longitude: Option<BigDecimal>,
     pub notes: Option<String>,
+    /// Inactive suppliers can't take new purchase orders or be an item's
+    /// default supplier
+    pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -50,6 +58,15 @@ pub struct CreatePurchaseInput {
     pub items: Vec<PurchaseItemInput>,
     pub purchase_date: Option<DateTime<Utc>>, // Defaults to now if not provided
     pub notes: Option<String>,
+    /// Optional purchase order being received; matching lines count against it
+    pub purchase_order_id: Option<Uuid>,
+    /// Optional location the goods are put away at (default: unassigned)
+    pub location_id: Option<Uuid>,
+    /// Batch that lines with a `cross_dock_quantity` go straight into
+    pub cross_dock: Option<CrossDockInput>,
+    /// Optional client-generated key (e.g. a UUID per delivery). Retrying with
+    /// the same key returns the first successful result instead of receiving again.
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, InputObject)]
@@ -59,13 +76,35 @@ pub struct PurchaseItemInput {
     pub unit_cost: BigDecimal, // Cost per unit for this purchase
     pub expiry_date: Option<chrono::NaiveDate>,
     pub batch_number: Option<String>,
+    /// Portion of this line that goes straight into the cross-dock batch
+    /// instead of being put away
+    pub cross_dock_quantity: Option<BigDecimal>,
 }
 
-#[derive(Debug, SimpleObject)]
+/// Where cross-docked purchase lines go: an in-progress batch, or a new batch
+/// started as part of the receipt. Exactly one must be given.
+#[derive(Debug, InputObject)]
+pub struct CrossDockInput {
+    /// ID of an in-progress batch
+    pub batch_id: Option<Uuid>,
+    /// A batch to start; its own ingredients are drawn from stock as usual
+    /// and may be empty
+    pub new_batch: Option<CreateProductionBatchInput>,
+}
+
+#[derive(Debug, SimpleObject, Serialize, Deserialize)]
 pub struct PurchaseResult {
     pub success: bool,
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
     pub updated_items: Vec<InventoryItem>,
+    /// Batch that received cross-docked lines, if any
+    pub batch_id: Option<Uuid>,
+    pub batch_number: Option<String>,
+    /// Lots received, each with its QR code for labelling
+    #[serde(default)]
+    pub lots: Vec<InventoryLot>,
 }
 
 #[derive(Debug, InputObject)]
@@ -78,6 +117,8 @@ pub struct DeleteInventoryItemInput {
 pub struct DeleteResult {
     pub success: bool,
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
 }
 
 #[derive(Debug, InputObject)]
@@ -92,6 +133,8 @@ pub struct CreateInventoryItemInput {
     pub default_supplier_id: Option<Uuid>,
     pub shelf_life_days: Option<i32>,
     pub storage_requirements: Option<String>,
+    /// 2 to 10 letters and digits, stored upper case
+    pub product_code: Option<String>,
 }
 
 #[derive(Debug, InputObject)]
@@ -107,13 +150,22 @@ pub struct UpdateInventoryItemInput {
     pub default_supplier_id: Option<Uuid>,
     pub shelf_life_days: Option<i32>,
     pub storage_requirements: Option<String>,
+    /// 2 to 10 letters and digits, stored upper case
+    pub product_code: Option<String>,
     pub is_active: Option<bool>,
+    /// The item's `version` when the edit started. If the item has been
+    /// edited since, the update is refused with `conflict` instead of overwriting.
+    pub expected_version: Option<i32>,
 }
 
 #[derive(Debug, SimpleObject)]
 pub struct InventoryItemResult {
     pub success: bool,
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// True when the update was refused because someone else changed the item first
+    pub conflict: bool,
     pub item: Option<InventoryItem>,
 }
 
@@ -146,11 +198,260 @@ pub struct UpdateSupplierInput {
     pub latitude: Option<BigDecimal>,
     pub longitude: Option<BigDecimal>,
     pub notes: Option<String>,
+    /// True to reactivate a deactivated supplier (use `deactivateSupplier`
+    /// to deactivate)
+    pub is_active: Option<bool>,
+}
+
+/// Input for deactivating or deleting a supplier. Items that default to the
+/// supplier block either unless they're moved to `reassignTo`.
+#[derive(Debug, InputObject)]
+pub struct RemoveSupplierInput {
+    pub supplier_id: Uuid,
+    /// Active supplier to make the default for the supplier's items
+    pub reassign_to: Option<Uuid>,
 }
 
 #[derive(Debug, SimpleObject)]
 pub struct SupplierResult {
     pub success: bool,
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
     pub supplier: Option<Supplier>,
 }
+
+/// A received or produced quantity of an inventory item, tracked separately so
+/// each lot can carry its own expiry date and be traced back to its source.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct InventoryLot {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub lot_number: Option<String>,
+    /// Internal code printed as a QR on the lot's label (received and
+    /// produced lots)
+    pub code: Option<String>,
+    pub source_type: String, // 'purchase', 'production', 'packaging'
+    pub supplier_id: Option<Uuid>,
+    pub production_batch_id: Option<Uuid>,
+    pub parent_lot_id: Option<Uuid>, // Bulk lot a packaged lot was filled from
+    pub location_id: Option<Uuid>,   // Where the lot is stored (None = unassigned)
+    pub initial_quantity: BigDecimal,
+    pub remaining_quantity: BigDecimal,
+    pub unit_cost: Option<BigDecimal>,
+    pub received_date: DateTime<Utc>,
+    pub expiry_date: Option<chrono::NaiveDate>, // Explicit expiry only
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl InventoryLot {
+    /// The lot's code as a QR code (SVG), for printing on its label
+    async fn qr_svg(&self) -> Option<String> {
+        let code = self.code.as_deref()?;
+        QrCode::encode(code).map(|qr| qr.svg())
+    }
+}
+
+/// What a scanned code stands for. `kind` is what matched ('lot', 'batch' or
+/// 'item'); the batch and item behind a lot, and the product of a batch, are
+/// filled in too.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct ScannedCode {
+    pub code: String,
+    pub kind: String,
+    pub lot_id: Option<Uuid>,
+    pub production_batch_id: Option<Uuid>,
+    pub inventory_id: Option<Uuid>,
+}
+
+#[ComplexObject]
+impl ScannedCode {
+    async fn lot(&self, ctx: &Context<'_>) -> Result<Option<InventoryLot>> {
+        let Some(lot_id) = self.lot_id else {
+            return Ok(None);
+        };
+        let pool = ctx.data::<PgPool>()?;
+
+        let lot = sqlx::query_as!(
+            InventoryLot,
+            r#"
+            SELECT
+                id, inventory_id, lot_number, code, source_type, supplier_id,
+                production_batch_id, parent_lot_id, location_id, initial_quantity, remaining_quantity,
+                unit_cost, received_date, expiry_date, created_at, updated_at
+            FROM inventory_lots
+            WHERE id = $1
+            "#,
+            lot_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(lot)
+    }
+
+    async fn batch(&self, ctx: &Context<'_>) -> Result<Option<ProductionBatch>> {
+        let Some(batch_id) = self.production_batch_id else {
+            return Ok(None);
+        };
+        let pool = ctx.data::<PgPool>()?;
+
+        let batch = sqlx::query_as!(
+            ProductionBatch,
+            r#"
+            SELECT
+                id, batch_number, product_inventory_id, recipe_template_id,
+                batch_size, unit, start_date, estimated_completion_date,
+                completion_date, production_date, status,
+                production_time_hours, yield_percentage, actual_yield,
+                quality_notes, storage_location_id,
+                (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                vessel_id, last_moved_at, notes,
+                created_at, updated_at
+            FROM production_batches
+            WHERE id = $1
+            "#,
+            batch_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(batch)
+    }
+
+    async fn item(&self, ctx: &Context<'_>) -> Result<Option<InventoryItem>> {
+        let Some(inventory_id) = self.inventory_id else {
+            return Ok(None);
+        };
+        let pool = ctx.data::<PgPool>()?;
+
+        let item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            SELECT
+                id, name, category, unit, current_stock, reserved_stock,
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point, cost_per_unit, default_supplier_id,
+                shelf_life_days, storage_requirements, product_code, is_active, version,
+                created_at, updated_at
+            FROM inventory
+            WHERE id = $1
+            "#,
+            inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(item)
+    }
+}
+
+/// An open lot that expires within the requested window (or already has).
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ExpiringLot {
+    pub lot_id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub lot_number: Option<String>,
+    pub remaining_quantity: BigDecimal,
+    pub unit: String,
+    /// Explicit lot expiry, or received date + the item's shelf_life_days
+    pub expiry_date: chrono::NaiveDate,
+    /// Negative when the lot has already expired
+    pub days_until_expiry: i32,
+    pub storage_requirements: Option<String>,
+}
+
+/// How long an item's stock will last at its recent rate of use.
+///
+/// Consumption is everything that went out over the window (sales,
+/// production, packaging, kits, waste); stock-count adjustments don't count.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct StockProjection {
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    /// Current less reserved stock
+    pub available_stock: BigDecimal,
+    pub reorder_point: BigDecimal,
+    /// Days of history the rate is averaged over
+    pub window_days: i32,
+    /// Quantity that went out during the window
+    pub consumed: BigDecimal,
+    pub average_daily_consumption: BigDecimal,
+    /// Days until available stock runs out (None when nothing is being used)
+    pub days_until_stockout: Option<BigDecimal>,
+    pub stockout_date: Option<chrono::NaiveDate>,
+    /// Days until available stock falls to the reorder point (0 when it
+    /// already has; None when nothing is being used)
+    pub days_until_reorder_point: Option<BigDecimal>,
+    pub reorder_date: Option<chrono::NaiveDate>,
+}
+
+/// How old one product's on-hand stock is, from its open lots.
+///
+/// Lot quantities are bucketed by days since the lot was received. Stock that
+/// predates lot tracking has no receipt date and is reported as `untracked`.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct InventoryAging {
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub category: String,
+    pub unit: String,
+    pub current_stock: BigDecimal,
+    /// On-hand quantity received 0-30 days ago
+    pub days_0_to_30: BigDecimal,
+    /// On-hand quantity received 31-60 days ago
+    pub days_31_to_60: BigDecimal,
+    /// On-hand quantity received 61-90 days ago
+    pub days_61_to_90: BigDecimal,
+    /// On-hand quantity received more than 90 days ago
+    pub over_90_days: BigDecimal,
+    /// Current stock not covered by any open lot
+    pub untracked: BigDecimal,
+    /// Quantity-weighted average age of lot-tracked stock, in days
+    pub average_age_days: Option<BigDecimal>,
+    /// On-hand quantity at or within the requested days of its best-by date
+    pub expiring_soon: BigDecimal,
+    /// Earliest best-by date among open lots
+    pub next_expiry_date: Option<chrono::NaiveDate>,
+    /// Last time stock went out (sold, used in production or packaging, kitted)
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Quantity that went out during the slow-mover window
+    pub used_in_window: BigDecimal,
+    /// Stock on hand but nothing went out during the slow-mover window
+    pub slow_mover: bool,
+}
+
+/// What stock was worth at a point in time.
+///
+/// Quantities are replayed back from current stock through the movement log.
+/// Each item is valued at the unit cost on its last log entry at or before
+/// `as_of`, which is what a unit was carried at then.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct InventoryValuation {
+    pub as_of: DateTime<Utc>,
+    /// Sum of the valued lines
+    pub total_value: BigDecimal,
+    /// Lines with stock but no known cost, left out of the total
+    pub unvalued_items: i32,
+    pub lines: Vec<InventoryValuationLine>,
+}
+
+/// One item's stock and value in an `InventoryValuation`.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct InventoryValuationLine {
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub category: String,
+    pub unit: String,
+    /// On hand at `as_of`
+    pub quantity: BigDecimal,
+    pub unit_cost: Option<BigDecimal>,
+    /// Quantity times unit cost (None when the cost isn't known)
+    pub value: Option<BigDecimal>,
+}
@@ -0,0 +1,101 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// An invoice for a sale paid later, due after the customer's payment terms.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub sale_id: Uuid,
+    pub sale_number: String,
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    pub issued_on: NaiveDate,
+    pub due_on: NaiveDate,
+    /// The sale's total
+    pub amount: BigDecimal,
+    pub amount_paid: BigDecimal,
+    /// What is still owed
+    pub balance: BigDecimal,
+    /// 'open', 'partially_paid', 'paid' or 'void' (the sale was refunded)
+    pub status: String,
+    /// Past its due date with something still owed
+    pub is_overdue: bool,
+    /// When the last of it was paid
+    pub paid_on: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A payment against an invoice, in full or in part.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct InvoicePayment {
+    pub id: Uuid,
+    pub invoice_id: Uuid,
+    pub amount: BigDecimal,
+    pub paid_on: NaiveDate,
+    /// 'check', 'cash', 'transfer', 'stripe', etc.
+    pub payment_method: Option<String>,
+    /// Check number, Stripe checkout session, ...
+    pub reference: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for recording a payment against an invoice.
+#[derive(Debug, InputObject)]
+pub struct RecordPaymentInput {
+    pub invoice_id: Uuid,
+    /// Up to the invoice's balance; less leaves it partially paid
+    pub amount: BigDecimal,
+    /// Defaults to today
+    pub paid_on: Option<NaiveDate>,
+    pub payment_method: Option<String>,
+    pub reference: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Result from recording a payment.
+#[derive(Debug, SimpleObject)]
+pub struct PaymentResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub invoice: Option<Invoice>,
+    pub payment: Option<InvoicePayment>,
+}
+
+/// Outstanding invoice balances by how long ago the invoices were issued.
+#[derive(Debug, SimpleObject)]
+pub struct ArAging {
+    pub as_of: NaiveDate,
+    pub days_0_to_30: BigDecimal,
+    pub days_31_to_60: BigDecimal,
+    pub days_61_to_90: BigDecimal,
+    pub over_90_days: BigDecimal,
+    pub total: BigDecimal,
+    /// Part of the total that is past due
+    pub overdue: BigDecimal,
+    /// One line per customer who owes something, biggest balance first
+    pub customers: Vec<ArAgingLine>,
+}
+
+/// What one customer owes, by age of invoice.
+#[derive(Debug, SimpleObject)]
+pub struct ArAgingLine {
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    /// Invoices with something owed
+    pub invoices: i64,
+    pub days_0_to_30: BigDecimal,
+    pub days_31_to_60: BigDecimal,
+    pub days_61_to_90: BigDecimal,
+    pub over_90_days: BigDecimal,
+    pub total: BigDecimal,
+    pub overdue: BigDecimal,
+}
@@ -0,0 +1,131 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{InventoryItem, MutationError};
+
+/// One component of an assembled kit (e.g. the salt packet in a sauerkraut
+/// starter kit). The kit itself is an ordinary inventory item.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct KitComponent {
+    pub id: Uuid,
+    pub kit_inventory_id: Uuid,
+    pub component_inventory_id: Uuid,
+    pub component_name: String,
+    /// Quantity of the component in one kit
+    pub quantity: BigDecimal,
+    pub unit: String,
+    /// Current cost of one unit of the component, if known
+    pub cost_per_unit: Option<BigDecimal>,
+}
+
+/// A run of kit assembly: components consumed and kits added to stock.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct KitAssembly {
+    pub id: Uuid,
+    pub assembly_number: String, // Format: KIT-YYYYMMDD-NNN
+    pub kit_inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    /// Component cost of one kit at assembly time
+    pub unit_cost: BigDecimal,
+    pub total_cost: BigDecimal,
+    pub notes: Option<String>,
+    pub assembled_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One component line of a kit definition.
+#[derive(Debug, InputObject)]
+pub struct KitComponentInput {
+    /// ID of the component inventory item
+    pub inventory_id: Uuid,
+    /// Quantity in one kit
+    pub quantity: BigDecimal,
+}
+
+/// Input for defining (or redefining) what goes into a kit.
+#[derive(Debug, InputObject)]
+pub struct DefineKitInput {
+    /// ID of the inventory item that represents the kit
+    pub kit_inventory_id: Uuid,
+    /// Components of one kit (replaces any existing definition)
+    pub components: Vec<KitComponentInput>,
+}
+
+/// Result from defining a kit.
+#[derive(Debug, SimpleObject)]
+pub struct KitDefinitionResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub components: Vec<KitComponent>,
+}
+
+/// Input for assembling kits from their components.
+#[derive(Debug, InputObject)]
+pub struct AssembleKitsInput {
+    /// ID of the kit inventory item
+    pub kit_inventory_id: Uuid,
+    /// Number of kits to assemble
+    pub quantity: BigDecimal,
+    /// Optional notes (e.g. "Holiday gift boxes")
+    pub notes: Option<String>,
+}
+
+/// Result from assembling kits.
+#[derive(Debug, SimpleObject)]
+pub struct AssembleKitsResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub assembly: Option<KitAssembly>,
+    /// The kit item with its new stock level
+    pub kit_item: Option<InventoryItem>,
+}
+
+/// Plan for building a seasonal run of kits (e.g. December gift boxes):
+/// what the remaining boxes need, what's short, and how far along the build is.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct BundlePlan {
+    pub kit_inventory_id: Uuid,
+    pub kit_name: String,
+    /// Number of kits wanted this season
+    pub target_quantity: BigDecimal,
+    /// Start of the season; assemblies from this day on count as progress
+    pub since: NaiveDate,
+    /// Kits assembled since the season started
+    pub assembled: BigDecimal,
+    /// Kits still to build
+    pub remaining: BigDecimal,
+    pub progress_percent: BigDecimal,
+    /// Kits that could be assembled from stock on hand right now
+    pub buildable_now: BigDecimal,
+    pub components: Vec<BundleComponentPlan>,
+}
+
+/// What one component needs for the rest of a bundle plan.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct BundleComponentPlan {
+    pub inventory_id: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub quantity_per_kit: BigDecimal,
+    /// Quantity needed for the kits still to build
+    pub required: BigDecimal,
+    /// Available stock (current less reserved)
+    pub available: BigDecimal,
+    /// Planned size of in-progress batches of this component
+    pub in_production: BigDecimal,
+    /// Quantity still to buy or make once stock and in-progress batches are used
+    pub shortfall: BigDecimal,
+    /// Recipe that makes this component, if it's made in-house
+    pub recipe_template_id: Option<Uuid>,
+    pub recipe_name: Option<String>,
+    /// Batches of that recipe needed to cover the shortfall
+    pub batches_needed: Option<i64>,
+}
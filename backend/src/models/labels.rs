@@ -0,0 +1,126 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A printed label run. The PDF itself is served from `url`.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct LabelRun {
+    pub id: Uuid,
+    pub locale: String,
+    pub label_count: i32,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl LabelRun {
+    /// Path of the print-ready PDF on this server (GET /label-runs/{id}/pdf)
+    async fn url(&self) -> String {
+        format!("/label-runs/{}/pdf", self.id)
+    }
+}
+
+/// Labels needed to package a set of batches.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LabelRunPlan {
+    /// One line per batch and pack size
+    pub lines: Vec<LabelRunLine>,
+    /// Label counts per packaged SKU, across all batches
+    pub skus: Vec<LabelSkuCount>,
+    /// Label stock the run draws from packaging inventory
+    pub label_stock: Vec<LabelStockNeed>,
+    pub total_labels: i32,
+    /// Why the run can't be printed yet (empty when it can)
+    pub problems: Vec<String>,
+}
+
+/// Labels for one pack size of one batch.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LabelRunLine {
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    pub pack_size_id: Uuid,
+    pub pack_name: String,
+    /// Product name as printed, in the run's locale
+    pub product_name: String,
+    /// Label text as printed, in the run's locale
+    pub label_text: Option<String>,
+    pub best_by: Option<NaiveDate>,
+    pub pack_count: i32,
+    pub label_count: i32,
+    /// Label stock consumed (none if the pack size has no label item)
+    pub label_inventory_id: Option<Uuid>,
+}
+
+/// Labels needed for one packaged SKU.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LabelSkuCount {
+    pub pack_size_id: Uuid,
+    pub packaged_inventory_id: Uuid,
+    pub pack_name: String,
+    pub product_name: String,
+    pub pack_count: i32,
+    pub label_count: i32,
+}
+
+/// Label stock needed against what is on hand.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LabelStockNeed {
+    pub inventory_id: Uuid,
+    pub name: String,
+    pub needed: BigDecimal,
+    pub on_hand: BigDecimal,
+    /// How many more labels are needed (0 when there are enough)
+    pub shortfall: BigDecimal,
+}
+
+/// Input for planning or printing a label run.
+#[derive(Debug, InputObject)]
+pub struct LabelRunInput {
+    /// Batches being packaged
+    pub batches: Vec<LabelRunBatchInput>,
+    /// Locale for label text (default "en", falling back like `productLabel`)
+    pub locale: Option<String>,
+    /// Optional notes about the run
+    pub notes: Option<String>,
+}
+
+/// A batch to label.
+#[derive(Debug, InputObject)]
+pub struct LabelRunBatchInput {
+    /// Completed batch being packaged
+    pub batch_id: Uuid,
+    /// Packs to label (default: fill the product's only pack size from the
+    /// batch's remaining output)
+    pub packs: Option<Vec<LabelRunPackInput>>,
+}
+
+/// Packs of one size to label.
+#[derive(Debug, InputObject)]
+pub struct LabelRunPackInput {
+    /// Pack size being filled; must be a pack size of the batch's product
+    pub pack_size_id: Uuid,
+    /// Number of packs
+    pub pack_count: i32,
+}
+
+/// Result from printing a label run.
+#[derive(Debug, SimpleObject)]
+pub struct LabelRunResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// What was (or would have been) printed
+    pub plan: Option<LabelRunPlan>,
+    /// The printed run (if successful)
+    pub label_run: Option<LabelRun>,
+}
@@ -0,0 +1,97 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// The built-in library of recipes and category and unit presets.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RecipeLibrary {
+    pub recipes: Vec<LibraryRecipe>,
+    pub categories: Vec<LibraryCategory>,
+    pub units: Vec<LibraryUnit>,
+}
+
+/// A recipe template from the library.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LibraryRecipe {
+    /// Pass to `importFromLibrary` (e.g. "sauerkraut")
+    pub key: String,
+    pub template_name: String,
+    pub description: String,
+    /// Finished product the recipe makes
+    pub product_name: String,
+    pub default_batch_size: BigDecimal,
+    pub default_unit: String,
+    pub estimated_duration_hours: BigDecimal,
+    pub ingredients: Vec<LibraryIngredient>,
+    /// True when a recipe with this name already exists
+    pub added: bool,
+}
+
+/// One ingredient of a library recipe.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LibraryIngredient {
+    /// Matched to an inventory item by name unless mapped
+    pub name: String,
+    /// Category for the item if it has to be created
+    pub category: String,
+    pub quantity_per_batch: BigDecimal,
+    pub unit: String,
+}
+
+/// A category preset from the library.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LibraryCategory {
+    pub name: String,
+    pub description: String,
+    pub added: bool,
+}
+
+/// A unit preset from the library.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct LibraryUnit {
+    pub name: String,
+    pub kind: String,
+    pub added: bool,
+}
+
+/// Input for copying library entries into this deployment.
+#[derive(Debug, InputObject)]
+pub struct ImportFromLibraryInput {
+    /// Recipe keys
+    pub recipes: Option<Vec<String>>,
+    /// Category preset names
+    pub categories: Option<Vec<String>>,
+    /// Unit preset names
+    pub units: Option<Vec<String>>,
+    /// Use these inventory items for library products and ingredients instead
+    /// of matching or creating them by name
+    pub mappings: Option<Vec<LibraryItemMappingInput>>,
+}
+
+/// Use an existing inventory item for a library product or ingredient.
+#[derive(Debug, InputObject)]
+pub struct LibraryItemMappingInput {
+    /// Name in the library (e.g. "Salt")
+    pub name: String,
+    /// ID of the inventory item to use
+    pub inventory_id: Uuid,
+}
+
+/// Result from copying library entries.
+#[derive(Debug, SimpleObject)]
+pub struct LibraryImportResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// Names of the recipes added
+    pub recipes: Vec<String>,
+    pub categories_added: i32,
+    pub units_added: i32,
+    /// Inventory items created for products and ingredients nothing matched
+    pub created_items: Vec<String>,
+    /// Entries not copied, and why
+    pub skipped: Vec<String>,
+}
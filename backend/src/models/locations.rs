@@ -0,0 +1,97 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A place stock and batches are kept (walk-in cooler, dry storage, the
+/// farmers-market van). Lots carry the location they sit in, so stock at a
+/// location is the remaining quantity of its open lots.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct Location {
+    pub id: Uuid,
+    pub name: String,
+    pub location_type: String, // 'cold_storage', 'dry_storage', 'vehicle', 'storage'
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Stock of one inventory item at one location.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct LocationStock {
+    /// None for stock not assigned to any location (including stock from
+    /// before lot tracking)
+    pub location_id: Option<Uuid>,
+    pub location_name: Option<String>,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    pub quantity: BigDecimal,
+}
+
+/// Input for creating a location.
+#[derive(Debug, InputObject)]
+pub struct CreateLocationInput {
+    /// Unique display name (e.g. "Walk-in Cooler")
+    pub name: String,
+    /// Kind of location (default "storage")
+    pub location_type: Option<String>,
+    /// Optional description
+    pub description: Option<String>,
+}
+
+/// Input for updating a location.
+#[derive(Debug, InputObject)]
+pub struct UpdateLocationInput {
+    /// ID of the location to update
+    pub id: Uuid,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Optional new location type
+    pub location_type: Option<String>,
+    /// Optional new description
+    pub description: Option<String>,
+    /// Set false to retire the location (it must be empty)
+    pub is_active: Option<bool>,
+}
+
+/// Result from creating or updating a location.
+#[derive(Debug, SimpleObject)]
+pub struct LocationResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub location: Option<Location>,
+}
+
+/// Input for moving stock between locations.
+#[derive(Debug, InputObject)]
+pub struct TransferStockInput {
+    /// ID of the inventory item to move
+    pub inventory_id: Uuid,
+    /// Location to move from; omit to move unassigned stock
+    pub from_location_id: Option<Uuid>,
+    /// Location to move to
+    pub to_location_id: Uuid,
+    /// Quantity to move
+    pub quantity: BigDecimal,
+    /// Optional notes (e.g. "Loading for Saturday market")
+    pub notes: Option<String>,
+}
+
+/// Result from moving stock between locations.
+#[derive(Debug, SimpleObject)]
+pub struct TransferStockResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The item's stock at each location after the move
+    pub stock: Vec<LocationStock>,
+}
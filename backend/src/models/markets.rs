@@ -0,0 +1,242 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MutationError, Sale};
+
+/// A farmers market we sell at.
+///
+/// The booth fee and travel cost are defaults for new sessions; each session
+/// keeps what that day actually cost.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct Market {
+    pub id: Uuid,
+    pub name: String,
+    pub location: Option<String>,
+    pub default_booth_fee: BigDecimal,
+    /// Mileage, parking and tolls for one trip
+    pub default_travel_cost: BigDecimal,
+    pub is_active: bool,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A day we attend a market: the booth we were given, what it cost, who
+/// worked it, and the sales made there.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct MarketSession {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub market_name: String,
+    /// Market day on the business calendar
+    pub calendar_event_id: Option<Uuid>,
+    pub session_date: NaiveDate,
+    /// Booth or stall assigned by the market (e.g. "B12")
+    pub booth: Option<String>,
+    pub booth_fee: BigDecimal,
+    pub travel_cost: BigDecimal,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl MarketSession {
+    /// Who works the booth
+    async fn staff(&self, ctx: &Context<'_>) -> Result<Vec<MarketSessionStaff>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let staff = sqlx::query_as!(
+            MarketSessionStaff,
+            r#"
+            SELECT id, session_id, user_id, staff_name
+            FROM market_session_staff
+            WHERE session_id = $1
+            ORDER BY staff_name
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(staff)
+    }
+
+    /// Sales made at the session, in the order they were rung up
+    async fn sales(&self, ctx: &Context<'_>) -> Result<Vec<Sale>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let sales = sqlx::query_as!(
+            Sale,
+            r#"
+            SELECT
+                id, sale_number, customer_id, sale_date,
+                subtotal, tax_amount, discount_amount, total_amount,
+                payment_method, payment_status, notes, channel, stock_check, oversold,
+                stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+            FROM sales
+            WHERE market_session_id = $1
+            ORDER BY sale_date
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sales)
+    }
+
+    /// Total of the session's sales that weren't refunded
+    async fn sales_total(&self, ctx: &Context<'_>) -> Result<BigDecimal> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(total_amount), 0) AS "total!"
+            FROM sales
+            WHERE market_session_id = $1 AND payment_status <> 'refunded'
+            "#,
+            self.id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(total)
+    }
+}
+
+/// Someone working the booth at a market session.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct MarketSessionStaff {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    /// Set when the staff member has an account
+    pub user_id: Option<Uuid>,
+    pub staff_name: String,
+}
+
+/// One market's takings and costs over a period.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct MarketProfitability {
+    pub market_id: Uuid,
+    pub market_name: String,
+    pub sessions: i64,
+    pub sales_count: i64,
+    /// Total of sales that weren't refunded
+    pub revenue: BigDecimal,
+    /// What the items sold cost, at each item's current cost per unit
+    pub cost_of_goods: BigDecimal,
+    pub booth_fees: BigDecimal,
+    pub travel_costs: BigDecimal,
+    /// Revenue less cost of goods, booth fees and travel
+    pub profit: BigDecimal,
+}
+
+/// Input for adding a market.
+#[derive(Debug, InputObject)]
+pub struct CreateMarketInput {
+    /// Display name (e.g. "Frederick Saturday Market")
+    pub name: String,
+    /// Optional address or directions
+    pub location: Option<String>,
+    /// Usual booth fee (defaults to 0)
+    pub default_booth_fee: Option<BigDecimal>,
+    /// Usual travel cost per trip (defaults to 0)
+    pub default_travel_cost: Option<BigDecimal>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for updating a market.
+#[derive(Debug, InputObject)]
+pub struct UpdateMarketInput {
+    /// ID of the market to update
+    pub id: Uuid,
+    /// Optional new name
+    pub name: Option<String>,
+    /// Optional new location
+    pub location: Option<String>,
+    /// Optional new usual booth fee
+    pub default_booth_fee: Option<BigDecimal>,
+    /// Optional new usual travel cost
+    pub default_travel_cost: Option<BigDecimal>,
+    /// Set false once we stop attending
+    pub is_active: Option<bool>,
+    /// Optional new notes
+    pub notes: Option<String>,
+}
+
+/// Someone assigned to work a market session: an app user, or a helper
+/// without an account given by name.
+#[derive(Debug, InputObject)]
+pub struct MarketStaffInput {
+    pub user_id: Option<Uuid>,
+    /// Required when there's no user; defaults to the user's name otherwise
+    pub name: Option<String>,
+}
+
+/// Input for scheduling a day at a market.
+#[derive(Debug, InputObject)]
+pub struct ScheduleMarketSessionInput {
+    pub market_id: Uuid,
+    pub session_date: NaiveDate,
+    /// Optional booth or stall assignment
+    pub booth: Option<String>,
+    /// Booth fee for the day (defaults to the market's)
+    pub booth_fee: Option<BigDecimal>,
+    /// Travel cost for the day (defaults to the market's)
+    pub travel_cost: Option<BigDecimal>,
+    /// Who works the booth
+    pub staff: Option<Vec<MarketStaffInput>>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for updating a market session.
+#[derive(Debug, InputObject)]
+pub struct UpdateMarketSessionInput {
+    /// ID of the session to update
+    pub id: Uuid,
+    /// Optional new booth assignment
+    pub booth: Option<String>,
+    /// Optional new booth fee
+    pub booth_fee: Option<BigDecimal>,
+    /// Optional new travel cost
+    pub travel_cost: Option<BigDecimal>,
+    /// Replaces the staff list when given
+    pub staff: Option<Vec<MarketStaffInput>>,
+    /// Optional new notes
+    pub notes: Option<String>,
+}
+
+/// Input for attaching sales rung up at a market to its session.
+#[derive(Debug, InputObject)]
+pub struct AssignSalesToMarketSessionInput {
+    pub session_id: Uuid,
+    pub sale_ids: Vec<Uuid>,
+}
+
+/// Result from creating or updating a market.
+#[derive(Debug, SimpleObject)]
+pub struct MarketResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub market: Option<Market>,
+}
+
+/// Result from scheduling or updating a market session.
+#[derive(Debug, SimpleObject)]
+pub struct MarketSessionResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub session: Option<MarketSession>,
+}
@@ -0,0 +1,103 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{CreateLocationInput, MutationError};
+
+/// A unit of measure offered when adding items and recipes.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct Unit {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: String, // 'mass', 'volume', 'count'
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An inventory category offered when adding items.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct InventoryCategory {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a new deployment is in setting itself up.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct OnboardingStatus {
+    /// True once every step is done
+    pub complete: bool,
+    /// Key of the first step still to do
+    pub next_step: Option<String>,
+    pub steps: Vec<OnboardingStep>,
+}
+
+/// One step of the setup wizard.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct OnboardingStep {
+    /// 'locations', 'units', 'categories' or 'recipes'
+    pub key: String,
+    pub title: String,
+    pub done: bool,
+    /// How many the deployment has so far
+    pub count: i64,
+    /// What to do, and which mutation does it
+    pub hint: String,
+}
+
+/// A unit to set up.
+#[derive(Debug, InputObject)]
+pub struct UnitInput {
+    pub name: String,
+    /// 'mass', 'volume' or 'count'
+    pub kind: String,
+}
+
+/// Input for the units step.
+#[derive(Debug, InputObject)]
+pub struct SetUpUnitsInput {
+    /// Units to add (default: the library presets)
+    pub units: Option<Vec<UnitInput>>,
+}
+
+/// A category to set up.
+#[derive(Debug, InputObject)]
+pub struct CategoryInput {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Input for the categories step.
+#[derive(Debug, InputObject)]
+pub struct SetUpCategoriesInput {
+    /// Categories to add (default: the library presets)
+    pub categories: Option<Vec<CategoryInput>>,
+}
+
+/// Input for the locations step.
+#[derive(Debug, InputObject)]
+pub struct SetUpLocationsInput {
+    /// Locations to add (default: a walk-in cooler and dry storage)
+    pub locations: Option<Vec<CreateLocationInput>>,
+}
+
+/// Input for the recipes step.
+#[derive(Debug, InputObject)]
+pub struct AddStarterRecipesInput {
+    /// Keys from `library.recipes`
+    pub keys: Vec<String>,
+}
+
+/// Result from a setup step, with where setup stands afterwards.
+#[derive(Debug, SimpleObject)]
+pub struct OnboardingResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub status: OnboardingStatus,
+}
@@ -0,0 +1,124 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+use crate::services::orders;
+
+/// An order a customer placed for delivery on a later day. Confirming it
+/// reserves its stock; fulfilling it makes the sale.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct CustomerOrder {
+    pub id: Uuid,
+    pub order_number: String, // ORDER_NUMBER_FORMAT, default ORD-YYYYMMDD-NNN
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    pub delivery_date: NaiveDate,
+    /// 'draft', 'confirmed', 'fulfilled', 'invoiced' or 'cancelled'
+    pub status: String,
+    /// Sum of the lines
+    pub total_amount: BigDecimal,
+    /// The sale it was fulfilled as
+    pub sale_id: Option<Uuid>,
+    pub sale_number: Option<String>,
+    pub notes: Option<String>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub fulfilled_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl CustomerOrder {
+    /// What was ordered, by item name
+    async fn items(&self, ctx: &Context<'_>) -> Result<Vec<CustomerOrderItem>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        Ok(orders::items(pool, self.id).await?)
+    }
+}
+
+/// One line of a customer order.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct CustomerOrderItem {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    pub quantity: BigDecimal,
+    pub unit_price: BigDecimal,
+    pub line_total: BigDecimal,
+    pub notes: Option<String>,
+}
+
+/// One line of a new or edited order.
+#[derive(Debug, InputObject)]
+pub struct OrderItemInput {
+    pub inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    /// When left out, the customer's price (see `priceFor`)
+    pub unit_price: Option<BigDecimal>,
+    pub notes: Option<String>,
+}
+
+/// Input for taking a customer order.
+#[derive(Debug, InputObject)]
+pub struct CreateOrderInput {
+    pub customer_id: Uuid,
+    pub delivery_date: NaiveDate,
+    pub items: Vec<OrderItemInput>,
+    pub notes: Option<String>,
+}
+
+/// Input for editing a draft order. Items, when given, replace the order's.
+#[derive(Debug, InputObject)]
+pub struct UpdateOrderInput {
+    pub order_id: Uuid,
+    pub delivery_date: Option<NaiveDate>,
+    pub items: Option<Vec<OrderItemInput>>,
+    pub notes: Option<String>,
+}
+
+/// Input for confirming a draft order.
+#[derive(Debug, InputObject)]
+pub struct ConfirmOrderInput {
+    pub order_id: Uuid,
+}
+
+/// Input for fulfilling a confirmed order.
+#[derive(Debug, InputObject)]
+pub struct FulfillOrderInput {
+    pub order_id: Uuid,
+    /// How it was paid on delivery. 'invoice' bills the customer at once;
+    /// left out, the sale waits on payment until `invoiceOrder`.
+    pub payment_method: Option<String>,
+}
+
+/// Input for invoicing a fulfilled order.
+#[derive(Debug, InputObject)]
+pub struct InvoiceOrderInput {
+    pub order_id: Uuid,
+}
+
+/// Input for cancelling a draft or confirmed order.
+#[derive(Debug, InputObject)]
+pub struct CancelOrderInput {
+    pub order_id: Uuid,
+    /// Optional reason, appended to the order's notes
+    pub reason: Option<String>,
+}
+
+/// Result from customer order operations.
+#[derive(Debug, SimpleObject)]
+pub struct OrderResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub order: Option<CustomerOrder>,
+}
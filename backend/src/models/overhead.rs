@@ -0,0 +1,99 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+use crate::services::overhead;
+
+/// A fixed cost (rent, utilities, insurance) spread over production by an
+/// allocation basis.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct OverheadPool {
+    pub id: Uuid,
+    pub name: String,
+    pub monthly_amount: BigDecimal,
+    pub allocation_basis: String, // 'per_batch', 'per_liter', 'per_labor_hour'
+    /// Budgeted batches, liters or labor hours per month (null = last 90 days' actuals)
+    pub expected_monthly_volume: Option<BigDecimal>,
+    pub is_active: bool,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl OverheadPool {
+    /// What this pool currently charges per batch, liter or labor hour
+    async fn rate(&self, ctx: &Context<'_>) -> Result<OverheadRate> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(overhead::rate(pool, self).await?)
+    }
+}
+
+/// An overhead pool's allocation rate.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct OverheadRate {
+    /// Monthly batches, liters or labor hours the amount is spread over
+    pub monthly_volume: BigDecimal,
+    /// 'expected' (budgeted) or 'trailing_90_days' (actual production)
+    pub volume_source: String,
+    /// Monthly amount over monthly volume (null when there is no volume yet)
+    pub rate: Option<BigDecimal>,
+}
+
+/// One overhead pool's share of a recipe cost.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct OverheadLine {
+    pub overhead_pool_id: Uuid,
+    pub name: String,
+    pub allocation_basis: String,
+    /// Batches, liters or labor hours charged (null when unknown)
+    pub quantity: Option<BigDecimal>,
+    pub rate: Option<BigDecimal>,
+    /// Null when the pool can't be allocated; see `note`
+    pub line_cost: Option<BigDecimal>,
+    /// Why the pool couldn't be allocated
+    pub note: Option<String>,
+}
+
+/// Input for adding an overhead pool.
+#[derive(Debug, InputObject)]
+pub struct CreateOverheadPoolInput {
+    pub name: String,
+    pub monthly_amount: BigDecimal,
+    /// 'per_batch', 'per_liter' or 'per_labor_hour'
+    pub allocation_basis: String,
+    /// Budgeted monthly volume of the basis (default: last 90 days' actuals)
+    pub expected_monthly_volume: Option<BigDecimal>,
+    pub notes: Option<String>,
+}
+
+/// Input for changing an overhead pool.
+#[derive(Debug, InputObject)]
+pub struct UpdateOverheadPoolInput {
+    /// ID of the overhead pool to update
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub monthly_amount: Option<BigDecimal>,
+    pub allocation_basis: Option<String>,
+    pub expected_monthly_volume: Option<BigDecimal>,
+    /// Go back to last 90 days' actuals instead of the budgeted volume
+    pub clear_expected_monthly_volume: Option<bool>,
+    /// False stops allocating it
+    pub is_active: Option<bool>,
+    pub notes: Option<String>,
+}
+
+/// Result from adding or changing an overhead pool.
+#[derive(Debug, SimpleObject)]
+pub struct OverheadPoolResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub overhead_pool: Option<OverheadPool>,
+}
@@ -0,0 +1,163 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{InventoryItem, MutationError, PackagingMaterialInput};
+
+/// A packaged SKU of a bulk product (e.g. a 16oz jar of sauerkraut).
+///
+/// The packaged SKU is its own inventory item, so jar counts are tracked
+/// separately from the bulk stock they were filled from.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PackSize {
+    pub id: Uuid,
+    /// Bulk product the packs are filled from
+    pub bulk_inventory_id: Uuid,
+    /// Inventory item holding the packaged stock
+    pub packaged_inventory_id: Uuid,
+    /// Display name (e.g. "16oz jar")
+    pub name: String,
+    /// Bulk quantity (in the bulk item's unit) that goes into one pack
+    pub bulk_quantity_per_pack: BigDecimal,
+    /// Label stock (e.g. a roll of 16oz jar labels) consumed by label runs
+    pub label_inventory_id: Option<Uuid>,
+    /// Labels applied to each pack
+    pub labels_per_pack: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Packaged stock for one pack size.
+#[derive(Debug, SimpleObject)]
+pub struct PackStock {
+    pub pack_size: PackSize,
+    /// Number of packs on hand
+    pub packs_on_hand: BigDecimal,
+    /// Packs on hand expressed in the bulk unit
+    pub bulk_equivalent: BigDecimal,
+}
+
+/// Bulk and packaged views of a single product.
+#[derive(Debug, SimpleObject)]
+pub struct PackagingBreakdown {
+    pub bulk_item: InventoryItem,
+    /// Unpackaged stock in the bulk unit
+    pub bulk_stock: BigDecimal,
+    pub packs: Vec<PackStock>,
+    /// Bulk stock plus all packaged stock, in the bulk unit
+    pub total_bulk_equivalent: BigDecimal,
+}
+
+/// Input for defining a pack size for a bulk product.
+#[derive(Debug, InputObject)]
+pub struct CreatePackSizeInput {
+    /// Bulk product the packs are filled from
+    pub bulk_inventory_id: Uuid,
+    /// Inventory item that holds the packaged stock
+    pub packaged_inventory_id: Uuid,
+    /// Display name (e.g. "16oz jar")
+    pub name: String,
+    /// Bulk quantity that goes into one pack
+    pub bulk_quantity_per_pack: BigDecimal,
+    /// Label stock consumed by label runs
+    pub label_inventory_id: Option<Uuid>,
+    /// Labels applied to each pack (default 1)
+    pub labels_per_pack: Option<i32>,
+}
+
+/// Input for changing which labels a pack size uses.
+#[derive(Debug, InputObject)]
+pub struct SetPackSizeLabelInput {
+    /// ID of the pack size
+    pub id: Uuid,
+    /// Label stock consumed by label runs (null to stop tracking label stock)
+    pub label_inventory_id: Option<Uuid>,
+    /// Labels applied to each pack (default 1)
+    pub labels_per_pack: Option<i32>,
+}
+
+/// Input for deleting a pack size.
+#[derive(Debug, InputObject)]
+pub struct DeletePackSizeInput {
+    /// ID of the pack size to delete
+    pub id: Uuid,
+}
+
+/// Result from creating a pack size.
+#[derive(Debug, SimpleObject)]
+pub struct PackSizeResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The created pack size (if successful)
+    pub pack_size: Option<PackSize>,
+}
+
+/// Input for converting bulk stock into packs.
+#[derive(Debug, InputObject)]
+pub struct PackageProductInput {
+    /// Pack size being filled
+    pub pack_size_id: Uuid,
+    /// Number of packs filled
+    pub pack_count: BigDecimal,
+    /// Optional notes about the packaging run
+    pub notes: Option<String>,
+}
+
+/// Result from a packaging run.
+#[derive(Debug, SimpleObject)]
+pub struct PackagingResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// Bulk item after the conversion
+    pub bulk_item: Option<InventoryItem>,
+    /// Packaged item after the conversion
+    pub packaged_item: Option<InventoryItem>,
+}
+
+/// Input for packaging a finished batch into one or more pack sizes.
+#[derive(Debug, InputObject)]
+pub struct SplitBatchOutputInput {
+    /// Completed batch whose bulk output is being packaged
+    pub batch_id: Uuid,
+    /// Packs to fill (e.g. 30 x 16oz and 10 x 32oz)
+    pub splits: Vec<BatchSplitInput>,
+    /// Optional notes about the packaging run
+    pub notes: Option<String>,
+}
+
+/// One pack size filled from a batch.
+#[derive(Debug, InputObject)]
+pub struct BatchSplitInput {
+    /// Pack size being filled; must be a pack size of the batch's product
+    pub pack_size_id: Uuid,
+    /// Number of packs filled
+    pub pack_count: BigDecimal,
+    /// Jars, lids, and labels used for these packs
+    pub packaging: Option<Vec<PackagingMaterialInput>>,
+}
+
+/// Result from splitting a batch into packaged SKUs.
+#[derive(Debug, SimpleObject)]
+pub struct SplitBatchOutputResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// Bulk item after the conversion
+    pub bulk_item: Option<InventoryItem>,
+    /// Packaged items after the conversion, in the order of the splits
+    pub packaged_items: Vec<InventoryItem>,
+}
@@ -0,0 +1,187 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A time slot for collecting orders at the shop (e.g. Thursday CSA pickup,
+/// 4-5pm). Times are shop-local.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct PickupWindow {
+    pub id: Uuid,
+    pub pickup_date: NaiveDate,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    /// Maximum number of orders booked into the window
+    pub capacity: i32,
+    pub notes: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl PickupWindow {
+    /// Orders booked into the window, not counting cancellations
+    async fn booked(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let booked = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM pickup_bookings
+            WHERE pickup_window_id = $1 AND status <> 'cancelled'
+            "#,
+            self.id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(booked)
+    }
+
+    /// Slots still open
+    async fn remaining(&self, ctx: &Context<'_>) -> Result<i64> {
+        let booked = self.booked(ctx).await?;
+        Ok((i64::from(self.capacity) - booked).max(0))
+    }
+}
+
+/// An order's booked pickup slot.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PickupBooking {
+    pub id: Uuid,
+    pub pickup_window_id: Uuid,
+    /// The order being collected
+    pub sale_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub status: String, // 'booked', 'picked_up', 'no_show', 'cancelled'
+    /// When the reminder alert went out
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub picked_up_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One order on a day's pickup manifest.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PickupManifestEntry {
+    pub booking_id: Uuid,
+    pub status: String,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+    pub sale_id: Uuid,
+    pub sale_number: String,
+    pub customer_id: Option<Uuid>,
+    pub customer_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub total_amount: BigDecimal,
+    /// Whether the order still needs paying at pickup
+    pub payment_status: String,
+    pub items: Vec<PickupItem>,
+    /// Pickups the customer has missed before
+    pub no_show_count: i64,
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+}
+
+/// A line of an order to hand over.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PickupItem {
+    pub inventory_id: Uuid,
+    pub name: String,
+    pub quantity: BigDecimal,
+    pub unit: String,
+}
+
+/// A customer who has missed pickups.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PickupNoShow {
+    pub customer_id: Uuid,
+    pub customer_name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub no_shows: i64,
+    pub last_missed: NaiveDate,
+}
+
+/// Input for opening a pickup window.
+#[derive(Debug, InputObject)]
+pub struct CreatePickupWindowInput {
+    /// Day of the pickup (must be open for deliveries in the business calendar)
+    pub pickup_date: NaiveDate,
+    /// Shop-local start time
+    pub start_time: NaiveTime,
+    /// Shop-local end time
+    pub end_time: NaiveTime,
+    /// Maximum number of orders
+    pub capacity: i32,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for changing a pickup window.
+#[derive(Debug, InputObject)]
+pub struct UpdatePickupWindowInput {
+    /// ID of the window to update
+    pub id: Uuid,
+    /// Optional new start time
+    pub start_time: Option<NaiveTime>,
+    /// Optional new end time
+    pub end_time: Option<NaiveTime>,
+    /// Optional new capacity (cannot drop below orders booked)
+    pub capacity: Option<i32>,
+    /// Optional new notes
+    pub notes: Option<String>,
+    /// Set to false to stop taking bookings
+    pub is_active: Option<bool>,
+}
+
+/// Input for booking an order into a pickup window. An order that is already
+/// booked moves to the new window.
+#[derive(Debug, InputObject)]
+pub struct BookPickupInput {
+    /// The order being collected
+    pub sale_id: Uuid,
+    /// The window to collect it in
+    pub pickup_window_id: Uuid,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for recording what happened to a booking.
+#[derive(Debug, InputObject)]
+pub struct UpdatePickupBookingInput {
+    /// ID of the booking
+    pub booking_id: Uuid,
+    /// 'picked_up', 'no_show' or 'cancelled'
+    pub status: String,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Result from creating or updating a pickup window.
+#[derive(Debug, SimpleObject)]
+pub struct PickupWindowResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub window: Option<PickupWindow>,
+}
+
+/// Result from booking or updating a pickup.
+#[derive(Debug, SimpleObject)]
+pub struct PickupBookingResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub booking: Option<PickupBooking>,
+}
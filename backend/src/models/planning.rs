@@ -0,0 +1,157 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A draft schedule entry: a batch of a recipe planned for a target date.
+/// Planned batches drive material requirements until a batch is started
+/// from them.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PlannedBatch {
+    pub id: Uuid,
+    pub recipe_template_id: Uuid,
+    pub recipe_name: String,
+    pub product_inventory_id: Option<Uuid>,
+    pub batch_size: BigDecimal,
+    pub unit: Option<String>,
+    pub target_date: NaiveDate,
+    pub status: String, // 'planned', 'started', 'cancelled'
+    /// Batch started from this plan
+    pub production_batch_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for adding a batch to the production plan.
+#[derive(Debug, InputObject)]
+pub struct CreatePlannedBatchInput {
+    pub recipe_template_id: Uuid,
+    /// Batch size (defaults to the recipe's default batch size)
+    pub batch_size: Option<BigDecimal>,
+    pub target_date: NaiveDate,
+    pub notes: Option<String>,
+}
+
+/// Input for rescheduling, resizing, or cancelling a planned batch.
+#[derive(Debug, InputObject)]
+pub struct UpdatePlannedBatchInput {
+    pub id: Uuid,
+    pub batch_size: Option<BigDecimal>,
+    pub target_date: Option<NaiveDate>,
+    /// Optional new status ('planned' or 'cancelled')
+    pub status: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Result from creating or updating a planned batch.
+#[derive(Debug, SimpleObject)]
+pub struct PlannedBatchResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub planned_batch: Option<PlannedBatch>,
+}
+
+/// Demand for one ingredient across the planned batches in a date range,
+/// netted against stock and open purchase orders.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct MaterialRequirement {
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    /// Total needed by the planned batches
+    pub required: BigDecimal,
+    /// Available stock (current less reserved)
+    pub available: BigDecimal,
+    /// Outstanding quantity on open purchase orders due by the end of the range
+    pub on_order: BigDecimal,
+    /// Quantity still to order (0 if covered)
+    pub shortfall: BigDecimal,
+    /// Target date of the earliest planned batch that needs it
+    pub first_needed: NaiveDate,
+    /// Number of planned batches that use it
+    pub planned_batches: i32,
+}
+
+/// Future output of a planned batch promised to a made-to-order (wholesale)
+/// order. Soft allocations only count against plan capacity; when the batch
+/// completes they become reserved stock of the product.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PlanAllocation {
+    pub id: Uuid,
+    pub planned_batch_id: Uuid,
+    /// The recipe's product
+    pub inventory_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub customer_name: Option<String>,
+    /// In the product's unit
+    pub quantity: BigDecimal,
+    pub due_date: Option<NaiveDate>,
+    pub status: String, // 'soft', 'reserved', 'released', 'cancelled'
+    /// Added to the product's reserved stock when the batch completed (less
+    /// than `quantity` if the yield fell short)
+    pub reserved_quantity: Option<BigDecimal>,
+    pub reserved_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How much of a planned batch's expected output is already promised.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PlanCapacity {
+    pub planned_batch_id: Uuid,
+    pub recipe_name: String,
+    pub product_inventory_id: Option<Uuid>,
+    pub product_name: Option<String>,
+    pub product_unit: Option<String>,
+    pub target_date: NaiveDate,
+    /// 'planned', or 'started' while the batch is in progress
+    pub status: String,
+    pub batch_size: BigDecimal,
+    /// Yield assumed: the recipe's average over completed batches, else 100
+    pub yield_percent: BigDecimal,
+    /// Batch size at that yield, in the product's unit
+    pub expected_yield: BigDecimal,
+    /// Soft allocations against the batch
+    pub allocated: BigDecimal,
+    /// Expected yield not yet allocated (negative if over-promised after a
+    /// resize or yield change)
+    pub unallocated: BigDecimal,
+    pub allocations: Vec<PlanAllocation>,
+}
+
+/// Input for promising part of a planned batch to an order.
+#[derive(Debug, InputObject)]
+pub struct AllocatePlannedBatchInput {
+    pub planned_batch_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    /// In the product's unit
+    pub quantity: BigDecimal,
+    pub due_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+}
+
+/// Input for releasing an allocation: a soft one is cancelled, a reserved one
+/// hands its reserved stock back (the order shipped or fell through).
+#[derive(Debug, InputObject)]
+pub struct ReleasePlanAllocationInput {
+    pub allocation_id: Uuid,
+    pub notes: Option<String>,
+}
+
+/// Result from allocating or releasing.
+#[derive(Debug, SimpleObject)]
+pub struct PlanAllocationResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub allocation: Option<PlanAllocation>,
+}
@@ -1,15 +1,19 @@
 use async_graphql::*;
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
+use crate::models::{CalendarEvent, InventoryLot, MutationError, OverheadLine, QualityCheck};
+use crate::services::recipes;
+
 /// Represents a production batch that converts ingredients into finished products.
 ///
 /// A production batch tracks the consumption of ingredients and the creation
 /// of finished goods, with full audit trail in inventory_logs.
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct ProductionBatch {
     pub id: Uuid,
     pub batch_number: String,
@@ -26,12 +30,286 @@ pub struct ProductionBatch {
     pub yield_percentage: Option<BigDecimal>,
     pub actual_yield: Option<BigDecimal>,
     pub quality_notes: Option<String>,
+    pub storage_location_id: Option<Uuid>,
+    /// Name of the storage location
     pub storage_location: Option<String>,
+    /// Vessel the batch is fermenting in
+    pub vessel_id: Option<Uuid>,
+    /// When the batch was last moved between storage areas
+    pub last_moved_at: Option<DateTime<Utc>>,
     pub notes: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[ComplexObject]
+impl ProductionBatch {
+    /// Fermentation monitoring readings for this batch, oldest first
+    async fn readings(&self, ctx: &Context<'_>) -> Result<Vec<BatchReading>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let readings = sqlx::query_as!(
+            BatchReading,
+            r#"
+            SELECT
+                id, batch_id, recorded_at, ph, temperature, salinity_percent,
+                brix, observations, source, device_name, created_at
+            FROM batch_readings
+            WHERE batch_id = $1
+            ORDER BY recorded_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(readings)
+    }
+
+    /// Alerts raised by this batch's readings, newest first
+    async fn alerts(&self, ctx: &Context<'_>) -> Result<Vec<BatchAlert>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let alerts = sqlx::query_as!(
+            BatchAlert,
+            r#"
+            SELECT
+                id, batch_id, reading_id, alert_type, message, measured_value,
+                threshold, acknowledged_at, created_at
+            FROM batch_alerts
+            WHERE batch_id = $1
+            ORDER BY created_at DESC
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(alerts)
+    }
+
+    /// Inventory credited when this batch was completed: the batch product
+    /// first, then any co-products
+    async fn outputs(&self, ctx: &Context<'_>) -> Result<Vec<ProductionBatchOutput>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let outputs = sqlx::query_as!(
+            ProductionBatchOutput,
+            r#"
+            SELECT id, batch_id, inventory_id, quantity, unit, is_primary, created_at
+            FROM production_batch_outputs
+            WHERE batch_id = $1
+            ORDER BY is_primary DESC, created_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(outputs)
+    }
+
+    /// Packaging materials used to package this batch's output
+    async fn packaging(&self, ctx: &Context<'_>) -> Result<Vec<ProductionBatchPackaging>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let packaging = sqlx::query_as!(
+            ProductionBatchPackaging,
+            r#"
+            SELECT id, batch_id, inventory_id, quantity, unit, created_at
+            FROM production_batch_packaging
+            WHERE batch_id = $1
+            ORDER BY created_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(packaging)
+    }
+
+    /// Aging/maturation stage the batch is in now (null for recipes without stages)
+    async fn current_stage(&self, ctx: &Context<'_>) -> Result<Option<BatchStage>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let stage = sqlx::query_as!(
+            BatchStage,
+            r#"
+            SELECT
+                id, batch_id, position, name, expected_days, started_at,
+                completed_at, notes, created_at
+            FROM batch_stages
+            WHERE batch_id = $1 AND completed_at IS NULL
+            "#,
+            self.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(stage)
+    }
+
+    /// Every stage the batch has entered, in order
+    async fn stages(&self, ctx: &Context<'_>) -> Result<Vec<BatchStage>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let stages = sqlx::query_as!(
+            BatchStage,
+            r#"
+            SELECT
+                id, batch_id, position, name, expected_days, started_at,
+                completed_at, notes, created_at
+            FROM batch_stages
+            WHERE batch_id = $1
+            ORDER BY position
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(stages)
+    }
+
+    /// HACCP quality checks recorded on this batch, oldest first
+    async fn quality_checks(&self, ctx: &Context<'_>) -> Result<Vec<QualityCheck>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let checks = sqlx::query_as!(
+            QualityCheck,
+            r#"
+            SELECT
+                id, batch_id, check_type, control_point, passed, measured_value,
+                unit, critical_limit, corrective_action, signed_by, checked_at,
+                notes, created_at
+            FROM quality_checks
+            WHERE batch_id = $1
+            ORDER BY checked_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(checks)
+    }
+}
+
+/// A timestamped fermentation check on a production batch.
+///
+/// Replaces the paper QA notebook: each reading captures whichever of pH,
+/// temperature, brine salinity, and brix were measured plus free-text observations.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct BatchReading {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    /// When the measurement was taken
+    pub recorded_at: DateTime<Utc>,
+    pub ph: Option<BigDecimal>,
+    /// Degrees Fahrenheit
+    pub temperature: Option<BigDecimal>,
+    /// Brine salt concentration (percent by weight)
+    pub salinity_percent: Option<BigDecimal>,
+    /// Sugar content (degrees Brix) for kombucha, kvass, etc.
+    pub brix: Option<BigDecimal>,
+    pub observations: Option<String>,
+    /// 'manual' (entered by hand) or 'sensor' (posted by a probe)
+    pub source: String,
+    /// Probe that sent the reading (sensor readings only)
+    pub device_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for recording a fermentation reading on a batch.
+#[derive(Debug, InputObject)]
+pub struct RecordBatchReadingInput {
+    /// ID of the batch being measured
+    pub batch_id: Uuid,
+    /// When the measurement was taken (defaults to now)
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// pH (0-14)
+    pub ph: Option<BigDecimal>,
+    /// Temperature in degrees Fahrenheit
+    pub temperature: Option<BigDecimal>,
+    /// Brine salinity percentage
+    pub salinity_percent: Option<BigDecimal>,
+    /// Degrees Brix
+    pub brix: Option<BigDecimal>,
+    /// Free-text observations (smell, color, kahm yeast, etc.)
+    pub observations: Option<String>,
+}
+
+/// Result from recording a batch reading.
+#[derive(Debug, SimpleObject)]
+pub struct BatchReadingResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The recorded reading (if successful)
+    pub reading: Option<BatchReading>,
+}
+
+/// A reading that fell outside the batch recipe's temperature range or pH
+/// curve, or a batch flagged as overdue.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct BatchAlert {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    /// Reading that triggered the alert
+    pub reading_id: Option<Uuid>,
+    pub alert_type: String, // 'temperature_low', 'temperature_high', 'ph_high', 'overdue'
+    pub message: String,
+    /// Reading value, or days past estimated completion for 'overdue'
+    pub measured_value: BigDecimal,
+    /// Limit that was crossed
+    pub threshold: BigDecimal,
+    /// When someone acknowledged the alert (None while open)
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for acknowledging a batch alert.
+#[derive(Debug, InputObject)]
+pub struct AcknowledgeBatchAlertInput {
+    /// ID of the alert to acknowledge
+    pub alert_id: Uuid,
+}
+
+/// Result from acknowledging a batch alert.
+#[derive(Debug, SimpleObject)]
+pub struct BatchAlertResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The updated alert (if successful)
+    pub alert: Option<BatchAlert>,
+}
+
+/// A batch still in progress past its estimated completion date.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct OverdueBatch {
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    pub product_inventory_id: Uuid,
+    pub product_name: String,
+    pub start_date: DateTime<Utc>,
+    pub estimated_completion_date: DateTime<Utc>,
+    /// Whole days past the estimated completion date
+    pub days_overdue: i32,
+    pub storage_location: Option<String>,
+    /// Vessel the batch is fermenting in
+    pub vessel_name: Option<String>,
+    /// When the batch got its open 'overdue' batch alert, if it has one
+    pub flagged_at: Option<DateTime<Utc>>,
+}
+
 /// Represents an ingredient used in a production batch.
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
 pub struct ProductionBatchIngredient {
@@ -43,6 +321,48 @@ pub struct ProductionBatchIngredient {
     pub notes: Option<String>,
 }
 
+/// An inventory item credited when a batch was completed.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ProductionBatchOutput {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    pub unit: String,
+    /// True for the batch's own product, false for co-products
+    pub is_primary: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for one output of a completed batch.
+#[derive(Debug, InputObject)]
+pub struct BatchOutputInput {
+    /// ID of the inventory item produced (the batch product or a co-product)
+    pub inventory_id: Uuid,
+    /// Quantity produced
+    pub quantity: BigDecimal,
+}
+
+/// Packaging material (jar, lid, label) used to package a batch's output.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ProductionBatchPackaging {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    pub unit: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for a packaging material used to package a batch's output.
+#[derive(Debug, InputObject)]
+pub struct PackagingMaterialInput {
+    /// ID of the packaging inventory item (jar, lid, label)
+    pub inventory_id: Uuid,
+    /// Quantity consumed
+    pub quantity: BigDecimal,
+}
+
 /// Input for a single ingredient in a production batch.
 #[derive(Debug, InputObject)]
 pub struct IngredientInput {
@@ -66,7 +386,16 @@ pub struct CreateProductionBatchInput {
     /// Optional estimated completion date
     pub estimated_completion_date: Option<DateTime<Utc>>,
     /// Optional storage location for the batch
+    pub storage_location_id: Option<Uuid>,
+    /// Optional storage location by name (created if new); ignored when
+    /// `storage_location_id` is given
     pub storage_location: Option<String>,
+    /// Optional vessel to ferment in (must be available)
+    pub vessel_id: Option<Uuid>,
+    /// Optional planned batch this starts (marked started)
+    pub planned_batch_id: Option<Uuid>,
+    /// Optional product in development this is a trial batch of
+    pub development_id: Option<Uuid>,
     /// List of ingredients consumed in this batch
     pub ingredients: Vec<IngredientInput>,
     /// Optional notes about the production batch
@@ -78,10 +407,16 @@ pub struct CreateProductionBatchInput {
 pub struct CompleteProductionBatchInput {
     /// ID of the batch to complete
     pub batch_id: Uuid,
-    /// Actual quantity produced (may differ from expected batch_size)
-    pub actual_yield: BigDecimal,
+    /// Actual quantity produced (may differ from expected batch_size).
+    /// Required unless the batch product is listed in `outputs`.
+    pub actual_yield: Option<BigDecimal>,
+    /// Everything the batch produced, e.g. sauerkraut plus brine. The entry for
+    /// the batch product (if any) sets the yield; the rest are co-products.
+    pub outputs: Option<Vec<BatchOutputInput>>,
     /// Optional quality notes about the finished product
     pub quality_notes: Option<String>,
+    /// Packaging bill of materials (jars, lids, labels) consumed by the output
+    pub packaging: Option<Vec<PackagingMaterialInput>>,
 }
 
 /// Input for marking a production batch as failed.
@@ -95,23 +430,57 @@ pub struct FailProductionBatchInput {
 
 /// Result from creating a production batch.
 #[derive(Debug, SimpleObject)]
+#[graphql(complex)]
 pub struct ProductionBatchResult {
     /// Whether the operation succeeded
     pub success: bool,
     /// Result message (success or error details)
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
     /// ID of the created batch (if successful)
     pub batch_id: Option<Uuid>,
     /// Batch number (if successful)
     pub batch_number: Option<String>,
 }
 
+#[ComplexObject]
+impl ProductionBatchResult {
+    /// Lots the batch produced, each with its QR code for labelling (empty
+    /// until it is completed)
+    async fn lots(&self, ctx: &Context<'_>) -> Result<Vec<InventoryLot>> {
+        let Some(batch_id) = self.batch_id else {
+            return Ok(Vec::new());
+        };
+        let pool = ctx.data::<PgPool>()?;
+
+        let lots = sqlx::query_as!(
+            InventoryLot,
+            r#"
+            SELECT
+                id, inventory_id, lot_number, code, source_type, supplier_id,
+                production_batch_id, parent_lot_id, location_id, initial_quantity, remaining_quantity,
+                unit_cost, received_date, expiry_date, created_at, updated_at
+            FROM inventory_lots
+            WHERE production_batch_id = $1 AND source_type = 'production'
+            ORDER BY received_date, created_at
+            "#,
+            batch_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(lots)
+    }
+}
+
 /// Represents a recipe template for repeatable production processes.
 ///
 /// Recipe templates define the standard process for making a product,
 /// including ingredient ratios. Can be used for intermediate steps (no product)
 /// or experimental recipes.
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct RecipeTemplate {
     pub id: Uuid,
     /// Optional product ID - null for intermediate/experimental recipes
@@ -121,15 +490,184 @@ pub struct RecipeTemplate {
     pub default_batch_size: Option<BigDecimal>,
     pub default_unit: Option<String>,
     pub estimated_duration_hours: Option<BigDecimal>,
-    /// JSONB field containing ingredient template as array of objects
-    /// Example: [{"inventory_id": "uuid", "quantity_per_unit": 500, "unit": "g"}]
+    /// Legacy JSONB ingredient template, kept for older clients
+    /// Example: {"ingredients": [{"inventory_id": "uuid", "quantity_per_batch": 500, "unit": "g"}]}
+    #[graphql(
+        deprecation = "Use ingredients",
+        visible = "crate::services::versions::before_v2"
+    )]
     pub ingredient_template: Option<serde_json::Value>,
     pub instructions: Option<String>,
+    /// Lowest acceptable fermentation temperature (°F)
+    pub min_temperature: Option<BigDecimal>,
+    /// Highest acceptable fermentation temperature (°F)
+    pub max_temperature: Option<BigDecimal>,
+    /// JSONB array of pH checkpoints the batch should reach
+    /// Example: [{"day": 3, "max_ph": 4.6}, {"day": 10, "max_ph": 3.8}]
+    pub target_ph_curve: Option<serde_json::Value>,
     pub is_active: bool,
+    /// Bumped by each edit; send it back as `expectedVersion`
+    pub version: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[ComplexObject]
+impl RecipeTemplate {
+    /// Ingredients for one default-size batch, in listing order. Recipes saved
+    /// before ingredients had their own table are read from the legacy JSONB.
+    async fn ingredients(&self, ctx: &Context<'_>) -> Result<Vec<RecipeIngredient>> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(recipes::ingredients(pool, self.id).await?)
+    }
+}
+
+/// One ingredient of a recipe template.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct RecipeIngredient {
+    pub inventory_id: Uuid,
+    pub ingredient_name: String,
+    /// Quantity needed for one batch of the recipe's default size
+    pub quantity_per_batch: BigDecimal,
+    pub unit: String,
+}
+
+/// One ingredient line when creating or updating a recipe template.
+#[derive(Debug, Clone, InputObject)]
+pub struct RecipeIngredientInput {
+    /// ID of the ingredient inventory item
+    pub inventory_id: Uuid,
+    /// Quantity needed for one batch of the recipe's default size
+    pub quantity_per_batch: BigDecimal,
+    /// Unit of the quantity (defaults to the item's unit)
+    pub unit: Option<String>,
+}
+
+/// What a batch of a recipe costs to make, and the margin at the product's
+/// sale price.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RecipeCost {
+    pub recipe_template_id: Uuid,
+    pub template_name: String,
+    pub product_inventory_id: Option<Uuid>,
+    /// Batch size the ingredients were scaled to
+    pub batch_size: BigDecimal,
+    pub unit: Option<String>,
+    /// 'current' (latest purchase cost) or 'average' (weighted over open lots)
+    pub cost_basis: String,
+    pub lines: Vec<RecipeCostLine>,
+    /// Total of the ingredients that have a cost
+    pub total_cost: BigDecimal,
+    /// Ingredient total divided by the batch size
+    pub cost_per_unit: BigDecimal,
+    /// Ingredients with no cost on record (left out of the total)
+    pub uncosted: Vec<String>,
+    /// Average hands-on hours of the recipe's completed batches
+    pub labor_hours: Option<BigDecimal>,
+    /// Each active overhead pool's share of the batch
+    pub overhead_lines: Vec<OverheadLine>,
+    /// Total of the overhead lines that could be allocated
+    pub overhead_cost: BigDecimal,
+    /// Ingredients plus overhead, divided by the batch size
+    pub full_cost_per_unit: BigDecimal,
+    /// Sale price per unit of the product, if known
+    pub sale_price: Option<BigDecimal>,
+    /// 'given' or 'recent_sales' (average over the last 90 days of sales)
+    pub price_source: Option<String>,
+    /// Sale price less the full cost per unit
+    pub margin_per_unit: Option<BigDecimal>,
+    pub margin_percent: Option<BigDecimal>,
+}
+
+/// One priced ingredient of a recipe cost.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RecipeCostLine {
+    pub inventory_id: Uuid,
+    pub ingredient_name: String,
+    /// Quantity for the requested batch size
+    pub quantity: BigDecimal,
+    pub unit: String,
+    pub unit_cost: Option<BigDecimal>,
+    pub line_cost: Option<BigDecimal>,
+}
+
+/// Whether a batch of a recipe can be made from stock on hand.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RecipeFeasibility {
+    pub recipe_template_id: Uuid,
+    pub template_name: String,
+    /// Batch size that was checked
+    pub batch_size: BigDecimal,
+    pub unit: Option<String>,
+    /// Whether every ingredient has enough available stock
+    pub can_make: bool,
+    /// Largest batch the available stock allows (null for recipes with no ingredients)
+    pub max_batch_size: Option<BigDecimal>,
+    pub lines: Vec<FeasibilityLine>,
+}
+
+/// Required vs available stock for one ingredient.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct FeasibilityLine {
+    pub inventory_id: Uuid,
+    pub ingredient_name: String,
+    pub unit: String,
+    /// Quantity needed for the requested batch size
+    pub required: BigDecimal,
+    /// Available stock (current less reserved)
+    pub available: BigDecimal,
+    /// How much more is needed (0 if there's enough)
+    pub shortfall: BigDecimal,
+    /// Whether this ingredient caps the maximum batch size
+    pub limiting: bool,
+}
+
+/// A watched recipe's cost limits and how its current cost compares.
+#[derive(Debug, Clone, SimpleObject, Serialize)]
+pub struct RecipeCostWatchStatus {
+    pub recipe_template_id: Uuid,
+    pub template_name: String,
+    pub max_unit_cost: Option<BigDecimal>,
+    pub min_margin_percent: Option<BigDecimal>,
+    /// Ingredient cost per unit at latest purchase costs (null if the recipe can't be costed)
+    pub unit_cost: Option<BigDecimal>,
+    /// Price the margin is measured against
+    pub sale_price: Option<BigDecimal>,
+    pub margin_percent: Option<BigDecimal>,
+    /// Whether any limit is crossed
+    pub breached: bool,
+    /// Which limits are crossed and by how much
+    pub reasons: Vec<String>,
+}
+
+/// Input for setting a recipe's cost limits. At least one limit is required.
+#[derive(Debug, InputObject)]
+pub struct SetRecipeCostWatchInput {
+    pub recipe_template_id: Uuid,
+    /// Alert when the ingredient cost per unit rises above this
+    pub max_unit_cost: Option<BigDecimal>,
+    /// Alert when the margin falls below this percentage
+    pub min_margin_percent: Option<BigDecimal>,
+    /// Optional sale price for the margin (defaults to recent sales)
+    pub sale_price: Option<BigDecimal>,
+}
+
+/// Input for removing a recipe's cost limits.
+#[derive(Debug, InputObject)]
+pub struct DeleteRecipeCostWatchInput {
+    pub recipe_template_id: Uuid,
+}
+
+/// Result from setting a recipe's cost limits.
+#[derive(Debug, SimpleObject)]
+pub struct RecipeCostWatchResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub status: Option<RecipeCostWatchStatus>,
+}
+
 /// Input for creating a new recipe template.
 #[derive(Debug, InputObject)]
 pub struct CreateRecipeTemplateInput {
@@ -145,11 +683,23 @@ pub struct CreateRecipeTemplateInput {
     pub default_unit: Option<String>,
     /// Estimated time to complete in hours
     pub estimated_duration_hours: Option<BigDecimal>,
-    /// JSONB ingredient template structure
+    /// Ingredients for one default-size batch
+    pub ingredients: Option<Vec<RecipeIngredientInput>>,
+    /// Legacy JSONB ingredient template, used when `ingredients` isn't given
     /// Format: {"ingredients": [{"inventory_id": "uuid", "quantity_per_batch": 0.5, "unit": "kg"}]}
+    #[graphql(
+        deprecation = "Use ingredients",
+        visible = "crate::services::versions::before_v2"
+    )]
     pub ingredient_template: Option<serde_json::Value>,
     /// Step-by-step instructions
     pub instructions: Option<String>,
+    /// Lowest acceptable fermentation temperature (°F)
+    pub min_temperature: Option<BigDecimal>,
+    /// Highest acceptable fermentation temperature (°F)
+    pub max_temperature: Option<BigDecimal>,
+    /// pH checkpoints: [{"day": 3, "max_ph": 4.6}, ...]
+    pub target_ph_curve: Option<serde_json::Value>,
 }
 
 /// Input for updating an existing recipe template.
@@ -169,10 +719,25 @@ pub struct UpdateRecipeTemplateInput {
     pub default_unit: Option<String>,
     /// Optional new estimated duration
     pub estimated_duration_hours: Option<BigDecimal>,
-    /// Optional new ingredient template
+    /// Optional replacement ingredient list
+    pub ingredients: Option<Vec<RecipeIngredientInput>>,
+    /// Optional new legacy ingredient template, used when `ingredients` isn't given
+    #[graphql(
+        deprecation = "Use ingredients",
+        visible = "crate::services::versions::before_v2"
+    )]
     pub ingredient_template: Option<serde_json::Value>,
     /// Optional new instructions
     pub instructions: Option<String>,
+    /// Optional new minimum temperature (°F)
+    pub min_temperature: Option<BigDecimal>,
+    /// Optional new maximum temperature (°F)
+    pub max_temperature: Option<BigDecimal>,
+    /// Optional new pH checkpoints
+    pub target_ph_curve: Option<serde_json::Value>,
+    /// The recipe's `version` when the edit started. If the recipe has been
+    /// edited since, the update is refused with `conflict` instead of overwriting.
+    pub expected_version: Option<i32>,
 }
 
 /// Input for deleting a recipe template.
@@ -189,6 +754,338 @@ pub struct RecipeTemplateResult {
     pub success: bool,
     /// Result message (success or error details)
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// True when the update was refused because someone else changed the recipe first
+    pub conflict: bool,
     /// The created or updated recipe template (if successful)
     pub recipe: Option<RecipeTemplate>,
 }
+
+/// A per-recipe rule for moving batches between storage areas.
+///
+/// For example, sauerkraut ferments at ambient temperature for 21 days and then
+/// moves to the walk-in cooler. The scheduler turns these rules into
+/// `BatchMoveTask`s for every in-progress batch made from the recipe.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct StorageTransitionRule {
+    pub id: Uuid,
+    pub recipe_template_id: Uuid,
+    /// Optional expected current location (e.g. "ambient shelf")
+    pub from_location: Option<String>,
+    /// Location the batch should be moved to (e.g. "walk-in cooler")
+    pub to_location: String,
+    /// Days after the batch start date when the move is due
+    pub after_days: i32,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A scheduled reminder to move a production batch to a different storage area.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct BatchMoveTask {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub rule_id: Option<Uuid>,
+    pub from_location: Option<String>,
+    pub to_location: String,
+    pub due_date: DateTime<Utc>,
+    pub status: String, // 'pending', 'completed', 'cancelled'
+    /// When the move actually occurred
+    pub completed_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for creating a storage transition rule on a recipe template.
+#[derive(Debug, InputObject)]
+pub struct CreateStorageTransitionRuleInput {
+    /// ID of the recipe template the rule applies to
+    pub recipe_template_id: Uuid,
+    /// Optional expected current location
+    pub from_location: Option<String>,
+    /// Location to move the batch to
+    pub to_location: String,
+    /// Days after batch start when the move is due
+    pub after_days: i32,
+    /// Optional notes shown with the reminder
+    pub notes: Option<String>,
+}
+
+/// Input for deleting a storage transition rule.
+#[derive(Debug, InputObject)]
+pub struct DeleteStorageTransitionRuleInput {
+    /// ID of the rule to delete
+    pub id: Uuid,
+}
+
+/// Result from creating a storage transition rule.
+#[derive(Debug, SimpleObject)]
+pub struct StorageTransitionRuleResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The created rule (if successful)
+    pub rule: Option<StorageTransitionRule>,
+}
+
+/// Input for recording that a batch move actually happened.
+#[derive(Debug, InputObject)]
+pub struct CompleteBatchMoveInput {
+    /// ID of the move task being completed
+    pub task_id: Uuid,
+    /// When the move happened (defaults to now)
+    pub moved_at: Option<DateTime<Utc>>,
+    /// Optional override of the destination (if moved somewhere else)
+    pub to_location: Option<String>,
+    /// Optional notes about the move
+    pub notes: Option<String>,
+}
+
+/// Result from completing a batch move task.
+#[derive(Debug, SimpleObject)]
+pub struct BatchMoveTaskResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The updated task (if successful)
+    pub task: Option<BatchMoveTask>,
+}
+
+/// One stage in a recipe's aging or maturation schedule (e.g. "koji",
+/// "primary fermentation", "aging").
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct RecipeStage {
+    pub id: Uuid,
+    pub recipe_template_id: Uuid,
+    /// Order of the stage, starting at 1
+    pub position: i32,
+    pub name: String,
+    /// How long the stage usually lasts
+    pub expected_days: Option<i32>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A stage a production batch has entered. The stage without a
+/// `completed_at` is the batch's current stage.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct BatchStage {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub position: i32,
+    pub name: String,
+    pub expected_days: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for one stage of a recipe.
+#[derive(Debug, InputObject)]
+pub struct RecipeStageInput {
+    /// Stage name (e.g. "aging")
+    pub name: String,
+    /// Optional typical length of the stage in days
+    pub expected_days: Option<i32>,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Input for defining a recipe's stages.
+#[derive(Debug, InputObject)]
+pub struct DefineRecipeStagesInput {
+    /// ID of the recipe template
+    pub recipe_template_id: Uuid,
+    /// Stages in order; replaces any existing stages (empty removes them)
+    pub stages: Vec<RecipeStageInput>,
+}
+
+/// Result from defining a recipe's stages.
+#[derive(Debug, SimpleObject)]
+pub struct RecipeStagesResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The recipe's stages in order
+    pub stages: Vec<RecipeStage>,
+}
+
+/// Input for moving a batch on to its next stage.
+#[derive(Debug, InputObject)]
+pub struct AdvanceBatchStageInput {
+    /// ID of the in-progress batch
+    pub batch_id: Uuid,
+    /// When the batch moved on (defaults to now)
+    pub advanced_at: Option<DateTime<Utc>>,
+    /// Optional notes recorded on the new stage
+    pub notes: Option<String>,
+}
+
+/// Result from advancing a batch's stage.
+#[derive(Debug, SimpleObject)]
+pub struct BatchStageResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The stage the batch is now in (if successful)
+    pub stage: Option<BatchStage>,
+}
+
+/// A recurring chore on a recipe (e.g. burp jars every 2 days for the first
+/// week). Batches made from the recipe get a `BatchTask` for each occurrence.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct RecipeTaskTemplate {
+    pub id: Uuid,
+    pub recipe_template_id: Uuid,
+    pub task_type: String, // 'burp', 'check_brine', 'transfer', etc.
+    pub description: String,
+    /// Days after the batch start date when the task is first due
+    pub start_day: i32,
+    /// Repeat interval in days (null for a one-off task)
+    pub repeat_every_days: Option<i32>,
+    /// Last day a repeating task is due
+    pub repeat_until_day: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A chore due on a production batch.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct BatchTask {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    pub template_id: Option<Uuid>,
+    pub task_type: String,
+    pub description: String,
+    pub due_date: DateTime<Utc>,
+    pub status: String, // 'pending', 'completed', 'cancelled'
+    pub completed_at: Option<DateTime<Utc>>,
+    pub completed_by: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Everything scheduled for one day of production.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ProductionCalendarDay {
+    pub date: NaiveDate,
+    /// Batch chores due that day
+    pub tasks: Vec<BatchTask>,
+    /// Storage moves due that day
+    pub moves: Vec<BatchMoveTask>,
+    /// Business calendar entries covering the day
+    pub events: Vec<CalendarEvent>,
+}
+
+/// Input for adding a recurring chore to a recipe template.
+#[derive(Debug, InputObject)]
+pub struct CreateRecipeTaskInput {
+    /// ID of the recipe template
+    pub recipe_template_id: Uuid,
+    /// Kind of chore (e.g. "burp", "check_brine", "transfer")
+    pub task_type: String,
+    /// What to do (e.g. "Burp jars to release CO2")
+    pub description: String,
+    /// Days after the batch start date when the task is first due
+    pub start_day: i32,
+    /// Optional repeat interval in days
+    pub repeat_every_days: Option<i32>,
+    /// Last day a repeating task is due (required with repeat_every_days)
+    pub repeat_until_day: Option<i32>,
+}
+
+/// Input for removing a recurring chore from a recipe template.
+#[derive(Debug, InputObject)]
+pub struct DeleteRecipeTaskInput {
+    /// ID of the task template to delete
+    pub id: Uuid,
+}
+
+/// Result from creating a recipe task template.
+#[derive(Debug, SimpleObject)]
+pub struct RecipeTaskResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The created task template (if successful)
+    pub task: Option<RecipeTaskTemplate>,
+}
+
+/// Input for marking a batch task done.
+#[derive(Debug, InputObject)]
+pub struct CompleteTaskInput {
+    /// ID of the task
+    pub task_id: Uuid,
+    /// Who did it
+    pub completed_by: Option<String>,
+    /// When it was done (defaults to now)
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Optional notes (e.g. "brine level low, topped up")
+    pub notes: Option<String>,
+}
+
+/// Result from completing a batch task.
+#[derive(Debug, SimpleObject)]
+pub struct BatchTaskResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The updated task (if successful)
+    pub task: Option<BatchTask>,
+}
+
+/// Ingredients drawn into production over a period: what a supply contract
+/// is negotiated from.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct IngredientConsumption {
+    pub start_date: NaiveDate,
+    /// Last day of the period (inclusive)
+    pub end_date: NaiveDate,
+    /// How lines are split besides ingredient: 'product', 'month' or both
+    pub group_by: Vec<String>,
+    pub lines: Vec<IngredientConsumptionLine>,
+    /// Cost of everything consumed in the period
+    pub total_cost: BigDecimal,
+}
+
+/// One ingredient's consumption, for one product and/or month when grouped so.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct IngredientConsumptionLine {
+    pub ingredient_id: Uuid,
+    pub ingredient_name: String,
+    pub unit: String,
+    /// Product the batches made (when grouped by product)
+    pub product_id: Option<Uuid>,
+    pub product_name: Option<String>,
+    /// First day of the month (when grouped by month)
+    pub month: Option<NaiveDate>,
+    pub quantity: BigDecimal,
+    /// At each draw's recorded unit cost
+    pub cost: BigDecimal,
+    /// Batches the ingredient went into
+    pub batch_count: i64,
+    /// Draws with no recorded cost, left out of `cost`
+    pub uncosted_entries: i64,
+}
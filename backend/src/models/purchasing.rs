@@ -0,0 +1,261 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+use crate::services::purchasing;
+
+/// An order placed with a supplier. Stock arrives when a purchase is recorded
+/// against it; until then its outstanding quantities count as on order.
+/// Orders over the requester's spend limit wait in `pending_approval` until
+/// the owner approves or rejects them.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct PurchaseOrder {
+    pub id: Uuid,
+    pub po_number: String, // Format: PO-YYYYMMDD-NNN
+    pub supplier_id: Uuid,
+    pub status: String, // 'pending_approval', 'open', 'received', 'rejected', 'cancelled'
+    pub order_date: DateTime<Utc>,
+    pub expected_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+    /// Sum of the lines with a unit cost
+    pub total_amount: BigDecimal,
+    /// Username of who placed the order
+    pub requested_by: Option<String>,
+    #[graphql(skip)]
+    pub requested_by_user_id: Option<Uuid>,
+    /// Owner who approved or rejected the order
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Reason given with the approval or rejection
+    pub decision_note: Option<String>,
+    /// Supplier contract the order draws down
+    pub contract_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl PurchaseOrder {
+    /// Lines of the order with what has been received so far
+    async fn items(&self, ctx: &Context<'_>) -> Result<Vec<PurchaseOrderItem>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let items = sqlx::query_as!(
+            PurchaseOrderItem,
+            r#"
+            SELECT
+                p.id, p.inventory_id, i.name AS item_name, i.unit, p.quantity, p.unit_cost,
+                p.received_quantity,
+                GREATEST(p.quantity - p.received_quantity, 0) AS "outstanding!"
+            FROM purchase_order_items p
+            JOIN inventory i ON i.id = p.inventory_id
+            WHERE p.purchase_order_id = $1
+            ORDER BY i.name
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(items)
+    }
+}
+
+/// One line of a purchase order.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PurchaseOrderItem {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    pub quantity: BigDecimal,
+    /// Agreed cost per unit, if known
+    pub unit_cost: Option<BigDecimal>,
+    pub received_quantity: BigDecimal,
+    /// Quantity still to arrive
+    pub outstanding: BigDecimal,
+}
+
+/// One line of a new purchase order.
+#[derive(Debug, InputObject)]
+pub struct PurchaseOrderItemInput {
+    /// ID of the inventory item being ordered
+    pub inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    /// Optional agreed cost per unit
+    pub unit_cost: Option<BigDecimal>,
+}
+
+/// Input for placing a purchase order.
+#[derive(Debug, InputObject)]
+pub struct CreatePurchaseOrderInput {
+    pub supplier_id: Uuid,
+    /// When the goods are expected to arrive
+    pub expected_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+    /// Supplier contract to draw down. Lines for items on the contract take
+    /// its agreed price when no unit cost is given.
+    pub contract_id: Option<Uuid>,
+    pub items: Vec<PurchaseOrderItemInput>,
+}
+
+/// Input for cancelling an open or pending purchase order.
+#[derive(Debug, InputObject)]
+pub struct CancelPurchaseOrderInput {
+    pub purchase_order_id: Uuid,
+    /// Optional reason, appended to the order's notes
+    pub reason: Option<String>,
+}
+
+/// Input for approving or rejecting a purchase order waiting for approval.
+#[derive(Debug, InputObject)]
+pub struct DecidePurchaseOrderInput {
+    pub purchase_order_id: Uuid,
+    /// Optional note for the requester; on a rejection, the reason
+    pub note: Option<String>,
+}
+
+/// Result from placing, approving, rejecting or cancelling a purchase order.
+#[derive(Debug, SimpleObject)]
+pub struct PurchaseOrderResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub purchase_order: Option<PurchaseOrder>,
+}
+
+/// Largest purchase order a role can place without the owner's approval.
+/// Roles without a limit need approval for every order; the owner never does.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PurchaseApprovalLimit {
+    pub id: Uuid,
+    pub role: String, // 'production', 'sales'
+    pub max_amount: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for setting or clearing a role's purchase approval limit.
+#[derive(Debug, InputObject)]
+pub struct SetPurchaseApprovalLimitInput {
+    /// 'production' or 'sales'
+    pub role: String,
+    /// Orders up to this total go straight to open; leave empty to require
+    /// approval for every order from the role
+    pub max_amount: Option<BigDecimal>,
+}
+
+/// Result from setting a purchase approval limit.
+#[derive(Debug, SimpleObject)]
+pub struct PurchaseApprovalLimitResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The limit now in force; empty when it was cleared
+    pub limit: Option<PurchaseApprovalLimit>,
+}
+
+/// A blanket order with a supplier: volumes we've committed to buy at agreed
+/// prices between two dates. Purchase orders placed against it draw it down.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct SupplierContract {
+    pub id: Uuid,
+    pub contract_number: String,
+    pub supplier_id: Uuid,
+    pub start_date: NaiveDate,
+    /// Last day the agreed prices hold
+    pub end_date: NaiveDate,
+    pub status: String, // 'active', 'closed'
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl SupplierContract {
+    /// Committed, ordered and remaining quantity per item
+    async fn lines(&self, ctx: &Context<'_>) -> Result<Vec<SupplierContractLine>> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut conn = pool.acquire().await?;
+
+        Ok(purchasing::contract_lines(&mut conn, self.id).await?)
+    }
+
+    /// Whether orders placed today get the agreed prices
+    async fn price_protected(&self) -> bool {
+        let today = Utc::now().date_naive();
+        self.status == "active" && self.start_date <= today && today <= self.end_date
+    }
+
+    /// Days left until price protection ends (negative once it has)
+    async fn days_until_expiry(&self) -> i64 {
+        (self.end_date - Utc::now().date_naive()).num_days()
+    }
+}
+
+/// One item on a supplier contract and how much of it has been ordered.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SupplierContractLine {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
+    pub committed_quantity: BigDecimal,
+    pub agreed_price: BigDecimal,
+    /// On orders against the contract; cancelled or rejected orders count
+    /// only what was received
+    pub ordered_quantity: BigDecimal,
+    pub received_quantity: BigDecimal,
+    /// Still to be ordered
+    pub remaining_quantity: BigDecimal,
+    /// `remaining_quantity` at the agreed price
+    pub remaining_value: BigDecimal,
+}
+
+/// One item committed to on a new contract.
+#[derive(Debug, InputObject)]
+pub struct SupplierContractItemInput {
+    pub inventory_id: Uuid,
+    pub committed_quantity: BigDecimal,
+    /// Agreed cost per unit
+    pub agreed_price: BigDecimal,
+}
+
+/// Input for recording a supplier contract.
+#[derive(Debug, InputObject)]
+pub struct CreateSupplierContractInput {
+    pub supplier_id: Uuid,
+    /// Our or the supplier's reference, e.g. "CAB-2025"
+    pub contract_number: String,
+    pub start_date: NaiveDate,
+    /// Last day the agreed prices hold
+    pub end_date: NaiveDate,
+    pub notes: Option<String>,
+    pub items: Vec<SupplierContractItemInput>,
+}
+
+/// Input for closing a supplier contract early or once it is used up.
+#[derive(Debug, InputObject)]
+pub struct CloseSupplierContractInput {
+    pub contract_id: Uuid,
+    /// Optional reason, appended to the contract's notes
+    pub reason: Option<String>,
+}
+
+/// Result from recording or closing a supplier contract.
+#[derive(Debug, SimpleObject)]
+pub struct SupplierContractResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub contract: Option<SupplierContract>,
+}
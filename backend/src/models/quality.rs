@@ -0,0 +1,197 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A customer complaint about a product, tied back to the lot and batch it came from.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct Complaint {
+    pub id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub sale_id: Option<Uuid>,
+    pub inventory_id: Option<Uuid>,
+    pub lot_id: Option<Uuid>,
+    pub production_batch_id: Option<Uuid>,
+    pub description: String,
+    pub severity: String,            // 'low', 'medium', 'high'
+    pub status: String,              // 'open', 'resolved'
+    pub disposition: Option<String>, // 'refund', 'replace', 'no_action'
+    pub refund_amount: Option<BigDecimal>,
+    pub replacement_quantity: Option<BigDecimal>,
+    pub resolution_notes: Option<String>,
+    pub reported_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Review of a sibling lot (same batch as the complaint) for the same defect.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ComplaintLotReview {
+    pub id: Uuid,
+    pub complaint_id: Uuid,
+    pub lot_id: Uuid,
+    pub status: String, // 'pending', 'cleared', 'quarantined', 'discarded'
+    pub notes: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A HACCP checkpoint record on a production batch.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct QualityCheck {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub check_type: String, // 'ph', 'temperature', 'salt_concentration', 'visual', 'label_review', etc.
+    /// HACCP plan reference (e.g. "CCP-1 Acidification")
+    pub control_point: Option<String>,
+    pub passed: bool,
+    pub measured_value: Option<BigDecimal>,
+    pub unit: Option<String>,
+    /// Limit the value was checked against (e.g. "pH <= 4.6")
+    pub critical_limit: Option<String>,
+    /// What was done about a failed check
+    pub corrective_action: Option<String>,
+    /// Who performed and signed off the check
+    pub signed_by: String,
+    pub checked_at: DateTime<Utc>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Complaint with its sibling lot reviews.
+#[derive(Debug, SimpleObject)]
+pub struct ComplaintDetails {
+    pub complaint: Complaint,
+    pub lot_reviews: Vec<ComplaintLotReview>,
+}
+
+/// Input for logging a customer complaint.
+///
+/// At least one of `lot_id`, `batch_number`, or `sale_id` is required so the
+/// complaint can be tied back to a batch.
+#[derive(Debug, InputObject)]
+pub struct CreateComplaintInput {
+    pub customer_id: Option<Uuid>,
+    /// Sale the product was bought on
+    pub sale_id: Option<Uuid>,
+    /// Product the complaint is about
+    pub inventory_id: Option<Uuid>,
+    /// Lot the product came from (e.g. from the jar label)
+    pub lot_id: Option<Uuid>,
+    /// Batch number the product came from (e.g. from the jar label)
+    pub batch_number: Option<String>,
+    /// What the customer reported
+    pub description: String,
+    /// 'low', 'medium' (default), or 'high'
+    pub severity: Option<String>,
+    /// When the complaint was reported (defaults to now)
+    pub reported_at: Option<DateTime<Utc>>,
+}
+
+/// Result from creating or resolving a complaint.
+#[derive(Debug, SimpleObject)]
+pub struct ComplaintResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The complaint with its lot reviews (if successful)
+    pub complaint: Option<ComplaintDetails>,
+}
+
+/// Input for recording the outcome of a sibling lot review.
+#[derive(Debug, InputObject)]
+pub struct ReviewComplaintLotInput {
+    /// ID of the lot review
+    pub review_id: Uuid,
+    /// 'cleared', 'quarantined', or 'discarded' (discarding writes off the lot's remaining stock)
+    pub status: String,
+    pub notes: Option<String>,
+}
+
+/// Result from reviewing a sibling lot.
+#[derive(Debug, SimpleObject)]
+pub struct ComplaintLotReviewResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The updated review (if successful)
+    pub review: Option<ComplaintLotReview>,
+}
+
+/// Input for resolving a complaint.
+#[derive(Debug, InputObject)]
+pub struct ResolveComplaintInput {
+    pub complaint_id: Uuid,
+    /// 'refund', 'replace', or 'no_action'
+    pub disposition: String,
+    /// Amount refunded (required for 'refund')
+    pub refund_amount: Option<BigDecimal>,
+    /// Quantity of product given as a replacement (required for 'replace')
+    pub replacement_quantity: Option<BigDecimal>,
+    pub resolution_notes: Option<String>,
+}
+
+/// Complaint statistics for one supplier or recipe.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct QualityStat {
+    /// Supplier or recipe template ID
+    pub id: Uuid,
+    pub name: String,
+    /// Batches produced in the period
+    pub batch_count: i64,
+    /// Batches marked as failed in the period
+    pub failed_batch_count: i64,
+    /// Complaints against those batches
+    pub complaint_count: i64,
+    pub refund_total: BigDecimal,
+}
+
+/// Quality analytics rolled up by supplier and recipe.
+#[derive(Debug, SimpleObject)]
+pub struct QualityAnalytics {
+    /// Suppliers whose ingredient lots went into the batches
+    pub suppliers: Vec<QualityStat>,
+    pub recipes: Vec<QualityStat>,
+}
+
+/// Input for recording a quality check on a batch.
+#[derive(Debug, InputObject)]
+pub struct RecordQualityCheckInput {
+    pub batch_id: Uuid,
+    /// Kind of check (e.g. "ph", "temperature", "visual")
+    pub check_type: String,
+    /// Optional HACCP plan reference
+    pub control_point: Option<String>,
+    pub passed: bool,
+    pub measured_value: Option<BigDecimal>,
+    pub unit: Option<String>,
+    pub critical_limit: Option<String>,
+    /// Required when the check failed
+    pub corrective_action: Option<String>,
+    /// Who performed and signed off the check
+    pub signed_by: String,
+    /// When the check was done (defaults to now)
+    pub checked_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+}
+
+/// Result from recording a quality check.
+#[derive(Debug, SimpleObject)]
+pub struct QualityCheckResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub check: Option<QualityCheck>,
+}
@@ -1,11 +1,11 @@
 use async_graphql::*;
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-use crate::models::InventoryItem;
+use crate::models::{InventoryItem, Invoice, MutationError};
 
 /// Represents a customer who purchases products.
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
@@ -23,6 +23,11 @@ pub struct Customer {
     pub longitude: Option<BigDecimal>,
     pub customer_type: Option<String>, // 'retail', 'wholesale', 'restaurant', etc.
     pub tax_exempt: bool,
+    /// Agreed to texted pickup reminders
+    pub sms_opt_in: bool,
+    /// Days their invoices have to be paid in (15 for net 15); 0 is due on
+    /// receipt
+    pub payment_terms_days: i32,
     pub notes: Option<String>,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
@@ -33,7 +38,7 @@ pub struct Customer {
 #[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
 pub struct Sale {
     pub id: Uuid,
-    pub sale_number: String, // Format: SALE-YYYYMMDD-NNN
+    pub sale_number: String, // SALE_NUMBER_FORMAT, default SALE-YYYYMMDD-NNN
     pub customer_id: Option<Uuid>,
     pub sale_date: DateTime<Utc>,
     pub subtotal: BigDecimal,
@@ -43,6 +48,17 @@ pub struct Sale {
     pub payment_method: Option<String>, // 'cash', 'card', 'check', 'invoice', etc.
     pub payment_status: String,         // 'completed', 'pending', 'refunded'
     pub notes: Option<String>,
+    /// Where the sale was made (e.g. 'in_person', 'farmers_market', 'online')
+    pub channel: String,
+    /// Stock check policy the sale was made under: 'strict', 'allow_negative' or 'review'
+    pub stock_check: String,
+    /// Sold more than was on record, taking stock negative
+    pub oversold: bool,
+    /// 'pending' while an oversold sale waits for review, then 'resolved'
+    pub stock_review_status: Option<String>,
+    pub stock_review_note: Option<String>,
+    /// Market day the sale was made at
+    pub market_session_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -53,18 +69,45 @@ pub struct SaleItem {
     pub id: Uuid,
     pub sale_id: Uuid,
     pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit: String,
     pub quantity: BigDecimal,
     pub unit_price: BigDecimal,
+    /// Quantity times unit price, before discounts
     pub line_total: BigDecimal,
+    /// Taken off this line by its own discounts
+    pub discount_amount: BigDecimal,
     pub notes: Option<String>,
 }
 
+/// A discount a sale got, on one of its lines or on the whole sale.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SaleDiscount {
+    pub id: Uuid,
+    pub sale_id: Uuid,
+    /// The line it was taken off; None for the whole sale
+    pub sale_item_id: Option<Uuid>,
+    pub promo_code_id: Option<Uuid>,
+    pub promo_code: Option<String>,
+    /// 'percentage' or 'fixed'
+    pub discount_type: String,
+    /// Percent off, or amount off
+    pub value: BigDecimal,
+    /// What it took off
+    pub amount: BigDecimal,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Sale with embedded items for convenient querying.
 #[derive(Debug, SimpleObject)]
 pub struct SaleWithItems {
     pub sale: Sale,
     pub items: Vec<SaleItem>,
     pub customer: Option<Customer>,
+    pub discounts: Vec<SaleDiscount>,
+    /// Set when the sale was paid for by invoice
+    pub invoice: Option<Invoice>,
 }
 
 /// Input for creating a new customer.
@@ -82,6 +125,9 @@ pub struct CreateCustomerInput {
     pub longitude: Option<BigDecimal>,
     pub customer_type: Option<String>,
     pub tax_exempt: Option<bool>,
+    pub sms_opt_in: Option<bool>,
+    /// Days invoices are due in (net 15, net 30); default 0, on receipt
+    pub payment_terms_days: Option<i32>,
     pub notes: Option<String>,
 }
 
@@ -101,6 +147,8 @@ pub struct UpdateCustomerInput {
     pub longitude: Option<BigDecimal>,
     pub customer_type: Option<String>,
     pub tax_exempt: Option<bool>,
+    pub sms_opt_in: Option<bool>,
+    pub payment_terms_days: Option<i32>,
     pub notes: Option<String>,
     pub is_active: Option<bool>,
 }
@@ -110,6 +158,8 @@ pub struct UpdateCustomerInput {
 pub struct CustomerResult {
     pub success: bool,
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
     pub customer: Option<Customer>,
 }
 
@@ -120,12 +170,26 @@ pub struct SaleItemInput {
     pub inventory_id: Uuid,
     /// Quantity sold
     pub quantity: BigDecimal,
-    /// Price per unit at time of sale
-    pub unit_price: BigDecimal,
+    /// Price per unit at time of sale; when left out, the customer's price
+    /// (see `priceFor`)
+    pub unit_price: Option<BigDecimal>,
+    /// Discount on this line only
+    pub discount: Option<DiscountInput>,
     /// Optional notes for this line item
     pub notes: Option<String>,
 }
 
+/// A discount given at the till.
+#[derive(Debug, Clone, InputObject)]
+pub struct DiscountInput {
+    /// 'percentage' or 'fixed'
+    pub discount_type: String,
+    /// Percent off (up to 100), or amount off
+    pub value: BigDecimal,
+    /// Why it was given (e.g. "dented lid", "staff")
+    pub reason: Option<String>,
+}
+
 /// Input for creating a new sale.
 #[derive(Debug, InputObject)]
 pub struct CreateSaleInput {
@@ -137,27 +201,272 @@ pub struct CreateSaleInput {
     pub items: Vec<SaleItemInput>,
     /// Optional tax amount
     pub tax_amount: Option<BigDecimal>,
-    /// Optional discount amount
+    /// Amount off the whole sale; taken as a fixed discount
+    #[graphql(
+        deprecation = "Use discounts",
+        visible = "crate::services::versions::before_v2"
+    )]
     pub discount_amount: Option<BigDecimal>,
-    /// Payment method
+    /// Discounts on the whole sale, taken after line discounts, in order
+    pub discounts: Option<Vec<DiscountInput>>,
+    /// Promo code the customer gave
+    pub promo_code: Option<String>,
+    /// Payment method; 'invoice' bills the customer, due after their
+    /// payment terms
     pub payment_method: Option<String>,
-    /// Payment status (defaults to 'completed')
+    /// Payment status (defaults to 'completed', or 'pending' when invoiced)
     pub payment_status: Option<String>,
     /// Optional notes about the sale
     pub notes: Option<String>,
+    /// Where the sale is made (default 'in_person'); its policy decides what
+    /// happens when an item has less stock on record than is sold
+    pub channel: Option<String>,
+    /// Market session the sale is made at; the channel then defaults to
+    /// 'farmers_market'
+    pub market_session_id: Option<Uuid>,
+    /// Optional client-generated key (e.g. a UUID per checkout). Retrying with
+    /// the same key returns the first successful result instead of selling again.
+    pub idempotency_key: Option<String>,
 }
 
 /// Result from creating a sale.
-#[derive(Debug, SimpleObject)]
+#[derive(Debug, SimpleObject, Serialize, Deserialize)]
 pub struct SaleResult {
     /// Whether the operation succeeded
     pub success: bool,
     /// Human-readable message
     pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
     /// ID of the created sale
     pub sale_id: Option<Uuid>,
     /// Sale number (e.g., SALE-20251015-001)
     pub sale_number: Option<String>,
     /// Inventory items that were updated (stock decremented)
     pub updated_items: Vec<InventoryItem>,
+    /// Items sold beyond the stock on record, when the channel allows it
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// How `createSale` treats a sale of more than is on record for one channel.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SaleChannelPolicy {
+    pub id: Uuid,
+    pub channel: String,
+    /// 'strict' (refuse the sale), 'allow_negative' (sell with a warning and
+    /// let stock go negative) or 'review' (the same, and queue the sale for review)
+    pub stock_check: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for setting a channel's stock check policy.
+#[derive(Debug, InputObject)]
+pub struct SetSaleChannelPolicyInput {
+    pub channel: String,
+    /// 'strict', 'allow_negative' or 'review'
+    pub stock_check: String,
+}
+
+/// Result from setting a channel's stock check policy.
+#[derive(Debug, SimpleObject)]
+pub struct SaleChannelPolicyResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub policy: Option<SaleChannelPolicy>,
+}
+
+/// A code customers give at checkout for a discount. Codes for an item
+/// take their discount off that item's lines (a fixed one off each unit),
+/// others off the whole sale.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PromoCode {
+    pub id: Uuid,
+    pub code: String,
+    pub description: Option<String>,
+    /// 'percentage' or 'fixed'
+    pub discount_type: String,
+    pub value: BigDecimal,
+    pub inventory_id: Option<Uuid>,
+    /// First day it can be used
+    pub starts_on: Option<NaiveDate>,
+    /// Last day it can be used
+    pub ends_on: Option<NaiveDate>,
+    /// Sales it can be used on; None for no limit
+    pub max_uses: Option<i32>,
+    /// Sales it has been used on
+    pub times_used: i64,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating a promo code.
+#[derive(Debug, InputObject)]
+pub struct CreatePromoCodeInput {
+    /// Letters, digits, '-' and '_'; stored upper case
+    pub code: String,
+    pub description: Option<String>,
+    /// 'percentage' or 'fixed'
+    pub discount_type: String,
+    pub value: BigDecimal,
+    /// Limits the code to this item
+    pub inventory_id: Option<Uuid>,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+    pub max_uses: Option<i32>,
+}
+
+/// Input for updating a promo code. Its discount can't change once sales
+/// may have used it; make a new code instead.
+#[derive(Debug, InputObject)]
+pub struct UpdatePromoCodeInput {
+    pub id: Uuid,
+    pub description: Option<String>,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+    pub max_uses: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+/// Result from promo code operations.
+#[derive(Debug, SimpleObject)]
+pub struct PromoCodeResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub promo_code: Option<PromoCode>,
+}
+
+/// Discounts given over a period, grouped by promo code, type and whether
+/// they were on lines or whole sales.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct DiscountSummaryLine {
+    /// None for discounts given by hand
+    pub promo_code: Option<String>,
+    /// 'percentage' or 'fixed'
+    pub discount_type: String,
+    /// 'line' or 'order'
+    pub scope: String,
+    pub discounts: i64,
+    pub sales_count: i64,
+    pub total: BigDecimal,
+}
+
+/// What an item sells for to one type of customer.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct PriceListItem {
+    pub id: Uuid,
+    pub customer_type: String, // 'retail', 'wholesale', 'restaurant', etc.
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit_price: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A price agreed with one customer, ahead of their type's price list.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct CustomerPrice {
+    pub id: Uuid,
+    pub customer_id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub unit_price: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The price `createSale` uses for an item when none is given.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ResolvedPrice {
+    pub inventory_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    /// None when no price applies; the sale then needs one given
+    pub unit_price: Option<BigDecimal>,
+    /// 'customer' (the customer's own price) or 'price_list'
+    pub source: Option<String>,
+    /// Customer type of the price list used ('retail' when the customer's
+    /// type has no price)
+    pub price_list: Option<String>,
+}
+
+/// Input for setting an item's price for a type of customer.
+#[derive(Debug, InputObject)]
+pub struct SetListPriceInput {
+    pub customer_type: String,
+    pub inventory_id: Uuid,
+    /// Null removes the price
+    pub unit_price: Option<BigDecimal>,
+}
+
+/// Result from setting a price list price.
+#[derive(Debug, SimpleObject)]
+pub struct ListPriceResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The price as set; None when it was removed
+    pub price: Option<PriceListItem>,
+}
+
+/// Input for setting an item's price for one customer.
+#[derive(Debug, InputObject)]
+pub struct SetCustomerPriceInput {
+    pub customer_id: Uuid,
+    pub inventory_id: Uuid,
+    /// Null removes the price, so the customer's price list applies again
+    pub unit_price: Option<BigDecimal>,
+}
+
+/// Result from setting a customer's price.
+#[derive(Debug, SimpleObject)]
+pub struct CustomerPriceResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The price as set; None when it was removed
+    pub price: Option<CustomerPrice>,
+}
+
+/// Input for closing the stock review of an oversold sale.
+#[derive(Debug, InputObject)]
+pub struct ResolveSaleStockReviewInput {
+    pub sale_id: Uuid,
+    /// What was found (e.g. "delivery from Monday hadn't been entered")
+    pub note: Option<String>,
+}
+
+/// Result from resolving a sale's stock review.
+#[derive(Debug, SimpleObject)]
+pub struct SaleReviewResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub sale: Option<Sale>,
+}
+
+/// Revenue for a period, split by source. Workshop tickets are their own line
+/// because they aren't sales of inventory.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RevenueSummary {
+    pub start_date: NaiveDate,
+    /// Last day of the period (inclusive)
+    pub end_date: NaiveDate,
+    /// Total of sales that weren't refunded
+    pub product_sales: BigDecimal,
+    pub sales_count: i64,
+    /// Taken off those sales by discounts
+    pub discounts: BigDecimal,
+    /// Total of workshop tickets that weren't cancelled
+    pub ticket_sales: BigDecimal,
+    pub tickets_sold: i64,
+    pub total: BigDecimal,
 }
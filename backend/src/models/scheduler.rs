@@ -0,0 +1,45 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::MutationError;
+
+/// A background job the scheduler runs, with its latest outcome.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    /// Job name (e.g. "expiry_scan")
+    pub name: String,
+    pub description: String,
+    /// Cron expression in UTC (e.g. "0 11 * * *"), or None when turned off
+    pub schedule: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// When the run in progress started, if one is
+    pub running_since: Option<DateTime<Utc>>,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// 'succeeded' or 'failed'
+    pub last_status: Option<String>,
+    /// What the last run did, or why it failed
+    pub last_message: Option<String>,
+    /// 'schedule' or 'manual' (runJobNow)
+    pub last_trigger: Option<String>,
+    pub run_count: i32,
+    pub failure_count: i32,
+}
+
+/// Input for running a scheduled job straight away.
+#[derive(Debug, InputObject)]
+pub struct RunJobNowInput {
+    /// Job name (see `scheduledJobs`)
+    pub name: String,
+}
+
+/// Result from running a job.
+#[derive(Debug, SimpleObject)]
+pub struct ScheduledJobResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub job: Option<ScheduledJob>,
+}
@@ -0,0 +1,64 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// An integration secret as exposed over GraphQL. The value itself is never
+/// returned; only a masked form showing its last few characters.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct IntegrationSecret {
+    pub id: Uuid,
+    /// Secret name (e.g. "alert_webhook_url", "stripe_api_key")
+    pub name: String,
+    pub description: Option<String>,
+    /// Masked value (e.g. "••••a1b2")
+    pub masked_value: String,
+    /// Incremented every time the value changes
+    pub version: i32,
+    /// When the value was last rotated
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating or replacing an integration secret.
+#[derive(Debug, InputObject)]
+pub struct SetSecretInput {
+    /// Secret name (lowercase letters, digits, and underscores)
+    pub name: String,
+    /// Plaintext value; encrypted before it is stored
+    pub value: String,
+    /// Optional note on what the secret is for
+    pub description: Option<String>,
+}
+
+/// Input for rotating an existing integration secret.
+#[derive(Debug, InputObject)]
+pub struct RotateSecretInput {
+    /// Name of the secret to rotate
+    pub name: String,
+    /// New plaintext value
+    pub value: String,
+}
+
+/// Input for deleting an integration secret.
+#[derive(Debug, InputObject)]
+pub struct DeleteSecretInput {
+    /// Name of the secret to delete
+    pub name: String,
+}
+
+/// Result from setting or rotating an integration secret.
+#[derive(Debug, SimpleObject)]
+pub struct SecretResult {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Result message (success or error details)
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The saved secret, masked (if successful)
+    pub secret: Option<IntegrationSecret>,
+}
@@ -0,0 +1,164 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A Shopify product variant and the inventory item it sells.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ShopifyProduct {
+    pub id: Uuid,
+    /// Shopify variant ID
+    pub variant_id: i64,
+    /// Shopify inventory item ID its stock level is set on
+    pub inventory_item_id: i64,
+    /// Product and variant title as Shopify showed them (e.g. "Kimchi - 16 oz")
+    pub shopify_name: Option<String>,
+    pub inventory_id: Uuid,
+    pub inventory_name: String,
+    /// Stock units one Shopify unit takes (e.g. 6 for a six-pack)
+    pub quantity_per_unit: BigDecimal,
+    /// Whether available stock is pushed to Shopify
+    pub sync_enabled: bool,
+    /// Quantity Shopify was last set to
+    pub last_pushed_quantity: Option<i32>,
+    pub last_pushed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A Shopify variant an order sold that has no mapping yet.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ShopifyVariant {
+    pub variant_id: i64,
+    pub name: Option<String>,
+}
+
+/// A paid Shopify order pulled by the sync.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ShopifyOrder {
+    pub id: Uuid,
+    /// Shopify order ID
+    pub order_id: i64,
+    /// Order name customers see (e.g. "#1001")
+    pub order_name: Option<String>,
+    pub processed_at: DateTime<Utc>,
+    /// 'pending', 'imported', 'unmapped' (waiting for `unmapped` to be mapped)
+    /// or 'skipped' (cancelled, or nothing stocked was sold)
+    pub status: String,
+    pub sale_id: Option<Uuid>,
+    pub sale_number: Option<String>,
+    pub unmapped: Vec<ShopifyVariant>,
+    /// Lines left out (e.g. custom items) and stock warnings
+    pub issues: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An unmapped Shopify variant, with how many orders wait on it.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct UnmappedShopifyVariant {
+    pub variant_id: i64,
+    pub name: Option<String>,
+    pub waiting_orders: i64,
+    pub oldest_order_at: DateTime<Utc>,
+}
+
+/// A disagreement between Shopify and the stock on record.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ShopifySyncConflict {
+    pub id: Uuid,
+    pub shopify_product_id: Option<Uuid>,
+    pub shopify_name: Option<String>,
+    /// Order that oversold, for 'oversold' conflicts
+    pub shopify_order_id: Option<Uuid>,
+    /// 'stock_drift' (Shopify's stock changed outside the sync) or
+    /// 'oversold' (an order sold more than was on record)
+    pub kind: String,
+    /// Quantity the sync last set on Shopify
+    pub expected_quantity: Option<i32>,
+    /// Quantity Shopify had
+    pub shopify_quantity: Option<i32>,
+    /// Quantity the sync set it to
+    pub pushed_quantity: Option<i32>,
+    pub message: String,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolution_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for mapping a Shopify variant to an inventory item.
+#[derive(Debug, InputObject)]
+pub struct MapShopifyProductInput {
+    /// Shopify variant ID (see `unmappedShopifyVariants`)
+    pub variant_id: i64,
+    pub inventory_id: Uuid,
+    /// Stock units one Shopify unit takes (defaults to 1)
+    pub quantity_per_unit: Option<BigDecimal>,
+    /// Push available stock to Shopify (defaults to true)
+    pub sync_enabled: Option<bool>,
+}
+
+/// Input for turning stock pushes for a mapped variant on or off.
+#[derive(Debug, InputObject)]
+pub struct SetShopifyProductSyncInput {
+    pub id: Uuid,
+    pub sync_enabled: bool,
+}
+
+/// Input for removing a Shopify mapping.
+#[derive(Debug, InputObject)]
+pub struct DeleteShopifyProductInput {
+    pub id: Uuid,
+}
+
+/// Input for closing a sync conflict.
+#[derive(Debug, InputObject)]
+pub struct ResolveShopifySyncConflictInput {
+    pub id: Uuid,
+    /// What was done about it (e.g. "Recounted, Shopify was right")
+    pub note: Option<String>,
+}
+
+/// Result from mapping a Shopify variant or changing its sync.
+#[derive(Debug, SimpleObject)]
+pub struct ShopifyProductResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub product: Option<ShopifyProduct>,
+    /// Waiting orders imported now that the variant is mapped
+    pub imported_orders: i32,
+}
+
+/// Result from resolving a sync conflict.
+#[derive(Debug, SimpleObject)]
+pub struct ShopifySyncConflictResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub conflict: Option<ShopifySyncConflict>,
+}
+
+/// Result from syncing with Shopify.
+#[derive(Debug, SimpleObject)]
+pub struct ShopifySyncResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// New orders pulled from Shopify
+    pub fetched: i32,
+    /// Orders that became sales
+    pub imported: i32,
+    /// Orders waiting for a mapping
+    pub unmapped: i32,
+    /// Stock levels changed on Shopify
+    pub pushed: i32,
+    /// Conflicts logged by this sync
+    pub conflicts: i32,
+}
@@ -0,0 +1,104 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A Square catalog item variation and the inventory item it sells.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct SquareItemMapping {
+    pub id: Uuid,
+    /// Square item variation ID
+    pub catalog_object_id: String,
+    /// Item and variation name as Square showed them (e.g. "Sauerkraut, 16 oz")
+    pub square_name: Option<String>,
+    pub inventory_id: Uuid,
+    pub inventory_name: String,
+    /// Stock units one Square unit takes (e.g. 6 for a six-pack)
+    pub quantity_per_unit: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A Square variation an order sold that has no mapping yet.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct SquareCatalogItem {
+    pub catalog_object_id: String,
+    pub name: Option<String>,
+}
+
+/// A completed Square order pulled by the import.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct SquareOrder {
+    pub id: Uuid,
+    /// Square order ID
+    pub order_id: String,
+    pub location_id: Option<String>,
+    pub closed_at: DateTime<Utc>,
+    /// 'pending', 'imported', 'unmapped' (waiting for `unmapped` to be mapped)
+    /// or 'skipped' (nothing stocked was sold)
+    pub status: String,
+    pub sale_id: Option<Uuid>,
+    pub sale_number: Option<String>,
+    pub unmapped: Vec<SquareCatalogItem>,
+    /// Lines left out (e.g. custom amounts) and stock warnings
+    pub issues: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An unmapped Square variation, with how many orders wait on it.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct UnmappedSquareItem {
+    pub catalog_object_id: String,
+    pub name: Option<String>,
+    pub waiting_orders: i64,
+    pub oldest_order_at: DateTime<Utc>,
+}
+
+/// Input for mapping a Square variation to an inventory item.
+#[derive(Debug, InputObject)]
+pub struct MapSquareItemInput {
+    /// Square item variation ID (see `unmappedSquareItems`)
+    pub catalog_object_id: String,
+    pub inventory_id: Uuid,
+    /// Stock units one Square unit takes (defaults to 1)
+    pub quantity_per_unit: Option<BigDecimal>,
+}
+
+/// Input for removing a Square mapping.
+#[derive(Debug, InputObject)]
+pub struct DeleteSquareItemMappingInput {
+    pub id: Uuid,
+}
+
+/// Result from mapping a Square variation.
+#[derive(Debug, SimpleObject)]
+pub struct SquareItemMappingResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub mapping: Option<SquareItemMapping>,
+    /// Waiting orders imported now that the variation is mapped
+    pub imported_orders: i32,
+}
+
+/// Result from pulling orders from Square.
+#[derive(Debug, SimpleObject)]
+pub struct SquareImportResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// New orders pulled from Square
+    pub fetched: i32,
+    /// Orders that became sales
+    pub imported: i32,
+    /// Orders waiting for a mapping
+    pub unmapped: i32,
+    /// Orders with nothing stocked to sell
+    pub skipped: i32,
+}
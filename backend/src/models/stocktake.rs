@@ -0,0 +1,164 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+use crate::services::stocktake;
+
+/// A quick count of one item, waiting on (or past) back-office review.
+///
+/// Submitting a count never touches stock. Approving it adjusts current_stock
+/// by the variance found at count time, so sales made between the count and
+/// the approval aren't undone.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct StockCount {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub counted_quantity: BigDecimal,
+    /// Stock on record when the count was submitted
+    pub expected_quantity: BigDecimal,
+    /// counted_quantity - expected_quantity (generated column)
+    pub variance: BigDecimal,
+    pub unit: String,
+    pub counted_by: String,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub status: String, // 'pending', 'approved', 'rejected'
+    pub counted_at: DateTime<Utc>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_notes: Option<String>,
+    /// Stock change made when the count was approved
+    pub applied_adjustment: Option<BigDecimal>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl StockCount {
+    /// Likely causes of the variance, most likely first, each with the entry
+    /// that would correct it (pending counts only)
+    async fn suggestions(&self, ctx: &Context<'_>) -> Result<Vec<ReconciliationSuggestion>> {
+        if self.status != "pending" {
+            return Ok(vec![]);
+        }
+        let pool = ctx.data::<PgPool>()?;
+        let mut conn = pool.acquire().await?;
+
+        Ok(stocktake::suggestions(
+            &mut conn,
+            self.inventory_id,
+            &self.variance,
+            self.counted_at,
+        )
+        .await?)
+    }
+
+    /// Photo evidence submitted with the count
+    async fn photos(&self, ctx: &Context<'_>) -> Result<Vec<StockCountPhoto>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let photos = sqlx::query_as!(
+            StockCountPhoto,
+            r#"
+            SELECT
+                id, filename, content_type, octet_length(data) AS "size_bytes!",
+                created_at
+            FROM stock_count_photos
+            WHERE stock_count_id = $1
+            ORDER BY created_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(photos)
+    }
+}
+
+/// A photo attached to a quick count. The image itself is served from `url`.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct StockCountPhoto {
+    pub id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl StockCountPhoto {
+    /// Path of the image on this server (GET /stock-counts/photos/{id})
+    async fn url(&self) -> String {
+        format!("/stock-counts/photos/{}", self.id)
+    }
+}
+
+/// Input for submitting a quick count of one item.
+#[derive(InputObject)]
+pub struct SubmitQuickCountInput {
+    /// ID of the inventory item counted
+    pub inventory_id: Uuid,
+    /// Quantity physically on hand
+    pub counted_quantity: BigDecimal,
+    /// Who did the count
+    pub counted_by: String,
+    /// Optional location of the count (e.g. "Saturday market")
+    pub location: Option<String>,
+    /// Optional notes
+    pub notes: Option<String>,
+    /// When the count was taken (defaults to now)
+    pub counted_at: Option<DateTime<Utc>>,
+    /// Photos of the counted stock (at least one, sent as multipart uploads)
+    pub photos: Vec<Upload>,
+}
+
+/// Input for approving or rejecting a quick count.
+#[derive(Debug, InputObject)]
+pub struct ReviewStockCountInput {
+    /// ID of the pending count
+    pub id: Uuid,
+    /// Who reviewed the count
+    pub reviewed_by: String,
+    /// Optional review notes
+    pub notes: Option<String>,
+    /// Approving only: `key` of a suggestion to book as the cause of the variance
+    pub suggestion: Option<String>,
+}
+
+/// A possible cause of a count variance, found by scanning the item's recent
+/// logs and lots, with the entry that would correct it.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct ReconciliationSuggestion {
+    /// Pass as `suggestion` to `approveStockCount` to book the correction
+    pub key: String,
+    /// 'double_entry', 'missing_waste' or 'unlogged_sample'
+    pub cause: String,
+    /// Rough chance this explains the variance, 0 to 1
+    pub likelihood: BigDecimal,
+    pub explanation: String,
+    /// Movement type of the correcting entry
+    pub movement_type: String,
+    /// Stock change of the correcting entry
+    pub quantity: BigDecimal,
+    /// Log entry suspected of being entered twice
+    pub related_log_id: Option<Uuid>,
+    /// Expired lot suspected of being thrown out without a waste entry
+    pub lot_id: Option<Uuid>,
+}
+
+/// Result from submitting or reviewing a quick count.
+#[derive(Debug, SimpleObject)]
+pub struct StockCountResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub count: Option<StockCount>,
+}
@@ -0,0 +1,45 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A Stripe payment link sent to collect a sale's payment.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct PaymentLink {
+    pub id: Uuid,
+    pub sale_id: Uuid,
+    pub sale_number: String,
+    /// Stripe payment link ID (plink_...)
+    pub stripe_payment_link_id: String,
+    /// Page the customer pays on
+    pub url: String,
+    pub amount: BigDecimal,
+    pub currency: String,
+    /// 'active', 'paid', 'failed' (the payment didn't clear) or 'deactivated'
+    /// (replaced after the sale's total changed)
+    pub status: String,
+    /// Stripe checkout session that paid it
+    pub checkout_session_id: Option<String>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating a payment link for a sale.
+#[derive(Debug, InputObject)]
+pub struct CreatePaymentLinkInput {
+    pub sale_id: Uuid,
+}
+
+/// Result from creating a payment link.
+#[derive(Debug, SimpleObject)]
+pub struct PaymentLinkResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub payment_link: Option<PaymentLink>,
+}
@@ -0,0 +1,52 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One insert, update, or delete of a row, for incremental warehouse sync.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// Position of this change; pass the last one seen as `sinceCursor`
+    pub cursor: String,
+    /// Table the row belongs to (e.g. "sales", "production_batches")
+    pub entity: String,
+    pub entity_id: Uuid,
+    /// 'insert', 'update', or 'delete'
+    pub operation: String,
+    pub changed_at: DateTime<Utc>,
+    /// Full row after the change (None for deletes)
+    pub data: Option<serde_json::Value>,
+}
+
+/// A page of changes after a cursor.
+#[derive(Debug, SimpleObject)]
+pub struct ChangeSet {
+    pub changes: Vec<ChangeRecord>,
+    /// Cursor to resume from (unchanged if there were no new changes)
+    pub next_cursor: String,
+    /// Whether more changes are available right away
+    pub has_more: bool,
+}
+
+/// A business event (a batch completed, a sale made, ...), as sent to
+/// webhook subscribers.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct DomainEvent {
+    /// Position of this event; pass the last one seen as `afterCursor`
+    pub cursor: String,
+    /// 'batchCompleted', 'saleCreated', 'lowStock' or 'purchaseReceived'
+    pub event_type: String,
+    pub occurred_at: DateTime<Utc>,
+    /// The event's details, the same `data` webhooks receive
+    pub data: serde_json::Value,
+}
+
+/// A page of events after a cursor.
+#[derive(Debug, SimpleObject)]
+pub struct EventSet {
+    pub events: Vec<DomainEvent>,
+    /// Cursor to resume from (unchanged if there were no new events)
+    pub next_cursor: String,
+    /// Whether more events are available right away
+    pub has_more: bool,
+}
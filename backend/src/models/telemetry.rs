@@ -0,0 +1,133 @@
+use async_graphql::*;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// An error returned to a client, as recorded by the telemetry extension.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ApiError {
+    pub id: Uuid,
+    /// Trace ID the client got back in `extensions.traceId`
+    pub trace_id: Uuid,
+    pub operation_name: Option<String>,
+    /// Field path of the failing resolver (None for parse/validation errors)
+    pub path: Option<String>,
+    /// Error message, scrubbed of emails, long numbers and tokens
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// How one client used one operation over a period, from `api_usage`.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct ApiUsageStat {
+    /// 'user:<id>', 'api_key:<id>' or 'anonymous'
+    pub client_id: String,
+    /// Username or API key name
+    pub client: String,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    /// 'v1' or 'v2'
+    pub api_version: String,
+    /// 'query', 'mutation', 'subscription' or 'invalid' (didn't parse)
+    pub operation_type: String,
+    /// Operation name, else its top-level fields (e.g. "inventoryItems")
+    pub operation: String,
+    pub calls: i64,
+    /// Calls whose response had errors
+    pub errors: i64,
+    /// Calls that used a deprecated field
+    pub deprecated_calls: i64,
+    /// Share of calls with errors, 0-100
+    pub error_rate: f64,
+    pub average_ms: f64,
+    pub max_ms: i32,
+    /// Calls per minute over the period asked for
+    pub calls_per_minute: f64,
+    pub last_called_at: DateTime<Utc>,
+}
+
+/// One API version's timeline and how much it is still used.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct ApiVersionUsage {
+    /// 'v1' or 'v2'
+    pub version: String,
+    /// Where it is served (e.g. "/graphql/v2")
+    pub path: String,
+    pub deprecated_on: Option<NaiveDate>,
+    /// When it stops being served
+    pub sunset_on: Option<NaiveDate>,
+    pub calls: i64,
+    /// Distinct clients that called it
+    pub clients: i64,
+    /// Calls that used a deprecated field
+    pub deprecated_calls: i64,
+    pub last_called_at: Option<DateTime<Utc>>,
+    /// Fields this version no longer has ("Type.field")
+    pub removed_fields: Vec<String>,
+}
+
+/// A problem report from an app user.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct UserFeedback {
+    pub id: Uuid,
+    /// Trace ID of the request that went wrong, if the app had one
+    pub trace_id: Option<Uuid>,
+    pub message: String,
+    /// Screen or route the user was on
+    pub page: Option<String>,
+    pub reported_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl UserFeedback {
+    /// Errors recorded for the attached trace
+    async fn errors(&self, ctx: &Context<'_>) -> Result<Vec<ApiError>> {
+        let Some(trace_id) = self.trace_id else {
+            return Ok(vec![]);
+        };
+        let pool = ctx.data::<PgPool>()?;
+
+        let errors = sqlx::query_as!(
+            ApiError,
+            r#"
+            SELECT id, trace_id, operation_name, path, message, created_at
+            FROM api_errors
+            WHERE trace_id = $1
+            ORDER BY created_at
+            "#,
+            trace_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(errors)
+    }
+}
+
+/// Input for reporting a problem.
+#[derive(Debug, InputObject)]
+pub struct SubmitFeedbackInput {
+    /// What happened (e.g. "the Save button didn't do anything")
+    pub message: String,
+    /// `extensions.traceId` from the failing response, if any
+    pub trace_id: Option<Uuid>,
+    /// Screen or route the user was on
+    pub page: Option<String>,
+    /// Who is reporting it
+    pub reported_by: Option<String>,
+}
+
+/// Result from reporting a problem.
+#[derive(Debug, SimpleObject)]
+pub struct FeedbackResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub feedback: Option<UserFeedback>,
+}
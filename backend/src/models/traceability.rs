@@ -0,0 +1,139 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{
+    InventoryLot, MutationError, ProductionBatch, ProductionBatchIngredient, Sale,
+};
+
+/// A quantity drawn from a lot, together with where that lot came from.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct TracedLot {
+    pub lot_id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub lot_number: Option<String>,
+    pub source_type: String, // 'purchase', 'production', 'packaging'
+    pub supplier_id: Option<Uuid>,
+    pub supplier_name: Option<String>,
+    /// Batch that produced the lot (for produced or packaged lots)
+    pub production_batch_id: Option<Uuid>,
+    pub batch_number: Option<String>,
+    pub received_date: DateTime<Utc>,
+    pub expiry_date: Option<NaiveDate>,
+    /// Quantity of the lot that was used
+    pub quantity: BigDecimal,
+}
+
+/// A production batch with the ingredients and ingredient lots that went into it.
+#[derive(Debug, SimpleObject)]
+pub struct BatchTrace {
+    pub batch: ProductionBatch,
+    pub ingredients: Vec<ProductionBatchIngredient>,
+    /// Lots drawn for the ingredients (empty for stock that predates lot tracking)
+    pub ingredient_lots: Vec<TracedLot>,
+}
+
+/// A sale line that contained product from a traced lot.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct TracedSaleLine {
+    pub sale_id: Uuid,
+    pub sale_number: String,
+    pub sale_date: DateTime<Utc>,
+    pub customer_id: Option<Uuid>,
+    pub customer_name: Option<String>,
+    pub customer_email: Option<String>,
+    pub customer_phone: Option<String>,
+    pub sale_item_id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub lot_id: Uuid,
+    pub lot_number: Option<String>,
+    /// Batch whose output was sold
+    pub batch_number: Option<String>,
+    pub quantity: BigDecimal,
+}
+
+/// A downstream production batch that used a traced lot as an ingredient.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct TracedBatchUse {
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    pub status: String,
+    pub lot_id: Uuid,
+    pub lot_number: Option<String>,
+    pub quantity: BigDecimal,
+}
+
+/// Everything a batch's output ended up in: lots, sales, and downstream batches.
+#[derive(Debug, SimpleObject)]
+pub struct ForwardTrace {
+    pub batch: ProductionBatch,
+    /// Lots produced by the batch and any downstream batches (including packaged lots)
+    pub lots: Vec<InventoryLot>,
+    /// Sale lines containing product from those lots
+    pub sales: Vec<TracedSaleLine>,
+    /// Batches that used the output as an ingredient
+    pub downstream_batches: Vec<TracedBatchUse>,
+}
+
+/// Everything that went into a sale or batch: lots, batches, and ingredient lots.
+#[derive(Debug, SimpleObject)]
+pub struct BackwardTrace {
+    /// The traced sale (when tracing from a sale)
+    pub sale: Option<Sale>,
+    /// Lots drawn for the sale's lines (when tracing from a sale)
+    pub sale_lots: Vec<TracedLot>,
+    /// Batches in the chain, starting with the ones closest to the sale
+    pub batches: Vec<BatchTrace>,
+}
+
+/// What a customer sees on the public trace page after scanning a jar's QR
+/// code. Fields the owner has hidden are `None` and left out of the JSON.
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicBatchTrace {
+    pub product_name: String,
+    pub batch_number: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub production_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_by: Option<NaiveDate>,
+    /// Ingredient names only, no quantities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingredients: Option<Vec<String>>,
+    /// Farms and suppliers whose lots went into the batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppliers: Option<Vec<String>>,
+    /// Where those suppliers are (e.g. "Frederick, MD")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origins: Option<Vec<String>>,
+}
+
+/// Whether one field shows on the public trace page.
+#[derive(Debug, Clone, FromRow, SimpleObject)]
+pub struct TracePageField {
+    /// 'production_date', 'best_by', 'ingredients', 'suppliers' or 'origins'
+    pub field: String,
+    pub description: String,
+    pub visible: bool,
+}
+
+/// Input for showing or hiding a field on the public trace page.
+#[derive(Debug, InputObject)]
+pub struct SetTracePageFieldInput {
+    pub field: String,
+    pub visible: bool,
+}
+
+/// Result from showing or hiding a trace page field.
+#[derive(Debug, SimpleObject)]
+pub struct TracePageFieldResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub field: Option<TracePageField>,
+}
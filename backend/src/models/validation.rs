@@ -0,0 +1,17 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Something in the data the schema can't rule out but that shouldn't be
+/// there, found by a validation scan.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct DataIssue {
+    /// Which check found it (e.g. "negative_stock", "sale_without_items")
+    pub check: String,
+    /// Table the offending row is in
+    pub entity: String,
+    pub entity_id: Uuid,
+    pub description: String,
+    /// What to do about it
+    pub fix: String,
+}
@@ -0,0 +1,145 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A line of a supplier's price catalog, as of its latest import.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SupplierCatalogItem {
+    pub id: Uuid,
+    pub supplier_id: Uuid,
+    /// The vendor's item code
+    pub sku: String,
+    pub description: String,
+    /// Pack size as the vendor lists it (e.g. "6 x 32 oz")
+    pub pack_size: Option<String>,
+    /// Price per vendor pack
+    pub price: BigDecimal,
+    pub is_available: bool,
+    /// Our inventory item this is bought as (None if we don't buy it)
+    pub inventory_id: Option<Uuid>,
+    /// When it dropped out of the vendor's catalog
+    pub removed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One import of a supplier's catalog file.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct SupplierCatalogImport {
+    pub id: Uuid,
+    pub supplier_id: Uuid,
+    pub file_name: Option<String>,
+    /// Rows in the file, not counting the header
+    pub row_count: i32,
+    pub added: i32,
+    /// Items whose price or availability changed
+    pub changed: i32,
+    /// Items missing from the file
+    pub removed: i32,
+    /// Price rises at or above this (for items we buy) raised an alert
+    pub alert_threshold_percent: BigDecimal,
+    pub created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl SupplierCatalogImport {
+    /// What the import changed, largest price rises first
+    async fn changes(&self, ctx: &Context<'_>) -> Result<Vec<SupplierCatalogChange>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let changes = sqlx::query_as!(
+            SupplierCatalogChange,
+            r#"
+            SELECT
+                c.id, c.catalog_item_id, c.change_type, c.sku, c.description,
+                i.inventory_id, c.old_price, c.new_price, c.price_change_percent,
+                c.was_available, c.is_available
+            FROM supplier_catalog_changes c
+            JOIN supplier_catalog_items i ON i.id = c.catalog_item_id
+            WHERE c.import_id = $1
+            ORDER BY c.price_change_percent DESC NULLS LAST, c.sku
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(changes)
+    }
+}
+
+/// A catalog item that an import added, changed, or found missing.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct SupplierCatalogChange {
+    pub id: Uuid,
+    pub catalog_item_id: Uuid,
+    /// 'added', 'changed', or 'removed'
+    pub change_type: String,
+    pub sku: String,
+    pub description: String,
+    /// Our inventory item, if we buy it
+    pub inventory_id: Option<Uuid>,
+    pub old_price: Option<BigDecimal>,
+    pub new_price: Option<BigDecimal>,
+    /// Positive for increases
+    pub price_change_percent: Option<BigDecimal>,
+    pub was_available: Option<bool>,
+    pub is_available: Option<bool>,
+}
+
+/// Input for importing a supplier's CSV price catalog.
+#[derive(Debug, InputObject)]
+pub struct ImportSupplierCatalogInput {
+    pub supplier_id: Uuid,
+    /// The CSV file's contents, header row first
+    pub csv: String,
+    /// Name of the file, kept with the import
+    pub file_name: Option<String>,
+    /// Optional column mapping as {"field": "Column Header"} for the fields
+    /// sku, description, pack_size, price and available; unmapped fields use
+    /// the headers SKU, Description, Pack, Price and Available
+    pub mapping: Option<serde_json::Value>,
+    /// Alert on price rises of at least this percent for items we buy
+    /// (default 5)
+    pub alert_threshold_percent: Option<BigDecimal>,
+    /// Work out the changes without saving them
+    pub dry_run: Option<bool>,
+}
+
+/// Input for linking a catalog item to the inventory item it's bought as.
+#[derive(Debug, InputObject)]
+pub struct MapSupplierCatalogItemInput {
+    pub catalog_item_id: Uuid,
+    /// None to unlink
+    pub inventory_id: Option<Uuid>,
+}
+
+/// Result from importing a catalog.
+#[derive(Debug, SimpleObject)]
+pub struct SupplierCatalogImportResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    /// The saved import (None on a dry run)
+    pub import: Option<SupplierCatalogImport>,
+    pub changes: Vec<SupplierCatalogChange>,
+    /// Rows that were skipped, and why
+    pub problems: Vec<String>,
+}
+
+/// Result from linking a catalog item.
+#[derive(Debug, SimpleObject)]
+pub struct SupplierCatalogItemResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub item: Option<SupplierCatalogItem>,
+}
@@ -0,0 +1,127 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// An archived inbound webhook delivery.
+#[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    /// 'stripe', 'shopify', or 'square'
+    pub provider: String,
+    /// Provider's event or delivery ID
+    pub event_id: Option<String>,
+    /// Provider's event type or topic (e.g. "payment_intent.succeeded", "orders/create")
+    pub event_type: Option<String>,
+    /// 'received', 'duplicate' (replayed delivery), 'rejected', or 'failed'
+    /// (verified but couldn't be applied; the provider's retry is received anew)
+    pub status: String,
+    /// Why the delivery was rejected, or why applying it failed
+    pub error: Option<String>,
+    /// Request headers as received
+    pub headers: serde_json::Value,
+    /// Raw request body
+    pub payload: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// An outbound webhook: a URL told about domain events as they happen.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    /// Events posted to the URL: 'batchCompleted', 'saleCreated', 'lowStock',
+    /// 'purchaseReceived'
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One event queued for, or posted to, one webhook subscription.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub event_type: String,
+    /// The event's `data`, as posted
+    pub payload: serde_json::Value,
+    /// 'pending' (waiting for its next attempt), 'delivered' or 'failed'
+    /// (gave up after the last retry)
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    /// HTTP status of the last attempt, if the receiver answered
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Input for registering an outbound webhook.
+#[derive(Debug, InputObject)]
+pub struct CreateWebhookSubscriptionInput {
+    /// http(s) URL to post events to
+    pub url: String,
+    /// Events to send
+    pub event_types: Vec<String>,
+    /// Signing secret (at least 16 characters); one is generated when omitted
+    pub secret: Option<String>,
+    /// Optional note (e.g. "Zapier: new sales to the bookkeeping sheet")
+    pub description: Option<String>,
+}
+
+/// Input for updating an outbound webhook.
+#[derive(Debug, InputObject)]
+pub struct UpdateWebhookSubscriptionInput {
+    pub id: Uuid,
+    /// Optional new URL
+    pub url: Option<String>,
+    /// Optional new event list (replaces the old one)
+    pub event_types: Option<Vec<String>>,
+    /// Optional new note
+    pub description: Option<String>,
+    /// Pause (false) or resume (true) deliveries
+    pub is_active: Option<bool>,
+    /// Generate a new signing secret, returned once in the result
+    pub rotate_secret: Option<bool>,
+}
+
+/// Input for removing an outbound webhook.
+#[derive(Debug, InputObject)]
+pub struct DeleteWebhookSubscriptionInput {
+    pub id: Uuid,
+}
+
+/// Input for sending a delivery again.
+#[derive(Debug, InputObject)]
+pub struct RetryWebhookDeliveryInput {
+    pub id: Uuid,
+}
+
+/// Result from registering or updating an outbound webhook.
+#[derive(Debug, SimpleObject)]
+pub struct WebhookSubscriptionResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub subscription: Option<WebhookSubscription>,
+    /// The signing secret, only when it was just generated. It can't be
+    /// read back later.
+    pub secret: Option<String>,
+}
+
+/// Result from queueing a delivery to be sent again.
+#[derive(Debug, SimpleObject)]
+pub struct WebhookDeliveryResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub delivery: Option<WebhookDelivery>,
+}
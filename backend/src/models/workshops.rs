@@ -0,0 +1,263 @@
+use async_graphql::*;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::MutationError;
+
+/// A ticketed event such as a fermentation workshop.
+///
+/// Ticket sales are kept on the attendee list rather than as sales of
+/// inventory, so they show up as their own revenue line. The materials kit
+/// is what each attendee takes home; it is drawn from inventory when the
+/// attendee checks in.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+#[graphql(complex)]
+pub struct Workshop {
+    pub id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub duration_minutes: Option<i32>,
+    pub location: Option<String>,
+    /// Maximum number of attendees
+    pub capacity: i32,
+    pub ticket_price: BigDecimal,
+    pub status: String, // 'scheduled', 'completed', 'cancelled'
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl Workshop {
+    /// Materials each attendee receives
+    async fn kit(&self, ctx: &Context<'_>) -> Result<Vec<WorkshopKitItem>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let items = sqlx::query_as!(
+            WorkshopKitItem,
+            r#"
+            SELECT k.id, k.inventory_id, i.name AS item_name, k.quantity_per_attendee, i.unit
+            FROM workshop_kit_items k
+            JOIN inventory i ON i.id = k.inventory_id
+            WHERE k.workshop_id = $1
+            ORDER BY i.name
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Everyone who bought a ticket, including cancellations
+    async fn attendees(&self, ctx: &Context<'_>) -> Result<Vec<WorkshopAttendee>> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let attendees = sqlx::query_as!(
+            WorkshopAttendee,
+            r#"
+            SELECT
+                id, workshop_id, ticket_number, customer_id, attendee_name, email, phone,
+                price_paid, payment_method, status, purchased_at, checked_in_at,
+                cancelled_at, notes, created_at, updated_at
+            FROM workshop_attendees
+            WHERE workshop_id = $1
+            ORDER BY purchased_at
+            "#,
+            self.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(attendees)
+    }
+
+    /// Tickets sold and not cancelled
+    async fn tickets_sold(&self, ctx: &Context<'_>) -> Result<i64> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let sold = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM workshop_attendees
+            WHERE workshop_id = $1 AND status <> 'cancelled'
+            "#,
+            self.id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(sold)
+    }
+
+    /// Seats still available
+    async fn seats_remaining(&self, ctx: &Context<'_>) -> Result<i64> {
+        let sold = self.tickets_sold(ctx).await?;
+        Ok((i64::from(self.capacity) - sold).max(0))
+    }
+
+    /// Revenue from tickets that weren't cancelled
+    async fn ticket_revenue(&self, ctx: &Context<'_>) -> Result<BigDecimal> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let revenue = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(price_paid), 0) AS "revenue!"
+            FROM workshop_attendees
+            WHERE workshop_id = $1 AND status <> 'cancelled'
+            "#,
+            self.id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(revenue)
+    }
+}
+
+/// One item in a workshop's per-attendee materials kit.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct WorkshopKitItem {
+    pub id: Uuid,
+    pub inventory_id: Uuid,
+    pub item_name: String,
+    pub quantity_per_attendee: BigDecimal,
+    pub unit: String,
+}
+
+/// An attendee holding a workshop ticket.
+#[derive(Debug, Clone, FromRow, SimpleObject, Serialize, Deserialize)]
+pub struct WorkshopAttendee {
+    pub id: Uuid,
+    pub workshop_id: Uuid,
+    pub ticket_number: String, // Format: TKT-YYYYMMDD-NNN
+    pub customer_id: Option<Uuid>,
+    pub attendee_name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub price_paid: BigDecimal,
+    pub payment_method: Option<String>,
+    pub status: String, // 'registered', 'checked_in', 'cancelled'
+    pub purchased_at: DateTime<Utc>,
+    /// When the attendee arrived and received their kit
+    pub checked_in_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One item of a workshop materials kit.
+#[derive(Debug, InputObject)]
+pub struct WorkshopKitItemInput {
+    /// ID of the inventory item (jar, cabbage, salt packet, etc.)
+    pub inventory_id: Uuid,
+    /// Quantity each attendee receives
+    pub quantity_per_attendee: BigDecimal,
+}
+
+/// Input for scheduling a workshop.
+#[derive(Debug, InputObject)]
+pub struct CreateWorkshopInput {
+    /// Title (e.g. "Sauerkraut 101")
+    pub title: String,
+    /// Optional description
+    pub description: Option<String>,
+    /// When the workshop starts
+    pub starts_at: DateTime<Utc>,
+    /// Optional length in minutes
+    pub duration_minutes: Option<i32>,
+    /// Optional location
+    pub location: Option<String>,
+    /// Maximum number of attendees
+    pub capacity: i32,
+    /// Price of one ticket
+    pub ticket_price: BigDecimal,
+    /// Optional notes
+    pub notes: Option<String>,
+    /// Optional materials kit each attendee receives
+    pub kit: Option<Vec<WorkshopKitItemInput>>,
+}
+
+/// Input for updating a workshop. Setting status to 'cancelled' cancels all
+/// outstanding tickets.
+#[derive(Debug, InputObject)]
+pub struct UpdateWorkshopInput {
+    /// ID of the workshop to update
+    pub id: Uuid,
+    /// Optional new title
+    pub title: Option<String>,
+    /// Optional new description
+    pub description: Option<String>,
+    /// Optional new start time
+    pub starts_at: Option<DateTime<Utc>>,
+    /// Optional new length in minutes
+    pub duration_minutes: Option<i32>,
+    /// Optional new location
+    pub location: Option<String>,
+    /// Optional new capacity (cannot drop below tickets sold)
+    pub capacity: Option<i32>,
+    /// Optional new ticket price (applies to tickets sold from now on)
+    pub ticket_price: Option<BigDecimal>,
+    /// Optional new status ('scheduled', 'completed', 'cancelled')
+    pub status: Option<String>,
+    /// Optional new notes
+    pub notes: Option<String>,
+    /// Optional replacement materials kit
+    pub kit: Option<Vec<WorkshopKitItemInput>>,
+}
+
+/// Input for selling a workshop ticket to one attendee.
+#[derive(Debug, InputObject)]
+pub struct SellWorkshopTicketInput {
+    /// ID of the workshop
+    pub workshop_id: Uuid,
+    /// Name of the attendee
+    pub attendee_name: String,
+    /// Optional email
+    pub email: Option<String>,
+    /// Optional phone
+    pub phone: Option<String>,
+    /// Optional customer record of the buyer
+    pub customer_id: Option<Uuid>,
+    /// Price paid (defaults to the workshop's ticket price)
+    pub price_paid: Option<BigDecimal>,
+    /// Optional payment method ('cash', 'card', etc.)
+    pub payment_method: Option<String>,
+    /// Optional notes (e.g. dietary needs)
+    pub notes: Option<String>,
+}
+
+/// Input for checking in or cancelling an attendee.
+#[derive(Debug, InputObject)]
+pub struct WorkshopAttendeeInput {
+    /// ID of the attendee
+    pub attendee_id: Uuid,
+    /// Optional notes
+    pub notes: Option<String>,
+}
+
+/// Result from creating or updating a workshop.
+#[derive(Debug, SimpleObject)]
+pub struct WorkshopResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub workshop: Option<Workshop>,
+}
+
+/// Result from selling, checking in, or cancelling a ticket.
+#[derive(Debug, SimpleObject)]
+pub struct WorkshopAttendeeResult {
+    pub success: bool,
+    pub message: String,
+    /// Typed reason when `success` is false
+    pub error: Option<MutationError>,
+    pub attendee: Option<WorkshopAttendee>,
+}
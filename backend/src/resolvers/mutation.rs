@@ -1,23 +1,134 @@
 use async_graphql::*;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use bigdecimal::BigDecimal;
-use chrono::Utc;
-use sqlx::PgPool;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Connection, PgConnection, PgPool};
+use uuid::Uuid;
 
+use crate::jobs::reading_alerts::ReadingQueue;
+use crate::jobs::scheduler::Scheduler;
 use crate::models::{
-    CompleteProductionBatchInput, CreateCustomerInput, CreateInventoryItemInput,
-    CreateProductionBatchInput, CreatePurchaseInput, CreateRecipeTemplateInput, CreateSaleInput,
-    CreateSupplierInput, Customer, CustomerResult, DeleteInventoryItemInput,
-    DeleteRecipeTemplateInput, DeleteResult, FailProductionBatchInput, InventoryItem,
-    InventoryItemResult, ProductionBatchResult, PurchaseResult, RecipeTemplate,
-    RecipeTemplateResult, SaleResult, Supplier, SupplierResult, UpdateCustomerInput,
-    UpdateInventoryItemInput, UpdateRecipeTemplateInput, UpdateSupplierInput,
+    AcknowledgeBatchAlertInput, ActionLink, ActionLinkResult, AddStarterRecipesInput,
+    AdvanceBatchStageInput, AdvanceProductDevelopmentInput, AllocatePlannedBatchInput,
+    Announcement, AnnouncementResult, ApiKey, ApiKeyResult, AssembleKitsInput, AssembleKitsResult,
+    AssignBatchVesselInput, AssignSalesToMarketSessionInput, AuthResult, BatchAlert,
+    BatchAlertResult, BatchMoveTaskResult, BatchReadingResult, BatchStageResult, BatchTask,
+    BatchTaskResult, BookPickupInput, CalendarEvent, CalendarEventResult, CancelOrderInput,
+    CancelPurchaseOrderInput, CloseSupplierContractInput, Complaint, ComplaintDetails,
+    ComplaintLotReview, ComplaintLotReviewResult, ComplaintResult, CompleteBatchMoveInput,
+    CompleteProductionBatchInput, CompleteTaskInput, ConfirmOrderInput, CreateActionLinkInput,
+    CreateAnnouncementInput, CreateApiKeyInput, CreateCalendarEventInput, CreateComplaintInput,
+    CreateCustomerInput, CreateDashboardInput, CreateInventoryItemInput, CreateLocationInput,
+    CreateMarketInput, CreateOrderInput, CreateOverheadPoolInput, CreatePackSizeInput,
+    CreatePaymentLinkInput, CreatePickupWindowInput, CreatePlannedBatchInput,
+    CreateProductDevelopmentInput, CreateProductionBatchInput, CreatePromoCodeInput,
+    CreatePurchaseInput, CreatePurchaseOrderInput, CreateRecipeTaskInput,
+    CreateRecipeTemplateInput, CreateSaleInput, CreateStorageTransitionRuleInput,
+    CreateSupplierContractInput, CreateSupplierInput, CreateUserInput, CreateVesselInput,
+    CreateWebhookSubscriptionInput, CreateWorkshopInput, CsvImportInput, CsvImportResult, Customer,
+    CustomerPriceResult, CustomerResult, DEFAULT_LOCALE, DashboardResult, DecidePurchaseOrderInput,
+    DefineKitInput, DefineRecipeStagesInput, DeleteAnnouncementInput, DeleteCalendarEventInput,
+    DeleteDashboardInput, DeleteInventoryItemInput, DeletePackSizeInput,
+    DeleteProductTranslationInput, DeleteRecipeCostWatchInput, DeleteRecipeTaskInput,
+    DeleteRecipeTemplateInput, DeleteResult, DeleteSecretInput, DeleteShopifyProductInput,
+    DeleteSquareItemMappingInput, DeleteStorageTransitionRuleInput, DeleteVesselInput,
+    DeleteWebhookSubscriptionInput, DiscontinueProductInput, DiscontinueProductResult,
+    DiscountInput, FailProductionBatchInput, FeedbackResult, FulfillOrderInput,
+    ImportFromLibraryInput, ImportSupplierCatalogInput, InventoryItem, InventoryItemResult,
+    InventoryLot, InvoiceOrderInput, KitAssembly, KitComponent, KitDefinitionResult, LabelRun,
+    LabelRunInput, LabelRunResult, LegacyImportInput, LegacyImportResult, LibraryImportResult,
+    ListPriceResult, Location, LocationResult, LoginInput, MapShopifyProductInput,
+    MapSquareItemInput, MapSupplierCatalogItemInput, Market, MarketResult, MarketSessionResult,
+    MutationError, NotificationSettingsResult, OnboardingResult, OrderResult, OverheadPool,
+    OverheadPoolResult, PackSize, PackSizeResult, PackageProductInput, PackagingMaterialInput,
+    PackagingResult, PaymentLinkResult, PaymentResult, PickupBooking, PickupBookingResult,
+    PickupWindow, PickupWindowResult, PlanAllocation, PlanAllocationResult, PlannedBatch,
+    PlannedBatchResult, ProductDevelopmentResult, ProductTranslation, ProductTranslationResult,
+    ProductionBatchResult, PromoCodeResult, PurchaseApprovalLimit, PurchaseApprovalLimitResult,
+    PurchaseOrder, PurchaseOrderResult, PurchaseResult, QualityCheck, QualityCheckResult,
+    RecipeCostWatchResult, RecipeStage, RecipeStagesResult, RecipeTaskResult, RecipeTaskTemplate,
+    RecipeTemplate, RecipeTemplateResult, RecordBatchReadingInput, RecordCleaningInput,
+    RecordPaymentInput, RecordQualityCheckInput, RefreshTokenInput, ReinstateProductInput,
+    Rejection, ReleasePlanAllocationInput, RemoveSupplierInput, ResolveComplaintInput,
+    ResolveSaleStockReviewInput, ResolveShopifySyncConflictInput, RetryWebhookDeliveryInput,
+    ReviewComplaintLotInput, ReviewStockCountInput, RevokeApiKeyInput, RotateSecretInput,
+    RunJobNowInput, Sale, SaleChannelPolicy, SaleChannelPolicyResult, SaleResult, SaleReviewResult,
+    SanitationLog, SanitationLogResult, ScheduleMarketSessionInput, ScheduledJobResult,
+    SecretResult, SellWorkshopTicketInput, SetCustomerPriceInput, SetListPriceInput,
+    SetPackSizeLabelInput, SetPurchaseApprovalLimitInput, SetRecipeCostWatchInput,
+    SetSaleChannelPolicyInput, SetSecretInput, SetShopifyProductSyncInput, SetTracePageFieldInput,
+    SetUpCategoriesInput, SetUpLocationsInput, SetUpUnitsInput, ShopifyProductResult,
+    ShopifySyncConflictResult, ShopifySyncResult, SplitBatchOutputInput, SplitBatchOutputResult,
+    SquareImportResult, SquareItemMappingResult, StockCount, StockCountResult,
+    StorageTransitionRule, StorageTransitionRuleResult, SubmitFeedbackInput, SubmitQuickCountInput,
+    Supplier, SupplierCatalogImport, SupplierCatalogImportResult, SupplierCatalogItem,
+    SupplierCatalogItemResult, SupplierContract, SupplierContractResult, SupplierResult,
+    TracePageField, TracePageFieldResult, TransferStockInput, TransferStockResult,
+    UpdateAnnouncementInput, UpdateCalendarEventInput, UpdateCustomerInput, UpdateDashboardInput,
+    UpdateInventoryItemInput, UpdateLocationInput, UpdateMarketInput, UpdateMarketSessionInput,
+    UpdateNotificationSettingsInput, UpdateOrderInput, UpdateOverheadPoolInput,
+    UpdatePickupBookingInput, UpdatePickupWindowInput, UpdatePlannedBatchInput,
+    UpdateProductDevelopmentInput, UpdatePromoCodeInput, UpdateRecipeTemplateInput,
+    UpdateSupplierInput, UpdateUserInput, UpdateVesselInput, UpdateWebhookSubscriptionInput,
+    UpdateWorkshopInput, UpsertProductTranslationInput, User, UserFeedback, UserResult, Vessel,
+    VesselResult, WebhookDeliveryResult, WebhookSubscription, WebhookSubscriptionResult, Workshop,
+    WorkshopAttendee, WorkshopAttendeeInput, WorkshopAttendeeResult, WorkshopResult,
 };
+use crate::services::action_links;
+use crate::services::auth::{self, AuthConfig, Identity, RequireRole};
+use crate::services::batches;
+use crate::services::calendar;
+use crate::services::codes;
+use crate::services::cost_watch;
+use crate::services::csv_import;
+use crate::services::dashboards;
+use crate::services::development;
+use crate::services::discontinuation;
+use crate::services::discounts;
+use crate::services::events;
+use crate::services::http::HttpClient;
+use crate::services::idempotency;
+use crate::services::invoices;
+use crate::services::labels;
+use crate::services::legacy_import;
+use crate::services::library;
+use crate::services::locations;
+use crate::services::lots;
+use crate::services::markets;
+use crate::services::notifications::{Alert, Notifier, SMS_ALERT_KINDS, normalize_phone};
+use crate::services::numbering;
+use crate::services::onboarding;
+use crate::services::orders;
+use crate::services::overhead;
+use crate::services::packaging;
+use crate::services::pickups;
+use crate::services::planning;
+use crate::services::pricing;
+use crate::services::purchasing;
+use crate::services::qr::QrCode;
+use crate::services::readings::{self, NewReading};
+use crate::services::recipes;
+use crate::services::sales;
+use crate::services::secrets::{SecretError, SecretStore};
+use crate::services::shopify;
+use crate::services::square;
+use crate::services::stages::{self, Advance};
+use crate::services::stocktake;
+use crate::services::stripe;
+use crate::services::tasks;
+use crate::services::traceability;
+use crate::services::vendor_catalog;
+use crate::services::versions;
+use crate::services::vessels::{self, Claim};
+use crate::services::webhooks;
+use crate::services::workshops;
 
 pub struct MutationRoot;
 
 #[Object]
 impl MutationRoot {
     /// Create a new purchase and update inventory
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
     async fn create_purchase(
         &self,
         ctx: &Context<'_>,
@@ -27,31 +138,243 @@ impl MutationRoot {
         let mut tx = pool.begin().await?;
 
         let purchase_date = input.purchase_date.unwrap_or_else(Utc::now);
+        let now = Utc::now();
         let mut updated_items = Vec::new();
+        let mut lots = Vec::new();
+
+        fn failed(rejection: impl Into<Rejection>) -> PurchaseResult {
+            let rejection = rejection.into();
+            PurchaseResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                updated_items: vec![],
+                batch_id: None,
+                batch_number: None,
+                lots: vec![],
+            }
+        }
+
+        // A retry of a purchase that already went through gets its original result
+        let idempotency_key = match &input.idempotency_key {
+            Some(key) => match idempotency::Key::new(key, ctx.data_opt::<Identity>(), &input) {
+                Ok(key) => Some(key),
+                Err(message) => return Ok(failed(message)),
+            },
+            None => None,
+        };
+        if let Some(key) = &idempotency_key {
+            match idempotency::claim(&mut tx, "createPurchase", key).await? {
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => {}
+                Err(rejection) => return Ok(failed(rejection)),
+            }
+        }
+
+        // Check cross-dock quantities before receiving anything
+        let zero = BigDecimal::from(0);
+        let cross_docked = input
+            .items
+            .iter()
+            .filter(|item| item.cross_dock_quantity.is_some())
+            .count();
+        if input.items.iter().any(|item| {
+            item.cross_dock_quantity
+                .as_ref()
+                .is_some_and(|quantity| *quantity <= zero || *quantity > item.quantity)
+        }) {
+            return Ok(failed(
+                "Cross-dock quantities must be greater than 0 and no more than the quantity received"
+                    .to_string(),
+            ));
+        }
+
+        // Receipts against a purchase order must match its supplier
+        let purchase_order = match input.purchase_order_id {
+            None => None,
+            Some(purchase_order_id) => {
+                let order = sqlx::query!(
+                    "SELECT po_number, supplier_id, status FROM purchase_orders WHERE id = $1 FOR UPDATE",
+                    purchase_order_id
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                match order {
+                    None => {
+                        return Ok(failed(Rejection::new(
+                            "Purchase order not found".to_string(),
+                            MutationError::not_found("Purchase order"),
+                        )));
+                    }
+                    Some(order) if order.status != "open" => {
+                        return Ok(failed(format!(
+                            "Purchase order {} is already {}",
+                            order.po_number, order.status
+                        )));
+                    }
+                    Some(order) if order.supplier_id != input.supplier_id => {
+                        return Ok(failed(format!(
+                            "Purchase order {} is with a different supplier",
+                            order.po_number
+                        )));
+                    }
+                    Some(order) => Some((purchase_order_id, order.po_number)),
+                }
+            }
+        };
+
+        // Received goods are put away at one location
+        if let Some(location_id) = input.location_id {
+            let active = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM locations WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                location_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !active {
+                return Ok(failed(Rejection::new(
+                    "Location not found or is inactive".to_string(),
+                    MutationError::not_found("Location"),
+                )));
+            }
+        }
+
+        // Find or start the batch that cross-docked lines go into
+        let mut warnings = Vec::new();
+        let batch = match (&input.cross_dock, cross_docked) {
+            (None, 0) => None,
+            (None, _) => {
+                return Ok(failed(
+                    "Cross-dock quantities need a batch to go into".to_string(),
+                ));
+            }
+            (Some(_), 0) => {
+                return Ok(failed(
+                    "No purchase lines have a cross-dock quantity".to_string(),
+                ));
+            }
+            (Some(cross_dock), _) => match (cross_dock.batch_id, &cross_dock.new_batch) {
+                (Some(batch_id), None) => {
+                    let batch = sqlx::query!(
+                        "SELECT batch_number, status FROM production_batches WHERE id = $1 FOR UPDATE",
+                        batch_id
+                    )
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+                    match batch {
+                        None => {
+                            return Ok(failed(Rejection::new(
+                                "Production batch not found".to_string(),
+                                MutationError::not_found("Production batch"),
+                            )));
+                        }
+                        Some(batch) if batch.status != "in_progress" => {
+                            return Ok(failed(format!(
+                                "Cannot cross-dock into batch {} with status '{}'",
+                                batch.batch_number, batch.status
+                            )));
+                        }
+                        Some(batch) => Some((batch_id, batch.batch_number)),
+                    }
+                }
+                (None, Some(new_batch)) => match batches::start(&mut tx, new_batch, now).await? {
+                    Ok(started) => {
+                        warnings = started.warnings;
+                        Some((started.batch_id, started.batch_number))
+                    }
+                    Err(rejection) => return Ok(failed(rejection)),
+                },
+                _ => {
+                    return Ok(failed(
+                        "Give either a batch ID or a new batch to cross-dock into".to_string(),
+                    ));
+                }
+            },
+        };
 
         // Process each item in the purchase
         for item_input in input.items {
             // 1. Add entry to inventory_logs
-            sqlx::query(
+            sqlx::query!(
                 r#"
                 INSERT INTO inventory_logs (
                     inventory_id, movement_type, quantity, unit_cost,
                     reason, batch_number, expiry_date, created_at
                 ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
                 "#,
+                item_input.inventory_id,
+                "purchase",
+                item_input.quantity,
+                item_input.unit_cost,
+                input.notes.as_deref().unwrap_or("Purchase"),
+                item_input.batch_number,
+                item_input.expiry_date,
+                purchase_date
             )
-            .bind(item_input.inventory_id)
-            .bind("purchase")
-            .bind(&item_input.quantity)
-            .bind(&item_input.unit_cost)
-            .bind(input.notes.as_deref().unwrap_or("Purchase"))
-            .bind(&item_input.batch_number)
-            .bind(item_input.expiry_date)
-            .bind(purchase_date)
             .execute(&mut *tx)
             .await?;
 
-            // 2. Update inventory stock and cost
+            // 2. Record the received lot with its code (expiry falls back to shelf_life_days)
+            let lot = sqlx::query_as!(
+                InventoryLot,
+                r#"
+                INSERT INTO inventory_lots (
+                    inventory_id, lot_number, code, source_type, supplier_id, location_id,
+                    initial_quantity, remaining_quantity, unit_cost,
+                    received_date, expiry_date
+                ) VALUES ($1, $2, $9, 'purchase', $3, $4, $5, $5, $6, $7, $8)
+                RETURNING
+                    id, inventory_id, lot_number, code, source_type, supplier_id,
+                    production_batch_id, parent_lot_id, location_id, initial_quantity, remaining_quantity,
+                    unit_cost, received_date, expiry_date, created_at, updated_at
+                "#,
+                item_input.inventory_id,
+                item_input.batch_number,
+                input.supplier_id,
+                input.location_id,
+                item_input.quantity,
+                item_input.unit_cost,
+                purchase_date,
+                item_input.expiry_date,
+                codes::new_lot_code()
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            let lot_id = lot.id;
+            lots.push(lot);
+
+            if let Some((purchase_order_id, _)) = &purchase_order {
+                purchasing::receive(
+                    &mut tx,
+                    *purchase_order_id,
+                    item_input.inventory_id,
+                    &item_input.quantity,
+                )
+                .await?;
+            }
+
+            // Cross-docked goods go from the received lot straight into the batch
+            if let Some(quantity) = &item_input.cross_dock_quantity
+                && let Some((batch_id, batch_number)) = &batch
+            {
+                let draw = lots::draw(&mut tx, lot_id, quantity).await?;
+                batches::add_ingredient(
+                    &mut tx,
+                    *batch_id,
+                    batch_number,
+                    item_input.inventory_id,
+                    quantity,
+                    &[draw],
+                    Some("Cross-docked from purchase"),
+                    now,
+                )
+                .await?;
+            }
+
+            // 3. Update inventory stock and cost
             let updated_item = sqlx::query_as!(
                 InventoryItem,
                 r#"
@@ -74,7 +397,9 @@ impl MutationRoot {
                     default_supplier_id,
                     shelf_life_days,
                     storage_requirements,
+                    product_code,
                     is_active,
+                    version,
                     created_at,
                     updated_at
                 "#,
@@ -89,1414 +414,12814 @@ impl MutationRoot {
             updated_items.push(updated_item);
         }
 
-        // Commit the transaction
-        tx.commit().await?;
+        let order_closed = match &purchase_order {
+            Some((purchase_order_id, _)) => {
+                purchasing::close_if_received(&mut tx, *purchase_order_id).await?
+            }
+            None => false,
+        };
+
+        let mut message = format!(
+            "Successfully processed purchase of {} items",
+            updated_items.len()
+        );
+        if let Some((_, po_number)) = &purchase_order {
+            message.push_str(&format!(
+                " against {}{}",
+                po_number,
+                if order_closed {
+                    " (now fully received)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if let Some((_, batch_number)) = &batch {
+            message.push_str(&format!(
+                ", {} cross-docked into batch {}",
+                cross_docked, batch_number
+            ));
+        }
+        for warning in warnings {
+            message.push_str(&format!(". Warning: {}", warning));
+        }
 
-        Ok(PurchaseResult {
+        let result = PurchaseResult {
             success: true,
-            message: format!(
-                "Successfully processed purchase of {} items",
-                updated_items.len()
-            ),
+            message,
+            error: None,
             updated_items,
-        })
+            batch_id: batch.as_ref().map(|(batch_id, _)| *batch_id),
+            batch_number: batch.map(|(_, batch_number)| batch_number),
+            lots,
+        };
+
+        events::publish(
+            &mut *tx,
+            "purchaseReceived",
+            serde_json::json!({
+                "supplier_id": input.supplier_id,
+                "purchase_order_id": purchase_order.as_ref().map(|(id, _)| id),
+                "po_number": purchase_order.as_ref().map(|(_, po_number)| po_number),
+                "purchase_order_closed": order_closed,
+                "purchase_date": purchase_date,
+                "lots": result.lots,
+            }),
+        )
+        .await?;
+
+        if let Some(key) = &idempotency_key {
+            idempotency::store(&mut tx, "createPurchase", key, &result).await?;
+        }
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        // New purchase costs may push recipes past their cost limits
+        cost_watch::recheck(pool, ctx.data::<Notifier>()?).await;
+
+        Ok(result)
     }
 
-    /// Create a new production batch that consumes ingredients and produces finished goods
-    async fn create_production_batch(
+    /// Place a purchase order with a supplier. Orders over the requester's
+    /// approval limit wait in `pending_approval` until the owner decides.
+    /// Nothing changes in stock until a purchase is recorded against the order.
+    #[graphql(guard = "RequireRole(auth::STAFF)")]
+    async fn create_purchase_order(
         &self,
         ctx: &Context<'_>,
-        input: CreateProductionBatchInput,
-    ) -> Result<ProductionBatchResult> {
+        mut input: CreatePurchaseOrderInput,
+    ) -> Result<PurchaseOrderResult> {
         let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
         let mut tx = pool.begin().await?;
 
-        // Validate batch size is positive
-        if input.batch_size <= BigDecimal::from(0) {
-            return Ok(ProductionBatchResult {
-                success: false,
-                message: "Batch size must be greater than 0".to_string(),
-                batch_id: None,
-                batch_number: None,
-            });
-        }
-
-        // Validate at least one ingredient
-        if input.ingredients.is_empty() {
-            return Ok(ProductionBatchResult {
+        if input.items.is_empty() {
+            return Ok(PurchaseOrderResult {
                 success: false,
-                message: "At least one ingredient is required".to_string(),
-                batch_id: None,
-                batch_number: None,
+                message: "At least one item is required".to_string(),
+                error: Some(MutationError::invalid()),
+                purchase_order: None,
             });
         }
 
-        // 1. Validate product exists
-        let product = sqlx::query!(
-            "SELECT name FROM inventory WHERE id = $1 AND is_active = true",
-            input.product_inventory_id
+        let supplier_exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM suppliers WHERE id = $1 AND is_active) AS \"exists!\"",
+            input.supplier_id
         )
-        .fetch_optional(&mut *tx)
+        .fetch_one(&mut *tx)
         .await?;
 
-        if product.is_none() {
-            return Ok(ProductionBatchResult {
+        if !supplier_exists {
+            return Ok(PurchaseOrderResult {
                 success: false,
-                message: "Product not found or is inactive".to_string(),
-                batch_id: None,
-                batch_number: None,
+                message: "Supplier not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Supplier")),
+                purchase_order: None,
             });
         }
 
-        // 2. Validate all ingredients exist and have sufficient stock
-        for ingredient in &input.ingredients {
-            if ingredient.quantity_used <= BigDecimal::from(0) {
-                return Ok(ProductionBatchResult {
+        for (index, item) in input.items.iter().enumerate() {
+            if item.quantity <= BigDecimal::from(0) {
+                return Ok(PurchaseOrderResult {
                     success: false,
-                    message: "All ingredient quantities must be greater than 0".to_string(),
-                    batch_id: None,
-                    batch_number: None,
+                    message: "All quantities must be greater than 0".to_string(),
+                    error: Some(MutationError::invalid()),
+                    purchase_order: None,
+                });
+            }
+
+            if item
+                .unit_cost
+                .as_ref()
+                .is_some_and(|cost| *cost < BigDecimal::from(0))
+            {
+                return Ok(PurchaseOrderResult {
+                    success: false,
+                    message: "Unit costs cannot be negative".to_string(),
+                    error: Some(MutationError::invalid()),
+                    purchase_order: None,
+                });
+            }
+
+            if input.items[..index]
+                .iter()
+                .any(|i| i.inventory_id == item.inventory_id)
+            {
+                return Ok(PurchaseOrderResult {
+                    success: false,
+                    message: format!("Item {} is listed more than once", item.inventory_id),
+                    error: Some(MutationError::invalid()),
+                    purchase_order: None,
                 });
             }
 
-            let inv = sqlx::query!(
-                "SELECT name, current_stock FROM inventory WHERE id = $1 AND is_active = true",
-                ingredient.inventory_id
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                item.inventory_id
             )
-            .fetch_optional(&mut *tx)
+            .fetch_one(&mut *tx)
             .await?;
 
-            match inv {
-                None => {
-                    return Ok(ProductionBatchResult {
-                        success: false,
-                        message: format!(
-                            "Ingredient with ID {} not found or is inactive",
-                            ingredient.inventory_id
-                        ),
-                        batch_id: None,
-                        batch_number: None,
-                    });
-                }
-                Some(inv_item) => {
-                    if inv_item.current_stock < ingredient.quantity_used {
-                        return Ok(ProductionBatchResult {
-                            success: false,
-                            message: format!(
-                                "Insufficient stock for {}: need {}, have {}",
-                                inv_item.name, ingredient.quantity_used, inv_item.current_stock
-                            ),
-                            batch_id: None,
-                            batch_number: None,
-                        });
-                    }
-                }
+            if !exists {
+                return Ok(PurchaseOrderResult {
+                    success: false,
+                    message: format!(
+                        "Item with ID {} not found or is inactive",
+                        item.inventory_id
+                    ),
+                    error: Some(MutationError::not_found("Item")),
+                    purchase_order: None,
+                });
             }
         }
 
-        // 3. Generate batch number (format: BATCH-YYYYMMDD-NNN)
-        let today = Utc::now();
-        let date_prefix = today.format("%Y%m%d").to_string();
-        let batch_prefix = format!("BATCH-{}", date_prefix);
+        let now = Utc::now();
 
-        // Find the next sequence number for today
-        let last_batch = sqlx::query!(
-            "SELECT batch_number FROM production_batches WHERE batch_number LIKE $1 ORDER BY batch_number DESC LIMIT 1",
-            format!("{}-%", batch_prefix)
-        )
-        .fetch_optional(&mut *tx)
-        .await?;
+        if let Some(contract_id) = input.contract_id
+            && let Err(rejection) = purchasing::draw_down_contract(
+                &mut tx,
+                contract_id,
+                input.supplier_id,
+                now.date_naive(),
+                &mut input.items,
+            )
+            .await?
+        {
+            return Ok(PurchaseOrderResult::rejected(rejection));
+        }
 
-        let sequence = match last_batch {
-            Some(batch) => {
-                // Extract sequence number from BATCH-YYYYMMDD-NNN
-                let parts: Vec<&str> = batch.batch_number.split('-').collect();
-                if parts.len() == 3 {
-                    parts[2].parse::<i32>().unwrap_or(0) + 1
-                } else {
-                    1
-                }
-            }
-            None => 1,
-        };
+        let total_amount: BigDecimal = input
+            .items
+            .iter()
+            .filter_map(|item| item.unit_cost.as_ref().map(|cost| &item.quantity * cost))
+            .sum::<BigDecimal>()
+            .round(2);
+        let priced = input.items.iter().all(|item| item.unit_cost.is_some());
+
+        let pending = !identity.is_owner()
+            && purchasing::needs_approval(&mut tx, &identity.role, &total_amount, priced).await?;
+        let status = if pending { "pending_approval" } else { "open" };
 
-        let batch_number = format!("{}-{:03}", batch_prefix, sequence);
+        let po_number = purchasing::next_number(&mut tx, now).await?;
 
-        // 4. Create production_batch record
-        let batch_id = sqlx::query_scalar!(
+        let purchase_order = sqlx::query_as!(
+            PurchaseOrder,
             r#"
-            INSERT INTO production_batches (
-                batch_number, product_inventory_id, recipe_template_id, batch_size, unit,
-                start_date, estimated_completion_date, production_date, status,
-                storage_location, notes
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-            RETURNING id
+            INSERT INTO purchase_orders
+                (po_number, supplier_id, status, order_date, expected_date, notes, total_amount,
+                 requested_by, requested_by_user_id, contract_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING
+                id, po_number, supplier_id, status, order_date, expected_date, notes,
+                total_amount, requested_by, requested_by_user_id, decided_by, decided_at,
+                decision_note, contract_id, created_at, updated_at
             "#,
-            batch_number,
-            input.product_inventory_id,
-            input.recipe_template_id,
-            input.batch_size,
-            input.unit,
-            today,
-            input.estimated_completion_date,
-            today,         // Legacy field
-            "in_progress", // Start as in-progress, complete manually later
-            input.storage_location,
-            input.notes
+            po_number,
+            input.supplier_id,
+            status,
+            now,
+            input.expected_date,
+            input.notes,
+            total_amount,
+            identity.username,
+            identity.user_id,
+            input.contract_id
         )
         .fetch_one(&mut *tx)
         .await?;
 
-        // 5. Process each ingredient: consume stock and log
-        for ingredient in &input.ingredients {
-            // Get ingredient unit
-            let inv = sqlx::query!(
-                "SELECT unit FROM inventory WHERE id = $1",
-                ingredient.inventory_id
-            )
-            .fetch_one(&mut *tx)
-            .await?;
-
-            // Create production_batch_ingredients record
+        for item in &input.items {
             sqlx::query!(
-                "INSERT INTO production_batch_ingredients (batch_id, ingredient_inventory_id, quantity_used, unit) VALUES ($1, $2, $3, $4)",
-                batch_id,
-                ingredient.inventory_id,
-                ingredient.quantity_used,
-                inv.unit
+                r#"
+                INSERT INTO purchase_order_items (purchase_order_id, inventory_id, quantity, unit_cost)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                purchase_order.id,
+                item.inventory_id,
+                item.quantity,
+                item.unit_cost
             )
             .execute(&mut *tx)
             .await?;
+        }
 
-            // Decrease ingredient stock
-            sqlx::query!(
-                "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
-                ingredient.quantity_used,
-                today,
-                ingredient.inventory_id
-            )
-            .execute(&mut *tx)
-            .await?;
+        tx.commit().await?;
+
+        if pending {
+            ctx.data::<Notifier>()?
+                .send(&Alert {
+                    kind: "purchase_order_approval".to_string(),
+                    message: format!(
+                        "Purchase order {} for ${} from {} is waiting for approval",
+                        purchase_order.po_number, purchase_order.total_amount, identity.username
+                    ),
+                    data: serde_json::json!({
+                        "purchase_order_id": purchase_order.id,
+                        "po_number": purchase_order.po_number,
+                        "total_amount": purchase_order.total_amount,
+                        "requested_by": identity.username,
+                    }),
+                })
+                .await;
+        }
+
+        Ok(PurchaseOrderResult {
+            success: true,
+            message: if pending {
+                format!(
+                    "Purchase order {} for {} items is waiting for owner approval",
+                    purchase_order.po_number,
+                    input.items.len()
+                )
+            } else {
+                format!(
+                    "Placed purchase order {} for {} items",
+                    purchase_order.po_number,
+                    input.items.len()
+                )
+            },
+            error: None,
+            purchase_order: Some(purchase_order),
+        })
+    }
+
+    /// Approve a purchase order waiting for approval. It becomes open and
+    /// counts as on order; the requester is notified.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn approve_purchase_order(
+        &self,
+        ctx: &Context<'_>,
+        input: DecidePurchaseOrderInput,
+    ) -> Result<PurchaseOrderResult> {
+        decide_purchase_order(ctx, input, true).await
+    }
+
+    /// Reject a purchase order waiting for approval, with an optional reason
+    /// for the requester, who is notified.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn reject_purchase_order(
+        &self,
+        ctx: &Context<'_>,
+        input: DecidePurchaseOrderInput,
+    ) -> Result<PurchaseOrderResult> {
+        decide_purchase_order(ctx, input, false).await
+    }
+
+    /// Set the largest purchase order a role can place without approval, or
+    /// clear it so every order from the role needs approval.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_purchase_approval_limit(
+        &self,
+        ctx: &Context<'_>,
+        input: SetPurchaseApprovalLimitInput,
+    ) -> Result<PurchaseApprovalLimitResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if !auth::STAFF.contains(&input.role.as_str()) {
+            return Ok(PurchaseApprovalLimitResult::failed(&format!(
+                "Approval limits apply to {}; the owner never needs approval",
+                auth::STAFF.join(" or ")
+            )));
+        }
 
-            // Log ingredient consumption
+        let Some(max_amount) = input.max_amount else {
             sqlx::query!(
-                r#"
-                INSERT INTO inventory_logs (
-                    inventory_id, movement_type, quantity, reason, batch_number, created_at
-                ) VALUES ($1, $2, $3, $4, $5, $6)
-                "#,
-                ingredient.inventory_id,
-                "production_use",
-                -ingredient.quantity_used.clone(), // Negative because it's consumption
-                format!("Used in production batch {}", batch_number),
-                batch_number,
-                today
+                "DELETE FROM purchase_approval_limits WHERE role = $1",
+                input.role
             )
-            .execute(&mut *tx)
+            .execute(pool)
             .await?;
+
+            return Ok(PurchaseApprovalLimitResult {
+                success: true,
+                message: format!("Every {} purchase order now needs approval", input.role),
+                error: None,
+                limit: None,
+            });
+        };
+
+        if max_amount < BigDecimal::from(0) {
+            return Ok(PurchaseApprovalLimitResult::failed(
+                "Limit cannot be negative",
+            ));
         }
 
-        // 6. Commit transaction (product will be added when batch is completed)
-        tx.commit().await?;
+        let limit = sqlx::query_as!(
+            PurchaseApprovalLimit,
+            r#"
+            INSERT INTO purchase_approval_limits (role, max_amount)
+            VALUES ($1, $2)
+            ON CONFLICT (role) DO UPDATE SET max_amount = EXCLUDED.max_amount, updated_at = NOW()
+            RETURNING id, role, max_amount, created_at, updated_at
+            "#,
+            input.role,
+            max_amount.round(2)
+        )
+        .fetch_one(pool)
+        .await?;
 
-        Ok(ProductionBatchResult {
+        Ok(PurchaseApprovalLimitResult {
             success: true,
             message: format!(
-                "Successfully created production batch {} with {} ingredients",
-                batch_number,
-                input.ingredients.len()
+                "{} purchase orders over ${} now need approval",
+                limit.role, limit.max_amount
             ),
-            batch_id: Some(batch_id),
-            batch_number: Some(batch_number),
+            error: None,
+            limit: Some(limit),
         })
     }
 
-    /// Complete a production batch and add finished product to inventory
-    async fn complete_production_batch(
+    /// Record a contract with a supplier: volumes committed at agreed prices
+    /// between two dates. Purchase orders draw it down with `contractId`.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_supplier_contract(
         &self,
         ctx: &Context<'_>,
-        input: CompleteProductionBatchInput,
-    ) -> Result<ProductionBatchResult> {
+        input: CreateSupplierContractInput,
+    ) -> Result<SupplierContractResult> {
         let pool = ctx.data::<PgPool>()?;
         let mut tx = pool.begin().await?;
 
-        // 1. Get batch details
-        let batch = sqlx::query!(
-            r#"
-            SELECT batch_number, product_inventory_id, batch_size, status, start_date
-            FROM production_batches
-            WHERE id = $1
-            "#,
-            input.batch_id
+        let contract_number = input.contract_number.trim();
+        if contract_number.is_empty() {
+            return Ok(SupplierContractResult::failed(
+                "Contract number is required",
+            ));
+        }
+        if input.end_date < input.start_date {
+            return Ok(SupplierContractResult::failed(
+                "End date cannot be before start date",
+            ));
+        }
+        if input.items.is_empty() {
+            return Ok(SupplierContractResult::failed(
+                "At least one item is required",
+            ));
+        }
+
+        let supplier_exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM suppliers WHERE id = $1 AND is_active) AS \"exists!\"",
+            input.supplier_id
         )
-        .fetch_optional(&mut *tx)
+        .fetch_one(&mut *tx)
         .await?;
+        if !supplier_exists {
+            return Ok(SupplierContractResult::not_found("Supplier"));
+        }
 
-        let batch = match batch {
-            Some(b) => b,
-            None => {
-                return Ok(ProductionBatchResult {
-                    success: false,
-                    message: "Production batch not found".to_string(),
-                    batch_id: None,
-                    batch_number: None,
-                });
-            }
-        };
-
-        if batch.status != "in_progress" {
-            return Ok(ProductionBatchResult {
-                success: false,
-                message: format!("Batch is already {}", batch.status),
-                batch_id: None,
-                batch_number: Some(batch.batch_number.clone()),
+        let taken = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM supplier_contracts WHERE contract_number = $1) AS \"exists!\"",
+            contract_number
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if taken {
+            return Ok(SupplierContractResult {
+                error: Some(MutationError::name_conflict(
+                    "Supplier contract",
+                    contract_number,
+                )),
+                ..SupplierContractResult::failed(&format!(
+                    "Contract {} already exists",
+                    contract_number
+                ))
             });
         }
 
-        // 2. Calculate yield percentage and production time
-        let yield_pct = if batch.batch_size > BigDecimal::from(0) {
-            (&input.actual_yield / &batch.batch_size) * BigDecimal::from(100)
-        } else {
-            BigDecimal::from(100)
-        };
+        for (index, item) in input.items.iter().enumerate() {
+            if item.committed_quantity <= BigDecimal::from(0) {
+                return Ok(SupplierContractResult::failed(
+                    "Committed quantities must be greater than 0",
+                ));
+            }
+            if item.agreed_price < BigDecimal::from(0) {
+                return Ok(SupplierContractResult::failed(
+                    "Agreed prices cannot be negative",
+                ));
+            }
+            if input.items[..index]
+                .iter()
+                .any(|i| i.inventory_id == item.inventory_id)
+            {
+                return Ok(SupplierContractResult::failed(&format!(
+                    "Item {} is listed more than once",
+                    item.inventory_id
+                )));
+            }
 
-        let now = Utc::now();
-        let duration_hours = BigDecimal::from((now - batch.start_date).num_hours().max(0));
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                item.inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if !exists {
+                return Ok(SupplierContractResult::not_found("Item"));
+            }
+        }
 
-        // 3. Update batch status
-        sqlx::query!(
+        let contract = sqlx::query_as!(
+            SupplierContract,
             r#"
-            UPDATE production_batches
-            SET status = 'completed',
-                completion_date = $1,
-                actual_yield = $2,
-                yield_percentage = $3,
-                production_time_hours = $4,
-                quality_notes = $5,
-                updated_at = $1
-            WHERE id = $6
+            INSERT INTO supplier_contracts (contract_number, supplier_id, start_date, end_date, notes)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id, contract_number, supplier_id, start_date, end_date, status, notes,
+                created_at, updated_at
             "#,
-            now,
-            input.actual_yield,
-            yield_pct,
-            duration_hours,
-            input.quality_notes,
-            input.batch_id
-        )
-        .execute(&mut *tx)
-        .await?;
-
-        // 4. Add finished product to inventory
-        sqlx::query!(
-            "UPDATE inventory SET current_stock = current_stock + $1, updated_at = $2 WHERE id = $3",
-            input.actual_yield,
-            now,
-            batch.product_inventory_id
+            contract_number,
+            input.supplier_id,
+            input.start_date,
+            input.end_date,
+            input.notes
         )
-        .execute(&mut *tx)
+        .fetch_one(&mut *tx)
         .await?;
 
-        // 5. Log production output
-        sqlx::query!(
-            r#"
-            INSERT INTO inventory_logs (
-                inventory_id, movement_type, quantity, reason, batch_number, created_at
-            ) VALUES ($1, $2, $3, $4, $5, $6)
-            "#,
-            batch.product_inventory_id,
-            "production_output",
-            input.actual_yield,
-            format!("Produced in batch {}", batch.batch_number),
-            batch.batch_number,
-            now
-        )
-        .execute(&mut *tx)
-        .await?;
+        for item in &input.items {
+            sqlx::query!(
+                r#"
+                INSERT INTO supplier_contract_items (contract_id, inventory_id, committed_quantity, agreed_price)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                contract.id,
+                item.inventory_id,
+                item.committed_quantity,
+                item.agreed_price
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
 
         tx.commit().await?;
 
-        Ok(ProductionBatchResult {
+        Ok(SupplierContractResult {
             success: true,
             message: format!(
-                "Successfully completed production batch {}. Yield: {:.1}%",
-                batch.batch_number, yield_pct
+                "Recorded contract {} for {} items, {} to {}",
+                contract.contract_number,
+                input.items.len(),
+                contract.start_date,
+                contract.end_date
             ),
-            batch_id: Some(input.batch_id),
-            batch_number: Some(batch.batch_number),
+            error: None,
+            contract: Some(contract),
         })
     }
 
-    /// Mark a production batch as failed
-    async fn fail_production_batch(
+    /// Close a supplier contract so no more orders draw it down. Orders
+    /// already placed against it are unaffected.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn close_supplier_contract(
         &self,
         ctx: &Context<'_>,
-        input: FailProductionBatchInput,
-    ) -> Result<ProductionBatchResult> {
+        input: CloseSupplierContractInput,
+    ) -> Result<SupplierContractResult> {
         let pool = ctx.data::<PgPool>()?;
-        let mut tx = pool.begin().await?;
 
-        // 1. Get batch details
-        let batch = sqlx::query!(
+        let reason = input
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty());
+
+        let contract = sqlx::query_as!(
+            SupplierContract,
             r#"
-            SELECT batch_number, status
-            FROM production_batches
-            WHERE id = $1
+            UPDATE supplier_contracts
+            SET status = 'closed',
+                notes = CASE WHEN $2::text IS NULL THEN notes
+                    ELSE concat_ws(E'\n', notes, 'Closed: ' || $2) END,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'active'
+            RETURNING
+                id, contract_number, supplier_id, start_date, end_date, status, notes,
+                created_at, updated_at
             "#,
-            input.batch_id
+            input.contract_id,
+            reason
         )
-        .fetch_optional(&mut *tx)
+        .fetch_optional(pool)
         .await?;
 
-        let batch = match batch {
-            Some(b) => b,
+        match contract {
+            Some(contract) => Ok(SupplierContractResult {
+                success: true,
+                message: format!("Closed contract {}", contract.contract_number),
+                error: None,
+                contract: Some(contract),
+            }),
             None => {
-                return Ok(ProductionBatchResult {
-                    success: false,
-                    message: "Production batch not found".to_string(),
-                    batch_id: None,
-                    batch_number: None,
-                });
+                let exists = sqlx::query_scalar!(
+                    "SELECT EXISTS(SELECT 1 FROM supplier_contracts WHERE id = $1) AS \"exists!\"",
+                    input.contract_id
+                )
+                .fetch_one(pool)
+                .await?;
+
+                if exists {
+                    Ok(SupplierContractResult::failed("Contract is already closed"))
+                } else {
+                    Ok(SupplierContractResult::not_found("Supplier contract"))
+                }
             }
+        }
+    }
+
+    /// Cancel an open or pending purchase order. Anything already received
+    /// stays in stock.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn cancel_purchase_order(
+        &self,
+        ctx: &Context<'_>,
+        input: CancelPurchaseOrderInput,
+    ) -> Result<PurchaseOrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT po_number, status FROM purchase_orders WHERE id = $1 FOR UPDATE",
+            input.purchase_order_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(PurchaseOrderResult {
+                success: false,
+                message: "Purchase order not found".to_string(),
+                error: Some(MutationError::not_found("Purchase order")),
+                purchase_order: None,
+            });
         };
 
-        if batch.status != "in_progress" {
-            return Ok(ProductionBatchResult {
+        if existing.status != "open" && existing.status != "pending_approval" {
+            return Ok(PurchaseOrderResult {
                 success: false,
-                message: format!("Batch is already {}", batch.status),
-                batch_id: None,
-                batch_number: Some(batch.batch_number.clone()),
+                message: format!(
+                    "Purchase order {} is already {}",
+                    existing.po_number, existing.status
+                ),
+                error: Some(MutationError::invalid()),
+                purchase_order: None,
             });
         }
 
-        // 2. Update batch status
-        let now = Utc::now();
-        sqlx::query!(
+        let purchase_order = sqlx::query_as!(
+            PurchaseOrder,
             r#"
-            UPDATE production_batches
-            SET status = 'failed',
-                completion_date = $1,
-                quality_notes = $2,
-                updated_at = $1
-            WHERE id = $3
+            UPDATE purchase_orders
+            SET
+                status = 'cancelled',
+                notes = CASE
+                    WHEN $2::text IS NULL THEN notes
+                    ELSE concat_ws(E'\n', notes, 'Cancelled: ' || $2)
+                END,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, po_number, supplier_id, status, order_date, expected_date, notes,
+                total_amount, requested_by, requested_by_user_id, decided_by, decided_at,
+                decision_note, contract_id, created_at, updated_at
             "#,
-            now,
-            input.reason,
-            input.batch_id
+            input.purchase_order_id,
+            input.reason
         )
-        .execute(&mut *tx)
+        .fetch_one(&mut *tx)
         .await?;
 
         tx.commit().await?;
 
-        Ok(ProductionBatchResult {
+        Ok(PurchaseOrderResult {
             success: true,
-            message: format!("Production batch {} marked as failed", batch.batch_number),
-            batch_id: Some(input.batch_id),
-            batch_number: Some(batch.batch_number),
+            message: format!("Cancelled purchase order {}", purchase_order.po_number),
+            error: None,
+            purchase_order: Some(purchase_order),
         })
     }
 
-    /// Create a new inventory item
-    async fn create_inventory_item(
+    /// Import a supplier's CSV price catalog. Prices, availability and
+    /// descriptions are brought up to date, the changes since the last import
+    /// are recorded, and an alert goes out for price rises at or above the
+    /// threshold on items we buy.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn import_supplier_catalog(
         &self,
         ctx: &Context<'_>,
-        input: CreateInventoryItemInput,
-    ) -> Result<InventoryItemResult> {
+        input: ImportSupplierCatalogInput,
+    ) -> Result<SupplierCatalogImportResult> {
         let pool = ctx.data::<PgPool>()?;
 
-        // Check if name already exists
-        let existing = sqlx::query!(
-            "SELECT id FROM inventory WHERE name = $1 AND is_active = true",
-            input.name
+        let supplier = sqlx::query_scalar!(
+            "SELECT name FROM suppliers WHERE id = $1",
+            input.supplier_id
         )
         .fetch_optional(pool)
         .await?;
 
-        if existing.is_some() {
-            return Ok(InventoryItemResult {
-                success: false,
-                message: format!("An item with the name '{}' already exists", input.name),
-                item: None,
-            });
+        let Some(supplier) = supplier else {
+            return Ok(SupplierCatalogImportResult::not_found("Supplier"));
+        };
+
+        let threshold = input
+            .alert_threshold_percent
+            .unwrap_or_else(|| BigDecimal::from(vendor_catalog::DEFAULT_ALERT_THRESHOLD_PERCENT));
+        if threshold < BigDecimal::from(0) {
+            return Ok(SupplierCatalogImportResult::failed(
+                "Alert threshold can't be negative",
+            ));
         }
 
-        // Validate supplier_id if provided
-        if let Some(supplier_id) = input.default_supplier_id {
-            let supplier_exists =
-                sqlx::query!("SELECT id FROM suppliers WHERE id = $1", supplier_id)
-                    .fetch_optional(pool)
-                    .await?;
+        let records = match vendor_catalog::parse_csv(&input.csv) {
+            Ok(records) => records,
+            Err(message) => return Ok(SupplierCatalogImportResult::failed(&message)),
+        };
 
-            if supplier_exists.is_none() {
-                return Ok(InventoryItemResult {
-                    success: false,
-                    message: "Supplier not found".to_string(),
-                    item: None,
-                });
-            }
+        let Some((header, records)) = records.split_first() else {
+            return Ok(SupplierCatalogImportResult::failed("The file is empty"));
+        };
+
+        let columns = match vendor_catalog::columns(header, input.mapping.as_ref()) {
+            Ok(columns) => columns,
+            Err(message) => return Ok(SupplierCatalogImportResult::failed(&message)),
+        };
+
+        let (lines, problems, skus) = vendor_catalog::rows(records, &columns);
+        if lines.is_empty() {
+            return Ok(SupplierCatalogImportResult {
+                problems,
+                ..SupplierCatalogImportResult::failed("No usable rows in the file")
+            });
         }
 
+        let dry_run = input.dry_run.unwrap_or(false);
         let now = Utc::now();
-        let current_stock = input.current_stock.unwrap_or(BigDecimal::from(0));
-        let reserved_stock = input.reserved_stock.unwrap_or(BigDecimal::from(0));
-        let reorder_point = input.reorder_point.unwrap_or(BigDecimal::from(0));
+        let mut tx = pool.begin().await?;
 
-        // Create the inventory item
-        let item = sqlx::query_as!(
-            InventoryItem,
+        let import_id = sqlx::query_scalar!(
             r#"
-            INSERT INTO inventory (
-                name, category, unit, current_stock, reserved_stock, reorder_point,
-                cost_per_unit, default_supplier_id, shelf_life_days, storage_requirements,
-                is_active, created_at, updated_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, true, $11, $11)
-            RETURNING
-                id,
-                name,
-                category,
-                unit,
-                current_stock as "current_stock!: BigDecimal",
-                reserved_stock as "reserved_stock!: BigDecimal",
-                available_stock as "available_stock!: BigDecimal",
-                reorder_point as "reorder_point!: BigDecimal",
-                cost_per_unit as "cost_per_unit?: BigDecimal",
-                default_supplier_id,
-                shelf_life_days,
-                storage_requirements,
-                is_active,
-                created_at,
-                updated_at
+            INSERT INTO supplier_catalog_imports
+                (supplier_id, file_name, row_count, alert_threshold_percent, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
             "#,
-            input.name,
-            input.category,
-            input.unit,
-            current_stock,
-            reserved_stock,
-            reorder_point,
-            input.cost_per_unit,
-            input.default_supplier_id,
-            input.shelf_life_days,
-            input.storage_requirements,
+            input.supplier_id,
+            input.file_name,
+            records.len() as i32,
+            threshold,
             now
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        Ok(InventoryItemResult {
+        let changes =
+            vendor_catalog::apply(&mut tx, input.supplier_id, import_id, &lines, &skus, now)
+                .await?;
+
+        let count = |change_type: &str| {
+            changes
+                .iter()
+                .filter(|c| c.change_type == change_type)
+                .count() as i32
+        };
+        let (added, changed, removed) = (count("added"), count("changed"), count("removed"));
+
+        let import = sqlx::query_as!(
+            SupplierCatalogImport,
+            r#"
+            UPDATE supplier_catalog_imports
+            SET added = $2, changed = $3, removed = $4
+            WHERE id = $1
+            RETURNING
+                id, supplier_id, file_name, row_count, added, changed, removed,
+                alert_threshold_percent, created_at
+            "#,
+            import_id,
+            added,
+            changed,
+            removed
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let increases = vendor_catalog::increases(&changes, &threshold);
+        let summary = format!(
+            "{} added, {} changed, {} removed, {} price rise(s) of {}% or more on items we buy",
+            added,
+            changed,
+            removed,
+            increases.len(),
+            threshold.normalized()
+        );
+
+        if dry_run {
+            tx.rollback().await?;
+
+            return Ok(SupplierCatalogImportResult {
+                success: true,
+                message: format!("Dry run: {}", summary),
+                error: None,
+                import: None,
+                changes,
+                problems,
+            });
+        }
+
+        tx.commit().await?;
+
+        if !increases.is_empty() {
+            let items = increases
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{} {} (+{}%)",
+                        c.sku,
+                        c.description,
+                        c.price_change_percent
+                            .as_ref()
+                            .map(|p| p.normalized().to_string())
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            ctx.data::<Notifier>()?
+                .send(&Alert {
+                    kind: "vendor_price_increase".to_string(),
+                    message: format!("{} raised prices: {}", supplier, items),
+                    data: serde_json::json!({
+                        "supplier_id": input.supplier_id,
+                        "supplier": supplier,
+                        "import_id": import.id,
+                        "threshold_percent": threshold,
+                        "changes": increases,
+                    }),
+                })
+                .await;
+        }
+
+        Ok(SupplierCatalogImportResult {
             success: true,
-            message: format!("Successfully created '{}'", item.name),
-            item: Some(item),
+            message: format!("Imported {} catalog: {}", supplier, summary),
+            error: None,
+            import: Some(import),
+            changes,
+            problems,
         })
     }
 
-    /// Update an existing inventory item
-    async fn update_inventory_item(
+    /// Link a supplier catalog item to the inventory item it's bought as, so
+    /// its price rises raise alerts (or unlink it)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn map_supplier_catalog_item(
         &self,
         ctx: &Context<'_>,
-        input: UpdateInventoryItemInput,
-    ) -> Result<InventoryItemResult> {
+        input: MapSupplierCatalogItemInput,
+    ) -> Result<SupplierCatalogItemResult> {
         let pool = ctx.data::<PgPool>()?;
-        let mut tx = pool.begin().await?;
-
-        // Check if item exists
-        let existing = sqlx::query!("SELECT name FROM inventory WHERE id = $1", input.id)
-            .fetch_optional(&mut *tx)
-            .await?;
-
-        if existing.is_none() {
-            return Ok(InventoryItemResult {
-                success: false,
-                message: "Inventory item not found".to_string(),
-                item: None,
-            });
-        }
 
-        // Check if new name conflicts with existing items (if name is being changed)
-        if let Some(ref new_name) = input.name {
-            let name_conflict = sqlx::query!(
-                "SELECT id FROM inventory WHERE name = $1 AND id != $2 AND is_active = true",
-                new_name,
-                input.id
+        if let Some(inventory_id) = input.inventory_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true)",
+                inventory_id
             )
-            .fetch_optional(&mut *tx)
-            .await?;
+            .fetch_one(pool)
+            .await?
+            .unwrap_or(false);
 
-            if name_conflict.is_some() {
-                return Ok(InventoryItemResult {
-                    success: false,
-                    message: format!("An item with the name '{}' already exists", new_name),
-                    item: None,
-                });
+            if !exists {
+                return Ok(SupplierCatalogItemResult::not_found("Inventory item"));
             }
         }
 
-        // Validate supplier_id if provided
-        if let Some(supplier_id) = input.default_supplier_id {
-            let supplier_exists =
-                sqlx::query!("SELECT id FROM suppliers WHERE id = $1", supplier_id)
-                    .fetch_optional(&mut *tx)
-                    .await?;
-
-            if supplier_exists.is_none() {
-                return Ok(InventoryItemResult {
-                    success: false,
-                    message: "Supplier not found".to_string(),
-                    item: None,
-                });
-            }
-        }
-
-        let now = Utc::now();
-
-        // Build update query dynamically based on provided fields
-        // For simplicity, we'll use COALESCE to keep existing values if new ones aren't provided
         let item = sqlx::query_as!(
-            InventoryItem,
+            SupplierCatalogItem,
             r#"
-            UPDATE inventory
-            SET
-                name = COALESCE($2, name),
-                category = COALESCE($3, category),
-                unit = COALESCE($4, unit),
-                current_stock = COALESCE($5, current_stock),
-                reserved_stock = COALESCE($6, reserved_stock),
-                reorder_point = COALESCE($7, reorder_point),
-                cost_per_unit = COALESCE($8, cost_per_unit),
-                default_supplier_id = COALESCE($9, default_supplier_id),
-                shelf_life_days = COALESCE($10, shelf_life_days),
-                storage_requirements = COALESCE($11, storage_requirements),
-                is_active = COALESCE($12, is_active),
-                updated_at = $13
+            UPDATE supplier_catalog_items
+            SET inventory_id = $2, updated_at = NOW()
             WHERE id = $1
             RETURNING
-                id,
-                name,
-                category,
-                unit,
-                current_stock as "current_stock!: BigDecimal",
-                reserved_stock as "reserved_stock!: BigDecimal",
-                available_stock as "available_stock!: BigDecimal",
-                reorder_point as "reorder_point!: BigDecimal",
-                cost_per_unit as "cost_per_unit?: BigDecimal",
-                default_supplier_id,
-                shelf_life_days,
-                storage_requirements,
-                is_active,
-                created_at,
-                updated_at
+                id, supplier_id, sku, description, pack_size, price, is_available,
+                inventory_id, removed_at, created_at, updated_at
             "#,
-            input.id,
-            input.name,
-            input.category,
-            input.unit,
-            input.current_stock,
-            input.reserved_stock,
-            input.reorder_point,
-            input.cost_per_unit,
-            input.default_supplier_id,
-            input.shelf_life_days,
-            input.storage_requirements,
-            input.is_active,
-            now
+            input.catalog_item_id,
+            input.inventory_id
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(pool)
         .await?;
 
-        tx.commit().await?;
+        let Some(item) = item else {
+            return Ok(SupplierCatalogItemResult::not_found("Catalog item"));
+        };
 
-        Ok(InventoryItemResult {
+        Ok(SupplierCatalogItemResult {
             success: true,
-            message: format!("Successfully updated '{}'", item.name),
+            message: match input.inventory_id {
+                Some(_) => format!("Linked {} to inventory", item.sku),
+                None => format!("Unlinked {} from inventory", item.sku),
+            },
+            error: None,
             item: Some(item),
         })
     }
 
-    /// Delete an inventory item (hard delete)
-    /// Use this for accidental additions or items that have gone completely bad
-    async fn delete_inventory_item(
+    /// Create a new production batch that consumes ingredients and produces finished goods
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_production_batch(
         &self,
         ctx: &Context<'_>,
-        input: DeleteInventoryItemInput,
-    ) -> Result<DeleteResult> {
+        input: CreateProductionBatchInput,
+    ) -> Result<ProductionBatchResult> {
         let pool = ctx.data::<PgPool>()?;
-
-        // Begin transaction
         let mut tx = pool.begin().await?;
 
-        // Check if item exists and has no dependencies
-        let item = sqlx::query!(
-            "SELECT name FROM inventory WHERE id = $1",
-            input.inventory_id
-        )
-        .fetch_optional(&mut *tx)
-        .await?;
+        // Validate at least one ingredient
+        if input.ingredients.is_empty() {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: "At least one ingredient is required".to_string(),
+                error: Some(MutationError::invalid()),
+                batch_id: None,
+                batch_number: None,
+            });
+        }
 
-        let item = match item {
-            Some(i) => i,
-            None => {
-                return Ok(DeleteResult {
+        let started = match batches::start(&mut tx, &input, Utc::now()).await? {
+            Ok(started) => started,
+            Err(rejection) => {
+                return Ok(ProductionBatchResult {
                     success: false,
-                    message: "Inventory item not found".to_string(),
+                    message: rejection.message,
+                    error: Some(rejection.error),
+                    batch_id: None,
+                    batch_number: None,
                 });
             }
         };
 
-        // Check for active production batches using this item
-        let active_batches = sqlx::query!(
+        // Commit transaction (product will be added when batch is completed)
+        tx.commit().await?;
+
+        let mut message = format!(
+            "Successfully created production batch {} with {} ingredients",
+            started.batch_number,
+            input.ingredients.len()
+        );
+        for warning in started.warnings {
+            message.push_str(&format!(". Warning: {}", warning));
+        }
+
+        Ok(ProductionBatchResult {
+            success: true,
+            message,
+            error: None,
+            batch_id: Some(started.batch_id),
+            batch_number: Some(started.batch_number),
+        })
+    }
+
+    /// Add a batch of a recipe to the production plan for a target date
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_planned_batch(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePlannedBatchInput,
+    ) -> Result<PlannedBatchResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let recipe = sqlx::query!(
             r#"
-            SELECT COUNT(*) as count
-            FROM production_batch_ingredients
-            JOIN production_batches ON production_batch_ingredients.batch_id = production_batches.id
-            WHERE production_batch_ingredients.ingredient_inventory_id = $1
-                AND production_batches.status = 'in_progress'
+            SELECT r.template_name, r.default_batch_size, i.name AS "product_name?",
+                i.discontinued_at AS "discontinued_at?"
+            FROM recipe_templates r
+            LEFT JOIN inventory i ON i.id = r.product_inventory_id
+            WHERE r.id = $1 AND r.is_active = true
             "#,
-            input.inventory_id
+            input.recipe_template_id
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(pool)
         .await?;
 
-        if active_batches.count.unwrap_or(0) > 0 {
-            return Ok(DeleteResult {
+        let Some(recipe) = recipe else {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Recipe template not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                planned_batch: None,
+            });
+        };
+
+        if recipe.discontinued_at.is_some() {
+            return Ok(PlannedBatchResult {
                 success: false,
                 message: format!(
-                    "Cannot delete '{}': item is used in active production batches",
-                    item.name
+                    "{} has been discontinued",
+                    recipe.product_name.unwrap_or_default()
                 ),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
             });
         }
 
-        // Delete the inventory item (cascading will handle related records)
-        sqlx::query!("DELETE FROM inventory WHERE id = $1", input.inventory_id)
-            .execute(&mut *tx)
-            .await?;
+        let Some(batch_size) = input.batch_size.or(recipe.default_batch_size) else {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Batch size is required for recipes without a default batch size"
+                    .to_string(),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
+            });
+        };
 
-        // Commit transaction
-        tx.commit().await?;
+        if batch_size <= BigDecimal::from(0) {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Batch size must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
+            });
+        }
 
-        Ok(DeleteResult {
+        let planned_batch = sqlx::query_as!(
+            PlannedBatch,
+            r#"
+            WITH inserted AS (
+                INSERT INTO planned_batches (recipe_template_id, batch_size, target_date, notes)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+            )
+            SELECT
+                p.id AS "id!", p.recipe_template_id AS "recipe_template_id!",
+                r.template_name AS recipe_name, r.product_inventory_id,
+                p.batch_size AS "batch_size!", r.default_unit AS unit,
+                p.target_date AS "target_date!", p.status AS "status!",
+                p.production_batch_id, p.notes,
+                p.created_at AS "created_at!", p.updated_at AS "updated_at!"
+            FROM inserted p
+            JOIN recipe_templates r ON r.id = p.recipe_template_id
+            "#,
+            input.recipe_template_id,
+            batch_size,
+            input.target_date,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(PlannedBatchResult {
             success: true,
-            message: format!("Successfully deleted '{}'", item.name),
+            message: format!(
+                "Planned {} {} of {} for {}",
+                planned_batch.batch_size,
+                planned_batch.unit.as_deref().unwrap_or("units"),
+                recipe.template_name,
+                planned_batch.target_date
+            ),
+            error: None,
+            planned_batch: Some(planned_batch),
         })
     }
 
-    /// Create a new supplier
-    async fn create_supplier(
+    /// Reschedule, resize, or cancel a planned batch that hasn't started
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn update_planned_batch(
         &self,
         ctx: &Context<'_>,
-        input: CreateSupplierInput,
-    ) -> Result<SupplierResult> {
+        input: UpdatePlannedBatchInput,
+    ) -> Result<PlannedBatchResult> {
         let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
 
-        // Check if name already exists
-        let existing = sqlx::query!("SELECT id FROM suppliers WHERE name = $1", input.name)
-            .fetch_optional(pool)
-            .await?;
+        let status = sqlx::query_scalar!(
+            "SELECT status FROM planned_batches WHERE id = $1 FOR UPDATE",
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
-        if existing.is_some() {
-            return Ok(SupplierResult {
+        let Some(status) = status else {
+            return Ok(PlannedBatchResult {
                 success: false,
-                message: format!("A supplier with the name '{}' already exists", input.name),
-                supplier: None,
+                message: "Planned batch not found".to_string(),
+                error: Some(MutationError::not_found("Planned batch")),
+                planned_batch: None,
+            });
+        };
+
+        if status == "started" {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Planned batch has already been started".to_string(),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
             });
         }
 
-        let now = Utc::now();
+        if input
+            .status
+            .as_deref()
+            .is_some_and(|s| s != "planned" && s != "cancelled")
+        {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Status must be 'planned' or 'cancelled'".to_string(),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
+            });
+        }
 
-        // Create the supplier
-        let supplier = sqlx::query_as!(
-            Supplier,
+        if input
+            .batch_size
+            .as_ref()
+            .is_some_and(|size| *size <= BigDecimal::from(0))
+        {
+            return Ok(PlannedBatchResult {
+                success: false,
+                message: "Batch size must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                planned_batch: None,
+            });
+        }
+
+        let planned_batch = sqlx::query_as!(
+            PlannedBatch,
             r#"
-            INSERT INTO suppliers (
-                name, contact_email, contact_phone, street_address, city, state, zip_code, country,
-                latitude, longitude, notes, created_at, updated_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $12)
-            RETURNING
-                id,
-                name,
-                contact_email,
-                contact_phone,
-                street_address,
-                city,
-                state,
-                zip_code,
-                country,
-                latitude as "latitude?: BigDecimal",
-                longitude as "longitude?: BigDecimal",
-                notes,
-                created_at,
-                updated_at
+            WITH updated AS (
+                UPDATE planned_batches
+                SET
+                    batch_size = COALESCE($2, batch_size),
+                    target_date = COALESCE($3, target_date),
+                    status = COALESCE($4, status),
+                    notes = COALESCE($5, notes),
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+            )
+            SELECT
+                p.id AS "id!", p.recipe_template_id AS "recipe_template_id!",
+                r.template_name AS recipe_name, r.product_inventory_id,
+                p.batch_size AS "batch_size!", r.default_unit AS unit,
+                p.target_date AS "target_date!", p.status AS "status!",
+                p.production_batch_id, p.notes,
+                p.created_at AS "created_at!", p.updated_at AS "updated_at!"
+            FROM updated p
+            JOIN recipe_templates r ON r.id = p.recipe_template_id
             "#,
-            input.name,
-            input.contact_email,
-            input.contact_phone,
-            input.street_address,
-            input.city,
-            input.state,
-            input.zip_code,
-            input.country,
-            input.latitude,
-            input.longitude,
-            input.notes,
-            now
+            input.id,
+            input.batch_size,
+            input.target_date,
+            input.status,
+            input.notes
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        Ok(SupplierResult {
+        let cancelled_allocations = if planned_batch.status == "cancelled" {
+            planning::cancel_allocations(&mut tx, input.id).await?
+        } else {
+            0
+        };
+
+        tx.commit().await?;
+
+        let cancelled_allocations = match cancelled_allocations {
+            0 => String::new(),
+            n => format!("; {} allocation(s) cancelled", n),
+        };
+
+        Ok(PlannedBatchResult {
             success: true,
-            message: format!("Successfully created '{}'", supplier.name),
-            supplier: Some(supplier),
+            message: format!(
+                "Planned batch of {} is {} for {}{}",
+                planned_batch.recipe_name,
+                planned_batch.status,
+                planned_batch.target_date,
+                cancelled_allocations
+            ),
+            error: None,
+            planned_batch: Some(planned_batch),
         })
     }
 
-    /// Update an existing supplier
-    async fn update_supplier(
+    /// Promise part of a planned batch's expected output to a made-to-order
+    /// (wholesale) order. The allocation is soft: it counts against the plan's
+    /// capacity and becomes reserved stock when the batch completes.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn allocate_planned_batch(
         &self,
         ctx: &Context<'_>,
-        input: UpdateSupplierInput,
-    ) -> Result<SupplierResult> {
+        input: AllocatePlannedBatchInput,
+    ) -> Result<PlanAllocationResult> {
         let pool = ctx.data::<PgPool>()?;
         let mut tx = pool.begin().await?;
 
-        // Check if supplier exists
-        let existing = sqlx::query!("SELECT name FROM suppliers WHERE id = $1", input.id)
-            .fetch_optional(&mut *tx)
-            .await?;
-
-        if existing.is_none() {
-            return Ok(SupplierResult {
-                success: false,
-                message: "Supplier not found".to_string(),
-                supplier: None,
-            });
+        if input.quantity <= BigDecimal::from(0) {
+            return Ok(PlanAllocationResult::failed(
+                "Quantity must be greater than 0",
+            ));
         }
 
-        // Check if new name conflicts with existing suppliers (if name is being changed)
-        if let Some(ref new_name) = input.name {
-            let name_conflict = sqlx::query!(
-                "SELECT id FROM suppliers WHERE name = $1 AND id != $2",
-                new_name,
-                input.id
-            )
-            .fetch_optional(&mut *tx)
-            .await?;
+        // Lock the plan so concurrent allocations can't both take the last of it
+        let locked = sqlx::query_scalar!(
+            "SELECT id FROM planned_batches WHERE id = $1 FOR UPDATE",
+            input.planned_batch_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
-            if name_conflict.is_some() {
-                return Ok(SupplierResult {
-                    success: false,
-                    message: format!("A supplier with the name '{}' already exists", new_name),
-                    supplier: None,
-                });
-            }
+        if locked.is_none() {
+            return Ok(PlanAllocationResult::not_found("Planned batch"));
         }
 
-        let now = Utc::now();
-
-        // Build update query dynamically based on provided fields
-        let supplier = sqlx::query_as!(
-            Supplier,
-            r#"
-            UPDATE suppliers
-            SET
-                name = COALESCE($2, name),
-                contact_email = COALESCE($3, contact_email),
-                contact_phone = COALESCE($4, contact_phone),
-                street_address = COALESCE($5, street_address),
-                city = COALESCE($6, city),
-                state = COALESCE($7, state),
-                zip_code = COALESCE($8, zip_code),
-                country = COALESCE($9, country),
-                latitude = COALESCE($10, latitude),
-                longitude = COALESCE($11, longitude),
-                notes = COALESCE($12, notes),
-                updated_at = $13
-            WHERE id = $1
-            RETURNING
-                id,
-                name,
-                contact_email,
-                contact_phone,
-                street_address,
-                city,
-                state,
-                zip_code,
-                country,
-                latitude as "latitude?: BigDecimal",
-                longitude as "longitude?: BigDecimal",
-                notes,
-                created_at,
-                updated_at
-            "#,
-            input.id,
-            input.name,
-            input.contact_email,
-            input.contact_phone,
-            input.street_address,
-            input.city,
-            input.state,
-            input.zip_code,
-            input.country,
-            input.latitude,
-            input.longitude,
-            input.notes,
-            now
-        )
-        .fetch_one(&mut *tx)
-        .await?;
+        let capacity = planning::capacity(&mut tx, Some(input.planned_batch_id), None, None)
+            .await?
+            .pop();
 
-        tx.commit().await?;
+        let Some(capacity) = capacity else {
+            return Ok(PlanAllocationResult::failed(
+                "Only planned batches, or started ones still in progress, can be allocated",
+            ));
+        };
 
-        Ok(SupplierResult {
-            success: true,
-            message: format!("Successfully updated '{}'", supplier.name),
-            supplier: Some(supplier),
-        })
-    }
+        let Some(product_id) = capacity.product_inventory_id else {
+            return Ok(PlanAllocationResult::failed(&format!(
+                "{} doesn't make a product to allocate",
+                capacity.recipe_name
+            )));
+        };
 
-    /// Create a new recipe template
-    async fn create_recipe_template(
-        &self,
-        ctx: &Context<'_>,
-        input: CreateRecipeTemplateInput,
-    ) -> Result<RecipeTemplateResult> {
-        let pool = ctx.data::<PgPool>()?;
-        let mut tx = pool.begin().await?;
+        if input.quantity > capacity.unallocated {
+            return Ok(PlanAllocationResult::failed(&format!(
+                "Only {} {} of the {} batch on {} is unallocated",
+                capacity.unallocated.max(BigDecimal::from(0)),
+                capacity.product_unit.as_deref().unwrap_or("units"),
+                capacity.recipe_name,
+                capacity.target_date
+            )));
+        }
 
-        // Validate product exists and is active (if product_inventory_id is provided)
-        if let Some(product_id) = input.product_inventory_id {
-            let product = sqlx::query!(
-                r#"SELECT id, name FROM inventory WHERE id = $1 AND is_active = true"#,
-                product_id
+        if let Some(customer_id) = input.customer_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1)",
+                customer_id
             )
-            .fetch_optional(&mut *tx)
-            .await?;
+            .fetch_one(&mut *tx)
+            .await?
+            .unwrap_or(false);
 
-            if product.is_none() {
-                return Ok(RecipeTemplateResult {
-                    success: false,
-                    message: "Product not found or is inactive".to_string(),
-                    recipe: None,
-                });
+            if !exists {
+                return Ok(PlanAllocationResult::not_found("Customer"));
             }
         }
 
-        // Insert new recipe template
-        let recipe = sqlx::query_as!(
-            RecipeTemplate,
+        let allocation = sqlx::query_as!(
+            PlanAllocation,
             r#"
-            INSERT INTO recipe_templates (
-                product_inventory_id, template_name, description,
-                default_batch_size, default_unit, estimated_duration_hours,
-                ingredient_template, instructions, is_active
+            WITH inserted AS (
+                INSERT INTO plan_allocations
+                    (planned_batch_id, inventory_id, customer_id, quantity, due_date, notes)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, true)
-            RETURNING
-                id, product_inventory_id, template_name, description,
-                default_batch_size, default_unit, estimated_duration_hours,
-                ingredient_template, instructions,
-                is_active as "is_active!", created_at, updated_at
+            SELECT
+                a.id AS "id!", a.planned_batch_id AS "planned_batch_id!",
+                a.inventory_id AS "inventory_id!", a.customer_id,
+                c.name AS "customer_name?", a.quantity AS "quantity!", a.due_date,
+                a.status AS "status!", a.reserved_quantity, a.reserved_at, a.notes,
+                a.created_at AS "created_at!", a.updated_at AS "updated_at!"
+            FROM inserted a
+            LEFT JOIN customers c ON c.id = a.customer_id
             "#,
-            input.product_inventory_id,
-            input.template_name,
-            input.description,
-            input.default_batch_size,
-            input.default_unit,
-            input.estimated_duration_hours,
-            input.ingredient_template,
-            input.instructions
+            input.planned_batch_id,
+            product_id,
+            input.customer_id,
+            input.quantity,
+            input.due_date,
+            input.notes
         )
         .fetch_one(&mut *tx)
         .await?;
 
         tx.commit().await?;
 
-        Ok(RecipeTemplateResult {
+        Ok(PlanAllocationResult {
             success: true,
-            message: format!("Successfully created recipe '{}'", recipe.template_name),
-            recipe: Some(recipe),
+            message: format!(
+                "Allocated {} {} of the {} batch on {}; {} left unallocated",
+                allocation.quantity,
+                capacity.product_unit.as_deref().unwrap_or("units"),
+                capacity.recipe_name,
+                capacity.target_date,
+                &capacity.unallocated - &allocation.quantity
+            ),
+            error: None,
+            allocation: Some(allocation),
         })
     }
 
-    /// Update an existing recipe template
-    async fn update_recipe_template(
+    /// Release an allocation. A soft one is cancelled; a reserved one hands
+    /// its reserved stock back, e.g. once the order has been sold (the sale
+    /// takes the stock itself) or has fallen through.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn release_plan_allocation(
         &self,
         ctx: &Context<'_>,
-        input: UpdateRecipeTemplateInput,
-    ) -> Result<RecipeTemplateResult> {
+        input: ReleasePlanAllocationInput,
+    ) -> Result<PlanAllocationResult> {
         let pool = ctx.data::<PgPool>()?;
         let mut tx = pool.begin().await?;
 
-        // Check if recipe exists
-        let existing = sqlx::query!(r#"SELECT id FROM recipe_templates WHERE id = $1"#, input.id)
-            .fetch_optional(&mut *tx)
-            .await?;
+        let existing = sqlx::query!(
+            r#"
+            SELECT inventory_id, status, reserved_quantity
+            FROM plan_allocations
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.allocation_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
-        if existing.is_none() {
-            return Ok(RecipeTemplateResult {
-                success: false,
-                message: "Recipe template not found".to_string(),
-                recipe: None,
-            });
-        }
+        let Some(existing) = existing else {
+            return Ok(PlanAllocationResult::not_found("Allocation"));
+        };
 
-        // If updating product, validate it exists and is active
-        if let Some(product_id) = input.product_inventory_id {
-            let product = sqlx::query!(
-                r#"SELECT id FROM inventory WHERE id = $1 AND is_active = true"#,
-                product_id
+        let status = match existing.status.as_str() {
+            "soft" => "cancelled",
+            "reserved" => "released",
+            status => {
+                return Ok(PlanAllocationResult::failed(&format!(
+                    "Allocation is already {}",
+                    status
+                )));
+            }
+        };
+
+        if let Some(reserved) = existing.reserved_quantity.as_ref() {
+            sqlx::query!(
+                r#"
+                UPDATE inventory
+                SET reserved_stock = GREATEST(reserved_stock - $1, 0), updated_at = NOW()
+                WHERE id = $2
+                "#,
+                reserved,
+                existing.inventory_id
             )
-            .fetch_optional(&mut *tx)
+            .execute(&mut *tx)
             .await?;
-
-            if product.is_none() {
-                return Ok(RecipeTemplateResult {
-                    success: false,
-                    message: "Product not found or is inactive".to_string(),
-                    recipe: None,
-                });
-            }
         }
 
-        let now = Utc::now();
-
-        // Update recipe template
-        let recipe = sqlx::query_as!(
-            RecipeTemplate,
+        let allocation = sqlx::query_as!(
+            PlanAllocation,
             r#"
-            UPDATE recipe_templates
-            SET
-                product_inventory_id = COALESCE($2, product_inventory_id),
-                template_name = COALESCE($3, template_name),
-                description = COALESCE($4, description),
-                default_batch_size = COALESCE($5, default_batch_size),
-                default_unit = COALESCE($6, default_unit),
-                estimated_duration_hours = COALESCE($7, estimated_duration_hours),
-                ingredient_template = COALESCE($8, ingredient_template),
-                instructions = COALESCE($9, instructions),
-                updated_at = $10
-            WHERE id = $1
-            RETURNING
-                id, product_inventory_id, template_name, description,
-                default_batch_size, default_unit, estimated_duration_hours,
-                ingredient_template, instructions,
-                is_active as "is_active!", created_at, updated_at
+            WITH updated AS (
+                UPDATE plan_allocations
+                SET
+                    status = $2,
+                    notes = CASE
+                        WHEN $3::text IS NULL THEN notes
+                        ELSE concat_ws(E'\n', notes, $3)
+                    END,
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+            )
+            SELECT
+                a.id AS "id!", a.planned_batch_id AS "planned_batch_id!",
+                a.inventory_id AS "inventory_id!", a.customer_id,
+                c.name AS "customer_name?", a.quantity AS "quantity!", a.due_date,
+                a.status AS "status!", a.reserved_quantity, a.reserved_at, a.notes,
+                a.created_at AS "created_at!", a.updated_at AS "updated_at!"
+            FROM updated a
+            LEFT JOIN customers c ON c.id = a.customer_id
             "#,
-            input.id,
-            input.product_inventory_id,
-            input.template_name,
-            input.description,
-            input.default_batch_size,
-            input.default_unit,
-            input.estimated_duration_hours,
-            input.ingredient_template,
-            input.instructions,
-            now
+            input.allocation_id,
+            status,
+            input.notes
         )
         .fetch_one(&mut *tx)
         .await?;
 
         tx.commit().await?;
 
-        Ok(RecipeTemplateResult {
+        let message = match existing.reserved_quantity {
+            Some(reserved) => format!("Released {} of reserved stock", reserved),
+            None => "Cancelled soft allocation".to_string(),
+        };
+
+        Ok(PlanAllocationResult {
             success: true,
-            message: format!("Successfully updated recipe '{}'", recipe.template_name),
-            recipe: Some(recipe),
+            message,
+            error: None,
+            allocation: Some(allocation),
         })
     }
 
-    /// Delete a recipe template (soft delete by setting is_active to false)
-    async fn delete_recipe_template(
+    /// Complete a production batch and add finished product to inventory
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn complete_production_batch(
         &self,
         ctx: &Context<'_>,
-        input: DeleteRecipeTemplateInput,
-    ) -> Result<DeleteResult> {
+        input: CompleteProductionBatchInput,
+    ) -> Result<ProductionBatchResult> {
         let pool = ctx.data::<PgPool>()?;
         let mut tx = pool.begin().await?;
 
-        // Check if recipe exists
-        let existing = sqlx::query!(
-            r#"SELECT template_name FROM recipe_templates WHERE id = $1"#,
-            input.id
-        )
-        .fetch_optional(&mut *tx)
-        .await?;
-
-        let Some(recipe) = existing else {
-            return Ok(DeleteResult {
-                success: false,
-                message: "Recipe template not found".to_string(),
-            });
-        };
-
-        // Check if any active production batches reference this recipe
-        let active_batches = sqlx::query!(
+        // 1. Get batch details
+        let batch = sqlx::query!(
             r#"
-            SELECT COUNT(*) as count
+            SELECT batch_number, product_inventory_id, batch_size, status, start_date,
+                storage_location_id
             FROM production_batches
-            WHERE recipe_template_id = $1 AND status = 'in_progress'
+            WHERE id = $1
             "#,
-            input.id
+            input.batch_id
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await?;
 
-        if active_batches.count.unwrap_or(0) > 0 {
-            return Ok(DeleteResult {
+        let batch = match batch {
+            Some(b) => b,
+            None => {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message: "Production batch not found".to_string(),
+                    error: Some(MutationError::not_found("Production batch")),
+                    batch_id: None,
+                    batch_number: None,
+                });
+            }
+        };
+
+        if batch.status != "in_progress" {
+            return Ok(ProductionBatchResult {
                 success: false,
-                message: format!(
-                    "Cannot delete recipe '{}': {} active production batch(es) reference it",
-                    recipe.template_name,
-                    active_batches.count.unwrap_or(0)
-                ),
+                message: format!("Batch is already {}", batch.status),
+                error: Some(MutationError::invalid()),
+                batch_id: None,
+                batch_number: Some(batch.batch_number.clone()),
             });
         }
 
-        // Soft delete by setting is_active to false
+        // Work out what the batch produced: its own product plus any co-products
+        let extra_outputs = input.outputs.unwrap_or_default();
+        let listed_yield = extra_outputs
+            .iter()
+            .find(|o| o.inventory_id == batch.product_inventory_id)
+            .map(|o| o.quantity.clone());
+
+        let actual_yield = match (input.actual_yield, listed_yield) {
+            (Some(_), Some(_)) => {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message:
+                        "Give the batch product's yield as actualYield or in outputs, not both"
+                            .to_string(),
+                    error: Some(MutationError::invalid()),
+                    batch_id: None,
+                    batch_number: Some(batch.batch_number),
+                });
+            }
+            (Some(y), None) | (None, Some(y)) => y,
+            (None, None) => {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message:
+                        "actualYield is required unless the batch product is listed in outputs"
+                            .to_string(),
+                    error: Some(MutationError::invalid()),
+                    batch_id: None,
+                    batch_number: Some(batch.batch_number),
+                });
+            }
+        };
+
+        // (inventory_id, quantity, is_primary), batch product first
+        let mut outputs = vec![(batch.product_inventory_id, actual_yield.clone(), true)];
+
+        for (index, output) in extra_outputs.iter().enumerate() {
+            if output.inventory_id == batch.product_inventory_id {
+                continue;
+            }
+
+            if output.quantity <= BigDecimal::from(0) {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message: "All co-product quantities must be greater than 0".to_string(),
+                    error: Some(MutationError::invalid()),
+                    batch_id: None,
+                    batch_number: Some(batch.batch_number),
+                });
+            }
+
+            if extra_outputs[..index]
+                .iter()
+                .any(|o| o.inventory_id == output.inventory_id)
+            {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message: format!("Output {} is listed more than once", output.inventory_id),
+                    error: Some(MutationError::invalid()),
+                    batch_id: None,
+                    batch_number: Some(batch.batch_number),
+                });
+            }
+
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true)",
+                output.inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?
+            .unwrap_or(false);
+
+            if !exists {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message: format!(
+                        "Output item with ID {} not found or is inactive",
+                        output.inventory_id
+                    ),
+                    error: Some(MutationError::not_found("Output item")),
+                    batch_id: None,
+                    batch_number: Some(batch.batch_number),
+                });
+            }
+
+            outputs.push((output.inventory_id, output.quantity.clone(), false));
+        }
+
+        // Validate the packaging bill of materials before changing anything
+        let packaging = input.packaging.unwrap_or_default();
+
+        if let Err(rejection) = packaging::validate_materials(&mut tx, &packaging).await? {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                batch_id: None,
+                batch_number: Some(batch.batch_number),
+            });
+        }
+
+        // 2. Calculate yield percentage and production time
+        let yield_pct = if batch.batch_size > BigDecimal::from(0) {
+            (&actual_yield / &batch.batch_size) * BigDecimal::from(100)
+        } else {
+            BigDecimal::from(100)
+        };
+
+        let now = Utc::now();
+        let duration_hours = BigDecimal::from((now - batch.start_date).num_hours().max(0));
+
+        // 3. Update batch status
         sqlx::query!(
-            r#"UPDATE recipe_templates SET is_active = false WHERE id = $1"#,
-            input.id
+            r#"
+            UPDATE production_batches
+            SET status = 'completed',
+                completion_date = $1,
+                actual_yield = $2,
+                yield_percentage = $3,
+                production_time_hours = $4,
+                quality_notes = $5,
+                updated_at = $1
+            WHERE id = $6
+            "#,
+            now,
+            actual_yield,
+            yield_pct,
+            duration_hours,
+            input.quality_notes,
+            input.batch_id
         )
         .execute(&mut *tx)
         .await?;
 
+        // 4. Credit each output to inventory, as its own lot (expiry derives
+        //    from the item's shelf life), and log it
+        for (inventory_id, quantity, is_primary) in &outputs {
+            let unit = sqlx::query_scalar!(
+                "UPDATE inventory SET current_stock = current_stock + $1, updated_at = $2 WHERE id = $3 RETURNING unit",
+                quantity,
+                now,
+                inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO production_batch_outputs (batch_id, inventory_id, quantity, unit, is_primary)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                input.batch_id,
+                inventory_id,
+                quantity,
+                unit,
+                is_primary
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_lots (
+                    inventory_id, lot_number, code, source_type, production_batch_id, location_id,
+                    initial_quantity, remaining_quantity, received_date
+                ) VALUES ($1, $2, $7, 'production', $3, $4, $5, $5, $6)
+                "#,
+                inventory_id,
+                batch.batch_number,
+                input.batch_id,
+                batch.storage_location_id,
+                quantity,
+                now,
+                codes::new_lot_code()
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let reason = if *is_primary {
+                format!("Produced in batch {}", batch.batch_number)
+            } else {
+                format!("Co-product of batch {}", batch.batch_number)
+            };
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_logs (
+                    inventory_id, movement_type, quantity, reason, batch_number, created_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                inventory_id,
+                "production_output",
+                quantity,
+                reason,
+                batch.batch_number,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // 5. Consume packaging materials and log them with the output
+        packaging::consume_materials(
+            &mut tx,
+            input.batch_id,
+            &batch.batch_number,
+            &packaging,
+            now,
+        )
+        .await?;
+
+        // 6. Finish the current stage, drop outstanding chores, and free the
+        //    vessel for cleaning
+        stages::close_current(&mut tx, input.batch_id, now).await?;
+        tasks::cancel_pending(&mut tx, input.batch_id).await?;
+        vessels::release(&mut tx, input.batch_id).await?;
+
+        // 7. Hold the output promised to made-to-order customers
+        let (reserved, short) = planning::reserve_allocations(
+            &mut tx,
+            input.batch_id,
+            batch.product_inventory_id,
+            &actual_yield,
+            now,
+        )
+        .await?;
+
+        events::publish(
+            &mut *tx,
+            "batchCompleted",
+            serde_json::json!({
+                "batch_id": input.batch_id,
+                "batch_number": batch.batch_number,
+                "product_inventory_id": batch.product_inventory_id,
+                "actual_yield": actual_yield,
+                "yield_percentage": yield_pct.round(2),
+                "completion_date": now,
+                "quality_notes": input.quality_notes,
+                "outputs": outputs
+                    .iter()
+                    .map(|(inventory_id, quantity, is_primary)| {
+                        serde_json::json!({
+                            "inventory_id": inventory_id,
+                            "quantity": quantity,
+                            "is_primary": is_primary,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+        )
+        .await?;
+
         tx.commit().await?;
 
-        Ok(DeleteResult {
+        let co_products = match outputs.len() - 1 {
+            0 => String::new(),
+            1 => ", plus 1 co-product".to_string(),
+            n => format!(", plus {} co-products", n),
+        };
+        let allocations = match (reserved, short) {
+            (0, _) => String::new(),
+            (n, 0) => format!(". {} allocation(s) reserved", n),
+            (n, short) => format!(". {} allocation(s) reserved, {} short", n, short),
+        };
+
+        Ok(ProductionBatchResult {
             success: true,
-            message: format!("Successfully deleted recipe '{}'", recipe.template_name),
+            message: format!(
+                "Successfully completed production batch {}. Yield: {:.1}%{}{}",
+                batch.batch_number, yield_pct, co_products, allocations
+            ),
+            error: None,
+            batch_id: Some(input.batch_id),
+            batch_number: Some(batch.batch_number),
         })
     }
 
-    /// Create a new customer
-    async fn create_customer(
+    /// Mark a production batch as failed
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn fail_production_batch(
         &self,
         ctx: &Context<'_>,
-        input: CreateCustomerInput,
-    ) -> Result<CustomerResult> {
+        input: FailProductionBatchInput,
+    ) -> Result<ProductionBatchResult> {
         let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
 
-        // Extract boolean with default
-        let tax_exempt = input.tax_exempt.unwrap_or(false);
+        // 1. Get batch details
+        let batch = sqlx::query!(
+            r#"
+            SELECT batch_number, status
+            FROM production_batches
+            WHERE id = $1
+            "#,
+            input.batch_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
-        let customer = sqlx::query_as!(
-            Customer,
+        let batch = match batch {
+            Some(b) => b,
+            None => {
+                return Ok(ProductionBatchResult {
+                    success: false,
+                    message: "Production batch not found".to_string(),
+                    error: Some(MutationError::not_found("Production batch")),
+                    batch_id: None,
+                    batch_number: None,
+                });
+            }
+        };
+
+        if batch.status != "in_progress" {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: format!("Batch is already {}", batch.status),
+                error: Some(MutationError::invalid()),
+                batch_id: None,
+                batch_number: Some(batch.batch_number.clone()),
+            });
+        }
+
+        // 2. Update batch status
+        let now = Utc::now();
+        sqlx::query!(
             r#"
-            INSERT INTO customers (
-                name, email, phone,
-                street_address, city, state, zip_code, country,
-                latitude, longitude,
-                customer_type, tax_exempt, notes
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
-            RETURNING
-                id, name, email, phone,
-                street_address, city, state, zip_code, country,
-                latitude, longitude,
-                customer_type,
-                tax_exempt as "tax_exempt!",
-                notes,
-                is_active as "is_active!",
-                created_at, updated_at
+            UPDATE production_batches
+            SET status = 'failed',
+                completion_date = $1,
+                quality_notes = $2,
+                updated_at = $1
+            WHERE id = $3
             "#,
-            input.name,
-            input.email,
-            input.phone,
-            input.street_address,
-            input.city,
-            input.state,
-            input.zip_code,
-            input.country,
-            input.latitude,
-            input.longitude,
-            input.customer_type,
-            tax_exempt,
-            input.notes
+            now,
+            input.reason,
+            input.batch_id
         )
-        .fetch_one(pool)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(CustomerResult {
+        // 3. Cancel any pending storage moves for the batch
+        sqlx::query!(
+            "UPDATE batch_move_tasks SET status = 'cancelled' WHERE batch_id = $1 AND status = 'pending'",
+            input.batch_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // 4. Finish the current stage, drop outstanding chores, and free the
+        //    vessel for cleaning
+        stages::close_current(&mut tx, input.batch_id, now).await?;
+        tasks::cancel_pending(&mut tx, input.batch_id).await?;
+        vessels::release(&mut tx, input.batch_id).await?;
+
+        tx.commit().await?;
+
+        Ok(ProductionBatchResult {
             success: true,
-            message: format!("Successfully created customer '{}'", customer.name),
-            customer: Some(customer),
+            message: format!("Production batch {} marked as failed", batch.batch_number),
+            error: None,
+            batch_id: Some(input.batch_id),
+            batch_number: Some(batch.batch_number),
         })
     }
 
-    /// Update an existing customer
-    async fn update_customer(
+    /// Record a fermentation reading (pH, temperature, salinity, brix) on a batch
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn record_batch_reading(
         &self,
         ctx: &Context<'_>,
-        input: UpdateCustomerInput,
-    ) -> Result<CustomerResult> {
+        input: RecordBatchReadingInput,
+    ) -> Result<BatchReadingResult> {
         let pool = ctx.data::<PgPool>()?;
+        let reading_queue = ctx.data::<ReadingQueue>()?;
+
+        let new_reading = NewReading {
+            recorded_at: input.recorded_at,
+            ph: input.ph,
+            temperature: input.temperature,
+            salinity_percent: input.salinity_percent,
+            brix: input.brix,
+            observations: input.observations,
+            source: "manual",
+            device_name: None,
+        };
 
-        // Fetch the existing customer to ensure it exists
-        let existing = sqlx::query!("SELECT id FROM customers WHERE id = $1", input.id)
-            .fetch_optional(pool)
-            .await?;
-
-        if existing.is_none() {
-            return Ok(CustomerResult {
+        if let Err(message) = readings::validate(&new_reading) {
+            return Ok(BatchReadingResult {
                 success: false,
-                message: "Customer not found".to_string(),
-                customer: None,
+                message,
+                error: Some(MutationError::invalid()),
+                reading: None,
             });
         }
 
-        // Handle optional boolean fields
-        let tax_exempt = input.tax_exempt;
-        let is_active = input.is_active;
+        let batch = sqlx::query!(
+            "SELECT batch_number FROM production_batches WHERE id = $1",
+            input.batch_id
+        )
+        .fetch_optional(pool)
+        .await?;
 
-        // Build dynamic UPDATE query based on provided fields
-        let customer = sqlx::query_as!(
-            Customer,
+        let Some(batch) = batch else {
+            return Ok(BatchReadingResult {
+                success: false,
+                message: "Production batch not found".to_string(),
+                error: Some(MutationError::not_found("Production batch")),
+                reading: None,
+            });
+        };
+
+        let reading = readings::insert(pool, input.batch_id, new_reading).await?;
+
+        reading_queue.submit(reading.clone());
+
+        Ok(BatchReadingResult {
+            success: true,
+            message: format!("Reading recorded for batch {}", batch.batch_number),
+            error: None,
+            reading: Some(reading),
+        })
+    }
+
+    /// Acknowledge a batch alert so it no longer shows as open
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn acknowledge_batch_alert(
+        &self,
+        ctx: &Context<'_>,
+        input: AcknowledgeBatchAlertInput,
+    ) -> Result<BatchAlertResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let alert = sqlx::query_as!(
+            BatchAlert,
             r#"
-            UPDATE customers SET
-                name = COALESCE($2, name),
-                email = COALESCE($3, email),
-                phone = COALESCE($4, phone),
-                street_address = COALESCE($5, street_address),
-                city = COALESCE($6, city),
-                state = COALESCE($7, state),
-                zip_code = COALESCE($8, zip_code),
-                country = COALESCE($9, country),
-                latitude = COALESCE($10, latitude),
-                longitude = COALESCE($11, longitude),
-                customer_type = COALESCE($12, customer_type),
-                tax_exempt = COALESCE($13::boolean, tax_exempt),
-                notes = COALESCE($14, notes),
-                is_active = COALESCE($15::boolean, is_active),
-                updated_at = NOW()
+            UPDATE batch_alerts
+            SET acknowledged_at = COALESCE(acknowledged_at, NOW())
             WHERE id = $1
             RETURNING
-                id, name, email, phone,
-                street_address, city, state, zip_code, country,
-                latitude, longitude,
-                customer_type,
-                tax_exempt as "tax_exempt!",
-                notes,
-                is_active as "is_active!",
-                created_at, updated_at
+                id, batch_id, reading_id, alert_type, message, measured_value,
+                threshold, acknowledged_at, created_at
             "#,
-            input.id,
-            input.name,
-            input.email,
-            input.phone,
-            input.street_address,
-            input.city,
-            input.state,
-            input.zip_code,
-            input.country,
-            input.latitude,
-            input.longitude,
-            input.customer_type,
-            tax_exempt,
-            input.notes,
-            is_active
+            input.alert_id
         )
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await?;
 
-        Ok(CustomerResult {
+        let Some(alert) = alert else {
+            return Ok(BatchAlertResult {
+                success: false,
+                message: "Alert not found".to_string(),
+                error: Some(MutationError::not_found("Alert")),
+                alert: None,
+            });
+        };
+
+        Ok(BatchAlertResult {
             success: true,
-            message: format!("Successfully updated customer '{}'", customer.name),
-            customer: Some(customer),
+            message: "Alert acknowledged".to_string(),
+            error: None,
+            alert: Some(alert),
         })
     }
 
-    /// Create a new sale and update inventory
-    async fn create_sale(&self, ctx: &Context<'_>, input: CreateSaleInput) -> Result<SaleResult> {
+    /// Record a HACCP quality check on a batch; failed checks need a corrective action
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn record_quality_check(
+        &self,
+        ctx: &Context<'_>,
+        input: RecordQualityCheckInput,
+    ) -> Result<QualityCheckResult> {
         let pool = ctx.data::<PgPool>()?;
-        let mut tx = pool.begin().await?;
 
-        let sale_date = input.sale_date.unwrap_or_else(Utc::now);
-
-        // Validate inputs
-        if input.items.is_empty() {
-            return Ok(SaleResult {
+        if input.check_type.trim().is_empty() || input.signed_by.trim().is_empty() {
+            return Ok(QualityCheckResult {
                 success: false,
-                message: "At least one item is required".to_string(),
-                sale_id: None,
-                sale_number: None,
-                updated_items: Vec::new(),
+                message: "Check type and signer are required".to_string(),
+                error: Some(MutationError::invalid()),
+                check: None,
             });
         }
 
-        // Validate all items have sufficient stock before processing
-        for item_input in &input.items {
-            if item_input.quantity <= BigDecimal::from(0) {
-                return Ok(SaleResult {
-                    success: false,
-                    message: "Quantity must be greater than 0".to_string(),
-                    sale_id: None,
-                    sale_number: None,
-                    updated_items: Vec::new(),
-                });
-            }
+        let corrective_action = input
+            .corrective_action
+            .map(|action| action.trim().to_string())
+            .filter(|action| !action.is_empty());
 
-            // Check stock availability
-            let inventory = sqlx::query!(
-                "SELECT current_stock FROM inventory WHERE id = $1",
-                item_input.inventory_id
-            )
-            .fetch_optional(&mut *tx)
-            .await?;
+        if !input.passed && corrective_action.is_none() {
+            return Ok(QualityCheckResult {
+                success: false,
+                message: "A failed check requires a corrective action".to_string(),
+                error: Some(MutationError::invalid()),
+                check: None,
+            });
+        }
 
-            let Some(inventory) = inventory else {
-                return Ok(SaleResult {
-                    success: false,
-                    message: format!("Inventory item not found: {}", item_input.inventory_id),
-                    sale_id: None,
-                    sale_number: None,
-                    updated_items: Vec::new(),
-                });
-            };
+        let checked_at = input.checked_at.unwrap_or_else(Utc::now);
 
-            if inventory.current_stock < item_input.quantity {
-                return Ok(SaleResult {
-                    success: false,
-                    message: format!(
-                        "Insufficient stock for item {}. Available: {}, Requested: {}",
-                        item_input.inventory_id, inventory.current_stock, item_input.quantity
-                    ),
-                    sale_id: None,
-                    sale_number: None,
-                    updated_items: Vec::new(),
-                });
-            }
+        if checked_at > Utc::now() {
+            return Ok(QualityCheckResult {
+                success: false,
+                message: "Check time cannot be in the future".to_string(),
+                error: Some(MutationError::invalid()),
+                check: None,
+            });
         }
 
-        // Calculate totals
-        let subtotal: BigDecimal = input
-            .items
-            .iter()
-            .map(|item| &item.quantity * &item.unit_price)
-            .sum();
-        let tax_amount = input.tax_amount.unwrap_or_else(|| BigDecimal::from(0));
-        let discount_amount = input.discount_amount.unwrap_or_else(|| BigDecimal::from(0));
-        let total_amount = &subtotal + &tax_amount - &discount_amount;
-
-        // Generate unique sale number (SALE-YYYYMMDD-NNN)
-        let date_str = sale_date.format("%Y%m%d").to_string();
-        let count = sqlx::query!(
-            "SELECT COUNT(*) as count FROM sales WHERE sale_number LIKE $1",
-            format!("SALE-{}-%%%", date_str)
+        let batch = sqlx::query!(
+            "SELECT batch_number FROM production_batches WHERE id = $1",
+            input.batch_id
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(pool)
         .await?;
 
-        let sale_number = format!("SALE-{}-{:03}", date_str, count.count.unwrap_or(0) + 1);
+        let Some(batch) = batch else {
+            return Ok(QualityCheckResult {
+                success: false,
+                message: "Production batch not found".to_string(),
+                error: Some(MutationError::not_found("Production batch")),
+                check: None,
+            });
+        };
 
-        // Create sale record
-        let sale_id = sqlx::query_scalar!(
+        let check = sqlx::query_as!(
+            QualityCheck,
             r#"
-            INSERT INTO sales (
-                sale_number, customer_id, sale_date,
-                subtotal, tax_amount, discount_amount, total_amount,
-                payment_method, payment_status, notes
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            RETURNING id
+            INSERT INTO quality_checks (
+                batch_id, check_type, control_point, passed, measured_value, unit,
+                critical_limit, corrective_action, signed_by, checked_at, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING
+                id, batch_id, check_type, control_point, passed, measured_value,
+                unit, critical_limit, corrective_action, signed_by, checked_at,
+                notes, created_at
             "#,
-            sale_number,
-            input.customer_id,
-            sale_date,
-            subtotal,
-            tax_amount,
-            discount_amount,
-            total_amount,
-            input.payment_method,
-            input
-                .payment_status
-                .unwrap_or_else(|| "completed".to_string()),
+            input.batch_id,
+            input.check_type.trim(),
+            input.control_point,
+            input.passed,
+            input.measured_value,
+            input.unit,
+            input.critical_limit,
+            corrective_action,
+            input.signed_by.trim(),
+            checked_at,
             input.notes
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(pool)
         .await?;
 
-        let mut updated_items = Vec::new();
+        Ok(QualityCheckResult {
+            success: true,
+            message: format!(
+                "{} check {} for batch {}",
+                check.check_type,
+                if check.passed { "passed" } else { "failed" },
+                batch.batch_number
+            ),
+            error: None,
+            check: Some(check),
+        })
+    }
 
-        // Process each sale item
-        for item_input in input.items {
-            let line_total = &item_input.quantity * &item_input.unit_price;
+    /// Submit a quick count of one item with photo evidence. The count waits
+    /// for back-office approval; stock is not changed until then.
+    #[graphql(guard = "RequireRole(auth::STAFF)")]
+    async fn submit_quick_count(
+        &self,
+        ctx: &Context<'_>,
+        input: SubmitQuickCountInput,
+    ) -> Result<StockCountResult> {
+        let pool = ctx.data::<PgPool>()?;
 
-            // Insert sale item
-            sqlx::query!(
-                r#"
-                INSERT INTO sale_items (
-                    sale_id, inventory_id, quantity, unit_price, line_total, notes
-                ) VALUES ($1, $2, $3, $4, $5, $6)
-                "#,
-                sale_id,
-                item_input.inventory_id,
-                item_input.quantity,
-                item_input.unit_price,
-                line_total,
-                item_input.notes
-            )
-            .execute(&mut *tx)
-            .await?;
+        if input.counted_quantity < BigDecimal::from(0) {
+            return Ok(StockCountResult {
+                success: false,
+                message: "Counted quantity cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                count: None,
+            });
+        }
 
-            // Update inventory (decrement stock)
-            let updated_item = sqlx::query_as!(
-                InventoryItem,
-                r#"
-                UPDATE inventory
-                SET
-                    current_stock = current_stock - $1,
-                    updated_at = $2
-                WHERE id = $3
-                RETURNING
-                    id,
-                    name,
-                    category,
-                    unit,
-                    current_stock as "current_stock!: BigDecimal",
-                    reserved_stock as "reserved_stock!: BigDecimal",
-                    available_stock as "available_stock!: BigDecimal",
-                    reorder_point as "reorder_point!: BigDecimal",
-                    cost_per_unit as "cost_per_unit?: BigDecimal",
-                    default_supplier_id,
-                    shelf_life_days,
-                    storage_requirements,
-                    is_active,
-                    created_at,
-                    updated_at
-                "#,
-                item_input.quantity,
-                sale_date,
-                item_input.inventory_id
-            )
-            .fetch_one(&mut *tx)
-            .await?;
+        if input.counted_by.trim().is_empty() {
+            return Ok(StockCountResult {
+                success: false,
+                message: "Counted by is required".to_string(),
+                error: Some(MutationError::invalid()),
+                count: None,
+            });
+        }
+
+        if input.photos.is_empty() {
+            return Ok(StockCountResult {
+                success: false,
+                message: "At least one photo of the counted stock is required".to_string(),
+                error: Some(MutationError::invalid()),
+                count: None,
+            });
+        }
+
+        let mut photos = Vec::with_capacity(input.photos.len());
+        for upload in &input.photos {
+            match stocktake::read_photo(upload.value(ctx)?) {
+                Ok(photo) => photos.push(photo),
+                Err(message) => {
+                    return Ok(StockCountResult {
+                        success: false,
+                        message,
+                        error: Some(MutationError::invalid()),
+                        count: None,
+                    });
+                }
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let item = sqlx::query!(
+            "SELECT name, unit, current_stock FROM inventory WHERE id = $1 AND is_active = true",
+            input.inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(item) = item else {
+            return Ok(StockCountResult {
+                success: false,
+                message: "Inventory item not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Inventory item")),
+                count: None,
+            });
+        };
+
+        let count = sqlx::query_as!(
+            StockCount,
+            r#"
+            INSERT INTO stock_counts (
+                inventory_id, counted_quantity, expected_quantity, unit,
+                counted_by, location, notes, counted_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING
+                id, inventory_id, counted_quantity, expected_quantity, variance as "variance!",
+                unit, counted_by, location, notes, status, counted_at, reviewed_by,
+                reviewed_at, review_notes, applied_adjustment, created_at, updated_at
+            "#,
+            input.inventory_id,
+            input.counted_quantity,
+            item.current_stock,
+            item.unit,
+            input.counted_by.trim(),
+            input.location,
+            input.notes,
+            input.counted_at.unwrap_or_else(Utc::now)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
 
-            // Log the sale in inventory_logs
+        for photo in &photos {
             sqlx::query!(
                 r#"
-                INSERT INTO inventory_logs (
-                    inventory_id, movement_type, quantity, unit_cost, reason, created_at
-                ) VALUES ($1, $2, $3, $4, $5, $6)
+                INSERT INTO stock_count_photos (stock_count_id, filename, content_type, data)
+                VALUES ($1, $2, $3, $4)
                 "#,
-                item_input.inventory_id,
-                "sale",
-                -item_input.quantity, // Negative for stock reduction
-                item_input.unit_price,
-                format!("Sale {}", sale_number),
-                sale_date
+                count.id,
+                photo.filename,
+                photo.content_type,
+                photo.data
             )
             .execute(&mut *tx)
             .await?;
-
-            updated_items.push(updated_item);
         }
 
-        // Commit the transaction
         tx.commit().await?;
 
-        Ok(SaleResult {
+        Ok(StockCountResult {
             success: true,
-            message: format!("Successfully created sale {}", sale_number),
-            sale_id: Some(sale_id),
-            sale_number: Some(sale_number),
-            updated_items,
+            message: format!(
+                "Counted {} {} of {} (variance {}); awaiting approval",
+                count.counted_quantity, count.unit, item.name, count.variance
+            ),
+            error: None,
+            count: Some(count),
         })
     }
+
+    /// Approve a pending quick count, adjusting stock by the variance it found,
+    /// optionally booking one of its suggestions as the cause
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn approve_stock_count(
+        &self,
+        ctx: &Context<'_>,
+        input: ReviewStockCountInput,
+    ) -> Result<StockCountResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let pending = sqlx::query!(
+            r#"
+            SELECT
+                c.inventory_id, c.variance as "variance!", c.counted_by, c.counted_at,
+                i.name, i.current_stock
+            FROM stock_counts c
+            JOIN inventory i ON i.id = c.inventory_id
+            WHERE c.id = $1 AND c.status = 'pending'
+            FOR UPDATE OF c, i
+            "#,
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pending) = pending else {
+            return Ok(StockCountResult {
+                success: false,
+                message: "Pending stock count not found".to_string(),
+                error: Some(MutationError::not_found("Pending stock count")),
+                count: None,
+            });
+        };
+
+        // Stock can't go below zero if it has been sold down since the count
+        let zero = BigDecimal::from(0);
+        let adjustment = if &pending.current_stock + &pending.variance < zero {
+            -pending.current_stock.clone()
+        } else {
+            pending.variance.clone()
+        };
+
+        // A chosen cause is booked as its own entry (sample, waste, reversal);
+        // whatever it doesn't explain is a plain adjustment
+        let suggestion = match input.suggestion.as_deref() {
+            Some(key) => {
+                let found = stocktake::suggestions(
+                    &mut tx,
+                    pending.inventory_id,
+                    &pending.variance,
+                    pending.counted_at,
+                )
+                .await?
+                .into_iter()
+                .find(|suggestion| suggestion.key == key);
+
+                match found {
+                    Some(suggestion) => Some(suggestion),
+                    None => {
+                        return Ok(StockCountResult {
+                            success: false,
+                            message: "That suggestion no longer applies to this count".to_string(),
+                            error: Some(MutationError::invalid()),
+                            count: None,
+                        });
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let approval = format!(
+            "Quick count by {} approved by {}",
+            pending.counted_by,
+            input.reviewed_by.trim()
+        );
+        let mut entries = Vec::new();
+        let mut unexplained = adjustment.clone();
+
+        if let Some(suggestion) = suggestion {
+            let corrected = if suggestion.quantity.abs() > adjustment.abs() {
+                adjustment.clone()
+            } else {
+                suggestion.quantity
+            };
+
+            if corrected != zero {
+                unexplained -= &corrected;
+                entries.push((
+                    suggestion.movement_type,
+                    corrected,
+                    format!("{} ({})", suggestion.explanation, approval),
+                ));
+            }
+        }
+        if unexplained != zero {
+            entries.push(("adjustment".to_string(), unexplained, approval));
+        }
+
+        let now = Utc::now();
+
+        if adjustment != zero {
+            sqlx::query!(
+                "UPDATE inventory SET current_stock = current_stock + $1, updated_at = $2 WHERE id = $3",
+                adjustment,
+                now,
+                pending.inventory_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            // Shrinkage is written off the oldest lots; found stock stays untracked
+            if adjustment < zero {
+                lots::consume(&mut tx, pending.inventory_id, &-adjustment.clone()).await?;
+            }
+        }
+
+        for (movement_type, quantity, reason) in entries {
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_logs (inventory_id, movement_type, quantity, reason, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                pending.inventory_id,
+                movement_type,
+                quantity,
+                reason,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let count = sqlx::query_as!(
+            StockCount,
+            r#"
+            UPDATE stock_counts
+            SET status = 'approved', reviewed_by = $2, reviewed_at = $3,
+                review_notes = $4, applied_adjustment = $5, updated_at = $3
+            WHERE id = $1
+            RETURNING
+                id, inventory_id, counted_quantity, expected_quantity, variance as "variance!",
+                unit, counted_by, location, notes, status, counted_at, reviewed_by,
+                reviewed_at, review_notes, applied_adjustment, created_at, updated_at
+            "#,
+            input.id,
+            input.reviewed_by.trim(),
+            now,
+            input.notes,
+            adjustment
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(StockCountResult {
+            success: true,
+            message: format!(
+                "Approved count of {}; stock adjusted by {}",
+                pending.name, adjustment
+            ),
+            error: None,
+            count: Some(count),
+        })
+    }
+
+    /// Reject a pending quick count without changing stock
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn reject_stock_count(
+        &self,
+        ctx: &Context<'_>,
+        input: ReviewStockCountInput,
+    ) -> Result<StockCountResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let count = sqlx::query_as!(
+            StockCount,
+            r#"
+            UPDATE stock_counts
+            SET status = 'rejected', reviewed_by = $2, reviewed_at = NOW(),
+                review_notes = $3, updated_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING
+                id, inventory_id, counted_quantity, expected_quantity, variance as "variance!",
+                unit, counted_by, location, notes, status, counted_at, reviewed_by,
+                reviewed_at, review_notes, applied_adjustment, created_at, updated_at
+            "#,
+            input.id,
+            input.reviewed_by.trim(),
+            input.notes
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(count) = count else {
+            return Ok(StockCountResult {
+                success: false,
+                message: "Pending stock count not found".to_string(),
+                error: Some(MutationError::not_found("Pending stock count")),
+                count: None,
+            });
+        };
+
+        Ok(StockCountResult {
+            success: true,
+            message: "Rejected stock count; stock unchanged".to_string(),
+            error: None,
+            count: Some(count),
+        })
+    }
+
+    /// Create a new inventory item
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_inventory_item(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateInventoryItemInput,
+    ) -> Result<InventoryItemResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Check if name already exists
+        let existing = sqlx::query!(
+            "SELECT id FROM inventory WHERE name = $1 AND is_active = true",
+            input.name
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if existing.is_some() {
+            return Ok(InventoryItemResult {
+                success: false,
+                message: format!("An item with the name '{}' already exists", input.name),
+                error: Some(MutationError::name_conflict("Inventory item", &input.name)),
+                conflict: false,
+                item: None,
+            });
+        }
+
+        // Validate supplier_id if provided
+        if let Some(supplier_id) = input.default_supplier_id {
+            let supplier_exists = sqlx::query!(
+                "SELECT id FROM suppliers WHERE id = $1 AND is_active",
+                supplier_id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if supplier_exists.is_none() {
+                return Ok(InventoryItemResult {
+                    success: false,
+                    message: "Supplier not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Supplier")),
+                    conflict: false,
+                    item: None,
+                });
+            }
+        }
+
+        // Product codes go into batch numbers
+        let product_code = match &input.product_code {
+            Some(code) => match numbering::product_code(pool, code, None).await? {
+                Ok(code) => Some(code),
+                Err(rejection) => {
+                    return Ok(InventoryItemResult {
+                        success: false,
+                        message: rejection.message,
+                        error: Some(rejection.error),
+                        conflict: false,
+                        item: None,
+                    });
+                }
+            },
+            None => None,
+        };
+
+        let now = Utc::now();
+        let current_stock = input.current_stock.unwrap_or(BigDecimal::from(0));
+        let reserved_stock = input.reserved_stock.unwrap_or(BigDecimal::from(0));
+        let reorder_point = input.reorder_point.unwrap_or(BigDecimal::from(0));
+
+        // Create the inventory item
+        let item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            INSERT INTO inventory (
+                name, category, unit, current_stock, reserved_stock, reorder_point,
+                cost_per_unit, default_supplier_id, shelf_life_days, storage_requirements,
+                product_code, is_active, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $12, true, $11, $11)
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            input.name,
+            input.category,
+            input.unit,
+            current_stock,
+            reserved_stock,
+            reorder_point,
+            input.cost_per_unit,
+            input.default_supplier_id,
+            input.shelf_life_days,
+            input.storage_requirements,
+            now,
+            product_code
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(InventoryItemResult {
+            success: true,
+            message: format!("Successfully created '{}'", item.name),
+            error: None,
+            conflict: false,
+            item: Some(item),
+        })
+    }
+
+    /// Update an existing inventory item
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn update_inventory_item(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateInventoryItemInput,
+    ) -> Result<InventoryItemResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Check if item exists, and lock it so a concurrent edit waits for this one
+        let existing = sqlx::query!(
+            "SELECT name, version, updated_at FROM inventory WHERE id = $1 FOR UPDATE",
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(InventoryItemResult {
+                success: false,
+                message: "Inventory item not found".to_string(),
+                error: Some(MutationError::not_found("Inventory item")),
+                conflict: false,
+                item: None,
+            });
+        };
+
+        if let Some(rejection) = stale_edit(
+            &mut tx,
+            input.id,
+            &existing.name,
+            existing.version,
+            existing.updated_at,
+            input.expected_version,
+        )
+        .await?
+        {
+            return Ok(InventoryItemResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                conflict: true,
+                item: None,
+            });
+        }
+
+        // Check if new name conflicts with existing items (if name is being changed)
+        if let Some(ref new_name) = input.name {
+            let name_conflict = sqlx::query!(
+                "SELECT id FROM inventory WHERE name = $1 AND id != $2 AND is_active = true",
+                new_name,
+                input.id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if name_conflict.is_some() {
+                return Ok(InventoryItemResult {
+                    success: false,
+                    message: format!("An item with the name '{}' already exists", new_name),
+                    error: Some(MutationError::name_conflict("Inventory item", new_name)),
+                    conflict: false,
+                    item: None,
+                });
+            }
+        }
+
+        // Validate supplier_id if provided
+        if let Some(supplier_id) = input.default_supplier_id {
+            let supplier_exists = sqlx::query!(
+                "SELECT id FROM suppliers WHERE id = $1 AND is_active",
+                supplier_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if supplier_exists.is_none() {
+                return Ok(InventoryItemResult {
+                    success: false,
+                    message: "Supplier not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Supplier")),
+                    conflict: false,
+                    item: None,
+                });
+            }
+        }
+
+        // Product codes go into batch numbers
+        let product_code = match &input.product_code {
+            Some(code) => match numbering::product_code(&mut *tx, code, Some(input.id)).await? {
+                Ok(code) => Some(code),
+                Err(rejection) => {
+                    return Ok(InventoryItemResult {
+                        success: false,
+                        message: rejection.message,
+                        error: Some(rejection.error),
+                        conflict: false,
+                        item: None,
+                    });
+                }
+            },
+            None => None,
+        };
+
+        let now = Utc::now();
+
+        // Build update query dynamically based on provided fields
+        // For simplicity, we'll use COALESCE to keep existing values if new ones aren't provided
+        let item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            UPDATE inventory
+            SET
+                name = COALESCE($2, name),
+                category = COALESCE($3, category),
+                unit = COALESCE($4, unit),
+                current_stock = COALESCE($5, current_stock),
+                reserved_stock = COALESCE($6, reserved_stock),
+                reorder_point = COALESCE($7, reorder_point),
+                cost_per_unit = COALESCE($8, cost_per_unit),
+                default_supplier_id = COALESCE($9, default_supplier_id),
+                shelf_life_days = COALESCE($10, shelf_life_days),
+                storage_requirements = COALESCE($11, storage_requirements),
+                is_active = COALESCE($12, is_active),
+                version = version + 1,
+                updated_at = $13,
+                product_code = COALESCE($14, product_code)
+            WHERE id = $1
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            input.id,
+            input.name,
+            input.category,
+            input.unit,
+            input.current_stock,
+            input.reserved_stock,
+            input.reorder_point,
+            input.cost_per_unit,
+            input.default_supplier_id,
+            input.shelf_life_days,
+            input.storage_requirements,
+            input.is_active,
+            now,
+            product_code
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if input.cost_per_unit.is_some() {
+            cost_watch::recheck(pool, ctx.data::<Notifier>()?).await;
+        }
+
+        Ok(InventoryItemResult {
+            success: true,
+            message: format!("Successfully updated '{}'", item.name),
+            error: None,
+            conflict: false,
+            item: Some(item),
+        })
+    }
+
+    /// Delete an inventory item (hard delete)
+    /// Use this for accidental additions or items that have gone completely bad
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_inventory_item(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteInventoryItemInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Begin transaction
+        let mut tx = pool.begin().await?;
+
+        // Check if item exists and has no dependencies
+        let item = sqlx::query!(
+            "SELECT name FROM inventory WHERE id = $1",
+            input.inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let item = match item {
+            Some(i) => i,
+            None => {
+                return Ok(DeleteResult {
+                    success: false,
+                    message: "Inventory item not found".to_string(),
+                    error: Some(MutationError::not_found("Inventory item")),
+                });
+            }
+        };
+
+        // Check for active production batches using this item
+        let active_batches = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM production_batch_ingredients
+            JOIN production_batches ON production_batch_ingredients.batch_id = production_batches.id
+            WHERE production_batch_ingredients.ingredient_inventory_id = $1
+                AND production_batches.status = 'in_progress'
+            "#,
+            input.inventory_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if active_batches.count.unwrap_or(0) > 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: format!(
+                    "Cannot delete '{}': item is used in active production batches",
+                    item.name
+                ),
+                error: Some(MutationError::invalid()),
+            });
+        }
+
+        // Delete the inventory item (cascading will handle related records)
+        sqlx::query!("DELETE FROM inventory WHERE id = $1", input.inventory_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Commit transaction
+        tx.commit().await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted '{}'", item.name),
+            error: None,
+        })
+    }
+
+    /// Define a pack size (packaged SKU) for a bulk product
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_pack_size(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePackSizeInput,
+    ) -> Result<PackSizeResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.bulk_quantity_per_pack <= BigDecimal::from(0) {
+            return Ok(PackSizeResult {
+                success: false,
+                message: "Bulk quantity per pack must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                pack_size: None,
+            });
+        }
+
+        let labels_per_pack = input.labels_per_pack.unwrap_or(1);
+        if labels_per_pack <= 0 {
+            return Ok(PackSizeResult {
+                success: false,
+                message: "Labels per pack must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                pack_size: None,
+            });
+        }
+
+        if input.bulk_inventory_id == input.packaged_inventory_id {
+            return Ok(PackSizeResult {
+                success: false,
+                message: "Packaged item must be different from the bulk item".to_string(),
+                error: Some(MutationError::invalid()),
+                pack_size: None,
+            });
+        }
+
+        for (item_id, label) in [
+            (input.bulk_inventory_id, "Bulk"),
+            (input.packaged_inventory_id, "Packaged"),
+        ]
+        .into_iter()
+        .chain(input.label_inventory_id.map(|id| (id, "Label")))
+        {
+            let item = sqlx::query!(
+                "SELECT id FROM inventory WHERE id = $1 AND is_active = true",
+                item_id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if item.is_none() {
+                return Ok(PackSizeResult {
+                    success: false,
+                    message: format!("{} item not found or is inactive", label),
+                    error: Some(MutationError::not_found(&format!("{} item", label))),
+                    pack_size: None,
+                });
+            }
+        }
+
+        let existing = sqlx::query!(
+            "SELECT name FROM pack_sizes WHERE packaged_inventory_id = $1",
+            input.packaged_inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(existing) = existing {
+            return Ok(PackSizeResult {
+                success: false,
+                message: format!(
+                    "Packaged item is already used by pack size '{}'",
+                    existing.name
+                ),
+                error: Some(MutationError::invalid()),
+                pack_size: None,
+            });
+        }
+
+        let pack_size = sqlx::query_as!(
+            PackSize,
+            r#"
+            INSERT INTO pack_sizes (
+                bulk_inventory_id, packaged_inventory_id, name, bulk_quantity_per_pack,
+                label_inventory_id, labels_per_pack
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id, bulk_inventory_id, packaged_inventory_id, name,
+                bulk_quantity_per_pack, label_inventory_id, labels_per_pack,
+                created_at, updated_at
+            "#,
+            input.bulk_inventory_id,
+            input.packaged_inventory_id,
+            input.name,
+            input.bulk_quantity_per_pack,
+            input.label_inventory_id,
+            labels_per_pack
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(PackSizeResult {
+            success: true,
+            message: format!("Successfully created pack size '{}'", pack_size.name),
+            error: None,
+            pack_size: Some(pack_size),
+        })
+    }
+
+    /// Set the label stock a pack size uses and how many labels go on each pack
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn set_pack_size_label(
+        &self,
+        ctx: &Context<'_>,
+        input: SetPackSizeLabelInput,
+    ) -> Result<PackSizeResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let labels_per_pack = input.labels_per_pack.unwrap_or(1);
+        if labels_per_pack <= 0 {
+            return Ok(PackSizeResult {
+                success: false,
+                message: "Labels per pack must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                pack_size: None,
+            });
+        }
+
+        if let Some(label_id) = input.label_inventory_id {
+            let item = sqlx::query!(
+                "SELECT id FROM inventory WHERE id = $1 AND is_active = true",
+                label_id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if item.is_none() {
+                return Ok(PackSizeResult {
+                    success: false,
+                    message: "Label item not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Label item")),
+                    pack_size: None,
+                });
+            }
+        }
+
+        let pack_size = sqlx::query_as!(
+            PackSize,
+            r#"
+            UPDATE pack_sizes
+            SET label_inventory_id = $2, labels_per_pack = $3, updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, bulk_inventory_id, packaged_inventory_id, name,
+                bulk_quantity_per_pack, label_inventory_id, labels_per_pack,
+                created_at, updated_at
+            "#,
+            input.id,
+            input.label_inventory_id,
+            labels_per_pack
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(pack_size) = pack_size else {
+            return Ok(PackSizeResult {
+                success: false,
+                message: "Pack size not found".to_string(),
+                error: Some(MutationError::not_found("Pack size")),
+                pack_size: None,
+            });
+        };
+
+        Ok(PackSizeResult {
+            success: true,
+            message: format!("Updated labels for pack size '{}'", pack_size.name),
+            error: None,
+            pack_size: Some(pack_size),
+        })
+    }
+
+    /// Delete a pack size (the packaged inventory item is kept)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn delete_pack_size(
+        &self,
+        ctx: &Context<'_>,
+        input: DeletePackSizeInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let deleted = sqlx::query!(
+            "DELETE FROM pack_sizes WHERE id = $1 RETURNING name",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(deleted) = deleted else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Pack size not found".to_string(),
+                error: Some(MutationError::not_found("Pack size")),
+            });
+        };
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted pack size '{}'", deleted.name),
+            error: None,
+        })
+    }
+
+    /// Convert bulk stock into packs of a given pack size
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn package_product(
+        &self,
+        ctx: &Context<'_>,
+        input: PackageProductInput,
+    ) -> Result<PackagingResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.pack_count <= BigDecimal::from(0) {
+            return Ok(PackagingResult {
+                success: false,
+                message: "Pack count must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                bulk_item: None,
+                packaged_item: None,
+            });
+        }
+
+        // 1. Load the pack size and the bulk stock it draws from
+        let pack = sqlx::query!(
+            r#"
+            SELECT
+                p.name, p.bulk_inventory_id, p.packaged_inventory_id, p.bulk_quantity_per_pack,
+                b.name as bulk_name, b.unit as bulk_unit, b.current_stock as bulk_stock
+            FROM pack_sizes p
+            JOIN inventory b ON b.id = p.bulk_inventory_id
+            WHERE p.id = $1
+            FOR UPDATE OF b
+            "#,
+            input.pack_size_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pack) = pack else {
+            return Ok(PackagingResult {
+                success: false,
+                message: "Pack size not found".to_string(),
+                error: Some(MutationError::not_found("Pack size")),
+                bulk_item: None,
+                packaged_item: None,
+            });
+        };
+
+        let bulk_needed = &input.pack_count * &pack.bulk_quantity_per_pack;
+
+        if pack.bulk_stock < bulk_needed {
+            return Ok(PackagingResult {
+                success: false,
+                message: format!(
+                    "Insufficient stock for {}: need {} {}, have {}",
+                    pack.bulk_name, bulk_needed, pack.bulk_unit, pack.bulk_stock
+                ),
+                error: Some(MutationError::insufficient_stock(
+                    pack.bulk_inventory_id,
+                    &pack.bulk_name,
+                    &bulk_needed,
+                    &pack.bulk_stock,
+                )),
+                bulk_item: None,
+                packaged_item: None,
+            });
+        }
+
+        let now = Utc::now();
+        let reason = format!("Packaged {} x {}", input.pack_count, pack.name);
+
+        // 2. Consume bulk stock
+        let bulk_item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            UPDATE inventory
+            SET
+                current_stock = current_stock - $1,
+                updated_at = $2
+            WHERE id = $3
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            bulk_needed,
+            now,
+            pack.bulk_inventory_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let draws = lots::consume(&mut tx, pack.bulk_inventory_id, &bulk_needed).await?;
+
+        // 3. Carry each bulk lot over to the packaged item so jars stay traceable
+        let mut packs_from_lots = BigDecimal::from(0);
+        for draw in &draws {
+            let packs = (&draw.quantity / &pack.bulk_quantity_per_pack)
+                .with_scale_round(4, bigdecimal::RoundingMode::HalfUp);
+            packs_from_lots += &packs;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_lots (
+                    inventory_id, lot_number, source_type, production_batch_id, parent_lot_id,
+                    initial_quantity, remaining_quantity, received_date, expiry_date
+                ) VALUES ($1, $2, 'packaging', $3, $4, $5, $5, $6, $7)
+                "#,
+                pack.packaged_inventory_id,
+                draw.lot_number,
+                draw.production_batch_id,
+                draw.lot_id,
+                packs,
+                now,
+                draw.expiry_date
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Bulk stock that predates lot tracking becomes an untracked packaged lot
+        let untracked_packs = &input.pack_count - &packs_from_lots;
+        if untracked_packs > BigDecimal::from(0) {
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_lots (
+                    inventory_id, source_type, initial_quantity, remaining_quantity, received_date
+                ) VALUES ($1, 'packaging', $2, $2, $3)
+                "#,
+                pack.packaged_inventory_id,
+                untracked_packs,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // 4. Add packaged stock
+        let packaged_item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            UPDATE inventory
+            SET
+                current_stock = current_stock + $1,
+                updated_at = $2
+            WHERE id = $3
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            input.pack_count,
+            now,
+            pack.packaged_inventory_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // 5. Log both sides of the conversion
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_logs (
+                inventory_id, movement_type, quantity, reason, created_at
+            ) VALUES ($1, 'packaging_use', $2, $3, $4), ($5, 'packaging_output', $6, $3, $4)
+            "#,
+            pack.bulk_inventory_id,
+            -bulk_needed.clone(), // Negative because bulk is consumed
+            input.notes.as_deref().unwrap_or(&reason),
+            now,
+            pack.packaged_inventory_id,
+            input.pack_count
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(PackagingResult {
+            success: true,
+            message: format!(
+                "{} using {} {} of {}",
+                reason, bulk_needed, pack.bulk_unit, pack.bulk_name
+            ),
+            error: None,
+            bulk_item: Some(bulk_item),
+            packaged_item: Some(packaged_item),
+        })
+    }
+
+    /// Package a completed batch's bulk output into one or more pack sizes,
+    /// consuming the batch's lot and the jars, lids, and labels used
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn split_batch_output(
+        &self,
+        ctx: &Context<'_>,
+        input: SplitBatchOutputInput,
+    ) -> Result<SplitBatchOutputResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.splits.is_empty() {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: "At least one pack size is required".to_string(),
+                error: Some(MutationError::invalid()),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        }
+
+        for (index, split) in input.splits.iter().enumerate() {
+            if split.pack_count <= BigDecimal::from(0) {
+                return Ok(SplitBatchOutputResult {
+                    success: false,
+                    message: "All pack counts must be greater than 0".to_string(),
+                    error: Some(MutationError::invalid()),
+                    bulk_item: None,
+                    packaged_items: vec![],
+                });
+            }
+
+            if input.splits[..index]
+                .iter()
+                .any(|s| s.pack_size_id == split.pack_size_id)
+            {
+                return Ok(SplitBatchOutputResult {
+                    success: false,
+                    message: format!("Pack size {} is listed more than once", split.pack_size_id),
+                    error: Some(MutationError::invalid()),
+                    bulk_item: None,
+                    packaged_items: vec![],
+                });
+            }
+        }
+
+        // 1. Load the batch and the lot its output was recorded as
+        let batch = sqlx::query!(
+            "SELECT batch_number, product_inventory_id, status FROM production_batches WHERE id = $1",
+            input.batch_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(batch) = batch else {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: "Production batch not found".to_string(),
+                error: Some(MutationError::not_found("Production batch")),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        };
+
+        if batch.status != "completed" {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: format!(
+                    "Batch {} is {}; only completed batches can be packaged",
+                    batch.batch_number, batch.status
+                ),
+                error: Some(MutationError::invalid()),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        }
+
+        let lot = sqlx::query!(
+            r#"
+            SELECT
+                l.id, l.remaining_quantity, i.name, i.unit, i.current_stock,
+                COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) as expiry_date
+            FROM inventory_lots l
+            JOIN inventory i ON i.id = l.inventory_id
+            WHERE l.production_batch_id = $1 AND l.inventory_id = $2 AND l.source_type = 'production'
+            FOR UPDATE OF l, i
+            "#,
+            input.batch_id,
+            batch.product_inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(lot) = lot else {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: format!("Batch {} has no recorded output lot", batch.batch_number),
+                error: Some(MutationError::invalid()),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        };
+
+        // 2. Resolve each pack size against the batch's product
+        let mut packs = Vec::with_capacity(input.splits.len());
+        let mut bulk_needed = BigDecimal::from(0);
+
+        for split in &input.splits {
+            let pack = sqlx::query!(
+                r#"
+                SELECT name, bulk_inventory_id, packaged_inventory_id, bulk_quantity_per_pack
+                FROM pack_sizes
+                WHERE id = $1
+                "#,
+                split.pack_size_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(pack) = pack else {
+                return Ok(SplitBatchOutputResult {
+                    success: false,
+                    message: format!("Pack size {} not found", split.pack_size_id),
+                    error: Some(MutationError::not_found("Pack size")),
+                    bulk_item: None,
+                    packaged_items: vec![],
+                });
+            };
+
+            if pack.bulk_inventory_id != batch.product_inventory_id {
+                return Ok(SplitBatchOutputResult {
+                    success: false,
+                    message: format!("Pack size '{}' is not filled from {}", pack.name, lot.name),
+                    error: Some(MutationError::invalid()),
+                    bulk_item: None,
+                    packaged_items: vec![],
+                });
+            }
+
+            bulk_needed += &split.pack_count * &pack.bulk_quantity_per_pack;
+            packs.push(pack);
+        }
+
+        let available = if lot.current_stock < lot.remaining_quantity {
+            &lot.current_stock
+        } else {
+            &lot.remaining_quantity
+        };
+
+        if *available < bulk_needed {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: format!(
+                    "Insufficient {} from batch {}: need {} {}, have {}",
+                    lot.name, batch.batch_number, bulk_needed, lot.unit, available
+                ),
+                error: Some(MutationError::insufficient_stock(
+                    batch.product_inventory_id,
+                    &lot.name,
+                    &bulk_needed,
+                    available,
+                )),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        }
+
+        // 3. Total up the packaging materials across all pack sizes
+        let mut materials: Vec<PackagingMaterialInput> = Vec::new();
+        for material in input
+            .splits
+            .iter()
+            .flat_map(|s| s.packaging.iter().flatten())
+        {
+            match materials
+                .iter_mut()
+                .find(|m| m.inventory_id == material.inventory_id)
+            {
+                Some(existing) => existing.quantity += &material.quantity,
+                None => materials.push(PackagingMaterialInput {
+                    inventory_id: material.inventory_id,
+                    quantity: material.quantity.clone(),
+                }),
+            }
+        }
+
+        if let Err(rejection) = packaging::validate_materials(&mut tx, &materials).await? {
+            return Ok(SplitBatchOutputResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                bulk_item: None,
+                packaged_items: vec![],
+            });
+        }
+
+        let now = Utc::now();
+
+        // 4. Consume the bulk output from the batch's own lot
+        let bulk_item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            UPDATE inventory
+            SET
+                current_stock = current_stock - $1,
+                updated_at = $2
+            WHERE id = $3
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            bulk_needed,
+            now,
+            batch.product_inventory_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE inventory_lots SET remaining_quantity = remaining_quantity - $1, updated_at = $2 WHERE id = $3",
+            bulk_needed,
+            now,
+            lot.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_logs (
+                inventory_id, movement_type, quantity, reason, batch_number, created_at
+            ) VALUES ($1, 'packaging_use', $2, $3, $4, $5)
+            "#,
+            batch.product_inventory_id,
+            -bulk_needed.clone(), // Negative because bulk is consumed
+            input
+                .notes
+                .clone()
+                .unwrap_or_else(|| format!("Split batch {} into packs", batch.batch_number)),
+            batch.batch_number,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // 5. Fill each pack size as a lot of the batch, so jars stay traceable
+        let mut packaged_items = Vec::with_capacity(packs.len());
+        let mut summary = Vec::with_capacity(packs.len());
+
+        for (split, pack) in input.splits.iter().zip(&packs) {
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_lots (
+                    inventory_id, lot_number, source_type, production_batch_id, parent_lot_id,
+                    initial_quantity, remaining_quantity, received_date, expiry_date
+                ) VALUES ($1, $2, 'packaging', $3, $4, $5, $5, $6, $7)
+                "#,
+                pack.packaged_inventory_id,
+                batch.batch_number,
+                input.batch_id,
+                lot.id,
+                split.pack_count,
+                now,
+                lot.expiry_date
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let packaged_item = sqlx::query_as!(
+                InventoryItem,
+                r#"
+                UPDATE inventory
+                SET
+                    current_stock = current_stock + $1,
+                    updated_at = $2
+                WHERE id = $3
+                RETURNING
+                    id,
+                    name,
+                    category,
+                    unit,
+                    current_stock as "current_stock!: BigDecimal",
+                    reserved_stock as "reserved_stock!: BigDecimal",
+                    available_stock as "available_stock!: BigDecimal",
+                    reorder_point as "reorder_point!: BigDecimal",
+                    cost_per_unit as "cost_per_unit?: BigDecimal",
+                    default_supplier_id,
+                    shelf_life_days,
+                    storage_requirements,
+                    product_code,
+                    is_active,
+                    version,
+                    created_at,
+                    updated_at
+                "#,
+                split.pack_count,
+                now,
+                pack.packaged_inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            let reason = format!("Packaged {} x {}", split.pack_count, pack.name);
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_logs (
+                    inventory_id, movement_type, quantity, reason, batch_number, created_at
+                ) VALUES ($1, 'packaging_output', $2, $3, $4, $5)
+                "#,
+                pack.packaged_inventory_id,
+                split.pack_count,
+                input.notes.as_deref().unwrap_or(&reason),
+                batch.batch_number,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            packaged_items.push(packaged_item);
+            summary.push(format!("{} x {}", split.pack_count, pack.name));
+        }
+
+        // 6. Consume jars, lids, and labels
+        packaging::consume_materials(
+            &mut tx,
+            input.batch_id,
+            &batch.batch_number,
+            &materials,
+            now,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(SplitBatchOutputResult {
+            success: true,
+            message: format!(
+                "Packaged {} from batch {} using {} {} of {}",
+                summary.join(", "),
+                batch.batch_number,
+                bulk_needed,
+                lot.unit,
+                lot.name
+            ),
+            error: None,
+            bulk_item: Some(bulk_item),
+            packaged_items,
+        })
+    }
+
+    /// Print the labels for a packaging day: draws the label stock down from
+    /// packaging inventory against each batch and stores a print-ready PDF
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_label_run(
+        &self,
+        ctx: &Context<'_>,
+        input: LabelRunInput,
+    ) -> Result<LabelRunResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let plan = labels::plan(&mut tx, &input).await?;
+
+        if !plan.problems.is_empty() {
+            return Ok(LabelRunResult {
+                success: false,
+                message: plan.problems.join("; "),
+                error: Some(MutationError::invalid()),
+                plan: Some(plan),
+                label_run: None,
+            });
+        }
+
+        let now = Utc::now();
+
+        // Labels are packaging, so they are recorded against the batch they go on
+        let mut batch_ids: Vec<uuid::Uuid> = Vec::new();
+        for line in &plan.lines {
+            if !batch_ids.contains(&line.batch_id) {
+                batch_ids.push(line.batch_id);
+            }
+        }
+
+        for batch_id in batch_ids {
+            let mut materials: Vec<PackagingMaterialInput> = Vec::new();
+            let mut batch_number = String::new();
+
+            for line in plan.lines.iter().filter(|line| line.batch_id == batch_id) {
+                batch_number.clone_from(&line.batch_number);
+                let Some(label_id) = line.label_inventory_id else {
+                    continue;
+                };
+                match materials.iter_mut().find(|m| m.inventory_id == label_id) {
+                    Some(material) => material.quantity += BigDecimal::from(line.label_count),
+                    None => materials.push(PackagingMaterialInput {
+                        inventory_id: label_id,
+                        quantity: BigDecimal::from(line.label_count),
+                    }),
+                }
+            }
+
+            packaging::consume_materials(&mut tx, batch_id, &batch_number, &materials, now).await?;
+        }
+
+        let pdf = labels::render_pdf(&plan.lines, now.date_naive());
+
+        let label_run = sqlx::query_as!(
+            LabelRun,
+            r#"
+            INSERT INTO label_runs (locale, label_count, notes, pdf, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, locale, label_count, notes, created_at
+            "#,
+            input.locale.as_deref().unwrap_or(DEFAULT_LOCALE),
+            plan.total_labels,
+            input.notes,
+            pdf,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(LabelRunResult {
+            success: true,
+            message: format!(
+                "Printed {} labels for {} SKUs",
+                plan.total_labels,
+                plan.skus.len()
+            ),
+            error: None,
+            plan: Some(plan),
+            label_run: Some(label_run),
+        })
+    }
+
+    /// Define the components that go into one kit (replaces the existing list)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn define_kit(
+        &self,
+        ctx: &Context<'_>,
+        input: DefineKitInput,
+    ) -> Result<KitDefinitionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let kit_name = sqlx::query_scalar!(
+            "SELECT name FROM inventory WHERE id = $1 AND is_active = true",
+            input.kit_inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(kit_name) = kit_name else {
+            return Ok(KitDefinitionResult {
+                success: false,
+                message: "Kit item not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Kit item")),
+                components: vec![],
+            });
+        };
+
+        if input.components.is_empty() {
+            return Ok(KitDefinitionResult {
+                success: false,
+                message: "At least one component is required".to_string(),
+                error: Some(MutationError::invalid()),
+                components: vec![],
+            });
+        }
+
+        for (index, component) in input.components.iter().enumerate() {
+            if component.quantity <= BigDecimal::from(0) {
+                return Ok(KitDefinitionResult {
+                    success: false,
+                    message: "All component quantities must be greater than 0".to_string(),
+                    error: Some(MutationError::invalid()),
+                    components: vec![],
+                });
+            }
+
+            if component.inventory_id == input.kit_inventory_id {
+                return Ok(KitDefinitionResult {
+                    success: false,
+                    message: "A kit cannot contain itself".to_string(),
+                    error: Some(MutationError::invalid()),
+                    components: vec![],
+                });
+            }
+
+            if input.components[..index]
+                .iter()
+                .any(|c| c.inventory_id == component.inventory_id)
+            {
+                return Ok(KitDefinitionResult {
+                    success: false,
+                    message: format!(
+                        "Component {} is listed more than once",
+                        component.inventory_id
+                    ),
+                    error: Some(MutationError::invalid()),
+                    components: vec![],
+                });
+            }
+
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                component.inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !exists {
+                return Ok(KitDefinitionResult {
+                    success: false,
+                    message: format!(
+                        "Component with ID {} not found or is inactive",
+                        component.inventory_id
+                    ),
+                    error: Some(MutationError::not_found("Component")),
+                    components: vec![],
+                });
+            }
+        }
+
+        sqlx::query!(
+            "DELETE FROM kit_components WHERE kit_inventory_id = $1",
+            input.kit_inventory_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for component in &input.components {
+            sqlx::query!(
+                r#"
+                INSERT INTO kit_components (kit_inventory_id, component_inventory_id, quantity)
+                VALUES ($1, $2, $3)
+                "#,
+                input.kit_inventory_id,
+                component.inventory_id,
+                component.quantity
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let components = sqlx::query_as!(
+            KitComponent,
+            r#"
+            SELECT
+                k.id, k.kit_inventory_id, k.component_inventory_id,
+                i.name AS component_name, k.quantity, i.unit, i.cost_per_unit
+            FROM kit_components k
+            JOIN inventory i ON i.id = k.component_inventory_id
+            WHERE k.kit_inventory_id = $1
+            ORDER BY i.name
+            "#,
+            input.kit_inventory_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(KitDefinitionResult {
+            success: true,
+            message: format!(
+                "Defined {} with {} component(s)",
+                kit_name,
+                components.len()
+            ),
+            error: None,
+            components,
+        })
+    }
+
+    /// Assemble kits: consume their components, add the kits to stock as a
+    /// lot, and cost them from the components' current costs
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn assemble_kits(
+        &self,
+        ctx: &Context<'_>,
+        input: AssembleKitsInput,
+    ) -> Result<AssembleKitsResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.quantity <= BigDecimal::from(0) {
+            return Ok(AssembleKitsResult {
+                success: false,
+                message: "Quantity must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                assembly: None,
+                kit_item: None,
+            });
+        }
+
+        // 1. Load the kit and its components, locking component stock
+        let kit = sqlx::query!(
+            "SELECT name FROM inventory WHERE id = $1 AND is_active = true FOR UPDATE",
+            input.kit_inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(kit) = kit else {
+            return Ok(AssembleKitsResult {
+                success: false,
+                message: "Kit item not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Kit item")),
+                assembly: None,
+                kit_item: None,
+            });
+        };
+
+        let components = sqlx::query!(
+            r#"
+            SELECT
+                k.component_inventory_id, k.quantity, i.name, i.unit,
+                i.current_stock, i.cost_per_unit
+            FROM kit_components k
+            JOIN inventory i ON i.id = k.component_inventory_id
+            WHERE k.kit_inventory_id = $1
+            ORDER BY i.name
+            FOR UPDATE OF i
+            "#,
+            input.kit_inventory_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if components.is_empty() {
+            return Ok(AssembleKitsResult {
+                success: false,
+                message: format!("{} has no components defined", kit.name),
+                error: Some(MutationError::invalid()),
+                assembly: None,
+                kit_item: None,
+            });
+        }
+
+        // 2. Check every component is in stock before consuming anything
+        for component in &components {
+            let needed = &component.quantity * &input.quantity;
+            if component.current_stock < needed {
+                return Ok(AssembleKitsResult {
+                    success: false,
+                    message: format!(
+                        "Insufficient stock for {}: need {} {}, have {}",
+                        component.name, needed, component.unit, component.current_stock
+                    ),
+                    error: Some(MutationError::insufficient_stock(
+                        component.component_inventory_id,
+                        &component.name,
+                        &needed,
+                        &component.current_stock,
+                    )),
+                    assembly: None,
+                    kit_item: None,
+                });
+            }
+        }
+
+        let now = Utc::now();
+
+        // 3. Generate assembly number (KIT-YYYYMMDD-NNN)
+        let date_str = now.format("%Y%m%d").to_string();
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM kit_assemblies WHERE assembly_number LIKE $1"#,
+            format!("KIT-{}-%", date_str)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let assembly_number = format!("KIT-{}-{:03}", date_str, count + 1);
+
+        // 4. Consume components, costing them and tracking the earliest expiry
+        //    (a kit with fresh cabbage in it keeps only as long as the cabbage)
+        let mut total_cost = BigDecimal::from(0);
+        let mut uncosted = Vec::new();
+        let mut expiry_date: Option<NaiveDate> = None;
+
+        for component in &components {
+            let needed = &component.quantity * &input.quantity;
+
+            sqlx::query!(
+                "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+                needed,
+                now,
+                component.component_inventory_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let draws = lots::consume(&mut tx, component.component_inventory_id, &needed).await?;
+            for draw in &draws {
+                if let Some(date) = draw.expiry_date
+                    && expiry_date.is_none_or(|earliest| date < earliest)
+                {
+                    expiry_date = Some(date);
+                }
+            }
+
+            match &component.cost_per_unit {
+                Some(cost) => total_cost += &needed * cost,
+                None => uncosted.push(component.name.clone()),
+            }
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_logs (
+                    inventory_id, movement_type, quantity, unit_cost, reason, batch_number, created_at
+                ) VALUES ($1, 'kit_component', $2, $3, $4, $5, $6)
+                "#,
+                component.component_inventory_id,
+                -needed.clone(), // Negative because it's consumption
+                component.cost_per_unit,
+                format!("Assembled into {} x {}", input.quantity, kit.name),
+                assembly_number,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let unit_cost =
+            (&total_cost / &input.quantity).with_scale_round(4, bigdecimal::RoundingMode::HalfUp);
+
+        // 5. Record the assembly and add the kits to stock as a lot
+        let assembly = sqlx::query_as!(
+            KitAssembly,
+            r#"
+            INSERT INTO kit_assemblies (
+                assembly_number, kit_inventory_id, quantity, unit_cost, total_cost,
+                notes, assembled_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING
+                id, assembly_number, kit_inventory_id, quantity, unit_cost, total_cost,
+                notes, assembled_at, created_at
+            "#,
+            assembly_number,
+            input.kit_inventory_id,
+            input.quantity,
+            unit_cost,
+            total_cost,
+            input.notes,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_lots (
+                inventory_id, lot_number, source_type, initial_quantity, remaining_quantity,
+                unit_cost, received_date, expiry_date
+            ) VALUES ($1, $2, 'assembly', $3, $3, $4, $5, $6)
+            "#,
+            input.kit_inventory_id,
+            assembly_number,
+            input.quantity,
+            unit_cost,
+            now,
+            expiry_date
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let kit_item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            UPDATE inventory
+            SET
+                current_stock = current_stock + $1,
+                cost_per_unit = $2,
+                updated_at = $3
+            WHERE id = $4
+            RETURNING
+                id,
+                name,
+                category,
+                unit,
+                current_stock as "current_stock!: BigDecimal",
+                reserved_stock as "reserved_stock!: BigDecimal",
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point as "reorder_point!: BigDecimal",
+                cost_per_unit as "cost_per_unit?: BigDecimal",
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            "#,
+            input.quantity,
+            unit_cost,
+            now,
+            input.kit_inventory_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_logs (
+                inventory_id, movement_type, quantity, unit_cost, reason, batch_number,
+                expiry_date, created_at
+            ) VALUES ($1, 'kit_assembly', $2, $3, $4, $5, $6, $7)
+            "#,
+            input.kit_inventory_id,
+            input.quantity,
+            unit_cost,
+            input
+                .notes
+                .clone()
+                .unwrap_or_else(|| format!("Assembled {} x {}", input.quantity, kit.name)),
+            assembly_number,
+            expiry_date,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let mut message = format!(
+            "Assembled {} x {} ({}) at {} each",
+            assembly.quantity, kit.name, assembly.assembly_number, assembly.unit_cost
+        );
+        if !uncosted.is_empty() {
+            message.push_str(&format!("; no cost on record for {}", uncosted.join(", ")));
+        }
+
+        Ok(AssembleKitsResult {
+            success: true,
+            message,
+            error: None,
+            assembly: Some(assembly),
+            kit_item: Some(kit_item),
+        })
+    }
+
+    /// Discontinue a product (and its packaged SKUs): take it off the catalog,
+    /// block new batches, deactivate its recipes and cancel their planned
+    /// batches. Remaining stock can still be sold; the report tracks sell-through
+    /// and flags kits and upcoming workshops that still use it.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn discontinue_product(
+        &self,
+        ctx: &Context<'_>,
+        input: DiscontinueProductInput,
+    ) -> Result<DiscontinueProductResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let product = sqlx::query!(
+            "SELECT name, is_active, discontinued_at FROM inventory WHERE id = $1 FOR UPDATE",
+            input.inventory_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(product) = product else {
+            return Ok(DiscontinueProductResult {
+                success: false,
+                message: "Product not found".to_string(),
+                error: Some(MutationError::not_found("Product")),
+                report: None,
+            });
+        };
+
+        if !product.is_active {
+            return Ok(DiscontinueProductResult {
+                success: false,
+                message: format!("{} is inactive", product.name),
+                error: Some(MutationError::invalid()),
+                report: None,
+            });
+        }
+
+        if product.discontinued_at.is_some() {
+            return Ok(DiscontinueProductResult {
+                success: false,
+                message: format!("{} is already discontinued", product.name),
+                error: Some(MutationError::invalid()),
+                report: None,
+            });
+        }
+
+        // The product and its packaged SKUs
+        let products = sqlx::query_scalar!(
+            r#"
+            UPDATE inventory
+            SET discontinued_at = NOW(), discontinued_reason = $2, updated_at = NOW()
+            WHERE discontinued_at IS NULL
+                AND (id = $1
+                    OR id IN (SELECT packaged_inventory_id FROM pack_sizes WHERE bulk_inventory_id = $1))
+            RETURNING id
+            "#,
+            input.inventory_id,
+            input.reason
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let cancelled_plans = sqlx::query!(
+            r#"
+            UPDATE planned_batches
+            SET status = 'cancelled', updated_at = NOW()
+            WHERE status = 'planned'
+                AND recipe_template_id IN (
+                    SELECT id FROM recipe_templates WHERE product_inventory_id = ANY($1)
+                )
+            "#,
+            &products
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let deactivated_recipes = if input.deactivate_recipes.unwrap_or(true) {
+            sqlx::query!(
+                r#"
+                UPDATE recipe_templates
+                SET is_active = false, updated_at = NOW()
+                WHERE product_inventory_id = ANY($1) AND is_active = true
+                "#,
+                &products
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+        } else {
+            0
+        };
+
+        tx.commit().await?;
+
+        let report = discontinuation::report(pool, input.inventory_id).await?;
+
+        let mut message = format!(
+            "Discontinued {}: {} recipe(s) deactivated, {} planned batch(es) cancelled",
+            product.name, deactivated_recipes, cancelled_plans
+        );
+        if let Some(report) = &report {
+            message.push_str(&format!(
+                ", {} {} left to sell through",
+                report.current_stock, report.unit
+            ));
+            if !report.kits.is_empty() {
+                message.push_str(&format!(". Still in kits: {}", report.kits.join(", ")));
+            }
+            if !report.workshops.is_empty() {
+                message.push_str(&format!(
+                    ". Still in workshop kits: {}",
+                    report.workshops.join(", ")
+                ));
+            }
+            if !report.orders.is_empty() {
+                message.push_str(&format!(". On open orders: {}", report.orders.join(", ")));
+            }
+        }
+
+        Ok(DiscontinueProductResult {
+            success: true,
+            message,
+            error: None,
+            report,
+        })
+    }
+
+    /// Bring a discontinued product (and its packaged SKUs) back to the
+    /// catalog. Recipes deactivated with it stay inactive until updated.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn reinstate_product(
+        &self,
+        ctx: &Context<'_>,
+        input: ReinstateProductInput,
+    ) -> Result<DiscontinueProductResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let reinstated = sqlx::query_scalar!(
+            r#"
+            UPDATE inventory
+            SET discontinued_at = NULL, discontinued_reason = NULL, updated_at = NOW()
+            WHERE discontinued_at IS NOT NULL
+                AND (id = $1
+                    OR id IN (SELECT packaged_inventory_id FROM pack_sizes WHERE bulk_inventory_id = $1))
+            RETURNING name
+            "#,
+            input.inventory_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        if reinstated.is_empty() {
+            return Ok(DiscontinueProductResult {
+                success: false,
+                message: "Product not found or is not discontinued".to_string(),
+                error: Some(MutationError::not_found("Product")),
+                report: None,
+            });
+        }
+
+        Ok(DiscontinueProductResult {
+            success: true,
+            message: format!("Reinstated {}", reinstated.join(", ")),
+            error: None,
+            report: None,
+        })
+    }
+
+    /// Add or replace the translation of a product for one locale
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn upsert_product_translation(
+        &self,
+        ctx: &Context<'_>,
+        input: UpsertProductTranslationInput,
+    ) -> Result<ProductTranslationResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let locale = input.locale.trim();
+        let valid_locale = match locale.split_once('-') {
+            Some((lang, region)) => {
+                lang.len() == 2
+                    && lang.chars().all(|c| c.is_ascii_lowercase())
+                    && region.len() == 2
+                    && region.chars().all(|c| c.is_ascii_uppercase())
+            }
+            None => locale.len() == 2 && locale.chars().all(|c| c.is_ascii_lowercase()),
+        };
+
+        if !valid_locale {
+            return Ok(ProductTranslationResult {
+                success: false,
+                message: "Locale must look like 'es' or 'es-MX'".to_string(),
+                error: Some(MutationError::invalid()),
+                translation: None,
+            });
+        }
+
+        if input.name.trim().is_empty() {
+            return Ok(ProductTranslationResult {
+                success: false,
+                message: "Translated name is required".to_string(),
+                error: Some(MutationError::invalid()),
+                translation: None,
+            });
+        }
+
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1) as \"exists!\"",
+            input.inventory_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if !exists {
+            return Ok(ProductTranslationResult {
+                success: false,
+                message: "Inventory item not found".to_string(),
+                error: Some(MutationError::not_found("Inventory item")),
+                translation: None,
+            });
+        }
+
+        let translation = sqlx::query_as!(
+            ProductTranslation,
+            r#"
+            INSERT INTO product_translations (inventory_id, locale, name, description, label_text)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (inventory_id, locale) DO UPDATE
+            SET name = EXCLUDED.name,
+                description = EXCLUDED.description,
+                label_text = EXCLUDED.label_text,
+                updated_at = NOW()
+            RETURNING id, inventory_id, locale, name, description, label_text, created_at, updated_at
+            "#,
+            input.inventory_id,
+            locale,
+            input.name.trim(),
+            input.description,
+            input.label_text
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(ProductTranslationResult {
+            success: true,
+            message: format!(
+                "Saved {} translation '{}'",
+                translation.locale, translation.name
+            ),
+            error: None,
+            translation: Some(translation),
+        })
+    }
+
+    /// Delete a product translation
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn delete_product_translation(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteProductTranslationInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let deleted = sqlx::query!(
+            "DELETE FROM product_translations WHERE id = $1 RETURNING locale",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(deleted) = deleted else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Translation not found".to_string(),
+                error: Some(MutationError::not_found("Translation")),
+            });
+        };
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted {} translation", deleted.locale),
+            error: None,
+        })
+    }
+
+    /// Create a new supplier
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_supplier(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateSupplierInput,
+    ) -> Result<SupplierResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Check if name already exists
+        let existing = sqlx::query!("SELECT id FROM suppliers WHERE name = $1", input.name)
+            .fetch_optional(pool)
+            .await?;
+
+        if existing.is_some() {
+            return Ok(SupplierResult {
+                success: false,
+                message: format!("A supplier with the name '{}' already exists", input.name),
+                error: Some(MutationError::name_conflict("Supplier", &input.name)),
+                supplier: None,
+            });
+        }
+
+        let now = Utc::now();
+
+        // Create the supplier
+        let supplier = sqlx::query_as!(
+            Supplier,
+            r#"
+            INSERT INTO suppliers (
+                name, contact_email, contact_phone, street_address, city, state, zip_code, country,
+                latitude, longitude, notes, created_at, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $12)
+            RETURNING
+                id,
+                name,
+                contact_email,
+                contact_phone,
+                street_address,
+                city,
+                state,
+                zip_code,
+                country,
+                latitude as "latitude?: BigDecimal",
+                longitude as "longitude?: BigDecimal",
+                notes,
+                is_active,
+                created_at,
+                updated_at
+            "#,
+            input.name,
+            input.contact_email,
+            input.contact_phone,
+            input.street_address,
+            input.city,
+            input.state,
+            input.zip_code,
+            input.country,
+            input.latitude,
+            input.longitude,
+            input.notes,
+            now
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(SupplierResult {
+            success: true,
+            message: format!("Successfully created '{}'", supplier.name),
+            error: None,
+            supplier: Some(supplier),
+        })
+    }
+
+    /// Update an existing supplier
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_supplier(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateSupplierInput,
+    ) -> Result<SupplierResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Check if supplier exists
+        let existing = sqlx::query!("SELECT name FROM suppliers WHERE id = $1", input.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if existing.is_none() {
+            return Ok(SupplierResult {
+                success: false,
+                message: "Supplier not found".to_string(),
+                error: Some(MutationError::not_found("Supplier")),
+                supplier: None,
+            });
+        }
+
+        if input.is_active == Some(false) {
+            return Ok(SupplierResult {
+                success: false,
+                message: "Use deactivateSupplier to deactivate a supplier".to_string(),
+                error: Some(MutationError::invalid()),
+                supplier: None,
+            });
+        }
+
+        // Check if new name conflicts with existing suppliers (if name is being changed)
+        if let Some(ref new_name) = input.name {
+            let name_conflict = sqlx::query!(
+                "SELECT id FROM suppliers WHERE name = $1 AND id != $2",
+                new_name,
+                input.id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if name_conflict.is_some() {
+                return Ok(SupplierResult {
+                    success: false,
+                    message: format!("A supplier with the name '{}' already exists", new_name),
+                    error: Some(MutationError::name_conflict("Supplier", new_name)),
+                    supplier: None,
+                });
+            }
+        }
+
+        let now = Utc::now();
+
+        // Build update query dynamically based on provided fields
+        let supplier = sqlx::query_as!(
+            Supplier,
+            r#"
+            UPDATE suppliers
+            SET
+                name = COALESCE($2, name),
+                contact_email = COALESCE($3, contact_email),
+                contact_phone = COALESCE($4, contact_phone),
+                street_address = COALESCE($5, street_address),
+                city = COALESCE($6, city),
+                state = COALESCE($7, state),
+                zip_code = COALESCE($8, zip_code),
+                country = COALESCE($9, country),
+                latitude = COALESCE($10, latitude),
+                longitude = COALESCE($11, longitude),
+                notes = COALESCE($12, notes),
+                is_active = is_active OR COALESCE($14, false),
+                updated_at = $13
+            WHERE id = $1
+            RETURNING
+                id,
+                name,
+                contact_email,
+                contact_phone,
+                street_address,
+                city,
+                state,
+                zip_code,
+                country,
+                latitude as "latitude?: BigDecimal",
+                longitude as "longitude?: BigDecimal",
+                notes,
+                is_active,
+                created_at,
+                updated_at
+            "#,
+            input.id,
+            input.name,
+            input.contact_email,
+            input.contact_phone,
+            input.street_address,
+            input.city,
+            input.state,
+            input.zip_code,
+            input.country,
+            input.latitude,
+            input.longitude,
+            input.notes,
+            now,
+            input.is_active
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(SupplierResult {
+            success: true,
+            message: format!("Successfully updated '{}'", supplier.name),
+            error: None,
+            supplier: Some(supplier),
+        })
+    }
+
+    /// Deactivate a supplier: it stays on past orders and receipts but can't
+    /// take new orders or be an item's default. Refused while it has open
+    /// purchase orders, or items defaulting to it without `reassignTo`.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn deactivate_supplier(
+        &self,
+        ctx: &Context<'_>,
+        input: RemoveSupplierInput,
+    ) -> Result<SupplierResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT name, is_active FROM suppliers WHERE id = $1 FOR UPDATE",
+            input.supplier_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(SupplierResult::not_found("Supplier"));
+        };
+
+        if !existing.is_active {
+            return Ok(SupplierResult::failed(&format!(
+                "{} is already inactive",
+                existing.name
+            )));
+        }
+
+        let open_orders = sqlx::query_scalar!(
+            "SELECT COUNT(*) AS \"count!\" FROM purchase_orders WHERE supplier_id = $1 AND status IN ('open', 'pending_approval')",
+            input.supplier_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if open_orders > 0 {
+            return Ok(SupplierResult::failed(&format!(
+                "{} has {} open or pending purchase order(s); receive or cancel them first",
+                existing.name, open_orders
+            )));
+        }
+
+        let moved = match purchasing::reassign_default_items(
+            &mut tx,
+            input.supplier_id,
+            &existing.name,
+            input.reassign_to,
+        )
+        .await?
+        {
+            Ok(moved) => moved,
+            Err(rejection) => return Ok(SupplierResult::rejected(rejection)),
+        };
+
+        let supplier = sqlx::query_as!(
+            Supplier,
+            r#"
+            UPDATE suppliers
+            SET is_active = false, updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id,
+                name,
+                contact_email,
+                contact_phone,
+                street_address,
+                city,
+                state,
+                zip_code,
+                country,
+                latitude as "latitude?: BigDecimal",
+                longitude as "longitude?: BigDecimal",
+                notes,
+                is_active,
+                created_at,
+                updated_at
+            "#,
+            input.supplier_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(SupplierResult {
+            success: true,
+            message: format!("Deactivated '{}'{}", supplier.name, moved_items(moved)),
+            error: None,
+            supplier: Some(supplier),
+        })
+    }
+
+    /// Delete a supplier that was never ordered from or received from
+    /// (otherwise deactivate it). Its catalog goes with it. Refused while
+    /// items default to it, unless `reassignTo` moves them.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_supplier(
+        &self,
+        ctx: &Context<'_>,
+        input: RemoveSupplierInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let name = sqlx::query_scalar!(
+            "SELECT name FROM suppliers WHERE id = $1 FOR UPDATE",
+            input.supplier_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(name) = name else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Supplier not found".to_string(),
+                error: Some(MutationError::not_found("Supplier")),
+            });
+        };
+
+        let history = sqlx::query!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM purchase_orders WHERE supplier_id = $1) AS "orders!",
+                (SELECT COUNT(*) FROM inventory_lots WHERE supplier_id = $1) AS "lots!"
+            "#,
+            input.supplier_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if history.orders > 0 || history.lots > 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: format!(
+                    "Cannot delete '{}': it has purchase history ({} purchase order(s), {} received lot(s)); deactivate it instead",
+                    name, history.orders, history.lots
+                ),
+                error: Some(MutationError::invalid()),
+            });
+        }
+
+        let moved = match purchasing::reassign_default_items(
+            &mut tx,
+            input.supplier_id,
+            &name,
+            input.reassign_to,
+        )
+        .await?
+        {
+            Ok(moved) => moved,
+            Err(rejection) => {
+                return Ok(DeleteResult {
+                    success: false,
+                    message: rejection.message,
+                    error: Some(rejection.error),
+                });
+            }
+        };
+
+        sqlx::query!("DELETE FROM suppliers WHERE id = $1", input.supplier_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted '{}'{}", name, moved_items(moved)),
+            error: None,
+        })
+    }
+
+    /// Create a new recipe template
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_recipe_template(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateRecipeTemplateInput,
+    ) -> Result<RecipeTemplateResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Validate product exists and is active (if product_inventory_id is provided)
+        if let Some(product_id) = input.product_inventory_id {
+            let product = sqlx::query!(
+                r#"SELECT id, name FROM inventory WHERE id = $1 AND is_active = true"#,
+                product_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if product.is_none() {
+                return Ok(RecipeTemplateResult {
+                    success: false,
+                    message: "Product not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Product")),
+                    conflict: false,
+                    recipe: None,
+                });
+            }
+        }
+
+        if let Err(message) = readings::validate_thresholds(
+            input.min_temperature.as_ref(),
+            input.max_temperature.as_ref(),
+            input.target_ph_curve.as_ref(),
+        ) {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                conflict: false,
+                recipe: None,
+            });
+        }
+
+        if input.ingredient_template.is_some() {
+            versions::deprecated_input(ctx, "CreateRecipeTemplateInput.ingredientTemplate")?;
+        }
+
+        let ingredients = match (&input.ingredients, &input.ingredient_template) {
+            (Some(ingredients), _) => Some(ingredients.clone()),
+            (None, Some(template)) => match recipes::parse_legacy(template) {
+                Ok(ingredients) => Some(ingredients),
+                Err(message) => {
+                    return Ok(RecipeTemplateResult {
+                        success: false,
+                        message,
+                        error: Some(MutationError::invalid()),
+                        conflict: false,
+                        recipe: None,
+                    });
+                }
+            },
+            (None, None) => None,
+        };
+
+        if let Some(ingredients) = &ingredients
+            && let Err(message) = recipes::validate(&mut tx, ingredients).await?
+        {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                conflict: false,
+                recipe: None,
+            });
+        }
+
+        // Insert new recipe template
+        let mut recipe = sqlx::query_as!(
+            RecipeTemplate,
+            r#"
+            INSERT INTO recipe_templates (
+                product_inventory_id, template_name, description,
+                default_batch_size, default_unit, estimated_duration_hours,
+                ingredient_template, instructions, min_temperature, max_temperature,
+                target_ph_curve, is_active
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, true)
+            RETURNING
+                id, product_inventory_id, template_name, description,
+                default_batch_size, default_unit, estimated_duration_hours,
+                ingredient_template, instructions, min_temperature, max_temperature,
+                target_ph_curve, is_active as "is_active!", version, created_at, updated_at
+            "#,
+            input.product_inventory_id,
+            input.template_name,
+            input.description,
+            input.default_batch_size,
+            input.default_unit,
+            input.estimated_duration_hours,
+            input.ingredient_template,
+            input.instructions,
+            input.min_temperature,
+            input.max_temperature,
+            input.target_ph_curve
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(ingredients) = &ingredients {
+            recipe.ingredient_template = recipes::replace(&mut tx, recipe.id, ingredients).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(RecipeTemplateResult {
+            success: true,
+            message: format!("Successfully created recipe '{}'", recipe.template_name),
+            error: None,
+            conflict: false,
+            recipe: Some(recipe),
+        })
+    }
+
+    /// Update an existing recipe template
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn update_recipe_template(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateRecipeTemplateInput,
+    ) -> Result<RecipeTemplateResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Check if recipe exists, and lock it so a concurrent edit waits for this one
+        let existing = sqlx::query!(
+            "SELECT template_name, version, updated_at FROM recipe_templates WHERE id = $1 FOR UPDATE",
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message: "Recipe template not found".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                conflict: false,
+                recipe: None,
+            });
+        };
+
+        if let Some(rejection) = stale_edit(
+            &mut tx,
+            input.id,
+            &existing.template_name,
+            existing.version,
+            existing.updated_at,
+            input.expected_version,
+        )
+        .await?
+        {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                conflict: true,
+                recipe: None,
+            });
+        }
+
+        // If updating product, validate it exists and is active
+        if let Some(product_id) = input.product_inventory_id {
+            let product = sqlx::query!(
+                r#"SELECT id FROM inventory WHERE id = $1 AND is_active = true"#,
+                product_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if product.is_none() {
+                return Ok(RecipeTemplateResult {
+                    success: false,
+                    message: "Product not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Product")),
+                    conflict: false,
+                    recipe: None,
+                });
+            }
+        }
+
+        if let Err(message) = readings::validate_thresholds(
+            input.min_temperature.as_ref(),
+            input.max_temperature.as_ref(),
+            input.target_ph_curve.as_ref(),
+        ) {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                conflict: false,
+                recipe: None,
+            });
+        }
+
+        if input.ingredient_template.is_some() {
+            versions::deprecated_input(ctx, "UpdateRecipeTemplateInput.ingredientTemplate")?;
+        }
+
+        let ingredients = match (&input.ingredients, &input.ingredient_template) {
+            (Some(ingredients), _) => Some(ingredients.clone()),
+            (None, Some(template)) => match recipes::parse_legacy(template) {
+                Ok(ingredients) => Some(ingredients),
+                Err(message) => {
+                    return Ok(RecipeTemplateResult {
+                        success: false,
+                        message,
+                        error: Some(MutationError::invalid()),
+                        conflict: false,
+                        recipe: None,
+                    });
+                }
+            },
+            (None, None) => None,
+        };
+
+        if let Some(ingredients) = &ingredients
+            && let Err(message) = recipes::validate(&mut tx, ingredients).await?
+        {
+            return Ok(RecipeTemplateResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                conflict: false,
+                recipe: None,
+            });
+        }
+
+        let now = Utc::now();
+
+        // Update recipe template
+        let mut recipe = sqlx::query_as!(
+            RecipeTemplate,
+            r#"
+            UPDATE recipe_templates
+            SET
+                product_inventory_id = COALESCE($2, product_inventory_id),
+                template_name = COALESCE($3, template_name),
+                description = COALESCE($4, description),
+                default_batch_size = COALESCE($5, default_batch_size),
+                default_unit = COALESCE($6, default_unit),
+                estimated_duration_hours = COALESCE($7, estimated_duration_hours),
+                ingredient_template = COALESCE($8, ingredient_template),
+                instructions = COALESCE($9, instructions),
+                min_temperature = COALESCE($10, min_temperature),
+                max_temperature = COALESCE($11, max_temperature),
+                target_ph_curve = COALESCE($12, target_ph_curve),
+                version = version + 1,
+                updated_at = $13
+            WHERE id = $1
+            RETURNING
+                id, product_inventory_id, template_name, description,
+                default_batch_size, default_unit, estimated_duration_hours,
+                ingredient_template, instructions, min_temperature, max_temperature,
+                target_ph_curve, is_active as "is_active!", version, created_at, updated_at
+            "#,
+            input.id,
+            input.product_inventory_id,
+            input.template_name,
+            input.description,
+            input.default_batch_size,
+            input.default_unit,
+            input.estimated_duration_hours,
+            input.ingredient_template,
+            input.instructions,
+            input.min_temperature,
+            input.max_temperature,
+            input.target_ph_curve,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(ingredients) = &ingredients {
+            recipe.ingredient_template = recipes::replace(&mut tx, recipe.id, ingredients).await?;
+        }
+
+        tx.commit().await?;
+
+        if ingredients.is_some() || input.default_batch_size.is_some() {
+            cost_watch::recheck(pool, ctx.data::<Notifier>()?).await;
+        }
+
+        Ok(RecipeTemplateResult {
+            success: true,
+            message: format!("Successfully updated recipe '{}'", recipe.template_name),
+            error: None,
+            conflict: false,
+            recipe: Some(recipe),
+        })
+    }
+
+    /// Set a recipe's cost limits. Whenever ingredient costs change, recipes
+    /// that newly cross a limit raise a `recipe_cost` alert.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_recipe_cost_watch(
+        &self,
+        ctx: &Context<'_>,
+        input: SetRecipeCostWatchInput,
+    ) -> Result<RecipeCostWatchResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if let Err(message) = cost_watch::validate_limits(
+            input.max_unit_cost.as_ref(),
+            input.min_margin_percent.as_ref(),
+        ) {
+            return Ok(RecipeCostWatchResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                status: None,
+            });
+        }
+
+        if input
+            .sale_price
+            .as_ref()
+            .is_some_and(|price| *price <= BigDecimal::from(0))
+        {
+            return Ok(RecipeCostWatchResult {
+                success: false,
+                message: "Sale price must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                status: None,
+            });
+        }
+
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM recipe_templates WHERE id = $1 AND is_active = true) AS \"exists!\"",
+            input.recipe_template_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if !exists {
+            return Ok(RecipeCostWatchResult {
+                success: false,
+                message: "Recipe template not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                status: None,
+            });
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recipe_cost_watches (
+                recipe_template_id, max_unit_cost, min_margin_percent, sale_price
+            ) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (recipe_template_id) DO UPDATE SET
+                max_unit_cost = EXCLUDED.max_unit_cost,
+                min_margin_percent = EXCLUDED.min_margin_percent,
+                sale_price = EXCLUDED.sale_price,
+                updated_at = NOW()
+            "#,
+            input.recipe_template_id,
+            input.max_unit_cost,
+            input.min_margin_percent,
+            input.sale_price
+        )
+        .execute(pool)
+        .await?;
+
+        cost_watch::recheck(pool, ctx.data::<Notifier>()?).await;
+
+        let status = cost_watch::evaluate(pool, Some(input.recipe_template_id))
+            .await?
+            .into_iter()
+            .next();
+
+        Ok(RecipeCostWatchResult {
+            success: true,
+            message: match &status {
+                Some(status) if status.breached => format!(
+                    "Watching '{}'; already over its limits: {}",
+                    status.template_name,
+                    status.reasons.join("; ")
+                ),
+                Some(status) => format!("Watching '{}'", status.template_name),
+                None => "Watching recipe".to_string(),
+            },
+            error: None,
+            status,
+        })
+    }
+
+    /// Stop watching a recipe's costs
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_recipe_cost_watch(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteRecipeCostWatchInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let result = sqlx::query!(
+            "DELETE FROM recipe_cost_watches WHERE recipe_template_id = $1",
+            input.recipe_template_id
+        )
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Recipe has no cost watch".to_string(),
+                error: Some(MutationError::invalid()),
+            });
+        }
+
+        Ok(DeleteResult {
+            success: true,
+            message: "Cost watch removed".to_string(),
+            error: None,
+        })
+    }
+
+    /// Add an overhead pool to spread over production costs
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_overhead_pool(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateOverheadPoolInput,
+    ) -> Result<OverheadPoolResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if let Err(message) = validate_overhead_pool(
+            &input.name,
+            &input.monthly_amount,
+            &input.allocation_basis,
+            input.expected_monthly_volume.as_ref(),
+        ) {
+            return Ok(OverheadPoolResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                overhead_pool: None,
+            });
+        }
+
+        let overhead_pool = sqlx::query_as!(
+            OverheadPool,
+            r#"
+            INSERT INTO overhead_pools
+                (name, monthly_amount, allocation_basis, expected_monthly_volume, notes)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING
+                id, name, monthly_amount, allocation_basis, expected_monthly_volume,
+                is_active, notes, created_at, updated_at
+            "#,
+            input.name.trim(),
+            input.monthly_amount,
+            input.allocation_basis,
+            input.expected_monthly_volume,
+            input.notes
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match overhead_pool {
+            Some(overhead_pool) => Ok(OverheadPoolResult {
+                success: true,
+                message: format!("Added overhead pool '{}'", overhead_pool.name),
+                error: None,
+                overhead_pool: Some(overhead_pool),
+            }),
+            None => Ok(OverheadPoolResult {
+                success: false,
+                message: format!(
+                    "An overhead pool named '{}' already exists",
+                    input.name.trim()
+                ),
+                error: Some(MutationError::name_conflict(
+                    "Overhead pool",
+                    input.name.trim(),
+                )),
+                overhead_pool: None,
+            }),
+        }
+    }
+
+    /// Change an overhead pool's amount, basis or volume, or retire it
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_overhead_pool(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateOverheadPoolInput,
+    ) -> Result<OverheadPoolResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT name, monthly_amount, allocation_basis, expected_monthly_volume, is_active, notes
+            FROM overhead_pools
+            WHERE id = $1
+            "#,
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(OverheadPoolResult {
+                success: false,
+                message: "Overhead pool not found".to_string(),
+                error: Some(MutationError::not_found("Overhead pool")),
+                overhead_pool: None,
+            });
+        };
+
+        let name = input.name.unwrap_or(existing.name);
+        let monthly_amount = input.monthly_amount.unwrap_or(existing.monthly_amount);
+        let allocation_basis = input.allocation_basis.unwrap_or(existing.allocation_basis);
+        let expected_monthly_volume = if input.clear_expected_monthly_volume.unwrap_or(false) {
+            None
+        } else {
+            input
+                .expected_monthly_volume
+                .or(existing.expected_monthly_volume)
+        };
+
+        if let Err(message) = validate_overhead_pool(
+            &name,
+            &monthly_amount,
+            &allocation_basis,
+            expected_monthly_volume.as_ref(),
+        ) {
+            return Ok(OverheadPoolResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                overhead_pool: None,
+            });
+        }
+
+        let overhead_pool = sqlx::query_as!(
+            OverheadPool,
+            r#"
+            UPDATE overhead_pools
+            SET name = $2, monthly_amount = $3, allocation_basis = $4,
+                expected_monthly_volume = $5, is_active = $6, notes = $7, updated_at = NOW()
+            WHERE id = $1
+                AND NOT EXISTS (SELECT 1 FROM overhead_pools WHERE name = $2 AND id <> $1)
+            RETURNING
+                id, name, monthly_amount, allocation_basis, expected_monthly_volume,
+                is_active, notes, created_at, updated_at
+            "#,
+            input.id,
+            name.trim(),
+            monthly_amount,
+            allocation_basis,
+            expected_monthly_volume,
+            input.is_active.unwrap_or(existing.is_active),
+            input.notes.or(existing.notes)
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match overhead_pool {
+            Some(overhead_pool) => Ok(OverheadPoolResult {
+                success: true,
+                message: format!("Updated overhead pool '{}'", overhead_pool.name),
+                error: None,
+                overhead_pool: Some(overhead_pool),
+            }),
+            None => Ok(OverheadPoolResult {
+                success: false,
+                message: format!("An overhead pool named '{}' already exists", name.trim()),
+                error: Some(MutationError::name_conflict("Overhead pool", name.trim())),
+                overhead_pool: None,
+            }),
+        }
+    }
+
+    /// Delete a recipe template (soft delete by setting is_active to false)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn delete_recipe_template(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteRecipeTemplateInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Check if recipe exists
+        let existing = sqlx::query!(
+            r#"SELECT template_name FROM recipe_templates WHERE id = $1"#,
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(recipe) = existing else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Recipe template not found".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+            });
+        };
+
+        // Check if any active production batches reference this recipe
+        let active_batches = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM production_batches
+            WHERE recipe_template_id = $1 AND status = 'in_progress'
+            "#,
+            input.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if active_batches.count.unwrap_or(0) > 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: format!(
+                    "Cannot delete recipe '{}': {} active production batch(es) reference it",
+                    recipe.template_name,
+                    active_batches.count.unwrap_or(0)
+                ),
+                error: Some(MutationError::invalid()),
+            });
+        }
+
+        // Soft delete by setting is_active to false
+        sqlx::query!(
+            r#"UPDATE recipe_templates SET is_active = false WHERE id = $1"#,
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted recipe '{}'", recipe.template_name),
+            error: None,
+        })
+    }
+
+    /// Define a recipe's aging/maturation stages, replacing any existing ones.
+    /// Batches already in progress keep the stages they have entered.
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn define_recipe_stages(
+        &self,
+        ctx: &Context<'_>,
+        input: DefineRecipeStagesInput,
+    ) -> Result<RecipeStagesResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let recipe = sqlx::query!(
+            "SELECT template_name FROM recipe_templates WHERE id = $1",
+            input.recipe_template_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(recipe) = recipe else {
+            return Ok(RecipeStagesResult {
+                success: false,
+                message: "Recipe template not found".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                stages: vec![],
+            });
+        };
+
+        if input
+            .stages
+            .iter()
+            .any(|stage| stage.name.trim().is_empty())
+        {
+            return Ok(RecipeStagesResult {
+                success: false,
+                message: "Every stage needs a name".to_string(),
+                error: Some(MutationError::invalid()),
+                stages: vec![],
+            });
+        }
+
+        if input
+            .stages
+            .iter()
+            .any(|stage| stage.expected_days.is_some_and(|days| days < 0))
+        {
+            return Ok(RecipeStagesResult {
+                success: false,
+                message: "Expected days cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                stages: vec![],
+            });
+        }
+
+        sqlx::query!(
+            "DELETE FROM recipe_stages WHERE recipe_template_id = $1",
+            input.recipe_template_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut stages = Vec::with_capacity(input.stages.len());
+        for (position, stage) in (1..).zip(&input.stages) {
+            let stage = sqlx::query_as!(
+                RecipeStage,
+                r#"
+                INSERT INTO recipe_stages (recipe_template_id, position, name, expected_days, notes)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, recipe_template_id, position, name, expected_days, notes, created_at
+                "#,
+                input.recipe_template_id,
+                position,
+                stage.name.trim(),
+                stage.expected_days,
+                stage.notes
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            stages.push(stage);
+        }
+
+        tx.commit().await?;
+
+        Ok(RecipeStagesResult {
+            success: true,
+            message: format!(
+                "Defined {} stage(s) for '{}'",
+                stages.len(),
+                recipe.template_name
+            ),
+            error: None,
+            stages,
+        })
+    }
+
+    /// Move an in-progress batch from its current stage to the next one
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn advance_batch_stage(
+        &self,
+        ctx: &Context<'_>,
+        input: AdvanceBatchStageInput,
+    ) -> Result<BatchStageResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let batch = sqlx::query!(
+            "SELECT batch_number, status, recipe_template_id FROM production_batches WHERE id = $1 FOR UPDATE",
+            input.batch_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(batch) = batch else {
+            return Ok(BatchStageResult {
+                success: false,
+                message: "Production batch not found".to_string(),
+                error: Some(MutationError::not_found("Production batch")),
+                stage: None,
+            });
+        };
+
+        if batch.status != "in_progress" {
+            return Ok(BatchStageResult {
+                success: false,
+                message: format!("Batch {} is {}", batch.batch_number, batch.status),
+                error: Some(MutationError::invalid()),
+                stage: None,
+            });
+        }
+
+        let advanced_at = input.advanced_at.unwrap_or_else(Utc::now);
+
+        let stage = match stages::advance(
+            &mut tx,
+            input.batch_id,
+            batch.recipe_template_id,
+            advanced_at,
+            input.notes,
+        )
+        .await?
+        {
+            Advance::Entered(stage) => stage,
+            Advance::Refused(message) => {
+                return Ok(BatchStageResult {
+                    success: false,
+                    message,
+                    error: Some(MutationError::invalid()),
+                    stage: None,
+                });
+            }
+        };
+
+        tx.commit().await?;
+
+        Ok(BatchStageResult {
+            success: true,
+            message: format!(
+                "Batch {} moved to stage {}: {}",
+                batch.batch_number, stage.position, stage.name
+            ),
+            error: None,
+            stage: Some(stage),
+        })
+    }
+
+    /// Create a storage transition rule (e.g. move to cold storage after N days)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_storage_transition_rule(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateStorageTransitionRuleInput,
+    ) -> Result<StorageTransitionRuleResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.after_days < 0 {
+            return Ok(StorageTransitionRuleResult {
+                success: false,
+                message: "after_days cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                rule: None,
+            });
+        }
+
+        if input.to_location.trim().is_empty() {
+            return Ok(StorageTransitionRuleResult {
+                success: false,
+                message: "Destination location is required".to_string(),
+                error: Some(MutationError::invalid()),
+                rule: None,
+            });
+        }
+
+        let recipe = sqlx::query!(
+            "SELECT template_name FROM recipe_templates WHERE id = $1 AND is_active = true",
+            input.recipe_template_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(recipe) = recipe else {
+            return Ok(StorageTransitionRuleResult {
+                success: false,
+                message: "Recipe template not found or is inactive".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                rule: None,
+            });
+        };
+
+        let rule = sqlx::query_as!(
+            StorageTransitionRule,
+            r#"
+            INSERT INTO storage_transition_rules (
+                recipe_template_id, from_location, to_location, after_days, notes
+            ) VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id, recipe_template_id, from_location, to_location,
+                after_days, notes, created_at, updated_at
+            "#,
+            input.recipe_template_id,
+            input.from_location,
+            input.to_location,
+            input.after_days,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(StorageTransitionRuleResult {
+            success: true,
+            message: format!(
+                "Batches of '{}' will be moved to {} after {} day(s)",
+                recipe.template_name, rule.to_location, rule.after_days
+            ),
+            error: None,
+            rule: Some(rule),
+        })
+    }
+
+    /// Delete a storage transition rule (pending move tasks from it are cancelled)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn delete_storage_transition_rule(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteStorageTransitionRuleInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Cancel pending tasks before the rule reference is nulled out
+        sqlx::query!(
+            "UPDATE batch_move_tasks SET status = 'cancelled' WHERE rule_id = $1 AND status = 'pending'",
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = sqlx::query!(
+            "DELETE FROM storage_transition_rules WHERE id = $1",
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Storage transition rule not found".to_string(),
+                error: Some(MutationError::not_found("Storage transition rule")),
+            });
+        }
+
+        tx.commit().await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: "Successfully deleted storage transition rule".to_string(),
+            error: None,
+        })
+    }
+
+    /// Add a recurring chore to a recipe template. In-progress batches made
+    /// from the recipe get the chore scheduled straight away.
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_recipe_task(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateRecipeTaskInput,
+    ) -> Result<RecipeTaskResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.task_type.trim().is_empty() || input.description.trim().is_empty() {
+            return Ok(RecipeTaskResult {
+                success: false,
+                message: "Task type and description are required".to_string(),
+                error: Some(MutationError::invalid()),
+                task: None,
+            });
+        }
+
+        if input.start_day < 0 {
+            return Ok(RecipeTaskResult {
+                success: false,
+                message: "Start day cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                task: None,
+            });
+        }
+
+        match (input.repeat_every_days, input.repeat_until_day) {
+            (None, None) => {}
+            (Some(every), Some(until)) if every > 0 && until >= input.start_day => {}
+            (Some(_), Some(_)) => {
+                return Ok(RecipeTaskResult {
+                    success: false,
+                    message: "Repeat interval must be positive and the last day on or after the start day"
+                        .to_string(),
+                    error: Some(MutationError::invalid()),
+                    task: None,
+                });
+            }
+            _ => {
+                return Ok(RecipeTaskResult {
+                    success: false,
+                    message: "Repeating tasks need both repeatEveryDays and repeatUntilDay"
+                        .to_string(),
+                    error: Some(MutationError::invalid()),
+                    task: None,
+                });
+            }
+        }
+
+        let recipe = sqlx::query!(
+            "SELECT template_name FROM recipe_templates WHERE id = $1",
+            input.recipe_template_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(recipe) = recipe else {
+            return Ok(RecipeTaskResult {
+                success: false,
+                message: "Recipe template not found".to_string(),
+                error: Some(MutationError::not_found("Recipe template")),
+                task: None,
+            });
+        };
+
+        let task = sqlx::query_as!(
+            RecipeTaskTemplate,
+            r#"
+            INSERT INTO recipe_task_templates (
+                recipe_template_id, task_type, description, start_day,
+                repeat_every_days, repeat_until_day
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id, recipe_template_id, task_type, description, start_day,
+                repeat_every_days, repeat_until_day, created_at, updated_at
+            "#,
+            input.recipe_template_id,
+            input.task_type.trim(),
+            input.description.trim(),
+            input.start_day,
+            input.repeat_every_days,
+            input.repeat_until_day
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let scheduled = tasks::generate(&mut tx, None, Some(task.id)).await?;
+
+        tx.commit().await?;
+
+        Ok(RecipeTaskResult {
+            success: true,
+            message: format!(
+                "Added '{}' to '{}'; scheduled {} task(s) on in-progress batches",
+                task.task_type, recipe.template_name, scheduled
+            ),
+            error: None,
+            task: Some(task),
+        })
+    }
+
+    /// Remove a recurring chore from a recipe (its pending tasks are cancelled)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn delete_recipe_task(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteRecipeTaskInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Cancel pending tasks before the template reference is nulled out
+        sqlx::query!(
+            "UPDATE batch_tasks SET status = 'cancelled' WHERE template_id = $1 AND status = 'pending'",
+            input.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = sqlx::query!("DELETE FROM recipe_task_templates WHERE id = $1", input.id)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Recipe task not found".to_string(),
+                error: Some(MutationError::not_found("Recipe task")),
+            });
+        }
+
+        tx.commit().await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: "Successfully deleted recipe task".to_string(),
+            error: None,
+        })
+    }
+
+    /// Mark a batch chore as done
+    #[graphql(guard = "RequireRole(auth::STAFF)")]
+    async fn complete_task(
+        &self,
+        ctx: &Context<'_>,
+        input: CompleteTaskInput,
+    ) -> Result<BatchTaskResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let status = sqlx::query_scalar!(
+            "SELECT status FROM batch_tasks WHERE id = $1",
+            input.task_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(status) = status else {
+            return Ok(BatchTaskResult {
+                success: false,
+                message: "Task not found".to_string(),
+                error: Some(MutationError::not_found("Task")),
+                task: None,
+            });
+        };
+
+        if status != "pending" {
+            return Ok(BatchTaskResult {
+                success: false,
+                message: format!("Task is already {}", status),
+                error: Some(MutationError::invalid()),
+                task: None,
+            });
+        }
+
+        let task = sqlx::query_as!(
+            BatchTask,
+            r#"
+            UPDATE batch_tasks t
+            SET status = 'completed',
+                completed_at = $2,
+                completed_by = $3,
+                notes = COALESCE($4, t.notes)
+            FROM production_batches b
+            WHERE t.id = $1 AND b.id = t.batch_id AND t.status = 'pending'
+            RETURNING
+                t.id, t.batch_id, b.batch_number, t.template_id, t.task_type, t.description,
+                t.due_date, t.status, t.completed_at, t.completed_by, t.notes, t.created_at
+            "#,
+            input.task_id,
+            input.completed_at.unwrap_or_else(Utc::now),
+            input.completed_by,
+            input.notes
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(task) = task else {
+            return Ok(BatchTaskResult {
+                success: false,
+                message: "Task was completed or cancelled in the meantime".to_string(),
+                error: Some(MutationError::invalid()),
+                task: None,
+            });
+        };
+
+        Ok(BatchTaskResult {
+            success: true,
+            message: format!(
+                "Completed '{}' on batch {}",
+                task.task_type, task.batch_number
+            ),
+            error: None,
+            task: Some(task),
+        })
+    }
+
+    /// Record that a scheduled batch move actually happened
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn complete_batch_move(
+        &self,
+        ctx: &Context<'_>,
+        input: CompleteBatchMoveInput,
+    ) -> Result<BatchMoveTaskResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let task = sqlx::query!(
+            "SELECT batch_id, to_location, status FROM batch_move_tasks WHERE id = $1",
+            input.task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(task) = task else {
+            return Ok(BatchMoveTaskResult {
+                success: false,
+                message: "Move task not found".to_string(),
+                error: Some(MutationError::not_found("Move task")),
+                task: None,
+            });
+        };
+
+        if task.status != "pending" {
+            return Ok(BatchMoveTaskResult {
+                success: false,
+                message: format!("Move task is already {}", task.status),
+                error: Some(MutationError::invalid()),
+                task: None,
+            });
+        }
+
+        let moved_at = input.moved_at.unwrap_or_else(Utc::now);
+        let to_location = input.to_location.unwrap_or(task.to_location);
+
+        let (batch_number, task) = locations::record_move(
+            &mut tx,
+            input.task_id,
+            task.batch_id,
+            &to_location,
+            moved_at,
+            input.notes,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(BatchMoveTaskResult {
+            success: true,
+            message: format!("Batch {} moved to {}", batch_number, task.to_location),
+            error: None,
+            task: Some(task),
+        })
+    }
+
+    /// Make a short-lived link (and QR code) that lets a staff member confirm
+    /// one stage transition or storage move from a phone without signing in
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_action_link(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateActionLinkInput,
+    ) -> Result<ActionLinkResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
+        let mut tx = pool.begin().await?;
+
+        let (link, token) = match action_links::create(&mut tx, &input, identity.user_id).await? {
+            Ok(created) => created,
+            Err(message) => return Ok(ActionLinkResult::failed(&message)),
+        };
+
+        tx.commit().await?;
+
+        let url = action_links::url(&token);
+        Ok(ActionLinkResult {
+            success: true,
+            message: format!(
+                "{} (link expires {})",
+                link.description,
+                link.expires_at.format("%H:%M UTC")
+            ),
+            error: None,
+            qr_svg: QrCode::encode(&url).map(|qr| qr.svg()),
+            url: Some(url),
+            link: Some(link),
+        })
+    }
+
+    /// Cancel an action link that hasn't been used yet
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn revoke_action_link(&self, ctx: &Context<'_>, id: Uuid) -> Result<ActionLinkResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let link = sqlx::query_as!(
+            ActionLink,
+            r#"
+            UPDATE action_links SET revoked_at = NOW()
+            WHERE id = $1 AND used_at IS NULL AND revoked_at IS NULL
+            RETURNING
+                id, action, batch_id, move_task_id, description, user_id, created_by,
+                expires_at, used_at, revoked_at, created_at
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match link {
+            Some(link) => Ok(ActionLinkResult {
+                success: true,
+                message: format!("Cancelled link: {}", link.description),
+                error: None,
+                link: Some(link),
+                url: None,
+                qr_svg: None,
+            }),
+            None => Ok(ActionLinkResult {
+                error: Some(MutationError::not_found("Action link")),
+                ..ActionLinkResult::failed("Action link not found, used or already cancelled")
+            }),
+        }
+    }
+
+    /// Start tracking a new product idea in the development pipeline
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_product_development(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateProductDevelopmentInput,
+    ) -> Result<ProductDevelopmentResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.name.trim().is_empty() {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: "Name is required".to_string(),
+                error: Some(MutationError::invalid()),
+                development: None,
+            });
+        }
+
+        if let Some(recipe_id) = input.recipe_template_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM recipe_templates WHERE id = $1) AS \"exists!\"",
+                recipe_id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            if !exists {
+                return Ok(ProductDevelopmentResult {
+                    success: false,
+                    message: "Recipe template not found".to_string(),
+                    error: Some(MutationError::not_found("Recipe template")),
+                    development: None,
+                });
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO product_developments (
+                name, description, recipe_template_id, target_launch_date, notes
+            ) VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            input.name.trim(),
+            input.description,
+            input.recipe_template_id,
+            input.target_launch_date,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO product_development_stages (development_id, to_stage) VALUES ($1, 'concept')",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let development = development::find(pool, id).await?;
+
+        Ok(ProductDevelopmentResult {
+            success: true,
+            message: format!("Started development of '{}'", input.name.trim()),
+            error: None,
+            development,
+        })
+    }
+
+    /// Update a product in development (recipe, launch product, label sign-off)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn update_product_development(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateProductDevelopmentInput,
+    ) -> Result<ProductDevelopmentResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!(
+            "SELECT name, stage FROM product_developments WHERE id = $1",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: "Product development not found".to_string(),
+                error: Some(MutationError::not_found("Product development")),
+                development: None,
+            });
+        };
+
+        if existing.stage == "launched" || existing.stage == "shelved" {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: format!("{} is already {}", existing.name, existing.stage),
+                error: Some(MutationError::invalid()),
+                development: None,
+            });
+        }
+
+        if input
+            .name
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: "Name cannot be empty".to_string(),
+                error: Some(MutationError::invalid()),
+                development: None,
+            });
+        }
+
+        if let Some(recipe_id) = input.recipe_template_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM recipe_templates WHERE id = $1) AS \"exists!\"",
+                recipe_id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            if !exists {
+                return Ok(ProductDevelopmentResult {
+                    success: false,
+                    message: "Recipe template not found".to_string(),
+                    error: Some(MutationError::not_found("Recipe template")),
+                    development: None,
+                });
+            }
+        }
+
+        if let Some(product_id) = input.product_inventory_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                product_id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            if !exists {
+                return Ok(ProductDevelopmentResult {
+                    success: false,
+                    message: "Product not found or is inactive".to_string(),
+                    error: Some(MutationError::not_found("Product")),
+                    development: None,
+                });
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE product_developments
+            SET
+                name = COALESCE($2, name),
+                description = COALESCE($3, description),
+                recipe_template_id = COALESCE($4, recipe_template_id),
+                product_inventory_id = COALESCE($5, product_inventory_id),
+                target_launch_date = COALESCE($6, target_launch_date),
+                label_approved_at = CASE $7::boolean
+                    WHEN true THEN COALESCE(label_approved_at, NOW())
+                    WHEN false THEN NULL
+                    ELSE label_approved_at
+                END,
+                notes = COALESCE($8, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            input.id,
+            input.name.as_deref().map(str::trim),
+            input.description,
+            input.recipe_template_id,
+            input.product_inventory_id,
+            input.target_launch_date,
+            input.label_approved,
+            input.notes
+        )
+        .execute(pool)
+        .await?;
+
+        let development = development::find(pool, input.id).await?;
+
+        Ok(ProductDevelopmentResult {
+            success: true,
+            message: format!("Updated development of '{}'", existing.name),
+            error: None,
+            development,
+        })
+    }
+
+    /// Move a product in development through its next stage gate, or shelve it
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn advance_product_development(
+        &self,
+        ctx: &Context<'_>,
+        input: AdvanceProductDevelopmentInput,
+    ) -> Result<ProductDevelopmentResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let Some(current) = development::find(pool, input.id).await? else {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: "Product development not found".to_string(),
+                error: Some(MutationError::not_found("Product development")),
+                development: None,
+            });
+        };
+
+        let shelve = input.shelve.unwrap_or(false);
+        let next_stage = if shelve {
+            (current.stage != "launched" && current.stage != "shelved").then_some("shelved")
+        } else {
+            development::next(&current.stage)
+        };
+
+        let Some(next_stage) = next_stage else {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: format!("{} is already {}", current.name, current.stage),
+                error: Some(MutationError::invalid()),
+                development: None,
+            });
+        };
+
+        // Shelving skips the gate
+        if !shelve {
+            let blockers = development::blockers(pool, &current).await?;
+            if !blockers.is_empty() {
+                return Ok(ProductDevelopmentResult {
+                    success: false,
+                    message: format!(
+                        "{} can't move to {}: {}",
+                        current.name,
+                        next_stage,
+                        blockers.join("; ")
+                    ),
+                    error: Some(MutationError::invalid()),
+                    development: None,
+                });
+            }
+        }
+
+        // Passing the costing gate records what the recipe costs to make
+        let costed_unit_cost = match (current.stage.as_str(), current.recipe_template_id) {
+            ("costing", Some(recipe_id)) if !shelve => {
+                recipes::cost(pool, recipe_id, None, "current", None)
+                    .await?
+                    .ok()
+                    .map(|cost| cost.cost_per_unit)
+            }
+            _ => None,
+        };
+
+        let mut tx = pool.begin().await?;
+
+        // Only move it if nobody else has in the meantime
+        let moved = sqlx::query!(
+            r#"
+            UPDATE product_developments
+            SET
+                stage = $2,
+                costed_unit_cost = COALESCE($3, costed_unit_cost),
+                launched_at = CASE WHEN $2::varchar = 'launched' THEN NOW() ELSE launched_at END,
+                stage_changed_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND stage = $4
+            "#,
+            input.id,
+            next_stage,
+            costed_unit_cost,
+            current.stage
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if moved == 0 {
+            return Ok(ProductDevelopmentResult {
+                success: false,
+                message: format!("{} has changed stage; try again", current.name),
+                error: Some(MutationError::invalid()),
+                development: None,
+            });
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO product_development_stages (development_id, from_stage, to_stage, notes)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            input.id,
+            current.stage,
+            next_stage,
+            input.notes
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let development = development::find(pool, input.id).await?;
+
+        Ok(ProductDevelopmentResult {
+            success: true,
+            message: if shelve {
+                format!("{} shelved", current.name)
+            } else {
+                format!("{} moved to {}", current.name, next_stage)
+            },
+            error: None,
+            development,
+        })
+    }
+
+    /// Create a storage location (walk-in cooler, dry storage, market van)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_location(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateLocationInput,
+    ) -> Result<LocationResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.name.trim().is_empty() {
+            return Ok(LocationResult {
+                success: false,
+                message: "Location name is required".to_string(),
+                error: Some(MutationError::invalid()),
+                location: None,
+            });
+        }
+
+        let existing = sqlx::query!(
+            "SELECT id FROM locations WHERE LOWER(name) = LOWER($1)",
+            input.name.trim()
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if existing.is_some() {
+            return Ok(LocationResult {
+                success: false,
+                message: format!("A location named '{}' already exists", input.name.trim()),
+                error: Some(MutationError::name_conflict("Location", input.name.trim())),
+                location: None,
+            });
+        }
+
+        let location = sqlx::query_as!(
+            Location,
+            r#"
+            INSERT INTO locations (name, location_type, description)
+            VALUES ($1, COALESCE($2, 'storage'), $3)
+            RETURNING id, name, location_type, description, is_active, created_at, updated_at
+            "#,
+            input.name.trim(),
+            input.location_type,
+            input.description
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(LocationResult {
+            success: true,
+            message: format!("Successfully created location '{}'", location.name),
+            error: None,
+            location: Some(location),
+        })
+    }
+
+    /// Update a storage location, or retire it once nothing is stored there
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_location(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateLocationInput,
+    ) -> Result<LocationResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!("SELECT id FROM locations WHERE id = $1", input.id)
+            .fetch_optional(pool)
+            .await?;
+
+        if existing.is_none() {
+            return Ok(LocationResult {
+                success: false,
+                message: "Location not found".to_string(),
+                error: Some(MutationError::not_found("Location")),
+                location: None,
+            });
+        }
+
+        if let Some(name) = &input.name {
+            let taken = sqlx::query!(
+                "SELECT id FROM locations WHERE LOWER(name) = LOWER($1) AND id <> $2",
+                name.trim(),
+                input.id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if name.trim().is_empty() || taken.is_some() {
+                return Ok(LocationResult {
+                    success: false,
+                    message: format!("A location named '{}' already exists", name.trim()),
+                    error: Some(MutationError::name_conflict("Location", name.trim())),
+                    location: None,
+                });
+            }
+        }
+
+        if input.is_active == Some(false) {
+            let in_use = sqlx::query!(
+                r#"
+                SELECT
+                    (SELECT COUNT(*) FROM inventory_lots
+                     WHERE location_id = $1 AND remaining_quantity > 0) AS "lots!",
+                    (SELECT COUNT(*) FROM production_batches
+                     WHERE storage_location_id = $1 AND status = 'in_progress') AS "batches!"
+                "#,
+                input.id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            if in_use.lots > 0 || in_use.batches > 0 {
+                return Ok(LocationResult {
+                    success: false,
+                    message: format!(
+                        "Location still holds {} open lot(s) and {} in-progress batch(es)",
+                        in_use.lots, in_use.batches
+                    ),
+                    error: Some(MutationError::invalid()),
+                    location: None,
+                });
+            }
+        }
+
+        let location = sqlx::query_as!(
+            Location,
+            r#"
+            UPDATE locations
+            SET
+                name = COALESCE($2, name),
+                location_type = COALESCE($3, location_type),
+                description = COALESCE($4, description),
+                is_active = COALESCE($5, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, name, location_type, description, is_active, created_at, updated_at
+            "#,
+            input.id,
+            input.name.as_deref().map(str::trim),
+            input.location_type,
+            input.description,
+            input.is_active
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(LocationResult {
+            success: true,
+            message: format!("Successfully updated location '{}'", location.name),
+            error: None,
+            location: Some(location),
+        })
+    }
+
+    /// Move stock of an item from one location to another
+    #[graphql(guard = "RequireRole(auth::STAFF)")]
+    async fn transfer_stock(
+        &self,
+        ctx: &Context<'_>,
+        input: TransferStockInput,
+    ) -> Result<TransferStockResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let message = match locations::transfer(&mut tx, &input).await? {
+            Ok(message) => message,
+            Err(message) => {
+                return Ok(TransferStockResult {
+                    success: false,
+                    message,
+                    error: Some(MutationError::invalid()),
+                    stock: vec![],
+                });
+            }
+        };
+
+        tx.commit().await?;
+
+        Ok(TransferStockResult {
+            success: true,
+            message,
+            error: None,
+            stock: locations::stock(pool, Some(input.inventory_id), None).await?,
+        })
+    }
+
+    /// Create a new vessel (crock, fermenter, brite tank)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn create_vessel(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateVesselInput,
+    ) -> Result<VesselResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.name.trim().is_empty() {
+            return Ok(VesselResult {
+                success: false,
+                message: "Vessel name is required".to_string(),
+                error: Some(MutationError::invalid()),
+                vessel: None,
+            });
+        }
+
+        if input.capacity <= BigDecimal::from(0) {
+            return Ok(VesselResult {
+                success: false,
+                message: "Capacity must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                vessel: None,
+            });
+        }
+
+        let existing = sqlx::query!("SELECT id FROM vessels WHERE name = $1", input.name.trim())
+            .fetch_optional(pool)
+            .await?;
+
+        if existing.is_some() {
+            return Ok(VesselResult {
+                success: false,
+                message: format!("A vessel named '{}' already exists", input.name.trim()),
+                error: Some(MutationError::name_conflict("Vessel", input.name.trim())),
+                vessel: None,
+            });
+        }
+
+        let vessel = sqlx::query_as!(
+            Vessel,
+            r#"
+            INSERT INTO vessels (name, vessel_type, capacity, capacity_unit, location, notes)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id, name, vessel_type, capacity, capacity_unit, status, location,
+                notes, is_active, created_at, updated_at
+            "#,
+            input.name.trim(),
+            input.vessel_type,
+            input.capacity,
+            input.capacity_unit,
+            input.location,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(VesselResult {
+            success: true,
+            message: format!("Successfully created vessel '{}'", vessel.name),
+            error: None,
+            vessel: Some(vessel),
+        })
+    }
+
+    /// Update a vessel's details or status
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn update_vessel(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateVesselInput,
+    ) -> Result<VesselResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!(
+            "SELECT status FROM vessels WHERE id = $1 AND is_active = true",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(VesselResult {
+                success: false,
+                message: "Vessel not found".to_string(),
+                error: Some(MutationError::not_found("Vessel")),
+                vessel: None,
+            });
+        };
+
+        if let Some(ref status) = input.status {
+            if !["available", "cleaning", "out_of_service"].contains(&status.as_str()) {
+                return Ok(VesselResult {
+                    success: false,
+                    message: "Status must be one of: available, cleaning, out_of_service"
+                        .to_string(),
+                    error: Some(MutationError::invalid()),
+                    vessel: None,
+                });
+            }
+
+            if existing.status == "in_use" {
+                return Ok(VesselResult {
+                    success: false,
+                    message: "Vessel is holding a batch; complete, fail, or move the batch first"
+                        .to_string(),
+                    error: Some(MutationError::invalid()),
+                    vessel: None,
+                });
+            }
+        }
+
+        if input
+            .capacity
+            .as_ref()
+            .is_some_and(|c| *c <= BigDecimal::from(0))
+        {
+            return Ok(VesselResult {
+                success: false,
+                message: "Capacity must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                vessel: None,
+            });
+        }
+
+        if let Some(ref name) = input.name {
+            let duplicate = sqlx::query!(
+                "SELECT id FROM vessels WHERE name = $1 AND id <> $2",
+                name.trim(),
+                input.id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if duplicate.is_some() {
+                return Ok(VesselResult {
+                    success: false,
+                    message: format!("A vessel named '{}' already exists", name.trim()),
+                    error: Some(MutationError::name_conflict("Vessel", name.trim())),
+                    vessel: None,
+                });
+            }
+        }
+
+        let vessel = sqlx::query_as!(
+            Vessel,
+            r#"
+            UPDATE vessels
+            SET
+                name = COALESCE($2, name),
+                vessel_type = COALESCE($3, vessel_type),
+                capacity = COALESCE($4, capacity),
+                capacity_unit = COALESCE($5, capacity_unit),
+                status = COALESCE($6, status),
+                location = COALESCE($7, location),
+                notes = COALESCE($8, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, name, vessel_type, capacity, capacity_unit, status, location,
+                notes, is_active, created_at, updated_at
+            "#,
+            input.id,
+            input.name.as_deref().map(str::trim),
+            input.vessel_type,
+            input.capacity,
+            input.capacity_unit,
+            input.status,
+            input.location,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(VesselResult {
+            success: true,
+            message: format!("Successfully updated vessel '{}'", vessel.name),
+            error: None,
+            vessel: Some(vessel),
+        })
+    }
+
+    /// Retire a vessel (soft delete by setting is_active to false)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn delete_vessel(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteVesselInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let vessel = sqlx::query!(
+            "SELECT name, status FROM vessels WHERE id = $1 AND is_active = true",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(vessel) = vessel else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Vessel not found".to_string(),
+                error: Some(MutationError::not_found("Vessel")),
+            });
+        };
+
+        if vessel.status == "in_use" {
+            return Ok(DeleteResult {
+                success: false,
+                message: format!("Vessel '{}' is holding a batch", vessel.name),
+                error: Some(MutationError::invalid()),
+            });
+        }
+
+        sqlx::query!(
+            "UPDATE vessels SET is_active = false, updated_at = NOW() WHERE id = $1",
+            input.id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully retired vessel '{}'", vessel.name),
+            error: None,
+        })
+    }
+
+    /// Move an in-progress batch into a vessel, or out of its current one
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn assign_batch_vessel(
+        &self,
+        ctx: &Context<'_>,
+        input: AssignBatchVesselInput,
+    ) -> Result<ProductionBatchResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let batch = sqlx::query!(
+            "SELECT batch_number, status, vessel_id FROM production_batches WHERE id = $1 FOR UPDATE",
+            input.batch_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(batch) = batch else {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: "Production batch not found".to_string(),
+                error: Some(MutationError::not_found("Production batch")),
+                batch_id: None,
+                batch_number: None,
+            });
+        };
+
+        if batch.status != "in_progress" {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: format!("Batch is already {}", batch.status),
+                error: Some(MutationError::invalid()),
+                batch_id: None,
+                batch_number: Some(batch.batch_number),
+            });
+        }
+
+        if batch.vessel_id.is_some() && batch.vessel_id == input.vessel_id {
+            return Ok(ProductionBatchResult {
+                success: false,
+                message: "Batch is already in that vessel".to_string(),
+                error: Some(MutationError::invalid()),
+                batch_id: Some(input.batch_id),
+                batch_number: Some(batch.batch_number),
+            });
+        }
+
+        // 1. Take the batch out of its current vessel
+        if batch.vessel_id.is_some() {
+            vessels::release(&mut tx, input.batch_id).await?;
+
+            sqlx::query!(
+                "UPDATE production_batches SET vessel_id = NULL, updated_at = NOW() WHERE id = $1",
+                input.batch_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // 2. Put it in the new one
+        let message = match input.vessel_id {
+            Some(vessel_id) => match vessels::claim(&mut tx, vessel_id, input.batch_id).await? {
+                Claim::Claimed { vessel_name } => {
+                    format!("Batch {} moved into {}", batch.batch_number, vessel_name)
+                }
+                Claim::Unavailable(message) => {
+                    return Ok(ProductionBatchResult {
+                        success: false,
+                        message,
+                        error: Some(MutationError::invalid()),
+                        batch_id: Some(input.batch_id),
+                        batch_number: Some(batch.batch_number),
+                    });
+                }
+            },
+            None => format!("Batch {} removed from its vessel", batch.batch_number),
+        };
+
+        tx.commit().await?;
+
+        Ok(ProductionBatchResult {
+            success: true,
+            message,
+            error: None,
+            batch_id: Some(input.batch_id),
+            batch_number: Some(batch.batch_number),
+        })
+    }
+
+    /// Record a vessel cleaning for the sanitation log; a vessel waiting on
+    /// cleaning becomes available again
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn record_cleaning(
+        &self,
+        ctx: &Context<'_>,
+        input: RecordCleaningInput,
+    ) -> Result<SanitationLogResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.chemical.trim().is_empty() || input.operator.trim().is_empty() {
+            return Ok(SanitationLogResult {
+                success: false,
+                message: "Chemical and operator are required".to_string(),
+                error: Some(MutationError::invalid()),
+                log: None,
+            });
+        }
+
+        if input.concentration < BigDecimal::from(0) {
+            return Ok(SanitationLogResult {
+                success: false,
+                message: "Concentration cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                log: None,
+            });
+        }
+
+        let cleaned_at = input.cleaned_at.unwrap_or_else(Utc::now);
+
+        if cleaned_at > Utc::now() {
+            return Ok(SanitationLogResult {
+                success: false,
+                message: "Cleaning time cannot be in the future".to_string(),
+                error: Some(MutationError::invalid()),
+                log: None,
+            });
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let vessel = sqlx::query!(
+            "SELECT name, status FROM vessels WHERE id = $1 AND is_active = true FOR UPDATE",
+            input.vessel_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(vessel) = vessel else {
+            return Ok(SanitationLogResult {
+                success: false,
+                message: "Vessel not found".to_string(),
+                error: Some(MutationError::not_found("Vessel")),
+                log: None,
+            });
+        };
+
+        if vessel.status == "in_use" {
+            return Ok(SanitationLogResult {
+                success: false,
+                message: format!("Vessel '{}' is holding a batch", vessel.name),
+                error: Some(MutationError::invalid()),
+                log: None,
+            });
+        }
+
+        let log = sqlx::query_as!(
+            SanitationLog,
+            r#"
+            INSERT INTO sanitation_logs (
+                vessel_id, chemical, concentration, concentration_unit,
+                operator, cleaned_at, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING
+                id, vessel_id, chemical, concentration, concentration_unit,
+                operator, cleaned_at, notes, created_at
+            "#,
+            input.vessel_id,
+            input.chemical.trim(),
+            input.concentration,
+            input
+                .concentration_unit
+                .unwrap_or_else(|| "ppm".to_string()),
+            input.operator.trim(),
+            cleaned_at,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if vessel.status == "cleaning" {
+            sqlx::query!(
+                "UPDATE vessels SET status = 'available', updated_at = NOW() WHERE id = $1",
+                input.vessel_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(SanitationLogResult {
+            success: true,
+            message: format!("Recorded cleaning of {}", vessel.name),
+            error: None,
+            log: Some(log),
+        })
+    }
+
+    /// Add a market day, holiday, or production blackout to the business calendar
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_calendar_event(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateCalendarEventInput,
+    ) -> Result<CalendarEventResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let end_date = input.end_date.unwrap_or(input.start_date);
+
+        if let Err(message) =
+            calendar::validate(&input.kind, &input.name, input.start_date, end_date)
+        {
+            return Ok(CalendarEventResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                event: None,
+            });
+        }
+
+        let (blocks_production, blocks_deliveries) = calendar::default_blocks(&input.kind);
+
+        let event = sqlx::query_as!(
+            CalendarEvent,
+            r#"
+            INSERT INTO business_calendar (
+                kind, name, start_date, end_date, blocks_production, blocks_deliveries, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING
+                id, kind, name, start_date, end_date, blocks_production,
+                blocks_deliveries, notes, created_at, updated_at
+            "#,
+            input.kind,
+            input.name.trim(),
+            input.start_date,
+            end_date,
+            input.blocks_production.unwrap_or(blocks_production),
+            input.blocks_deliveries.unwrap_or(blocks_deliveries),
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(CalendarEventResult {
+            success: true,
+            message: format!(
+                "Successfully added {} '{}' ({} to {})",
+                event.kind, event.name, event.start_date, event.end_date
+            ),
+            error: None,
+            event: Some(event),
+        })
+    }
+
+    /// Update a business calendar entry
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_calendar_event(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateCalendarEventInput,
+    ) -> Result<CalendarEventResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!(
+            "SELECT kind, name, start_date, end_date FROM business_calendar WHERE id = $1",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(CalendarEventResult {
+                success: false,
+                message: "Calendar entry not found".to_string(),
+                error: Some(MutationError::not_found("Calendar entry")),
+                event: None,
+            });
+        };
+
+        if let Err(message) = calendar::validate(
+            input.kind.as_deref().unwrap_or(&existing.kind),
+            input.name.as_deref().unwrap_or(&existing.name),
+            input.start_date.unwrap_or(existing.start_date),
+            input.end_date.unwrap_or(existing.end_date),
+        ) {
+            return Ok(CalendarEventResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                event: None,
+            });
+        }
+
+        let event = sqlx::query_as!(
+            CalendarEvent,
+            r#"
+            UPDATE business_calendar
+            SET
+                kind = COALESCE($2, kind),
+                name = COALESCE($3, name),
+                start_date = COALESCE($4, start_date),
+                end_date = COALESCE($5, end_date),
+                blocks_production = COALESCE($6, blocks_production),
+                blocks_deliveries = COALESCE($7, blocks_deliveries),
+                notes = COALESCE($8, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, kind, name, start_date, end_date, blocks_production,
+                blocks_deliveries, notes, created_at, updated_at
+            "#,
+            input.id,
+            input.kind,
+            input.name.as_deref().map(str::trim),
+            input.start_date,
+            input.end_date,
+            input.blocks_production,
+            input.blocks_deliveries,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(CalendarEventResult {
+            success: true,
+            message: format!("Successfully updated '{}'", event.name),
+            error: None,
+            event: Some(event),
+        })
+    }
+
+    /// Remove a business calendar entry
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn delete_calendar_event(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteCalendarEventInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let deleted = sqlx::query_scalar!(
+            "DELETE FROM business_calendar WHERE id = $1 RETURNING name",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(name) = deleted else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Calendar entry not found".to_string(),
+                error: Some(MutationError::not_found("Calendar entry")),
+            });
+        };
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully removed '{}' from the calendar", name),
+            error: None,
+        })
+    }
+
+    /// Add a farmers market we sell at, with its usual booth fee and travel cost
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_market(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateMarketInput,
+    ) -> Result<MarketResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Ok(MarketResult::failed("Market name is required"));
+        }
+        let zero = BigDecimal::from(0);
+        let booth_fee = input.default_booth_fee.unwrap_or_else(|| zero.clone());
+        let travel_cost = input.default_travel_cost.unwrap_or_else(|| zero.clone());
+        if booth_fee < zero || travel_cost < zero {
+            return Ok(MarketResult::failed(
+                "Booth fee and travel cost can't be negative",
+            ));
+        }
+
+        let existing = sqlx::query!("SELECT id FROM markets WHERE LOWER(name) = LOWER($1)", name)
+            .fetch_optional(pool)
+            .await?;
+
+        if existing.is_some() {
+            return Ok(MarketResult {
+                error: Some(MutationError::name_conflict("Market", name)),
+                ..MarketResult::failed(&format!("A market named '{}' already exists", name))
+            });
+        }
+
+        let market = sqlx::query_as!(
+            Market,
+            r#"
+            INSERT INTO markets (name, location, default_booth_fee, default_travel_cost, notes)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id, name, location, default_booth_fee, default_travel_cost, is_active, notes,
+                created_at, updated_at
+            "#,
+            name,
+            input.location,
+            booth_fee,
+            travel_cost,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(MarketResult {
+            success: true,
+            message: format!("Successfully added market '{}'", market.name),
+            error: None,
+            market: Some(market),
+        })
+    }
+
+    /// Update a market, or mark it inactive once we stop attending
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_market(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateMarketInput,
+    ) -> Result<MarketResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let name = input.name.as_deref().map(str::trim);
+        if name.is_some_and(str::is_empty) {
+            return Ok(MarketResult::failed("Market name can't be empty"));
+        }
+        let zero = BigDecimal::from(0);
+        if input
+            .default_booth_fee
+            .as_ref()
+            .is_some_and(|fee| *fee < zero)
+            || input
+                .default_travel_cost
+                .as_ref()
+                .is_some_and(|cost| *cost < zero)
+        {
+            return Ok(MarketResult::failed(
+                "Booth fee and travel cost can't be negative",
+            ));
+        }
+
+        if let Some(name) = name {
+            let conflict = sqlx::query!(
+                "SELECT id FROM markets WHERE LOWER(name) = LOWER($1) AND id <> $2",
+                name,
+                input.id
+            )
+            .fetch_optional(pool)
+            .await?;
+
+            if conflict.is_some() {
+                return Ok(MarketResult {
+                    error: Some(MutationError::name_conflict("Market", name)),
+                    ..MarketResult::failed(&format!("A market named '{}' already exists", name))
+                });
+            }
+        }
+
+        let market = sqlx::query_as!(
+            Market,
+            r#"
+            UPDATE markets
+            SET
+                name = COALESCE($2, name),
+                location = COALESCE($3, location),
+                default_booth_fee = COALESCE($4, default_booth_fee),
+                default_travel_cost = COALESCE($5, default_travel_cost),
+                is_active = COALESCE($6, is_active),
+                notes = COALESCE($7, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, name, location, default_booth_fee, default_travel_cost, is_active, notes,
+                created_at, updated_at
+            "#,
+            input.id,
+            name,
+            input.location,
+            input.default_booth_fee,
+            input.default_travel_cost,
+            input.is_active,
+            input.notes
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(market) = market else {
+            return Ok(MarketResult::not_found("Market"));
+        };
+
+        Ok(MarketResult {
+            success: true,
+            message: format!("Successfully updated market '{}'", market.name),
+            error: None,
+            market: Some(market),
+        })
+    }
+
+    /// Schedule a day at a market with its booth, fees and staff. The day goes
+    /// on the business calendar as a market day (reusing an entry of the same
+    /// name that already covers it).
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn schedule_market_session(
+        &self,
+        ctx: &Context<'_>,
+        input: ScheduleMarketSessionInput,
+    ) -> Result<MarketSessionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let market = sqlx::query_as!(
+            Market,
+            r#"
+            SELECT
+                id, name, location, default_booth_fee, default_travel_cost, is_active, notes,
+                created_at, updated_at
+            FROM markets
+            WHERE id = $1
+            "#,
+            input.market_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(market) = market else {
+            return Ok(MarketSessionResult::not_found("Market"));
+        };
+        if !market.is_active {
+            return Ok(MarketSessionResult::failed(&format!(
+                "{} is inactive; reactivate it to schedule a session",
+                market.name
+            )));
+        }
+
+        let zero = BigDecimal::from(0);
+        let booth_fee = input.booth_fee.unwrap_or(market.default_booth_fee);
+        let travel_cost = input.travel_cost.unwrap_or(market.default_travel_cost);
+        if booth_fee < zero || travel_cost < zero {
+            return Ok(MarketSessionResult::failed(
+                "Booth fee and travel cost can't be negative",
+            ));
+        }
+
+        let already = sqlx::query_scalar!(
+            "SELECT id FROM market_sessions WHERE market_id = $1 AND session_date = $2",
+            market.id,
+            input.session_date
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if already.is_some() {
+            return Ok(MarketSessionResult::failed(&format!(
+                "{} is already scheduled on {}",
+                market.name, input.session_date
+            )));
+        }
+
+        let calendar_event_id =
+            markets::calendar_event(&mut tx, &market.name, input.session_date).await?;
+
+        let session_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO market_sessions (
+                market_id, calendar_event_id, session_date, booth, booth_fee, travel_cost, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+            market.id,
+            calendar_event_id,
+            input.session_date,
+            input.booth,
+            booth_fee,
+            travel_cost,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(staff) = &input.staff
+            && let Err(message) = markets::set_staff(&mut tx, session_id, staff).await?
+        {
+            return Ok(MarketSessionResult::failed(&message));
+        }
+
+        let session = markets::session(&mut *tx, session_id).await?;
+        tx.commit().await?;
+
+        Ok(MarketSessionResult {
+            success: true,
+            message: format!("Scheduled {} on {}", market.name, input.session_date),
+            error: None,
+            session,
+        })
+    }
+
+    /// Update a market session's booth, fees, staff or notes
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_market_session(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateMarketSessionInput,
+    ) -> Result<MarketSessionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let zero = BigDecimal::from(0);
+        if input.booth_fee.as_ref().is_some_and(|fee| *fee < zero)
+            || input.travel_cost.as_ref().is_some_and(|cost| *cost < zero)
+        {
+            return Ok(MarketSessionResult::failed(
+                "Booth fee and travel cost can't be negative",
+            ));
+        }
+
+        let updated = sqlx::query_scalar!(
+            r#"
+            UPDATE market_sessions
+            SET
+                booth = COALESCE($2, booth),
+                booth_fee = COALESCE($3, booth_fee),
+                travel_cost = COALESCE($4, travel_cost),
+                notes = COALESCE($5, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id
+            "#,
+            input.id,
+            input.booth,
+            input.booth_fee,
+            input.travel_cost,
+            input.notes
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if updated.is_none() {
+            return Ok(MarketSessionResult::not_found("Market session"));
+        }
+
+        if let Some(staff) = &input.staff
+            && let Err(message) = markets::set_staff(&mut tx, input.id, staff).await?
+        {
+            return Ok(MarketSessionResult::failed(&message));
+        }
+
+        let session = markets::session(&mut *tx, input.id).await?;
+        tx.commit().await?;
+
+        Ok(MarketSessionResult {
+            success: true,
+            message: "Successfully updated market session".to_string(),
+            error: None,
+            session,
+        })
+    }
+
+    /// Attach sales rung up at a market (e.g. entered after the day) to its
+    /// session, so they count toward that market's profitability
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn assign_sales_to_market_session(
+        &self,
+        ctx: &Context<'_>,
+        input: AssignSalesToMarketSessionInput,
+    ) -> Result<MarketSessionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let Some(session) = markets::session(&mut *tx, input.session_id).await? else {
+            return Ok(MarketSessionResult::not_found("Market session"));
+        };
+
+        let assigned = sqlx::query_scalar!(
+            r#"
+            UPDATE sales SET market_session_id = $1, updated_at = NOW()
+            WHERE id = ANY($2)
+            RETURNING id
+            "#,
+            session.id,
+            &input.sale_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if let Some(missing) = input.sale_ids.iter().find(|id| !assigned.contains(id)) {
+            return Ok(MarketSessionResult {
+                error: Some(MutationError::not_found("Sale")),
+                ..MarketSessionResult::failed(&format!("Sale not found: {}", missing))
+            });
+        }
+
+        tx.commit().await?;
+
+        Ok(MarketSessionResult {
+            success: true,
+            message: format!(
+                "Assigned {} sales to {} on {}",
+                assigned.len(),
+                session.market_name,
+                session.session_date
+            ),
+            error: None,
+            session: Some(session),
+        })
+    }
+
+    /// Schedule a workshop, optionally with a per-attendee materials kit
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_workshop(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateWorkshopInput,
+    ) -> Result<WorkshopResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.title.trim().is_empty() {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Title is required".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input.capacity <= 0 {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Capacity must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input.ticket_price < BigDecimal::from(0) {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Ticket price cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input.duration_minutes.is_some_and(|minutes| minutes <= 0) {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Duration must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        let kit = input.kit.unwrap_or_default();
+        if let Err(message) = workshops::validate_kit(&mut tx, &kit).await? {
+            return Ok(WorkshopResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        let workshop = sqlx::query_as!(
+            Workshop,
+            r#"
+            INSERT INTO workshops (
+                title, description, starts_at, duration_minutes, location,
+                capacity, ticket_price, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING
+                id, title, description, starts_at, duration_minutes, location, capacity,
+                ticket_price, status, notes, created_at, updated_at
+            "#,
+            input.title.trim(),
+            input.description,
+            input.starts_at,
+            input.duration_minutes,
+            input.location,
+            input.capacity,
+            input.ticket_price,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        workshops::replace_kit(&mut tx, workshop.id, &kit).await?;
+
+        tx.commit().await?;
+
+        Ok(WorkshopResult {
+            success: true,
+            message: format!(
+                "Scheduled '{}' for {} attendees with a {}-item kit",
+                workshop.title,
+                workshop.capacity,
+                kit.len()
+            ),
+            error: None,
+            workshop: Some(workshop),
+        })
+    }
+
+    /// Update a workshop. Cancelling it cancels every outstanding ticket.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_workshop(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateWorkshopInput,
+    ) -> Result<WorkshopResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT
+                status,
+                (SELECT COUNT(*) FROM workshop_attendees
+                 WHERE workshop_id = w.id AND status <> 'cancelled') AS "sold!",
+                (SELECT COUNT(*) FROM workshop_attendees
+                 WHERE workshop_id = w.id AND status = 'checked_in') AS "checked_in!"
+            FROM workshops w
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Workshop not found".to_string(),
+                error: Some(MutationError::not_found("Workshop")),
+                workshop: None,
+            });
+        };
+
+        if let Some(status) = &input.status
+            && !workshops::STATUSES.contains(&status.as_str())
+        {
+            return Ok(WorkshopResult {
+                success: false,
+                message: format!("Status must be one of: {}", workshops::STATUSES.join(", ")),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Title cannot be empty".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if let Some(capacity) = input.capacity
+            && i64::from(capacity) < existing.sold.max(1)
+        {
+            return Ok(WorkshopResult {
+                success: false,
+                message: format!(
+                    "Capacity cannot be below the {} ticket(s) already sold",
+                    existing.sold
+                ),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input
+            .ticket_price
+            .as_ref()
+            .is_some_and(|price| *price < BigDecimal::from(0))
+        {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Ticket price cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if input.duration_minutes.is_some_and(|minutes| minutes <= 0) {
+            return Ok(WorkshopResult {
+                success: false,
+                message: "Duration must be greater than 0".to_string(),
+                error: Some(MutationError::invalid()),
+                workshop: None,
+            });
+        }
+
+        if let Some(kit) = &input.kit {
+            // Kits already handed out were drawn from the old list
+            if existing.checked_in > 0 {
+                return Ok(WorkshopResult {
+                    success: false,
+                    message: "Kit cannot be changed after attendees have checked in".to_string(),
+                    error: Some(MutationError::invalid()),
+                    workshop: None,
+                });
+            }
+
+            if let Err(message) = workshops::validate_kit(&mut tx, kit).await? {
+                return Ok(WorkshopResult {
+                    success: false,
+                    message,
+                    error: Some(MutationError::invalid()),
+                    workshop: None,
+                });
+            }
+
+            workshops::replace_kit(&mut tx, input.id, kit).await?;
+        }
+
+        let workshop = sqlx::query_as!(
+            Workshop,
+            r#"
+            UPDATE workshops
+            SET
+                title = COALESCE($2, title),
+                description = COALESCE($3, description),
+                starts_at = COALESCE($4, starts_at),
+                duration_minutes = COALESCE($5, duration_minutes),
+                location = COALESCE($6, location),
+                capacity = COALESCE($7, capacity),
+                ticket_price = COALESCE($8, ticket_price),
+                status = COALESCE($9, status),
+                notes = COALESCE($10, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, title, description, starts_at, duration_minutes, location, capacity,
+                ticket_price, status, notes, created_at, updated_at
+            "#,
+            input.id,
+            input.title.as_deref().map(str::trim),
+            input.description,
+            input.starts_at,
+            input.duration_minutes,
+            input.location,
+            input.capacity,
+            input.ticket_price,
+            input.status,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut message = format!("Successfully updated '{}'", workshop.title);
+
+        if workshop.status == "cancelled" && existing.status != "cancelled" {
+            let cancelled = sqlx::query!(
+                r#"
+                UPDATE workshop_attendees
+                SET status = 'cancelled', cancelled_at = NOW(), updated_at = NOW()
+                WHERE workshop_id = $1 AND status = 'registered'
+                "#,
+                workshop.id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            message = format!(
+                "Cancelled '{}' and {} ticket(s)",
+                workshop.title,
+                cancelled.rows_affected()
+            );
+        }
+
+        tx.commit().await?;
+
+        Ok(WorkshopResult {
+            success: true,
+            message,
+            error: None,
+            workshop: Some(workshop),
+        })
+    }
+
+    /// Sell a workshop ticket to one attendee (recorded as ticket revenue,
+    /// not an inventory sale)
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn sell_workshop_ticket(
+        &self,
+        ctx: &Context<'_>,
+        input: SellWorkshopTicketInput,
+    ) -> Result<WorkshopAttendeeResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if input.attendee_name.trim().is_empty() {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Attendee name is required".to_string(),
+                error: Some(MutationError::invalid()),
+                attendee: None,
+            });
+        }
+
+        // Lock the workshop so concurrent sales can't oversell it
+        let workshop = sqlx::query!(
+            r#"
+            SELECT
+                title, capacity, ticket_price, status,
+                (SELECT COUNT(*) FROM workshop_attendees
+                 WHERE workshop_id = w.id AND status <> 'cancelled') AS "sold!"
+            FROM workshops w
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.workshop_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(workshop) = workshop else {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Workshop not found".to_string(),
+                error: Some(MutationError::not_found("Workshop")),
+                attendee: None,
+            });
+        };
+
+        if workshop.status != "scheduled" {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: format!("Workshop is {}", workshop.status),
+                error: Some(MutationError::invalid()),
+                attendee: None,
+            });
+        }
+
+        if workshop.sold >= i64::from(workshop.capacity) {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: format!(
+                    "'{}' is sold out ({} seats)",
+                    workshop.title, workshop.capacity
+                ),
+                error: Some(MutationError::invalid()),
+                attendee: None,
+            });
+        }
+
+        let price_paid = input.price_paid.unwrap_or(workshop.ticket_price);
+        if price_paid < BigDecimal::from(0) {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Price paid cannot be negative".to_string(),
+                error: Some(MutationError::invalid()),
+                attendee: None,
+            });
+        }
+
+        if let Some(customer_id) = input.customer_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1) AS \"exists!\"",
+                customer_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !exists {
+                return Ok(WorkshopAttendeeResult {
+                    success: false,
+                    message: "Customer not found".to_string(),
+                    error: Some(MutationError::not_found("Customer")),
+                    attendee: None,
+                });
+            }
+        }
+
+        let now = Utc::now();
+        let ticket_number = workshops::next_ticket_number(&mut tx, now).await?;
+
+        let attendee = sqlx::query_as!(
+            WorkshopAttendee,
+            r#"
+            INSERT INTO workshop_attendees (
+                workshop_id, ticket_number, customer_id, attendee_name, email, phone,
+                price_paid, payment_method, purchased_at, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING
+                id, workshop_id, ticket_number, customer_id, attendee_name, email, phone,
+                price_paid, payment_method, status, purchased_at, checked_in_at,
+                cancelled_at, notes, created_at, updated_at
+            "#,
+            input.workshop_id,
+            ticket_number,
+            input.customer_id,
+            input.attendee_name.trim(),
+            input.email,
+            input.phone,
+            price_paid,
+            input.payment_method,
+            now,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(WorkshopAttendeeResult {
+            success: true,
+            message: format!(
+                "Sold ticket {} for '{}' to {} ({} of {} seats taken)",
+                attendee.ticket_number,
+                workshop.title,
+                attendee.attendee_name,
+                workshop.sold + 1,
+                workshop.capacity
+            ),
+            error: None,
+            attendee: Some(attendee),
+        })
+    }
+
+    /// Check an attendee in and hand over their materials kit, drawing it
+    /// from inventory
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn check_in_workshop_attendee(
+        &self,
+        ctx: &Context<'_>,
+        input: WorkshopAttendeeInput,
+    ) -> Result<WorkshopAttendeeResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let registered = sqlx::query!(
+            r#"
+            SELECT a.workshop_id, a.attendee_name, a.ticket_number, w.title, w.status
+            FROM workshop_attendees a
+            JOIN workshops w ON w.id = a.workshop_id
+            WHERE a.id = $1 AND a.status = 'registered'
+            FOR UPDATE OF a
+            "#,
+            input.attendee_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(registered) = registered else {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Registered attendee not found".to_string(),
+                error: Some(MutationError::not_found("Registered attendee")),
+                attendee: None,
+            });
+        };
+
+        if registered.status == "cancelled" {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Workshop is cancelled".to_string(),
+                error: Some(MutationError::invalid()),
+                attendee: None,
+            });
+        }
+
+        let now = Utc::now();
+        let label = format!(
+            "Workshop kit for {} ({}) at '{}'",
+            registered.attendee_name, registered.ticket_number, registered.title
+        );
+
+        let items = match workshops::issue_kit(&mut tx, registered.workshop_id, &label, now).await?
+        {
+            Ok(items) => items,
+            Err(rejection) => {
+                return Ok(WorkshopAttendeeResult {
+                    success: false,
+                    message: rejection.message,
+                    error: Some(rejection.error),
+                    attendee: None,
+                });
+            }
+        };
+
+        let attendee = sqlx::query_as!(
+            WorkshopAttendee,
+            r#"
+            UPDATE workshop_attendees
+            SET status = 'checked_in', checked_in_at = $2,
+                notes = COALESCE($3, notes), updated_at = $2
+            WHERE id = $1
+            RETURNING
+                id, workshop_id, ticket_number, customer_id, attendee_name, email, phone,
+                price_paid, payment_method, status, purchased_at, checked_in_at,
+                cancelled_at, notes, created_at, updated_at
+            "#,
+            input.attendee_id,
+            now,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(WorkshopAttendeeResult {
+            success: true,
+            message: format!(
+                "Checked in {}; issued a {}-item kit",
+                attendee.attendee_name, items
+            ),
+            error: None,
+            attendee: Some(attendee),
+        })
+    }
+
+    /// Cancel a ticket that hasn't been used, freeing the seat
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn cancel_workshop_ticket(
+        &self,
+        ctx: &Context<'_>,
+        input: WorkshopAttendeeInput,
+    ) -> Result<WorkshopAttendeeResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let attendee = sqlx::query_as!(
+            WorkshopAttendee,
+            r#"
+            UPDATE workshop_attendees
+            SET status = 'cancelled', cancelled_at = NOW(),
+                notes = COALESCE($2, notes), updated_at = NOW()
+            WHERE id = $1 AND status = 'registered'
+            RETURNING
+                id, workshop_id, ticket_number, customer_id, attendee_name, email, phone,
+                price_paid, payment_method, status, purchased_at, checked_in_at,
+                cancelled_at, notes, created_at, updated_at
+            "#,
+            input.attendee_id,
+            input.notes
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(attendee) = attendee else {
+            return Ok(WorkshopAttendeeResult {
+                success: false,
+                message: "Registered attendee not found".to_string(),
+                error: Some(MutationError::not_found("Registered attendee")),
+                attendee: None,
+            });
+        };
+
+        Ok(WorkshopAttendeeResult {
+            success: true,
+            message: format!(
+                "Cancelled ticket {} for {}",
+                attendee.ticket_number, attendee.attendee_name
+            ),
+            error: None,
+            attendee: Some(attendee),
+        })
+    }
+
+    /// Create a new customer
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_customer(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateCustomerInput,
+    ) -> Result<CustomerResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Extract boolean with default
+        let tax_exempt = input.tax_exempt.unwrap_or(false);
+        let sms_opt_in = input.sms_opt_in.unwrap_or(false);
+        let payment_terms_days = input.payment_terms_days.unwrap_or(0);
+        if payment_terms_days < 0 {
+            return Ok(CustomerResult::failed("Payment terms can't be negative"));
+        }
+
+        let customer = sqlx::query_as!(
+            Customer,
+            r#"
+            INSERT INTO customers (
+                name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type, tax_exempt, sms_opt_in, payment_terms_days, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING
+                id, name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type,
+                tax_exempt as "tax_exempt!",
+                sms_opt_in,
+                payment_terms_days,
+                notes,
+                is_active as "is_active!",
+                created_at, updated_at
+            "#,
+            input.name,
+            input.email,
+            input.phone,
+            input.street_address,
+            input.city,
+            input.state,
+            input.zip_code,
+            input.country,
+            input.latitude,
+            input.longitude,
+            input.customer_type,
+            tax_exempt,
+            sms_opt_in,
+            payment_terms_days,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(CustomerResult {
+            success: true,
+            message: format!("Successfully created customer '{}'", customer.name),
+            error: None,
+            customer: Some(customer),
+        })
+    }
+
+    /// Update an existing customer
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_customer(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateCustomerInput,
+    ) -> Result<CustomerResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Fetch the existing customer to ensure it exists
+        let existing = sqlx::query!("SELECT id FROM customers WHERE id = $1", input.id)
+            .fetch_optional(pool)
+            .await?;
+
+        if existing.is_none() {
+            return Ok(CustomerResult {
+                success: false,
+                message: "Customer not found".to_string(),
+                error: Some(MutationError::not_found("Customer")),
+                customer: None,
+            });
+        }
+        if input.payment_terms_days.is_some_and(|days| days < 0) {
+            return Ok(CustomerResult::failed("Payment terms can't be negative"));
+        }
+
+        // Handle optional boolean fields
+        let tax_exempt = input.tax_exempt;
+        let is_active = input.is_active;
+
+        // Build dynamic UPDATE query based on provided fields
+        let customer = sqlx::query_as!(
+            Customer,
+            r#"
+            UPDATE customers SET
+                name = COALESCE($2, name),
+                email = COALESCE($3, email),
+                phone = COALESCE($4, phone),
+                street_address = COALESCE($5, street_address),
+                city = COALESCE($6, city),
+                state = COALESCE($7, state),
+                zip_code = COALESCE($8, zip_code),
+                country = COALESCE($9, country),
+                latitude = COALESCE($10, latitude),
+                longitude = COALESCE($11, longitude),
+                customer_type = COALESCE($12, customer_type),
+                tax_exempt = COALESCE($13::boolean, tax_exempt),
+                notes = COALESCE($14, notes),
+                is_active = COALESCE($15::boolean, is_active),
+                sms_opt_in = COALESCE($16::boolean, sms_opt_in),
+                payment_terms_days = COALESCE($17, payment_terms_days),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type,
+                tax_exempt as "tax_exempt!",
+                sms_opt_in,
+                payment_terms_days,
+                notes,
+                is_active as "is_active!",
+                created_at, updated_at
+            "#,
+            input.id,
+            input.name,
+            input.email,
+            input.phone,
+            input.street_address,
+            input.city,
+            input.state,
+            input.zip_code,
+            input.country,
+            input.latitude,
+            input.longitude,
+            input.customer_type,
+            tax_exempt,
+            input.notes,
+            is_active,
+            input.sms_opt_in,
+            input.payment_terms_days
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(CustomerResult {
+            success: true,
+            message: format!("Successfully updated customer '{}'", customer.name),
+            error: None,
+            customer: Some(customer),
+        })
+    }
+
+    /// Create a new sale and update inventory
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_sale(&self, ctx: &Context<'_>, input: CreateSaleInput) -> Result<SaleResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        fn failed(rejection: impl Into<Rejection>) -> SaleResult {
+            let rejection = rejection.into();
+            SaleResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                sale_id: None,
+                sale_number: None,
+                updated_items: Vec::new(),
+                warnings: Vec::new(),
+            }
+        }
+
+        if input.discount_amount.is_some() {
+            versions::deprecated_input(ctx, "CreateSaleInput.discountAmount")?;
+        }
+
+        // A retry of a sale that already went through gets its original result
+        let idempotency_key = match &input.idempotency_key {
+            Some(key) => match idempotency::Key::new(key, ctx.data_opt::<Identity>(), &input) {
+                Ok(key) => Some(key),
+                Err(message) => return Ok(failed(message)),
+            },
+            None => None,
+        };
+        if let Some(key) = &idempotency_key {
+            match idempotency::claim(&mut tx, "createSale", key).await? {
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => {}
+                Err(rejection) => return Ok(failed(rejection)),
+            }
+        }
+
+        let sale_date = input.sale_date.unwrap_or_else(Utc::now);
+
+        // An invoiced sale is billed to its customer and paid later
+        let invoiced = input.payment_method.as_deref() == Some("invoice");
+        if invoiced && input.customer_id.is_none() {
+            return Ok(failed(
+                "An invoiced sale needs a customer to bill".to_string(),
+            ));
+        }
+
+        // Validate inputs
+        if input.items.is_empty() {
+            return Ok(SaleResult {
+                success: false,
+                message: "At least one item is required".to_string(),
+                error: Some(MutationError::invalid()),
+                sale_id: None,
+                sale_number: None,
+                updated_items: Vec::new(),
+                warnings: Vec::new(),
+            });
+        }
+
+        if let Some(session_id) = input.market_session_id {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM market_sessions WHERE id = $1) AS \"exists!\"",
+                session_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !exists {
+                return Ok(SaleResult {
+                    success: false,
+                    message: format!("Market session not found: {}", session_id),
+                    error: Some(MutationError::not_found("Market session")),
+                    sale_id: None,
+                    sale_number: None,
+                    updated_items: Vec::new(),
+                    warnings: Vec::new(),
+                });
+            }
+        }
+
+        // The channel's policy decides whether a sale can take stock negative
+        let channel = input
+            .channel
+            .as_deref()
+            .map(|channel| channel.trim().to_lowercase())
+            .filter(|channel| !channel.is_empty())
+            .unwrap_or_else(|| match input.market_session_id {
+                Some(_) => "farmers_market".to_string(),
+                None => "in_person".to_string(),
+            });
+        let stock_check = sales::stock_check(&mut tx, &channel).await?;
+
+        let mut warnings = Vec::new();
+        let mut lines = Vec::new();
+
+        // Validate all items have sufficient stock before processing
+        for item_input in &input.items {
+            if item_input.quantity <= BigDecimal::from(0) {
+                return Ok(SaleResult {
+                    success: false,
+                    message: "Quantity must be greater than 0".to_string(),
+                    error: Some(MutationError::invalid()),
+                    sale_id: None,
+                    sale_number: None,
+                    updated_items: Vec::new(),
+                    warnings: Vec::new(),
+                });
+            }
+
+            // Check stock availability; stock confirmed orders reserved isn't for sale
+            let inventory = sqlx::query!(
+                r#"SELECT name, available_stock AS "available_stock!" FROM inventory WHERE id = $1 FOR UPDATE"#,
+                item_input.inventory_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(inventory) = inventory else {
+                return Ok(SaleResult {
+                    success: false,
+                    message: format!("Inventory item not found: {}", item_input.inventory_id),
+                    error: Some(MutationError::not_found("Inventory item")),
+                    sale_id: None,
+                    sale_number: None,
+                    updated_items: Vec::new(),
+                    warnings: Vec::new(),
+                });
+            };
+
+            if inventory.available_stock < item_input.quantity && stock_check != "strict" {
+                warnings.push(format!(
+                    "{}: sold {} with {} available",
+                    inventory.name, item_input.quantity, inventory.available_stock
+                ));
+            } else if inventory.available_stock < item_input.quantity {
+                return Ok(SaleResult {
+                    success: false,
+                    message: format!(
+                        "Insufficient stock for item {}. Available: {}, Requested: {}",
+                        item_input.inventory_id, inventory.available_stock, item_input.quantity
+                    ),
+                    error: Some(MutationError::insufficient_stock(
+                        item_input.inventory_id,
+                        &inventory.name,
+                        &item_input.quantity,
+                        &inventory.available_stock,
+                    )),
+                    sale_id: None,
+                    sale_number: None,
+                    updated_items: Vec::new(),
+                    warnings: Vec::new(),
+                });
+            }
+
+            // Items without a price sell at the customer's price
+            let unit_price = match &item_input.unit_price {
+                Some(unit_price) => unit_price.clone(),
+                None => {
+                    let price =
+                        pricing::price_for(&mut *tx, input.customer_id, item_input.inventory_id)
+                            .await?;
+                    let Some(unit_price) = price.unit_price else {
+                        return Ok(SaleResult {
+                            success: false,
+                            message: format!(
+                                "{} has no price for this customer; give a unit price",
+                                inventory.name
+                            ),
+                            error: Some(MutationError::invalid()),
+                            sale_id: None,
+                            sale_number: None,
+                            updated_items: Vec::new(),
+                            warnings: Vec::new(),
+                        });
+                    };
+                    unit_price
+                }
+            };
+            lines.push(sales::SaleLine {
+                inventory_id: item_input.inventory_id,
+                quantity: item_input.quantity.clone(),
+                unit_price,
+                discounts: Vec::new(),
+                notes: item_input.notes.clone(),
+            });
+        }
+
+        // Discounts: each line's own, then the promo code's and the whole sale's
+        let promo = match input
+            .promo_code
+            .as_deref()
+            .filter(|code| !code.trim().is_empty())
+        {
+            Some(code) => match discounts::promo_code(&mut tx, code, sale_date.date_naive()).await?
+            {
+                Ok(promo) => Some(promo),
+                Err(rejection) => return Ok(failed(rejection)),
+            },
+            None => None,
+        };
+        let line_discounts: Vec<Option<DiscountInput>> = input
+            .items
+            .iter()
+            .map(|item| item.discount.clone())
+            .collect();
+        let mut order_discounts = input.discounts.clone().unwrap_or_default();
+        if let Some(amount) = input
+            .discount_amount
+            .clone()
+            .filter(|amount| *amount != BigDecimal::from(0))
+        {
+            order_discounts.insert(
+                0,
+                DiscountInput {
+                    discount_type: "fixed".to_string(),
+                    value: amount,
+                    reason: None,
+                },
+            );
+        }
+        let sale_discounts = match discounts::apply(
+            &mut lines,
+            &line_discounts,
+            &order_discounts,
+            promo.as_ref(),
+        ) {
+            Ok(sale_discounts) => sale_discounts,
+            Err(message) => return Ok(failed(message)),
+        };
+
+        // Calculate totals
+        let subtotal: BigDecimal = lines.iter().map(sales::SaleLine::total).sum();
+        let tax_amount = input.tax_amount.unwrap_or_else(|| BigDecimal::from(0));
+        let discount_amount: BigDecimal = lines
+            .iter()
+            .map(sales::SaleLine::discount_amount)
+            .chain(
+                sale_discounts
+                    .iter()
+                    .map(|discount| discount.amount.clone()),
+            )
+            .sum();
+        let total_amount = &subtotal + &tax_amount - &discount_amount;
+
+        // Number the sale (SALE_NUMBER_FORMAT)
+        let sale_number = sales::next_sale_number(&mut tx, sale_date).await?;
+
+        let oversold = !warnings.is_empty();
+        let needs_review = oversold && stock_check == "review";
+
+        // Create sale record
+        let sale_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO sales (
+                sale_number, customer_id, sale_date,
+                subtotal, tax_amount, discount_amount, total_amount,
+                payment_method, payment_status, notes,
+                channel, stock_check, oversold, stock_review_status, market_session_id
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING id
+            "#,
+            sale_number,
+            input.customer_id,
+            sale_date,
+            subtotal,
+            tax_amount,
+            discount_amount,
+            total_amount,
+            input.payment_method,
+            input.payment_status.unwrap_or_else(|| match invoiced {
+                true => "pending".to_string(),
+                false => "completed".to_string(),
+            }),
+            input.notes,
+            channel,
+            stock_check,
+            oversold,
+            needs_review.then_some("pending"),
+            input.market_session_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut updated_items = Vec::new();
+        let mut sold = Vec::new();
+
+        // Process each sale item
+        let sale = sales::SaleHeader {
+            id: sale_id,
+            sale_number: &sale_number,
+            sale_date,
+        };
+        for line in lines {
+            let (updated_item, line) = sales::sell_item(&mut tx, &sale, line).await?;
+            sold.push(line);
+            updated_items.push(updated_item);
+        }
+        discounts::record(&mut tx, sale_id, None, &sale_discounts).await?;
+
+        let invoice = match (invoiced, input.customer_id) {
+            (true, Some(customer_id)) => Some(
+                invoices::open(
+                    &mut tx,
+                    sale_id,
+                    customer_id,
+                    &total_amount,
+                    sale_date.date_naive(),
+                )
+                .await?,
+            ),
+            _ => None,
+        };
+
+        events::publish(
+            &mut *tx,
+            "saleCreated",
+            serde_json::json!({
+                "sale_id": sale_id,
+                "sale_number": sale_number,
+                "sale_date": sale_date,
+                "customer_id": input.customer_id,
+                "channel": channel,
+                "market_session_id": input.market_session_id,
+                "subtotal": subtotal,
+                "tax_amount": tax_amount,
+                "discount_amount": discount_amount,
+                "total_amount": total_amount,
+                "payment_method": input.payment_method,
+                "promo_code": promo.as_ref().map(|promo| &promo.code),
+                "items": sold,
+            }),
+        )
+        .await?;
+
+        let message = if needs_review {
+            format!(
+                "Created sale {}; it sold more than was on record and is queued for stock review",
+                sale_number
+            )
+        } else if oversold {
+            format!(
+                "Created sale {}; it sold more than was on record",
+                sale_number
+            )
+        } else if let Some(invoice) = &invoice {
+            format!(
+                "Successfully created sale {}; invoiced, due {}",
+                sale_number, invoice.due_on
+            )
+        } else {
+            format!("Successfully created sale {}", sale_number)
+        };
+
+        let result = SaleResult {
+            success: true,
+            message,
+            error: None,
+            sale_id: Some(sale_id),
+            sale_number: Some(sale_number),
+            updated_items,
+            warnings,
+        };
+
+        if let Some(key) = &idempotency_key {
+            idempotency::store(&mut tx, "createSale", key, &result).await?;
+        }
+
+        // Commit the transaction
+        tx.commit().await?;
+
+        Ok(result)
+    }
+
+    /// Set how `createSale` treats selling more than is on record for a
+    /// channel: refuse ('strict'), sell and warn ('allow_negative'), or sell,
+    /// warn and queue the sale for review ('review'). Channels without a
+    /// policy are strict.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_sale_channel_policy(
+        &self,
+        ctx: &Context<'_>,
+        input: SetSaleChannelPolicyInput,
+    ) -> Result<SaleChannelPolicyResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let channel = input.channel.trim().to_lowercase();
+        if channel.is_empty() {
+            return Ok(SaleChannelPolicyResult::failed("Channel is required"));
+        }
+        if !["strict", "allow_negative", "review"].contains(&input.stock_check.as_str()) {
+            return Ok(SaleChannelPolicyResult::failed(&format!(
+                "Unknown stock check '{}'; expected strict, allow_negative or review",
+                input.stock_check
+            )));
+        }
+
+        let policy = sqlx::query_as!(
+            SaleChannelPolicy,
+            r#"
+            INSERT INTO sale_channel_policies (channel, stock_check)
+            VALUES ($1, $2)
+            ON CONFLICT (channel) DO UPDATE SET stock_check = EXCLUDED.stock_check, updated_at = NOW()
+            RETURNING id, channel, stock_check, created_at, updated_at
+            "#,
+            channel,
+            input.stock_check
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(SaleChannelPolicyResult {
+            success: true,
+            message: format!(
+                "{} sales now use the {} stock check",
+                policy.channel, policy.stock_check
+            ),
+            error: None,
+            policy: Some(policy),
+        })
+    }
+
+    /// Set what an item sells for to a type of customer ('retail',
+    /// 'wholesale', 'restaurant', ...), or remove the price with a null
+    /// `unitPrice`. `createSale` uses it for items given without a price.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_list_price(
+        &self,
+        ctx: &Context<'_>,
+        input: SetListPriceInput,
+    ) -> Result<ListPriceResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let customer_type = match pricing::customer_type(&input.customer_type) {
+            Ok(customer_type) => customer_type,
+            Err(message) => return Ok(ListPriceResult::failed(&message)),
+        };
+        if input
+            .unit_price
+            .as_ref()
+            .is_some_and(|price| *price < BigDecimal::from(0))
+        {
+            return Ok(ListPriceResult::failed("Unit price can't be negative"));
+        }
+        let item_name = sqlx::query_scalar!(
+            "SELECT name FROM inventory WHERE id = $1",
+            input.inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(item_name) = item_name else {
+            return Ok(ListPriceResult::not_found("Inventory item"));
+        };
+
+        let price = pricing::set_list_price(
+            pool,
+            &customer_type,
+            input.inventory_id,
+            input.unit_price.as_ref(),
+        )
+        .await?;
+
+        Ok(ListPriceResult {
+            success: true,
+            message: match &price {
+                Some(price) => format!(
+                    "{} now sells for {} to {} customers",
+                    item_name, price.unit_price, customer_type
+                ),
+                None => format!("Removed the {} price of {}", customer_type, item_name),
+            },
+            error: None,
+            price,
+        })
+    }
+
+    /// Set what an item sells for to one customer, ahead of their type's
+    /// price list, or remove the price with a null `unitPrice`.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_customer_price(
+        &self,
+        ctx: &Context<'_>,
+        input: SetCustomerPriceInput,
+    ) -> Result<CustomerPriceResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input
+            .unit_price
+            .as_ref()
+            .is_some_and(|price| *price < BigDecimal::from(0))
+        {
+            return Ok(CustomerPriceResult::failed("Unit price can't be negative"));
+        }
+        let customer_name = sqlx::query_scalar!(
+            "SELECT name FROM customers WHERE id = $1",
+            input.customer_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(customer_name) = customer_name else {
+            return Ok(CustomerPriceResult::not_found("Customer"));
+        };
+        let item_name = sqlx::query_scalar!(
+            "SELECT name FROM inventory WHERE id = $1",
+            input.inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(item_name) = item_name else {
+            return Ok(CustomerPriceResult::not_found("Inventory item"));
+        };
+
+        let price = pricing::set_customer_price(
+            pool,
+            input.customer_id,
+            input.inventory_id,
+            input.unit_price.as_ref(),
+        )
+        .await?;
+
+        Ok(CustomerPriceResult {
+            success: true,
+            message: match &price {
+                Some(price) => format!(
+                    "{} now sells for {} to {}",
+                    item_name, price.unit_price, customer_name
+                ),
+                None => format!(
+                    "{} sells to {} at their price list's price again",
+                    item_name, customer_name
+                ),
+            },
+            error: None,
+            price,
+        })
+    }
+
+    /// Create a promo code for `createSale`: a percentage or fixed discount
+    /// off the whole sale, or off one item's lines when `inventoryId` is set
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_promo_code(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePromoCodeInput,
+    ) -> Result<PromoCodeResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let code = discounts::normalize_code(&input.code);
+        if code.is_empty()
+            || code.len() > 50
+            || !code
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Ok(PromoCodeResult::failed(
+                "Promo codes are 1 to 50 letters, digits, '-' and '_'",
+            ));
+        }
+        if let Err(message) = discounts::check(&input.discount_type, &input.value) {
+            return Ok(PromoCodeResult::failed(&message));
+        }
+        if let Some(message) = promo_code_limits(input.starts_on, input.ends_on, input.max_uses) {
+            return Ok(PromoCodeResult::failed(message));
+        }
+        if let Some(inventory_id) = input.inventory_id {
+            let exists = sqlx::query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1) AS "exists!""#,
+                inventory_id
+            )
+            .fetch_one(pool)
+            .await?;
+            if !exists {
+                return Ok(PromoCodeResult::not_found("Inventory item"));
+            }
+        }
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO promo_codes (
+                code, description, discount_type, value, inventory_id, starts_on, ends_on, max_uses
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (code) DO NOTHING
+            RETURNING id
+            "#,
+            code,
+            input.description,
+            input.discount_type,
+            input.value,
+            input.inventory_id,
+            input.starts_on,
+            input.ends_on,
+            input.max_uses
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(id) = id else {
+            return Ok(PromoCodeResult {
+                error: Some(MutationError::name_conflict("Promo code", &code)),
+                ..PromoCodeResult::failed(&format!("Promo code {} already exists", code))
+            });
+        };
+        let promo_code = discounts::by_id(pool, id).await?;
+
+        Ok(PromoCodeResult {
+            success: true,
+            message: format!("Created promo code {}", code),
+            error: None,
+            promo_code,
+        })
+    }
+
+    /// Change when a promo code can be used, how often, or switch it off
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_promo_code(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdatePromoCodeInput,
+    ) -> Result<PromoCodeResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT starts_on, ends_on, max_uses FROM promo_codes WHERE id = $1 FOR UPDATE",
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(existing) = existing else {
+            return Ok(PromoCodeResult::not_found("Promo code"));
+        };
+        if let Some(message) = promo_code_limits(
+            input.starts_on.or(existing.starts_on),
+            input.ends_on.or(existing.ends_on),
+            input.max_uses.or(existing.max_uses),
+        ) {
+            return Ok(PromoCodeResult::failed(message));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE promo_codes
+            SET description = COALESCE($2, description),
+                starts_on = COALESCE($3, starts_on),
+                ends_on = COALESCE($4, ends_on),
+                max_uses = COALESCE($5, max_uses),
+                is_active = COALESCE($6, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            input.id,
+            input.description,
+            input.starts_on,
+            input.ends_on,
+            input.max_uses,
+            input.is_active
+        )
+        .execute(&mut *tx)
+        .await?;
+        let promo_code = discounts::by_id(&mut *tx, input.id).await?;
+        tx.commit().await?;
+
+        Ok(PromoCodeResult {
+            success: true,
+            message: match &promo_code {
+                Some(promo_code) => format!("Updated promo code {}", promo_code.code),
+                None => "Updated promo code".to_string(),
+            },
+            error: None,
+            promo_code,
+        })
+    }
+
+    /// Close the stock review of an oversold sale once the count has been
+    /// squared up (a stocktake or a late receipt), noting what was found.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn resolve_sale_stock_review(
+        &self,
+        ctx: &Context<'_>,
+        input: ResolveSaleStockReviewInput,
+    ) -> Result<SaleReviewResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT sale_number, stock_review_status FROM sales WHERE id = $1 FOR UPDATE",
+            input.sale_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(SaleReviewResult::not_found("Sale"));
+        };
+        match existing.stock_review_status.as_deref() {
+            Some("pending") => {}
+            Some(_) => {
+                return Ok(SaleReviewResult::failed(&format!(
+                    "Sale {} has already been reviewed",
+                    existing.sale_number
+                )));
+            }
+            None => {
+                return Ok(SaleReviewResult::failed(&format!(
+                    "Sale {} isn't waiting for stock review",
+                    existing.sale_number
+                )));
+            }
+        }
+
+        let note = input.note.filter(|note| !note.trim().is_empty());
+        let sale = sqlx::query_as!(
+            Sale,
+            r#"
+            UPDATE sales
+            SET stock_review_status = 'resolved', stock_review_note = $1, updated_at = NOW()
+            WHERE id = $2
+            RETURNING
+                id, sale_number, customer_id, sale_date,
+                subtotal, tax_amount, discount_amount, total_amount,
+                payment_method, payment_status, notes, channel, stock_check, oversold,
+                stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+            "#,
+            note,
+            input.sale_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(SaleReviewResult {
+            success: true,
+            message: format!("Resolved the stock review of sale {}", sale.sale_number),
+            error: None,
+            sale: Some(sale),
+        })
+    }
+
+    /// Open a pickup window for collecting orders at the shop
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_pickup_window(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePickupWindowInput,
+    ) -> Result<PickupWindowResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if let Err(message) =
+            pickups::validate_window(input.start_time, input.end_time, input.capacity)
+        {
+            return Ok(PickupWindowResult::failed(&message));
+        }
+
+        if let Some(message) = pickups::closure(pool, input.pickup_date).await? {
+            return Ok(PickupWindowResult::failed(&message));
+        }
+
+        let window = sqlx::query_as!(
+            PickupWindow,
+            r#"
+            INSERT INTO pickup_windows (pickup_date, start_time, end_time, capacity, notes)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id, pickup_date, start_time, end_time, capacity, notes, is_active,
+                created_at, updated_at
+            "#,
+            input.pickup_date,
+            input.start_time,
+            input.end_time,
+            input.capacity,
+            input.notes
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(PickupWindowResult {
+            success: true,
+            message: format!(
+                "Opened pickup window {} {}-{} for {} orders",
+                window.pickup_date,
+                window.start_time.format("%H:%M"),
+                window.end_time.format("%H:%M"),
+                window.capacity
+            ),
+            error: None,
+            window: Some(window),
+        })
+    }
+
+    /// Change a pickup window's times or capacity, or stop taking bookings
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_pickup_window(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdatePickupWindowInput,
+    ) -> Result<PickupWindowResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        // Lock the window so bookings can't slip in while capacity changes
+        let current = sqlx::query!(
+            r#"
+            SELECT
+                start_time, end_time, capacity,
+                (SELECT COUNT(*) FROM pickup_bookings
+                 WHERE pickup_window_id = w.id AND status <> 'cancelled') AS "booked!"
+            FROM pickup_windows w
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(current) = current else {
+            return Ok(PickupWindowResult::not_found("Pickup window"));
+        };
+
+        let capacity = input.capacity.unwrap_or(current.capacity);
+        if let Err(message) = pickups::validate_window(
+            input.start_time.unwrap_or(current.start_time),
+            input.end_time.unwrap_or(current.end_time),
+            capacity,
+        ) {
+            return Ok(PickupWindowResult::failed(&message));
+        }
+
+        if i64::from(capacity) < current.booked {
+            return Ok(PickupWindowResult::failed(&format!(
+                "Capacity cannot drop below the {} orders already booked",
+                current.booked
+            )));
+        }
+
+        let window = sqlx::query_as!(
+            PickupWindow,
+            r#"
+            UPDATE pickup_windows
+            SET
+                start_time = COALESCE($2, start_time),
+                end_time = COALESCE($3, end_time),
+                capacity = COALESCE($4, capacity),
+                notes = COALESCE($5, notes),
+                is_active = COALESCE($6, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, pickup_date, start_time, end_time, capacity, notes, is_active,
+                created_at, updated_at
+            "#,
+            input.id,
+            input.start_time,
+            input.end_time,
+            input.capacity,
+            input.notes,
+            input.is_active
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(PickupWindowResult {
+            success: true,
+            message: format!("Updated pickup window {}", window.pickup_date),
+            error: None,
+            window: Some(window),
+        })
+    }
+
+    /// Book an order into a pickup window, or move its booking to another window
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn book_pickup(
+        &self,
+        ctx: &Context<'_>,
+        input: BookPickupInput,
+    ) -> Result<PickupBookingResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let sale = sqlx::query!(
+            "SELECT sale_number, customer_id FROM sales WHERE id = $1",
+            input.sale_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(sale) = sale else {
+            return Ok(PickupBookingResult::not_found("Order"));
+        };
+
+        let existing = sqlx::query!(
+            "SELECT status FROM pickup_bookings WHERE sale_id = $1 FOR UPDATE",
+            input.sale_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if existing.as_ref().is_some_and(|b| b.status == "picked_up") {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Order {} has already been picked up",
+                sale.sale_number
+            )));
+        }
+
+        // Lock the window so concurrent bookings can't overfill it
+        let window = sqlx::query!(
+            r#"
+            SELECT
+                pickup_date, start_time, end_time, capacity, is_active,
+                (SELECT COUNT(*) FROM pickup_bookings
+                 WHERE pickup_window_id = w.id AND status <> 'cancelled'
+                    AND sale_id <> $2) AS "booked!"
+            FROM pickup_windows w
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            input.pickup_window_id,
+            input.sale_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(window) = window else {
+            return Ok(PickupBookingResult::not_found("Pickup window"));
+        };
+
+        if !window.is_active {
+            return Ok(PickupBookingResult::failed(
+                "Pickup window is not taking bookings",
+            ));
+        }
+
+        if window.pickup_date < Utc::now().date_naive() {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Pickup window {} has already passed",
+                window.pickup_date
+            )));
+        }
+
+        if let Some(message) = pickups::closure(&mut *tx, window.pickup_date).await? {
+            return Ok(PickupBookingResult::failed(&message));
+        }
+
+        if window.booked >= i64::from(window.capacity) {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Pickup window {} {}-{} is full ({} orders)",
+                window.pickup_date,
+                window.start_time.format("%H:%M"),
+                window.end_time.format("%H:%M"),
+                window.capacity
+            )));
+        }
+
+        let booking = sqlx::query_as!(
+            PickupBooking,
+            r#"
+            INSERT INTO pickup_bookings (pickup_window_id, sale_id, customer_id, notes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (sale_id) DO UPDATE SET
+                pickup_window_id = EXCLUDED.pickup_window_id,
+                customer_id = EXCLUDED.customer_id,
+                status = 'booked',
+                reminded_at = NULL,
+                notes = COALESCE(EXCLUDED.notes, pickup_bookings.notes),
+                updated_at = NOW()
+            RETURNING
+                id, pickup_window_id, sale_id, customer_id, status, reminded_at,
+                picked_up_at, notes, created_at, updated_at
+            "#,
+            input.pickup_window_id,
+            input.sale_id,
+            sale.customer_id,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(PickupBookingResult {
+            success: true,
+            message: format!(
+                "{} order {} for pickup {} {}-{}",
+                if existing.is_some() {
+                    "Rebooked"
+                } else {
+                    "Booked"
+                },
+                sale.sale_number,
+                window.pickup_date,
+                window.start_time.format("%H:%M"),
+                window.end_time.format("%H:%M")
+            ),
+            error: None,
+            booking: Some(booking),
+        })
+    }
+
+    /// Record a booking as picked up, a no-show, or cancelled. No-shows raise
+    /// a `pickup_no_show` alert with the customer's running count.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_pickup_booking(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdatePickupBookingInput,
+    ) -> Result<PickupBookingResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if !pickups::OUTCOMES.contains(&input.status.as_str()) {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Status must be one of: {}",
+                pickups::OUTCOMES.join(", ")
+            )));
+        }
+
+        let current = sqlx::query!(
+            r#"
+            SELECT b.status, s.sale_number
+            FROM pickup_bookings b
+            JOIN sales s ON s.id = b.sale_id
+            WHERE b.id = $1
+            FOR UPDATE OF b
+            "#,
+            input.booking_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(current) = current else {
+            return Ok(PickupBookingResult::not_found("Pickup booking"));
+        };
+
+        // A no-show can still turn up late; anything else is final
+        if current.status != "booked" && current.status != "no_show" {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Order {} is already {}",
+                current.sale_number, current.status
+            )));
+        }
+
+        if current.status == input.status {
+            return Ok(PickupBookingResult::failed(&format!(
+                "Order {} is already {}",
+                current.sale_number, current.status
+            )));
+        }
+
+        let booking = sqlx::query_as!(
+            PickupBooking,
+            r#"
+            UPDATE pickup_bookings
+            SET
+                status = $2,
+                picked_up_at = CASE WHEN $2::varchar = 'picked_up' THEN NOW() END,
+                notes = COALESCE($3, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, pickup_window_id, sale_id, customer_id, status, reminded_at,
+                picked_up_at, notes, created_at, updated_at
+            "#,
+            input.booking_id,
+            input.status,
+            input.notes
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if booking.status == "no_show" {
+            let customer = match booking.customer_id {
+                Some(customer_id) => {
+                    let name = sqlx::query_scalar!(
+                        "SELECT name FROM customers WHERE id = $1",
+                        customer_id
+                    )
+                    .fetch_one(pool)
+                    .await?;
+                    Some((name, pickups::no_show_count(pool, customer_id).await?))
+                }
+                None => None,
+            };
+
+            let (name, no_shows) = customer.unwrap_or_else(|| ("Walk-in customer".to_string(), 1));
+            ctx.data::<Notifier>()?
+                .send(&Alert {
+                    kind: "pickup_no_show".to_string(),
+                    message: format!(
+                        "{} missed the pickup of order {} ({} missed pickup(s) so far)",
+                        name, current.sale_number, no_shows
+                    ),
+                    data: serde_json::json!({
+                        "booking_id": booking.id,
+                        "sale_id": booking.sale_id,
+                        "customer_id": booking.customer_id,
+                        "no_show_count": no_shows,
+                    }),
+                })
+                .await;
+        }
+
+        Ok(PickupBookingResult {
+            success: true,
+            message: format!(
+                "Order {} marked {}",
+                current.sale_number,
+                booking.status.replace('_', " ")
+            ),
+            error: None,
+            booking: Some(booking),
+        })
+    }
+
+    /// Log a customer complaint and open reviews of sibling lots from the same batch
+    #[graphql(guard = "RequireRole(auth::STAFF)")]
+    async fn create_complaint(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateComplaintInput,
+    ) -> Result<ComplaintResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let severity = input.severity.unwrap_or_else(|| "medium".to_string());
+        if !["low", "medium", "high"].contains(&severity.as_str()) {
+            return Ok(ComplaintResult {
+                success: false,
+                message: "Severity must be one of: low, medium, high".to_string(),
+                error: Some(MutationError::invalid()),
+                complaint: None,
+            });
+        }
+
+        if input.description.trim().is_empty() {
+            return Ok(ComplaintResult {
+                success: false,
+                message: "Description is required".to_string(),
+                error: Some(MutationError::invalid()),
+                complaint: None,
+            });
+        }
+
+        if input.lot_id.is_none() && input.batch_number.is_none() && input.sale_id.is_none() {
+            return Ok(ComplaintResult {
+                success: false,
+                message: "Provide a lot, batch number, or sale so the complaint can be traced"
+                    .to_string(),
+                error: Some(MutationError::invalid()),
+                complaint: None,
+            });
+        }
+
+        let mut lot_id = input.lot_id;
+        let mut inventory_id = input.inventory_id;
+        let mut batch_id = None;
+
+        // 1. Resolve the lot and the batch behind it
+        if let Some(id) = lot_id {
+            let lot = sqlx::query!(
+                "SELECT inventory_id, production_batch_id FROM inventory_lots WHERE id = $1",
+                id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(lot) = lot else {
+                return Ok(ComplaintResult {
+                    success: false,
+                    message: "Lot not found".to_string(),
+                    error: Some(MutationError::not_found("Lot")),
+                    complaint: None,
+                });
+            };
+
+            inventory_id = inventory_id.or(Some(lot.inventory_id));
+            batch_id = lot.production_batch_id;
+        }
+
+        if let Some(ref batch_number) = input.batch_number {
+            let batch = sqlx::query!(
+                "SELECT id, product_inventory_id FROM production_batches WHERE batch_number = $1",
+                batch_number
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some(batch) = batch else {
+                return Ok(ComplaintResult {
+                    success: false,
+                    message: format!("Batch {} not found", batch_number),
+                    error: Some(MutationError::not_found("Batch")),
+                    complaint: None,
+                });
+            };
+
+            inventory_id = inventory_id.or(Some(batch.product_inventory_id));
+            batch_id = Some(batch.id);
+        }
+
+        // 2. Fall back to the lots drawn by the sale
+        if let Some(sale_id) = input.sale_id {
+            let sale = sqlx::query!("SELECT id FROM sales WHERE id = $1", sale_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            if sale.is_none() {
+                return Ok(ComplaintResult {
+                    success: false,
+                    message: "Sale not found".to_string(),
+                    error: Some(MutationError::not_found("Sale")),
+                    complaint: None,
+                });
+            }
+
+            if lot_id.is_none() && batch_id.is_none() {
+                let sold_lot = sqlx::query!(
+                    r#"
+                    SELECT l.id, l.inventory_id, l.production_batch_id
+                    FROM lot_consumptions c
+                    JOIN sale_items si ON si.id = c.sale_item_id
+                    JOIN inventory_lots l ON l.id = c.lot_id
+                    WHERE si.sale_id = $1
+                        AND ($2::uuid IS NULL OR si.inventory_id = $2)
+                    ORDER BY l.production_batch_id IS NULL, c.quantity DESC
+                    LIMIT 1
+                    "#,
+                    sale_id,
+                    inventory_id
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                if let Some(sold_lot) = sold_lot {
+                    lot_id = Some(sold_lot.id);
+                    inventory_id = inventory_id.or(Some(sold_lot.inventory_id));
+                    batch_id = sold_lot.production_batch_id;
+                }
+            }
+        }
+
+        // 3. Record the complaint
+        let complaint_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO complaints (
+                customer_id, sale_id, inventory_id, lot_id, production_batch_id,
+                description, severity, reported_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#,
+            input.customer_id,
+            input.sale_id,
+            inventory_id,
+            lot_id,
+            batch_id,
+            input.description,
+            severity,
+            input.reported_at.unwrap_or_else(Utc::now)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // 4. Open a review for every lot from the same batch that is still on hand
+        let reviews = sqlx::query!(
+            r#"
+            INSERT INTO complaint_lot_reviews (complaint_id, lot_id)
+            SELECT $1, id
+            FROM inventory_lots
+            WHERE remaining_quantity > 0
+                AND (($2::uuid IS NOT NULL AND production_batch_id = $2) OR id = $3)
+            ON CONFLICT (complaint_id, lot_id) DO NOTHING
+            "#,
+            complaint_id,
+            batch_id,
+            lot_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let details = load_complaint_details(&mut tx, complaint_id).await?;
+        tx.commit().await?;
+
+        let message = format!(
+            "Complaint logged; {} sibling lot(s) flagged for review",
+            reviews.rows_affected()
+        );
+
+        if severity == "high" {
+            let notifier = ctx.data::<Notifier>()?;
+            notifier
+                .send(&Alert {
+                    kind: "complaint".to_string(),
+                    message: format!("High severity complaint: {}", input.description),
+                    data: serde_json::to_value(&details.complaint).unwrap_or_default(),
+                })
+                .await;
+        }
+
+        Ok(ComplaintResult {
+            success: true,
+            message,
+            error: None,
+            complaint: Some(details),
+        })
+    }
+
+    /// Record the outcome of a sibling lot review (discarding writes off the lot)
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn review_complaint_lot(
+        &self,
+        ctx: &Context<'_>,
+        input: ReviewComplaintLotInput,
+    ) -> Result<ComplaintLotReviewResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if !["cleared", "quarantined", "discarded"].contains(&input.status.as_str()) {
+            return Ok(ComplaintLotReviewResult {
+                success: false,
+                message: "Status must be one of: cleared, quarantined, discarded".to_string(),
+                error: Some(MutationError::invalid()),
+                review: None,
+            });
+        }
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT r.status, r.lot_id, l.inventory_id, l.remaining_quantity, l.lot_number
+            FROM complaint_lot_reviews r
+            JOIN inventory_lots l ON l.id = r.lot_id
+            WHERE r.id = $1
+            FOR UPDATE OF l
+            "#,
+            input.review_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(ComplaintLotReviewResult {
+                success: false,
+                message: "Lot review not found".to_string(),
+                error: Some(MutationError::not_found("Lot review")),
+                review: None,
+            });
+        };
+
+        if existing.status == "discarded" {
+            return Ok(ComplaintLotReviewResult {
+                success: false,
+                message: "Lot has already been discarded".to_string(),
+                error: Some(MutationError::invalid()),
+                review: None,
+            });
+        }
+
+        // Write off whatever is left of the lot
+        if input.status == "discarded" && existing.remaining_quantity > BigDecimal::from(0) {
+            let now = Utc::now();
+
+            sqlx::query!(
+                "UPDATE inventory_lots SET remaining_quantity = 0, updated_at = $1 WHERE id = $2",
+                now,
+                existing.lot_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+                existing.remaining_quantity,
+                now,
+                existing.inventory_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO inventory_logs (
+                    inventory_id, movement_type, quantity, reason, batch_number, created_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                existing.inventory_id,
+                "waste",
+                -existing.remaining_quantity.clone(), // Negative for stock reduction
+                "Lot discarded after complaint review",
+                existing.lot_number,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let review = sqlx::query_as!(
+            ComplaintLotReview,
+            r#"
+            UPDATE complaint_lot_reviews
+            SET status = $1, notes = COALESCE($2, notes), reviewed_at = NOW()
+            WHERE id = $3
+            RETURNING id, complaint_id, lot_id, status, notes, reviewed_at, created_at
+            "#,
+            input.status,
+            input.notes,
+            input.review_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(ComplaintLotReviewResult {
+            success: true,
+            message: format!("Lot review marked as {}", review.status),
+            error: None,
+            review: Some(review),
+        })
+    }
+
+    /// Resolve a complaint with a refund, replacement, or no action
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn resolve_complaint(
+        &self,
+        ctx: &Context<'_>,
+        input: ResolveComplaintInput,
+    ) -> Result<ComplaintResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let complaint = sqlx::query!(
+            "SELECT status, sale_id, inventory_id FROM complaints WHERE id = $1 FOR UPDATE",
+            input.complaint_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(complaint) = complaint else {
+            return Ok(ComplaintResult {
+                success: false,
+                message: "Complaint not found".to_string(),
+                error: Some(MutationError::not_found("Complaint")),
+                complaint: None,
+            });
+        };
+
+        if complaint.status != "open" {
+            return Ok(ComplaintResult {
+                success: false,
+                message: format!("Complaint is already {}", complaint.status),
+                error: Some(MutationError::invalid()),
+                complaint: None,
+            });
+        }
+
+        let zero = BigDecimal::from(0);
+        let now = Utc::now();
+
+        match input.disposition.as_str() {
+            "refund" => {
+                let Some(ref refund_amount) = input.refund_amount else {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: "Refund amount is required for a refund".to_string(),
+                        error: Some(MutationError::invalid()),
+                        complaint: None,
+                    });
+                };
+
+                if *refund_amount <= zero {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: "Refund amount must be greater than 0".to_string(),
+                        error: Some(MutationError::invalid()),
+                        complaint: None,
+                    });
+                }
+
+                // A full refund flips the original sale to refunded, and
+                // voids what was still owed on its invoice
+                if let Some(sale_id) = complaint.sale_id {
+                    let refunded = sqlx::query!(
+                        r#"
+                        UPDATE sales
+                        SET payment_status = 'refunded', updated_at = $1
+                        WHERE id = $2 AND total_amount <= $3
+                        "#,
+                        now,
+                        sale_id,
+                        refund_amount
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+
+                    if refunded.rows_affected() > 0 {
+                        invoices::void_for_sale(&mut *tx, sale_id).await?;
+                    }
+                }
+            }
+            "replace" => {
+                let Some(ref quantity) = input.replacement_quantity else {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: "Replacement quantity is required for a replacement".to_string(),
+                        error: Some(MutationError::invalid()),
+                        complaint: None,
+                    });
+                };
+
+                let Some(inventory_id) = complaint.inventory_id else {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: "Complaint has no product to replace".to_string(),
+                        error: Some(MutationError::invalid()),
+                        complaint: None,
+                    });
+                };
+
+                if *quantity <= zero {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: "Replacement quantity must be greater than 0".to_string(),
+                        error: Some(MutationError::invalid()),
+                        complaint: None,
+                    });
+                }
+
+                // Stock confirmed orders reserved can't go out as a replacement
+                let item = sqlx::query!(
+                    r#"SELECT name, available_stock AS "available_stock!" FROM inventory WHERE id = $1 FOR UPDATE"#,
+                    inventory_id
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+
+                if item.available_stock < *quantity {
+                    return Ok(ComplaintResult {
+                        success: false,
+                        message: format!(
+                            "Insufficient stock for {}: need {}, have {}",
+                            item.name, quantity, item.available_stock
+                        ),
+                        error: Some(MutationError::insufficient_stock(
+                            inventory_id,
+                            &item.name,
+                            quantity,
+                            &item.available_stock,
+                        )),
+                        complaint: None,
+                    });
+                }
+
+                sqlx::query!(
+                    "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+                    quantity,
+                    now,
+                    inventory_id
+                )
+                .execute(&mut *tx)
+                .await?;
+
+                lots::consume(&mut tx, inventory_id, quantity).await?;
+
+                sqlx::query!(
+                    r#"
+                    INSERT INTO inventory_logs (
+                        inventory_id, movement_type, quantity, reason, created_at
+                    ) VALUES ($1, $2, $3, $4, $5)
+                    "#,
+                    inventory_id,
+                    "replacement",
+                    -quantity.clone(), // Negative for stock reduction
+                    "Replacement for customer complaint",
+                    now
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            "no_action" => {}
+            _ => {
+                return Ok(ComplaintResult {
+                    success: false,
+                    message: "Disposition must be one of: refund, replace, no_action".to_string(),
+                    error: Some(MutationError::invalid()),
+                    complaint: None,
+                });
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE complaints
+            SET status = 'resolved',
+                disposition = $1,
+                refund_amount = $2,
+                replacement_quantity = $3,
+                resolution_notes = $4,
+                resolved_at = $5,
+                updated_at = $5
+            WHERE id = $6
+            "#,
+            input.disposition,
+            input.refund_amount,
+            input.replacement_quantity,
+            input.resolution_notes,
+            now,
+            input.complaint_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let details = load_complaint_details(&mut tx, input.complaint_id).await?;
+        tx.commit().await?;
+
+        Ok(ComplaintResult {
+            success: true,
+            message: format!("Complaint resolved ({})", input.disposition),
+            error: None,
+            complaint: Some(details),
+        })
+    }
+
+    /// Show or hide a field on the public batch trace page that jar QR
+    /// codes open (e.g. hide suppliers while a contract is confidential)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_trace_page_field(
+        &self,
+        ctx: &Context<'_>,
+        input: SetTracePageFieldInput,
+    ) -> Result<TracePageFieldResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let Some((field, description)) = traceability::TRACE_PAGE_FIELDS
+            .iter()
+            .find(|(field, _)| *field == input.field)
+        else {
+            return Ok(TracePageFieldResult::failed(&format!(
+                "Unknown trace page field '{}'. Choose from: {}",
+                input.field,
+                traceability::TRACE_PAGE_FIELDS
+                    .iter()
+                    .map(|(field, _)| *field)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO trace_page_fields (field, visible)
+            VALUES ($1, $2)
+            ON CONFLICT (field) DO UPDATE SET visible = EXCLUDED.visible, updated_at = NOW()
+            "#,
+            field,
+            input.visible
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(TracePageFieldResult {
+            success: true,
+            message: format!(
+                "The trace page now {} {}",
+                if input.visible { "shows" } else { "hides" },
+                field.replace('_', " ")
+            ),
+            error: None,
+            field: Some(TracePageField {
+                field: field.to_string(),
+                description: description.to_string(),
+                visible: input.visible,
+            }),
+        })
+    }
+
+    /// Import historical batches, sales, or purchases exported from the legacy spreadsheets.
+    ///
+    /// Original numbers and dates are kept. Re-running an import skips rows that
+    /// were already imported, so a sheet can be imported again after fixing
+    /// rejected rows.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn import_legacy_records(
+        &self,
+        ctx: &Context<'_>,
+        input: LegacyImportInput,
+    ) -> Result<LegacyImportResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let failure = |message: String| LegacyImportResult {
+            success: false,
+            message,
+            error: Some(MutationError::invalid()),
+            imported: 0,
+            skipped: 0,
+            rejected: 0,
+            flagged: 0,
+            outcomes: Vec::new(),
+        };
+
+        let Some(record_type) = legacy_import::RecordType::parse(&input.record_type) else {
+            return Ok(failure(
+                "Record type must be one of: batches, sales, purchases".to_string(),
+            ));
+        };
+
+        let mapping = match legacy_import::Mapping::new(record_type, input.mapping.as_ref()) {
+            Ok(mapping) => mapping,
+            Err(message) => return Ok(failure(message)),
+        };
+
+        let Some(rows) = input.rows.as_array() else {
+            return Ok(failure(
+                "Rows must be a JSON array of objects keyed by column header".to_string(),
+            ));
+        };
+
+        let dry_run = input.dry_run.unwrap_or(false);
+        let outcomes = legacy_import::run(pool, &mapping, rows, dry_run).await?;
+
+        let count = |status: &str| outcomes.iter().filter(|o| o.status == status).count() as i32;
+        let imported = count("imported");
+        let skipped = count("skipped");
+        let rejected = count("rejected");
+        let flagged = outcomes
+            .iter()
+            .filter(|o| o.status == "imported" && !o.issues.is_empty())
+            .count() as i32;
+
+        Ok(LegacyImportResult {
+            success: true,
+            message: format!(
+                "{}{} imported ({} flagged), {} already imported, {} rejected",
+                if dry_run { "Dry run: " } else { "" },
+                imported,
+                flagged,
+                skipped,
+                rejected
+            ),
+            error: None,
+            imported,
+            skipped,
+            rejected,
+            flagged,
+            outcomes,
+        })
+    }
+
+    /// Import inventory items or suppliers from a CSV file, so onboarding a
+    /// catalog isn't one mutation per row. Rows are matched to existing
+    /// records by name; `upsert` updates them from the row's non-empty cells
+    /// instead of rejecting the row. A dry run reports what each row would do.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn import_csv(
+        &self,
+        ctx: &Context<'_>,
+        input: CsvImportInput,
+    ) -> Result<CsvImportResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let failure = |message: String| CsvImportResult {
+            success: false,
+            message,
+            error: Some(MutationError::invalid()),
+            created: 0,
+            updated: 0,
+            rejected: 0,
+            outcomes: Vec::new(),
+        };
+
+        let Some(entity) = csv_import::Entity::parse(&input.entity) else {
+            return Ok(failure(
+                "Entity must be one of: inventory, suppliers".to_string(),
+            ));
+        };
+
+        let Some(text) = BASE64
+            .decode(input.file.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+        else {
+            return Ok(failure("File must be base64-encoded UTF-8 CSV".to_string()));
+        };
+
+        let records = match vendor_catalog::parse_csv(&text) {
+            Ok(records) => records,
+            Err(message) => return Ok(failure(message)),
+        };
+        let Some((header, rows)) = records.split_first() else {
+            return Ok(failure("The file is empty".to_string()));
+        };
+
+        let columns = match csv_import::Columns::new(entity, header, input.mapping.as_ref()) {
+            Ok(columns) => columns,
+            Err(message) => return Ok(failure(message)),
+        };
+
+        let dry_run = input.dry_run.unwrap_or(false);
+        let outcomes =
+            csv_import::run(pool, &columns, rows, input.upsert.unwrap_or(false), dry_run).await?;
+
+        let count = |status: &str| outcomes.iter().filter(|o| o.status == status).count() as i32;
+        let created = count("created");
+        let updated = count("updated");
+        let rejected = count("rejected");
+
+        Ok(CsvImportResult {
+            success: true,
+            message: format!(
+                "{}{} created, {} updated, {} rejected",
+                if dry_run { "Dry run: " } else { "" },
+                created,
+                updated,
+                rejected
+            ),
+            error: None,
+            created,
+            updated,
+            rejected,
+            outcomes,
+        })
+    }
+
+    /// Store an integration secret (API key, password, signing secret), encrypted at rest
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_secret(&self, ctx: &Context<'_>, input: SetSecretInput) -> Result<SecretResult> {
+        let secrets = ctx.data::<SecretStore>()?;
+
+        let name = input.name.trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Ok(SecretResult {
+                success: false,
+                message: "Secret name must use lowercase letters, digits, and underscores"
+                    .to_string(),
+                error: Some(MutationError::invalid()),
+                secret: None,
+            });
+        }
+
+        if input.value.is_empty() {
+            return Ok(SecretResult {
+                success: false,
+                message: "Secret value cannot be empty".to_string(),
+                error: Some(MutationError::invalid()),
+                secret: None,
+            });
+        }
+
+        match secrets
+            .set(name, &input.value, input.description.as_deref(), false)
+            .await
+        {
+            Ok(secret) => Ok(SecretResult {
+                success: true,
+                message: format!(
+                    "Saved secret '{}' (version {})",
+                    secret.name, secret.version
+                ),
+                error: None,
+                secret: Some(secret),
+            }),
+            Err(SecretError::Database(e)) => Err(e.into()),
+            Err(e) => Ok(SecretResult {
+                success: false,
+                message: e.to_string(),
+                error: Some(MutationError::invalid()),
+                secret: None,
+            }),
+        }
+    }
+
+    /// Replace the value of an existing integration secret
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn rotate_secret(
+        &self,
+        ctx: &Context<'_>,
+        input: RotateSecretInput,
+    ) -> Result<SecretResult> {
+        let secrets = ctx.data::<SecretStore>()?;
+
+        if !secrets.exists(&input.name).await? {
+            return Ok(SecretResult {
+                success: false,
+                message: format!("Secret '{}' not found", input.name),
+                error: Some(MutationError::not_found("Secret")),
+                secret: None,
+            });
+        }
+
+        if input.value.is_empty() {
+            return Ok(SecretResult {
+                success: false,
+                message: "Secret value cannot be empty".to_string(),
+                error: Some(MutationError::invalid()),
+                secret: None,
+            });
+        }
+
+        match secrets.set(&input.name, &input.value, None, true).await {
+            Ok(secret) => Ok(SecretResult {
+                success: true,
+                message: format!(
+                    "Rotated secret '{}' (now version {})",
+                    secret.name, secret.version
+                ),
+                error: None,
+                secret: Some(secret),
+            }),
+            Err(SecretError::Database(e)) => Err(e.into()),
+            Err(e) => Ok(SecretResult {
+                success: false,
+                message: e.to_string(),
+                error: Some(MutationError::invalid()),
+                secret: None,
+            }),
+        }
+    }
+
+    /// Delete an integration secret (integrations fall back to env vars)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_secret(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteSecretInput,
+    ) -> Result<DeleteResult> {
+        let secrets = ctx.data::<SecretStore>()?;
+
+        if !secrets.delete(&input.name).await? {
+            return Ok(DeleteResult {
+                success: false,
+                message: format!("Secret '{}' not found", input.name),
+                error: Some(MutationError::not_found("Secret")),
+            });
+        }
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Successfully deleted secret '{}'", input.name),
+            error: None,
+        })
+    }
+
+    /// Register a URL to be posted domain events (batchCompleted, saleCreated,
+    /// lowStock, purchaseReceived). Deliveries are signed with the
+    /// subscription's secret, which is generated and returned once when not
+    /// given.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_webhook_subscription(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateWebhookSubscriptionInput,
+    ) -> Result<WebhookSubscriptionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        if let Some(message) = webhooks::check_url(&input.url) {
+            return Ok(WebhookSubscriptionResult::failed(&message));
+        }
+        if let Some(message) = webhooks::check_event_types(&input.event_types) {
+            return Ok(WebhookSubscriptionResult::failed(&message));
+        }
+
+        let (secret, generated) = match input.secret {
+            Some(secret) if secret.chars().count() < webhooks::MIN_SECRET_LENGTH => {
+                return Ok(WebhookSubscriptionResult::failed(&format!(
+                    "Signing secret must be at least {} characters",
+                    webhooks::MIN_SECRET_LENGTH
+                )));
+            }
+            Some(secret) => (secret, false),
+            None => (webhooks::generate_secret(), true),
+        };
+
+        let mut event_types = input.event_types;
+        event_types.sort();
+        event_types.dedup();
+
+        // The secret is stored first, so a subscription never exists without one
+        let id = Uuid::new_v4();
+        match secrets
+            .set(
+                &webhooks::secret_name(id),
+                &secret,
+                Some("Outbound webhook signing secret"),
+                false,
+            )
+            .await
+        {
+            Ok(_) => {}
+            Err(SecretError::Database(e)) => return Err(e.into()),
+            Err(e) => return Ok(WebhookSubscriptionResult::failed(&e.to_string())),
+        }
+
+        let subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            INSERT INTO webhook_subscriptions (id, url, event_types, description)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, url, event_types, description, is_active, created_at, updated_at
+            "#,
+            id,
+            input.url.trim(),
+            &event_types,
+            input.description
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(WebhookSubscriptionResult {
+            success: true,
+            message: format!(
+                "Registered webhook for {} to {}",
+                subscription.event_types.join(", "),
+                subscription.url
+            ),
+            error: None,
+            subscription: Some(subscription),
+            secret: generated.then_some(secret),
+        })
+    }
+
+    /// Change an outbound webhook's URL, events or note, pause or resume
+    /// it, or rotate its signing secret
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_webhook_subscription(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateWebhookSubscriptionInput,
+    ) -> Result<WebhookSubscriptionResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        if webhooks::subscription(pool, input.id).await?.is_none() {
+            return Ok(WebhookSubscriptionResult::not_found("Webhook subscription"));
+        }
+
+        if let Some(url) = &input.url
+            && let Some(message) = webhooks::check_url(url)
+        {
+            return Ok(WebhookSubscriptionResult::failed(&message));
+        }
+        let event_types = input.event_types.map(|mut event_types| {
+            event_types.sort();
+            event_types.dedup();
+            event_types
+        });
+        if let Some(event_types) = &event_types
+            && let Some(message) = webhooks::check_event_types(event_types)
+        {
+            return Ok(WebhookSubscriptionResult::failed(&message));
+        }
+
+        let secret = if input.rotate_secret.unwrap_or(false) {
+            let secret = webhooks::generate_secret();
+            match secrets
+                .set(&webhooks::secret_name(input.id), &secret, None, true)
+                .await
+            {
+                Ok(_) => Some(secret),
+                Err(SecretError::Database(e)) => return Err(e.into()),
+                Err(e) => return Ok(WebhookSubscriptionResult::failed(&e.to_string())),
+            }
+        } else {
+            None
+        };
+
+        let subscription = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            UPDATE webhook_subscriptions
+            SET url = COALESCE($2, url),
+                event_types = COALESCE($3, event_types),
+                description = COALESCE($4, description),
+                is_active = COALESCE($5, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, url, event_types, description, is_active, created_at, updated_at
+            "#,
+            input.id,
+            input.url.as_deref().map(str::trim),
+            event_types.as_deref(),
+            input.description,
+            input.is_active
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(WebhookSubscriptionResult {
+            success: true,
+            message: format!(
+                "Updated webhook to {}{}",
+                subscription.url,
+                if secret.is_some() {
+                    "; its signing secret was rotated"
+                } else {
+                    ""
+                }
+            ),
+            error: None,
+            subscription: Some(subscription),
+            secret,
+        })
+    }
+
+    /// Remove an outbound webhook, its delivery history and its signing secret
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_webhook_subscription(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteWebhookSubscriptionInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        let url = sqlx::query_scalar!(
+            "DELETE FROM webhook_subscriptions WHERE id = $1 RETURNING url",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(url) = url else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Webhook subscription not found".to_string(),
+                error: Some(MutationError::not_found("Webhook subscription")),
+            });
+        };
+
+        secrets.delete(&webhooks::secret_name(input.id)).await?;
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Deleted webhook to {}", url),
+            error: None,
+        })
+    }
+
+    /// Send a failed (or still pending) delivery again on the next dispatch
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn retry_webhook_delivery(
+        &self,
+        ctx: &Context<'_>,
+        input: RetryWebhookDeliveryInput,
+    ) -> Result<WebhookDeliveryResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let Some(delivery) = webhooks::delivery(pool, input.id).await? else {
+            return Ok(WebhookDeliveryResult::not_found("Webhook delivery"));
+        };
+
+        if delivery.status == "delivered" {
+            return Ok(WebhookDeliveryResult::failed(
+                "Delivery already went through",
+            ));
+        }
+
+        sqlx::query!(
+            "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, next_attempt_at = NOW() WHERE id = $1",
+            input.id
+        )
+        .execute(pool)
+        .await?;
+
+        let delivery = webhooks::delivery(pool, input.id).await?;
+        let event_type = delivery.as_ref().map_or("", |d| d.event_type.as_str());
+
+        Ok(WebhookDeliveryResult {
+            success: true,
+            message: format!("Queued {} delivery to be sent again", event_type),
+            error: None,
+            delivery,
+        })
+    }
+
+    /// Run a scheduled job (see `scheduledJobs`) now instead of waiting for
+    /// its schedule. The run is recorded like a scheduled one.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn run_job_now(
+        &self,
+        ctx: &Context<'_>,
+        input: RunJobNowInput,
+    ) -> Result<ScheduledJobResult> {
+        let scheduler = ctx.data::<Scheduler>()?;
+
+        match scheduler.run_now(input.name.trim()).await? {
+            Ok(job) => Ok(ScheduledJobResult {
+                success: true,
+                message: format!(
+                    "Ran {}: {}",
+                    job.name,
+                    job.last_message.as_deref().unwrap_or("done")
+                ),
+                error: None,
+                job: Some(job),
+            }),
+            Err(rejection) => Ok(ScheduledJobResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                job: None,
+            }),
+        }
+    }
+
+    /// Create a Stripe payment link for what a pending sale still owes (e.g.
+    /// a wholesale invoice). Paying it marks the sale's payment completed.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_payment_link(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePaymentLinkInput,
+    ) -> Result<PaymentLinkResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let http = ctx.data::<HttpClient>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        match stripe::create_payment_link(pool, http, secrets, input.sale_id).await? {
+            Ok(link) => Ok(PaymentLinkResult {
+                success: true,
+                message: format!(
+                    "Payment link for sale {} ({} {})",
+                    link.sale_number,
+                    link.amount.with_scale(2),
+                    link.currency.to_uppercase()
+                ),
+                error: None,
+                payment_link: Some(link),
+            }),
+            Err(rejection) => Ok(PaymentLinkResult {
+                success: false,
+                message: rejection.message,
+                error: Some(rejection.error),
+                payment_link: None,
+            }),
+        }
+    }
+
+    /// Record a payment against an invoice. Part payments leave it
+    /// partially paid; the one that settles it marks the sale's payment
+    /// completed.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn record_payment(
+        &self,
+        ctx: &Context<'_>,
+        input: RecordPaymentInput,
+    ) -> Result<PaymentResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let trimmed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let payment = match invoices::record_payment(
+            &mut tx,
+            input.invoice_id,
+            &input.amount,
+            input.paid_on.unwrap_or_else(|| Utc::now().date_naive()),
+            trimmed(&input.payment_method).as_deref(),
+            trimmed(&input.reference).as_deref(),
+            trimmed(&input.notes).as_deref(),
+        )
+        .await?
+        {
+            Ok(payment) => payment,
+            Err(rejection) => {
+                return Ok(PaymentResult {
+                    success: false,
+                    message: rejection.message,
+                    error: Some(rejection.error),
+                    invoice: None,
+                    payment: None,
+                });
+            }
+        };
+        let invoice = invoices::by_id(&mut *tx, input.invoice_id)
+            .await?
+            .ok_or("Invoice not found")?;
+
+        tx.commit().await?;
+
+        let message = match invoice.status.as_str() {
+            "paid" => format!("Invoice for sale {} paid in full", invoice.sale_number),
+            _ => format!(
+                "Recorded {} against sale {}; {} still owed",
+                payment.amount, invoice.sale_number, invoice.balance
+            ),
+        };
+        Ok(PaymentResult {
+            success: true,
+            message,
+            error: None,
+            invoice: Some(invoice),
+            payment: Some(payment),
+        })
+    }
+
+    /// Take a customer's order for delivery on a later day, as a draft.
+    /// Items without a price take the customer's.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn create_order(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let order_id = match orders::create(&mut tx, &input).await? {
+            Ok(order_id) => order_id,
+            Err(rejection) => return Ok(OrderResult::failed(rejection)),
+        };
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, order_id, "Took order").await
+    }
+
+    /// Change a draft order's delivery date, notes or items
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn update_order(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if let Err(rejection) = orders::update(&mut tx, &input).await? {
+            return Ok(OrderResult::failed(rejection));
+        }
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, input.order_id, "Updated order").await
+    }
+
+    /// Confirm a draft order, reserving its stock until it's fulfilled or
+    /// cancelled
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn confirm_order(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if let Err(rejection) = orders::confirm(&mut tx, input.order_id, Utc::now()).await? {
+            return Ok(OrderResult::failed(rejection));
+        }
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, input.order_id, "Confirmed order").await
+    }
+
+    /// Fulfil a confirmed order on delivery, selling it to the customer at
+    /// the order's prices. `paymentMethod: "invoice"` bills them at once.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn fulfill_order(
+        &self,
+        ctx: &Context<'_>,
+        input: FulfillOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let payment_method = input
+            .payment_method
+            .as_deref()
+            .map(str::trim)
+            .filter(|method| !method.is_empty());
+        if let Err(rejection) =
+            orders::fulfill(&mut tx, input.order_id, payment_method, Utc::now()).await?
+        {
+            return Ok(OrderResult::failed(rejection));
+        }
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, input.order_id, "Fulfilled order").await
+    }
+
+    /// Invoice a fulfilled order's sale, due after the customer's payment
+    /// terms
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn invoice_order(
+        &self,
+        ctx: &Context<'_>,
+        input: InvoiceOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        if let Err(rejection) = orders::invoice(&mut tx, input.order_id, Utc::now()).await? {
+            return Ok(OrderResult::failed(rejection));
+        }
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, input.order_id, "Invoiced order").await
+    }
+
+    /// Cancel a draft or confirmed order, releasing any stock it reserved
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn cancel_order(
+        &self,
+        ctx: &Context<'_>,
+        input: CancelOrderInput,
+    ) -> Result<OrderResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let reason = input
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty());
+        if let Err(rejection) = orders::cancel(&mut tx, input.order_id, reason, Utc::now()).await? {
+            return Ok(OrderResult::failed(rejection));
+        }
+
+        tx.commit().await?;
+
+        OrderResult::done(pool, input.order_id, "Cancelled order").await
+    }
+
+    /// Map a Square item variation to the inventory item it sells, then
+    /// import the orders that were waiting on it
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn map_square_item(
+        &self,
+        ctx: &Context<'_>,
+        input: MapSquareItemInput,
+    ) -> Result<SquareItemMappingResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let catalog_object_id = input.catalog_object_id.trim();
+        if catalog_object_id.is_empty() {
+            return Ok(SquareItemMappingResult::failed(
+                "Square catalog item ID is required",
+            ));
+        }
+
+        let quantity_per_unit = input
+            .quantity_per_unit
+            .unwrap_or_else(|| BigDecimal::from(1));
+        if quantity_per_unit <= BigDecimal::from(0) {
+            return Ok(SquareItemMappingResult::failed(
+                "Quantity per unit must be greater than 0",
+            ));
+        }
+
+        let item = sqlx::query!(
+            "SELECT name, is_active FROM inventory WHERE id = $1",
+            input.inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(item) = item else {
+            return Ok(SquareItemMappingResult::not_found("Inventory item"));
+        };
+        if !item.is_active {
+            return Ok(SquareItemMappingResult::failed(&format!(
+                "{} is inactive",
+                item.name
+            )));
+        }
+
+        // Keep the name Square showed on the orders waiting for this variation
+        let square_name = sqlx::query_scalar!(
+            r#"
+            SELECT item->>'name'
+            FROM square_orders o, jsonb_array_elements(o.unmapped) AS item
+            WHERE o.status = 'unmapped' AND item->>'catalog_object_id' = $1
+            ORDER BY o.closed_at DESC
+            LIMIT 1
+            "#,
+            catalog_object_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO square_item_mappings
+                (catalog_object_id, square_name, inventory_id, quantity_per_unit)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (catalog_object_id) DO UPDATE SET
+                square_name = COALESCE(EXCLUDED.square_name, square_item_mappings.square_name),
+                inventory_id = EXCLUDED.inventory_id,
+                quantity_per_unit = EXCLUDED.quantity_per_unit,
+                updated_at = NOW()
+            RETURNING id
+            "#,
+            catalog_object_id,
+            square_name,
+            input.inventory_id,
+            quantity_per_unit
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let imported = square::import_waiting(pool).await?;
+        let mapping = square::mapping(pool, id).await?;
+
+        let message = match imported.imported {
+            0 => format!("Mapped {} to {}", catalog_object_id, item.name),
+            n => format!(
+                "Mapped {} to {}; imported {} waiting order(s)",
+                catalog_object_id, item.name, n
+            ),
+        };
+
+        Ok(SquareItemMappingResult {
+            success: true,
+            message,
+            error: None,
+            mapping,
+            imported_orders: imported.imported,
+        })
+    }
+
+    /// Remove a Square mapping. Orders already imported keep their sales;
+    /// later orders for the variation wait for a new mapping.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn delete_square_item_mapping(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteSquareItemMappingInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let catalog_object_id = sqlx::query_scalar!(
+            "DELETE FROM square_item_mappings WHERE id = $1 RETURNING catalog_object_id",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(catalog_object_id) = catalog_object_id else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Square mapping not found".to_string(),
+                error: Some(MutationError::not_found("Square mapping")),
+            });
+        };
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Removed the mapping for {}", catalog_object_id),
+            error: None,
+        })
+    }
+
+    /// Pull completed Square orders now instead of waiting for the next
+    /// scheduled import
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn import_square_orders(&self, ctx: &Context<'_>) -> Result<SquareImportResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let http = ctx.data::<HttpClient>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        let summary = match square::sync(pool, http, secrets).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                return Ok(SquareImportResult {
+                    success: false,
+                    message: e.to_string(),
+                    error: Some(MutationError::invalid()),
+                    fetched: 0,
+                    imported: 0,
+                    unmapped: 0,
+                    skipped: 0,
+                });
+            }
+        };
+
+        let message = match summary.unmapped {
+            0 => format!(
+                "Pulled {} new Square order(s); imported {}",
+                summary.fetched, summary.imported
+            ),
+            n => format!(
+                "Pulled {} new Square order(s); imported {}, {} waiting for item mappings",
+                summary.fetched, summary.imported, n
+            ),
+        };
+
+        Ok(SquareImportResult {
+            success: true,
+            message,
+            error: None,
+            fetched: summary.fetched,
+            imported: summary.imported,
+            unmapped: summary.unmapped,
+            skipped: summary.skipped,
+        })
+    }
+
+    /// Map a Shopify variant to the inventory item it sells, then import the
+    /// orders that were waiting on it
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn map_shopify_product(
+        &self,
+        ctx: &Context<'_>,
+        input: MapShopifyProductInput,
+    ) -> Result<ShopifyProductResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let http = ctx.data::<HttpClient>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        let quantity_per_unit = input
+            .quantity_per_unit
+            .unwrap_or_else(|| BigDecimal::from(1));
+        if quantity_per_unit <= BigDecimal::from(0) {
+            return Ok(ShopifyProductResult::failed(
+                "Quantity per unit must be greater than 0",
+            ));
+        }
+
+        let item = sqlx::query!(
+            "SELECT name, is_active FROM inventory WHERE id = $1",
+            input.inventory_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(item) = item else {
+            return Ok(ShopifyProductResult::not_found("Inventory item"));
+        };
+        if !item.is_active {
+            return Ok(ShopifyProductResult::failed(&format!(
+                "{} is inactive",
+                item.name
+            )));
+        }
+
+        // Stock levels are set on the variant's inventory item, so ask Shopify for it
+        let variant = match shopify::variant(http, secrets, input.variant_id).await {
+            Ok(Some(variant)) => variant,
+            Ok(None) => return Ok(ShopifyProductResult::not_found("Shopify variant")),
+            Err(e) => return Ok(ShopifyProductResult::failed(&e.to_string())),
+        };
+
+        // Prefer the full name orders showed ("Kimchi - 16 oz") over the variant's own title
+        let ordered_name = sqlx::query_scalar!(
+            r#"
+            SELECT item->>'name'
+            FROM shopify_orders o, jsonb_array_elements(o.unmapped) AS item
+            WHERE o.status = 'unmapped' AND (item->>'variant_id')::bigint = $1
+            ORDER BY o.processed_at DESC
+            LIMIT 1
+            "#,
+            input.variant_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+        let shopify_name = ordered_name.or(variant.title);
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO shopify_products (
+                variant_id, inventory_item_id, shopify_name, inventory_id,
+                quantity_per_unit, sync_enabled
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (variant_id) DO UPDATE SET
+                inventory_item_id = EXCLUDED.inventory_item_id,
+                shopify_name = COALESCE(EXCLUDED.shopify_name, shopify_products.shopify_name),
+                inventory_id = EXCLUDED.inventory_id,
+                quantity_per_unit = EXCLUDED.quantity_per_unit,
+                sync_enabled = EXCLUDED.sync_enabled,
+                updated_at = NOW()
+            RETURNING id
+            "#,
+            input.variant_id,
+            variant.inventory_item_id,
+            shopify_name,
+            input.inventory_id,
+            quantity_per_unit,
+            input.sync_enabled.unwrap_or(true)
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let imported = shopify::import_waiting(pool).await?;
+        let product = shopify::product(pool, id).await?;
+
+        let message = match imported.imported {
+            0 => format!("Mapped variant {} to {}", input.variant_id, item.name),
+            n => format!(
+                "Mapped variant {} to {}; imported {} waiting order(s)",
+                input.variant_id, item.name, n
+            ),
+        };
+
+        Ok(ShopifyProductResult {
+            success: true,
+            message,
+            error: None,
+            product,
+            imported_orders: imported.imported,
+        })
+    }
+
+    /// Turn stock pushes to Shopify on or off for a mapped variant. Its
+    /// orders are imported either way.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn set_shopify_product_sync(
+        &self,
+        ctx: &Context<'_>,
+        input: SetShopifyProductSyncInput,
+    ) -> Result<ShopifyProductResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        // Shopify's level isn't tracked while pushes are off, so forget the
+        // last one rather than reporting the gap as drift when they resume
+        let updated = sqlx::query!(
+            r#"
+            UPDATE shopify_products
+            SET sync_enabled = $2,
+                last_pushed_quantity = CASE WHEN $2 THEN last_pushed_quantity END,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            input.id,
+            input.sync_enabled
+        )
+        .execute(pool)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(ShopifyProductResult::not_found("Shopify product"));
+        }
+
+        let product = shopify::product(pool, input.id).await?;
+        let name = product
+            .as_ref()
+            .and_then(|product| product.shopify_name.clone())
+            .unwrap_or_else(|| "the variant".to_string());
+
+        Ok(ShopifyProductResult {
+            success: true,
+            message: match input.sync_enabled {
+                true => format!("Stock for {} is pushed to Shopify", name),
+                false => format!("Stopped pushing stock for {}", name),
+            },
+            error: None,
+            product,
+            imported_orders: 0,
+        })
+    }
+
+    /// Remove a Shopify mapping. Orders already imported keep their sales;
+    /// later orders for the variant wait for a new mapping.
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn delete_shopify_product(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteShopifyProductInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let variant_id = sqlx::query_scalar!(
+            "DELETE FROM shopify_products WHERE id = $1 RETURNING variant_id",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(variant_id) = variant_id else {
+            return Ok(DeleteResult {
+                success: false,
+                message: "Shopify product not found".to_string(),
+                error: Some(MutationError::not_found("Shopify product")),
+            });
+        };
+
+        Ok(DeleteResult {
+            success: true,
+            message: format!("Removed the mapping for variant {}", variant_id),
+            error: None,
+        })
+    }
+
+    /// Pull paid Shopify orders and push stock now instead of waiting for
+    /// the next scheduled sync
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn sync_shopify(&self, ctx: &Context<'_>) -> Result<ShopifySyncResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let http = ctx.data::<HttpClient>()?;
+        let secrets = ctx.data::<SecretStore>()?;
+
+        let summary = match shopify::sync(pool, http, secrets).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                return Ok(ShopifySyncResult {
+                    success: false,
+                    message: e.to_string(),
+                    error: Some(MutationError::invalid()),
+                    fetched: 0,
+                    imported: 0,
+                    unmapped: 0,
+                    pushed: 0,
+                    conflicts: 0,
+                });
+            }
+        };
+
+        let mut message = format!(
+            "Pulled {} new Shopify order(s); imported {}, pushed {} stock level(s)",
+            summary.fetched, summary.imported, summary.pushed
+        );
+        if summary.unmapped > 0 {
+            message.push_str(&format!(
+                "; {} waiting for variant mappings",
+                summary.unmapped
+            ));
+        }
+        if summary.conflicts > 0 {
+            message.push_str(&format!("; {} conflict(s) logged", summary.conflicts));
+        }
+
+        Ok(ShopifySyncResult {
+            success: true,
+            message,
+            error: None,
+            fetched: summary.fetched,
+            imported: summary.imported,
+            unmapped: summary.unmapped,
+            pushed: summary.pushed,
+            conflicts: summary.conflicts,
+        })
+    }
+
+    /// Close a Shopify sync conflict once it's been looked into
+    #[graphql(guard = "RequireRole(auth::SALES)")]
+    async fn resolve_shopify_sync_conflict(
+        &self,
+        ctx: &Context<'_>,
+        input: ResolveShopifySyncConflictInput,
+    ) -> Result<ShopifySyncConflictResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let resolved = sqlx::query_scalar!(
+            r#"
+            UPDATE shopify_sync_conflicts
+            SET resolved_at = NOW(), resolution_note = $2
+            WHERE id = $1 AND resolved_at IS NULL
+            RETURNING id
+            "#,
+            input.id,
+            input.note
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let conflict = shopify::conflict(pool, input.id).await?;
+
+        let (success, message, error) = match (resolved, &conflict) {
+            (Some(_), _) => (true, "Conflict resolved".to_string(), None),
+            (None, Some(_)) => (
+                false,
+                "Conflict is already resolved".to_string(),
+                Some(MutationError::invalid()),
+            ),
+            (None, None) => (
+                false,
+                "Conflict not found".to_string(),
+                Some(MutationError::not_found("Conflict")),
+            ),
+        };
+
+        Ok(ShopifySyncConflictResult {
+            success,
+            message,
+            error,
+            conflict,
+        })
+    }
+
+    /// Post an announcement ("what's new", planned downtime, or a notice)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_announcement(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateAnnouncementInput,
+    ) -> Result<AnnouncementResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let kind = input.kind.unwrap_or_else(|| "notice".to_string());
+        let publish_at = input.publish_at.unwrap_or_else(Utc::now);
+
+        if let Err(message) = validate_announcement(
+            &input.title,
+            &input.body,
+            &kind,
+            publish_at,
+            input.expires_at,
+        ) {
+            return Ok(AnnouncementResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                announcement: None,
+            });
+        }
+
+        let announcement = sqlx::query_as!(
+            Announcement,
+            r#"
+            INSERT INTO announcements (title, body, kind, publish_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id, title, body, kind, publish_at, expires_at, is_active, created_at, updated_at
+            "#,
+            input.title.trim(),
+            input.body.trim(),
+            kind,
+            publish_at,
+            input.expires_at
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(AnnouncementResult {
+            success: true,
+            message: format!("Posted announcement '{}'", announcement.title),
+            error: None,
+            announcement: Some(announcement),
+        })
+    }
+
+    /// Edit an announcement
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_announcement(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateAnnouncementInput,
+    ) -> Result<AnnouncementResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let existing = sqlx::query!(
+            "SELECT title, body, kind, publish_at, expires_at FROM announcements WHERE id = $1 AND is_active = true",
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(AnnouncementResult {
+                success: false,
+                message: "Announcement not found".to_string(),
+                error: Some(MutationError::not_found("Announcement")),
+                announcement: None,
+            });
+        };
+
+        let title = input.title.unwrap_or(existing.title);
+        let body = input.body.unwrap_or(existing.body);
+        let kind = input.kind.unwrap_or(existing.kind);
+        let publish_at = input.publish_at.unwrap_or(existing.publish_at);
+        let expires_at = input.expires_at.or(existing.expires_at);
+
+        if let Err(message) = validate_announcement(&title, &body, &kind, publish_at, expires_at) {
+            return Ok(AnnouncementResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                announcement: None,
+            });
+        }
+
+        let announcement = sqlx::query_as!(
+            Announcement,
+            r#"
+            UPDATE announcements
+            SET title = $2, body = $3, kind = $4, publish_at = $5, expires_at = $6,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, title, body, kind, publish_at, expires_at, is_active, created_at, updated_at
+            "#,
+            input.id,
+            title.trim(),
+            body.trim(),
+            kind,
+            publish_at,
+            expires_at
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(AnnouncementResult {
+            success: true,
+            message: format!("Updated announcement '{}'", announcement.title),
+            error: None,
+            announcement: Some(announcement),
+        })
+    }
+
+    /// Take an announcement down
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn delete_announcement(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteAnnouncementInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let title = sqlx::query_scalar!(
+            r#"
+            UPDATE announcements
+            SET is_active = false, updated_at = NOW()
+            WHERE id = $1 AND is_active = true
+            RETURNING title
+            "#,
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match title {
+            Some(title) => Ok(DeleteResult {
+                success: true,
+                message: format!("Removed announcement '{}'", title),
+                error: None,
+            }),
+            None => Ok(DeleteResult {
+                success: false,
+                message: "Announcement not found".to_string(),
+                error: Some(MutationError::not_found("Announcement")),
+            }),
+        }
+    }
+
+    /// Report a problem from the app, optionally with the trace ID of the
+    /// request that failed
+    async fn submit_feedback(
+        &self,
+        ctx: &Context<'_>,
+        input: SubmitFeedbackInput,
+    ) -> Result<FeedbackResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.message.trim().is_empty() {
+            return Ok(FeedbackResult {
+                success: false,
+                message: "Describe what went wrong".to_string(),
+                error: Some(MutationError::invalid()),
+                feedback: None,
+            });
+        }
+
+        let feedback = sqlx::query_as!(
+            UserFeedback,
+            r#"
+            INSERT INTO user_feedback (trace_id, message, page, reported_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, trace_id, message, page, reported_by, created_at
+            "#,
+            input.trace_id,
+            input.message.trim(),
+            input.page,
+            input.reported_by
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(FeedbackResult {
+            success: true,
+            message: "Thanks, your report was sent".to_string(),
+            error: None,
+            feedback: Some(feedback),
+        })
+    }
+
+    /// Sign in with a username and password
+    async fn login(&self, ctx: &Context<'_>, input: LoginInput) -> Result<AuthResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let config = ctx.data::<AuthConfig>()?;
+
+        let Some(user) = auth::login(pool, &input.username, &input.password).await? else {
+            return Ok(AuthResult::failed("Wrong username or password"));
+        };
+
+        let mut conn = pool.acquire().await?;
+        let refresh_token = auth::issue_refresh(&mut conn, user.id).await?;
+
+        AuthResult::signed_in(config, user, refresh_token, "Signed in")
+    }
+
+    /// Trade a refresh token for a new access token and refresh token
+    async fn refresh_token(
+        &self,
+        ctx: &Context<'_>,
+        input: RefreshTokenInput,
+    ) -> Result<AuthResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let config = ctx.data::<AuthConfig>()?;
+        let mut tx = pool.begin().await?;
+
+        let Some(user_id) = auth::redeem_refresh(&mut tx, &input.refresh_token).await? else {
+            // Commit so a reused token's revocations stick
+            tx.commit().await?;
+            return Ok(AuthResult::failed("Session expired, sign in again"));
+        };
+        let Some(user) = auth::find_user(pool, user_id).await? else {
+            tx.commit().await?;
+            return Ok(AuthResult::failed("Account is disabled"));
+        };
+
+        let refresh_token = auth::issue_refresh(&mut tx, user.id).await?;
+        tx.commit().await?;
+
+        AuthResult::signed_in(config, user, refresh_token, "Token refreshed")
+    }
+
+    /// Sign out by revoking a refresh token
+    async fn logout(&self, ctx: &Context<'_>, input: RefreshTokenInput) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if auth::revoke_refresh(pool, &input.refresh_token).await? {
+            Ok(DeleteResult {
+                success: true,
+                message: "Signed out".to_string(),
+                error: None,
+            })
+        } else {
+            Ok(DeleteResult {
+                success: false,
+                message: "Session already ended".to_string(),
+                error: Some(MutationError::invalid()),
+            })
+        }
+    }
+
+    /// Add a user (owner only)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_user(&self, ctx: &Context<'_>, input: CreateUserInput) -> Result<UserResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let username = input.username.trim().to_lowercase();
+        let role = input.role.unwrap_or_else(|| "read_only".to_string());
+
+        if let Err(message) = validate_user(&username, Some(&input.password), &role) {
+            return Ok(UserResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                user: None,
+            });
+        }
+
+        let password_hash = auth::hash_password(&input.password)
+            .map_err(|e| Error::new(format!("Failed to hash password: {}", e)))?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (username, password_hash, display_name, role)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (username) DO NOTHING
+            RETURNING id, username, display_name, role, is_active, last_login_at, created_at
+            "#,
+            username,
+            password_hash,
+            input.display_name,
+            role
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match user {
+            Some(user) => Ok(UserResult {
+                success: true,
+                message: format!("Added user '{}'", user.username),
+                error: None,
+                user: Some(user),
+            }),
+            None => Ok(UserResult {
+                success: false,
+                message: format!("Username '{}' is taken", username),
+                error: Some(MutationError::invalid()),
+                user: None,
+            }),
+        }
+    }
+
+    /// Change a user's role, name or password, or disable them (owner only).
+    /// Disabling or a new password signs them out everywhere
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn update_user(&self, ctx: &Context<'_>, input: UpdateUserInput) -> Result<UserResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let mut tx = pool.begin().await?;
+
+        let existing = sqlx::query!(
+            "SELECT username, display_name, role, is_active FROM users WHERE id = $1 FOR UPDATE",
+            input.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(UserResult {
+                success: false,
+                message: "User not found".to_string(),
+                error: Some(MutationError::not_found("User")),
+                user: None,
+            });
+        };
+
+        let role = input.role.unwrap_or(existing.role.clone());
+        let is_active = input.is_active.unwrap_or(existing.is_active);
+
+        if let Err(message) = validate_user(&existing.username, input.password.as_deref(), &role) {
+            return Ok(UserResult {
+                success: false,
+                message,
+                error: Some(MutationError::invalid()),
+                user: None,
+            });
+        }
+
+        // Someone has to be able to manage users
+        if existing.role == "owner" && existing.is_active && (role != "owner" || !is_active) {
+            let other_owners = sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "count!"
+                FROM users
+                WHERE role = 'owner' AND is_active = true AND id <> $1
+                "#,
+                input.id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if other_owners == 0 {
+                return Ok(UserResult {
+                    success: false,
+                    message: "Can't demote or disable the last owner".to_string(),
+                    error: Some(MutationError::invalid()),
+                    user: None,
+                });
+            }
+        }
+
+        let password_hash = match &input.password {
+            Some(password) => Some(
+                auth::hash_password(password)
+                    .map_err(|e| Error::new(format!("Failed to hash password: {}", e)))?,
+            ),
+            None => None,
+        };
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET display_name = $2, role = $3, is_active = $4,
+                password_hash = COALESCE($5, password_hash), updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, username, display_name, role, is_active, last_login_at, created_at
+            "#,
+            input.id,
+            input.display_name.or(existing.display_name),
+            role,
+            is_active,
+            password_hash
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !is_active || input.password.is_some() {
+            sqlx::query!(
+                "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+                input.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(UserResult {
+            success: true,
+            message: format!("Updated user '{}'", user.username),
+            error: None,
+            user: Some(user),
+        })
+    }
+
+    /// Create an API key for a device or script (owner only). The key is
+    /// returned once; only its hash is kept
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn create_api_key(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateApiKeyInput,
+    ) -> Result<ApiKeyResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
+
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Ok(ApiKeyResult::failed("Name is required"));
+        }
+        if !auth::API_KEY_SCOPES.contains(&input.scope.as_str()) {
+            return Ok(ApiKeyResult::failed(&format!(
+                "Scope must be one of: {}",
+                auth::API_KEY_SCOPES.join(", ")
+            )));
+        }
+
+        let (api_key, key) =
+            auth::create_api_key(pool, name, &input.scope, identity.user_id).await?;
+
+        Ok(ApiKeyResult {
+            success: true,
+            message: format!(
+                "Created API key '{}'. Copy it now; it won't be shown again",
+                name
+            ),
+            error: None,
+            api_key: Some(api_key),
+            key: Some(key),
+        })
+    }
+
+    /// Revoke an API key (owner only). Requests using it fail straight away
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn revoke_api_key(
+        &self,
+        ctx: &Context<'_>,
+        input: RevokeApiKeyInput,
+    ) -> Result<ApiKeyResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys SET revoked_at = NOW()
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING id, name, prefix, scope, created_by, last_used_at, revoked_at, created_at
+            "#,
+            input.id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match api_key {
+            Some(api_key) => Ok(ApiKeyResult {
+                success: true,
+                message: format!("Revoked API key '{}'", api_key.name),
+                error: None,
+                api_key: Some(api_key),
+                key: None,
+            }),
+            None => Ok(ApiKeyResult {
+                error: Some(MutationError::not_found("API key")),
+                ..ApiKeyResult::failed("API key not found or already revoked")
+            }),
+        }
+    }
+
+    /// Change the signed-in user's phone number and which alerts are texted to it
+    async fn update_notification_settings(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateNotificationSettingsInput,
+    ) -> Result<NotificationSettingsResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let notifier = ctx.data::<Notifier>()?;
+        let identity = ctx.data::<Identity>()?;
+
+        // An empty string clears the number
+        let phone = match input.phone.as_deref().map(str::trim) {
+            Some("") => Some(None),
+            Some(phone) => match normalize_phone(phone) {
+                Some(phone) => Some(Some(phone)),
+                None => {
+                    return Ok(NotificationSettingsResult::failed(
+                        "Phone must be a 10-digit US number or start with + and a country code",
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        if let Some(ref kinds) = input.sms_alert_kinds
+            && let Some(kind) = kinds
+                .iter()
+                .find(|k| !SMS_ALERT_KINDS.contains(&k.as_str()))
+        {
+            return Ok(NotificationSettingsResult::failed(&format!(
+                "'{}' can't be texted. Choose from: {}",
+                kind,
+                SMS_ALERT_KINDS.join(", ")
+            )));
+        }
+
+        let updated = sqlx::query_scalar!(
+            r#"
+            UPDATE users SET
+                phone = CASE WHEN $2 THEN $3 ELSE phone END,
+                sms_alert_kinds = COALESCE($4, sms_alert_kinds),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING phone
+            "#,
+            identity.user_id,
+            phone.is_some(),
+            phone.flatten(),
+            input.sms_alert_kinds.as_deref()
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(phone) = updated else {
+            return Ok(NotificationSettingsResult::not_found("User"));
+        };
+
+        let settings = notifier.settings(identity.user_id).await?;
+
+        let message = match (&settings, phone) {
+            (Some(s), None) if !s.sms_alert_kinds.is_empty() => {
+                "Saved. Add a phone number to receive texts".to_string()
+            }
+            (Some(s), Some(_)) if !s.sms_configured && !s.sms_alert_kinds.is_empty() => {
+                "Saved. Texts start once an SMS provider is set up".to_string()
+            }
+            _ => "Notification settings saved".to_string(),
+        };
+
+        Ok(NotificationSettingsResult {
+            success: true,
+            message,
+            error: None,
+            settings,
+        })
+    }
+
+    /// Save a dashboard layout for the signed-in user
+    async fn create_dashboard(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateDashboardInput,
+    ) -> Result<DashboardResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
+
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Ok(DashboardResult::failed("Dashboard name cannot be empty"));
+        }
+        let widgets = match dashboards::widgets(input.widgets) {
+            Ok(widgets) => widgets,
+            Err(message) => return Ok(DashboardResult::failed(&message)),
+        };
+
+        let mut tx = pool.begin().await?;
+
+        let (taken, first) = sqlx::query!(
+            r#"
+            SELECT
+                EXISTS(SELECT 1 FROM dashboards WHERE user_id = $1 AND lower(name) = lower($2)) AS "taken!",
+                NOT EXISTS(SELECT 1 FROM dashboards WHERE user_id = $1) AS "first!"
+            "#,
+            identity.user_id,
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map(|row| (row.taken, row.first))?;
+
+        if taken {
+            return Ok(DashboardResult {
+                error: Some(MutationError::name_conflict("Dashboard", name)),
+                ..DashboardResult::failed(&format!("You already have a dashboard named {}", name))
+            });
+        }
+
+        let is_default = input.is_default.unwrap_or(first);
+        if is_default {
+            sqlx::query!(
+                "UPDATE dashboards SET is_default = false, updated_at = NOW() WHERE user_id = $1 AND is_default",
+                identity.user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO dashboards (user_id, name, widgets, is_default)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+            identity.user_id,
+            name,
+            serde_json::to_value(&widgets)?,
+            is_default
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let dashboard = dashboards::get(&mut *tx, identity.user_id, id).await?;
+        tx.commit().await?;
+
+        Ok(DashboardResult {
+            success: true,
+            message: format!("Saved dashboard {}", name),
+            error: None,
+            dashboard,
+        })
+    }
+
+    /// Rename one of the signed-in user's dashboards, replace its widgets, or
+    /// make it the one opened first
+    async fn update_dashboard(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateDashboardInput,
+    ) -> Result<DashboardResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
+
+        let name = input.name.as_deref().map(str::trim);
+        if name == Some("") {
+            return Ok(DashboardResult::failed("Dashboard name cannot be empty"));
+        }
+        let widgets = match input.widgets.map(dashboards::widgets).transpose() {
+            Ok(widgets) => widgets,
+            Err(message) => return Ok(DashboardResult::failed(&message)),
+        };
+
+        let mut tx = pool.begin().await?;
+
+        if dashboards::get(&mut *tx, identity.user_id, input.id)
+            .await?
+            .is_none()
+        {
+            return Ok(DashboardResult::not_found("Dashboard"));
+        }
+
+        if let Some(name) = name {
+            let taken = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM dashboards
+                    WHERE user_id = $1 AND lower(name) = lower($2) AND id <> $3
+                ) AS "taken!"
+                "#,
+                identity.user_id,
+                name,
+                input.id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if taken {
+                return Ok(DashboardResult {
+                    error: Some(MutationError::name_conflict("Dashboard", name)),
+                    ..DashboardResult::failed(&format!(
+                        "You already have a dashboard named {}",
+                        name
+                    ))
+                });
+            }
+        }
+
+        if input.is_default == Some(true) {
+            sqlx::query!(
+                r#"
+                UPDATE dashboards SET is_default = false, updated_at = NOW()
+                WHERE user_id = $1 AND is_default AND id <> $2
+                "#,
+                identity.user_id,
+                input.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE dashboards
+            SET name = COALESCE($2, name),
+                widgets = COALESCE($3, widgets),
+                is_default = COALESCE($4, is_default),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            input.id,
+            name,
+            widgets.as_ref().map(serde_json::to_value).transpose()?,
+            input.is_default
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let dashboard = dashboards::get(&mut *tx, identity.user_id, input.id).await?;
+        tx.commit().await?;
+
+        Ok(DashboardResult {
+            success: true,
+            message: "Dashboard saved".to_string(),
+            error: None,
+            dashboard,
+        })
+    }
+
+    /// Delete one of the signed-in user's dashboards
+    async fn delete_dashboard(
+        &self,
+        ctx: &Context<'_>,
+        input: DeleteDashboardInput,
+    ) -> Result<DeleteResult> {
+        let pool = ctx.data::<PgPool>()?;
+        let identity = ctx.data::<Identity>()?;
+
+        let name = sqlx::query_scalar!(
+            "DELETE FROM dashboards WHERE id = $1 AND user_id = $2 RETURNING name",
+            input.id,
+            identity.user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(match name {
+            Some(name) => DeleteResult {
+                success: true,
+                message: format!("Deleted dashboard {}", name),
+                error: None,
+            },
+            None => DeleteResult {
+                success: false,
+                message: "Dashboard not found".to_string(),
+                error: Some(MutationError::not_found("Dashboard")),
+            },
+        })
+    }
+
+    /// Copy recipes and category and unit presets from the built-in library.
+    /// Recipe products and ingredients use the mapped inventory item, else
+    /// the item of the same name, else a new item with no stock
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn import_from_library(
+        &self,
+        ctx: &Context<'_>,
+        input: ImportFromLibraryInput,
+    ) -> Result<LibraryImportResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let failed = |message: String| LibraryImportResult {
+            success: false,
+            message,
+            error: Some(MutationError::invalid()),
+            recipes: vec![],
+            categories_added: 0,
+            units_added: 0,
+            created_items: vec![],
+            skipped: vec![],
+        };
+
+        let recipe_keys = input.recipes.unwrap_or_default();
+        let category_names = input.categories.unwrap_or_default();
+        let unit_names = input.units.unwrap_or_default();
+        if recipe_keys.is_empty() && category_names.is_empty() && unit_names.is_empty() {
+            return Ok(failed("Pick at least one library entry".to_string()));
+        }
+
+        let mappings: Vec<(String, Uuid)> = input
+            .mappings
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mapping| (mapping.name.trim().to_string(), mapping.inventory_id))
+            .collect();
+
+        let mut tx = pool.begin().await?;
+
+        for (name, inventory_id) in &mappings {
+            let exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+                inventory_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !exists {
+                return Ok(failed(format!(
+                    "Item mapped to '{}' not found or is inactive",
+                    name
+                )));
+            }
+        }
+
+        let mut skipped = Vec::new();
+
+        let mut categories_added = 0;
+        for name in &category_names {
+            match library::category(name) {
+                Some((name, description)) => {
+                    if library::add_category(&mut tx, name, Some(description)).await? {
+                        categories_added += 1;
+                    } else {
+                        skipped.push(format!("Category '{}' already exists", name));
+                    }
+                }
+                None => skipped.push(format!("No library category '{}'", name)),
+            }
+        }
+
+        let mut units_added = 0;
+        for name in &unit_names {
+            match library::unit(name) {
+                Some((name, kind)) => {
+                    if library::add_unit(&mut tx, name, kind).await? {
+                        units_added += 1;
+                    } else {
+                        skipped.push(format!("Unit '{}' already exists", name));
+                    }
+                }
+                None => skipped.push(format!("No library unit '{}'", name)),
+            }
+        }
+
+        let mut recipes = Vec::new();
+        let mut created_items = Vec::new();
+        for key in &recipe_keys {
+            // A skipped recipe leaves no items behind
+            let mut savepoint = tx.begin().await?;
+            match library::add_recipe(&mut savepoint, key, &mappings).await? {
+                Ok(recipe) => {
+                    savepoint.commit().await?;
+                    recipes.push(recipe.template_name);
+                    created_items.extend(recipe.created_items);
+                }
+                Err(reason) => {
+                    savepoint.rollback().await?;
+                    skipped.push(reason);
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        let copied = recipes.len() + categories_added + units_added;
+        let message = match (copied, skipped.len()) {
+            (0, _) => "Nothing copied".to_string(),
+            (_, 0) => format!("Copied {} library entries", copied),
+            (_, skipped) => format!("Copied {} library entries; skipped {}", copied, skipped),
+        };
+
+        Ok(LibraryImportResult {
+            success: copied > 0,
+            message,
+            error: (copied == 0).then(MutationError::invalid),
+            recipes,
+            categories_added: categories_added as i32,
+            units_added: units_added as i32,
+            created_items,
+            skipped,
+        })
+    }
+
+    /// Setup wizard: add storage locations (default: a walk-in cooler and
+    /// dry storage). Names that already exist are skipped
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_up_locations(
+        &self,
+        ctx: &Context<'_>,
+        input: SetUpLocationsInput,
+    ) -> Result<OnboardingResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let locations: Vec<(String, String, Option<String>)> = match input.locations {
+            Some(locations) => locations
+                .into_iter()
+                .map(|location| {
+                    (
+                        location.name.trim().to_string(),
+                        location
+                            .location_type
+                            .unwrap_or_else(|| "storage".to_string()),
+                        location.description,
+                    )
+                })
+                .collect(),
+            None => onboarding::DEFAULT_LOCATIONS
+                .iter()
+                .map(|(name, kind, description)| {
+                    (
+                        name.to_string(),
+                        kind.to_string(),
+                        Some(description.to_string()),
+                    )
+                })
+                .collect(),
+        };
+
+        if locations.is_empty() || locations.iter().any(|(name, _, _)| name.is_empty()) {
+            return OnboardingResult::failed(pool, "Every location needs a name").await;
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut added = 0;
+        for (name, location_type, description) in &locations {
+            added += sqlx::query!(
+                r#"
+                INSERT INTO locations (name, location_type, description)
+                SELECT $1::varchar, $2, $3
+                WHERE NOT EXISTS (SELECT 1 FROM locations WHERE LOWER(name) = LOWER($1))
+                "#,
+                name,
+                location_type,
+                description.as_deref()
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+
+        OnboardingResult::done(pool, "Locations", added, locations.len()).await
+    }
+
+    /// Setup wizard: add units of measure (default: the library presets).
+    /// Names that already exist are skipped
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_up_units(
+        &self,
+        ctx: &Context<'_>,
+        input: SetUpUnitsInput,
+    ) -> Result<OnboardingResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let units: Vec<(String, String)> = match input.units {
+            Some(units) => units
+                .into_iter()
+                .map(|unit| (unit.name.trim().to_lowercase(), unit.kind))
+                .collect(),
+            None => library::UNITS
+                .iter()
+                .map(|(name, kind)| (name.to_string(), kind.to_string()))
+                .collect(),
+        };
+
+        if units.is_empty() || units.iter().any(|(name, _)| name.is_empty()) {
+            return OnboardingResult::failed(pool, "Every unit needs a name").await;
+        }
+        if units
+            .iter()
+            .any(|(_, kind)| !library::UNIT_KINDS.contains(&kind.as_str()))
+        {
+            let message = format!(
+                "Unit kind must be one of: {}",
+                library::UNIT_KINDS.join(", ")
+            );
+            return OnboardingResult::failed(pool, &message).await;
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut added = 0;
+        for (name, kind) in &units {
+            added += library::add_unit(&mut tx, name, kind).await? as u64;
+        }
+        tx.commit().await?;
+
+        OnboardingResult::done(pool, "Units", added, units.len()).await
+    }
+
+    /// Setup wizard: add inventory categories (default: the library
+    /// presets). Names that already exist are skipped
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn set_up_categories(
+        &self,
+        ctx: &Context<'_>,
+        input: SetUpCategoriesInput,
+    ) -> Result<OnboardingResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        let categories: Vec<(String, Option<String>)> = match input.categories {
+            Some(categories) => categories
+                .into_iter()
+                .map(|category| (category.name.trim().to_string(), category.description))
+                .collect(),
+            None => library::CATEGORIES
+                .iter()
+                .map(|(name, description)| (name.to_string(), Some(description.to_string())))
+                .collect(),
+        };
+
+        if categories.is_empty() || categories.iter().any(|(name, _)| name.is_empty()) {
+            return OnboardingResult::failed(pool, "Every category needs a name").await;
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut added = 0;
+        for (name, description) in &categories {
+            added += library::add_category(&mut tx, name, description.as_deref()).await? as u64;
+        }
+        tx.commit().await?;
+
+        OnboardingResult::done(pool, "Categories", added, categories.len()).await
+    }
+
+    /// Setup wizard: add recipes from the built-in library, with their
+    /// products and any ingredients not yet in inventory
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn add_starter_recipes(
+        &self,
+        ctx: &Context<'_>,
+        input: AddStarterRecipesInput,
+    ) -> Result<OnboardingResult> {
+        let pool = ctx.data::<PgPool>()?;
+
+        if input.keys.is_empty() {
+            return OnboardingResult::failed(pool, "Pick at least one starter recipe").await;
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut added = Vec::new();
+        let mut skipped = Vec::new();
+        for key in &input.keys {
+            match library::add_recipe(&mut tx, key, &[]).await? {
+                Ok(recipe) => added.push(recipe.template_name),
+                Err(reason) => skipped.push(reason),
+            }
+        }
+        tx.commit().await?;
+
+        let mut message = if added.is_empty() {
+            "No recipes added".to_string()
+        } else {
+            format!("Added {}", added.join(", "))
+        };
+        if !skipped.is_empty() {
+            message = format!("{}. Skipped: {}", message, skipped.join("; "));
+        }
+
+        Ok(OnboardingResult {
+            success: !added.is_empty(),
+            message,
+            error: added.is_empty().then(MutationError::invalid),
+            status: onboarding::status(pool).await?,
+        })
+    }
+}
+
+/// Check an announcement's text, kind and display window.
+fn validate_user(username: &str, password: Option<&str>, role: &str) -> Result<(), String> {
+    if username.is_empty() {
+        return Err("Username is required".to_string());
+    }
+
+    if password.is_some_and(|password| password.chars().count() < auth::MIN_PASSWORD_LENGTH) {
+        return Err(format!(
+            "Password must be at least {} characters",
+            auth::MIN_PASSWORD_LENGTH
+        ));
+    }
+
+    if !auth::ROLES.contains(&role) {
+        return Err(format!("Role must be one of: {}", auth::ROLES.join(", ")));
+    }
+
+    Ok(())
+}
+
+fn validate_overhead_pool(
+    name: &str,
+    monthly_amount: &BigDecimal,
+    allocation_basis: &str,
+    expected_monthly_volume: Option<&BigDecimal>,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+
+    if *monthly_amount < BigDecimal::from(0) {
+        return Err("Monthly amount can't be negative".to_string());
+    }
+
+    if !overhead::BASES.contains(&allocation_basis) {
+        return Err(format!(
+            "Allocation basis must be one of: {}",
+            overhead::BASES.join(", ")
+        ));
+    }
+
+    if expected_monthly_volume.is_some_and(|volume| *volume <= BigDecimal::from(0)) {
+        return Err("Expected monthly volume must be greater than 0".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_announcement(
+    title: &str,
+    body: &str,
+    kind: &str,
+    publish_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), String> {
+    if title.trim().is_empty() || body.trim().is_empty() {
+        return Err("Title and body are required".to_string());
+    }
+
+    if !matches!(kind, "whats_new" | "downtime" | "notice") {
+        return Err("Kind must be one of: whats_new, downtime, notice".to_string());
+    }
+
+    if expires_at.is_some_and(|expires_at| expires_at <= publish_at) {
+        return Err("Expiry must be after the publish time".to_string());
+    }
+
+    Ok(())
+}
+
+/// Load a complaint together with its sibling lot reviews.
+async fn load_complaint_details(
+    conn: &mut PgConnection,
+    complaint_id: Uuid,
+) -> Result<ComplaintDetails, sqlx::Error> {
+    let complaint = sqlx::query_as!(
+        Complaint,
+        r#"
+        SELECT
+            id, customer_id, sale_id, inventory_id, lot_id, production_batch_id,
+            description, severity, status, disposition, refund_amount,
+            replacement_quantity, resolution_notes, reported_at, resolved_at,
+            created_at, updated_at
+        FROM complaints
+        WHERE id = $1
+        "#,
+        complaint_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let lot_reviews = sqlx::query_as!(
+        ComplaintLotReview,
+        r#"
+        SELECT id, complaint_id, lot_id, status, notes, reviewed_at, created_at
+        FROM complaint_lot_reviews
+        WHERE complaint_id = $1
+        ORDER BY created_at
+        "#,
+        complaint_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(ComplaintDetails {
+        complaint,
+        lot_reviews,
+    })
+}
+
+/// Check an edit against the row's `version` when the edit started. Only edits
+/// bump the version, so stock moving in the meantime doesn't count. Returns why
+/// the edit is stale (with who changed the row last, if the audit log knows) or
+/// `None` when it can go ahead. Edits without an expected version always can.
+async fn stale_edit(
+    conn: &mut PgConnection,
+    entity_id: Uuid,
+    name: &str,
+    version: i32,
+    updated_at: DateTime<Utc>,
+    expected_version: Option<i32>,
+) -> Result<Option<Rejection>, sqlx::Error> {
+    if expected_version.is_none_or(|expected| expected == version) {
+        return Ok(None);
+    }
+
+    let actor = sqlx::query_scalar!(
+        "SELECT actor FROM mutation_audit WHERE entity_id = $1 ORDER BY changed_at DESC LIMIT 1",
+        entity_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let by = actor
+        .as_ref()
+        .map(|actor| format!(" by {}", actor))
+        .unwrap_or_default();
+
+    Ok(Some(Rejection::new(
+        format!(
+            "'{}' was changed{} at {} after you started editing; reload it and make your changes again",
+            name,
+            by,
+            updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        MutationError::stale_edit(updated_at, actor),
+    )))
+}
+
+/// ", moved N item(s) to the new supplier" for supplier removal messages.
+/// Approve or reject a purchase order waiting for approval and tell the
+/// person who placed it.
+async fn decide_purchase_order(
+    ctx: &Context<'_>,
+    input: DecidePurchaseOrderInput,
+    approve: bool,
+) -> Result<PurchaseOrderResult> {
+    let pool = ctx.data::<PgPool>()?;
+    let identity = ctx.data::<Identity>()?;
+    let mut tx = pool.begin().await?;
+
+    let existing = sqlx::query!(
+        "SELECT po_number, status FROM purchase_orders WHERE id = $1 FOR UPDATE",
+        input.purchase_order_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(existing) = existing else {
+        return Ok(PurchaseOrderResult::not_found("Purchase order"));
+    };
+
+    if existing.status != "pending_approval" {
+        return Ok(PurchaseOrderResult::failed(&format!(
+            "Purchase order {} is {}, not waiting for approval",
+            existing.po_number, existing.status
+        )));
+    }
+
+    let purchase_order = sqlx::query_as!(
+        PurchaseOrder,
+        r#"
+        UPDATE purchase_orders
+        SET
+            status = $2,
+            decided_by = $3,
+            decided_at = NOW(),
+            decision_note = $4,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING
+            id, po_number, supplier_id, status, order_date, expected_date, notes,
+            total_amount, requested_by, requested_by_user_id, decided_by, decided_at,
+            decision_note, contract_id, created_at, updated_at
+        "#,
+        input.purchase_order_id,
+        if approve { "open" } else { "rejected" },
+        identity.username,
+        input.note
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let decision = if approve { "approved" } else { "rejected" };
+    let mut message = format!(
+        "Purchase order {} for ${} was {} by {}",
+        purchase_order.po_number, purchase_order.total_amount, decision, identity.username
+    );
+    if let Some(note) = &purchase_order.decision_note {
+        message.push_str(&format!(": {}", note));
+    }
+
+    // Orders whose requester's account was deleted have no one to tell
+    if let Some(user_id) = purchase_order.requested_by_user_id {
+        let alert = Alert {
+            kind: "purchase_order_decision".to_string(),
+            message,
+            data: serde_json::json!({
+                "purchase_order_id": purchase_order.id,
+                "po_number": purchase_order.po_number,
+                "decision": decision,
+                "requested_by": purchase_order.requested_by,
+                "note": purchase_order.decision_note,
+            }),
+        };
+        ctx.data::<Notifier>()?.send_to(user_id, &alert).await;
+    }
+
+    Ok(PurchaseOrderResult {
+        success: true,
+        message: format!(
+            "{} purchase order {}",
+            if approve { "Approved" } else { "Rejected" },
+            purchase_order.po_number
+        ),
+        error: None,
+        purchase_order: Some(purchase_order),
+    })
+}
+
+fn moved_items(moved: u64) -> String {
+    match moved {
+        0 => String::new(),
+        n => format!("; {} item(s) moved to the new default supplier", n),
+    }
+}
+
+impl AuthResult {
+    fn failed(message: &str) -> Self {
+        AuthResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            access_token: None,
+            access_token_expires_at: None,
+            refresh_token: None,
+            user: None,
+        }
+    }
+
+    fn signed_in(
+        auth: &AuthConfig,
+        user: User,
+        refresh_token: String,
+        message: &str,
+    ) -> Result<Self> {
+        let (access_token, expires_at) = auth
+            .issue(&user)
+            .map_err(|e| Error::new(format!("Failed to sign token: {}", e)))?;
+
+        Ok(AuthResult {
+            success: true,
+            message: message.to_string(),
+            error: None,
+            access_token: Some(access_token),
+            access_token_expires_at: Some(expires_at),
+            refresh_token: Some(refresh_token),
+            user: Some(user),
+        })
+    }
+}
+
+impl ApiKeyResult {
+    fn failed(message: &str) -> Self {
+        ApiKeyResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            api_key: None,
+            key: None,
+        }
+    }
+}
+
+impl OnboardingResult {
+    async fn failed(pool: &PgPool, message: &str) -> Result<Self> {
+        Ok(OnboardingResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            status: onboarding::status(pool).await?,
+        })
+    }
+
+    async fn done(pool: &PgPool, what: &str, added: u64, requested: usize) -> Result<Self> {
+        let skipped = requested as u64 - added;
+        let message = match skipped {
+            0 => format!("{}: {} added", what, added),
+            _ => format!("{}: {} added, {} already existed", what, added, skipped),
+        };
+
+        Ok(OnboardingResult {
+            success: true,
+            message,
+            error: None,
+            status: onboarding::status(pool).await?,
+        })
+    }
+}
+
+impl MarketResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            market: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl MarketSessionResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            session: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl PickupWindowResult {
+    fn failed(message: &str) -> Self {
+        PickupWindowResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            window: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        PickupWindowResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl PickupBookingResult {
+    fn failed(message: &str) -> Self {
+        PickupBookingResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            booking: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        PickupBookingResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl NotificationSettingsResult {
+    fn failed(message: &str) -> Self {
+        NotificationSettingsResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            settings: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        NotificationSettingsResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl SupplierCatalogImportResult {
+    fn failed(message: &str) -> Self {
+        SupplierCatalogImportResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            import: None,
+            changes: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        SupplierCatalogImportResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl SupplierCatalogItemResult {
+    fn not_found(entity: &str) -> Self {
+        SupplierCatalogItemResult {
+            success: false,
+            message: format!("{} not found", entity),
+            error: Some(MutationError::not_found(entity)),
+            item: None,
+        }
+    }
+}
+
+impl PlanAllocationResult {
+    fn failed(message: &str) -> Self {
+        PlanAllocationResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            allocation: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        PlanAllocationResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl SupplierResult {
+    fn failed(message: &str) -> Self {
+        SupplierResult {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            supplier: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        SupplierResult {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+
+    fn rejected(rejection: Rejection) -> Self {
+        SupplierResult {
+            error: Some(rejection.error),
+            ..Self::failed(&rejection.message)
+        }
+    }
+}
+
+impl PurchaseOrderResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            purchase_order: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            success: false,
+            message: format!("{} not found", entity),
+            error: Some(MutationError::not_found(entity)),
+            purchase_order: None,
+        }
+    }
+
+    fn rejected(rejection: Rejection) -> Self {
+        Self {
+            error: Some(rejection.error),
+            ..Self::failed(&rejection.message)
+        }
+    }
+}
+
+impl PurchaseApprovalLimitResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            limit: None,
+        }
+    }
+}
+
+impl ActionLinkResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            link: None,
+            url: None,
+            qr_svg: None,
+        }
+    }
+}
+
+impl SaleChannelPolicyResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            policy: None,
+        }
+    }
+}
+
+impl PromoCodeResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            promo_code: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+/// What's wrong with a promo code's dates and use limit, if anything.
+fn promo_code_limits(
+    starts_on: Option<NaiveDate>,
+    ends_on: Option<NaiveDate>,
+    max_uses: Option<i32>,
+) -> Option<&'static str> {
+    if starts_on
+        .zip(ends_on)
+        .is_some_and(|(starts_on, ends_on)| ends_on < starts_on)
+    {
+        return Some("A promo code can't end before it starts");
+    }
+    if max_uses.is_some_and(|max_uses| max_uses < 1) {
+        return Some("A promo code's use limit must be at least 1");
+    }
+    None
+}
+
+impl ListPriceResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            price: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl CustomerPriceResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            price: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl CustomerResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            customer: None,
+        }
+    }
+}
+
+impl OrderResult {
+    fn failed(rejection: Rejection) -> Self {
+        Self {
+            success: false,
+            message: rejection.message,
+            error: Some(rejection.error),
+            order: None,
+        }
+    }
+
+    /// The order as it stands after `action` (e.g. "Confirmed order").
+    async fn done(pool: &PgPool, order_id: Uuid, action: &str) -> Result<Self> {
+        let order = orders::by_id(pool, order_id)
+            .await?
+            .ok_or("Order not found")?;
+
+        Ok(Self {
+            success: true,
+            message: format!("{} {}", action, order.order_number),
+            error: None,
+            order: Some(order),
+        })
+    }
+}
+
+impl TracePageFieldResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            field: None,
+        }
+    }
+}
+
+impl SaleReviewResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            sale: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl SupplierContractResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            contract: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl WebhookSubscriptionResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            subscription: None,
+            secret: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl WebhookDeliveryResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            delivery: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl DashboardResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            dashboard: None,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl SquareItemMappingResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            mapping: None,
+            imported_orders: 0,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
+}
+
+impl ShopifyProductResult {
+    fn failed(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            error: Some(MutationError::invalid()),
+            product: None,
+            imported_orders: 0,
+        }
+    }
+
+    fn not_found(entity: &str) -> Self {
+        Self {
+            error: Some(MutationError::not_found(entity)),
+            ..Self::failed(&format!("{} not found", entity))
+        }
+    }
 }
@@ -1,11 +1,46 @@
 use async_graphql::*;
-use bigdecimal::BigDecimal;
-use chrono::{DateTime, Utc};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc};
 use sqlx::PgPool;
 
+use crate::jobs::availability::AvailabilityFeed;
+use crate::jobs::scheduler;
 use crate::models::{
-    Customer, InventoryItem, ProductionBatch, RecipeTemplate, Sale, SaleItem, SaleWithItems,
-    Supplier,
+    ActionLink, Announcement, ApiError, ApiKey, ApiUsageStat, ApiVersionUsage, ArAging, AuditEntry,
+    BackwardTrace, BatchAlert, BatchMoveTask, BatchTask, BundleComponentPlan, BundlePlan,
+    BusinessDay, CalendarEvent, ChangeSet, Complaint, ComplaintDetails, ComplaintLotReview,
+    CsvImportField, Customer, CustomerOrder, CustomerPrice, DEFAULT_LOCALE, Dashboard, DataIssue,
+    DiscontinuationReport, DiscountSummaryLine, EventSet, ExpiringLot, FeasibilityLine,
+    ForwardTrace, IngredientConsumption, IngredientConsumptionLine, IntegrationSecret,
+    InventoryAging, InventoryCategory, InventoryItem, InventoryLot, InventoryValuation, Invoice,
+    InvoicePayment, KitAssembly, KitComponent, LabelRunInput, LabelRunPlan, LegacyImportField,
+    LegacyImportRow, LocalizedProduct, Location, LocationStock, Market, MarketProfitability,
+    MarketSession, MaterialRequirement, NotificationSettings, OnboardingStatus, OverdueBatch,
+    OverheadPool, PackSize, PackStock, PackagingBreakdown, PaymentLink, PickupManifestEntry,
+    PickupNoShow, PickupWindow, PlanAllocation, PlanCapacity, PlannedBatch, PriceListItem,
+    ProductAvailability, ProductDevelopment, ProductTranslation, ProductionBatch,
+    ProductionCalendarDay, PromoCode, PurchaseApprovalLimit, PurchaseOrder, QualityAnalytics,
+    QualityCheck, QualityStat, RecipeCost, RecipeCostWatchStatus, RecipeFeasibility, RecipeLibrary,
+    RecipeStage, RecipeTaskTemplate, RecipeTemplate, ResolvedPrice, RevenueSummary, Sale,
+    SaleChannelPolicy, SaleWithItems, SanitationLog, ScannedCode, ScheduledJob, ShopifyOrder,
+    ShopifyProduct, ShopifySyncConflict, SquareItemMapping, SquareOrder, StockCount,
+    StockProjection, StorageTransitionRule, Supplier, SupplierCatalogImport, SupplierCatalogItem,
+    SupplierContract, TracePageField, Unit, UnmappedShopifyVariant, UnmappedSquareItem, User,
+    UserFeedback, Vessel, VesselSanitationStatus, VesselUtilization, WebhookDelivery, WebhookEvent,
+    WebhookSubscription, Workshop,
+};
+use crate::services::auth::{self, Identity, RequireRole};
+use crate::services::calendar::{self, Purpose};
+use crate::services::database::ReportingPool;
+use crate::services::http::{HttpClient, IntegrationHealth};
+use crate::services::notifications::Notifier;
+use crate::services::secrets::SecretStore;
+use crate::services::versions::ApiVersion;
+use crate::services::{
+    batches, changes, codes, cost_watch, csv_import, dashboards, development, discontinuation,
+    discounts, events, invoices, labels, legacy_import, library, locations, lots, markets,
+    onboarding, orders, pickups, planning, pricing, recipes, sales, shopify, square, stripe,
+    traceability, validation, valuation, versions,
 };
 
 pub struct QueryRoot;
@@ -17,13 +52,15 @@ pub struct HealthCheck {
     pub database_connected: bool,
     pub version: String,
     pub uptime_seconds: f64,
+    /// Circuit breaker state of each external integration
+    pub integrations: Vec<IntegrationHealth>,
 }
 
 #[Object]
 impl QueryRoot {
     /// Health Check
     async fn health_check(&self, ctx: &Context<'_>) -> Result<HealthCheck> {
-        let pool = ctx.data::<PgPool>()?;
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
         // ✅ This handles the borrowing correctly
         let uptime = match ctx.data::<std::time::Instant>() {
             Ok(start_time) => start_time.elapsed().as_secs_f64(),
@@ -31,27 +68,41 @@ impl QueryRoot {
         };
 
         // Test database connection
-        let database_connected = sqlx::query("SELECT 1").fetch_one(pool).await.is_ok();
+        let database_connected = sqlx::query!("SELECT 1 AS one")
+            .fetch_one(pool)
+            .await
+            .is_ok();
+
+        let integrations = ctx
+            .data::<HttpClient>()
+            .map(|http| http.health())
+            .unwrap_or_default();
+        let integrations_ok = integrations.iter().all(|i| i.circuit_state == "closed");
 
         Ok(HealthCheck {
-            status: if database_connected {
-                "healthy".to_string()
-            } else {
-                "unhealthy".to_string()
+            status: match (database_connected, integrations_ok) {
+                (false, _) => "unhealthy".to_string(),
+                (true, false) => "degraded".to_string(),
+                (true, true) => "healthy".to_string(),
             },
             timestamp: Utc::now(),
             database_connected,
             version: env!("CARGO_PKG_VERSION").to_string(),
             uptime_seconds: uptime,
+            integrations,
         })
     }
 
     async fn ping(&self) -> String {
         "pong".to_string()
     }
-    /// Get all inventory items
-    async fn inventory_items(&self, ctx: &Context<'_>) -> Result<Vec<InventoryItem>> {
-        let pool = ctx.data::<PgPool>()?;
+    /// Get all inventory items (optionally just those stocked at a location)
+    async fn inventory_items(
+        &self,
+        ctx: &Context<'_>,
+        location_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<InventoryItem>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
         let items = sqlx::query_as!(
             InventoryItem,
@@ -68,12 +119,21 @@ impl QueryRoot {
                 default_supplier_id,
                 shelf_life_days,
                 storage_requirements,
+                product_code,
                 is_active,
+                version,
                 created_at,
                 updated_at
             FROM inventory
             WHERE is_active = true
-            ORDER BY name"
+                AND ($1::uuid IS NULL OR EXISTS(
+                    SELECT 1 FROM inventory_lots l
+                    WHERE l.inventory_id = inventory.id
+                        AND l.location_id = $1
+                        AND l.remaining_quantity > 0
+                ))
+            ORDER BY name",
+            location_id
         )
         .fetch_all(pool)
         .await?;
@@ -81,52 +141,63 @@ impl QueryRoot {
         Ok(items)
     }
 
-    /// Get all suppliers
-    async fn suppliers(&self, ctx: &Context<'_>) -> Result<Vec<Supplier>> {
-        let pool = ctx.data::<PgPool>()?;
+    /// Get lots for an inventory item (open lots only unless include_depleted
+    /// is set), optionally just those at a location
+    async fn inventory_lots(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: uuid::Uuid,
+        include_depleted: Option<bool>,
+        location_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<InventoryLot>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
-        let suppliers = sqlx::query_as!(
-            Supplier,
-            "SELECT id, name, contact_email, contact_phone, street_address, city, state, zip_code, country, latitude, longitude, notes, created_at, updated_at FROM suppliers ORDER BY name"
+        let lots = sqlx::query_as!(
+            InventoryLot,
+            r#"
+            SELECT
+                id, inventory_id, lot_number, code, source_type, supplier_id,
+                production_batch_id, parent_lot_id, location_id, initial_quantity, remaining_quantity,
+                unit_cost, received_date, expiry_date, created_at, updated_at
+            FROM inventory_lots
+            WHERE inventory_id = $1
+                AND ($2 OR remaining_quantity > 0)
+                AND ($3::uuid IS NULL OR location_id = $3)
+            ORDER BY received_date DESC
+            "#,
+            inventory_id,
+            include_depleted.unwrap_or(false),
+            location_id
         )
         .fetch_all(pool)
         .await?;
 
-        Ok(suppliers)
+        Ok(lots)
     }
 
-    /// Get all active production batches (in_progress status)
-    async fn active_batches(&self, ctx: &Context<'_>) -> Result<Vec<ProductionBatch>> {
-        let pool = ctx.data::<PgPool>()?;
-
-        let batches = sqlx::query_as!(
-            ProductionBatch,
-            r#"
-            SELECT
-                id, batch_number, product_inventory_id, recipe_template_id,
-                batch_size, unit, start_date, estimated_completion_date,
-                completion_date, production_date, status,
-                production_time_hours, yield_percentage, actual_yield,
-                quality_notes, storage_location, notes,
-                created_at, updated_at
-            FROM production_batches
-            WHERE status = 'in_progress'
-            ORDER BY start_date DESC
-            "#
-        )
-        .fetch_all(pool)
-        .await?;
+    /// Look up a scanned or typed code: an internal lot code, a batch number,
+    /// or a lot, batch or item ID
+    async fn resolve_code(&self, ctx: &Context<'_>, code: String) -> Result<Option<ScannedCode>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
-        Ok(batches)
+        Ok(codes::resolve(pool, &code).await?)
     }
 
-    /// Get a specific production batch by ID
-    async fn production_batch(
+    /// The batch a scanned jar label belongs to: its batch number, or the
+    /// code of a lot the batch produced
+    async fn lookup_by_barcode(
         &self,
         ctx: &Context<'_>,
-        id: uuid::Uuid,
+        code: String,
     ) -> Result<Option<ProductionBatch>> {
-        let pool = ctx.data::<PgPool>()?;
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let batch_id = codes::resolve(pool, &code)
+            .await?
+            .and_then(|scanned| scanned.production_batch_id);
+        let Some(batch_id) = batch_id else {
+            return Ok(None);
+        };
 
         let batch = sqlx::query_as!(
             ProductionBatch,
@@ -136,12 +207,14 @@ impl QueryRoot {
                 batch_size, unit, start_date, estimated_completion_date,
                 completion_date, production_date, status,
                 production_time_hours, yield_percentage, actual_yield,
-                quality_notes, storage_location, notes,
+                quality_notes, storage_location_id,
+                (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                vessel_id, last_moved_at, notes,
                 created_at, updated_at
             FROM production_batches
             WHERE id = $1
             "#,
-            id
+            batch_id
         )
         .fetch_optional(pool)
         .await?;
@@ -149,299 +222,3518 @@ impl QueryRoot {
         Ok(batch)
     }
 
-    /// Get production history with optional filters
-    async fn production_history(
+    /// Get quick stock counts, oldest first (optionally by status or item)
+    async fn stock_counts(
         &self,
         ctx: &Context<'_>,
-        product_inventory_id: Option<uuid::Uuid>,
-        limit: Option<i32>,
-    ) -> Result<Vec<ProductionBatch>> {
-        let pool = ctx.data::<PgPool>()?;
-        let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
-
-        let batches = if let Some(product_id) = product_inventory_id {
-            sqlx::query_as!(
-                ProductionBatch,
-                r#"
-                SELECT
-                    id, batch_number, product_inventory_id, recipe_template_id,
-                    batch_size, unit, start_date, estimated_completion_date,
-                    completion_date, production_date, status,
-                    production_time_hours, yield_percentage, actual_yield,
-                    quality_notes, storage_location, notes,
-                    created_at, updated_at
-                FROM production_batches
-                WHERE product_inventory_id = $1
-                ORDER BY start_date DESC
-                LIMIT $2
-                "#,
-                product_id,
-                limit as i64
-            )
-            .fetch_all(pool)
-            .await?
-        } else {
-            sqlx::query_as!(
-                ProductionBatch,
-                r#"
-                SELECT
-                    id, batch_number, product_inventory_id, recipe_template_id,
-                    batch_size, unit, start_date, estimated_completion_date,
-                    completion_date, production_date, status,
-                    production_time_hours, yield_percentage, actual_yield,
-                    quality_notes, storage_location, notes,
-                    created_at, updated_at
-                FROM production_batches
-                ORDER BY start_date DESC
-                LIMIT $1
-                "#,
-                limit as i64
-            )
-            .fetch_all(pool)
-            .await?
-        };
-
-        Ok(batches)
-    }
+        status: Option<String>,
+        inventory_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<StockCount>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
-    /// Get all active recipe templates
-    async fn recipe_templates(&self, ctx: &Context<'_>) -> Result<Vec<RecipeTemplate>> {
-        let pool = ctx.data::<PgPool>()?;
-
-        let templates = sqlx::query_as!(
-            RecipeTemplate,
+        let counts = sqlx::query_as!(
+            StockCount,
             r#"
             SELECT
-                id, product_inventory_id, template_name, description,
-                default_batch_size, default_unit, estimated_duration_hours,
-                ingredient_template, instructions,
-                is_active as "is_active!", created_at, updated_at
-            FROM recipe_templates
-            WHERE is_active = true
-            ORDER BY template_name
-            "#
+                id, inventory_id, counted_quantity, expected_quantity, variance as "variance!",
+                unit, counted_by, location, notes, status, counted_at, reviewed_by,
+                reviewed_at, review_notes, applied_adjustment, created_at, updated_at
+            FROM stock_counts
+            WHERE ($1::varchar IS NULL OR status = $1)
+                AND ($2::uuid IS NULL OR inventory_id = $2)
+            ORDER BY counted_at
+            "#,
+            status,
+            inventory_id
         )
         .fetch_all(pool)
         .await?;
 
-        Ok(templates)
+        Ok(counts)
     }
 
-    /// Get a specific recipe template by ID
-    async fn recipe_template(
+    /// Get open lots that expire within the given number of days (default 7),
+    /// including lots that have already expired
+    async fn expiring_items(
         &self,
         ctx: &Context<'_>,
-        id: uuid::Uuid,
-    ) -> Result<Option<RecipeTemplate>> {
-        let pool = ctx.data::<PgPool>()?;
+        within_days: Option<i32>,
+    ) -> Result<Vec<ExpiringLot>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let within_days = within_days.unwrap_or(7).max(0);
 
-        let template = sqlx::query_as!(
-            RecipeTemplate,
+        Ok(lots::expiring(pool, within_days).await?)
+    }
+
+    /// Get how old on-hand stock is per product (0-30, 31-60, 61-90, 90+ days),
+    /// with quantities within `expiringWithinDays` (default 14) of their
+    /// best-by date and whether anything went out in the last `slowAfterDays`
+    /// (default 60), oldest stock first
+    async fn inventory_aging(
+        &self,
+        ctx: &Context<'_>,
+        expiring_within_days: Option<i32>,
+        slow_after_days: Option<i32>,
+        category: Option<String>,
+    ) -> Result<Vec<InventoryAging>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let expiring_within_days = expiring_within_days.unwrap_or(14).max(0);
+        let slow_after_days = slow_after_days.unwrap_or(60).max(1);
+
+        Ok(lots::aging(
+            pool,
+            expiring_within_days,
+            slow_after_days,
+            category.as_deref(),
+        )
+        .await?)
+    }
+
+    /// What stock was worth at `asOf` (default now), optionally for one
+    /// category, from the unit costs recorded on each stock movement
+    async fn inventory_valuation(
+        &self,
+        ctx: &Context<'_>,
+        as_of: Option<DateTime<Utc>>,
+        category: Option<String>,
+    ) -> Result<InventoryValuation> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let as_of = as_of.unwrap_or_else(Utc::now);
+
+        Ok(valuation::as_of(pool, as_of, category.as_deref()).await?)
+    }
+
+    /// Project how long an item's available stock will last, and when it hits
+    /// its reorder point, from average daily consumption over the last
+    /// `windowDays` (default 30)
+    async fn stock_projection(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: uuid::Uuid,
+        window_days: Option<i32>,
+    ) -> Result<Option<StockProjection>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let window_days = window_days.unwrap_or(30).max(1);
+
+        let item = sqlx::query!(
             r#"
             SELECT
-                id, product_inventory_id, template_name, description,
-                default_batch_size, default_unit, estimated_duration_hours,
-                ingredient_template, instructions,
-                is_active as "is_active!", created_at, updated_at
-            FROM recipe_templates
-            WHERE id = $1
+                i.name, i.unit, i.available_stock AS "available_stock!", i.reorder_point,
+                COALESCE((
+                    SELECT SUM(-l.quantity)
+                    FROM inventory_logs l
+                    WHERE l.inventory_id = i.id
+                        AND l.quantity < 0
+                        AND l.movement_type <> 'adjustment'
+                        AND l.created_at >= NOW() - make_interval(days => $2)
+                ), 0) AS "consumed!"
+            FROM inventory i
+            WHERE i.id = $1
             "#,
-            id
+            inventory_id,
+            window_days
         )
         .fetch_optional(pool)
         .await?;
 
-        Ok(template)
+        let Some(item) = item else {
+            return Ok(None);
+        };
+
+        let zero = BigDecimal::from(0);
+        let daily = (&item.consumed / BigDecimal::from(window_days))
+            .with_scale_round(3, bigdecimal::RoundingMode::HalfUp);
+        let today = Utc::now().date_naive();
+
+        // Days for available stock to fall by `drop` at the daily rate
+        let days_until = |drop: BigDecimal| -> Option<BigDecimal> {
+            if daily <= zero {
+                return None;
+            }
+            let days = if drop > zero {
+                drop / &daily
+            } else {
+                zero.clone()
+            };
+            Some(days.with_scale_round(1, bigdecimal::RoundingMode::HalfUp))
+        };
+        let date_after = |days: &BigDecimal| {
+            days.to_i64()
+                .map(|days| today + chrono::Duration::days(days))
+        };
+
+        let days_until_stockout = days_until(item.available_stock.clone());
+        let days_until_reorder_point = days_until(&item.available_stock - &item.reorder_point);
+
+        Ok(Some(StockProjection {
+            inventory_id,
+            item_name: item.name,
+            unit: item.unit,
+            stockout_date: days_until_stockout.as_ref().and_then(date_after),
+            reorder_date: days_until_reorder_point.as_ref().and_then(date_after),
+            available_stock: item.available_stock,
+            reorder_point: item.reorder_point,
+            window_days,
+            consumed: item.consumed,
+            average_daily_consumption: daily,
+            days_until_stockout,
+            days_until_reorder_point,
+        }))
     }
 
-    /// Get all active customers
-    async fn customers(&self, ctx: &Context<'_>) -> Result<Vec<Customer>> {
-        let pool = ctx.data::<PgPool>()?;
+    /// Get products with stock on hand that nothing has drawn on in the last
+    /// `slowAfterDays` (default 60), oldest stock first
+    async fn slow_movers(
+        &self,
+        ctx: &Context<'_>,
+        slow_after_days: Option<i32>,
+        category: Option<String>,
+    ) -> Result<Vec<InventoryAging>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let slow_after_days = slow_after_days.unwrap_or(60).max(1);
 
-        let customers = sqlx::query_as!(
-            Customer,
+        let mut rows = lots::aging(pool, 14, slow_after_days, category.as_deref()).await?;
+        rows.retain(|row| row.slow_mover);
+
+        Ok(rows)
+    }
+
+    /// Get pack sizes defined for a bulk product
+    async fn pack_sizes(
+        &self,
+        ctx: &Context<'_>,
+        bulk_inventory_id: uuid::Uuid,
+    ) -> Result<Vec<PackSize>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let pack_sizes = sqlx::query_as!(
+            PackSize,
             r#"
             SELECT
-                id, name, email, phone,
-                street_address, city, state, zip_code, country,
-                latitude, longitude,
-                customer_type,
-                tax_exempt as "tax_exempt!",
-                notes,
-                is_active as "is_active!",
+                id, bulk_inventory_id, packaged_inventory_id, name,
+                bulk_quantity_per_pack, label_inventory_id, labels_per_pack,
                 created_at, updated_at
-            FROM customers
-            WHERE is_active = true
-            ORDER BY name
-            "#
+            FROM pack_sizes
+            WHERE bulk_inventory_id = $1
+            ORDER BY bulk_quantity_per_pack
+            "#,
+            bulk_inventory_id
         )
         .fetch_all(pool)
         .await?;
 
-        Ok(customers)
+        Ok(pack_sizes)
     }
 
-    /// Get a specific customer by ID
-    async fn customer(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<Customer>> {
-        let pool = ctx.data::<PgPool>()?;
+    /// Get the components of a kit
+    async fn kit_components(
+        &self,
+        ctx: &Context<'_>,
+        kit_inventory_id: uuid::Uuid,
+    ) -> Result<Vec<KitComponent>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
-        let customer = sqlx::query_as!(
-            Customer,
+        let components = sqlx::query_as!(
+            KitComponent,
             r#"
             SELECT
-                id, name, email, phone,
-                street_address, city, state, zip_code, country,
-                latitude, longitude,
-                customer_type,
-                tax_exempt as "tax_exempt!",
-                notes,
-                is_active as "is_active!",
-                created_at, updated_at
-            FROM customers
-            WHERE id = $1
+                k.id, k.kit_inventory_id, k.component_inventory_id,
+                i.name AS component_name, k.quantity, i.unit, i.cost_per_unit
+            FROM kit_components k
+            JOIN inventory i ON i.id = k.component_inventory_id
+            WHERE k.kit_inventory_id = $1
+            ORDER BY i.name
             "#,
-            id
+            kit_inventory_id
         )
-        .fetch_optional(pool)
+        .fetch_all(pool)
         .await?;
 
-        Ok(customer)
+        Ok(components)
     }
 
-    /// Get sales with optional filters
-    async fn sales(
+    /// Get kit assembly runs, newest first, optionally for one kit
+    async fn kit_assemblies(
         &self,
         ctx: &Context<'_>,
-        customer_id: Option<uuid::Uuid>,
-        start_date: Option<DateTime<Utc>>,
-        end_date: Option<DateTime<Utc>>,
+        kit_inventory_id: Option<uuid::Uuid>,
         limit: Option<i32>,
-    ) -> Result<Vec<Sale>> {
-        let pool = ctx.data::<PgPool>()?;
+    ) -> Result<Vec<KitAssembly>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
         let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
 
-        let sales = if let Some(customer_id) = customer_id {
-            sqlx::query_as!(
-                Sale,
-                r#"
-                SELECT
-                    id, sale_number, customer_id, sale_date,
-                    subtotal, tax_amount, discount_amount, total_amount,
-                    payment_method, payment_status, notes,
-                    created_at, updated_at
-                FROM sales
-                WHERE customer_id = $1
-                    AND ($2::timestamptz IS NULL OR sale_date >= $2)
-                    AND ($3::timestamptz IS NULL OR sale_date <= $3)
-                ORDER BY sale_date DESC
-                LIMIT $4
-                "#,
-                customer_id,
-                start_date,
-                end_date,
-                limit as i64
-            )
-            .fetch_all(pool)
-            .await?
-        } else {
-            sqlx::query_as!(
-                Sale,
-                r#"
-                SELECT
-                    id, sale_number, customer_id, sale_date,
-                    subtotal, tax_amount, discount_amount, total_amount,
-                    payment_method, payment_status, notes,
-                    created_at, updated_at
-                FROM sales
-                WHERE ($1::timestamptz IS NULL OR sale_date >= $1)
-                    AND ($2::timestamptz IS NULL OR sale_date <= $2)
-                ORDER BY sale_date DESC
-                LIMIT $3
-                "#,
-                start_date,
-                end_date,
-                limit as i64
-            )
-            .fetch_all(pool)
-            .await?
-        };
+        let assemblies = sqlx::query_as!(
+            KitAssembly,
+            r#"
+            SELECT
+                id, assembly_number, kit_inventory_id, quantity, unit_cost, total_cost,
+                notes, assembled_at, created_at
+            FROM kit_assemblies
+            WHERE ($1::uuid IS NULL OR kit_inventory_id = $1)
+            ORDER BY assembled_at DESC
+            LIMIT $2
+            "#,
+            kit_inventory_id,
+            limit as i64
+        )
+        .fetch_all(pool)
+        .await?;
 
-        Ok(sales)
+        Ok(assemblies)
     }
 
-    /// Get full sale details including items and customer
-    async fn sale_details(
+    /// Plan a seasonal kit build (e.g. December gift boxes): component needs
+    /// for the kits still to build against stock and in-progress batches,
+    /// batches to start for components made in-house, and build progress.
+    /// The season defaults to the current calendar year.
+    async fn bundle_plan(
         &self,
         ctx: &Context<'_>,
-        sale_id: uuid::Uuid,
-    ) -> Result<Option<SaleWithItems>> {
-        let pool = ctx.data::<PgPool>()?;
+        kit_inventory_id: uuid::Uuid,
+        target_quantity: BigDecimal,
+        since: Option<NaiveDate>,
+    ) -> Result<BundlePlan> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let zero = BigDecimal::from(0);
 
-        // Fetch the sale
-        let sale = sqlx::query_as!(
-            Sale,
+        if target_quantity <= zero {
+            return Err("Target quantity must be greater than 0".into());
+        }
+
+        let today = Utc::now().date_naive();
+        let since = since.unwrap_or_else(|| today.with_ordinal(1).unwrap_or(today));
+
+        let kit_name =
+            sqlx::query_scalar!("SELECT name FROM inventory WHERE id = $1", kit_inventory_id)
+                .fetch_optional(pool)
+                .await?
+                .ok_or("Kit item not found")?;
+
+        let assembled = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(quantity), 0) AS "assembled!"
+            FROM kit_assemblies
+            WHERE kit_inventory_id = $1 AND assembled_at >= $2
+            "#,
+            kit_inventory_id,
+            since.and_time(NaiveTime::MIN).and_utc()
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let rows = sqlx::query!(
             r#"
             SELECT
-                id, sale_number, customer_id, sale_date,
-                subtotal, tax_amount, discount_amount, total_amount,
-                payment_method, payment_status, notes,
+                k.component_inventory_id, i.name, i.unit, k.quantity,
+                i.available_stock AS "available!",
+                (SELECT COALESCE(SUM(b.batch_size), 0)
+                 FROM production_batches b
+                 WHERE b.product_inventory_id = i.id AND b.status = 'in_progress') AS "in_production!",
+                r.id AS "recipe_template_id?", r.template_name AS "recipe_name?",
+                r.default_batch_size AS "default_batch_size?"
+            FROM kit_components k
+            JOIN inventory i ON i.id = k.component_inventory_id
+            LEFT JOIN LATERAL (
+                SELECT id, template_name, default_batch_size
+                FROM recipe_templates
+                WHERE product_inventory_id = i.id AND is_active = true
+                ORDER BY created_at
+                LIMIT 1
+            ) r ON true
+            WHERE k.kit_inventory_id = $1
+            ORDER BY i.name
+            "#,
+            kit_inventory_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        if rows.is_empty() {
+            return Err(format!("{} has no components defined", kit_name).into());
+        }
+
+        let remaining = if assembled < target_quantity {
+            &target_quantity - &assembled
+        } else {
+            zero.clone()
+        };
+
+        let mut buildable_now: Option<BigDecimal> = None;
+        let mut components = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let buildable = (&row.available / &row.quantity)
+                .with_scale_round(0, bigdecimal::RoundingMode::Floor)
+                .max(zero.clone());
+            if buildable_now
+                .as_ref()
+                .is_none_or(|least| buildable < *least)
+            {
+                buildable_now = Some(buildable);
+            }
+
+            let required = &row.quantity * &remaining;
+            let covered = &row.available + &row.in_production;
+            let shortfall = if covered < required {
+                &required - &covered
+            } else {
+                zero.clone()
+            };
+
+            let batches_needed = match &row.default_batch_size {
+                Some(size) if *size > zero => (&shortfall / size)
+                    .with_scale_round(0, bigdecimal::RoundingMode::Ceiling)
+                    .to_i64(),
+                _ => None,
+            }
+            .filter(|_| row.recipe_template_id.is_some());
+
+            components.push(BundleComponentPlan {
+                inventory_id: row.component_inventory_id,
+                name: row.name,
+                unit: row.unit,
+                quantity_per_kit: row.quantity,
+                required,
+                available: row.available,
+                in_production: row.in_production,
+                shortfall,
+                recipe_template_id: row.recipe_template_id,
+                recipe_name: row.recipe_name,
+                batches_needed,
+            });
+        }
+
+        let progress_percent = (&assembled * BigDecimal::from(100) / &target_quantity)
+            .with_scale_round(1, bigdecimal::RoundingMode::HalfUp)
+            .min(BigDecimal::from(100));
+
+        Ok(BundlePlan {
+            kit_inventory_id,
+            kit_name,
+            target_quantity,
+            since,
+            assembled,
+            remaining,
+            progress_percent,
+            buildable_now: buildable_now.unwrap_or(zero),
+            components,
+        })
+    }
+
+    /// Get bulk vs packaged stock for one product, or for every product with pack sizes
+    async fn packaging_breakdown(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<PackagingBreakdown>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let bulk_items = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            SELECT
+                id,
+                name,
+                category,
+                unit,
+                current_stock,
+                reserved_stock,
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point,
+                cost_per_unit,
+                default_supplier_id,
+                shelf_life_days,
+                storage_requirements,
+                product_code,
+                is_active,
+                version,
+                created_at,
+                updated_at
+            FROM inventory
+            WHERE ($1::uuid IS NOT NULL AND id = $1)
+                OR ($1::uuid IS NULL AND id IN (SELECT bulk_inventory_id FROM pack_sizes))
+            ORDER BY name
+            "#,
+            inventory_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let pack_rows = sqlx::query!(
+            r#"
+            SELECT
+                p.id, p.bulk_inventory_id, p.packaged_inventory_id, p.name,
+                p.bulk_quantity_per_pack, p.label_inventory_id, p.labels_per_pack,
+                p.created_at, p.updated_at, i.current_stock as packs_on_hand
+            FROM pack_sizes p
+            JOIN inventory i ON i.id = p.packaged_inventory_id
+            WHERE $1::uuid IS NULL OR p.bulk_inventory_id = $1
+            ORDER BY p.bulk_quantity_per_pack
+            "#,
+            inventory_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let breakdowns = bulk_items
+            .into_iter()
+            .map(|bulk_item| {
+                let packs: Vec<PackStock> = pack_rows
+                    .iter()
+                    .filter(|row| row.bulk_inventory_id == bulk_item.id)
+                    .map(|row| PackStock {
+                        bulk_equivalent: &row.packs_on_hand * &row.bulk_quantity_per_pack,
+                        packs_on_hand: row.packs_on_hand.clone(),
+                        pack_size: PackSize {
+                            id: row.id,
+                            bulk_inventory_id: row.bulk_inventory_id,
+                            packaged_inventory_id: row.packaged_inventory_id,
+                            name: row.name.clone(),
+                            bulk_quantity_per_pack: row.bulk_quantity_per_pack.clone(),
+                            label_inventory_id: row.label_inventory_id,
+                            labels_per_pack: row.labels_per_pack,
+                            created_at: row.created_at,
+                            updated_at: row.updated_at,
+                        },
+                    })
+                    .collect();
+
+                let total_bulk_equivalent = packs
+                    .iter()
+                    .fold(bulk_item.current_stock.clone(), |total, pack| {
+                        total + &pack.bulk_equivalent
+                    });
+
+                PackagingBreakdown {
+                    bulk_stock: bulk_item.current_stock.clone(),
+                    bulk_item,
+                    packs,
+                    total_bulk_equivalent,
+                }
+            })
+            .collect();
+
+        Ok(breakdowns)
+    }
+
+    /// Sell-through status of every discontinued product (packaged SKUs
+    /// included), most recently discontinued first
+    async fn discontinued_products(&self, ctx: &Context<'_>) -> Result<Vec<DiscontinuationReport>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let ids = sqlx::query_scalar!(
+            "SELECT id FROM inventory WHERE discontinued_at IS NOT NULL ORDER BY discontinued_at DESC, name"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut reports = Vec::new();
+        for id in ids {
+            reports.extend(discontinuation::report(pool, id).await?);
+        }
+
+        Ok(reports)
+    }
+
+    /// Sell-through status of one discontinued product
+    async fn discontinuation_report(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: uuid::Uuid,
+    ) -> Result<Option<DiscontinuationReport>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(discontinuation::report(pool, inventory_id).await?)
+    }
+
+    /// Get all translations for an inventory item
+    async fn product_translations(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: uuid::Uuid,
+    ) -> Result<Vec<ProductTranslation>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let translations = sqlx::query_as!(
+            ProductTranslation,
+            r#"
+            SELECT id, inventory_id, locale, name, description, label_text, created_at, updated_at
+            FROM product_translations
+            WHERE inventory_id = $1
+            ORDER BY locale
+            "#,
+            inventory_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(translations)
+    }
+
+    /// Get the customer-facing product catalog in a locale (defaults to English)
+    async fn catalog(
+        &self,
+        ctx: &Context<'_>,
+        locale: Option<String>,
+    ) -> Result<Vec<LocalizedProduct>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let locale = locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+
+        // Finished products and their packaged SKUs
+        let products = sqlx::query_as!(
+            LocalizedProduct,
+            r#"
+            SELECT
+                i.id as inventory_id,
+                t.locale as "locale?",
+                COALESCE(t.name, i.name) as "name!",
+                t.description as "description?",
+                t.label_text as "label_text?",
+                i.unit,
+                i.available_stock as "available_stock!"
+            FROM inventory i
+            LEFT JOIN LATERAL (
+                SELECT locale, name, description, label_text
+                FROM product_translations
+                WHERE inventory_id = i.id
+                    AND locale IN ($1::varchar, split_part($1, '-', 1), $2)
+                ORDER BY locale = $1::varchar DESC, locale = split_part($1, '-', 1) DESC
+                LIMIT 1
+            ) t ON true
+            WHERE i.is_active = true
+                AND i.discontinued_at IS NULL
+                AND (i.category = 'finished_product'
+                    OR EXISTS (SELECT 1 FROM pack_sizes ps WHERE ps.packaged_inventory_id = i.id))
+            ORDER BY 3
+            "#,
+            locale,
+            DEFAULT_LOCALE
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(products)
+    }
+
+    /// Get the public availability band (in stock / low / sold out) of every
+    /// catalog product, as last checked by the server
+    async fn product_availability(&self, ctx: &Context<'_>) -> Result<Vec<ProductAvailability>> {
+        let feed = ctx.data::<AvailabilityFeed>()?;
+
+        Ok(feed.snapshot().await)
+    }
+
+    /// Get label text for a product in a locale (defaults to English)
+    async fn product_label(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: uuid::Uuid,
+        locale: Option<String>,
+    ) -> Result<Option<LocalizedProduct>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let locale = locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+
+        let label = sqlx::query_as!(
+            LocalizedProduct,
+            r#"
+            SELECT
+                i.id as inventory_id,
+                t.locale as "locale?",
+                COALESCE(t.name, i.name) as "name!",
+                t.description as "description?",
+                t.label_text as "label_text?",
+                i.unit,
+                i.available_stock as "available_stock!"
+            FROM inventory i
+            LEFT JOIN LATERAL (
+                SELECT locale, name, description, label_text
+                FROM product_translations
+                WHERE inventory_id = i.id
+                    AND locale IN ($2::varchar, split_part($2, '-', 1), $3)
+                ORDER BY locale = $2::varchar DESC, locale = split_part($2, '-', 1) DESC
+                LIMIT 1
+            ) t ON true
+            WHERE i.id = $1
+            "#,
+            inventory_id,
+            locale,
+            DEFAULT_LOCALE
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    /// Plan the labels for a packaging day: label counts per batch and SKU,
+    /// the label stock they use, and anything stopping the run being printed
+    async fn label_run(&self, ctx: &Context<'_>, input: LabelRunInput) -> Result<LabelRunPlan> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let mut conn = pool.acquire().await?;
+
+        Ok(labels::plan(&mut conn, &input).await?)
+    }
+
+    /// Get all suppliers (deactivated ones only when asked)
+    async fn suppliers(
+        &self,
+        ctx: &Context<'_>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<Supplier>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let suppliers = sqlx::query_as!(
+            Supplier,
+            "SELECT id, name, contact_email, contact_phone, street_address, city, state, zip_code, country, latitude, longitude, notes, is_active, created_at, updated_at FROM suppliers WHERE is_active OR $1 ORDER BY name",
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(suppliers)
+    }
+
+    /// Get purchase orders, newest first (optionally by status or supplier)
+    async fn purchase_orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        supplier_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<PurchaseOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let orders = sqlx::query_as!(
+            PurchaseOrder,
+            r#"
+            SELECT
+                id, po_number, supplier_id, status, order_date, expected_date, notes,
+                total_amount, requested_by, requested_by_user_id, decided_by, decided_at,
+                decision_note, contract_id, created_at, updated_at
+            FROM purchase_orders
+            WHERE ($1::varchar IS NULL OR status = $1)
+                AND ($2::uuid IS NULL OR supplier_id = $2)
+            ORDER BY order_date DESC
+            "#,
+            status,
+            supplier_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(orders)
+    }
+
+    /// Supplier contracts with what's left of each commitment and when price
+    /// protection ends, soonest expiry first. Closed contracts are left out
+    /// unless `includeClosed` is set.
+    async fn supplier_contracts(
+        &self,
+        ctx: &Context<'_>,
+        supplier_id: Option<uuid::Uuid>,
+        include_closed: Option<bool>,
+    ) -> Result<Vec<SupplierContract>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let contracts = sqlx::query_as!(
+            SupplierContract,
+            r#"
+            SELECT
+                id, contract_number, supplier_id, start_date, end_date, status, notes,
                 created_at, updated_at
-            FROM sales
+            FROM supplier_contracts
+            WHERE ($1::uuid IS NULL OR supplier_id = $1)
+                AND ($2 OR status = 'active')
+            ORDER BY end_date, contract_number
+            "#,
+            supplier_id,
+            include_closed.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(contracts)
+    }
+
+    /// Get a single purchase order by ID
+    async fn purchase_order(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<PurchaseOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let order = sqlx::query_as!(
+            PurchaseOrder,
+            r#"
+            SELECT
+                id, po_number, supplier_id, status, order_date, expected_date, notes,
+                total_amount, requested_by, requested_by_user_id, decided_by, decided_at,
+                decision_note, contract_id, created_at, updated_at
+            FROM purchase_orders
             WHERE id = $1
             "#,
-            sale_id
+            id
         )
         .fetch_optional(pool)
         .await?;
 
-        let Some(sale) = sale else {
-            return Ok(None);
-        };
+        Ok(order)
+    }
+
+    /// Spend limits above which a role's purchase orders wait for approval
+    async fn purchase_approval_limits(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<PurchaseApprovalLimit>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let limits = sqlx::query_as!(
+            PurchaseApprovalLimit,
+            "SELECT id, role, max_amount, created_at, updated_at FROM purchase_approval_limits ORDER BY role"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(limits)
+    }
+
+    /// A supplier's price catalog from its latest import (optionally by SKU or
+    /// description, and including items no longer listed)
+    async fn supplier_catalog(
+        &self,
+        ctx: &Context<'_>,
+        supplier_id: uuid::Uuid,
+        search: Option<String>,
+        include_removed: Option<bool>,
+    ) -> Result<Vec<SupplierCatalogItem>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
 
-        // Fetch sale items
         let items = sqlx::query_as!(
-            SaleItem,
+            SupplierCatalogItem,
             r#"
             SELECT
-                id, sale_id, inventory_id, quantity,
-                unit_price, line_total, notes
-            FROM sale_items
-            WHERE sale_id = $1
+                id, supplier_id, sku, description, pack_size, price, is_available,
+                inventory_id, removed_at, created_at, updated_at
+            FROM supplier_catalog_items
+            WHERE supplier_id = $1
+                AND ($2::boolean OR removed_at IS NULL)
+                AND ($3::text IS NULL OR sku ILIKE '%' || $3 || '%' OR description ILIKE '%' || $3 || '%')
+            ORDER BY description, sku
             "#,
-            sale_id
+            supplier_id,
+            include_removed.unwrap_or(false),
+            search
         )
         .fetch_all(pool)
         .await?;
 
-        // Fetch customer if exists
-        let customer = if let Some(customer_id) = sale.customer_id {
-            sqlx::query_as!(
-                Customer,
-                r#"
-                SELECT
-                    id, name, email, phone,
-                    street_address, city, state, zip_code, country,
-                    latitude, longitude,
-                    customer_type,
-                    tax_exempt as "tax_exempt!",
-                    notes,
-                    is_active as "is_active!",
-                    created_at, updated_at
-                FROM customers
-                WHERE id = $1
-                "#,
-                customer_id
-            )
-            .fetch_optional(pool)
-            .await?
-        } else {
-            None
-        };
+        Ok(items)
+    }
 
-        Ok(Some(SaleWithItems {
-            sale,
-            items,
-            customer,
-        }))
+    /// Catalog imports, newest first (optionally for one supplier)
+    async fn supplier_catalog_imports(
+        &self,
+        ctx: &Context<'_>,
+        supplier_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<SupplierCatalogImport>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let imports = sqlx::query_as!(
+            SupplierCatalogImport,
+            r#"
+            SELECT
+                id, supplier_id, file_name, row_count, added, changed, removed,
+                alert_threshold_percent, created_at
+            FROM supplier_catalog_imports
+            WHERE ($1::uuid IS NULL OR supplier_id = $1)
+            ORDER BY created_at DESC
+            "#,
+            supplier_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(imports)
+    }
+
+    /// Get all active production batches (in_progress status)
+    async fn active_batches(&self, ctx: &Context<'_>) -> Result<Vec<ProductionBatch>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let batches = sqlx::query_as!(
+            ProductionBatch,
+            r#"
+            SELECT
+                id, batch_number, product_inventory_id, recipe_template_id,
+                batch_size, unit, start_date, estimated_completion_date,
+                completion_date, production_date, status,
+                production_time_hours, yield_percentage, actual_yield,
+                quality_notes, storage_location_id,
+                (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                vessel_id, last_moved_at, notes,
+                created_at, updated_at
+            FROM production_batches
+            WHERE status = 'in_progress'
+            ORDER BY start_date DESC
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(batches)
+    }
+
+    /// Batches still in progress past their estimated completion date, most
+    /// overdue first (optionally only those at least `minDaysOverdue` days over)
+    async fn overdue_batches(
+        &self,
+        ctx: &Context<'_>,
+        min_days_overdue: Option<i32>,
+    ) -> Result<Vec<OverdueBatch>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(batches::overdue(pool, min_days_overdue.unwrap_or(0)).await?)
+    }
+
+    /// Get a specific production batch by ID
+    async fn production_batch(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<ProductionBatch>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let batch = sqlx::query_as!(
+            ProductionBatch,
+            r#"
+            SELECT
+                id, batch_number, product_inventory_id, recipe_template_id,
+                batch_size, unit, start_date, estimated_completion_date,
+                completion_date, production_date, status,
+                production_time_hours, yield_percentage, actual_yield,
+                quality_notes, storage_location_id,
+                (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                vessel_id, last_moved_at, notes,
+                created_at, updated_at
+            FROM production_batches
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(batch)
+    }
+
+    /// Get production history with optional filters
+    async fn production_history(
+        &self,
+        ctx: &Context<'_>,
+        product_inventory_id: Option<uuid::Uuid>,
+        limit: Option<i32>,
+    ) -> Result<Vec<ProductionBatch>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
+
+        let batches = if let Some(product_id) = product_inventory_id {
+            sqlx::query_as!(
+                ProductionBatch,
+                r#"
+                SELECT
+                    id, batch_number, product_inventory_id, recipe_template_id,
+                    batch_size, unit, start_date, estimated_completion_date,
+                    completion_date, production_date, status,
+                    production_time_hours, yield_percentage, actual_yield,
+                    quality_notes, storage_location_id,
+                    (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                    vessel_id, last_moved_at, notes,
+                    created_at, updated_at
+                FROM production_batches
+                WHERE product_inventory_id = $1
+                ORDER BY start_date DESC
+                LIMIT $2
+                "#,
+                product_id,
+                limit as i64
+            )
+            .fetch_all(pool)
+            .await?
+        } else {
+            sqlx::query_as!(
+                ProductionBatch,
+                r#"
+                SELECT
+                    id, batch_number, product_inventory_id, recipe_template_id,
+                    batch_size, unit, start_date, estimated_completion_date,
+                    completion_date, production_date, status,
+                    production_time_hours, yield_percentage, actual_yield,
+                    quality_notes, storage_location_id,
+                    (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+                    vessel_id, last_moved_at, notes,
+                    created_at, updated_at
+                FROM production_batches
+                ORDER BY start_date DESC
+                LIMIT $1
+                "#,
+                limit as i64
+            )
+            .fetch_all(pool)
+            .await?
+        };
+
+        Ok(batches)
+    }
+
+    /// Trace a batch forward: which lots, sales, and downstream batches contain its output
+    async fn trace_forward(
+        &self,
+        ctx: &Context<'_>,
+        batch_number: String,
+    ) -> Result<Option<ForwardTrace>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(traceability::forward_from_batch(pool, &batch_number).await?)
+    }
+
+    /// Trace a sale or batch backward to the batches and ingredient lots that went into it
+    async fn trace_backward(
+        &self,
+        ctx: &Context<'_>,
+        sale_id: Option<uuid::Uuid>,
+        batch_number: Option<String>,
+    ) -> Result<Option<BackwardTrace>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        match (sale_id, batch_number) {
+            (Some(sale_id), None) => Ok(traceability::backward_from_sale(pool, sale_id).await?),
+            (None, Some(batch_number)) => {
+                Ok(traceability::backward_from_batch(pool, &batch_number).await?)
+            }
+            _ => Err("Provide exactly one of saleId or batchNumber".into()),
+        }
+    }
+
+    /// Fields of the public batch trace page (`/trace/{batchNumber}`) and
+    /// whether each is shown
+    async fn trace_page_fields(&self, ctx: &Context<'_>) -> Result<Vec<TracePageField>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(traceability::trace_page_fields(pool).await?)
+    }
+
+    /// Get HACCP quality checks across batches (e.g. for an inspection report)
+    async fn quality_checks(
+        &self,
+        ctx: &Context<'_>,
+        batch_id: Option<uuid::Uuid>,
+        failed_only: Option<bool>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<QualityCheck>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let checks = sqlx::query_as!(
+            QualityCheck,
+            r#"
+            SELECT
+                id, batch_id, check_type, control_point, passed, measured_value,
+                unit, critical_limit, corrective_action, signed_by, checked_at,
+                notes, created_at
+            FROM quality_checks
+            WHERE ($1::uuid IS NULL OR batch_id = $1)
+                AND (NOT $2 OR passed = false)
+                AND ($3::timestamptz IS NULL OR checked_at >= $3)
+                AND ($4::timestamptz IS NULL OR checked_at <= $4)
+            ORDER BY checked_at DESC
+            "#,
+            batch_id,
+            failed_only.unwrap_or(false),
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(checks)
+    }
+
+    /// Get batch alerts, newest first (optionally for one batch or only unacknowledged)
+    async fn batch_alerts(
+        &self,
+        ctx: &Context<'_>,
+        batch_id: Option<uuid::Uuid>,
+        unacknowledged_only: Option<bool>,
+        limit: Option<i32>,
+    ) -> Result<Vec<BatchAlert>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
+
+        let alerts = sqlx::query_as!(
+            BatchAlert,
+            r#"
+            SELECT
+                id, batch_id, reading_id, alert_type, message, measured_value,
+                threshold, acknowledged_at, created_at
+            FROM batch_alerts
+            WHERE ($1::uuid IS NULL OR batch_id = $1)
+                AND (NOT $2 OR acknowledged_at IS NULL)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            batch_id,
+            unacknowledged_only.unwrap_or(false),
+            limit as i64
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(alerts)
+    }
+
+    /// Get storage locations (active only unless include_inactive is set)
+    async fn locations(
+        &self,
+        ctx: &Context<'_>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<Location>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let locations = sqlx::query_as!(
+            Location,
+            r#"
+            SELECT id, name, location_type, description, is_active, created_at, updated_at
+            FROM locations
+            WHERE $1 OR is_active = true
+            ORDER BY name
+            "#,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(locations)
+    }
+
+    /// Get stock per location, optionally for one item or one location.
+    /// Stock that isn't in any location is listed without one.
+    async fn location_stock(
+        &self,
+        ctx: &Context<'_>,
+        inventory_id: Option<uuid::Uuid>,
+        location_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<LocationStock>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(locations::stock(pool, inventory_id, location_id).await?)
+    }
+
+    /// Get active vessels (optionally filtered by status)
+    async fn vessels(&self, ctx: &Context<'_>, status: Option<String>) -> Result<Vec<Vessel>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let vessels = sqlx::query_as!(
+            Vessel,
+            r#"
+            SELECT
+                id, name, vessel_type, capacity, capacity_unit, status, location,
+                notes, is_active, created_at, updated_at
+            FROM vessels
+            WHERE is_active = true AND ($1::text IS NULL OR status = $1)
+            ORDER BY name
+            "#,
+            status
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(vessels)
+    }
+
+    /// Get a specific vessel by ID
+    async fn vessel(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<Vessel>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let vessel = sqlx::query_as!(
+            Vessel,
+            r#"
+            SELECT
+                id, name, vessel_type, capacity, capacity_unit, status, location,
+                notes, is_active, created_at, updated_at
+            FROM vessels
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(vessel)
+    }
+
+    /// Get how much of a period each vessel was occupied (defaults to the last 30 days)
+    async fn vessel_utilization(
+        &self,
+        ctx: &Context<'_>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<VesselUtilization>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let end_date = end_date.unwrap_or_else(Utc::now);
+        let start_date = start_date.unwrap_or(end_date - chrono::Duration::days(30));
+
+        if start_date >= end_date {
+            return Err("start_date must be before end_date".into());
+        }
+
+        let utilization = sqlx::query_as!(
+            VesselUtilization,
+            r#"
+            WITH occupancy AS (
+                SELECT
+                    pb.vessel_id,
+                    EXTRACT(EPOCH FROM
+                        LEAST(COALESCE(pb.completion_date, NOW()), $2)
+                        - GREATEST(pb.start_date, $1)
+                    ) / 3600 AS hours
+                FROM production_batches pb
+                WHERE pb.vessel_id IS NOT NULL
+                    AND pb.start_date < $2
+                    AND COALESCE(pb.completion_date, NOW()) > $1
+            )
+            SELECT
+                v.id as vessel_id,
+                v.name,
+                v.vessel_type,
+                v.status,
+                COUNT(o.vessel_id) as "batch_count!",
+                ROUND(COALESCE(SUM(o.hours), 0), 1) as "occupied_hours!",
+                ROUND(
+                    COALESCE(SUM(o.hours), 0) * 100
+                        / (EXTRACT(EPOCH FROM ($2::timestamptz - $1::timestamptz)) / 3600),
+                    1
+                ) as "utilization_percent!"
+            FROM vessels v
+            LEFT JOIN occupancy o ON o.vessel_id = v.id
+            WHERE v.is_active = true
+            GROUP BY v.id, v.name, v.vessel_type, v.status
+            ORDER BY 7 DESC, v.name
+            "#,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(utilization)
+    }
+
+    /// Get the sanitation log, newest first (optionally for one vessel or a date range)
+    async fn sanitation_logs(
+        &self,
+        ctx: &Context<'_>,
+        vessel_id: Option<uuid::Uuid>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<Vec<SanitationLog>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let logs = sqlx::query_as!(
+            SanitationLog,
+            r#"
+            SELECT
+                id, vessel_id, chemical, concentration, concentration_unit,
+                operator, cleaned_at, notes, created_at
+            FROM sanitation_logs
+            WHERE ($1::uuid IS NULL OR vessel_id = $1)
+                AND ($2::timestamptz IS NULL OR cleaned_at >= $2)
+                AND ($3::timestamptz IS NULL OR cleaned_at <= $3)
+            ORDER BY cleaned_at DESC
+            "#,
+            vessel_id,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    /// Get when each active vessel was last cleaned (never-cleaned vessels first)
+    async fn vessel_sanitation_status(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<VesselSanitationStatus>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let statuses = sqlx::query_as!(
+            VesselSanitationStatus,
+            r#"
+            SELECT
+                v.id as vessel_id,
+                v.name,
+                v.status,
+                sl.cleaned_at as "last_cleaned_at?",
+                sl.chemical as "last_chemical?",
+                sl.concentration as "last_concentration?",
+                sl.concentration_unit as "last_concentration_unit?",
+                sl.operator as "last_operator?",
+                EXTRACT(DAY FROM NOW() - sl.cleaned_at)::int as "days_since_cleaned?"
+            FROM vessels v
+            LEFT JOIN LATERAL (
+                SELECT cleaned_at, chemical, concentration, concentration_unit, operator
+                FROM sanitation_logs
+                WHERE vessel_id = v.id
+                ORDER BY cleaned_at DESC
+                LIMIT 1
+            ) sl ON true
+            WHERE v.is_active = true
+            ORDER BY sl.cleaned_at ASC NULLS FIRST, v.name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(statuses)
+    }
+
+    /// Get business calendar entries overlapping a date range (optionally of one kind)
+    async fn calendar_events(
+        &self,
+        ctx: &Context<'_>,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+        kind: Option<String>,
+    ) -> Result<Vec<CalendarEvent>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let events = sqlx::query_as!(
+            CalendarEvent,
+            r#"
+            SELECT
+                id, kind, name, start_date, end_date, blocks_production,
+                blocks_deliveries, notes, created_at, updated_at
+            FROM business_calendar
+            WHERE ($1::date IS NULL OR end_date >= $1)
+                AND ($2::date IS NULL OR start_date <= $2)
+                AND ($3::varchar IS NULL OR kind = $3)
+            ORDER BY start_date, name
+            "#,
+            start_date,
+            end_date,
+            kind
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Get whether a day is open for production and deliveries
+    async fn business_day(&self, ctx: &Context<'_>, date: NaiveDate) -> Result<BusinessDay> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let events = calendar::events_on(pool, date).await?;
+
+        Ok(BusinessDay {
+            date,
+            production_open: !events.iter().any(|e| e.blocks_production),
+            deliveries_open: !events.iter().any(|e| e.blocks_deliveries),
+            events,
+        })
+    }
+
+    /// Get the first day on or after a date (default today) that's open for
+    /// 'production' or 'delivery', for scheduling deliveries and order cutoffs
+    async fn next_open_day(
+        &self,
+        ctx: &Context<'_>,
+        purpose: String,
+        from: Option<NaiveDate>,
+    ) -> Result<Option<NaiveDate>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let purpose =
+            Purpose::parse(&purpose).ok_or("Purpose must be one of: production, delivery")?;
+        let from = from.unwrap_or_else(|| Utc::now().date_naive());
+
+        Ok(calendar::next_open_day(pool, from, purpose).await?)
+    }
+
+    /// Get all active recipe templates
+    async fn recipe_templates(&self, ctx: &Context<'_>) -> Result<Vec<RecipeTemplate>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let templates = sqlx::query_as!(
+            RecipeTemplate,
+            r#"
+            SELECT
+                id, product_inventory_id, template_name, description,
+                default_batch_size, default_unit, estimated_duration_hours,
+                ingredient_template, instructions, min_temperature, max_temperature,
+                target_ph_curve, is_active as "is_active!", version, created_at, updated_at
+            FROM recipe_templates
+            WHERE is_active = true
+            ORDER BY template_name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(templates)
+    }
+
+    /// Get a specific recipe template by ID
+    async fn recipe_template(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<RecipeTemplate>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let template = sqlx::query_as!(
+            RecipeTemplate,
+            r#"
+            SELECT
+                id, product_inventory_id, template_name, description,
+                default_batch_size, default_unit, estimated_duration_hours,
+                ingredient_template, instructions, min_temperature, max_temperature,
+                target_ph_curve, is_active as "is_active!", version, created_at, updated_at
+            FROM recipe_templates
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(template)
+    }
+
+    /// Get storage transition rules for a recipe template
+    async fn storage_transition_rules(
+        &self,
+        ctx: &Context<'_>,
+        recipe_template_id: uuid::Uuid,
+    ) -> Result<Vec<StorageTransitionRule>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let rules = sqlx::query_as!(
+            StorageTransitionRule,
+            r#"
+            SELECT
+                id, recipe_template_id, from_location, to_location,
+                after_days, notes, created_at, updated_at
+            FROM storage_transition_rules
+            WHERE recipe_template_id = $1
+            ORDER BY after_days
+            "#,
+            recipe_template_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Price a recipe's ingredients for a batch (default: the recipe's default
+    /// batch size) at 'current' or 'average' ingredient costs, and compare the
+    /// cost per unit against a sale price (default: the product's average
+    /// price over the last 90 days of sales)
+    async fn recipe_cost(
+        &self,
+        ctx: &Context<'_>,
+        recipe_id: uuid::Uuid,
+        batch_size: Option<BigDecimal>,
+        cost_basis: Option<String>,
+        sale_price: Option<BigDecimal>,
+    ) -> Result<RecipeCost> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let cost_basis = cost_basis.unwrap_or_else(|| "current".to_string());
+        let cost = recipes::cost(pool, recipe_id, batch_size, &cost_basis, sale_price).await?;
+
+        Ok(cost?)
+    }
+
+    /// Watched recipes with their current cost and margin against their limits
+    /// (only those over a limit if `alerting_only` is set)
+    async fn recipe_cost_watches(
+        &self,
+        ctx: &Context<'_>,
+        alerting_only: Option<bool>,
+    ) -> Result<Vec<RecipeCostWatchStatus>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let mut statuses = cost_watch::evaluate(pool, None).await?;
+        if alerting_only.unwrap_or(false) {
+            statuses.retain(|status| status.breached);
+        }
+
+        Ok(statuses)
+    }
+
+    /// Overhead pools with their current allocation rates
+    async fn overhead_pools(
+        &self,
+        ctx: &Context<'_>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<OverheadPool>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let pools = sqlx::query_as!(
+            OverheadPool,
+            r#"
+            SELECT
+                id, name, monthly_amount, allocation_basis, expected_monthly_volume,
+                is_active, notes, created_at, updated_at
+            FROM overhead_pools
+            WHERE $1 OR is_active = true
+            ORDER BY name
+            "#,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(pools)
+    }
+
+    /// Check whether a batch of a recipe (default: the recipe's default batch
+    /// size) can be made from available stock, and the largest batch that can
+    async fn recipe_feasibility(
+        &self,
+        ctx: &Context<'_>,
+        recipe_id: uuid::Uuid,
+        batch_size: Option<BigDecimal>,
+    ) -> Result<RecipeFeasibility> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let zero = BigDecimal::from(0);
+
+        let recipe = sqlx::query!(
+            "SELECT template_name, default_batch_size, default_unit FROM recipe_templates WHERE id = $1",
+            recipe_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or("Recipe template not found")?;
+
+        let batch_size = batch_size
+            .or(recipe.default_batch_size.clone())
+            .ok_or("Batch size is required for recipes without a default batch size")?;
+        if batch_size <= zero {
+            return Err("Batch size must be greater than 0".into());
+        }
+
+        let factor = recipes::scale(recipe.default_batch_size.as_ref(), &batch_size);
+
+        // Ingredient quantities are per this many units of batch
+        let per = recipe
+            .default_batch_size
+            .clone()
+            .filter(|size| *size > zero)
+            .unwrap_or_else(|| batch_size.clone());
+
+        let mut lines = Vec::new();
+        let mut max_batch_size: Option<BigDecimal> = None;
+
+        for ingredient in recipes::ingredients(pool, recipe_id).await? {
+            let available = sqlx::query_scalar!(
+                r#"SELECT available_stock AS "available!" FROM inventory WHERE id = $1"#,
+                ingredient.inventory_id
+            )
+            .fetch_one(pool)
+            .await?
+            .max(zero.clone());
+
+            let required = (&ingredient.quantity_per_batch * &factor)
+                .with_scale_round(3, bigdecimal::RoundingMode::HalfUp);
+            let shortfall = if available < required {
+                &required - &available
+            } else {
+                zero.clone()
+            };
+
+            let possible = (&available / &ingredient.quantity_per_batch * &per)
+                .with_scale_round(3, bigdecimal::RoundingMode::Floor);
+            if max_batch_size.as_ref().is_none_or(|max| possible < *max) {
+                max_batch_size = Some(possible.clone());
+            }
+
+            lines.push((
+                possible,
+                FeasibilityLine {
+                    inventory_id: ingredient.inventory_id,
+                    ingredient_name: ingredient.ingredient_name,
+                    unit: ingredient.unit,
+                    required,
+                    available,
+                    shortfall,
+                    limiting: false,
+                },
+            ));
+        }
+
+        let lines: Vec<FeasibilityLine> = lines
+            .into_iter()
+            .map(|(possible, mut line)| {
+                line.limiting = max_batch_size.as_ref() == Some(&possible);
+                line
+            })
+            .collect();
+
+        Ok(RecipeFeasibility {
+            recipe_template_id: recipe_id,
+            template_name: recipe.template_name,
+            can_make: lines.iter().all(|line| line.shortfall == zero),
+            batch_size,
+            unit: recipe.default_unit,
+            max_batch_size,
+            lines,
+        })
+    }
+
+    /// Get a recipe template's aging/maturation stages in order
+    async fn recipe_stages(
+        &self,
+        ctx: &Context<'_>,
+        recipe_template_id: uuid::Uuid,
+    ) -> Result<Vec<RecipeStage>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let stages = sqlx::query_as!(
+            RecipeStage,
+            r#"
+            SELECT id, recipe_template_id, position, name, expected_days, notes, created_at
+            FROM recipe_stages
+            WHERE recipe_template_id = $1
+            ORDER BY position
+            "#,
+            recipe_template_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(stages)
+    }
+
+    /// Get batch move tasks, optionally filtered by status and due date
+    async fn batch_move_tasks(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        due_before: Option<DateTime<Utc>>,
+        batch_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<BatchMoveTask>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let tasks = sqlx::query_as!(
+            BatchMoveTask,
+            r#"
+            SELECT
+                id, batch_id, rule_id, from_location, to_location,
+                due_date, status, completed_at, notes, created_at
+            FROM batch_move_tasks
+            WHERE ($1::varchar IS NULL OR status = $1)
+                AND ($2::timestamptz IS NULL OR due_date <= $2)
+                AND ($3::uuid IS NULL OR batch_id = $3)
+            ORDER BY due_date
+            "#,
+            status,
+            due_before,
+            batch_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    /// Action links, newest first, optionally for one batch. Used, cancelled
+    /// and expired links are left out unless `includeInactive` is set.
+    #[graphql(guard = "RequireRole(auth::PRODUCTION)")]
+    async fn action_links(
+        &self,
+        ctx: &Context<'_>,
+        batch_id: Option<uuid::Uuid>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<ActionLink>> {
+        // Links are credentials, which live outside the reporting role's grants
+        let pool = ctx.data::<PgPool>()?;
+
+        let links = sqlx::query_as!(
+            ActionLink,
+            r#"
+            SELECT
+                id, action, batch_id, move_task_id, description, user_id, created_by,
+                expires_at, used_at, revoked_at, created_at
+            FROM action_links
+            WHERE ($1::uuid IS NULL OR batch_id = $1)
+                AND ($2 OR (used_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()))
+            ORDER BY created_at DESC
+            "#,
+            batch_id,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(links)
+    }
+
+    /// Get a recipe template's recurring chores
+    async fn recipe_tasks(
+        &self,
+        ctx: &Context<'_>,
+        recipe_template_id: uuid::Uuid,
+    ) -> Result<Vec<RecipeTaskTemplate>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let tasks = sqlx::query_as!(
+            RecipeTaskTemplate,
+            r#"
+            SELECT
+                id, recipe_template_id, task_type, description, start_day,
+                repeat_every_days, repeat_until_day, created_at, updated_at
+            FROM recipe_task_templates
+            WHERE recipe_template_id = $1
+            ORDER BY start_day, task_type
+            "#,
+            recipe_template_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    /// Get batch chores, optionally filtered by status and batch
+    async fn batch_tasks(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        batch_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<BatchTask>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let tasks = sqlx::query_as!(
+            BatchTask,
+            r#"
+            SELECT
+                t.id, t.batch_id, b.batch_number, t.template_id, t.task_type, t.description,
+                t.due_date, t.status, t.completed_at, t.completed_by, t.notes, t.created_at
+            FROM batch_tasks t
+            JOIN production_batches b ON b.id = t.batch_id
+            WHERE ($1::varchar IS NULL OR t.status = $1)
+                AND ($2::uuid IS NULL OR t.batch_id = $2)
+            ORDER BY t.due_date, b.batch_number
+            "#,
+            status,
+            batch_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
+    /// Ingredients consumed in production between two dates (inclusive), with
+    /// quantity and cost per ingredient, split by product and/or month
+    async fn ingredient_consumption(
+        &self,
+        ctx: &Context<'_>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        group_by: Option<Vec<String>>,
+    ) -> Result<IngredientConsumption> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if end_date < start_date {
+            return Err("End date cannot be before start date".into());
+        }
+
+        let mut group_by = group_by.unwrap_or_default();
+        if let Some(unknown) = group_by
+            .iter()
+            .find(|group| !["product", "month"].contains(&group.as_str()))
+        {
+            return Err(
+                format!("Unknown grouping '{}'; expected product or month", unknown).into(),
+            );
+        }
+        group_by.sort();
+        group_by.dedup();
+        let by_product = group_by.iter().any(|group| group == "product");
+        let by_month = group_by.iter().any(|group| group == "month");
+
+        let start = start_date.and_time(NaiveTime::MIN).and_utc();
+        let end = end_date.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1);
+
+        // Every ingredient draw is logged as 'production_use' against its batch
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                i.id AS ingredient_id, i.name AS ingredient_name, i.unit,
+                CASE WHEN $3 THEN p.id END AS product_id,
+                CASE WHEN $3 THEN p.name END AS product_name,
+                CASE WHEN $4 THEN date_trunc('month', l.created_at AT TIME ZONE 'UTC')::date END AS month,
+                -SUM(l.quantity) AS "quantity!",
+                COALESCE(-SUM(l.value), 0) AS "cost!",
+                COUNT(DISTINCT l.batch_number) AS "batch_count!",
+                COUNT(*) FILTER (WHERE l.unit_cost IS NULL) AS "uncosted_entries!"
+            FROM inventory_logs l
+            JOIN inventory i ON i.id = l.inventory_id
+            LEFT JOIN production_batches b ON b.batch_number = l.batch_number
+            LEFT JOIN inventory p ON p.id = b.product_inventory_id
+            WHERE l.movement_type = 'production_use'
+                AND l.created_at >= $1 AND l.created_at < $2
+            GROUP BY 1, 2, 3, 4, 5, 6
+            ORDER BY 6 NULLS FIRST, 2, 5 NULLS FIRST
+            "#,
+            start,
+            end,
+            by_product,
+            by_month
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let lines: Vec<IngredientConsumptionLine> = rows
+            .into_iter()
+            .map(|row| IngredientConsumptionLine {
+                ingredient_id: row.ingredient_id,
+                ingredient_name: row.ingredient_name,
+                unit: row.unit,
+                product_id: row.product_id,
+                product_name: row.product_name,
+                month: row.month,
+                quantity: row.quantity,
+                cost: row.cost,
+                batch_count: row.batch_count,
+                uncosted_entries: row.uncosted_entries,
+            })
+            .collect();
+
+        Ok(IngredientConsumption {
+            start_date,
+            end_date,
+            group_by,
+            total_cost: lines.iter().map(|line| &line.cost).sum(),
+            lines,
+        })
+    }
+
+    /// Get the day-by-day production schedule between two dates (inclusive):
+    /// batch chores, storage moves, and business calendar entries
+    async fn production_calendar(
+        &self,
+        ctx: &Context<'_>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<ProductionCalendarDay>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if to < from {
+            return Err("'to' cannot be before 'from'".into());
+        }
+
+        if (to - from).num_days() >= 366 {
+            return Err("Production calendar is limited to one year at a time".into());
+        }
+
+        let start = from.and_time(NaiveTime::MIN).and_utc();
+        let end = to.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1);
+
+        let tasks = sqlx::query_as!(
+            BatchTask,
+            r#"
+            SELECT
+                t.id, t.batch_id, b.batch_number, t.template_id, t.task_type, t.description,
+                t.due_date, t.status, t.completed_at, t.completed_by, t.notes, t.created_at
+            FROM batch_tasks t
+            JOIN production_batches b ON b.id = t.batch_id
+            WHERE t.due_date >= $1 AND t.due_date < $2
+                AND t.status <> 'cancelled'
+            ORDER BY t.due_date, b.batch_number
+            "#,
+            start,
+            end
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let moves = sqlx::query_as!(
+            BatchMoveTask,
+            r#"
+            SELECT
+                id, batch_id, rule_id, from_location, to_location,
+                due_date, status, completed_at, notes, created_at
+            FROM batch_move_tasks
+            WHERE due_date >= $1 AND due_date < $2
+                AND status <> 'cancelled'
+            ORDER BY due_date
+            "#,
+            start,
+            end
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let events = sqlx::query_as!(
+            CalendarEvent,
+            r#"
+            SELECT
+                id, kind, name, start_date, end_date, blocks_production,
+                blocks_deliveries, notes, created_at, updated_at
+            FROM business_calendar
+            WHERE end_date >= $1 AND start_date <= $2
+            ORDER BY start_date, name
+            "#,
+            from,
+            to
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let days = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| ProductionCalendarDay {
+                date,
+                tasks: tasks
+                    .iter()
+                    .filter(|t| t.due_date.date_naive() == date)
+                    .cloned()
+                    .collect(),
+                moves: moves
+                    .iter()
+                    .filter(|m| m.due_date.date_naive() == date)
+                    .cloned()
+                    .collect(),
+                events: events
+                    .iter()
+                    .filter(|e| e.start_date <= date && date <= e.end_date)
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+
+        Ok(days)
+    }
+
+    /// Get the product development pipeline, soonest target launch first
+    /// (launched and shelved products only when include_closed is set)
+    async fn product_pipeline(
+        &self,
+        ctx: &Context<'_>,
+        include_closed: Option<bool>,
+    ) -> Result<Vec<ProductDevelopment>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(development::list(pool, include_closed.unwrap_or(false)).await?)
+    }
+
+    /// Get one product in development
+    async fn product_development(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<ProductDevelopment>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(development::find(pool, id).await?)
+    }
+
+    /// Get the production plan between two dates (inclusive), by target date
+    async fn planned_batches(
+        &self,
+        ctx: &Context<'_>,
+        from: NaiveDate,
+        to: NaiveDate,
+        status: Option<String>,
+    ) -> Result<Vec<PlannedBatch>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if to < from {
+            return Err("'to' cannot be before 'from'".into());
+        }
+
+        let planned = sqlx::query_as!(
+            PlannedBatch,
+            r#"
+            SELECT
+                p.id, p.recipe_template_id, r.template_name AS recipe_name,
+                r.product_inventory_id, p.batch_size, r.default_unit AS unit, p.target_date,
+                p.status, p.production_batch_id, p.notes, p.created_at, p.updated_at
+            FROM planned_batches p
+            JOIN recipe_templates r ON r.id = p.recipe_template_id
+            WHERE p.target_date BETWEEN $1 AND $2
+                AND ($3::varchar IS NULL OR p.status = $3)
+            ORDER BY p.target_date, r.template_name
+            "#,
+            from,
+            to,
+            status
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(planned)
+    }
+
+    /// Ingredient demand of the batches planned between two dates (inclusive),
+    /// netted against available stock and open purchase orders, shortfalls first
+    async fn material_requirements(
+        &self,
+        ctx: &Context<'_>,
+        from: NaiveDate,
+        to: NaiveDate,
+        shortfalls_only: Option<bool>,
+    ) -> Result<Vec<MaterialRequirement>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if to < from {
+            return Err("'to' cannot be before 'from'".into());
+        }
+
+        if (to - from).num_days() >= 366 {
+            return Err("Material requirements are limited to one year at a time".into());
+        }
+
+        let mut requirements = planning::requirements(pool, from, to).await?;
+        if shortfalls_only.unwrap_or(false) {
+            requirements.retain(|r| r.shortfall > BigDecimal::from(0));
+        }
+
+        Ok(requirements)
+    }
+
+    /// Expected output of the planned batches still open to allocation
+    /// (optionally by target date), with how much is promised to
+    /// made-to-order customers and how much is left
+    async fn plan_capacity(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<PlanCapacity>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let mut conn = pool.acquire().await?;
+
+        Ok(planning::capacity(&mut conn, None, from, to).await?)
+    }
+
+    /// Allocations of planned output, soonest due first (optionally by status
+    /// or customer)
+    async fn plan_allocations(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        customer_id: Option<uuid::Uuid>,
+    ) -> Result<Vec<PlanAllocation>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let allocations = sqlx::query_as!(
+            PlanAllocation,
+            r#"
+            SELECT
+                a.id, a.planned_batch_id, a.inventory_id, a.customer_id,
+                c.name AS "customer_name?", a.quantity, a.due_date, a.status,
+                a.reserved_quantity, a.reserved_at, a.notes, a.created_at, a.updated_at
+            FROM plan_allocations a
+            LEFT JOIN customers c ON c.id = a.customer_id
+            WHERE ($1::varchar IS NULL OR a.status = $1)
+                AND ($2::uuid IS NULL OR a.customer_id = $2)
+            ORDER BY a.due_date NULLS LAST, a.created_at
+            "#,
+            status,
+            customer_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(allocations)
+    }
+
+    /// Get all active customers
+    async fn customers(&self, ctx: &Context<'_>) -> Result<Vec<Customer>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let customers = sqlx::query_as!(
+            Customer,
+            r#"
+            SELECT
+                id, name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type,
+                tax_exempt as "tax_exempt!",
+                sms_opt_in,
+                payment_terms_days,
+                notes,
+                is_active as "is_active!",
+                created_at, updated_at
+            FROM customers
+            WHERE is_active = true
+            ORDER BY name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(customers)
+    }
+
+    /// Get a specific customer by ID
+    async fn customer(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<Customer>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let customer = sqlx::query_as!(
+            Customer,
+            r#"
+            SELECT
+                id, name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type,
+                tax_exempt as "tax_exempt!",
+                sms_opt_in,
+                payment_terms_days,
+                notes,
+                is_active as "is_active!",
+                created_at, updated_at
+            FROM customers
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(customer)
+    }
+
+    /// Stock check policy of every channel that has one (others are strict)
+    async fn sale_channel_policies(&self, ctx: &Context<'_>) -> Result<Vec<SaleChannelPolicy>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let policies = sqlx::query_as!(
+            SaleChannelPolicy,
+            r#"
+            SELECT id, channel, stock_check, created_at, updated_at
+            FROM sale_channel_policies
+            ORDER BY channel
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(policies)
+    }
+
+    /// Price list prices, for one customer type ('retail', 'wholesale', ...)
+    /// or all of them
+    async fn price_list(
+        &self,
+        ctx: &Context<'_>,
+        customer_type: Option<String>,
+    ) -> Result<Vec<PriceListItem>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let customer_type = customer_type
+            .map(|customer_type| pricing::customer_type(&customer_type))
+            .transpose()?;
+        Ok(pricing::price_list(pool, customer_type.as_deref()).await?)
+    }
+
+    /// Prices agreed with a customer, ahead of their type's price list
+    async fn customer_prices(
+        &self,
+        ctx: &Context<'_>,
+        customer_id: uuid::Uuid,
+    ) -> Result<Vec<CustomerPrice>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(pricing::customer_prices(pool, customer_id).await?)
+    }
+
+    /// What an item sells for to a customer (leave out `customerId` for an
+    /// anonymous sale): their own price, else their type's price list, else
+    /// the retail list. This is the price `createSale` uses when an item is
+    /// given without one.
+    async fn price_for(
+        &self,
+        ctx: &Context<'_>,
+        customer_id: Option<uuid::Uuid>,
+        inventory_id: uuid::Uuid,
+    ) -> Result<ResolvedPrice> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(pricing::price_for(pool, customer_id, inventory_id).await?)
+    }
+
+    /// Square item variations and the inventory items they sell
+    async fn square_item_mappings(&self, ctx: &Context<'_>) -> Result<Vec<SquareItemMapping>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(square::mappings(pool).await?)
+    }
+
+    /// Square orders pulled by the import, newest first. Status is 'pending',
+    /// 'imported', 'unmapped' or 'skipped'.
+    async fn square_orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<SquareOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(square::orders(pool, status.as_deref(), limit.unwrap_or(50).clamp(1, 500)).await?)
+    }
+
+    /// Square variations with orders waiting on a mapping; map them with
+    /// `mapSquareItem`
+    async fn unmapped_square_items(&self, ctx: &Context<'_>) -> Result<Vec<UnmappedSquareItem>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(square::unmapped_items(pool).await?)
+    }
+
+    /// Shopify variants and the inventory items they sell, with their last
+    /// stock push
+    async fn shopify_products(&self, ctx: &Context<'_>) -> Result<Vec<ShopifyProduct>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(shopify::products(pool).await?)
+    }
+
+    /// Shopify orders pulled by the sync, newest first. Status is 'pending',
+    /// 'imported', 'unmapped' or 'skipped'.
+    async fn shopify_orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ShopifyOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(shopify::orders(pool, status.as_deref(), limit.unwrap_or(50).clamp(1, 500)).await?)
+    }
+
+    /// Shopify variants with orders waiting on a mapping; map them with
+    /// `mapShopifyProduct`
+    async fn unmapped_shopify_variants(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<UnmappedShopifyVariant>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(shopify::unmapped_variants(pool).await?)
+    }
+
+    /// Times Shopify's stock disagreed with the stock on record, newest
+    /// first; open ones only unless `includeResolved`
+    async fn shopify_sync_conflicts(
+        &self,
+        ctx: &Context<'_>,
+        include_resolved: Option<bool>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ShopifySyncConflict>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(shopify::conflicts(
+            pool,
+            include_resolved.unwrap_or(false),
+            limit.unwrap_or(50).clamp(1, 500),
+        )
+        .await?)
+    }
+
+    /// Background jobs the scheduler runs, with their schedules and how
+    /// their last run went
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn scheduled_jobs(&self, ctx: &Context<'_>) -> Result<Vec<ScheduledJob>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(scheduler::jobs(pool).await?)
+    }
+
+    /// Stripe payment links, newest first, optionally for one sale or with one
+    /// status ('active', 'paid', 'failed' or 'deactivated')
+    async fn payment_links(
+        &self,
+        ctx: &Context<'_>,
+        sale_id: Option<uuid::Uuid>,
+        status: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<PaymentLink>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(stripe::payment_links(
+            pool,
+            sale_id,
+            status.as_deref(),
+            limit.unwrap_or(50).clamp(1, 500),
+        )
+        .await?)
+    }
+
+    /// Invoices by due date, optionally for one customer, with one status
+    /// ('open', 'partially_paid', 'paid' or 'void'), or only those overdue
+    async fn invoices(
+        &self,
+        ctx: &Context<'_>,
+        customer_id: Option<uuid::Uuid>,
+        status: Option<String>,
+        overdue_only: Option<bool>,
+    ) -> Result<Vec<Invoice>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(invoices::invoices(
+            pool,
+            customer_id,
+            status.as_deref(),
+            overdue_only.unwrap_or(false),
+        )
+        .await?)
+    }
+
+    /// Payments recorded against an invoice, oldest first
+    async fn invoice_payments(
+        &self,
+        ctx: &Context<'_>,
+        invoice_id: uuid::Uuid,
+    ) -> Result<Vec<InvoicePayment>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(invoices::payments(pool, invoice_id).await?)
+    }
+
+    /// Accounts-receivable aging: what customers owed at the end of `asOf`
+    /// (default today), bucketed by days since each invoice was issued
+    /// (0-30, 31-60, 61-90, over 90), with how much of it is past due
+    async fn ar_aging(&self, ctx: &Context<'_>, as_of: Option<NaiveDate>) -> Result<ArAging> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let as_of = as_of.unwrap_or_else(|| Utc::now().date_naive());
+        Ok(invoices::aging(pool, as_of).await?)
+    }
+
+    /// A customer order by ID
+    async fn order(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<CustomerOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(orders::by_id(pool, id).await?)
+    }
+
+    /// Customer orders by delivery date, optionally with one status ('draft',
+    /// 'confirmed', 'fulfilled', 'invoiced' or 'cancelled'), for one customer,
+    /// or delivering between two dates (inclusive)
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        customer_id: Option<uuid::Uuid>,
+        delivery_from: Option<NaiveDate>,
+        delivery_to: Option<NaiveDate>,
+    ) -> Result<Vec<CustomerOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let statuses = status.map(|status| vec![status]);
+        Ok(orders::orders(
+            pool,
+            statuses.as_deref(),
+            customer_id,
+            delivery_from,
+            delivery_to,
+        )
+        .await?)
+    }
+
+    /// Orders still to deliver (drafts and confirmed), by delivery date,
+    /// optionally between two dates (inclusive)
+    async fn open_orders(
+        &self,
+        ctx: &Context<'_>,
+        delivery_from: Option<NaiveDate>,
+        delivery_to: Option<NaiveDate>,
+    ) -> Result<Vec<CustomerOrder>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let statuses = orders::OPEN.map(str::to_string);
+        Ok(orders::orders(pool, Some(&statuses), None, delivery_from, delivery_to).await?)
+    }
+
+    /// Oversold sales queued for stock review, oldest first
+    async fn sales_awaiting_stock_review(&self, ctx: &Context<'_>) -> Result<Vec<Sale>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let sales = sqlx::query_as!(
+            Sale,
+            r#"
+            SELECT
+                id, sale_number, customer_id, sale_date,
+                subtotal, tax_amount, discount_amount, total_amount,
+                payment_method, payment_status, notes, channel, stock_check, oversold,
+                stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+            FROM sales
+            WHERE stock_review_status = 'pending'
+            ORDER BY sale_date
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sales)
+    }
+
+    /// Get sales with optional filters
+    async fn sales(
+        &self,
+        ctx: &Context<'_>,
+        customer_id: Option<uuid::Uuid>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+        limit: Option<i32>,
+    ) -> Result<Vec<Sale>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
+
+        let sales = if let Some(customer_id) = customer_id {
+            sqlx::query_as!(
+                Sale,
+                r#"
+                SELECT
+                    id, sale_number, customer_id, sale_date,
+                    subtotal, tax_amount, discount_amount, total_amount,
+                    payment_method, payment_status, notes, channel, stock_check, oversold,
+                    stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+                FROM sales
+                WHERE customer_id = $1
+                    AND ($2::timestamptz IS NULL OR sale_date >= $2)
+                    AND ($3::timestamptz IS NULL OR sale_date <= $3)
+                ORDER BY sale_date DESC
+                LIMIT $4
+                "#,
+                customer_id,
+                start_date,
+                end_date,
+                limit as i64
+            )
+            .fetch_all(pool)
+            .await?
+        } else {
+            sqlx::query_as!(
+                Sale,
+                r#"
+                SELECT
+                    id, sale_number, customer_id, sale_date,
+                    subtotal, tax_amount, discount_amount, total_amount,
+                    payment_method, payment_status, notes, channel, stock_check, oversold,
+                    stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+                FROM sales
+                WHERE ($1::timestamptz IS NULL OR sale_date >= $1)
+                    AND ($2::timestamptz IS NULL OR sale_date <= $2)
+                ORDER BY sale_date DESC
+                LIMIT $3
+                "#,
+                start_date,
+                end_date,
+                limit as i64
+            )
+            .fetch_all(pool)
+            .await?
+        };
+
+        Ok(sales)
+    }
+
+    /// Get full sale details including items and customer
+    async fn sale_details(
+        &self,
+        ctx: &Context<'_>,
+        sale_id: uuid::Uuid,
+    ) -> Result<Option<SaleWithItems>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(sales::with_items(pool, sale_id).await?)
+    }
+
+    /// A sale with its items (and their names) and its customer, for
+    /// reprinting a receipt
+    async fn sale(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<SaleWithItems>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(sales::with_items(pool, id).await?)
+    }
+
+    /// A sale by its number as printed on the receipt (e.g.
+    /// `SALE-20250601-004`), ignoring case and surrounding spaces
+    async fn sale_by_number(
+        &self,
+        ctx: &Context<'_>,
+        sale_number: String,
+    ) -> Result<Option<SaleWithItems>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let sale_id = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM sales
+            WHERE sale_number = $1 OR UPPER(sale_number) = UPPER($1)
+            ORDER BY sale_number = $1 DESC
+            LIMIT 1
+            "#,
+            sale_number.trim()
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match sale_id {
+            Some(sale_id) => Ok(sales::with_items(pool, sale_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Get revenue between two dates (inclusive), with product sales and
+    /// workshop tickets as separate lines
+    async fn revenue_summary(
+        &self,
+        ctx: &Context<'_>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<RevenueSummary> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if end_date < start_date {
+            return Err("End date cannot be before start date".into());
+        }
+
+        let start = start_date.and_time(NaiveTime::MIN).and_utc();
+        let end = end_date.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1);
+
+        let sales = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(SUM(total_amount), 0) AS "total!", COUNT(*) AS "count!",
+                COALESCE(SUM(discount_amount), 0) AS "discounts!"
+            FROM sales
+            WHERE sale_date >= $1 AND sale_date < $2
+                AND payment_status <> 'refunded'
+            "#,
+            start,
+            end
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let tickets = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(price_paid), 0) AS "total!", COUNT(*) AS "count!"
+            FROM workshop_attendees
+            WHERE purchased_at >= $1 AND purchased_at < $2
+                AND status <> 'cancelled'
+            "#,
+            start,
+            end
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(RevenueSummary {
+            start_date,
+            end_date,
+            total: &sales.total + &tickets.total,
+            product_sales: sales.total,
+            sales_count: sales.count,
+            discounts: sales.discounts,
+            ticket_sales: tickets.total,
+            tickets_sold: tickets.count,
+        })
+    }
+
+    /// Discounts given on sales between two dates (inclusive), by promo code,
+    /// type and whether they were on lines or whole sales, biggest first
+    async fn discount_summary(
+        &self,
+        ctx: &Context<'_>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<DiscountSummaryLine>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if end_date < start_date {
+            return Err("End date cannot be before start date".into());
+        }
+
+        Ok(discounts::summary(pool, start_date, end_date).await?)
+    }
+
+    /// Promo codes by code; inactive ones only when asked
+    async fn promo_codes(
+        &self,
+        ctx: &Context<'_>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<PromoCode>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        Ok(discounts::promo_codes(pool, include_inactive.unwrap_or(false)).await?)
+    }
+
+    /// Farmers markets we sell at, by name
+    async fn markets(
+        &self,
+        ctx: &Context<'_>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<Market>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let markets = sqlx::query_as!(
+            Market,
+            r#"
+            SELECT
+                id, name, location, default_booth_fee, default_travel_cost, is_active, notes,
+                created_at, updated_at
+            FROM markets
+            WHERE is_active OR $1
+            ORDER BY name
+            "#,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(markets)
+    }
+
+    /// Market sessions, optionally for one market and/or between two dates
+    /// (inclusive), in date order
+    async fn market_sessions(
+        &self,
+        ctx: &Context<'_>,
+        market_id: Option<uuid::Uuid>,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> Result<Vec<MarketSession>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let sessions = sqlx::query_as!(
+            MarketSession,
+            r#"
+            SELECT
+                s.id, s.market_id, m.name AS market_name, s.calendar_event_id, s.session_date,
+                s.booth, s.booth_fee, s.travel_cost, s.notes, s.created_at, s.updated_at
+            FROM market_sessions s
+            JOIN markets m ON m.id = s.market_id
+            WHERE ($1::uuid IS NULL OR s.market_id = $1)
+                AND ($2::date IS NULL OR s.session_date >= $2)
+                AND ($3::date IS NULL OR s.session_date <= $3)
+            ORDER BY s.session_date, m.name
+            "#,
+            market_id,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Revenue, cost of goods, booth fees and travel per market for sessions
+    /// between two dates (inclusive), most profitable first
+    async fn market_profitability(
+        &self,
+        ctx: &Context<'_>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<MarketProfitability>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        if end_date < start_date {
+            return Err("End date cannot be before start date".into());
+        }
+
+        Ok(markets::profitability(pool, start_date, end_date).await?)
+    }
+
+    /// Get workshops, optionally only upcoming ones or those with a given status
+    async fn workshops(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        upcoming_only: Option<bool>,
+    ) -> Result<Vec<Workshop>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let workshops = sqlx::query_as!(
+            Workshop,
+            r#"
+            SELECT
+                id, title, description, starts_at, duration_minutes, location, capacity,
+                ticket_price, status, notes, created_at, updated_at
+            FROM workshops
+            WHERE ($1::varchar IS NULL OR status = $1)
+                AND (NOT $2 OR starts_at >= NOW())
+            ORDER BY starts_at
+            "#,
+            status,
+            upcoming_only.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(workshops)
+    }
+
+    /// Get a workshop by ID, with its kit and attendee list
+    async fn workshop(&self, ctx: &Context<'_>, id: uuid::Uuid) -> Result<Option<Workshop>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let workshop = sqlx::query_as!(
+            Workshop,
+            r#"
+            SELECT
+                id, title, description, starts_at, duration_minutes, location, capacity,
+                ticket_price, status, notes, created_at, updated_at
+            FROM workshops
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(workshop)
+    }
+
+    /// Get pickup windows between two dates (default: the next two weeks),
+    /// with how many orders each has booked
+    async fn pickup_windows(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        include_inactive: Option<bool>,
+    ) -> Result<Vec<PickupWindow>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let from = from.unwrap_or_else(|| Utc::now().date_naive());
+        let to = to.unwrap_or(from + chrono::Duration::days(13));
+
+        let windows = sqlx::query_as!(
+            PickupWindow,
+            r#"
+            SELECT
+                id, pickup_date, start_time, end_time, capacity, notes, is_active,
+                created_at, updated_at
+            FROM pickup_windows
+            WHERE pickup_date BETWEEN $1 AND $2
+                AND ($3 OR is_active = true)
+            ORDER BY pickup_date, start_time
+            "#,
+            from,
+            to,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(windows)
+    }
+
+    /// Get the pickup manifest for a day (default: today): every booked order
+    /// by window, with contact details, what to hand over, and past no-shows
+    async fn pickup_manifest(
+        &self,
+        ctx: &Context<'_>,
+        date: Option<NaiveDate>,
+    ) -> Result<Vec<PickupManifestEntry>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let date = date.unwrap_or_else(|| Utc::now().date_naive());
+
+        Ok(pickups::manifest(pool, date).await?)
+    }
+
+    /// Get customers who have missed pickups (optionally only since a date),
+    /// most no-shows first
+    async fn pickup_no_shows(
+        &self,
+        ctx: &Context<'_>,
+        since: Option<NaiveDate>,
+    ) -> Result<Vec<PickupNoShow>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let no_shows = sqlx::query_as!(
+            PickupNoShow,
+            r#"
+            SELECT
+                c.id as customer_id, c.name as customer_name, c.phone, c.email,
+                COUNT(*) as "no_shows!",
+                MAX(w.pickup_date) as "last_missed!"
+            FROM pickup_bookings b
+            JOIN pickup_windows w ON w.id = b.pickup_window_id
+            JOIN customers c ON c.id = b.customer_id
+            WHERE b.status = 'no_show'
+                AND ($1::date IS NULL OR w.pickup_date >= $1)
+            GROUP BY c.id
+            ORDER BY COUNT(*) DESC, MAX(w.pickup_date) DESC
+            "#,
+            since
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(no_shows)
+    }
+
+    /// Get complaints, optionally filtered by status
+    async fn complaints(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        limit: Option<i32>,
+    ) -> Result<Vec<Complaint>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let limit = limit.unwrap_or(50).min(500); // Default 50, max 500
+
+        let complaints = sqlx::query_as!(
+            Complaint,
+            r#"
+            SELECT
+                id, customer_id, sale_id, inventory_id, lot_id, production_batch_id,
+                description, severity, status, disposition, refund_amount,
+                replacement_quantity, resolution_notes, reported_at, resolved_at,
+                created_at, updated_at
+            FROM complaints
+            WHERE ($1::varchar IS NULL OR status = $1)
+            ORDER BY reported_at DESC
+            LIMIT $2
+            "#,
+            status,
+            limit as i64
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(complaints)
+    }
+
+    /// Get a complaint with its sibling lot reviews
+    async fn complaint(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<ComplaintDetails>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let complaint = sqlx::query_as!(
+            Complaint,
+            r#"
+            SELECT
+                id, customer_id, sale_id, inventory_id, lot_id, production_batch_id,
+                description, severity, status, disposition, refund_amount,
+                replacement_quantity, resolution_notes, reported_at, resolved_at,
+                created_at, updated_at
+            FROM complaints
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(complaint) = complaint else {
+            return Ok(None);
+        };
+
+        let lot_reviews = sqlx::query_as!(
+            ComplaintLotReview,
+            r#"
+            SELECT id, complaint_id, lot_id, status, notes, reviewed_at, created_at
+            FROM complaint_lot_reviews
+            WHERE complaint_id = $1
+            ORDER BY created_at
+            "#,
+            id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Some(ComplaintDetails {
+            complaint,
+            lot_reviews,
+        }))
+    }
+
+    /// Get complaint and batch failure statistics by supplier and recipe
+    async fn quality_analytics(
+        &self,
+        ctx: &Context<'_>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+    ) -> Result<QualityAnalytics> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        // Suppliers are linked to batches through the ingredient lots they supplied
+        let suppliers = sqlx::query_as!(
+            QualityStat,
+            r#"
+            WITH batch_suppliers AS (
+                SELECT DISTINCT c.production_batch_id AS batch_id, l.supplier_id
+                FROM lot_consumptions c
+                JOIN inventory_lots l ON l.id = c.lot_id
+                WHERE c.production_batch_id IS NOT NULL AND l.supplier_id IS NOT NULL
+            )
+            SELECT
+                s.id,
+                s.name,
+                COUNT(DISTINCT pb.id) as "batch_count!",
+                COUNT(DISTINCT pb.id) FILTER (WHERE pb.status = 'failed') as "failed_batch_count!",
+                COUNT(DISTINCT co.id) as "complaint_count!",
+                COALESCE(SUM(co.refund_amount), 0) as "refund_total!"
+            FROM batch_suppliers bs
+            JOIN suppliers s ON s.id = bs.supplier_id
+            JOIN production_batches pb ON pb.id = bs.batch_id
+            LEFT JOIN complaints co ON co.production_batch_id = pb.id
+            WHERE ($1::timestamptz IS NULL OR pb.start_date >= $1)
+                AND ($2::timestamptz IS NULL OR pb.start_date <= $2)
+            GROUP BY s.id, s.name
+            ORDER BY 5 DESC, s.name
+            "#,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let recipes = sqlx::query_as!(
+            QualityStat,
+            r#"
+            SELECT
+                rt.id,
+                rt.template_name as name,
+                COUNT(DISTINCT pb.id) as "batch_count!",
+                COUNT(DISTINCT pb.id) FILTER (WHERE pb.status = 'failed') as "failed_batch_count!",
+                COUNT(DISTINCT co.id) as "complaint_count!",
+                COALESCE(SUM(co.refund_amount), 0) as "refund_total!"
+            FROM production_batches pb
+            JOIN recipe_templates rt ON rt.id = pb.recipe_template_id
+            LEFT JOIN complaints co ON co.production_batch_id = pb.id
+            WHERE ($1::timestamptz IS NULL OR pb.start_date >= $1)
+                AND ($2::timestamptz IS NULL OR pb.start_date <= $2)
+            GROUP BY rt.id, rt.template_name
+            ORDER BY 5 DESC, rt.template_name
+            "#,
+            start_date,
+            end_date
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(QualityAnalytics { suppliers, recipes })
+    }
+
+    /// Get all integration secrets (values are masked)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn integration_secrets(&self, ctx: &Context<'_>) -> Result<Vec<IntegrationSecret>> {
+        let secrets = ctx.data::<SecretStore>()?;
+
+        Ok(secrets.list().await?)
+    }
+
+    /// Get the fields a CSV import reads and their default columns
+    async fn csv_import_fields(&self, entity: String) -> Result<Vec<CsvImportField>> {
+        let entity = csv_import::Entity::parse(&entity)
+            .ok_or("Entity must be one of: inventory, suppliers")?;
+
+        Ok(entity.describe())
+    }
+
+    /// Get the fields a legacy import reads and their default sheet columns
+    async fn legacy_import_mapping(&self, record_type: String) -> Result<Vec<LegacyImportField>> {
+        let record_type = legacy_import::RecordType::parse(&record_type)
+            .ok_or("Record type must be one of: batches, sales, purchases")?;
+
+        Ok(record_type.describe())
+    }
+
+    /// Get rows from legacy imports (optionally only flagged ones)
+    async fn legacy_import_rows(
+        &self,
+        ctx: &Context<'_>,
+        record_type: Option<String>,
+        status: Option<String>,
+        flagged_only: Option<bool>,
+    ) -> Result<Vec<LegacyImportRow>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let rows = sqlx::query_as!(
+            LegacyImportRow,
+            r#"
+            SELECT
+                id, record_type, source_key, status, entity_id, issues, raw,
+                imported_at, created_at, updated_at
+            FROM legacy_import_rows
+            WHERE ($1::text IS NULL OR record_type = $1)
+                AND ($2::text IS NULL OR status = $2)
+                AND (NOT $3 OR jsonb_array_length(issues) > 0)
+            ORDER BY record_type, source_key
+            "#,
+            record_type,
+            status,
+            flagged_only.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Get inserts, updates, and deletes after a cursor, for incremental
+    /// warehouse sync (start with no cursor, then pass back `nextCursor`)
+    async fn changes(
+        &self,
+        ctx: &Context<'_>,
+        since_cursor: Option<String>,
+        entities: Option<Vec<String>>,
+        limit: Option<i32>,
+    ) -> Result<ChangeSet> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let since = changes::Cursor::parse(since_cursor.as_deref())?;
+
+        if let Some(ref entities) = entities {
+            changes::validate_entities(entities)?;
+        }
+
+        let change_set = changes::fetch(
+            pool,
+            since,
+            entities.as_deref(),
+            limit.map_or(changes::DEFAULT_LIMIT, i64::from),
+        )
+        .await?;
+
+        Ok(change_set)
+    }
+
+    /// Get domain events after a cursor, oldest first, for integrations to
+    /// catch up on events they missed. Start with no cursor and pass back
+    /// `nextCursor` each time.
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        after_cursor: Option<String>,
+        event_types: Option<Vec<String>>,
+        limit: Option<i32>,
+    ) -> Result<EventSet> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let after = changes::Cursor::parse(after_cursor.as_deref())?;
+
+        if let Some(ref event_types) = event_types {
+            events::validate_event_types(event_types)?;
+        }
+
+        let event_set = events::fetch(
+            pool,
+            after,
+            event_types.as_deref(),
+            limit.map_or(events::DEFAULT_LIMIT, i64::from),
+        )
+        .await?;
+
+        Ok(event_set)
+    }
+
+    /// Get archived inbound webhook deliveries, newest first (for debugging integrations)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn webhook_events(
+        &self,
+        ctx: &Context<'_>,
+        provider: Option<String>,
+        status: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<WebhookEvent>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let events = sqlx::query_as!(
+            WebhookEvent,
+            r#"
+            SELECT
+                id, provider, event_id, event_type, status, error,
+                headers, payload, received_at
+            FROM webhook_events
+            WHERE ($1::text IS NULL OR provider = $1)
+                AND ($2::text IS NULL OR status = $2)
+            ORDER BY received_at DESC
+            LIMIT $3
+            "#,
+            provider,
+            status,
+            limit.unwrap_or(50).clamp(1, 500)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Get outbound webhook subscriptions, oldest first
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn webhook_subscriptions(&self, ctx: &Context<'_>) -> Result<Vec<WebhookSubscription>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let subscriptions = sqlx::query_as!(
+            WebhookSubscription,
+            r#"
+            SELECT id, url, event_types, description, is_active, created_at, updated_at
+            FROM webhook_subscriptions
+            ORDER BY created_at
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    /// Get outbound webhook deliveries, newest first, optionally for one
+    /// subscription or with one status ('pending', 'delivered', 'failed')
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn webhook_deliveries(
+        &self,
+        ctx: &Context<'_>,
+        subscription_id: Option<uuid::Uuid>,
+        status: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                d.id, d.subscription_id, e.event_type, e.payload, d.status, d.attempts,
+                d.next_attempt_at, d.last_status_code, d.last_error, d.delivered_at, d.created_at
+            FROM webhook_deliveries d
+            JOIN domain_events e ON e.id = d.event_id
+            WHERE ($1::uuid IS NULL OR d.subscription_id = $1)
+                AND ($2::text IS NULL OR d.status = $2)
+            ORDER BY d.created_at DESC
+            LIMIT $3
+            "#,
+            subscription_id,
+            status,
+            limit.unwrap_or(50).clamp(1, 500)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Get announcements to show now, newest first (optionally of one kind).
+    /// Set include_scheduled to also see ones not yet published, for admins.
+    async fn announcements(
+        &self,
+        ctx: &Context<'_>,
+        kind: Option<String>,
+        include_scheduled: Option<bool>,
+    ) -> Result<Vec<Announcement>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let announcements = sqlx::query_as!(
+            Announcement,
+            r#"
+            SELECT id, title, body, kind, publish_at, expires_at, is_active, created_at, updated_at
+            FROM announcements
+            WHERE is_active = true
+                AND ($1 OR publish_at <= NOW())
+                AND (expires_at IS NULL OR expires_at > NOW())
+                AND ($2::varchar IS NULL OR kind = $2)
+            ORDER BY publish_at DESC
+            "#,
+            include_scheduled.unwrap_or(false),
+            kind
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(announcements)
+    }
+
+    /// Get recent API errors, newest first (optionally for one trace ID)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn recent_errors(
+        &self,
+        ctx: &Context<'_>,
+        trace_id: Option<uuid::Uuid>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ApiError>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let errors = sqlx::query_as!(
+            ApiError,
+            r#"
+            SELECT id, trace_id, operation_name, path, message, created_at
+            FROM api_errors
+            WHERE ($1::uuid IS NULL OR trace_id = $1)
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            trace_id,
+            limit.unwrap_or(50).clamp(1, 500)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(errors)
+    }
+
+    /// Calls per client, API version and operation since `since` (default the
+    /// last 24 hours), busiest first: how often, how slow, and how often they
+    /// fail.
+    /// Counted by the hour and written every minute, so the latest calls show
+    /// up after a short delay.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn api_usage(
+        &self,
+        ctx: &Context<'_>,
+        since: Option<DateTime<Utc>>,
+        client_id: Option<String>,
+        operation: Option<String>,
+        #[graphql(desc = "'v1' or 'v2'")] api_version: Option<String>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ApiUsageStat>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let since = since.unwrap_or_else(|| Utc::now() - chrono::Duration::hours(24));
+
+        let usage = sqlx::query_as!(
+            ApiUsageStat,
+            r#"
+            SELECT
+                client_id,
+                (array_agg(client ORDER BY last_called_at DESC))[1] AS "client!",
+                (array_agg(user_id ORDER BY last_called_at DESC))[1] AS user_id,
+                (array_agg(api_key_id ORDER BY last_called_at DESC))[1] AS api_key_id,
+                api_version,
+                operation_type,
+                operation,
+                SUM(calls)::bigint AS "calls!",
+                SUM(errors)::bigint AS "errors!",
+                SUM(deprecated_calls)::bigint AS "deprecated_calls!",
+                (100.0 * SUM(errors) / SUM(calls))::float8 AS "error_rate!",
+                (SUM(total_ms)::numeric / SUM(calls))::float8 AS "average_ms!",
+                MAX(max_ms) AS "max_ms!",
+                (SUM(calls) / GREATEST(EXTRACT(EPOCH FROM NOW() - $1) / 60, 1))::float8
+                    AS "calls_per_minute!",
+                MAX(last_called_at) AS "last_called_at!"
+            FROM api_usage
+            WHERE bucket >= date_trunc('hour', $1)
+                AND ($2::varchar IS NULL OR client_id = $2)
+                AND ($3::varchar IS NULL OR operation = $3)
+                AND ($4::varchar IS NULL OR api_version = $4)
+            GROUP BY client_id, api_version, operation_type, operation
+            ORDER BY SUM(calls) DESC
+            LIMIT $5
+            "#,
+            since,
+            client_id,
+            operation,
+            api_version,
+            limit.unwrap_or(50).clamp(1, 500)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Each API version's deprecation timeline and its calls since `since`
+    /// (default the last 30 days), for knowing when an old version can be
+    /// switched off. `apiUsage(apiVersion: "v1")` lists who still calls it.
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn api_versions(
+        &self,
+        ctx: &Context<'_>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<ApiVersionUsage>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        let since = since.unwrap_or_else(|| Utc::now() - chrono::Duration::days(30));
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                api_version,
+                SUM(calls)::bigint AS "calls!",
+                COUNT(DISTINCT client_id) AS "clients!",
+                SUM(deprecated_calls)::bigint AS "deprecated_calls!",
+                MAX(last_called_at) AS "last_called_at!"
+            FROM api_usage
+            WHERE bucket >= date_trunc('hour', $1::timestamptz)
+            GROUP BY api_version
+            "#,
+            since
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let versions = ApiVersion::ALL
+            .into_iter()
+            .map(|version| {
+                let row = rows.iter().find(|row| row.api_version == version.as_str());
+                let (deprecated_on, sunset_on) = version.timeline();
+
+                ApiVersionUsage {
+                    version: version.as_str().to_string(),
+                    path: version.path().to_string(),
+                    deprecated_on,
+                    sunset_on,
+                    calls: row.map_or(0, |row| row.calls),
+                    clients: row.map_or(0, |row| row.clients),
+                    deprecated_calls: row.map_or(0, |row| row.deprecated_calls),
+                    last_called_at: row.map(|row| row.last_called_at),
+                    removed_fields: versions::removed_in(version)
+                        .map(|deprecation| deprecation.field.to_string())
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Ok(versions)
+    }
+
+    /// Get problem reports from app users, newest first
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn user_feedback(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> Result<Vec<UserFeedback>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let feedback = sqlx::query_as!(
+            UserFeedback,
+            r#"
+            SELECT id, trace_id, message, page, reported_by, created_at
+            FROM user_feedback
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+            limit.unwrap_or(50).clamp(1, 500)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(feedback)
+    }
+
+    /// Everyone who can sign in (owner only)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn users(&self, ctx: &Context<'_>, include_inactive: Option<bool>) -> Result<Vec<User>> {
+        // Credentials live outside the reporting role's grants
+        let pool = ctx.data::<PgPool>()?;
+
+        let users = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id, username, display_name, role, is_active, last_login_at, created_at
+            FROM users
+            WHERE $1 OR is_active = true
+            ORDER BY username
+            "#,
+            include_inactive.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    /// How far a new deployment is through setup, for the onboarding wizard
+    async fn onboarding_status(&self, ctx: &Context<'_>) -> Result<OnboardingStatus> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(onboarding::status(pool).await?)
+    }
+
+    /// The built-in library of recipes and category and unit presets, for
+    /// `importFromLibrary`
+    async fn library(&self, ctx: &Context<'_>) -> Result<RecipeLibrary> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(library::catalog(pool).await?)
+    }
+
+    /// Units of measure to offer when adding items and recipes
+    async fn units(&self, ctx: &Context<'_>) -> Result<Vec<Unit>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let units = sqlx::query_as!(
+            Unit,
+            r#"
+            SELECT id, name, kind, is_active, created_at
+            FROM units
+            WHERE is_active = true
+            ORDER BY kind, name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(units)
+    }
+
+    /// Inventory categories to offer when adding items
+    async fn inventory_categories(&self, ctx: &Context<'_>) -> Result<Vec<InventoryCategory>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let categories = sqlx::query_as!(
+            InventoryCategory,
+            r#"
+            SELECT id, name, description, is_active, created_at
+            FROM inventory_categories
+            WHERE is_active = true
+            ORDER BY name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(categories)
+    }
+
+    /// Who changed what, newest first (owner only). Filter by the changed
+    /// row (e.g. an inventory item) and/or the user
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn audit_log(
+        &self,
+        ctx: &Context<'_>,
+        entity_id: Option<uuid::Uuid>,
+        user_id: Option<uuid::Uuid>,
+        limit: Option<i32>,
+    ) -> Result<Vec<AuditEntry>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let entries = sqlx::query_as!(
+            AuditEntry,
+            r#"
+            SELECT id, user_id, actor, entity, entity_id, operation, diff, changed_at
+            FROM mutation_audit
+            WHERE ($1::uuid IS NULL OR entity_id = $1)
+                AND ($2::uuid IS NULL OR user_id = $2)
+            ORDER BY changed_at DESC, id DESC
+            LIMIT $3
+            "#,
+            entity_id,
+            user_id,
+            limit.unwrap_or(100).clamp(1, 1000) as i64
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Rows that break rules the schema can't enforce, each with a suggested
+    /// fix (owner only). Run after imports and migrations; the
+    /// `validate-data` command runs the same scan
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn data_issues(&self, ctx: &Context<'_>) -> Result<Vec<DataIssue>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+        Ok(validation::scan(pool).await?)
+    }
+
+    /// API keys for devices and scripts, newest first (owner only)
+    #[graphql(guard = "RequireRole(auth::OWNER)")]
+    async fn api_keys(
+        &self,
+        ctx: &Context<'_>,
+        include_revoked: Option<bool>,
+    ) -> Result<Vec<ApiKey>> {
+        // Credentials live outside the reporting role's grants
+        let pool = ctx.data::<PgPool>()?;
+
+        let api_keys = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, name, prefix, scope, created_by, last_used_at, revoked_at, created_at
+            FROM api_keys
+            WHERE $1 OR revoked_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+            include_revoked.unwrap_or(false)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(api_keys)
+    }
+
+    /// The signed-in user (null when anonymous)
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        // Credentials live outside the reporting role's grants
+        let pool = ctx.data::<PgPool>()?;
+
+        match ctx.data_opt::<Identity>() {
+            Some(identity) => Ok(auth::find_user(pool, identity.user_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// The signed-in user's saved dashboards, the default first
+    async fn dashboards(&self, ctx: &Context<'_>) -> Result<Vec<Dashboard>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        match ctx.data_opt::<Identity>() {
+            Some(identity) => Ok(dashboards::list(pool, identity.user_id).await?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The signed-in user's notification settings
+    async fn notification_settings(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<NotificationSettings>> {
+        let notifier = ctx.data::<Notifier>()?;
+
+        match ctx.data_opt::<Identity>() {
+            Some(identity) => Ok(notifier.settings(identity.user_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Federation entity resolver for `Customer @key(fields: "id")`
+    #[graphql(entity)]
+    async fn find_customer_by_id(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<Customer>> {
+        self.customer(ctx, id).await
+    }
+
+    /// Federation entity resolver for `InventoryItem @key(fields: "id")`
+    #[graphql(entity)]
+    async fn find_inventory_item_by_id(
+        &self,
+        ctx: &Context<'_>,
+        id: uuid::Uuid,
+    ) -> Result<Option<InventoryItem>> {
+        let pool: &PgPool = ctx.data::<ReportingPool>()?;
+
+        let item = sqlx::query_as!(
+            InventoryItem,
+            r#"
+            SELECT
+                id, name, category, unit, current_stock, reserved_stock,
+                available_stock as "available_stock!: BigDecimal",
+                reorder_point, cost_per_unit, default_supplier_id,
+                shelf_life_days, storage_requirements, product_code, is_active, version,
+                created_at, updated_at
+            FROM inventory
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(item)
     }
 }
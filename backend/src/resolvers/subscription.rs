@@ -0,0 +1,59 @@
+use async_graphql::*;
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+use crate::jobs::availability::AvailabilityFeed;
+use crate::models::{BatchAlert, ProductAvailability};
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Stream batch alerts as readings cross recipe thresholds or batches are
+    /// flagged overdue (optionally for one batch)
+    async fn batch_alerts(
+        &self,
+        ctx: &Context<'_>,
+        batch_id: Option<uuid::Uuid>,
+    ) -> Result<impl Stream<Item = BatchAlert>> {
+        let alerts = ctx.data::<broadcast::Sender<BatchAlert>>()?;
+
+        Ok(
+            BroadcastStream::new(alerts.subscribe()).filter_map(move |alert| {
+                // Lagged subscribers skip the alerts they missed
+                alert
+                    .ok()
+                    .filter(|alert| batch_id.is_none_or(|id| alert.batch_id == id))
+            }),
+        )
+    }
+
+    /// Stream catalog availability bands (in stock / low / sold out), starting
+    /// with the current band of each product. Changes are checked at most every
+    /// 30 seconds and never include stock quantities.
+    async fn product_availability(
+        &self,
+        ctx: &Context<'_>,
+        inventory_ids: Option<Vec<uuid::Uuid>>,
+    ) -> Result<impl Stream<Item = ProductAvailability>> {
+        let feed = ctx.data::<AvailabilityFeed>()?;
+
+        // Subscribe before taking the snapshot so no change falls in between
+        let changes = BroadcastStream::new(feed.subscribe());
+        let snapshot = feed.snapshot().await;
+
+        let wanted = move |product: &ProductAvailability| {
+            inventory_ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&product.inventory_id))
+        };
+        let wanted_change = wanted.clone();
+
+        Ok(tokio_stream::iter(snapshot)
+            .filter(wanted)
+            .chain(changes.filter_map(move |change| {
+                // Lagged subscribers skip the changes they missed
+                change.ok().filter(&wanted_change)
+            })))
+    }
+}
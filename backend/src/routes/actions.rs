@@ -0,0 +1,132 @@
+use axum::{
+    Form,
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::services::response_cache::ResponseCache;
+use crate::services::{action_links, audit};
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmForm {
+    pub notes: Option<String>,
+}
+
+/// GET /actions/{token}
+///
+/// The page an action link's QR code opens: what confirming does, and a
+/// button to do it. Opening the page changes nothing, so link previews and
+/// repeat scans are harmless.
+pub async fn show_action(
+    Extension(pool): Extension<PgPool>,
+    Path(token): Path<String>,
+) -> Response {
+    let link = match action_links::find(&pool, &token).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return page(StatusCode::NOT_FOUND, "This link isn't valid", ""),
+        Err(e) => return failed(e),
+    };
+
+    if let Some(reason) = action_links::unusable(&link) {
+        return page(StatusCode::GONE, reason, &paragraph(&link.description));
+    }
+
+    let body = format!(
+        r#"<p>{}</p>
+<form method="post">
+<label>Notes (optional)<br><textarea name="notes" rows="3"></textarea></label>
+<p><button type="submit">Confirm</button></p>
+</form>
+<p><small>Link expires {}</small></p>"#,
+        escape(&link.description),
+        link.expires_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    page(StatusCode::OK, "Confirm", &body)
+}
+
+/// POST /actions/{token}
+///
+/// Carry out the link's transition as the staff member it was made for, so
+/// the audit trail records them rather than an anonymous request.
+pub async fn confirm_action(
+    Extension(pool): Extension<PgPool>,
+    Extension(cache): Extension<ResponseCache>,
+    Path(token): Path<String>,
+    Form(form): Form<ConfirmForm>,
+) -> Response {
+    let link = match action_links::find(&pool, &token).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return page(StatusCode::NOT_FOUND, "This link isn't valid", ""),
+        Err(e) => return failed(e),
+    };
+
+    if let Some(reason) = action_links::unusable(&link) {
+        return page(StatusCode::GONE, reason, &paragraph(&link.description));
+    }
+
+    let identity = match action_links::holder(&pool, &link).await {
+        Ok(Some(identity)) => identity,
+        Ok(None) => {
+            return page(
+                StatusCode::FORBIDDEN,
+                "This link's account is no longer active",
+                "",
+            );
+        }
+        Err(e) => return failed(e),
+    };
+
+    let outcome = audit::acting_as(
+        Some(identity),
+        action_links::confirm(&pool, &token, form.notes),
+    )
+    .await;
+
+    match outcome {
+        Ok(Ok(message)) => {
+            cache.invalidate();
+            page(StatusCode::OK, "Done", &paragraph(&message))
+        }
+        Ok(Err(reason)) => page(StatusCode::CONFLICT, "Not done", &paragraph(&reason)),
+        Err(e) => failed(e),
+    }
+}
+
+fn failed(e: sqlx::Error) -> Response {
+    tracing::error!("❌ Failed to handle action link: {}", e);
+    page(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Something went wrong",
+        &paragraph("Try again in a moment."),
+    )
+}
+
+/// A bare page that reads well on a phone. `body` is HTML; `title` is text.
+pub(crate) fn page(status: StatusCode, title: &str, body: &str) -> Response {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>body{{font-family:sans-serif;margin:1.5em;font-size:1.2em}}button{{font-size:1.2em;padding:.6em 2em}}textarea{{width:100%}}</style>
+</head><body><h1>{title}</h1>
+{body}
+</body></html>"#,
+        title = escape(title),
+        body = body
+    );
+    (status, Html(html)).into_response()
+}
+
+pub(crate) fn paragraph(text: &str) -> String {
+    format!("<p>{}</p>", escape(text))
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,361 @@
+use crate::services::changes::{self, Cursor};
+use crate::services::csv_export::{self, cell, optional};
+use crate::services::database::ReportingPool;
+use axum::{
+    extract::{Extension, Query},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesParams {
+    /// Cursor from the previous page's `X-Next-Cursor` header
+    pub since: Option<String>,
+    /// Comma-separated entity names to include (default: all)
+    pub entities: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InventoryExportParams {
+    /// Include deactivated items (default: active only)
+    pub include_inactive: Option<bool>,
+}
+
+/// Dates for an export, both inclusive (UTC days). Either end may be left open.
+#[derive(Debug, Deserialize)]
+pub struct DateRangeParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRangeParams {
+    fn is_reversed(&self) -> bool {
+        matches!((self.from, self.to), (Some(from), Some(to)) if to < from)
+    }
+
+    /// Start of `from`, if given.
+    fn start(&self) -> Option<DateTime<Utc>> {
+        self.from
+            .map(|from| from.and_time(NaiveTime::MIN).and_utc())
+    }
+
+    /// Start of the day after `to` (an exclusive end), if given.
+    fn end(&self) -> Option<DateTime<Utc>> {
+        self.to
+            .map(|to| to.and_time(NaiveTime::MIN).and_utc() + chrono::Duration::days(1))
+    }
+}
+
+/// GET /export/changes
+///
+/// Same data as the `changes` GraphQL query, as newline-delimited JSON so
+/// DuckDB can read it directly (`read_json('.../export/changes?since=...')`).
+/// The cursor to resume from is returned in `X-Next-Cursor`, and
+/// `X-Has-More: true` means another page is ready.
+pub async fn export_changes(
+    Extension(pool): Extension<ReportingPool>,
+    Query(params): Query<ChangesParams>,
+) -> Response {
+    let since = match Cursor::parse(params.since.as_deref()) {
+        Ok(since) => since,
+        Err(error) => return (StatusCode::BAD_REQUEST, error).into_response(),
+    };
+
+    let entities: Option<Vec<String>> = params.entities.map(|entities| {
+        entities
+            .split(',')
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect()
+    });
+
+    if let Some(ref entities) = entities
+        && let Err(error) = changes::validate_entities(entities)
+    {
+        return (StatusCode::BAD_REQUEST, error).into_response();
+    }
+
+    let change_set = match changes::fetch(
+        &pool,
+        since,
+        entities.as_deref(),
+        params.limit.unwrap_or(changes::DEFAULT_LIMIT),
+    )
+    .await
+    {
+        Ok(change_set) => change_set,
+        Err(e) => {
+            tracing::error!("❌ Failed to export changes: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read changes").into_response();
+        }
+    };
+
+    let body: String = change_set
+        .changes
+        .iter()
+        .filter_map(|change| serde_json::to_string(change).ok())
+        .map(|line| line + "\n")
+        .collect();
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::HeaderName::from_static("x-next-cursor"),
+                change_set.next_cursor,
+            ),
+            (
+                header::HeaderName::from_static("x-has-more"),
+                change_set.has_more.to_string(),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// GET /export/inventory.csv
+///
+/// Every inventory item with its stock, costing and supplier. The columns
+/// match `importCsv`'s defaults, so an edited export can be imported back
+/// with `upsert`. `?include_inactive=true` adds deactivated items.
+pub async fn export_inventory(
+    Extension(pool): Extension<ReportingPool>,
+    Query(params): Query<InventoryExportParams>,
+) -> Response {
+    let (csv, response) = csv_export::download(
+        "inventory.csv",
+        &[
+            "Name",
+            "Category",
+            "Unit",
+            "Current Stock",
+            "Reserved Stock",
+            "Available Stock",
+            "Reorder Point",
+            "Cost Per Unit",
+            "Stock Value",
+            "Default Supplier",
+            "Shelf Life Days",
+            "Storage Requirements",
+            "Active",
+            "Discontinued At",
+        ],
+    );
+    let include_inactive = params.include_inactive.unwrap_or(false);
+
+    tokio::spawn(async move {
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT
+                i.name, i.category, i.unit, i.current_stock, i.reserved_stock,
+                i.available_stock AS "available_stock!", i.reorder_point, i.cost_per_unit,
+                ROUND(i.current_stock * i.cost_per_unit, 2) AS stock_value,
+                s.name AS "default_supplier?", i.shelf_life_days, i.storage_requirements,
+                i.is_active, i.discontinued_at
+            FROM inventory i
+            LEFT JOIN suppliers s ON s.id = i.default_supplier_id
+            WHERE $1 OR i.is_active
+            ORDER BY i.category, i.name
+            "#,
+            include_inactive
+        )
+        .fetch(&*pool);
+
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => return csv.fail("inventory", e).await,
+            };
+
+            let sent = csv
+                .row(&[
+                    cell(row.name),
+                    cell(row.category),
+                    cell(row.unit),
+                    cell(row.current_stock),
+                    cell(row.reserved_stock),
+                    cell(row.available_stock),
+                    cell(row.reorder_point),
+                    optional(row.cost_per_unit),
+                    optional(row.stock_value),
+                    optional(row.default_supplier),
+                    optional(row.shelf_life_days),
+                    optional(row.storage_requirements),
+                    cell(if row.is_active { "yes" } else { "no" }),
+                    optional(row.discontinued_at.map(|at| at.to_rfc3339())),
+                ])
+                .await;
+            if !sent {
+                return;
+            }
+        }
+    });
+
+    response
+}
+
+/// GET /export/sales.csv?from=&to=
+///
+/// One row per sale made in the date range (inclusive, UTC days), oldest
+/// first, with its totals, payment and customer.
+pub async fn export_sales(
+    Extension(pool): Extension<ReportingPool>,
+    Query(params): Query<DateRangeParams>,
+) -> Response {
+    if params.is_reversed() {
+        return (StatusCode::BAD_REQUEST, "`to` cannot be before `from`").into_response();
+    }
+    let (start, end) = (params.start(), params.end());
+
+    let (csv, response) = csv_export::download(
+        "sales.csv",
+        &[
+            "Sale Number",
+            "Sale Date",
+            "Customer",
+            "Items",
+            "Subtotal",
+            "Tax",
+            "Discount",
+            "Total",
+            "Payment Method",
+            "Payment Status",
+            "Notes",
+        ],
+    );
+
+    tokio::spawn(async move {
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT
+                s.sale_number, s.sale_date, c.name AS "customer?",
+                (SELECT COUNT(*) FROM sale_items si WHERE si.sale_id = s.id) AS "items!",
+                s.subtotal, s.tax_amount, s.discount_amount, s.total_amount,
+                s.payment_method, s.payment_status, s.notes
+            FROM sales s
+            LEFT JOIN customers c ON c.id = s.customer_id
+            WHERE ($1::timestamptz IS NULL OR s.sale_date >= $1)
+                AND ($2::timestamptz IS NULL OR s.sale_date < $2)
+            ORDER BY s.sale_date, s.sale_number
+            "#,
+            start,
+            end
+        )
+        .fetch(&*pool);
+
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => return csv.fail("sales", e).await,
+            };
+
+            let sent = csv
+                .row(&[
+                    cell(row.sale_number),
+                    cell(row.sale_date.to_rfc3339()),
+                    optional(row.customer),
+                    cell(row.items),
+                    cell(row.subtotal),
+                    cell(row.tax_amount),
+                    cell(row.discount_amount),
+                    cell(row.total_amount),
+                    optional(row.payment_method),
+                    cell(row.payment_status),
+                    optional(row.notes),
+                ])
+                .await;
+            if !sent {
+                return;
+            }
+        }
+    });
+
+    response
+}
+
+/// GET /export/batches.csv?from=&to=
+///
+/// One row per production batch started in the date range (inclusive, UTC
+/// days), oldest first, with its product, recipe, yield and location.
+pub async fn export_batches(
+    Extension(pool): Extension<ReportingPool>,
+    Query(params): Query<DateRangeParams>,
+) -> Response {
+    if params.is_reversed() {
+        return (StatusCode::BAD_REQUEST, "`to` cannot be before `from`").into_response();
+    }
+    let (start, end) = (params.start(), params.end());
+
+    let (csv, response) = csv_export::download(
+        "batches.csv",
+        &[
+            "Batch Number",
+            "Product",
+            "Recipe",
+            "Status",
+            "Batch Size",
+            "Unit",
+            "Start Date",
+            "Completion Date",
+            "Actual Yield",
+            "Yield %",
+            "Storage Location",
+            "Notes",
+        ],
+    );
+
+    tokio::spawn(async move {
+        let mut rows = sqlx::query!(
+            r#"
+            SELECT
+                b.batch_number, i.name AS product, r.template_name AS "recipe?", b.status,
+                b.batch_size, b.unit, b.start_date, b.completion_date, b.actual_yield,
+                b.yield_percentage, l.name AS "storage_location?", b.notes
+            FROM production_batches b
+            JOIN inventory i ON i.id = b.product_inventory_id
+            LEFT JOIN recipe_templates r ON r.id = b.recipe_template_id
+            LEFT JOIN locations l ON l.id = b.storage_location_id
+            WHERE ($1::timestamptz IS NULL OR b.start_date >= $1)
+                AND ($2::timestamptz IS NULL OR b.start_date < $2)
+            ORDER BY b.start_date, b.batch_number
+            "#,
+            start,
+            end
+        )
+        .fetch(&*pool);
+
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => return csv.fail("batches", e).await,
+            };
+
+            let sent = csv
+                .row(&[
+                    cell(row.batch_number),
+                    cell(row.product),
+                    optional(row.recipe),
+                    cell(row.status),
+                    cell(row.batch_size),
+                    cell(row.unit),
+                    cell(row.start_date.to_rfc3339()),
+                    optional(row.completion_date.map(|at| at.to_rfc3339())),
+                    optional(row.actual_yield),
+                    optional(row.yield_percentage),
+                    optional(row.storage_location),
+                    optional(row.notes),
+                ])
+                .await;
+            if !sent {
+                return;
+            }
+        }
+    });
+
+    response
+}
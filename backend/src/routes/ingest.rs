@@ -0,0 +1,222 @@
+use axum::{
+    Json,
+    extract::Extension,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::jobs::reading_alerts::ReadingQueue;
+use crate::services::auth::ApiKeyAuth;
+use crate::services::readings::{self, NewReading};
+use crate::services::response_cache::ResponseCache;
+use crate::services::secrets::{self, SecretStore};
+
+/// Probe tokens allowed to post readings, from the `ingest_device_tokens`
+/// secret or the `INGEST_DEVICE_TOKENS` environment variable.
+///
+/// The value is a comma-separated list of `device_name:token` pairs, e.g.
+/// `crock-shelf-probe:3f9c...,walk-in-probe:a81d...`. With no tokens
+/// configured the ingest endpoint rejects every request.
+struct DeviceTokens {
+    devices: Vec<(String, String)>,
+}
+
+impl DeviceTokens {
+    async fn load(secrets: &SecretStore) -> Self {
+        let devices = secrets
+            .resolve(secrets::INGEST_DEVICE_TOKENS, "INGEST_DEVICE_TOKENS")
+            .await
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| {
+                let (name, token) = pair.trim().split_once(':')?;
+                (!name.is_empty() && !token.is_empty())
+                    .then(|| (name.to_string(), token.to_string()))
+            })
+            .collect();
+
+        Self { devices }
+    }
+
+    /// Name of the device whose token is in the `Authorization: Bearer` header.
+    fn authenticate(&self, headers: &HeaderMap) -> Option<&str> {
+        let token = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+
+        self.devices
+            .iter()
+            .find(|(_, expected)| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// A batch of probe readings.
+#[derive(Debug, Deserialize)]
+pub struct IngestRequest {
+    pub readings: Vec<SensorReading>,
+}
+
+/// One probe reading, keyed by batch ID, batch number, or vessel.
+#[derive(Debug, Deserialize)]
+pub struct SensorReading {
+    pub batch_id: Option<Uuid>,
+    pub batch_number: Option<String>,
+    /// Name of the vessel the probe sits in (resolves to its in-progress batch)
+    pub vessel: Option<String>,
+    pub recorded_at: Option<DateTime<Utc>>,
+    pub ph: Option<BigDecimal>,
+    pub temperature: Option<BigDecimal>,
+    pub salinity_percent: Option<BigDecimal>,
+    pub brix: Option<BigDecimal>,
+    pub observations: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestResponse {
+    pub accepted: usize,
+    pub rejected: Vec<RejectedReading>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RejectedReading {
+    /// Position of the reading in the request
+    pub index: usize,
+    pub error: String,
+}
+
+/// POST /ingest/readings
+///
+/// Records readings from fermentation chamber probes into `batch_readings`
+/// and queues them for threshold evaluation. Each reading is accepted or rejected on its
+/// own so one bad row doesn't drop the rest of the batch.
+///
+/// Probes authenticate with a device token or an 'ingest' (or 'full') API
+/// key; readings are tagged with the device or key name.
+pub async fn ingest_readings(
+    Extension(pool): Extension<PgPool>,
+    Extension(reading_queue): Extension<ReadingQueue>,
+    Extension(secrets): Extension<SecretStore>,
+    Extension(cache): Extension<ResponseCache>,
+    api_key: Option<ApiKeyAuth>,
+    headers: HeaderMap,
+    Json(request): Json<IngestRequest>,
+) -> Response {
+    let device_name = match api_key {
+        Some(ApiKeyAuth(key)) if matches!(key.scope.as_str(), "ingest" | "full") => key.name,
+        Some(_) => {
+            return (StatusCode::FORBIDDEN, "This API key can't post readings").into_response();
+        }
+        None => match DeviceTokens::load(&secrets).await.authenticate(&headers) {
+            Some(device_name) => device_name.to_string(),
+            None => {
+                return (StatusCode::UNAUTHORIZED, "Invalid or missing device token")
+                    .into_response();
+            }
+        },
+    };
+    let device_name = device_name.as_str();
+
+    let mut accepted = 0;
+    let mut rejected = Vec::new();
+
+    for (index, reading) in request.readings.into_iter().enumerate() {
+        match ingest_one(&pool, &reading_queue, device_name, reading).await {
+            Ok(()) => accepted += 1,
+            Err(error) => rejected.push(RejectedReading { index, error }),
+        }
+    }
+
+    if accepted > 0 {
+        cache.invalidate();
+    }
+
+    tracing::info!(
+        "📡 {} posted {} reading(s), {} rejected",
+        device_name,
+        accepted + rejected.len(),
+        rejected.len()
+    );
+
+    Json(IngestResponse { accepted, rejected }).into_response()
+}
+
+async fn ingest_one(
+    pool: &PgPool,
+    reading_queue: &ReadingQueue,
+    device_name: &str,
+    reading: SensorReading,
+) -> Result<(), String> {
+    let batch_id = resolve_batch(pool, &reading).await?;
+
+    let new_reading = NewReading {
+        recorded_at: reading.recorded_at,
+        ph: reading.ph,
+        temperature: reading.temperature,
+        salinity_percent: reading.salinity_percent,
+        brix: reading.brix,
+        observations: reading.observations,
+        source: "sensor",
+        device_name: Some(device_name.to_string()),
+    };
+
+    readings::validate(&new_reading)?;
+
+    let reading = readings::insert(pool, batch_id, new_reading)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    reading_queue.submit(reading);
+
+    Ok(())
+}
+
+async fn resolve_batch(pool: &PgPool, reading: &SensorReading) -> Result<Uuid, String> {
+    if let Some(batch_id) = reading.batch_id {
+        return sqlx::query_scalar!("SELECT id FROM production_batches WHERE id = $1", batch_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Batch {} not found", batch_id));
+    }
+
+    if let Some(ref batch_number) = reading.batch_number {
+        return sqlx::query_scalar!(
+            "SELECT id FROM production_batches WHERE batch_number = $1",
+            batch_number
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Batch {} not found", batch_number));
+    }
+
+    if let Some(ref vessel) = reading.vessel {
+        return sqlx::query_scalar!(
+            r#"
+            SELECT pb.id
+            FROM production_batches pb
+            JOIN vessels v ON v.id = pb.vessel_id
+            WHERE pb.status = 'in_progress' AND v.name = $1
+            "#,
+            vessel
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No in-progress batch in vessel {}", vessel));
+    }
+
+    Err("Reading must include batch_id, batch_number, or vessel".to_string())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
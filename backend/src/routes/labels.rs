@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::labels;
+use crate::services::qr::QrCode;
+
+/// Most labels one request renders: ten sheets.
+const MAX_BATCH_LABELS: usize = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct BatchLabelParams {
+    /// Labels to print (default one sheet of 30)
+    pub count: Option<usize>,
+}
+
+/// GET /label-runs/{id}/pdf
+///
+/// Serves a label run's print-ready sheets (the `url` of a `LabelRun`).
+pub async fn label_run_pdf(Extension(pool): Extension<PgPool>, Path(id): Path<Uuid>) -> Response {
+    let pdf = sqlx::query_scalar!("SELECT pdf FROM label_runs WHERE id = $1", id)
+        .fetch_optional(&pool)
+        .await;
+
+    match pdf {
+        Ok(Some(pdf)) => (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("inline; filename=\"labels-{}.pdf\"", id),
+                ),
+            ],
+            pdf,
+        )
+            .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Label run not found").into_response(),
+        Err(e) => {
+            tracing::error!("❌ Failed to load label run {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load label run",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /lots/{id}/qr.svg
+///
+/// Serves a lot's code as a QR code for its label (received and produced
+/// lots have one).
+pub async fn lot_qr_svg(Extension(pool): Extension<PgPool>, Path(id): Path<Uuid>) -> Response {
+    let code = sqlx::query_scalar!("SELECT code FROM inventory_lots WHERE id = $1", id)
+        .fetch_optional(&pool)
+        .await;
+
+    match code {
+        Ok(Some(Some(code))) => match QrCode::encode(&code) {
+            Some(qr) => ([(header::CONTENT_TYPE, "image/svg+xml")], qr.svg()).into_response(),
+            None => (StatusCode::INTERNAL_SERVER_ERROR, "Lot code too long").into_response(),
+        },
+        Ok(Some(None)) => (StatusCode::NOT_FOUND, "Lot has no code").into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Lot not found").into_response(),
+        Err(e) => {
+            tracing::error!("❌ Failed to load lot {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load lot").into_response()
+        }
+    }
+}
+
+/// GET /batches/{id}/labels.pdf?count=N
+///
+/// Prints a completed batch's jar labels: product, batch number, produced
+/// and best-by dates, and a QR code of the batch's public trace page, which
+/// `lookupByBarcode` also takes.
+pub async fn batch_labels_pdf(
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<BatchLabelParams>,
+) -> Response {
+    let count = params.count.unwrap_or(30);
+    if !(1..=MAX_BATCH_LABELS).contains(&count) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("count must be from 1 to {}", MAX_BATCH_LABELS),
+        )
+            .into_response();
+    }
+
+    match labels::batch_label(&pool, id).await {
+        Ok(Some(label)) if label.status != "completed" => (
+            StatusCode::CONFLICT,
+            format!(
+                "Batch {} is {}; only completed batches are labelled",
+                label.batch_number, label.status
+            ),
+        )
+            .into_response(),
+        Ok(Some(label)) => (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("inline; filename=\"labels-{}.pdf\"", label.batch_number),
+                ),
+            ],
+            labels::render_batch_labels(&label, count),
+        )
+            .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Production batch not found").into_response(),
+        Err(e) => {
+            tracing::error!("❌ Failed to load batch {} for labels: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load batch").into_response()
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Extension, Path},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// GET /stock-counts/photos/{id}
+///
+/// Serves a quick count's photo evidence (the `url` of a `StockCountPhoto`).
+pub async fn stock_count_photo(
+    Extension(pool): Extension<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let photo = sqlx::query!(
+        "SELECT content_type, data FROM stock_count_photos WHERE id = $1",
+        id
+    )
+    .fetch_optional(&pool)
+    .await;
+
+    match photo {
+        Ok(Some(photo)) => {
+            ([(header::CONTENT_TYPE, photo.content_type)], photo.data).into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "Photo not found").into_response(),
+        Err(e) => {
+            tracing::error!("❌ Failed to load stock count photo {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load photo").into_response()
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+
+use crate::routes::actions::{escape, page, paragraph};
+use crate::services::traceability;
+
+/// GET /trace/{batch_number}
+///
+/// The page a jar's QR code opens for customers: when the batch was made,
+/// what went in, and where it came from, minus any fields the owner has
+/// hidden. No sign-in. Clients that send `Accept: application/json` get the
+/// same details as JSON.
+pub async fn batch_trace(
+    Extension(pool): Extension<PgPool>,
+    Path(batch_number): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+
+    let trace = match traceability::public_trace(&pool, &batch_number).await {
+        Ok(Some(trace)) => trace,
+        Ok(None) if wants_json => {
+            return (StatusCode::NOT_FOUND, "Batch not found").into_response();
+        }
+        Ok(None) => {
+            return page(
+                StatusCode::NOT_FOUND,
+                "Batch not found",
+                &paragraph("Check the code on the jar and try again."),
+            );
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to trace batch {}: {}", batch_number, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load batch").into_response();
+        }
+    };
+
+    if wants_json {
+        return Json(trace).into_response();
+    }
+
+    let mut body = paragraph(&format!("Batch {}", trace.batch_number));
+    if let Some(date) = trace.production_date {
+        body.push_str(&paragraph(&format!("Made {}", date.format("%B %-d, %Y"))));
+    }
+    if let Some(date) = trace.best_by {
+        body.push_str(&paragraph(&format!(
+            "Best by {}",
+            date.format("%B %-d, %Y")
+        )));
+    }
+    for (heading, names) in [
+        ("Ingredients", &trace.ingredients),
+        ("Sourced from", &trace.suppliers),
+        ("Grown in", &trace.origins),
+    ] {
+        if let Some(names) = names.as_ref().filter(|names| !names.is_empty()) {
+            let items: String = names
+                .iter()
+                .map(|name| format!("<li>{}</li>", escape(name)))
+                .collect();
+            body.push_str(&format!("<h2>{}</h2>\n<ul>{}</ul>\n", heading, items));
+        }
+    }
+
+    page(StatusCode::OK, &trace.product_name, &body)
+}
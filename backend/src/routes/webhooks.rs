@@ -0,0 +1,447 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Extension, Path},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::jobs::shopify_sync::ShopifySync;
+use crate::jobs::square_import::SquareImport;
+use crate::services::response_cache::ResponseCache;
+use crate::services::secrets::{self, SecretStore};
+use crate::services::stripe;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How far a signed delivery timestamp may be from now before the delivery
+/// is treated as a replay.
+const TIMESTAMP_TOLERANCE_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    Stripe,
+    Shopify,
+    Square,
+}
+
+impl Provider {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "stripe" => Some(Provider::Stripe),
+            "shopify" => Some(Provider::Shopify),
+            "square" => Some(Provider::Square),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Provider::Stripe => "stripe",
+            Provider::Shopify => "shopify",
+            Provider::Square => "square",
+        }
+    }
+
+    /// Signing secret (secret name, env var fallback).
+    fn signing_secret(self) -> (&'static str, &'static str) {
+        match self {
+            Provider::Stripe => (secrets::STRIPE_WEBHOOK_SECRET, "STRIPE_WEBHOOK_SECRET"),
+            Provider::Shopify => (secrets::SHOPIFY_WEBHOOK_SECRET, "SHOPIFY_WEBHOOK_SECRET"),
+            Provider::Square => (
+                secrets::SQUARE_WEBHOOK_SIGNATURE_KEY,
+                "SQUARE_WEBHOOK_SIGNATURE_KEY",
+            ),
+        }
+    }
+}
+
+/// How much of a rejected delivery's body is archived. Anyone can post to
+/// the webhook routes, so rejected deliveries keep only enough to debug with.
+const REJECTED_PAYLOAD_BYTES: usize = 512;
+
+/// Headers archived with a rejected delivery. Signatures and credentials
+/// aren't among them.
+const REJECTED_HEADERS: &[&str] = &["content-type", "content-length", "user-agent"];
+
+/// Identifying details of a delivery whose signature checked out.
+struct Verified {
+    event_id: Option<String>,
+    event_type: Option<String>,
+}
+
+/// POST /webhooks/{provider}
+///
+/// Receives Stripe, Shopify, and Square webhooks. Every delivery is archived
+/// to `webhook_events` with its headers and raw body. Rejected ones keep only
+/// their content type, length and user agent and the start of the body
+/// (`REJECTED_PAYLOAD_BYTES`), so failed signatures can be debugged without
+/// junk posts filling the table. Deliveries with a bad signature or a
+/// stale timestamp get a 401, and verified ones without an event ID (Shopify's
+/// `X-Shopify-Webhook-Id`, Square's `event_id`, Stripe's `id`) a 400, since
+/// replays couldn't be caught. Replays of an already received event ID are
+/// acknowledged with a 200 so the provider stops retrying, but are not
+/// treated as new. New Square order and payment events start the Square
+/// import straight away, and new Shopify order events the Shopify sync.
+/// Stripe checkout events settle payment links; if that fails the delivery
+/// is marked 'failed' and gets a 500, so Stripe's retry is applied anew.
+#[allow(clippy::too_many_arguments)]
+pub async fn receive_webhook(
+    Extension(pool): Extension<PgPool>,
+    Extension(secrets): Extension<SecretStore>,
+    Extension(square_import): Extension<SquareImport>,
+    Extension(shopify_sync): Extension<ShopifySync>,
+    Extension(cache): Extension<ResponseCache>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(provider) = Provider::parse(&provider) else {
+        return (StatusCode::NOT_FOUND, "Unknown webhook provider").into_response();
+    };
+
+    let (secret_name, env_var) = provider.signing_secret();
+    let Some(secret) = secrets.resolve(secret_name, env_var).await else {
+        tracing::error!(
+            "❌ {} webhook received but {} is not configured",
+            provider.name(),
+            env_var
+        );
+        archive_rejected(
+            &pool,
+            provider,
+            &headers,
+            &body,
+            "Signing secret not configured",
+        )
+        .await;
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Webhook signing secret not configured",
+        )
+            .into_response();
+    };
+
+    let verified = match provider {
+        Provider::Stripe => verify_stripe(&secret, &headers, &body),
+        Provider::Shopify => verify_shopify(&secret, &headers, &body),
+        Provider::Square => match secrets
+            .resolve(secrets::SQUARE_WEBHOOK_URL, "SQUARE_WEBHOOK_URL")
+            .await
+        {
+            Some(url) => verify_square(&secret, &url, &headers, &body),
+            None => Err("Square webhook URL not configured".to_string()),
+        },
+    };
+
+    let verified = match verified {
+        Ok(verified) => verified,
+        Err(error) => {
+            tracing::warn!("🚫 Rejected {} webhook: {}", provider.name(), error);
+            archive_rejected(&pool, provider, &headers, &body, &error).await;
+            return (StatusCode::UNAUTHORIZED, error).into_response();
+        }
+    };
+
+    // Replay protection keys on the event ID, so a delivery without one
+    // can't be told from a replay
+    let Some(event_id) = verified.event_id.as_deref() else {
+        let error = format!("{} webhook has no event ID", provider.name());
+        tracing::warn!("🚫 Rejected {}", error);
+        archive_rejected(&pool, provider, &headers, &body, &error).await;
+        return (StatusCode::BAD_REQUEST, error).into_response();
+    };
+
+    match archive_received(&pool, provider, &verified, &headers, &body).await {
+        Ok(Some(archived)) => {
+            tracing::info!(
+                "🪝 {} webhook {} ({})",
+                provider.name(),
+                event_id,
+                verified.event_type.as_deref().unwrap_or("unknown type")
+            );
+            if matches!(provider, Provider::Square)
+                && verified.event_type.as_deref().is_some_and(|event| {
+                    event.starts_with("order.") || event.starts_with("payment.")
+                })
+            {
+                square_import.trigger();
+            }
+            if matches!(provider, Provider::Shopify)
+                && verified
+                    .event_type
+                    .as_deref()
+                    .is_some_and(|topic| topic.starts_with("orders/"))
+            {
+                shopify_sync.trigger();
+            }
+            if matches!(provider, Provider::Stripe) {
+                match stripe::apply_event(&pool, &body).await {
+                    Ok(Some(outcome)) => {
+                        cache.invalidate();
+                        tracing::info!("💳 {}", outcome);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::error!("❌ Failed to apply stripe webhook: {}", e);
+                        mark_failed(&pool, archived, &e.to_string()).await;
+                        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply webhook")
+                            .into_response();
+                    }
+                }
+            }
+            Json(json!({ "received": true, "duplicate": false })).into_response()
+        }
+        Ok(None) => {
+            tracing::info!(
+                "🔁 Ignored replayed {} webhook {}",
+                provider.name(),
+                event_id
+            );
+            Json(json!({ "received": true, "duplicate": true })).into_response()
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to archive {} webhook: {}", provider.name(), e);
+            // A 5xx makes the provider retry the delivery later
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to record webhook",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Stripe: `Stripe-Signature: t=<unix>,v1=<hex>` over `"<t>.<body>"`.
+fn verify_stripe(secret: &str, headers: &HeaderMap, body: &[u8]) -> Result<Verified, String> {
+    let signature_header = header_str(headers, "stripe-signature")
+        .ok_or_else(|| "Missing Stripe-Signature header".to_string())?;
+
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+
+    for part in signature_header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {}
+        }
+    }
+
+    let timestamp =
+        timestamp.ok_or_else(|| "Stripe-Signature header has no timestamp".to_string())?;
+
+    let mut signed_payload = format!("{}.", timestamp).into_bytes();
+    signed_payload.extend_from_slice(body);
+
+    if !signatures
+        .iter()
+        .any(|signature| hmac_matches(secret, &signed_payload, signature))
+    {
+        return Err("Invalid Stripe signature".to_string());
+    }
+
+    check_timestamp(DateTime::from_timestamp(timestamp, 0))?;
+
+    let event: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+
+    Ok(Verified {
+        event_id: json_str(&event, "id"),
+        event_type: json_str(&event, "type"),
+    })
+}
+
+/// Shopify: `X-Shopify-Hmac-Sha256: <base64>` over the body.
+fn verify_shopify(secret: &str, headers: &HeaderMap, body: &[u8]) -> Result<Verified, String> {
+    let signature = header_str(headers, "x-shopify-hmac-sha256")
+        .and_then(|value| BASE64.decode(value).ok())
+        .ok_or_else(|| "Missing or malformed X-Shopify-Hmac-Sha256 header".to_string())?;
+
+    if !hmac_matches(secret, body, &signature) {
+        return Err("Invalid Shopify signature".to_string());
+    }
+
+    // Shopify retries reuse the webhook ID with a fresh trigger time, so the
+    // ID (not the timestamp) is what catches replays
+    Ok(Verified {
+        event_id: header_str(headers, "x-shopify-webhook-id")
+            .or_else(|| header_str(headers, "x-shopify-event-id"))
+            .map(str::to_string),
+        event_type: header_str(headers, "x-shopify-topic").map(str::to_string),
+    })
+}
+
+/// Square: `X-Square-HmacSha256-Signature: <base64>` over the notification
+/// URL followed by the body.
+fn verify_square(
+    secret: &str,
+    notification_url: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Verified, String> {
+    let signature = header_str(headers, "x-square-hmacsha256-signature")
+        .and_then(|value| BASE64.decode(value).ok())
+        .ok_or_else(|| "Missing or malformed X-Square-HmacSha256-Signature header".to_string())?;
+
+    let mut signed_payload = notification_url.as_bytes().to_vec();
+    signed_payload.extend_from_slice(body);
+
+    if !hmac_matches(secret, &signed_payload, &signature) {
+        return Err("Invalid Square signature".to_string());
+    }
+
+    let event: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+
+    Ok(Verified {
+        event_id: json_str(&event, "event_id"),
+        event_type: json_str(&event, "type"),
+    })
+}
+
+/// Constant-time comparison of `signature` against HMAC-SHA256(secret, payload).
+fn hmac_matches(secret: &str, payload: &[u8], signature: &[u8]) -> bool {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(signature).is_ok()
+}
+
+fn check_timestamp(sent_at: Option<DateTime<Utc>>) -> Result<(), String> {
+    let sent_at = sent_at.ok_or_else(|| "Invalid signature timestamp".to_string())?;
+    let age = (Utc::now() - sent_at).num_seconds().abs();
+
+    if age > TIMESTAMP_TOLERANCE_SECS {
+        return Err(format!(
+            "Signature timestamp is {}s off; limit is {}s",
+            age, TIMESTAMP_TOLERANCE_SECS
+        ));
+    }
+
+    Ok(())
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+fn json_str(value: &serde_json::Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+fn headers_json(headers: &HeaderMap) -> serde_json::Value {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), json!(value.to_str().ok()?))))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Archive a verified delivery and return its row. Returns None if the event
+/// ID was already received, in which case the delivery is archived as a
+/// duplicate instead.
+async fn archive_received(
+    pool: &PgPool,
+    provider: Provider,
+    verified: &Verified,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let headers = headers_json(headers);
+    let payload = String::from_utf8_lossy(body);
+
+    let inserted = sqlx::query_scalar!(
+        r#"
+        INSERT INTO webhook_events (provider, event_id, event_type, status, headers, payload)
+        VALUES ($1, $2, $3, 'received', $4, $5)
+        ON CONFLICT (provider, event_id) WHERE status = 'received' DO NOTHING
+        RETURNING id
+        "#,
+        provider.name(),
+        verified.event_id,
+        verified.event_type,
+        headers,
+        payload.as_ref()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    if inserted.is_some() {
+        return Ok(inserted);
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_events (provider, event_id, event_type, status, headers, payload)
+        VALUES ($1, $2, $3, 'duplicate', $4, $5)
+        "#,
+        provider.name(),
+        verified.event_id,
+        verified.event_type,
+        headers,
+        payload.as_ref()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(None)
+}
+
+/// Take a received delivery that couldn't be applied out of replay
+/// protection, so the provider's retry is received as new.
+async fn mark_failed(pool: &PgPool, id: Uuid, error: &str) {
+    let result = sqlx::query!(
+        "UPDATE webhook_events SET status = 'failed', error = $2 WHERE id = $1",
+        id,
+        error
+    )
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("❌ Failed to mark webhook {} failed: {}", id, e);
+    }
+}
+
+/// Archive a rejected delivery: its error, a few harmless headers and the
+/// start of its body.
+async fn archive_rejected(
+    pool: &PgPool,
+    provider: Provider,
+    headers: &HeaderMap,
+    body: &[u8],
+    error: &str,
+) {
+    let headers: serde_json::Map<_, _> = REJECTED_HEADERS
+        .iter()
+        .filter_map(|name| Some((name.to_string(), json!(header_str(headers, name)?))))
+        .collect();
+    let payload = String::from_utf8_lossy(&body[..body.len().min(REJECTED_PAYLOAD_BYTES)]);
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO webhook_events (provider, status, error, headers, payload)
+        VALUES ($1, 'rejected', $2, $3, $4)
+        "#,
+        provider.name(),
+        error,
+        serde_json::Value::from(headers),
+        payload.as_ref()
+    )
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(
+            "❌ Failed to archive rejected {} webhook: {}",
+            provider.name(),
+            e
+        );
+    }
+}
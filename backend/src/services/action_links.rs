@@ -0,0 +1,329 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{ActionLink, CreateActionLinkInput};
+use crate::services::auth::{self, Identity};
+use crate::services::config;
+use crate::services::locations;
+use crate::services::stages::{self, Advance};
+
+/// How long a link works when no expiry is given.
+pub const DEFAULT_MINUTES: i32 = 30;
+/// Longest a link can work: one shift and then some.
+pub const MAX_MINUTES: i32 = 24 * 60;
+
+/// Address a phone opens for `token`. `PUBLIC_URL` is where phones on the
+/// floor reach this server (default `http://localhost:4000`).
+pub fn url(token: &str) -> String {
+    format!("{}/actions/{}", config::public_url(), token)
+}
+
+/// Make a link for one transition, returning it with its token (which isn't
+/// stored), or the reason it can't be made.
+pub async fn create(
+    conn: &mut PgConnection,
+    input: &CreateActionLinkInput,
+    created_by: Uuid,
+) -> Result<Result<(ActionLink, String), String>, sqlx::Error> {
+    let minutes = input.expires_in_minutes.unwrap_or(DEFAULT_MINUTES);
+    if !(1..=MAX_MINUTES).contains(&minutes) {
+        return Ok(Err(format!(
+            "Links can last from 1 to {} minutes",
+            MAX_MINUTES
+        )));
+    }
+
+    // Whoever confirms must be able to do production work
+    let user_id = input.user_id.unwrap_or(created_by);
+    let user = sqlx::query!(
+        "SELECT username, role FROM users WHERE id = $1 AND is_active = true",
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(user) = user else {
+        return Ok(Err("User not found or inactive".to_string()));
+    };
+    if user.role != "owner" && user.role != "production" {
+        return Ok(Err(format!(
+            "{} can't confirm production work (role {})",
+            user.username, user.role
+        )));
+    }
+
+    let (batch_id, from_position, description) = match input.action.as_str() {
+        "advance_stage" => {
+            let Some(batch_id) = input.batch_id else {
+                return Ok(Err("advance_stage links need a batchId".to_string()));
+            };
+
+            let batch = sqlx::query!(
+                r#"
+                SELECT b.batch_number, b.status, current.position AS "position?",
+                    current.name AS "current_stage?", next.name AS "next_stage?"
+                FROM production_batches b
+                LEFT JOIN batch_stages current
+                    ON current.batch_id = b.id AND current.completed_at IS NULL
+                LEFT JOIN recipe_stages next
+                    ON next.recipe_template_id = b.recipe_template_id
+                    AND next.position = COALESCE(current.position, 0) + 1
+                WHERE b.id = $1
+                "#,
+                batch_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            let Some(batch) = batch else {
+                return Ok(Err("Production batch not found".to_string()));
+            };
+            if batch.status != "in_progress" {
+                return Ok(Err(format!(
+                    "Batch {} is {}",
+                    batch.batch_number, batch.status
+                )));
+            }
+            let Some(next_stage) = batch.next_stage else {
+                return Ok(Err(format!(
+                    "Batch {} has no next stage to move to",
+                    batch.batch_number
+                )));
+            };
+
+            let description = match batch.current_stage {
+                Some(current) => format!(
+                    "Move batch {} from {} to {}",
+                    batch.batch_number, current, next_stage
+                ),
+                None => format!("Move batch {} into {}", batch.batch_number, next_stage),
+            };
+            (batch_id, batch.position, description)
+        }
+        "complete_move" => {
+            let Some(task_id) = input.move_task_id else {
+                return Ok(Err("complete_move links need a moveTaskId".to_string()));
+            };
+
+            let task = sqlx::query!(
+                r#"
+                SELECT t.batch_id, t.to_location, t.status, b.batch_number
+                FROM batch_move_tasks t
+                JOIN production_batches b ON b.id = t.batch_id
+                WHERE t.id = $1
+                "#,
+                task_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            let Some(task) = task else {
+                return Ok(Err("Move task not found".to_string()));
+            };
+            if task.status != "pending" {
+                return Ok(Err(format!("Move task is already {}", task.status)));
+            }
+
+            let description = format!("Move batch {} to {}", task.batch_number, task.to_location);
+            (task.batch_id, None, description)
+        }
+        other => {
+            return Ok(Err(format!(
+                "Unknown action '{}'; expected advance_stage or complete_move",
+                other
+            )));
+        }
+    };
+
+    let move_task_id = match input.action.as_str() {
+        "complete_move" => input.move_task_id,
+        _ => None,
+    };
+
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+
+    let link = sqlx::query_as!(
+        ActionLink,
+        r#"
+        INSERT INTO action_links (
+            token_hash, action, batch_id, move_task_id, from_position,
+            description, user_id, created_by, expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW() + make_interval(mins => $9))
+        RETURNING
+            id, action, batch_id, move_task_id, description, user_id, created_by,
+            expires_at, used_at, revoked_at, created_at
+        "#,
+        auth::token_hash(&token),
+        input.action,
+        batch_id,
+        move_task_id,
+        from_position,
+        description,
+        user_id,
+        created_by,
+        minutes
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Ok((link, token)))
+}
+
+/// The link behind a token, used or not.
+pub async fn find(pool: &PgPool, token: &str) -> Result<Option<ActionLink>, sqlx::Error> {
+    sqlx::query_as!(
+        ActionLink,
+        r#"
+        SELECT
+            id, action, batch_id, move_task_id, description, user_id, created_by,
+            expires_at, used_at, revoked_at, created_at
+        FROM action_links
+        WHERE token_hash = $1
+        "#,
+        auth::token_hash(token)
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Why a link can no longer be used, if it can't.
+pub fn unusable(link: &ActionLink) -> Option<&'static str> {
+    if link.used_at.is_some() {
+        Some("This link has already been used")
+    } else if link.revoked_at.is_some() {
+        Some("This link was cancelled")
+    } else if link.expires_at <= Utc::now() {
+        Some("This link has expired; ask for a new one")
+    } else {
+        None
+    }
+}
+
+/// Who confirming a link acts as: its user, while their account is active.
+pub async fn holder(pool: &PgPool, link: &ActionLink) -> Result<Option<Identity>, sqlx::Error> {
+    Ok(auth::find_user(pool, link.user_id)
+        .await?
+        .map(|user| Identity {
+            user_id: user.id,
+            username: user.username,
+            role: user.role,
+            api_key_id: None,
+        }))
+}
+
+/// Carry out a link's transition and use it up. Run it as the link's holder
+/// (see `audit::acting_as`) so the change is recorded against them. Returns
+/// what happened, or why it was refused; a refused link stays usable.
+pub async fn confirm(
+    pool: &PgPool,
+    token: &str,
+    notes: Option<String>,
+) -> Result<Result<String, String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let link = sqlx::query!(
+        r#"
+        SELECT id, batch_id, move_task_id, from_position, used_at, revoked_at,
+            expires_at <= NOW() AS "expired!"
+        FROM action_links
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        auth::token_hash(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(link) = link else {
+        return Ok(Err("This link isn't valid".to_string()));
+    };
+    if link.used_at.is_some() || link.revoked_at.is_some() || link.expired {
+        return Ok(Err("This link can no longer be used".to_string()));
+    }
+
+    let notes = notes.filter(|notes| !notes.trim().is_empty());
+    let now = Utc::now();
+
+    let message = match link.move_task_id {
+        None => {
+            let batch = sqlx::query!(
+                r#"
+                SELECT b.batch_number, b.status, b.recipe_template_id,
+                    (SELECT position FROM batch_stages
+                     WHERE batch_id = b.id AND completed_at IS NULL) AS position
+                FROM production_batches b
+                WHERE b.id = $1
+                FOR UPDATE
+                "#,
+                link.batch_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if batch.status != "in_progress" {
+                return Ok(Err(format!(
+                    "Batch {} is {}",
+                    batch.batch_number, batch.status
+                )));
+            }
+            if batch.position != link.from_position {
+                return Ok(Err(format!(
+                    "Batch {} has changed stage since this link was made",
+                    batch.batch_number
+                )));
+            }
+
+            match stages::advance(&mut tx, link.batch_id, batch.recipe_template_id, now, notes)
+                .await?
+            {
+                Advance::Entered(stage) => format!(
+                    "Batch {} moved to stage {}: {}",
+                    batch.batch_number, stage.position, stage.name
+                ),
+                Advance::Refused(message) => return Ok(Err(message)),
+            }
+        }
+        Some(task_id) => {
+            let task = sqlx::query!(
+                "SELECT to_location, status FROM batch_move_tasks WHERE id = $1 FOR UPDATE",
+                task_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if task.status != "pending" {
+                return Ok(Err(format!("Move task is already {}", task.status)));
+            }
+
+            let (batch_number, task) = locations::record_move(
+                &mut tx,
+                task_id,
+                link.batch_id,
+                &task.to_location,
+                now,
+                notes,
+            )
+            .await?;
+            format!("Batch {} moved to {}", batch_number, task.to_location)
+        }
+    };
+
+    sqlx::query!(
+        "UPDATE action_links SET used_at = $1 WHERE id = $2",
+        now,
+        link.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Ok(message))
+}
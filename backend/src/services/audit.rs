@@ -0,0 +1,42 @@
+use std::future::Future;
+
+use sqlx::PgConnection;
+
+use crate::services::auth::Identity;
+
+tokio::task_local! {
+    /// The user behind the request being handled.
+    static ACTOR: Identity;
+}
+
+/// Run a request with `identity` as the actor its writes are audited
+/// against. Requests without one write unattributed.
+pub async fn acting_as<F: Future>(identity: Option<Identity>, request: F) -> F::Output {
+    match identity {
+        Some(identity) => ACTOR.scope(identity, request).await,
+        None => request.await,
+    }
+}
+
+/// Tag a write-pool connection with the current actor (or nobody) as it is
+/// handed out, so the `record_audit` trigger knows who made each change.
+/// Runs in the acquiring task, which is where the actor is in scope.
+pub async fn tag_connection(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let (user_id, actor) = ACTOR
+        .try_with(|identity| (identity.user_id.to_string(), identity.username.clone()))
+        .unwrap_or_default();
+
+    sqlx::query!(
+        r#"
+        SELECT
+            set_config('app.actor_id', $1, false) AS actor_id,
+            set_config('app.actor', $2, false) AS actor
+        "#,
+        user_id,
+        actor
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,611 @@
+use std::sync::Arc;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{self, SaltString};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, OperationType, Selection};
+use async_graphql::{Context, Data, Guard, ServerError, ServerResult, Variables};
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{ApiKey, User};
+use crate::services::config::Config;
+
+/// How long an access token is good for. Clients refresh after this.
+pub const ACCESS_TOKEN_MINUTES: i64 = 15;
+/// How long an unused refresh token is good for.
+pub const REFRESH_TOKEN_DAYS: i64 = 30;
+/// Minimum password length for new users.
+pub const MIN_PASSWORD_LENGTH: usize = 10;
+
+/// Roles a user can have. The owner can do everything, 'production' runs
+/// batches and recipes, 'sales' runs sales, customers and workshops, and
+/// 'read_only' can only query.
+pub const ROLES: &[&str] = &["owner", "production", "sales", "read_only"];
+
+/// Role groups for `RequireRole`, besides the owner who passes every guard.
+pub const OWNER: &[&str] = &[];
+pub const PRODUCTION: &[&str] = &["production"];
+pub const SALES: &[&str] = &["sales"];
+pub const STAFF: &[&str] = &["production", "sales"];
+
+/// What an API key may do: 'read_only' queries and exports, 'ingest' posts
+/// probe readings and nothing else, 'full' acts as the owner.
+pub const API_KEY_SCOPES: &[&str] = &["read_only", "ingest", "full"];
+/// API keys start with this, which is how `authenticate` tells them from
+/// access tokens.
+pub const API_KEY_PREFIX: &str = "ffk_";
+
+/// Mutations that work without signing in.
+const PUBLIC_MUTATIONS: &[&str] = &["login", "refreshToken", "__typename"];
+/// Query fields that work without signing in when public queries are off:
+/// monitoring probes and introspection.
+const OPEN_QUERIES: &[&str] = &["ping", "healthCheck", "__schema", "__type", "__typename"];
+
+/// The signed-in user, put in the GraphQL context by `authenticate`.
+/// Resolvers read it with `ctx.data_opt::<Identity>()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    /// The API key used, when the request came with one
+    #[serde(default)]
+    pub api_key_id: Option<Uuid>,
+}
+
+impl Identity {
+    pub fn is_owner(&self) -> bool {
+        self.role == "owner"
+    }
+
+    /// Who an API key acts as in GraphQL and the export routes. Ingest keys
+    /// have no identity there; they only post readings.
+    pub fn for_api_key(key: &ApiKey) -> Option<Identity> {
+        let role = match key.scope.as_str() {
+            "full" => "owner",
+            "read_only" => "read_only",
+            _ => return None,
+        };
+
+        Some(Identity {
+            user_id: key.created_by,
+            username: format!("{} (API key)", key.name),
+            role: role.to_string(),
+            api_key_id: Some(key.id),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: Uuid,
+    username: String,
+    role: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// Signing keys for access tokens, plus whether queries are open to anyone.
+///
+/// The secret comes from `JWT_SECRET` (at least 32 bytes). Without it, a
+/// random secret is used outside production, so tokens stop working when the
+/// server restarts. `AUTH_PUBLIC_QUERIES=true` lets read-only queries and
+/// subscriptions through without a token; mutations always need one.
+#[derive(Clone)]
+pub struct AuthConfig {
+    keys: Arc<(EncodingKey, DecodingKey)>,
+    pub public_queries: bool,
+}
+
+impl AuthConfig {
+    /// Keys from the validated `config`.
+    pub fn new(config: &Config) -> Self {
+        let secret = match &config.jwt_secret {
+            Some(secret) => secret.clone().into_bytes(),
+            None => {
+                tracing::warn!("🔑 JWT_SECRET not set; using a random secret until restart");
+                let mut secret = vec![0u8; 32];
+                OsRng.fill_bytes(&mut secret);
+                secret
+            }
+        };
+
+        AuthConfig {
+            keys: Arc::new((
+                EncodingKey::from_secret(&secret),
+                DecodingKey::from_secret(&secret),
+            )),
+            public_queries: config.public_queries,
+        }
+    }
+
+    /// Sign an access token for `user`, returning it with its expiry.
+    pub fn issue(
+        &self,
+        user: &User,
+    ) -> Result<(String, DateTime<Utc>), jsonwebtoken::errors::Error> {
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(ACCESS_TOKEN_MINUTES);
+        let claims = Claims {
+            sub: user.id,
+            username: user.username.clone(),
+            role: user.role.clone(),
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &self.keys.0)?;
+        Ok((token, expires_at))
+    }
+
+    /// The identity in a valid, unexpired access token.
+    pub fn verify(&self, token: &str) -> Option<Identity> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.keys.1, &Validation::default())
+            .ok()?
+            .claims;
+
+        Some(Identity {
+            user_id: claims.sub,
+            username: claims.username,
+            role: claims.role,
+            api_key_id: None,
+        })
+    }
+
+    /// Identity for a subscription socket, from `authToken` (or
+    /// `Authorization: Bearer ...`) in the `connection_init` payload.
+    /// A bad token refuses the connection; no token leaves it anonymous.
+    pub async fn connection_init(self, payload: serde_json::Value) -> async_graphql::Result<Data> {
+        let token = payload
+            .get("authToken")
+            .or_else(|| payload.get("Authorization"))
+            .and_then(|value| value.as_str())
+            .map(|value| value.trim_start_matches("Bearer ").trim());
+
+        let mut data = Data::default();
+        if let Some(token) = token {
+            let identity = self
+                .verify(token)
+                .ok_or_else(|| async_graphql::Error::new("Invalid or expired token"))?;
+            data.insert(identity);
+        }
+
+        Ok(data)
+    }
+}
+
+/// Middleware: validate the `Authorization: Bearer` access token or API key,
+/// if any, and attach its `Identity` to the request. A bad or expired token
+/// is a 401 so the client knows to refresh; no token carries on anonymously.
+pub async fn authenticate(mut request: Request, next: Next) -> Response {
+    let Some(token) = bearer(request.headers()) else {
+        return next.run(request).await;
+    };
+
+    if token.starts_with(API_KEY_PREFIX) {
+        let Some(pool) = request.extensions().get::<PgPool>() else {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Authentication is not configured",
+            )
+                .into_response();
+        };
+
+        return match find_api_key(pool, &token).await {
+            Ok(Some(key)) => match Identity::for_api_key(&key) {
+                Some(identity) => {
+                    request.extensions_mut().insert(identity);
+                    next.run(request).await
+                }
+                None => {
+                    (StatusCode::FORBIDDEN, "This API key can only post readings").into_response()
+                }
+            },
+            Ok(None) => (StatusCode::UNAUTHORIZED, "Invalid or revoked API key").into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+    }
+    let Some(config) = request.extensions().get::<AuthConfig>() else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Authentication is not configured",
+        )
+            .into_response();
+    };
+
+    match config.verify(&token) {
+        Some(identity) => {
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        None => (StatusCode::UNAUTHORIZED, "Invalid or expired token").into_response(),
+    }
+}
+
+/// Middleware for read-only HTTP routes: require a signed-in user unless
+/// public queries are on. Runs after `authenticate`.
+pub async fn require_reader(request: Request, next: Next) -> Response {
+    let public = request
+        .extensions()
+        .get::<AuthConfig>()
+        .is_some_and(|config| config.public_queries);
+
+    if public || request.extensions().get::<Identity>().is_some() {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, "Sign in required").into_response()
+    }
+}
+
+/// Extractor for handlers that take API keys directly, such as probe
+/// ingest. As `Option<ApiKeyAuth>` it is `None` when the bearer token isn't
+/// an API key, so the handler can fall back to other credentials; a
+/// revoked or unknown key is rejected either way.
+pub struct ApiKeyAuth(pub ApiKey);
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ApiKeyAuth {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let Some(token) = bearer(&parts.headers).filter(|t| t.starts_with(API_KEY_PREFIX)) else {
+            return Ok(None);
+        };
+        let Some(pool) = parts.extensions.get::<PgPool>() else {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Authentication is not configured",
+            )
+                .into_response());
+        };
+
+        match find_api_key(pool, &token).await {
+            Ok(Some(key)) => Ok(Some(ApiKeyAuth(key))),
+            Ok(None) => {
+                Err((StatusCode::UNAUTHORIZED, "Invalid or revoked API key").into_response())
+            }
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ApiKeyAuth {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        <Self as OptionalFromRequestParts<S>>::from_request_parts(parts, state)
+            .await?
+            .ok_or_else(|| (StatusCode::UNAUTHORIZED, "API key required").into_response())
+    }
+}
+
+fn bearer(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    value
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+/// Field guard: the caller must be the owner or have one of the roles, e.g.
+/// `#[graphql(guard = "RequireRole(auth::PRODUCTION)")]`. Roles are read from
+/// the access token, so a role change applies at the next refresh.
+pub struct RequireRole(pub &'static [&'static str]);
+
+impl Guard for RequireRole {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let Some(identity) = ctx.data_opt::<Identity>() else {
+            return Err("Sign in required".into());
+        };
+
+        if identity.is_owner() || self.0.contains(&identity.role.as_str()) {
+            Ok(())
+        } else {
+            Err(format!("The {} role can't do this", identity.role).into())
+        }
+    }
+}
+
+/// Schema extension that turns away anonymous operations: every mutation
+/// except `login` / `refreshToken`, and queries and subscriptions unless
+/// public queries are on. Health checks and introspection (for GraphiQL)
+/// stay open.
+pub struct RequireSignIn;
+
+impl ExtensionFactory for RequireSignIn {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequireSignInExtension)
+    }
+}
+
+struct RequireSignInExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RequireSignInExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        if ctx.data_opt::<Identity>().is_none() {
+            let public_queries = ctx
+                .data_opt::<AuthConfig>()
+                .is_some_and(|config| config.public_queries);
+
+            if needs_sign_in(&document, public_queries) {
+                return Err(ServerError::new("Sign in required", None));
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+fn needs_sign_in(document: &ExecutableDocument, public_queries: bool) -> bool {
+    document.operations.iter().any(|(_, operation)| {
+        let open = match operation.node.ty {
+            OperationType::Mutation => PUBLIC_MUTATIONS,
+            _ if public_queries => return false,
+            _ => OPEN_QUERIES,
+        };
+
+        operation
+            .node
+            .selection_set
+            .node
+            .items
+            .iter()
+            .any(|selection| match &selection.node {
+                Selection::Field(field) => !open.contains(&field.node.name.node.as_str()),
+                // Fragments at the root could hide anything
+                _ => true,
+            })
+    })
+}
+
+/// Hash a password for storage.
+pub fn hash_password(password: &str) -> Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Check a password against a stored hash.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|parsed| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok()
+    })
+}
+
+/// Look up an active user by username and password. Unknown users still pay
+/// for a hash so response times don't reveal which usernames exist.
+pub async fn login(
+    pool: &PgPool,
+    username: &str,
+    password: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    let account = sqlx::query!(
+        r#"
+        SELECT id, password_hash
+        FROM users
+        WHERE username = LOWER($1) AND is_active = true
+        "#,
+        username.trim()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(account) = account else {
+        let _ = hash_password(password);
+        return Ok(None);
+    };
+    if !verify_password(password, &account.password_hash) {
+        return Ok(None);
+    }
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users SET last_login_at = NOW()
+        WHERE id = $1
+        RETURNING id, username, display_name, role, is_active, last_login_at, created_at
+        "#,
+        account.id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Some(user))
+}
+
+/// An active user by ID.
+pub async fn find_user(pool: &PgPool, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, username, display_name, role, is_active, last_login_at, created_at
+        FROM users
+        WHERE id = $1 AND is_active = true
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create a refresh token for `user_id`. Only its hash is stored.
+pub async fn issue_refresh(conn: &mut PgConnection, user_id: Uuid) -> Result<String, sqlx::Error> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(days => $3))
+        "#,
+        user_id,
+        token_hash(&token),
+        REFRESH_TOKEN_DAYS as i32
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// Use up a refresh token, returning its user. Presenting a token that was
+/// already used means it leaked, so every token for that user is revoked.
+pub async fn redeem_refresh(
+    conn: &mut PgConnection,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let stored = sqlx::query!(
+        r#"
+        SELECT id, user_id, revoked_at, expires_at > NOW() AS "live!"
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        token_hash(token)
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+
+    if stored.revoked_at.is_some() {
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            stored.user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1",
+        stored.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(stored.live.then_some(stored.user_id))
+}
+
+/// Revoke a refresh token (sign out). False if it wasn't live.
+pub async fn revoke_refresh(pool: &PgPool, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE refresh_tokens SET revoked_at = NOW()
+        WHERE token_hash = $1 AND revoked_at IS NULL
+        "#,
+        token_hash(token)
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Create an API key, returning it with the key itself, which is never
+/// stored and can't be shown again.
+pub async fn create_api_key(
+    pool: &PgPool,
+    name: &str,
+    scope: &str,
+    created_by: Uuid,
+) -> Result<(ApiKey, String), sqlx::Error> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let key = format!("{}{}", API_KEY_PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (name, prefix, key_hash, scope, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, name, prefix, scope, created_by, last_used_at, revoked_at, created_at
+        "#,
+        name,
+        &key[..API_KEY_PREFIX.len() + 6],
+        token_hash(&key),
+        scope,
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((api_key, key))
+}
+
+/// The live API key matching `key`, marking it used.
+pub async fn find_api_key(pool: &PgPool, key: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE key_hash = $1 AND revoked_at IS NULL
+        RETURNING id, name, prefix, scope, created_by, last_used_at, revoked_at, created_at
+        "#,
+        token_hash(key)
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// How refresh tokens, API keys and action link tokens are stored.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Create the owner from `ADMIN_USERNAME` / `ADMIN_PASSWORD` when there are
+/// no users yet, so a fresh install can sign in.
+pub async fn bootstrap_admin(pool: &PgPool, config: &Config) -> anyhow::Result<()> {
+    let Some((username, password)) = &config.admin else {
+        return Ok(());
+    };
+
+    let hash = hash_password(password).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO users (username, password_hash, role)
+        SELECT LOWER($1), $2, 'owner'
+        WHERE NOT EXISTS (SELECT 1 FROM users)
+        "#,
+        username.trim(),
+        hash
+    )
+    .execute(pool)
+    .await?;
+
+    if created.rows_affected() > 0 {
+        tracing::info!("👤 Created owner '{}'", username.trim().to_lowercase());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+
+use anyhow::{Context, bail};
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::services::changes::TRACKED_ENTITIES;
+
+/// Marks a file as one of our archives.
+pub const FORMAT: &str = "frederick-ferments-backup";
+/// Archive layout version. Bump it when the layout (not the schema) changes.
+pub const VERSION: i32 = 1;
+
+/// Untracked rows that hang off tracked tables and would be deleted with
+/// their parents on restore (catalog price changes, stock count photos).
+const CHILD_TABLES: &[&str] = &["supplier_catalog_changes", "stock_count_photos"];
+
+/// Untracked tables archived on their own. Without the number counters a
+/// restored database would start its batch, sale and order numbers over.
+/// Label runs (printed PDFs, made again from the data) and dashboards
+/// (which belong to users, who aren't archived) are left out.
+const STANDALONE_TABLES: &[&str] = &["number_sequences"];
+
+/// How rows are ordered in the archive; tables without an `id` name their key.
+fn row_order(table: &str) -> &'static str {
+    match table {
+        "number_sequences" => "t.kind, t.scope",
+        _ => "t.id",
+    }
+}
+
+const USAGE: &str = "usage: frederick-ferments-api backup [FILE] | restore FILE --replace | validate-data | backfill-product-codes | backfill-log-costs";
+
+/// Run `backup [FILE]` or `restore FILE --replace` from the command line.
+/// Backups go to stdout when no file is given; progress goes to stderr.
+pub async fn run(pool: &PgPool, args: &[String]) -> anyhow::Result<()> {
+    match args {
+        [command, rest @ ..] if command == "backup" && rest.len() <= 1 => {
+            let counts = match rest.first() {
+                Some(path) => {
+                    let file = File::create(path).with_context(|| format!("creating {}", path))?;
+                    backup(pool, &mut BufWriter::new(file)).await?
+                }
+                None => backup(pool, &mut BufWriter::new(std::io::stdout().lock())).await?,
+            };
+
+            let rows: i64 = counts.iter().map(|(_, count)| count).sum();
+            eprintln!("💾 Backed up {} rows from {} tables", rows, counts.len());
+            Ok(())
+        }
+        [command, path, flag] if command == "restore" && flag == "--replace" => {
+            let mut archive = String::new();
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut archive))
+                .with_context(|| format!("reading {}", path))?;
+
+            let counts = restore(pool, archive).await?;
+            for (table, count) in &counts {
+                eprintln!("   {}: {}", table, count);
+            }
+            let rows: u64 = counts.iter().map(|(_, count)| count).sum();
+            eprintln!("♻️  Restored {} rows into {} tables", rows, counts.len());
+            Ok(())
+        }
+        [command, _] if command == "restore" => {
+            bail!(
+                "restore replaces every domain table; pass --replace to confirm\n{}",
+                USAGE
+            )
+        }
+        _ => bail!(USAGE),
+    }
+}
+
+/// Every table an archive holds, in the change feed's order.
+fn tables() -> Vec<&'static str> {
+    TRACKED_ENTITIES
+        .iter()
+        .chain(CHILD_TABLES)
+        .chain(STANDALONE_TABLES)
+        .copied()
+        .collect()
+}
+
+/// Write every domain table to `out` as one JSON archive:
+/// `{"format", "version", "created_at", "tables": {"suppliers": [rows], ...}}`.
+/// Rows are read in one snapshot, so the archive is consistent. Users, API
+/// keys, secrets, the audit trail, telemetry, label PDFs and dashboards are
+/// left out. Returns the
+/// row count per table.
+pub async fn backup(pool: &PgPool, out: &mut impl Write) -> anyhow::Result<Vec<(String, i64)>> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    write!(
+        out,
+        "{{\"format\":\"{}\",\"version\":{},\"created_at\":\"{}\",\"tables\":{{",
+        FORMAT,
+        VERSION,
+        Utc::now().to_rfc3339()
+    )?;
+
+    let mut counts = Vec::new();
+    for (i, table) in tables().into_iter().enumerate() {
+        // Numbers are rendered by Postgres, so decimals keep their precision
+        let (count, rows): (i64, String) = sqlx::query_as(&format!(
+            "SELECT COUNT(*), COALESCE(json_agg(t ORDER BY {}), '[]')::text FROM {} t",
+            row_order(table),
+            table
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        write!(out, "\n\"{}\":{}", table, rows)?;
+        counts.push((table.to_string(), count));
+    }
+
+    out.write_all(b"\n}}\n")?;
+    out.flush()?;
+    tx.commit().await?;
+
+    Ok(counts)
+}
+
+/// Replace every domain table with the rows in `archive`, in one
+/// transaction. Columns the archive lacks take their defaults, and links to
+/// users that don't exist here are cleared. Returns the rows restored per table.
+pub async fn restore(pool: &PgPool, archive: String) -> anyhow::Result<Vec<(String, u64)>> {
+    let tables = tables();
+    let mut tx = pool.begin().await?;
+
+    // Parse once on the server: jsonb keeps decimals exact
+    sqlx::query("CREATE TEMP TABLE backup_archive (doc JSONB NOT NULL) ON COMMIT DROP")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO backup_archive VALUES ($1::jsonb)")
+        .bind(archive)
+        .execute(&mut *tx)
+        .await
+        .context("the file isn't valid JSON")?;
+
+    let (format, version, archived): (Option<String>, Option<i32>, Vec<String>) = sqlx::query_as(
+        r#"
+        SELECT doc->>'format', (doc->>'version')::int,
+            ARRAY(SELECT jsonb_object_keys(COALESCE(doc->'tables', '{}')))
+        FROM backup_archive
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if format.as_deref() != Some(FORMAT) {
+        bail!("not a {} archive", FORMAT);
+    }
+    match version {
+        Some(version) if version > VERSION => bail!(
+            "archive version {} is newer than this build reads ({}); upgrade first",
+            version,
+            VERSION
+        ),
+        None => bail!("archive has no version"),
+        _ => {}
+    }
+    if let Some(unknown) = archived.iter().find(|t| !tables.contains(&t.as_str())) {
+        bail!("archive has a table this build doesn't know: {}", unknown);
+    }
+
+    let order = insert_order(&mut tx, &tables).await?;
+
+    // Children first, so nothing is left pointing at a deleted parent
+    for table in order.iter().rev() {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let mut counts = Vec::new();
+    for table in order {
+        let columns = sqlx::query_scalar!(
+            r#"
+            SELECT attname::text AS "column!"
+            FROM pg_attribute
+            WHERE attrelid = $1::text::regclass AND attnum > 0
+                AND NOT attisdropped AND attgenerated = ''
+            ORDER BY attnum
+            "#,
+            table
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let present: HashSet<String> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT jsonb_object_keys(row)
+            FROM backup_archive, jsonb_array_elements(COALESCE(doc->'tables'->$1, '[]')) row
+            "#,
+        )
+        .bind(table)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let columns: Vec<String> = columns
+            .into_iter()
+            .filter(|column| present.contains(column))
+            .collect();
+        if columns.is_empty() {
+            counts.push((table.to_string(), 0));
+            continue;
+        }
+
+        // Links outside the archive (e.g. who placed a purchase order) only
+        // survive where that row exists in this database
+        let outside = sqlx::query!(
+            r#"
+            SELECT a.attname::text AS "column!", c.confrelid::regclass::text AS "ref_table!",
+                ra.attname::text AS "ref_column!"
+            FROM pg_constraint c
+            JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+            JOIN pg_attribute ra ON ra.attrelid = c.confrelid AND ra.attnum = c.confkey[1]
+            WHERE c.contype = 'f' AND c.conrelid = $1::text::regclass
+                AND cardinality(c.conkey) = 1
+                AND NOT (c.confrelid::regclass::text = ANY($2))
+            "#,
+            table,
+            &tables.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let values: Vec<String> = columns
+            .iter()
+            .map(
+                |column| match outside.iter().find(|fk| &fk.column == column) {
+                    Some(fk) => format!(
+                        "(SELECT {rc} FROM {rt} WHERE {rc} = r.{c})",
+                        rc = ident(&fk.ref_column),
+                        rt = fk.ref_table,
+                        c = ident(column)
+                    ),
+                    None => format!("r.{}", ident(column)),
+                },
+            )
+            .collect();
+
+        let restored = sqlx::query(&format!(
+            r#"
+            INSERT INTO {table} ({columns})
+            SELECT {values}
+            FROM backup_archive, jsonb_populate_recordset(NULL::{table}, doc->'tables'->$1) r
+            "#,
+            table = table,
+            columns = columns
+                .iter()
+                .map(|c| ident(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            values = values.join(", ")
+        ))
+        .bind(table)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("restoring {}", table))?;
+
+        counts.push((table.to_string(), restored.rows_affected()));
+    }
+
+    tx.commit().await?;
+
+    Ok(counts)
+}
+
+/// `tables` with every table after the ones it references, keeping the
+/// given order where foreign keys don't decide it.
+async fn insert_order(
+    conn: &mut sqlx::PgConnection,
+    tables: &[&'static str],
+) -> anyhow::Result<Vec<&'static str>> {
+    let references = sqlx::query!(
+        r#"
+        SELECT conrelid::regclass::text AS "table!", confrelid::regclass::text AS "references!"
+        FROM pg_constraint
+        WHERE contype = 'f' AND conrelid <> confrelid
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut order: Vec<&'static str> = Vec::with_capacity(tables.len());
+    while order.len() < tables.len() {
+        let next = tables.iter().find(|table| {
+            !order.contains(table)
+                && references.iter().all(|fk| {
+                    fk.table != **table
+                        || !tables.contains(&fk.references.as_str())
+                        || order.contains(&fk.references.as_str())
+                })
+        });
+
+        match next {
+            Some(table) => order.push(table),
+            None => bail!("foreign keys between archived tables form a cycle"),
+        }
+    }
+
+    Ok(order)
+}
+
+/// A quoted SQL identifier.
+fn ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
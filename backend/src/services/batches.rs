@@ -0,0 +1,382 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::models::{
+    BatchAlert, CreateProductionBatchInput, MutationError, OverdueBatch, Rejection,
+};
+use crate::services::calendar;
+use crate::services::config;
+use crate::services::locations;
+use crate::services::lots::{self, LotDraw, LotUsage};
+use crate::services::numbering;
+use crate::services::planning;
+use crate::services::stages;
+use crate::services::tasks;
+use crate::services::vessels::{self, Claim};
+
+/// A batch that has been started.
+pub struct Started {
+    pub batch_id: Uuid,
+    pub batch_number: String,
+    /// Calendar closures on the start or estimated completion date
+    pub warnings: Vec<String>,
+}
+
+/// Start a production batch: check the product and ingredients, number the
+/// batch, claim its vessel, enter its first stage, schedule its tasks, and
+/// consume its ingredients from stock. Returns the reason if it can't start.
+pub async fn start(
+    conn: &mut PgConnection,
+    input: &CreateProductionBatchInput,
+    now: DateTime<Utc>,
+) -> Result<Result<Started, Rejection>, sqlx::Error> {
+    // Validate batch size is positive
+    if input.batch_size <= BigDecimal::from(0) {
+        return Ok(Err("Batch size must be greater than 0".to_string().into()));
+    }
+
+    // 1. Validate product exists
+    let product = sqlx::query!(
+        "SELECT name, product_code, discontinued_at FROM inventory WHERE id = $1 AND is_active = true",
+        input.product_inventory_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(product) = product else {
+        return Ok(Err(Rejection::new(
+            "Product not found or is inactive".to_string(),
+            MutationError::not_found("Product"),
+        )));
+    };
+
+    // Discontinued products sell through what's left; no new batches
+    if product.discontinued_at.is_some() {
+        return Ok(Err(format!("{} has been discontinued", product.name).into()));
+    }
+
+    // 2. Validate all ingredients exist and have sufficient stock
+    for ingredient in &input.ingredients {
+        if ingredient.quantity_used <= BigDecimal::from(0) {
+            return Ok(Err("All ingredient quantities must be greater than 0"
+                .to_string()
+                .into()));
+        }
+
+        let inv = sqlx::query!(
+            "SELECT name, current_stock FROM inventory WHERE id = $1 AND is_active = true",
+            ingredient.inventory_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match inv {
+            None => {
+                return Ok(Err(Rejection::new(
+                    format!(
+                        "Ingredient with ID {} not found or is inactive",
+                        ingredient.inventory_id
+                    ),
+                    MutationError::not_found("Ingredient"),
+                )));
+            }
+            Some(inv_item) => {
+                if inv_item.current_stock < ingredient.quantity_used {
+                    return Ok(Err(Rejection::new(
+                        format!(
+                            "Insufficient stock for {}: need {}, have {}",
+                            inv_item.name, ingredient.quantity_used, inv_item.current_stock
+                        ),
+                        MutationError::insufficient_stock(
+                            ingredient.inventory_id,
+                            &inv_item.name,
+                            &ingredient.quantity_used,
+                            &inv_item.current_stock,
+                        ),
+                    )));
+                }
+            }
+        }
+    }
+
+    // Trial batches belong to a product still in development
+    if let Some(development_id) = input.development_id {
+        let stage = sqlx::query_scalar!(
+            "SELECT stage FROM product_developments WHERE id = $1",
+            development_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match stage.as_deref() {
+            None => {
+                return Ok(Err(Rejection::new(
+                    "Product development not found".to_string(),
+                    MutationError::not_found("Product development"),
+                )));
+            }
+            Some(stage @ ("launched" | "shelved")) => {
+                return Ok(Err(format!(
+                    "Cannot make trial batches of a {} product",
+                    stage
+                )
+                .into()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let storage_location_id = match locations::for_batch(
+        &mut *conn,
+        input.storage_location_id,
+        input.storage_location.as_deref(),
+    )
+    .await?
+    {
+        Ok(location_id) => location_id,
+        Err(message) => return Ok(Err(message.into())),
+    };
+
+    // 3. Number the batch (BATCH_NUMBER_FORMAT)
+    let product_code = product
+        .product_code
+        .clone()
+        .unwrap_or_else(|| numbering::suggested_code(&product.name));
+    let batch_number = numbering::next(
+        &mut *conn,
+        numbering::Kind::Batch,
+        &config::batch_number_format(),
+        now.date_naive(),
+        &product_code,
+    )
+    .await?;
+
+    // 4. Create production_batch record
+    let batch_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO production_batches (
+            batch_number, product_inventory_id, recipe_template_id, batch_size, unit,
+            start_date, estimated_completion_date, production_date, status,
+            storage_location_id, development_id, notes
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id
+        "#,
+        batch_number,
+        input.product_inventory_id,
+        input.recipe_template_id,
+        input.batch_size,
+        input.unit,
+        now,
+        input.estimated_completion_date,
+        now,           // Legacy field
+        "in_progress", // Start as in-progress, complete manually later
+        storage_location_id,
+        input.development_id,
+        input.notes
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // Put the batch in its vessel (fails if the vessel is taken)
+    if let Some(vessel_id) = input.vessel_id
+        && let Claim::Unavailable(message) = vessels::claim(&mut *conn, vessel_id, batch_id).await?
+    {
+        return Ok(Err(message.into()));
+    }
+
+    // Take the batch off the production plan
+    if let Some(planned_batch_id) = input.planned_batch_id
+        && let Err(message) = planning::mark_started(&mut *conn, planned_batch_id, batch_id).await?
+    {
+        return Ok(Err(message.into()));
+    }
+
+    // Enter the first aging stage if the recipe defines stages
+    stages::enter_first(&mut *conn, batch_id, input.recipe_template_id, now).await?;
+
+    // Schedule the recipe's chores (burping, brine checks, transfers)
+    tasks::generate(&mut *conn, Some(batch_id), None).await?;
+
+    // 5. Process each ingredient: consume stock and log
+    for ingredient in &input.ingredients {
+        // Draw the quantity from the ingredient's open lots
+        let draws = lots::consume(
+            &mut *conn,
+            ingredient.inventory_id,
+            &ingredient.quantity_used,
+        )
+        .await?;
+
+        add_ingredient(
+            &mut *conn,
+            batch_id,
+            &batch_number,
+            ingredient.inventory_id,
+            &ingredient.quantity_used,
+            &draws,
+            None,
+            now,
+        )
+        .await?;
+    }
+
+    // The calendar is advisory: flag closures instead of refusing the batch
+    let mut warnings = Vec::new();
+    warnings
+        .extend(calendar::production_warning(&mut *conn, now.date_naive(), "Start date").await?);
+    if let Some(estimated) = input.estimated_completion_date {
+        warnings.extend(
+            calendar::production_warning(
+                &mut *conn,
+                estimated.date_naive(),
+                "Estimated completion",
+            )
+            .await?,
+        );
+    }
+
+    Ok(Ok(Started {
+        batch_id,
+        batch_number,
+        warnings,
+    }))
+}
+
+/// Record an ingredient going into a batch whose lots have already been drawn:
+/// add the ingredient line, take it out of stock, trace the lots, and log it.
+#[allow(clippy::too_many_arguments)]
+pub async fn add_ingredient(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    batch_number: &str,
+    inventory_id: Uuid,
+    quantity: &BigDecimal,
+    draws: &[LotDraw],
+    notes: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    // Get ingredient unit
+    let unit = sqlx::query_scalar!("SELECT unit FROM inventory WHERE id = $1", inventory_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // Create production_batch_ingredients record
+    sqlx::query!(
+        "INSERT INTO production_batch_ingredients (batch_id, ingredient_inventory_id, quantity_used, unit, notes) VALUES ($1, $2, $3, $4, $5)",
+        batch_id,
+        inventory_id,
+        quantity,
+        unit,
+        notes
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Decrease ingredient stock
+    sqlx::query!(
+        "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+        quantity,
+        now,
+        inventory_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    lots::record_usage(&mut *conn, draws, LotUsage::ProductionBatch(batch_id)).await?;
+
+    // Log ingredient consumption
+    sqlx::query!(
+        r#"
+        INSERT INTO inventory_logs (
+            inventory_id, movement_type, quantity, reason, batch_number, created_at
+        ) VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        inventory_id,
+        "production_use",
+        -quantity.clone(), // Negative because it's consumption
+        notes.map_or_else(
+            || format!("Used in production batch {}", batch_number),
+            |notes| format!("{} into production batch {}", notes, batch_number)
+        ),
+        batch_number,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Batches still in progress at least `min_days_overdue` whole days past
+/// their estimated completion date, most overdue first.
+pub async fn overdue<'e>(
+    executor: impl PgExecutor<'e>,
+    min_days_overdue: i32,
+) -> Result<Vec<OverdueBatch>, sqlx::Error> {
+    sqlx::query_as!(
+        OverdueBatch,
+        r#"
+        SELECT
+            b.id AS batch_id, b.batch_number, b.product_inventory_id,
+            i.name AS product_name, b.start_date,
+            b.estimated_completion_date AS "estimated_completion_date!",
+            EXTRACT(DAY FROM NOW() - b.estimated_completion_date)::int AS "days_overdue!",
+            l.name AS "storage_location?", v.name AS "vessel_name?",
+            (
+                SELECT MAX(a.created_at) FROM batch_alerts a
+                WHERE a.batch_id = b.id AND a.alert_type = 'overdue' AND a.acknowledged_at IS NULL
+            ) AS flagged_at
+        FROM production_batches b
+        JOIN inventory i ON i.id = b.product_inventory_id
+        LEFT JOIN locations l ON l.id = b.storage_location_id
+        LEFT JOIN vessels v ON v.id = b.vessel_id
+        WHERE b.status = 'in_progress'
+            AND b.estimated_completion_date < NOW() - make_interval(days => $1)
+        ORDER BY b.estimated_completion_date
+        "#,
+        min_days_overdue.max(0)
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Flag batches `after_days` or more past their estimated completion with an
+/// 'overdue' batch alert, so they show with the batch and in `batchAlerts`
+/// until someone acknowledges them. A batch is flagged once per estimated
+/// completion date; moving the date out lets it be flagged again. Returns
+/// the new alerts.
+pub async fn flag_overdue<'e>(
+    executor: impl PgExecutor<'e>,
+    after_days: i32,
+) -> Result<Vec<BatchAlert>, sqlx::Error> {
+    sqlx::query_as!(
+        BatchAlert,
+        r#"
+        INSERT INTO batch_alerts (batch_id, alert_type, message, measured_value, threshold)
+        SELECT
+            b.id, 'overdue',
+            'Batch ' || b.batch_number || ': still in progress '
+                || EXTRACT(DAY FROM NOW() - b.estimated_completion_date)::int
+                || ' days after its estimated completion',
+            EXTRACT(DAY FROM NOW() - b.estimated_completion_date)::int,
+            $1::int
+        FROM production_batches b
+        WHERE b.status = 'in_progress'
+            AND b.estimated_completion_date < NOW() - make_interval(days => $1::int)
+            AND NOT EXISTS (
+                SELECT 1 FROM batch_alerts a
+                WHERE a.batch_id = b.id
+                    AND a.alert_type = 'overdue'
+                    AND a.created_at > b.estimated_completion_date
+            )
+        RETURNING
+            id, batch_id, reading_id, alert_type, message, measured_value,
+            threshold, acknowledged_at, created_at
+        "#,
+        after_days.max(0)
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use sqlx::PgExecutor;
+
+use crate::models::CalendarEvent;
+
+pub const KINDS: [&str; 3] = ["market", "holiday", "blackout"];
+
+/// What a day is being checked for.
+#[derive(Debug, Clone, Copy)]
+pub enum Purpose {
+    /// Starting, moving, or finishing batches
+    Production,
+    /// Deliveries and order pickups
+    Delivery,
+}
+
+impl Purpose {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "production" => Some(Purpose::Production),
+            "delivery" => Some(Purpose::Delivery),
+            _ => None,
+        }
+    }
+
+    /// Name understood by the next_open_day() SQL function.
+    pub fn name(self) -> &'static str {
+        match self {
+            Purpose::Production => "production",
+            Purpose::Delivery => "delivery",
+        }
+    }
+
+    fn blocked_by(self, event: &CalendarEvent) -> bool {
+        match self {
+            Purpose::Production => event.blocks_production,
+            Purpose::Delivery => event.blocks_deliveries,
+        }
+    }
+}
+
+/// Default (blocks_production, blocks_deliveries) for an entry kind.
+pub fn default_blocks(kind: &str) -> (bool, bool) {
+    match kind {
+        "holiday" => (true, true),
+        "blackout" => (true, false),
+        _ => (false, false),
+    }
+}
+
+/// Check a calendar entry before saving it.
+pub fn validate(
+    kind: &str,
+    name: &str,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<(), String> {
+    if !KINDS.contains(&kind) {
+        return Err(format!("Kind must be one of: {}", KINDS.join(", ")));
+    }
+
+    if name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+
+    if end_date < start_date {
+        return Err("End date cannot be before start date".to_string());
+    }
+
+    Ok(())
+}
+
+/// Calendar entries covering a day.
+pub async fn events_on<'e>(
+    executor: impl PgExecutor<'e>,
+    date: NaiveDate,
+) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    sqlx::query_as!(
+        CalendarEvent,
+        r#"
+        SELECT
+            id, kind, name, start_date, end_date, blocks_production,
+            blocks_deliveries, notes, created_at, updated_at
+        FROM business_calendar
+        WHERE $1 BETWEEN start_date AND end_date
+        ORDER BY start_date, name
+        "#,
+        date
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Calendar entries that close a day for the given purpose.
+pub async fn closures<'e>(
+    executor: impl PgExecutor<'e>,
+    date: NaiveDate,
+    purpose: Purpose,
+) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    let mut events = events_on(executor, date).await?;
+    events.retain(|event| purpose.blocked_by(event));
+    Ok(events)
+}
+
+/// First day on or after `from` that's open for the given purpose, or None
+/// if the calendar keeps it closed for over a year.
+pub async fn next_open_day<'e>(
+    executor: impl PgExecutor<'e>,
+    from: NaiveDate,
+    purpose: Purpose,
+) -> Result<Option<NaiveDate>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT next_open_day($1, $2)", from, purpose.name())
+        .fetch_one(executor)
+        .await
+}
+
+/// Warning for a production date that the calendar closes, if it does.
+pub async fn production_warning<'e>(
+    executor: impl PgExecutor<'e>,
+    date: NaiveDate,
+    what: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let closed = closures(executor, date, Purpose::Production).await?;
+
+    if closed.is_empty() {
+        return Ok(None);
+    }
+
+    let names: Vec<&str> = closed.iter().map(|event| event.name.as_str()).collect();
+
+    Ok(Some(format!(
+        "{} {} falls on a production closure ({})",
+        what,
+        date,
+        names.join(", ")
+    )))
+}
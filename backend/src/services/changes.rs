@@ -0,0 +1,196 @@
+use sqlx::PgPool;
+
+use crate::models::{ChangeRecord, ChangeSet};
+
+/// Tables whose changes are exported. Keep in sync with the trigger list in init.sql.
+pub const TRACKED_ENTITIES: &[&str] = &[
+    "suppliers",
+    "units",
+    "inventory_categories",
+    "locations",
+    "inventory",
+    "inventory_logs",
+    "inventory_lots",
+    "supplier_contracts",
+    "supplier_contract_items",
+    "purchase_orders",
+    "purchase_order_items",
+    "purchase_approval_limits",
+    "supplier_catalog_items",
+    "supplier_catalog_imports",
+    "stock_counts",
+    "product_translations",
+    "recipe_templates",
+    "recipe_ingredients",
+    "recipe_cost_watches",
+    "overhead_pools",
+    "product_developments",
+    "product_development_stages",
+    "vessels",
+    "sanitation_logs",
+    "production_batches",
+    "production_batch_ingredients",
+    "production_batch_packaging",
+    "production_batch_outputs",
+    "pack_sizes",
+    "kit_components",
+    "kit_assemblies",
+    "storage_transition_rules",
+    "recipe_stages",
+    "batch_stages",
+    "recipe_task_templates",
+    "batch_tasks",
+    "planned_batches",
+    "plan_allocations",
+    "batch_move_tasks",
+    "batch_readings",
+    "batch_alerts",
+    "quality_checks",
+    "business_calendar",
+    "markets",
+    "market_sessions",
+    "market_session_staff",
+    "customers",
+    "sales",
+    "sale_items",
+    "promo_codes",
+    "sale_discounts",
+    "invoices",
+    "invoice_payments",
+    "customer_orders",
+    "customer_order_items",
+    "sale_channel_policies",
+    "price_list_items",
+    "customer_prices",
+    "square_item_mappings",
+    "shopify_products",
+    "pickup_windows",
+    "pickup_bookings",
+    "lot_consumptions",
+    "workshops",
+    "workshop_kit_items",
+    "workshop_attendees",
+    "complaints",
+    "complaint_lot_reviews",
+    "trace_page_fields",
+];
+
+pub const DEFAULT_LIMIT: i64 = 1000;
+pub const MAX_LIMIT: i64 = 10_000;
+
+/// Position in the change log: (writing transaction ID, change log ID).
+///
+/// Change log IDs are handed out before commit, so a consumer paging by ID
+/// alone could skip a row from a slow transaction that commits after a later
+/// ID was read. Changes are instead only released once every transaction
+/// older than theirs has finished, and ordered by transaction first, so a
+/// cursor never moves past a change that can still appear.
+///
+/// Domain events are paged the same way (`services/events.rs`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cursor {
+    pub(crate) txid: i64,
+    pub(crate) id: i64,
+}
+
+impl Cursor {
+    /// Parse a cursor from a previous page; None or "" means the beginning.
+    pub fn parse(cursor: Option<&str>) -> Result<Self, String> {
+        let Some(cursor) = cursor.filter(|c| !c.is_empty()) else {
+            return Ok(Self::default());
+        };
+
+        cursor
+            .split_once('-')
+            .and_then(|(txid, id)| {
+                Some(Self {
+                    txid: txid.parse().ok()?,
+                    id: id.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| format!("Invalid cursor '{}'", cursor))
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.txid, self.id)
+    }
+}
+
+/// Check an entity filter against the tracked tables.
+pub fn validate_entities(entities: &[String]) -> Result<(), String> {
+    match entities
+        .iter()
+        .find(|e| !TRACKED_ENTITIES.contains(&e.as_str()))
+    {
+        Some(unknown) => Err(format!(
+            "Unknown entity '{}'; expected one of: {}",
+            unknown,
+            TRACKED_ENTITIES.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Changes after `since`, oldest first.
+pub async fn fetch(
+    pool: &PgPool,
+    since: Cursor,
+    entities: Option<&[String]>,
+    limit: i64,
+) -> Result<ChangeSet, sqlx::Error> {
+    let limit = limit.clamp(1, MAX_LIMIT);
+
+    // Fetch one extra row to learn whether there's another page
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT id, txid, entity, entity_id, operation, row_data, changed_at
+        FROM change_log
+        WHERE (txid, id) > ($1, $2)
+            AND txid < pg_snapshot_xmin(pg_current_snapshot())::text::bigint
+            AND ($3::text[] IS NULL OR entity = ANY($3))
+        ORDER BY txid, id
+        LIMIT $4
+        "#,
+        since.txid,
+        since.id,
+        entities,
+        limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+
+    let next_cursor = rows
+        .last()
+        .map(|row| Cursor {
+            txid: row.txid,
+            id: row.id,
+        })
+        .unwrap_or(since);
+
+    let changes = rows
+        .into_iter()
+        .map(|row| ChangeRecord {
+            cursor: Cursor {
+                txid: row.txid,
+                id: row.id,
+            }
+            .to_string(),
+            entity: row.entity,
+            entity_id: row.entity_id,
+            operation: row.operation,
+            changed_at: row.changed_at,
+            data: row.row_data,
+        })
+        .collect();
+
+    Ok(ChangeSet {
+        changes,
+        next_cursor: next_cursor.to_string(),
+        has_more,
+    })
+}
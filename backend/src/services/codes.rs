@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextPrepareRequest,
+};
+use async_graphql::parser::types::{
+    DocumentOperations, ExecutableDocument, OperationDefinition, OperationType, Selection,
+};
+use async_graphql::{Name, Request, ServerError, ServerResult, Variables};
+use async_graphql_value::{ConstValue, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::ScannedCode;
+
+/// Prefix of internal lot codes, so a scan can be told from a supplier's
+/// barcode at a glance.
+pub const LOT_CODE_PREFIX: &str = "FFL-";
+
+/// Crockford base32: no I, L, O or U to misread when typed in by hand.
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// A new internal lot code, e.g. `FFL-7K3M9QX2AB` (50 random bits).
+pub fn new_lot_code() -> String {
+    let random = Uuid::new_v4().as_u128();
+    let code: String = (0..10)
+        .map(|i| CODE_ALPHABET[((random >> (i * 5)) & 31) as usize] as char)
+        .collect();
+    format!("{}{}", LOT_CODE_PREFIX, code)
+}
+
+/// What a scanned or typed code stands for: an internal lot code, a batch
+/// number (or the trace page address printed on its jars), or the ID of a
+/// lot, batch or inventory item. Lot codes are matched case-insensitively.
+pub async fn resolve(pool: &PgPool, code: &str) -> Result<Option<ScannedCode>, sqlx::Error> {
+    let code = code.trim();
+    let code = code.rsplit_once("/trace/").map_or(code, |(_, batch)| batch);
+
+    sqlx::query_as!(
+        ScannedCode,
+        r#"
+        SELECT code AS "code!", kind AS "kind!", lot_id, production_batch_id, inventory_id
+        FROM (
+            SELECT 1 AS priority, $1 AS code, 'lot' AS kind, id AS lot_id,
+                production_batch_id, inventory_id
+            FROM inventory_lots
+            WHERE code = upper($1) OR id::text = lower($1)
+            UNION ALL
+            SELECT 2, $1, 'batch', NULL, id, product_inventory_id
+            FROM production_batches
+            WHERE batch_number = $1 OR id::text = lower($1)
+            UNION ALL
+            SELECT 3, $1, 'item', NULL, NULL, id
+            FROM inventory
+            WHERE id::text = lower($1)
+        ) matches
+        ORDER BY priority
+        LIMIT 1
+        "#,
+        code
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// What an ID argument expects a scanned code to give.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Target {
+    Item,
+    Lot,
+}
+
+impl Target {
+    /// `inventoryId`, `productInventoryId`, ... take items; `lotId`,
+    /// `parentLotId`, ... take lots.
+    fn of(key: &str) -> Option<Self> {
+        if key == "inventoryId" || key.ends_with("InventoryId") {
+            Some(Target::Item)
+        } else if key == "lotId" || key.ends_with("LotId") {
+            Some(Target::Lot)
+        } else {
+            None
+        }
+    }
+}
+
+/// Schema extension that lets mutations take a scanned code wherever they take
+/// an inventory item or lot ID. Strings under `inventoryId` / `lotId` style
+/// keys that aren't UUIDs are looked up with `resolve` and swapped for the ID
+/// they stand for, in variables and in literal arguments alike. A lot code
+/// given for an item stands for the lot's item, and a batch number for the
+/// batch's product. Unknown codes fail the request before anything runs.
+pub struct ScannedCodes;
+
+impl ExtensionFactory for ScannedCodes {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ScannedCodesExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct ScannedCodesExtension {
+    /// Codes resolved in `prepare_request`, applied to literals once parsed
+    resolved: Mutex<HashMap<(Target, String), Uuid>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for ScannedCodesExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        mut request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        // Malformed queries are reported by the normal parse
+        let (Ok(document), Some(pool)) = (
+            async_graphql::parser::parse_query(&request.query),
+            ctx.data_opt::<PgPool>(),
+        ) else {
+            return next.run(ctx, request).await;
+        };
+
+        let mut codes = Vec::new();
+        let mut variables = Vec::new();
+        for (_, operation) in document.operations.iter() {
+            if operation.node.ty != OperationType::Mutation {
+                continue;
+            }
+            for (name, argument) in arguments(&operation.node) {
+                literal_codes(argument, Target::of(name), &mut codes, &mut variables);
+            }
+        }
+        for (name, target) in &variables {
+            if let Some(value) = request.variables.get(name) {
+                variable_codes(value, *target, &mut codes);
+            }
+        }
+
+        if codes.is_empty() {
+            return next.run(ctx, request).await;
+        }
+
+        let mut resolved = HashMap::new();
+        for (target, code) in codes {
+            if resolved.contains_key(&(target, code.clone())) {
+                continue;
+            }
+
+            let scanned = resolve(pool, &code)
+                .await
+                .map_err(|e| ServerError::new(format!("Failed to look up code: {}", e), None))?;
+            let id = scanned.and_then(|scanned| match target {
+                Target::Item => scanned.inventory_id,
+                Target::Lot => scanned.lot_id,
+            });
+
+            let Some(id) = id else {
+                let expected = match target {
+                    Target::Item => "inventory item",
+                    Target::Lot => "lot",
+                };
+                return Err(ServerError::new(
+                    format!("Code '{}' doesn't match any {}", code, expected),
+                    None,
+                ));
+            };
+            resolved.insert((target, code), id);
+        }
+
+        replace_variables(&mut request.variables, &variables, &resolved);
+        if let Ok(mut stored) = self.resolved.lock() {
+            *stored = resolved;
+        }
+
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let mut document = next.run(ctx, query, variables).await?;
+
+        let resolved = self
+            .resolved
+            .lock()
+            .map(|resolved| resolved.clone())
+            .unwrap_or_default();
+        if resolved.is_empty() {
+            return Ok(document);
+        }
+
+        let operations = match &mut document.operations {
+            DocumentOperations::Single(operation) => vec![operation],
+            DocumentOperations::Multiple(operations) => operations.values_mut().collect(),
+        };
+        for operation in operations {
+            if operation.node.ty != OperationType::Mutation {
+                continue;
+            }
+            for selection in &mut operation.node.selection_set.node.items {
+                if let Selection::Field(field) = &mut selection.node {
+                    for (name, value) in &mut field.node.arguments {
+                        replace_literals(&mut value.node, Target::of(&name.node), &resolved);
+                    }
+                }
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+/// Arguments of an operation's top-level fields.
+fn arguments(operation: &OperationDefinition) -> impl Iterator<Item = (&str, &Value)> {
+    operation
+        .selection_set
+        .node
+        .items
+        .iter()
+        .filter_map(|selection| match &selection.node {
+            Selection::Field(field) => Some(field),
+            _ => None,
+        })
+        .flat_map(|field| {
+            field
+                .node
+                .arguments
+                .iter()
+                .map(|(name, value)| (name.node.as_str(), &value.node))
+        })
+}
+
+fn is_code(value: &str) -> bool {
+    Uuid::parse_str(value).is_err()
+}
+
+/// Codes written into the query, and the variables used under each key.
+fn literal_codes(
+    value: &Value,
+    target: Option<Target>,
+    codes: &mut Vec<(Target, String)>,
+    variables: &mut Vec<(Name, Option<Target>)>,
+) {
+    match value {
+        Value::Variable(name) => variables.push((name.clone(), target)),
+        Value::String(code) => {
+            if let Some(target) = target
+                && is_code(code)
+            {
+                codes.push((target, code.clone()));
+            }
+        }
+        Value::List(items) => {
+            for item in items {
+                literal_codes(item, target, codes, variables);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                literal_codes(field, Target::of(key), codes, variables);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn variable_codes(value: &ConstValue, target: Option<Target>, codes: &mut Vec<(Target, String)>) {
+    match value {
+        ConstValue::String(code) => {
+            if let Some(target) = target
+                && is_code(code)
+            {
+                codes.push((target, code.clone()));
+            }
+        }
+        ConstValue::List(items) => {
+            for item in items {
+                variable_codes(item, target, codes);
+            }
+        }
+        ConstValue::Object(fields) => {
+            for (key, field) in fields {
+                variable_codes(field, Target::of(key), codes);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn replace_variables(
+    variables: &mut Variables,
+    used: &[(Name, Option<Target>)],
+    resolved: &HashMap<(Target, String), Uuid>,
+) {
+    for (name, target) in used {
+        if let Some(value) = variables.get_mut(name) {
+            replace_value(value, *target, resolved);
+        }
+    }
+}
+
+fn replace_value(
+    value: &mut ConstValue,
+    target: Option<Target>,
+    resolved: &HashMap<(Target, String), Uuid>,
+) {
+    match value {
+        ConstValue::String(code) => {
+            if let Some(target) = target
+                && let Some(id) = resolved.get(&(target, code.clone()))
+            {
+                *code = id.to_string();
+            }
+        }
+        ConstValue::List(items) => {
+            for item in items {
+                replace_value(item, target, resolved);
+            }
+        }
+        ConstValue::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                replace_value(field, Target::of(key), resolved);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn replace_literals(
+    value: &mut Value,
+    target: Option<Target>,
+    resolved: &HashMap<(Target, String), Uuid>,
+) {
+    match value {
+        Value::String(code) => {
+            if let Some(target) = target
+                && let Some(id) = resolved.get(&(target, code.clone()))
+            {
+                *code = id.to_string();
+            }
+        }
+        Value::List(items) => {
+            for item in items {
+                replace_literals(item, target, resolved);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                replace_literals(field, Target::of(key), resolved);
+            }
+        }
+        _ => {}
+    }
+}
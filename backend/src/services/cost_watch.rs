@@ -0,0 +1,158 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::RecipeCostWatchStatus;
+use crate::services::notifications::{Alert, Notifier};
+use crate::services::recipes;
+
+/// Cost watched recipes (all, or just one) at their default batch size and
+/// latest purchase costs, and compare against their limits.
+pub async fn evaluate(
+    pool: &PgPool,
+    recipe_template_id: Option<Uuid>,
+) -> Result<Vec<RecipeCostWatchStatus>, sqlx::Error> {
+    let watches = sqlx::query!(
+        r#"
+        SELECT w.recipe_template_id, r.template_name, w.max_unit_cost, w.min_margin_percent,
+            w.sale_price
+        FROM recipe_cost_watches w
+        JOIN recipe_templates r ON r.id = w.recipe_template_id
+        WHERE r.is_active = true
+            AND ($1::uuid IS NULL OR w.recipe_template_id = $1)
+        ORDER BY r.template_name
+        "#,
+        recipe_template_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut statuses = Vec::new();
+
+    for watch in watches {
+        let cost = recipes::cost(
+            pool,
+            watch.recipe_template_id,
+            None,
+            "current",
+            watch.sale_price,
+        )
+        .await?
+        .ok();
+
+        let unit_cost = cost.as_ref().map(|cost| cost.cost_per_unit.clone());
+        let sale_price = cost.as_ref().and_then(|cost| cost.sale_price.clone());
+        let margin_percent = cost.and_then(|cost| cost.margin_percent);
+
+        let mut reasons = Vec::new();
+        if let (Some(cost), Some(max)) = (&unit_cost, &watch.max_unit_cost)
+            && cost > max
+        {
+            reasons.push(format!("Unit cost {} is above the {} limit", cost, max));
+        }
+        if let (Some(margin), Some(min)) = (&margin_percent, &watch.min_margin_percent)
+            && margin < min
+        {
+            reasons.push(format!("Margin {}% is below the {}% target", margin, min));
+        }
+
+        statuses.push(RecipeCostWatchStatus {
+            recipe_template_id: watch.recipe_template_id,
+            template_name: watch.template_name,
+            max_unit_cost: watch.max_unit_cost,
+            min_margin_percent: watch.min_margin_percent,
+            unit_cost,
+            sale_price,
+            margin_percent,
+            breached: !reasons.is_empty(),
+            reasons,
+        });
+    }
+
+    Ok(statuses)
+}
+
+/// Re-evaluate every watch, record the results, and alert on recipes that
+/// have newly crossed a limit (recipes already alerting aren't repeated).
+pub async fn check(pool: &PgPool, notifier: &Notifier) -> Result<(), sqlx::Error> {
+    let statuses = evaluate(pool, None).await?;
+    let mut newly_breached = Vec::new();
+
+    for status in statuses {
+        let was_alerting = sqlx::query_scalar!(
+            r#"
+            UPDATE recipe_cost_watches w
+            SET
+                is_alerting = $2,
+                last_unit_cost = $3,
+                last_margin_percent = $4,
+                last_checked_at = NOW()
+            FROM recipe_cost_watches old
+            WHERE old.id = w.id AND w.recipe_template_id = $1
+            RETURNING old.is_alerting
+            "#,
+            status.recipe_template_id,
+            status.breached,
+            status.unit_cost,
+            status.margin_percent
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if status.breached && !was_alerting {
+            newly_breached.push(status);
+        }
+    }
+
+    if newly_breached.is_empty() {
+        return Ok(());
+    }
+
+    let summary = newly_breached
+        .iter()
+        .map(|status| format!("{} ({})", status.template_name, status.reasons.join("; ")))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    notifier
+        .send(&Alert {
+            kind: "recipe_cost".to_string(),
+            message: format!(
+                "{} recipe(s) crossed their cost limits: {}",
+                newly_breached.len(),
+                summary
+            ),
+            data: serde_json::to_value(&newly_breached).unwrap_or_default(),
+        })
+        .await;
+
+    Ok(())
+}
+
+/// Run `check` after ingredient costs or recipes change, logging failures
+/// rather than failing the change that triggered it.
+pub async fn recheck(pool: &PgPool, notifier: &Notifier) {
+    if let Err(e) = check(pool, notifier).await {
+        tracing::error!("❌ Failed to check recipe cost watches: {}", e);
+    }
+}
+
+/// Whether a limit is usable (positive cost, margin under 100%).
+pub fn validate_limits(
+    max_unit_cost: Option<&BigDecimal>,
+    min_margin_percent: Option<&BigDecimal>,
+) -> Result<(), String> {
+    if max_unit_cost.is_none() && min_margin_percent.is_none() {
+        return Err("Set a maximum unit cost, a minimum margin, or both".to_string());
+    }
+
+    if max_unit_cost.is_some_and(|max| *max <= BigDecimal::from(0)) {
+        return Err("Maximum unit cost must be greater than 0".to_string());
+    }
+
+    if min_margin_percent.is_some_and(|min| *min >= BigDecimal::from(100)) {
+        return Err("Minimum margin must be below 100%".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Timelike, Utc};
+
+/// Furthest ahead `next_after` looks; a schedule with no match in this
+/// long (e.g. "0 0 30 2 *") never runs.
+const SEARCH_YEARS: i32 = 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A standard five-field cron expression (minute hour day-of-month month
+/// day-of-week), evaluated in UTC. Fields take `*`, numbers, ranges (`1-5`),
+/// steps (`*/15`, `0-30/10`), lists (`1,15`) and month and day names (`jan`,
+/// `mon`); Sunday is 0 or 7. `@hourly`, `@daily`, `@weekly`, `@monthly` and
+/// `@yearly` are shorthands. As in cron, when both day fields are restricted
+/// a day matching either one runs.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expression = expression.trim();
+        let expanded = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "'{}' should have 5 fields (minute hour day month weekday), not {}",
+                expression,
+                fields.len()
+            ));
+        };
+
+        // Sunday may be written 7; fold it onto 0
+        let mut weekdays = parse_field(weekday, 0, 7, &DAY_NAMES, "weekday")?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            source: expression.to_string(),
+            minutes: parse_field(minute, 0, 59, &[], "minute")?,
+            hours: parse_field(hour, 0, 23, &[], "hour")?,
+            days: parse_field(day, 1, 31, &[], "day")?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES, "month")?,
+            weekdays,
+            any_day: *day == "*",
+            any_weekday: *weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Bit set of the values a field allows. `names` spell out values from `min`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    label: &str,
+) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lower = text.to_lowercase();
+        let parsed = match names.iter().position(|name| *name == lower) {
+            Some(index) => index as u32 + min,
+            None => text
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' isn't a valid {}", text, label))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(format!("{} {} is outside {}-{}", label, parsed, min, max));
+        }
+        Ok(parsed)
+    };
+
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("'{}' has an invalid {} step", part, label))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // "5/15" runs from 5 to the end of the field
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("'{}' runs backwards", part));
+        }
+
+        for v in (start..=end).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl Schedule {
+    /// The first minute strictly after `after` that the schedule matches.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = after + Duration::days(366 * SEARCH_YEARS as i64);
+
+        while t <= limit {
+            if self.months & (1 << t.month()) == 0 {
+                // First day of the next month
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.day_matches(t) {
+                t = (t + Duration::days(1))
+                    .duration_trunc(Duration::days(1))
+                    .ok()?;
+                continue;
+            }
+            if self.hours & (1 << t.hour()) == 0 {
+                t = (t + Duration::hours(1))
+                    .duration_trunc(Duration::hours(1))
+                    .ok()?;
+                continue;
+            }
+            if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+
+        None
+    }
+
+    fn day_matches(&self, t: DateTime<Utc>) -> bool {
+        let day = self.days & (1 << t.day()) != 0;
+        let weekday = self.weekdays & (1 << t.weekday().num_days_from_sunday()) != 0;
+
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
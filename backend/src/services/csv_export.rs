@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use axum::body::Body;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Rows buffered ahead of a slow client before the query waits.
+const BUFFER_ROWS: usize = 256;
+
+/// The writing end of a streamed CSV download. Rows go out as they are
+/// written, so a large export never sits in memory as a whole.
+pub struct CsvStream {
+    lines: mpsc::Sender<Result<String, std::io::Error>>,
+}
+
+/// Start a CSV download named `filename` whose first row is `header`.
+/// Returns the stream to write rows to and the response to hand back; write
+/// from a spawned task, since the body only flows once the response is sent.
+pub fn download(filename: &str, header: &[&str]) -> (CsvStream, Response) {
+    let (lines, receiver) = mpsc::channel(BUFFER_ROWS);
+    let header: Vec<String> = header.iter().map(cell).collect();
+    // Can't fail: the channel is empty and the receiver is right here
+    let _ = lines.try_send(Ok(line(&header)));
+
+    let response = (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(receiver)),
+    )
+        .into_response();
+
+    (CsvStream { lines }, response)
+}
+
+impl CsvStream {
+    /// Send one row. False once the client has gone away, so the writer can
+    /// stop early.
+    pub async fn row(&self, cells: &[String]) -> bool {
+        self.lines.send(Ok(line(cells))).await.is_ok()
+    }
+
+    /// Cut the download off after a failed query, so the client sees an
+    /// incomplete transfer rather than a file that looks whole.
+    pub async fn fail(self, what: &str, e: sqlx::Error) {
+        tracing::error!("❌ Failed to export {}: {}", what, e);
+        let _ = self
+            .lines
+            .send(Err(std::io::Error::other(e.to_string())))
+            .await;
+    }
+}
+
+/// One CSV field. Fields with commas, quotes, line breaks or edge spaces
+/// are quoted (RFC 4180). Text that a spreadsheet would run as a formula
+/// (`=`, `+`, `-`, `@`) gets a leading `'`; numbers are left alone.
+pub fn cell(value: impl Display) -> String {
+    let mut value = value.to_string();
+
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+        value.insert(0, '\'');
+    }
+
+    if value.contains([',', '"', '\n', '\r']) || value.trim() != value {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// A field that may be empty.
+pub fn optional(value: Option<impl Display>) -> String {
+    value.map(cell).unwrap_or_default()
+}
+
+fn line(cells: &[String]) -> String {
+    let mut line = cells.join(",");
+    line.push_str("\r\n");
+    line
+}
@@ -0,0 +1,465 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use serde_json::Value;
+use sqlx::{Connection, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{CsvImportField, CsvImportOutcome};
+
+/// (field, default column header, required)
+type FieldSpec = (&'static str, &'static str, bool);
+
+const INVENTORY_FIELDS: &[FieldSpec] = &[
+    ("name", "Name", true),
+    ("category", "Category", false),
+    ("unit", "Unit", false),
+    ("current_stock", "Current Stock", false),
+    ("reorder_point", "Reorder Point", false),
+    ("cost_per_unit", "Cost Per Unit", false),
+    ("default_supplier", "Default Supplier", false),
+    ("shelf_life_days", "Shelf Life Days", false),
+    ("storage_requirements", "Storage Requirements", false),
+];
+
+const SUPPLIER_FIELDS: &[FieldSpec] = &[
+    ("name", "Name", true),
+    ("contact_email", "Email", false),
+    ("contact_phone", "Phone", false),
+    ("street_address", "Street Address", false),
+    ("city", "City", false),
+    ("state", "State", false),
+    ("zip_code", "Zip Code", false),
+    ("country", "Country", false),
+    ("notes", "Notes", false),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Entity {
+    Inventory,
+    Suppliers,
+}
+
+impl Entity {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "inventory" => Some(Entity::Inventory),
+            "suppliers" => Some(Entity::Suppliers),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Entity::Inventory => "inventory",
+            Entity::Suppliers => "suppliers",
+        }
+    }
+
+    fn fields(self) -> &'static [FieldSpec] {
+        match self {
+            Entity::Inventory => INVENTORY_FIELDS,
+            Entity::Suppliers => SUPPLIER_FIELDS,
+        }
+    }
+
+    /// Fields the importer reads and their default columns.
+    pub fn describe(self) -> Vec<CsvImportField> {
+        self.fields()
+            .iter()
+            .map(|&(field, column, required)| CsvImportField {
+                field: field.to_string(),
+                default_column: column.to_string(),
+                required,
+            })
+            .collect()
+    }
+}
+
+/// Where each field is in the file's rows.
+pub struct Columns {
+    entity: Entity,
+    index: HashMap<&'static str, usize>,
+}
+
+impl Columns {
+    /// Find each field's column in the header row, using the mapping's header
+    /// where one is given. Headers match case-insensitively; optional fields
+    /// without a column are left alone.
+    pub fn new(entity: Entity, header: &[String], mapping: Option<&Value>) -> Result<Self, String> {
+        let mapping = match mapping {
+            None => None,
+            Some(Value::Object(mapping)) => Some(mapping),
+            Some(_) => {
+                return Err(
+                    "Mapping must be an object of {\"field\": \"Column Header\"}".to_string(),
+                );
+            }
+        };
+
+        if let Some(unknown) = mapping
+            .into_iter()
+            .flat_map(|m| m.keys())
+            .find(|key| !entity.fields().iter().any(|(field, _, _)| field == key))
+        {
+            let known: Vec<&str> = entity.fields().iter().map(|(f, _, _)| *f).collect();
+            return Err(format!(
+                "Unknown {} field '{}' in mapping; expected one of: {}",
+                entity.name(),
+                unknown,
+                known.join(", ")
+            ));
+        }
+
+        let mut index = HashMap::new();
+        for &(field, default_header, required) in entity.fields() {
+            let wanted = mapping
+                .and_then(|m| m.get(field))
+                .and_then(Value::as_str)
+                .unwrap_or(default_header);
+
+            match header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(wanted.trim()))
+            {
+                Some(position) => {
+                    index.insert(field, position);
+                }
+                None if required => return Err(format!("No '{}' column for {}", wanted, field)),
+                None => {}
+            }
+        }
+
+        Ok(Self { entity, index })
+    }
+}
+
+/// One data row: its non-empty cells by field.
+struct Row<'a> {
+    cells: HashMap<&'static str, &'a str>,
+}
+
+impl<'a> Row<'a> {
+    fn new(record: &'a [String], columns: &Columns) -> Self {
+        let cells = columns
+            .index
+            .iter()
+            .filter_map(|(&field, &position)| {
+                let cell = record.get(position).map(|c| c.trim()).unwrap_or("");
+                (!cell.is_empty()).then_some((field, cell))
+            })
+            .collect();
+        Self { cells }
+    }
+
+    fn text(&self, field: &str) -> Option<String> {
+        self.cells.get(field).map(|cell| cell.to_string())
+    }
+
+    /// A non-negative number, allowing `$` and thousands separators.
+    fn amount(&self, field: &str, errors: &mut Vec<String>) -> Option<BigDecimal> {
+        let cell = self.cells.get(field)?;
+        let cleaned: String = cell
+            .chars()
+            .filter(|c| !matches!(c, '$' | ',' | ' '))
+            .collect();
+
+        match cleaned.parse::<BigDecimal>() {
+            Ok(amount) if amount >= BigDecimal::from(0) => Some(amount),
+            _ => {
+                errors.push(format!("{} '{}' isn't a number of 0 or more", field, cell));
+                None
+            }
+        }
+    }
+
+    fn days(&self, field: &str, errors: &mut Vec<String>) -> Option<i32> {
+        let cell = self.cells.get(field)?;
+        match cell.parse::<i32>() {
+            Ok(days) if days > 0 => Some(days),
+            _ => {
+                errors.push(format!("{} '{}' isn't a whole number of days", field, cell));
+                None
+            }
+        }
+    }
+}
+
+enum Outcome {
+    Created(Uuid, Vec<String>),
+    Updated(Uuid, Vec<String>),
+    Rejected(Vec<String>),
+}
+
+/// Import the rows after the header inside one transaction, each row in its
+/// own savepoint so a bad row is rejected without losing the rest. Rows are
+/// matched to existing records by name (case-insensitively): without
+/// `upsert` a match is rejected, with it the record is updated from the
+/// row's non-empty cells. A dry run does the same work and rolls it all back.
+pub async fn run(
+    pool: &PgPool,
+    columns: &Columns,
+    records: &[Vec<String>],
+    upsert: bool,
+    dry_run: bool,
+) -> Result<Vec<CsvImportOutcome>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut outcomes = Vec::new();
+    let mut names = HashSet::new();
+
+    for (index, record) in records.iter().enumerate() {
+        // Row 1 is the header
+        let row_number = index as i32 + 2;
+        let row = Row::new(record, columns);
+        let name = row.text("name");
+
+        let outcome = match &name {
+            None => Outcome::Rejected(vec!["No name".to_string()]),
+            Some(name) if !names.insert(name.to_lowercase()) => {
+                Outcome::Rejected(vec![format!("'{}' is listed more than once", name)])
+            }
+            Some(name) => {
+                let mut savepoint = tx.begin().await?;
+
+                let result = match columns.entity {
+                    Entity::Inventory => import_item(&mut savepoint, name, &row, upsert).await,
+                    Entity::Suppliers => import_supplier(&mut savepoint, name, &row, upsert).await,
+                };
+
+                match result {
+                    Ok(Outcome::Rejected(errors)) => {
+                        savepoint.rollback().await?;
+                        Outcome::Rejected(errors)
+                    }
+                    Ok(outcome) => {
+                        savepoint.commit().await?;
+                        outcome
+                    }
+                    // Values the file allowed but the schema doesn't (e.g. too many digits)
+                    Err(sqlx::Error::Database(e)) => {
+                        savepoint.rollback().await?;
+                        Outcome::Rejected(vec![e.message().to_string()])
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+
+        let (status, entity_id, issues) = match outcome {
+            Outcome::Created(id, warnings) => ("created", Some(id), warnings),
+            Outcome::Updated(id, warnings) => ("updated", Some(id), warnings),
+            Outcome::Rejected(errors) => ("rejected", None, errors),
+        };
+
+        outcomes.push(CsvImportOutcome {
+            row_number,
+            name,
+            status: status.to_string(),
+            entity_id,
+            issues,
+        });
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    Ok(outcomes)
+}
+
+async fn import_item(
+    conn: &mut PgConnection,
+    name: &str,
+    row: &Row<'_>,
+    upsert: bool,
+) -> Result<Outcome, sqlx::Error> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let current_stock = row.amount("current_stock", &mut errors);
+    let reorder_point = row.amount("reorder_point", &mut errors);
+    let cost_per_unit = row.amount("cost_per_unit", &mut errors);
+    let shelf_life_days = row.days("shelf_life_days", &mut errors);
+
+    let default_supplier_id = match row.text("default_supplier") {
+        None => None,
+        Some(supplier) => {
+            let id = sqlx::query_scalar!(
+                "SELECT id FROM suppliers WHERE lower(name) = lower($1) AND is_active",
+                supplier
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if id.is_none() {
+                errors.push(format!(
+                    "No active supplier named '{}' (import suppliers first)",
+                    supplier
+                ));
+            }
+            id
+        }
+    };
+
+    let existing = sqlx::query_scalar!(
+        "SELECT id FROM inventory WHERE lower(name) = lower($1) AND is_active",
+        name
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        if !upsert {
+            errors.push(format!(
+                "An item named '{}' already exists; import with upsert to update it",
+                name
+            ));
+        }
+        if !errors.is_empty() {
+            return Ok(Outcome::Rejected(errors));
+        }
+        if current_stock.is_some() {
+            warnings.push(
+                "Current stock is only set on new items; record a stock count to change it"
+                    .to_string(),
+            );
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE inventory
+            SET
+                category = COALESCE($2, category),
+                unit = COALESCE($3, unit),
+                reorder_point = COALESCE($4, reorder_point),
+                cost_per_unit = COALESCE($5, cost_per_unit),
+                default_supplier_id = COALESCE($6, default_supplier_id),
+                shelf_life_days = COALESCE($7, shelf_life_days),
+                storage_requirements = COALESCE($8, storage_requirements),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            row.text("category"),
+            row.text("unit"),
+            reorder_point,
+            cost_per_unit,
+            default_supplier_id,
+            shelf_life_days,
+            row.text("storage_requirements")
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        return Ok(Outcome::Updated(id, warnings));
+    }
+
+    let (Some(category), Some(unit)) = (row.text("category"), row.text("unit")) else {
+        errors.push("New items need a category and a unit".to_string());
+        return Ok(Outcome::Rejected(errors));
+    };
+    if !errors.is_empty() {
+        return Ok(Outcome::Rejected(errors));
+    }
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO inventory (
+            name, category, unit, current_stock, reorder_point, cost_per_unit,
+            default_supplier_id, shelf_life_days, storage_requirements
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+        name,
+        category,
+        unit,
+        current_stock.unwrap_or_else(|| BigDecimal::from(0)),
+        reorder_point.unwrap_or_else(|| BigDecimal::from(0)),
+        cost_per_unit,
+        default_supplier_id,
+        shelf_life_days,
+        row.text("storage_requirements")
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Outcome::Created(id, warnings))
+}
+
+async fn import_supplier(
+    conn: &mut PgConnection,
+    name: &str,
+    row: &Row<'_>,
+    upsert: bool,
+) -> Result<Outcome, sqlx::Error> {
+    let existing = sqlx::query_scalar!(
+        "SELECT id FROM suppliers WHERE lower(name) = lower($1)",
+        name
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        if !upsert {
+            return Ok(Outcome::Rejected(vec![format!(
+                "A supplier named '{}' already exists; import with upsert to update it",
+                name
+            )]));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE suppliers
+            SET
+                contact_email = COALESCE($2, contact_email),
+                contact_phone = COALESCE($3, contact_phone),
+                street_address = COALESCE($4, street_address),
+                city = COALESCE($5, city),
+                state = COALESCE($6, state),
+                zip_code = COALESCE($7, zip_code),
+                country = COALESCE($8, country),
+                notes = COALESCE($9, notes),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+            row.text("contact_email"),
+            row.text("contact_phone"),
+            row.text("street_address"),
+            row.text("city"),
+            row.text("state"),
+            row.text("zip_code"),
+            row.text("country"),
+            row.text("notes")
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        return Ok(Outcome::Updated(id, Vec::new()));
+    }
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO suppliers (
+            name, contact_email, contact_phone, street_address, city, state, zip_code,
+            country, notes
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+        name,
+        row.text("contact_email"),
+        row.text("contact_phone"),
+        row.text("street_address"),
+        row.text("city"),
+        row.text("state"),
+        row.text("zip_code"),
+        row.text("country"),
+        row.text("notes")
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Outcome::Created(id, Vec::new()))
+}
@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::models::{Dashboard, DashboardWidget, DashboardWidgetInput};
+
+/// Widgets the app knows how to draw. Each reads the query of the same name
+/// (`low_stock` reads `inventoryItems` below the reorder point, `recent_sales`
+/// reads `sales`).
+pub const WIDGET_KINDS: &[&str] = &[
+    "revenue_summary",
+    "recent_sales",
+    "sales_awaiting_stock_review",
+    "market_profitability",
+    "pickup_manifest",
+    "low_stock",
+    "expiring_items",
+    "inventory_valuation",
+    "slow_movers",
+    "stock_projection",
+    "active_batches",
+    "batch_alerts",
+    "batch_tasks",
+    "production_calendar",
+    "planned_batches",
+    "purchase_orders",
+];
+
+/// Most widgets one dashboard can hold.
+const MAX_WIDGETS: usize = 30;
+
+/// Widest a widget can be, in grid columns.
+const MAX_WIDTH: i32 = 4;
+
+/// Check a widget list and fill in defaults. Returns the reason when it
+/// can't be saved.
+pub fn widgets(inputs: Vec<DashboardWidgetInput>) -> Result<Vec<DashboardWidget>, String> {
+    if inputs.len() > MAX_WIDGETS {
+        return Err(format!(
+            "A dashboard can hold at most {} widgets",
+            MAX_WIDGETS
+        ));
+    }
+
+    inputs
+        .into_iter()
+        .map(|input| {
+            if !WIDGET_KINDS.contains(&input.kind.as_str()) {
+                return Err(format!(
+                    "Unknown widget '{}'. Choose from: {}",
+                    input.kind,
+                    WIDGET_KINDS.join(", ")
+                ));
+            }
+
+            let width = input.width.unwrap_or(1);
+            if !(1..=MAX_WIDTH).contains(&width) {
+                return Err(format!(
+                    "Widget width must be between 1 and {} columns",
+                    MAX_WIDTH
+                ));
+            }
+
+            let filters = input
+                .filters
+                .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+            if !filters.is_object() {
+                return Err(format!(
+                    "Filters for '{}' must be a JSON object",
+                    input.kind
+                ));
+            }
+
+            Ok(DashboardWidget {
+                kind: input.kind,
+                title: input
+                    .title
+                    .map(|title| title.trim().to_string())
+                    .filter(|title| !title.is_empty()),
+                width,
+                filters,
+            })
+        })
+        .collect()
+}
+
+/// A user's dashboards, the default first, then by name.
+pub async fn list<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<Vec<Dashboard>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        DashboardRow,
+        r#"
+        SELECT id, user_id, name, widgets AS "widgets: Json<Vec<DashboardWidget>>",
+            is_default, created_at, updated_at
+        FROM dashboards
+        WHERE user_id = $1
+        ORDER BY is_default DESC, name
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows.into_iter().map(Dashboard::from).collect())
+}
+
+/// One of a user's dashboards. Other users' dashboards are not found.
+pub async fn get<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<Option<Dashboard>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        DashboardRow,
+        r#"
+        SELECT id, user_id, name, widgets AS "widgets: Json<Vec<DashboardWidget>>",
+            is_default, created_at, updated_at
+        FROM dashboards
+        WHERE user_id = $1 AND id = $2
+        "#,
+        user_id,
+        id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row.map(Dashboard::from))
+}
+
+/// Dashboard as stored, with its widgets still wrapped for decoding.
+struct DashboardRow {
+    id: Uuid,
+    user_id: Uuid,
+    name: String,
+    widgets: Json<Vec<DashboardWidget>>,
+    is_default: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<DashboardRow> for Dashboard {
+    fn from(row: DashboardRow) -> Self {
+        Dashboard {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            widgets: row.widgets.0,
+            is_default: row.is_default,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
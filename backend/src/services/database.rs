@@ -0,0 +1,144 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+
+use crate::services::audit;
+
+/// Pool for reporting reads: GraphQL queries and the change export.
+///
+/// It connects as `DATABASE_READONLY_URL` (falling back to `DATABASE_URL`),
+/// and every connection defaults to read-only transactions, so a reporting
+/// path can't write even when both pools share a role. Mutations keep the
+/// plain read-write `PgPool`.
+#[derive(Clone)]
+pub struct ReportingPool(PgPool);
+
+impl Deref for ReportingPool {
+    type Target = PgPool;
+
+    fn deref(&self) -> &PgPool {
+        &self.0
+    }
+}
+
+/// Tests read through the test database's only pool.
+#[cfg(test)]
+impl From<PgPool> for ReportingPool {
+    fn from(pool: PgPool) -> Self {
+        ReportingPool(pool)
+    }
+}
+
+/// Connect the read-write pool for mutations and background jobs.
+///
+/// Each connection is tagged with the acting user as it is handed out, for
+/// the audit trail. That costs a round trip per acquire.
+pub async fn connect(url: &str, max_connections: u32) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .after_connect(|conn, _| Box::pin(audit::tag_connection(conn)))
+        .before_acquire(|conn, _| {
+            Box::pin(async move {
+                audit::tag_connection(conn).await?;
+                Ok(true)
+            })
+        })
+        .connect(url)
+        .await
+}
+
+/// Connect the read-only reporting pool. With a `statement_timeout`, Postgres
+/// cancels any statement that runs longer, so a runaway query can't hold a
+/// connection.
+pub async fn connect_reporting(
+    url: &str,
+    max_connections: u32,
+    statement_timeout: Option<Duration>,
+) -> Result<ReportingPool, sqlx::Error> {
+    let statement_timeout = statement_timeout.map_or("0".to_string(), |timeout| {
+        format!("{}ms", timeout.as_millis())
+    });
+
+    let pool = PgPoolOptions::new()
+        .max_connections(max_connections)
+        .after_connect(move |conn, _| {
+            let statement_timeout = statement_timeout.clone();
+            Box::pin(async move {
+                sqlx::query!("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                    .execute(&mut *conn)
+                    .await?;
+                sqlx::query!(
+                    "SELECT set_config('statement_timeout', $1, false)",
+                    statement_timeout
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok(())
+            })
+        })
+        .connect(url)
+        .await?;
+
+    Ok(ReportingPool(pool))
+}
+
+/// What the pool's role can do beyond its job: create or alter schema objects
+/// (superuser, CREATE on the database or public schema, or owning tables),
+/// and for the reporting pool, write to tables. Empty when the role is
+/// least-privilege.
+pub async fn excess_privileges(pool: &PgPool, read_only: bool) -> Result<Vec<String>, sqlx::Error> {
+    let role = sqlx::query!(
+        r#"
+        SELECT
+            current_user AS "role!",
+            r.rolsuper AS "superuser!",
+            has_database_privilege(current_user, current_database(), 'CREATE') AS "create_database!",
+            has_schema_privilege(current_user, 'public', 'CREATE') AS "create_schema!",
+            (SELECT COUNT(*) FROM pg_tables
+             WHERE schemaname = 'public' AND tableowner = current_user) AS "owned_tables!",
+            (SELECT COUNT(*) FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p')
+                AND has_table_privilege(c.oid, 'INSERT, UPDATE, DELETE, TRUNCATE')
+            ) AS "writable_tables!"
+        FROM pg_roles r
+        WHERE r.rolname = current_user
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let mut problems = Vec::new();
+
+    if role.superuser {
+        problems.push(format!("role '{}' is a superuser", role.role));
+    }
+    if role.create_database {
+        problems.push(format!(
+            "role '{}' can create schemas in the database",
+            role.role
+        ));
+    }
+    if role.create_schema {
+        problems.push(format!(
+            "role '{}' can create objects in schema public",
+            role.role
+        ));
+    }
+    if role.owned_tables > 0 {
+        problems.push(format!(
+            "role '{}' owns {} table(s) and can alter or drop them",
+            role.role, role.owned_tables
+        ));
+    }
+    if read_only && role.writable_tables > 0 {
+        problems.push(format!(
+            "reporting role '{}' can write to {} table(s)",
+            role.role, role.writable_tables
+        ));
+    }
+
+    Ok(problems)
+}
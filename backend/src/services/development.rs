@@ -0,0 +1,117 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::ProductDevelopment;
+use crate::services::recipes;
+
+/// Pipeline stages in order. 'shelved' sits outside the order.
+pub const STAGES: &[&str] = &["concept", "trials", "costing", "compliance", "launched"];
+
+/// The stage after `stage`, if there is one.
+pub fn next(stage: &str) -> Option<&'static str> {
+    let position = STAGES.iter().position(|s| *s == stage)?;
+    STAGES.get(position + 1).copied()
+}
+
+/// Products in development, soonest target launch first (launched and
+/// shelved products only when `include_closed` is set).
+pub async fn list(
+    pool: &PgPool,
+    include_closed: bool,
+) -> Result<Vec<ProductDevelopment>, sqlx::Error> {
+    sqlx::query_as!(
+        ProductDevelopment,
+        r#"
+        SELECT
+            d.id, d.name, d.description, d.stage, d.recipe_template_id,
+            r.template_name AS "recipe_name?", d.product_inventory_id, d.target_launch_date,
+            d.costed_unit_cost, d.label_approved_at, d.launched_at, d.notes,
+            d.stage_changed_at, d.created_at, d.updated_at
+        FROM product_developments d
+        LEFT JOIN recipe_templates r ON r.id = d.recipe_template_id
+        WHERE $1 OR d.stage NOT IN ('launched', 'shelved')
+        ORDER BY d.target_launch_date NULLS LAST, d.created_at
+        "#,
+        include_closed
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// One product in development.
+pub async fn find(pool: &PgPool, id: Uuid) -> Result<Option<ProductDevelopment>, sqlx::Error> {
+    sqlx::query_as!(
+        ProductDevelopment,
+        r#"
+        SELECT
+            d.id, d.name, d.description, d.stage, d.recipe_template_id,
+            r.template_name AS "recipe_name?", d.product_inventory_id, d.target_launch_date,
+            d.costed_unit_cost, d.label_approved_at, d.launched_at, d.notes,
+            d.stage_changed_at, d.created_at, d.updated_at
+        FROM product_developments d
+        LEFT JOIN recipe_templates r ON r.id = d.recipe_template_id
+        WHERE d.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The stage gate out of a product's current stage: what still has to happen
+/// before it can advance.
+///
+/// - concept → trials: a candidate recipe is picked
+/// - trials → costing: at least one trial batch is completed
+/// - costing → compliance: every ingredient of the recipe has a cost
+/// - compliance → launched: the label is signed off and the product it
+///   launches as is linked
+pub async fn blockers(
+    pool: &PgPool,
+    development: &ProductDevelopment,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut blockers = Vec::new();
+
+    match development.stage.as_str() {
+        "concept" if development.recipe_template_id.is_none() => {
+            blockers.push("Pick a candidate recipe".to_string());
+        }
+        "trials" => {
+            let completed = sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "count!"
+                FROM production_batches
+                WHERE development_id = $1 AND status = 'completed'
+                "#,
+                development.id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            if completed == 0 {
+                blockers.push("Complete at least one trial batch".to_string());
+            }
+        }
+        "costing" => match development.recipe_template_id {
+            None => blockers.push("Pick a candidate recipe".to_string()),
+            Some(recipe_id) => match recipes::cost(pool, recipe_id, None, "current", None).await? {
+                Err(reason) => blockers.push(reason),
+                Ok(cost) if !cost.uncosted.is_empty() => {
+                    blockers.push(format!("No cost for: {}", cost.uncosted.join(", ")))
+                }
+                Ok(_) => {}
+            },
+        },
+        "compliance" => {
+            if development.label_approved_at.is_none() {
+                blockers.push("Sign off the label and compliance review".to_string());
+            }
+            if development.product_inventory_id.is_none() {
+                blockers.push("Link the product it launches as".to_string());
+            }
+        }
+        _ => {}
+    }
+
+    Ok(blockers)
+}
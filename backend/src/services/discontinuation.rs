@@ -0,0 +1,122 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::DiscontinuationReport;
+use crate::services::orders;
+
+/// Sell-through status of a discontinued product, or `None` if the product
+/// isn't discontinued.
+pub async fn report(
+    pool: &PgPool,
+    inventory_id: Uuid,
+) -> Result<Option<DiscontinuationReport>, sqlx::Error> {
+    let product = sqlx::query!(
+        r#"
+        SELECT
+            i.id, i.name, i.unit, i.current_stock, i.reserved_stock,
+            i.discontinued_at AS "discontinued_at!", i.discontinued_reason,
+            COALESCE((
+                SELECT SUM(batch_size) FROM production_batches
+                WHERE product_inventory_id = i.id AND status = 'in_progress'
+            ), 0) AS "in_production!",
+            COALESCE(sold.quantity, 0) AS "sold_since!",
+            COALESCE(sold.revenue, 0) AS "revenue_since!",
+            sold.last_sold_at
+        FROM inventory i
+        LEFT JOIN LATERAL (
+            SELECT SUM(si.quantity) AS quantity,
+                SUM(si.line_total - si.discount_amount) AS revenue,
+                MAX(s.sale_date) AS last_sold_at
+            FROM sale_items si
+            JOIN sales s ON s.id = si.sale_id
+            WHERE si.inventory_id = i.id
+                AND s.sale_date >= i.discontinued_at
+                AND s.payment_status <> 'refunded'
+        ) sold ON true
+        WHERE i.id = $1 AND i.discontinued_at IS NOT NULL
+        "#,
+        inventory_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(product) = product else {
+        return Ok(None);
+    };
+
+    let kits = sqlx::query_scalar!(
+        r#"
+        SELECT k.name
+        FROM kit_components c
+        JOIN inventory k ON k.id = c.kit_inventory_id
+        WHERE c.component_inventory_id = $1 AND k.is_active = true
+        ORDER BY k.name
+        "#,
+        inventory_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let workshops = sqlx::query_scalar!(
+        r#"
+        SELECT w.title
+        FROM workshop_kit_items k
+        JOIN workshops w ON w.id = k.workshop_id
+        WHERE k.inventory_id = $1 AND w.status = 'scheduled' AND w.starts_at >= NOW()
+        ORDER BY w.starts_at
+        "#,
+        inventory_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let pack_sizes = sqlx::query_scalar!(
+        "SELECT name FROM pack_sizes WHERE bulk_inventory_id = $1 ORDER BY name",
+        inventory_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Its packaged SKUs were discontinued with it, so orders for them count too
+    let orders = sqlx::query_scalar!(
+        r#"
+        SELECT o.order_number || ' for ' || c.name || ' on ' || o.delivery_date AS "order!"
+        FROM customer_orders o
+        JOIN customers c ON c.id = o.customer_id
+        WHERE o.status = ANY($2)
+            AND EXISTS (
+                SELECT 1 FROM customer_order_items oi
+                WHERE oi.order_id = o.id
+                    AND (oi.inventory_id = $1 OR oi.inventory_id IN (
+                        SELECT packaged_inventory_id FROM pack_sizes WHERE bulk_inventory_id = $1
+                    ))
+            )
+        ORDER BY o.delivery_date, o.order_number
+        "#,
+        inventory_id,
+        &orders::OPEN.map(str::to_string)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let zero = bigdecimal::BigDecimal::from(0);
+
+    Ok(Some(DiscontinuationReport {
+        sold_through: product.current_stock <= zero && product.in_production <= zero,
+        inventory_id: product.id,
+        name: product.name,
+        unit: product.unit,
+        discontinued_at: product.discontinued_at,
+        reason: product.discontinued_reason,
+        current_stock: product.current_stock,
+        reserved_stock: product.reserved_stock,
+        in_production: product.in_production,
+        sold_since: product.sold_since,
+        revenue_since: product.revenue_since,
+        last_sold_at: product.last_sold_at,
+        kits,
+        workshops,
+        pack_sizes,
+        orders,
+    }))
+}
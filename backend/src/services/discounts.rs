@@ -0,0 +1,344 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::models::{
+    DiscountInput, DiscountSummaryLine, MutationError, PromoCode, Rejection, SaleDiscount,
+};
+use crate::services::sales::SaleLine;
+
+/// Kinds of discount: percent off, or an amount off.
+pub const DISCOUNT_TYPES: [&str; 2] = ["percentage", "fixed"];
+
+/// A discount on a sale or one of its lines, with what it takes off.
+#[derive(Debug, Clone)]
+pub struct Discount {
+    pub discount_type: String,
+    pub value: BigDecimal,
+    pub amount: BigDecimal,
+    pub reason: Option<String>,
+    pub promo_code_id: Option<Uuid>,
+}
+
+impl Discount {
+    /// A fixed discount that was already taken elsewhere (an imported
+    /// sale's, or a sheet's discount column).
+    pub fn fixed(amount: BigDecimal, reason: &str) -> Self {
+        Self {
+            discount_type: "fixed".to_string(),
+            value: amount.clone(),
+            amount,
+            reason: Some(reason.to_string()),
+            promo_code_id: None,
+        }
+    }
+}
+
+/// Check a discount type and value, explaining what's wrong with them.
+pub fn check(discount_type: &str, value: &BigDecimal) -> Result<(), String> {
+    if !DISCOUNT_TYPES.contains(&discount_type) {
+        return Err(format!(
+            "Unknown discount type '{}'; expected percentage or fixed",
+            discount_type
+        ));
+    }
+    if *value <= BigDecimal::from(0) {
+        return Err("A discount must be more than 0".to_string());
+    }
+    if discount_type == "percentage" && *value > BigDecimal::from(100) {
+        return Err("A percentage discount can't be more than 100".to_string());
+    }
+    Ok(())
+}
+
+/// What a discount takes off `base`, to the cent. A fixed discount bigger
+/// than `base` is refused, or cut down to it when `cap` is set.
+fn amount_off(
+    discount_type: &str,
+    value: &BigDecimal,
+    base: &BigDecimal,
+    cap: bool,
+) -> Result<BigDecimal, String> {
+    let amount = match discount_type {
+        "percentage" => {
+            (base * value / BigDecimal::from(100)).with_scale_round(2, RoundingMode::HalfUp)
+        }
+        _ if value > base && !cap => {
+            return Err(format!(
+                "A discount of {} is more than the {} it comes off",
+                value,
+                base.with_scale_round(2, RoundingMode::HalfUp)
+            ));
+        }
+        _ => value.min(base).clone(),
+    };
+    Ok(amount)
+}
+
+/// A discount given at the till, taken off `base`.
+fn given(input: &DiscountInput, base: &BigDecimal) -> Result<Discount, String> {
+    check(&input.discount_type, &input.value)?;
+    Ok(Discount {
+        discount_type: input.discount_type.clone(),
+        value: input.value.clone(),
+        amount: amount_off(&input.discount_type, &input.value, base, false)?,
+        reason: input
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string),
+        promo_code_id: None,
+    })
+}
+
+/// Work out a sale's discounts. Each line's own discount comes off its
+/// total and is added to the line; a promo code for an item then comes off
+/// that item's lines (a fixed one off each unit). Whole-sale discounts come
+/// off what is left, in the order given, and the promo code last; those are
+/// returned. A promo code is never refused for being worth more than the
+/// sale, it just takes it to zero.
+pub fn apply(
+    lines: &mut [SaleLine],
+    line_discounts: &[Option<DiscountInput>],
+    order_discounts: &[DiscountInput],
+    promo: Option<&PromoCode>,
+) -> Result<Vec<Discount>, String> {
+    for (line, input) in lines.iter_mut().zip(line_discounts) {
+        if let Some(input) = input {
+            let discount = given(input, &line.net_total())?;
+            line.discounts.push(discount);
+        }
+    }
+
+    if let (Some(promo), Some(inventory_id)) = (promo, promo.and_then(|promo| promo.inventory_id)) {
+        let mut matched = false;
+        for line in lines
+            .iter_mut()
+            .filter(|line| line.inventory_id == inventory_id)
+        {
+            let value = match promo.discount_type.as_str() {
+                "fixed" => &promo.value * &line.quantity,
+                _ => promo.value.clone(),
+            };
+            line.discounts.push(Discount {
+                discount_type: promo.discount_type.clone(),
+                value: promo.value.clone(),
+                amount: amount_off(&promo.discount_type, &value, &line.net_total(), true)?,
+                reason: promo.description.clone(),
+                promo_code_id: Some(promo.id),
+            });
+            matched = true;
+        }
+        if !matched {
+            return Err(format!(
+                "Promo code {} is for an item this sale doesn't include",
+                promo.code
+            ));
+        }
+    }
+
+    let mut remaining: BigDecimal = lines.iter().map(SaleLine::net_total).sum();
+    let mut applied = Vec::new();
+    for input in order_discounts {
+        let discount = given(input, &remaining)?;
+        remaining -= &discount.amount;
+        applied.push(discount);
+    }
+    if let Some(promo) = promo.filter(|promo| promo.inventory_id.is_none()) {
+        applied.push(Discount {
+            discount_type: promo.discount_type.clone(),
+            value: promo.value.clone(),
+            amount: amount_off(&promo.discount_type, &promo.value, &remaining, true)?,
+            reason: promo.description.clone(),
+            promo_code_id: Some(promo.id),
+        });
+    }
+
+    Ok(applied)
+}
+
+/// Record discounts a sale got, on the line `sale_item_id` or on the whole
+/// sale. They are timed as they're written, so they list in the order they
+/// were taken.
+pub async fn record(
+    conn: &mut PgConnection,
+    sale_id: Uuid,
+    sale_item_id: Option<Uuid>,
+    discounts: &[Discount],
+) -> Result<(), sqlx::Error> {
+    for discount in discounts {
+        sqlx::query!(
+            r#"
+            INSERT INTO sale_discounts (
+                sale_id, sale_item_id, promo_code_id, discount_type, value, amount, reason,
+                created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, clock_timestamp())
+            "#,
+            sale_id,
+            sale_item_id,
+            discount.promo_code_id,
+            discount.discount_type,
+            discount.value,
+            discount.amount,
+            discount.reason
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// A promo code as customers type it: trimmed, upper case.
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// The promo code a customer gave, if it can be used on `date`, or why
+/// not. The code's row stays locked until the caller's transaction ends, so
+/// two sales can't both take its last use.
+pub async fn promo_code(
+    conn: &mut PgConnection,
+    code: &str,
+    date: NaiveDate,
+) -> Result<Result<PromoCode, Rejection>, sqlx::Error> {
+    let code = normalize_code(code);
+    let id = sqlx::query_scalar!(
+        "SELECT id FROM promo_codes WHERE code = $1 FOR UPDATE",
+        code
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(id) = id else {
+        return Ok(Err(Rejection::new(
+            format!("Promo code {} doesn't exist", code),
+            MutationError::not_found("Promo code"),
+        )));
+    };
+
+    // Counted once the lock is held, so uses by sales that got it first show
+    let promo = by_id(&mut *conn, id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    let problem = if !promo.is_active {
+        Some(format!("Promo code {} is no longer active", code))
+    } else if promo.starts_on.is_some_and(|starts_on| date < starts_on) {
+        Some(format!("Promo code {} can't be used yet", code))
+    } else if promo.ends_on.is_some_and(|ends_on| date > ends_on) {
+        Some(format!("Promo code {} has expired", code))
+    } else if promo
+        .max_uses
+        .is_some_and(|max_uses| promo.times_used >= i64::from(max_uses))
+    {
+        Some(format!("Promo code {} has been used up", code))
+    } else {
+        None
+    };
+
+    Ok(match problem {
+        Some(problem) => Err(problem.into()),
+        None => Ok(promo),
+    })
+}
+
+/// A promo code by ID.
+pub async fn by_id(
+    executor: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Option<PromoCode>, sqlx::Error> {
+    sqlx::query_as!(
+        PromoCode,
+        r#"
+        SELECT
+            p.id, p.code, p.description, p.discount_type, p.value, p.inventory_id,
+            p.starts_on, p.ends_on, p.max_uses,
+            (SELECT COUNT(DISTINCT d.sale_id) FROM sale_discounts d WHERE d.promo_code_id = p.id)
+                AS "times_used!",
+            p.is_active, p.created_at, p.updated_at
+        FROM promo_codes p
+        WHERE p.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Promo codes by code, leaving out inactive ones unless asked.
+pub async fn promo_codes(
+    executor: impl PgExecutor<'_>,
+    include_inactive: bool,
+) -> Result<Vec<PromoCode>, sqlx::Error> {
+    sqlx::query_as!(
+        PromoCode,
+        r#"
+        SELECT
+            p.id, p.code, p.description, p.discount_type, p.value, p.inventory_id,
+            p.starts_on, p.ends_on, p.max_uses,
+            (SELECT COUNT(DISTINCT d.sale_id) FROM sale_discounts d WHERE d.promo_code_id = p.id)
+                AS "times_used!",
+            p.is_active, p.created_at, p.updated_at
+        FROM promo_codes p
+        WHERE p.is_active OR $1
+        ORDER BY p.code
+        "#,
+        include_inactive
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// A sale's discounts, lines' first, in the order they were taken.
+pub async fn for_sale(
+    executor: impl PgExecutor<'_>,
+    sale_id: Uuid,
+) -> Result<Vec<SaleDiscount>, sqlx::Error> {
+    sqlx::query_as!(
+        SaleDiscount,
+        r#"
+        SELECT
+            d.id, d.sale_id, d.sale_item_id, d.promo_code_id, p.code AS "promo_code?",
+            d.discount_type, d.value, d.amount, d.reason, d.created_at
+        FROM sale_discounts d
+        LEFT JOIN promo_codes p ON p.id = d.promo_code_id
+        WHERE d.sale_id = $1
+        ORDER BY d.sale_item_id IS NULL, d.created_at, d.id
+        "#,
+        sale_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Discounts on sales between two dates (inclusive) that weren't refunded,
+/// biggest total first.
+pub async fn summary(
+    executor: impl PgExecutor<'_>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Vec<DiscountSummaryLine>, sqlx::Error> {
+    sqlx::query_as!(
+        DiscountSummaryLine,
+        r#"
+        SELECT
+            p.code AS "promo_code?",
+            d.discount_type,
+            CASE WHEN d.sale_item_id IS NULL THEN 'order' ELSE 'line' END AS "scope!",
+            COUNT(*) AS "discounts!",
+            COUNT(DISTINCT d.sale_id) AS "sales_count!",
+            SUM(d.amount) AS "total!"
+        FROM sale_discounts d
+        JOIN sales s ON s.id = d.sale_id
+        LEFT JOIN promo_codes p ON p.id = d.promo_code_id
+        WHERE s.sale_date >= $1 AND s.sale_date < $2
+            AND s.payment_status <> 'refunded'
+        GROUP BY 1, 2, 3
+        ORDER BY 6 DESC, 1 NULLS FIRST, 2, 3
+        "#,
+        start_date.and_time(NaiveTime::MIN).and_utc(),
+        end_date.and_time(NaiveTime::MIN).and_utc() + Duration::days(1)
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,177 @@
+use sqlx::{PgExecutor, PgPool};
+
+use crate::models::{DomainEvent, EventSet};
+use crate::services::changes::Cursor;
+
+/// Events the app records.
+pub const EVENT_TYPES: &[&str] = &[
+    "batchCompleted",
+    "saleCreated",
+    "lowStock",
+    "purchaseReceived",
+];
+
+pub const DEFAULT_LIMIT: i64 = 500;
+pub const MAX_LIMIT: i64 = 5_000;
+
+/// Check an event type filter against the known events.
+pub fn validate_event_types(event_types: &[String]) -> Result<(), String> {
+    match event_types
+        .iter()
+        .find(|event| !EVENT_TYPES.contains(&event.as_str()))
+    {
+        Some(unknown) => Err(format!(
+            "Unknown event '{}'; expected one of: {}",
+            unknown,
+            EVENT_TYPES.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Record an event and queue it for every active webhook subscription that
+/// wants it. Call it inside the transaction that caused the event, so events
+/// exist exactly when their change does. Returns the number of deliveries
+/// queued.
+pub async fn publish<'e>(
+    executor: impl PgExecutor<'e>,
+    event_type: &str,
+    payload: serde_json::Value,
+) -> Result<u64, sqlx::Error> {
+    let queued = sqlx::query!(
+        r#"
+        WITH event AS (
+            INSERT INTO domain_events (event_type, payload)
+            VALUES ($1, $2)
+            RETURNING id
+        )
+        INSERT INTO webhook_deliveries (subscription_id, event_id)
+        SELECT s.id, event.id FROM webhook_subscriptions s, event
+        WHERE s.is_active AND $1 = ANY(s.event_types)
+        "#,
+        event_type,
+        payload
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(queued.rows_affected())
+}
+
+/// Events after `after`, oldest first.
+pub async fn fetch(
+    pool: &PgPool,
+    after: Cursor,
+    event_types: Option<&[String]>,
+    limit: i64,
+) -> Result<EventSet, sqlx::Error> {
+    let limit = limit.clamp(1, MAX_LIMIT);
+
+    // Fetch one extra row to learn whether there's another page
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT id, txid, event_type, payload, occurred_at
+        FROM domain_events
+        WHERE (txid, id) > ($1, $2)
+            AND txid < pg_snapshot_xmin(pg_current_snapshot())::text::bigint
+            AND ($3::text[] IS NULL OR event_type = ANY($3))
+        ORDER BY txid, id
+        LIMIT $4
+        "#,
+        after.txid,
+        after.id,
+        event_types,
+        limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+
+    let next_cursor = rows
+        .last()
+        .map(|row| Cursor {
+            txid: row.txid,
+            id: row.id,
+        })
+        .unwrap_or(after);
+
+    let events = rows
+        .into_iter()
+        .map(|row| DomainEvent {
+            cursor: Cursor {
+                txid: row.txid,
+                id: row.id,
+            }
+            .to_string(),
+            event_type: row.event_type,
+            occurred_at: row.occurred_at,
+            data: row.payload,
+        })
+        .collect();
+
+    Ok(EventSet {
+        events,
+        next_cursor: next_cursor.to_string(),
+        has_more,
+    })
+}
+
+/// Publish a lowStock event for each active item that has dropped to its
+/// reorder point since it was last flagged, and clear flags for items that
+/// are back above it. Returns the number of items newly flagged.
+pub async fn detect_low_stock(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM low_stock_flags f
+        USING inventory i
+        WHERE i.id = f.inventory_id
+            AND (NOT i.is_active OR i.reorder_point <= 0 OR i.available_stock > i.reorder_point)
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let flagged = sqlx::query!(
+        r#"
+        WITH flagged AS (
+            INSERT INTO low_stock_flags (inventory_id)
+            SELECT id FROM inventory
+            WHERE is_active AND reorder_point > 0 AND available_stock <= reorder_point
+            ON CONFLICT (inventory_id) DO NOTHING
+            RETURNING inventory_id
+        )
+        SELECT i.id, i.name, i.category, i.unit, i.available_stock, i.reorder_point,
+            i.default_supplier_id
+        FROM flagged f
+        JOIN inventory i ON i.id = f.inventory_id
+        ORDER BY i.name
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for item in &flagged {
+        publish(
+            &mut *tx,
+            "lowStock",
+            serde_json::json!({
+                "inventory_id": item.id,
+                "name": item.name,
+                "category": item.category,
+                "unit": item.unit,
+                "available_stock": item.available_stock,
+                "reorder_point": item.reorder_point,
+                "default_supplier_id": item.default_supplier_id,
+            }),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(flagged.len())
+}
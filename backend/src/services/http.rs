@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Attempts per call, including the first one.
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubles on each subsequent retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Consecutive failures that trip an integration's circuit breaker.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long a tripped breaker rejects calls before letting a trial call through.
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// Error returned by `HttpClient::execute`.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The integration's circuit breaker is open; the call was not attempted
+    CircuitOpen(&'static str),
+    /// Connection failure, timeout, or non-success status after all retries
+    Request(reqwest::Error),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::CircuitOpen(integration) => {
+                write!(f, "{} is unavailable (circuit open)", integration)
+            }
+            HttpError::Request(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Health of one external integration as seen by its circuit breaker.
+#[derive(Debug, Clone, SimpleObject)]
+pub struct IntegrationHealth {
+    /// Integration name (e.g. "alert_webhook")
+    pub name: String,
+    /// 'closed' (healthy), 'open' (rejecting calls), or 'half_open' (trial call allowed)
+    pub circuit_state: String,
+    pub consecutive_failures: u32,
+    pub total_calls: u64,
+    pub total_failures: u64,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    total_calls: u64,
+    total_failures: u64,
+    last_success_at: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+impl Breaker {
+    fn state(&self) -> &'static str {
+        match self.opened_at {
+            None => "closed",
+            Some(opened_at) if opened_at.elapsed() < OPEN_DURATION => "open",
+            Some(_) => "half_open",
+        }
+    }
+}
+
+/// Shared outbound HTTP client for third-party integrations.
+///
+/// Every call goes through `execute`, which applies connect/request timeouts,
+/// retries transient failures with exponential backoff, and tracks a circuit
+/// breaker per integration so a hung provider fails fast instead of tying up
+/// resolvers and background jobs.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    breakers: Arc<Mutex<HashMap<&'static str, Breaker>>>,
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("failed to build HTTP client");
+
+        Self {
+            client,
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Underlying client, for building requests to pass to `execute`.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Send a request on behalf of `integration`, with retries and circuit breaking.
+    ///
+    /// Non-success statuses count as failures; only 5xx and 429 responses and
+    /// connection/timeout errors are retried.
+    pub async fn execute(
+        &self,
+        integration: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, HttpError> {
+        if self.state(integration) == "open" {
+            return Err(HttpError::CircuitOpen(integration));
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+
+        loop {
+            // Bodies are JSON/bytes, so requests can always be cloned
+            let result = request
+                .try_clone()
+                .expect("streaming request bodies are not supported")
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match result {
+                Ok(response) => {
+                    self.record_success(integration);
+                    return Ok(response);
+                }
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                    tracing::warn!(
+                        "⚠️  {} call failed (attempt {}/{}), retrying in {:?}: {}",
+                        integration,
+                        attempt,
+                        MAX_ATTEMPTS,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.record_failure(integration, &e);
+                    return Err(HttpError::Request(e));
+                }
+            }
+        }
+    }
+
+    /// Circuit breaker health for every integration that has been called.
+    pub fn health(&self) -> Vec<IntegrationHealth> {
+        let breakers = self.breakers.lock().unwrap();
+
+        let mut health: Vec<IntegrationHealth> = breakers
+            .iter()
+            .map(|(name, breaker)| IntegrationHealth {
+                name: name.to_string(),
+                circuit_state: breaker.state().to_string(),
+                consecutive_failures: breaker.consecutive_failures,
+                total_calls: breaker.total_calls,
+                total_failures: breaker.total_failures,
+                last_success_at: breaker.last_success_at,
+                last_failure_at: breaker.last_failure_at,
+                last_error: breaker.last_error.clone(),
+            })
+            .collect();
+
+        health.sort_by(|a, b| a.name.cmp(&b.name));
+        health
+    }
+
+    fn state(&self, integration: &'static str) -> &'static str {
+        let breakers = self.breakers.lock().unwrap();
+        breakers
+            .get(integration)
+            .map(Breaker::state)
+            .unwrap_or("closed")
+    }
+
+    fn record_success(&self, integration: &'static str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(integration).or_default();
+
+        if breaker.opened_at.is_some() {
+            tracing::info!("✅ {} recovered, closing circuit", integration);
+        }
+
+        breaker.total_calls += 1;
+        breaker.consecutive_failures = 0;
+        breaker.opened_at = None;
+        breaker.last_success_at = Some(Utc::now());
+    }
+
+    fn record_failure(&self, integration: &'static str, error: &reqwest::Error) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(integration).or_default();
+
+        breaker.total_calls += 1;
+        breaker.total_failures += 1;
+        breaker.consecutive_failures += 1;
+        breaker.last_failure_at = Some(Utc::now());
+        breaker.last_error = Some(error.to_string());
+
+        // A failed trial call in half-open state re-opens the circuit immediately
+        if breaker.consecutive_failures >= FAILURE_THRESHOLD || breaker.opened_at.is_some() {
+            if breaker.opened_at.is_none() {
+                tracing::warn!(
+                    "🔌 {} failed {} times in a row, opening circuit for {:?}",
+                    integration,
+                    breaker.consecutive_failures,
+                    OPEN_DURATION
+                );
+            }
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    if error.is_timeout() || error.is_connect() {
+        return true;
+    }
+
+    error
+        .status()
+        .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+}
@@ -0,0 +1,126 @@
+use async_graphql::InputType;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use sqlx::PgConnection;
+
+use crate::models::{MutationError, Rejection};
+use crate::services::auth::Identity;
+
+/// A client-supplied key, tied to who sent it and what they asked for, so
+/// another caller's key, or a retry with different input, is never answered
+/// with a stored result.
+pub struct Key {
+    key: String,
+    /// The API key, else the signed-in user, that sent the request
+    caller: String,
+    /// SHA-256 of the mutation's input
+    request_hash: String,
+}
+
+impl Key {
+    /// Check a client-supplied key before using it.
+    pub fn new(
+        key: &str,
+        identity: Option<&Identity>,
+        input: &impl InputType,
+    ) -> Result<Key, String> {
+        if key.trim().is_empty() || key.len() > 255 {
+            return Err("Idempotency key must be 1 to 255 characters".to_string());
+        }
+
+        let caller = match identity {
+            Some(identity) => match identity.api_key_id {
+                Some(key) => format!("key:{}", key),
+                None => format!("user:{}", identity.user_id),
+            },
+            None => "anonymous".to_string(),
+        };
+        let input = serde_json::to_vec(&input.to_value()).map_err(|e| e.to_string())?;
+
+        Ok(Key {
+            key: key.to_string(),
+            caller,
+            request_hash: hex::encode(Sha256::digest(input)),
+        })
+    }
+}
+
+/// Claim a key for an operation inside the mutation's transaction. Returns the
+/// stored result if an earlier call with the key already committed, or `None`
+/// if this call should run. A concurrent call with the same key waits on the
+/// claim until this transaction commits (and then replays it) or rolls back
+/// (and then runs itself), so failed attempts never use up a key. Refused
+/// when the key was used for a request with different input.
+pub async fn claim<T: DeserializeOwned>(
+    conn: &mut PgConnection,
+    operation: &str,
+    key: &Key,
+) -> Result<Result<Option<T>, Rejection>, sqlx::Error> {
+    let claimed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO idempotency_keys (operation, caller, key, request_hash)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (operation, caller, key) DO NOTHING
+        RETURNING key
+        "#,
+        operation,
+        key.caller,
+        key.key,
+        key.request_hash
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if claimed.is_some() {
+        return Ok(Ok(None));
+    }
+
+    let stored = sqlx::query!(
+        "SELECT request_hash, result FROM idempotency_keys WHERE operation = $1 AND caller = $2 AND key = $3",
+        operation,
+        key.caller,
+        key.key
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if stored.request_hash != key.request_hash {
+        return Ok(Err(Rejection::new(
+            format!(
+                "Idempotency key {} was already used for a different {}",
+                key.key, operation
+            ),
+            MutationError::invalid(),
+        )));
+    }
+    let Some(result) = stored.result else {
+        return Err(sqlx::Error::RowNotFound);
+    };
+
+    let result = serde_json::from_value(result).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+
+    Ok(Ok(Some(result)))
+}
+
+/// Store the result of a claimed key, in the same transaction as the claim.
+pub async fn store<T: Serialize>(
+    conn: &mut PgConnection,
+    operation: &str,
+    key: &Key,
+    result: &T,
+) -> Result<(), sqlx::Error> {
+    let result = serde_json::to_value(result).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    sqlx::query!(
+        "UPDATE idempotency_keys SET result = $4 WHERE operation = $1 AND caller = $2 AND key = $3",
+        operation,
+        key.caller,
+        key.key,
+        result
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
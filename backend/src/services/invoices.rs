@@ -0,0 +1,329 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::models::{ArAging, ArAgingLine, Invoice, InvoicePayment, MutationError, Rejection};
+
+/// Invoice statuses that still have something owed.
+pub const UNPAID: [&str; 2] = ["open", "partially_paid"];
+
+/// Open the invoice for a sale just made, issued on the sale's day and due
+/// after the customer's payment terms.
+pub async fn open(
+    conn: &mut PgConnection,
+    sale_id: Uuid,
+    customer_id: Uuid,
+    amount: &BigDecimal,
+    issued_on: NaiveDate,
+) -> Result<Invoice, sqlx::Error> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO invoices (sale_id, customer_id, issued_on, due_on, amount)
+        SELECT $1, c.id, $3, $3::date + c.payment_terms_days, $4
+        FROM customers c
+        WHERE c.id = $2
+        RETURNING id
+        "#,
+        sale_id,
+        customer_id,
+        issued_on,
+        amount
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    by_id(&mut *conn, id).await?.ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Record a payment against an invoice. A payment that settles the balance
+/// marks the invoice paid and its sale's payment completed; one for more
+/// than is owed is refused.
+pub async fn record_payment(
+    conn: &mut PgConnection,
+    invoice_id: Uuid,
+    amount: &BigDecimal,
+    paid_on: NaiveDate,
+    payment_method: Option<&str>,
+    reference: Option<&str>,
+    notes: Option<&str>,
+) -> Result<Result<InvoicePayment, Rejection>, sqlx::Error> {
+    let invoice = sqlx::query!(
+        r#"
+        SELECT i.status, i.amount - i.amount_paid AS "balance!", s.sale_number
+        FROM invoices i
+        JOIN sales s ON s.id = i.sale_id
+        WHERE i.id = $1
+        FOR UPDATE OF i
+        "#,
+        invoice_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(invoice) = invoice else {
+        return Ok(Err(Rejection::new(
+            "Invoice not found".to_string(),
+            MutationError::not_found("Invoice"),
+        )));
+    };
+    if *amount <= BigDecimal::from(0) {
+        return Ok(Err("A payment must be more than 0".to_string().into()));
+    }
+    if !UNPAID.contains(&invoice.status.as_str()) {
+        return Ok(Err(format!(
+            "The invoice for sale {} is {}; there's nothing to pay",
+            invoice.sale_number, invoice.status
+        )
+        .into()));
+    }
+    if *amount > invoice.balance {
+        return Ok(Err(format!(
+            "A payment of {} is more than the {} owed on sale {}",
+            amount, invoice.balance, invoice.sale_number
+        )
+        .into()));
+    }
+
+    let payment = sqlx::query_as!(
+        InvoicePayment,
+        r#"
+        INSERT INTO invoice_payments (invoice_id, amount, paid_on, payment_method, reference, notes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, invoice_id, amount, paid_on, payment_method, reference, notes, created_at
+        "#,
+        invoice_id,
+        amount,
+        paid_on,
+        payment_method,
+        reference,
+        notes
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let invoice = sqlx::query!(
+        r#"
+        UPDATE invoices
+        SET amount_paid = amount_paid + $2,
+            status = CASE WHEN amount_paid + $2 >= amount THEN 'paid' ELSE 'partially_paid' END,
+            paid_on = CASE WHEN amount_paid + $2 >= amount
+                THEN (SELECT MAX(p.paid_on) FROM invoice_payments p WHERE p.invoice_id = $1)
+            END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING sale_id, status
+        "#,
+        invoice_id,
+        amount
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if invoice.status == "paid" {
+        sqlx::query!(
+            r#"
+            UPDATE sales
+            SET payment_status = 'completed', updated_at = NOW()
+            WHERE id = $1 AND payment_status = 'pending'
+            "#,
+            invoice.sale_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(Ok(payment))
+}
+
+/// Void a refunded sale's invoice if anything was still owed on it.
+pub async fn void_for_sale(
+    executor: impl PgExecutor<'_>,
+    sale_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE invoices
+        SET status = 'void', updated_at = NOW()
+        WHERE sale_id = $1 AND status = ANY($2)
+        "#,
+        sale_id,
+        &UNPAID.map(str::to_string)
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// An invoice by ID.
+pub async fn by_id(
+    executor: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Option<Invoice>, sqlx::Error> {
+    sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            i.id, i.sale_id, s.sale_number, i.customer_id, c.name AS customer_name,
+            i.issued_on, i.due_on, i.amount, i.amount_paid,
+            i.amount - i.amount_paid AS "balance!", i.status,
+            (i.status IN ('open', 'partially_paid') AND i.due_on < CURRENT_DATE) AS "is_overdue!",
+            i.paid_on, i.created_at, i.updated_at
+        FROM invoices i
+        JOIN sales s ON s.id = i.sale_id
+        JOIN customers c ON c.id = i.customer_id
+        WHERE i.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// A sale's invoice, if it was invoiced.
+pub async fn for_sale(
+    executor: impl PgExecutor<'_>,
+    sale_id: Uuid,
+) -> Result<Option<Invoice>, sqlx::Error> {
+    sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            i.id, i.sale_id, s.sale_number, i.customer_id, c.name AS customer_name,
+            i.issued_on, i.due_on, i.amount, i.amount_paid,
+            i.amount - i.amount_paid AS "balance!", i.status,
+            (i.status IN ('open', 'partially_paid') AND i.due_on < CURRENT_DATE) AS "is_overdue!",
+            i.paid_on, i.created_at, i.updated_at
+        FROM invoices i
+        JOIN sales s ON s.id = i.sale_id
+        JOIN customers c ON c.id = i.customer_id
+        WHERE i.sale_id = $1
+        "#,
+        sale_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Invoices by due date, optionally for one customer, with one status, or
+/// only those overdue.
+pub async fn invoices(
+    executor: impl PgExecutor<'_>,
+    customer_id: Option<Uuid>,
+    status: Option<&str>,
+    overdue_only: bool,
+) -> Result<Vec<Invoice>, sqlx::Error> {
+    sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            i.id, i.sale_id, s.sale_number, i.customer_id, c.name AS customer_name,
+            i.issued_on, i.due_on, i.amount, i.amount_paid,
+            i.amount - i.amount_paid AS "balance!", i.status,
+            (i.status IN ('open', 'partially_paid') AND i.due_on < CURRENT_DATE) AS "is_overdue!",
+            i.paid_on, i.created_at, i.updated_at
+        FROM invoices i
+        JOIN sales s ON s.id = i.sale_id
+        JOIN customers c ON c.id = i.customer_id
+        WHERE ($1::uuid IS NULL OR i.customer_id = $1)
+            AND ($2::varchar IS NULL OR i.status = $2)
+            AND (NOT $3 OR (i.status IN ('open', 'partially_paid') AND i.due_on < CURRENT_DATE))
+        ORDER BY i.due_on, s.sale_number
+        "#,
+        customer_id,
+        status,
+        overdue_only
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Payments against an invoice, oldest first.
+pub async fn payments(
+    executor: impl PgExecutor<'_>,
+    invoice_id: Uuid,
+) -> Result<Vec<InvoicePayment>, sqlx::Error> {
+    sqlx::query_as!(
+        InvoicePayment,
+        r#"
+        SELECT id, invoice_id, amount, paid_on, payment_method, reference, notes, created_at
+        FROM invoice_payments
+        WHERE invoice_id = $1
+        ORDER BY paid_on, created_at
+        "#,
+        invoice_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// What customers owed at the end of `as_of`, bucketed by how many days
+/// before then each invoice was issued. Payments made after `as_of` don't
+/// count, so past dates give the aging as it stood then; void invoices are
+/// left out.
+pub async fn aging(
+    executor: impl PgExecutor<'_>,
+    as_of: NaiveDate,
+) -> Result<ArAging, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        WITH balances AS (
+            SELECT
+                i.customer_id,
+                $1::date - i.issued_on AS age,
+                i.due_on < $1::date AS overdue,
+                i.amount - COALESCE(
+                    (SELECT SUM(p.amount) FROM invoice_payments p
+                     WHERE p.invoice_id = i.id AND p.paid_on <= $1),
+                    0
+                ) AS balance
+            FROM invoices i
+            WHERE i.status <> 'void' AND i.issued_on <= $1
+        )
+        SELECT
+            c.id AS "customer_id!",
+            c.name AS "customer_name!",
+            COUNT(*) AS "invoices!",
+            COALESCE(SUM(b.balance) FILTER (WHERE b.age <= 30), 0) AS "days_0_to_30!",
+            COALESCE(SUM(b.balance) FILTER (WHERE b.age BETWEEN 31 AND 60), 0) AS "days_31_to_60!",
+            COALESCE(SUM(b.balance) FILTER (WHERE b.age BETWEEN 61 AND 90), 0) AS "days_61_to_90!",
+            COALESCE(SUM(b.balance) FILTER (WHERE b.age > 90), 0) AS "over_90_days!",
+            SUM(b.balance) AS "total!",
+            COALESCE(SUM(b.balance) FILTER (WHERE b.overdue), 0) AS "overdue!"
+        FROM balances b
+        JOIN customers c ON c.id = b.customer_id
+        WHERE b.balance > 0
+        GROUP BY c.id, c.name
+        ORDER BY 8 DESC, c.name
+        "#,
+        as_of
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let customers: Vec<ArAgingLine> = rows
+        .into_iter()
+        .map(|row| ArAgingLine {
+            customer_id: row.customer_id,
+            customer_name: row.customer_name,
+            invoices: row.invoices,
+            days_0_to_30: row.days_0_to_30,
+            days_31_to_60: row.days_31_to_60,
+            days_61_to_90: row.days_61_to_90,
+            over_90_days: row.over_90_days,
+            total: row.total,
+            overdue: row.overdue,
+        })
+        .collect();
+
+    Ok(ArAging {
+        as_of,
+        days_0_to_30: customers.iter().map(|line| &line.days_0_to_30).sum(),
+        days_31_to_60: customers.iter().map(|line| &line.days_31_to_60).sum(),
+        days_61_to_90: customers.iter().map(|line| &line.days_61_to_90).sum(),
+        over_90_days: customers.iter().map(|line| &line.over_90_days).sum(),
+        total: customers.iter().map(|line| &line.total).sum(),
+        overdue: customers.iter().map(|line| &line.overdue).sum(),
+        customers,
+    })
+}
@@ -0,0 +1,517 @@
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{
+    DEFAULT_LOCALE, LabelRunInput, LabelRunLine, LabelRunPlan, LabelSkuCount, LabelStockNeed,
+};
+use crate::services::qr::QrCode;
+use crate::services::traceability;
+
+// Sheet layout: 30-up address labels (Avery 5160 and compatibles), 2-5/8" x 1"
+// in 3 columns of 10 on US Letter. All measurements in points.
+const PAGE_WIDTH: f64 = 612.0;
+const PAGE_HEIGHT: f64 = 792.0;
+const COLUMNS: usize = 3;
+const ROWS: usize = 10;
+const LEFT_MARGIN: f64 = 13.5;
+const TOP_MARGIN: f64 = 36.0;
+const COLUMN_PITCH: f64 = 198.0;
+const ROW_PITCH: f64 = 72.0;
+const LABEL_WIDTH: f64 = 189.0;
+const PADDING: f64 = 9.0;
+/// Side of the QR code on a batch label, quiet zone included.
+const QR_SIZE: f64 = 64.0;
+
+/// Work out the labels needed to package the given batches: one line per
+/// batch and pack size, totals per SKU, and the label stock drawn down.
+/// Anything that would stop the run being printed is listed in `problems`.
+pub async fn plan(
+    conn: &mut PgConnection,
+    input: &LabelRunInput,
+) -> Result<LabelRunPlan, sqlx::Error> {
+    let locale = input.locale.as_deref().unwrap_or(DEFAULT_LOCALE);
+    let mut lines: Vec<LabelRunLine> = Vec::new();
+    let mut problems = Vec::new();
+
+    if input.batches.is_empty() {
+        problems.push("List at least one batch to label".to_string());
+    }
+
+    for (index, requested) in input.batches.iter().enumerate() {
+        if input.batches[..index]
+            .iter()
+            .any(|b| b.batch_id == requested.batch_id)
+        {
+            problems.push(format!(
+                "Batch {} is listed more than once",
+                requested.batch_id
+            ));
+            continue;
+        }
+
+        let batch = sqlx::query!(
+            "SELECT batch_number, product_inventory_id, status FROM production_batches WHERE id = $1",
+            requested.batch_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(batch) = batch else {
+            problems.push(format!("Production batch {} not found", requested.batch_id));
+            continue;
+        };
+
+        if batch.status != "completed" {
+            problems.push(format!(
+                "Batch {} is {}; only completed batches can be packaged",
+                batch.batch_number, batch.status
+            ));
+            continue;
+        }
+
+        let lot = sqlx::query!(
+            r#"
+            SELECT
+                LEAST(l.remaining_quantity, i.current_stock) as "available!",
+                COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) as best_by
+            FROM inventory_lots l
+            JOIN inventory i ON i.id = l.inventory_id
+            WHERE l.production_batch_id = $1 AND l.inventory_id = $2 AND l.source_type = 'production'
+            "#,
+            requested.batch_id,
+            batch.product_inventory_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(lot) = lot else {
+            problems.push(format!(
+                "Batch {} has no recorded output lot",
+                batch.batch_number
+            ));
+            continue;
+        };
+
+        let packs: Vec<(Uuid, i32)> = match &requested.packs {
+            Some(packs) => packs
+                .iter()
+                .map(|p| (p.pack_size_id, p.pack_count))
+                .collect(),
+            None => {
+                let sizes = sqlx::query!(
+                    "SELECT id, bulk_quantity_per_pack FROM pack_sizes WHERE bulk_inventory_id = $1",
+                    batch.product_inventory_id
+                )
+                .fetch_all(&mut *conn)
+                .await?;
+
+                let [size] = sizes.as_slice() else {
+                    problems.push(format!(
+                        "Batch {}'s product has {} pack sizes; list the packs to label",
+                        batch.batch_number,
+                        sizes.len()
+                    ));
+                    continue;
+                };
+
+                let fills = (&lot.available / &size.bulk_quantity_per_pack)
+                    .with_scale_round(0, RoundingMode::Down);
+                vec![(size.id, fills.to_i32().unwrap_or(0))]
+            }
+        };
+
+        let mut bulk_needed = BigDecimal::from(0);
+
+        for (pack_index, &(pack_size_id, pack_count)) in packs.iter().enumerate() {
+            if packs[..pack_index].iter().any(|p| p.0 == pack_size_id) {
+                problems.push(format!(
+                    "Pack size {} is listed more than once for batch {}",
+                    pack_size_id, batch.batch_number
+                ));
+                continue;
+            }
+
+            let pack = sqlx::query!(
+                r#"
+                SELECT
+                    p.name, p.bulk_inventory_id, p.packaged_inventory_id,
+                    p.bulk_quantity_per_pack, p.label_inventory_id, p.labels_per_pack,
+                    COALESCE(t.name, i.name) as "product_name!",
+                    t.label_text as "label_text?"
+                FROM pack_sizes p
+                JOIN inventory i ON i.id = p.bulk_inventory_id
+                LEFT JOIN LATERAL (
+                    SELECT name, label_text
+                    FROM product_translations
+                    WHERE inventory_id IN (p.packaged_inventory_id, p.bulk_inventory_id)
+                        AND locale IN ($2::varchar, split_part($2, '-', 1), $3)
+                    ORDER BY
+                        inventory_id = p.packaged_inventory_id DESC,
+                        locale = $2::varchar DESC,
+                        locale = split_part($2, '-', 1) DESC
+                    LIMIT 1
+                ) t ON true
+                WHERE p.id = $1
+                "#,
+                pack_size_id,
+                locale,
+                DEFAULT_LOCALE
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            let Some(pack) = pack else {
+                problems.push(format!("Pack size {} not found", pack_size_id));
+                continue;
+            };
+
+            if pack.bulk_inventory_id != batch.product_inventory_id {
+                problems.push(format!(
+                    "Pack size '{}' is not filled from batch {}'s product",
+                    pack.name, batch.batch_number
+                ));
+                continue;
+            }
+
+            if pack_count <= 0 {
+                problems.push(format!(
+                    "Batch {} has nothing to package as '{}'",
+                    batch.batch_number, pack.name
+                ));
+                continue;
+            }
+
+            bulk_needed += BigDecimal::from(pack_count) * &pack.bulk_quantity_per_pack;
+
+            lines.push(LabelRunLine {
+                batch_id: requested.batch_id,
+                batch_number: batch.batch_number.clone(),
+                pack_size_id,
+                pack_name: pack.name,
+                product_name: pack.product_name,
+                label_text: pack.label_text,
+                best_by: lot.best_by,
+                pack_count,
+                label_count: pack_count * pack.labels_per_pack,
+                label_inventory_id: pack.label_inventory_id,
+            });
+        }
+
+        if bulk_needed > lot.available {
+            problems.push(format!(
+                "Batch {} has {} left, not enough for the packs listed ({})",
+                batch.batch_number, lot.available, bulk_needed
+            ));
+        }
+    }
+
+    let mut skus: Vec<LabelSkuCount> = Vec::new();
+    for line in &lines {
+        match skus
+            .iter_mut()
+            .find(|s| s.pack_size_id == line.pack_size_id)
+        {
+            Some(sku) => {
+                sku.pack_count += line.pack_count;
+                sku.label_count += line.label_count;
+            }
+            None => {
+                let packaged_inventory_id = sqlx::query_scalar!(
+                    "SELECT packaged_inventory_id FROM pack_sizes WHERE id = $1",
+                    line.pack_size_id
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+
+                skus.push(LabelSkuCount {
+                    pack_size_id: line.pack_size_id,
+                    packaged_inventory_id,
+                    pack_name: line.pack_name.clone(),
+                    product_name: line.product_name.clone(),
+                    pack_count: line.pack_count,
+                    label_count: line.label_count,
+                });
+            }
+        }
+    }
+
+    let mut label_stock: Vec<LabelStockNeed> = Vec::new();
+    for line in &lines {
+        let Some(label_id) = line.label_inventory_id else {
+            continue;
+        };
+
+        match label_stock.iter_mut().find(|s| s.inventory_id == label_id) {
+            Some(need) => need.needed += BigDecimal::from(line.label_count),
+            None => {
+                let item = sqlx::query!(
+                    "SELECT name, current_stock FROM inventory WHERE id = $1",
+                    label_id
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+
+                label_stock.push(LabelStockNeed {
+                    inventory_id: label_id,
+                    name: item.name,
+                    needed: BigDecimal::from(line.label_count),
+                    on_hand: item.current_stock,
+                    shortfall: BigDecimal::from(0),
+                });
+            }
+        }
+    }
+
+    for need in &mut label_stock {
+        if need.needed > need.on_hand {
+            need.shortfall = &need.needed - &need.on_hand;
+            problems.push(format!(
+                "Not enough {}: need {}, have {}",
+                need.name, need.needed, need.on_hand
+            ));
+        }
+    }
+
+    Ok(LabelRunPlan {
+        total_labels: lines.iter().map(|line| line.label_count).sum(),
+        lines,
+        skus,
+        label_stock,
+        problems,
+    })
+}
+
+/// Render every label in the plan onto 30-up letter sheets as a PDF, in plan
+/// order. Uses the standard Helvetica fonts, so nothing needs embedding.
+pub fn render_pdf(lines: &[LabelRunLine], packed_on: NaiveDate) -> Vec<u8> {
+    let faces: Vec<&LabelRunLine> = lines
+        .iter()
+        .flat_map(|line| std::iter::repeat_n(line, line.label_count.max(0) as usize))
+        .collect();
+
+    let pages: Vec<String> = faces
+        .chunks(COLUMNS * ROWS)
+        .map(|sheet| {
+            let mut content = String::new();
+            for (slot, line) in sheet.iter().enumerate() {
+                let x = LEFT_MARGIN + (slot % COLUMNS) as f64 * COLUMN_PITCH + PADDING;
+                let top = PAGE_HEIGHT - TOP_MARGIN - (slot / COLUMNS) as f64 * ROW_PITCH;
+                let mut text = vec![
+                    ("F2", 10.0, 14.0, line.product_name.clone()),
+                    ("F1", 7.5, 25.0, line.pack_name.clone()),
+                ];
+                if let Some(label_text) = line.label_text.as_deref().and_then(|t| t.lines().next())
+                {
+                    text.push(("F1", 6.5, 35.0, label_text.to_string()));
+                }
+                text.push((
+                    "F1",
+                    7.0,
+                    47.0,
+                    format!("Batch {}  Packed {}", line.batch_number, packed_on),
+                ));
+                if let Some(best_by) = line.best_by {
+                    text.push(("F2", 7.0, 57.0, format!("Best by {}", best_by)));
+                }
+
+                for (font, size, offset, value) in text {
+                    content.push_str(&format!(
+                        "BT /{} {} Tf {:.1} {:.1} Td ({}) Tj ET\n",
+                        font,
+                        size,
+                        x,
+                        top - offset,
+                        pdf_string(&fit(&value, size, LABEL_WIDTH - 2.0 * PADDING))
+                    ));
+                }
+            }
+            content
+        })
+        .collect();
+
+    write_pdf(&pages)
+}
+
+/// What goes on a finished batch's jar labels.
+pub struct BatchLabel {
+    pub product_name: String,
+    pub batch_number: String,
+    pub produced_on: NaiveDate,
+    pub best_by: Option<NaiveDate>,
+    /// Only completed batches are labelled
+    pub status: String,
+}
+
+/// Label details for a batch.
+pub async fn batch_label(pool: &PgPool, batch_id: Uuid) -> Result<Option<BatchLabel>, sqlx::Error> {
+    sqlx::query_as!(
+        BatchLabel,
+        r#"
+        SELECT
+            b.batch_number, b.status, i.name AS product_name,
+            COALESCE(b.completion_date, b.production_date)::date AS "produced_on!",
+            (SELECT COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days)
+             FROM inventory_lots l
+             WHERE l.production_batch_id = b.id AND l.inventory_id = b.product_inventory_id
+                AND l.source_type = 'production'
+             LIMIT 1) AS best_by
+        FROM production_batches b
+        JOIN inventory i ON i.id = b.product_inventory_id
+        WHERE b.id = $1
+        "#,
+        batch_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Render `count` copies of a batch's label onto 30-up letter sheets as a
+/// PDF. Each carries a QR code of the batch's public trace page, which
+/// `lookupByBarcode` also resolves back to the batch.
+pub fn render_batch_labels(label: &BatchLabel, count: usize) -> Vec<u8> {
+    let qr = QrCode::encode(&traceability::trace_url(&label.batch_number));
+    let text_width = LABEL_WIDTH - 2.0 * PADDING - if qr.is_some() { QR_SIZE } else { 0.0 };
+
+    let mut text = vec![
+        ("F2", 10.0, 14.0, label.product_name.clone()),
+        ("F1", 7.5, 28.0, format!("Batch {}", label.batch_number)),
+        ("F1", 7.0, 40.0, format!("Produced {}", label.produced_on)),
+    ];
+    if let Some(best_by) = label.best_by {
+        text.push(("F2", 7.0, 52.0, format!("Best by {}", best_by)));
+    }
+
+    let pages: Vec<String> = (0..count)
+        .collect::<Vec<_>>()
+        .chunks(COLUMNS * ROWS)
+        .map(|sheet| {
+            let mut content = String::new();
+            for slot in 0..sheet.len() {
+                let x = LEFT_MARGIN + (slot % COLUMNS) as f64 * COLUMN_PITCH + PADDING;
+                let top = PAGE_HEIGHT - TOP_MARGIN - (slot / COLUMNS) as f64 * ROW_PITCH;
+
+                for (font, size, offset, value) in &text {
+                    content.push_str(&format!(
+                        "BT /{} {} Tf {:.1} {:.1} Td ({}) Tj ET\n",
+                        font,
+                        size,
+                        x,
+                        top - offset,
+                        pdf_string(&fit(value, *size, text_width))
+                    ));
+                }
+
+                // Right-hand end of the label, centred vertically
+                if let Some(qr) = &qr {
+                    content.push_str(&qr.pdf(
+                        x - PADDING + LABEL_WIDTH - QR_SIZE,
+                        top - (ROW_PITCH + QR_SIZE) / 2.0,
+                        QR_SIZE,
+                    ));
+                }
+            }
+            content
+        })
+        .collect();
+
+    write_pdf(&pages)
+}
+
+/// Shorten text to roughly fit `width` points at the given font size.
+fn fit(text: &str, size: f64, width: f64) -> String {
+    let max_chars = (width / (size * 0.55)) as usize;
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    short.push_str("...");
+    short
+}
+
+/// Escape text as a PDF literal string in WinAnsiEncoding.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            '€' => out.push_str("\\200"),
+            '‘' => out.push_str("\\221"),
+            '’' => out.push_str("\\222"),
+            '“' => out.push_str("\\223"),
+            '”' => out.push_str("\\224"),
+            '•' => out.push_str("\\225"),
+            '–' => out.push_str("\\226"),
+            '—' => out.push_str("\\227"),
+            c if c.is_whitespace() => out.push(' '),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+/// Assemble a PDF from one content stream per page.
+fn write_pdf(pages: &[String]) -> Vec<u8> {
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + 2 * i).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+
+    for (page_id, content) in page_ids.iter().zip(pages) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
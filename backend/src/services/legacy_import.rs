@@ -0,0 +1,922 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{Map, Value, json};
+use sqlx::{Connection, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{LegacyImportField, LegacyImportOutcome};
+use crate::services::discounts::{self, Discount};
+use crate::services::locations;
+
+/// Note prepended to every imported batch, sale, and purchase.
+const IMPORT_NOTE: &str = "Imported from legacy spreadsheet";
+
+/// (field, default column header, required)
+type FieldSpec = (&'static str, &'static str, bool);
+
+const BATCH_FIELDS: &[FieldSpec] = &[
+    ("batch_number", "Batch #", true),
+    ("product", "Product", true),
+    ("batch_size", "Batch Size", true),
+    ("unit", "Unit", true),
+    ("start_date", "Start Date", true),
+    ("completion_date", "Completion Date", false),
+    ("status", "Status", false),
+    ("actual_yield", "Yield", false),
+    ("storage_location", "Location", false),
+    ("notes", "Notes", false),
+];
+
+/// Sales sheets have one row per line item; rows sharing a sale number form one sale.
+const SALE_FIELDS: &[FieldSpec] = &[
+    ("sale_number", "Sale #", true),
+    ("sale_date", "Date", true),
+    ("customer", "Customer", false),
+    ("product", "Product", true),
+    ("quantity", "Qty", true),
+    ("unit_price", "Unit Price", true),
+    ("tax_amount", "Tax", false),
+    ("discount_amount", "Discount", false),
+    ("total_amount", "Total", false),
+    ("payment_method", "Payment Method", false),
+    ("notes", "Notes", false),
+];
+
+const PURCHASE_FIELDS: &[FieldSpec] = &[
+    ("purchase_date", "Date", true),
+    ("supplier", "Supplier", false),
+    ("product", "Item", true),
+    ("quantity", "Qty", true),
+    ("unit_cost", "Unit Cost", true),
+    ("lot_number", "Lot #", false),
+    ("expiry_date", "Expiry", false),
+    ("notes", "Notes", false),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum RecordType {
+    Batches,
+    Sales,
+    Purchases,
+}
+
+impl RecordType {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "batches" => Some(RecordType::Batches),
+            "sales" => Some(RecordType::Sales),
+            "purchases" => Some(RecordType::Purchases),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RecordType::Batches => "batches",
+            RecordType::Sales => "sales",
+            RecordType::Purchases => "purchases",
+        }
+    }
+
+    fn fields(self) -> &'static [FieldSpec] {
+        match self {
+            RecordType::Batches => BATCH_FIELDS,
+            RecordType::Sales => SALE_FIELDS,
+            RecordType::Purchases => PURCHASE_FIELDS,
+        }
+    }
+
+    /// Fields the importer reads and their default sheet columns.
+    pub fn describe(self) -> Vec<LegacyImportField> {
+        self.fields()
+            .iter()
+            .map(|&(field, column, required)| LegacyImportField {
+                field: field.to_string(),
+                default_column: column.to_string(),
+                required,
+            })
+            .collect()
+    }
+}
+
+/// Which sheet column each field is read from.
+pub struct Mapping {
+    record_type: RecordType,
+    columns: HashMap<&'static str, String>,
+}
+
+impl Mapping {
+    /// Default columns, overridden by `{"field": "Column Header"}` entries.
+    pub fn new(record_type: RecordType, overrides: Option<&Value>) -> Result<Self, String> {
+        let mut columns: HashMap<&'static str, String> = record_type
+            .fields()
+            .iter()
+            .map(|&(field, column, _)| (field, column.to_string()))
+            .collect();
+
+        let Some(overrides) = overrides else {
+            return Ok(Self {
+                record_type,
+                columns,
+            });
+        };
+
+        let overrides = overrides.as_object().ok_or_else(|| {
+            "Mapping must be an object of {\"field\": \"Column Header\"}".to_string()
+        })?;
+
+        for (field, column) in overrides {
+            let Some(&(field, _, _)) = record_type.fields().iter().find(|(f, _, _)| f == field)
+            else {
+                let known: Vec<&str> = record_type.fields().iter().map(|(f, _, _)| *f).collect();
+                return Err(format!(
+                    "Unknown {} field '{}' in mapping; expected one of: {}",
+                    record_type.name(),
+                    field,
+                    known.join(", ")
+                ));
+            };
+
+            let column = column
+                .as_str()
+                .ok_or_else(|| format!("Mapping for '{}' must be a column header", field))?;
+            columns.insert(field, column.to_string());
+        }
+
+        Ok(Self {
+            record_type,
+            columns,
+        })
+    }
+
+    fn required(&self, field: &str) -> bool {
+        self.record_type
+            .fields()
+            .iter()
+            .any(|&(f, _, required)| f == field && required)
+    }
+}
+
+/// One record to import: a single row, or every row of a multi-line sale.
+struct Record<'a> {
+    row_index: usize,
+    source_key: Option<String>,
+    rows: Vec<&'a Map<String, Value>>,
+    errors: Vec<String>,
+}
+
+enum Outcome {
+    Imported {
+        entity_id: Uuid,
+        warnings: Vec<String>,
+    },
+    Rejected(Vec<String>),
+}
+
+/// Import sheet rows inside one transaction, each record in its own savepoint
+/// so a bad row is rejected without losing the rest. A dry run does the same
+/// work and rolls it all back.
+///
+/// Historical records are written as they happened but never touch current
+/// stock levels: the sheets' stock movements are already reflected in today's
+/// counts.
+pub async fn run(
+    pool: &PgPool,
+    mapping: &Mapping,
+    rows: &[Value],
+    dry_run: bool,
+) -> Result<Vec<LegacyImportOutcome>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut outcomes = Vec::new();
+
+    for record in group(mapping, rows) {
+        outcomes.push(import_record(&mut tx, mapping, record).await?);
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    Ok(outcomes)
+}
+
+fn group<'a>(mapping: &Mapping, rows: &'a [Value]) -> Vec<Record<'a>> {
+    let mut records: Vec<Record<'a>> = Vec::new();
+    let mut sales: HashMap<String, usize> = HashMap::new();
+
+    for (row_index, value) in rows.iter().enumerate() {
+        let Some(row) = value.as_object() else {
+            records.push(Record {
+                row_index,
+                source_key: None,
+                rows: Vec::new(),
+                errors: vec!["Row is not an object keyed by column header".to_string()],
+            });
+            continue;
+        };
+
+        let source_key = match mapping.record_type {
+            RecordType::Batches => text(row, mapping, "batch_number"),
+            RecordType::Sales => text(row, mapping, "sale_number"),
+            RecordType::Purchases => purchase_fingerprint(row, mapping),
+        };
+
+        if let (RecordType::Sales, Some(sale_number)) = (mapping.record_type, &source_key)
+            && let Some(&index) = sales.get(sale_number)
+        {
+            records[index].rows.push(row);
+            continue;
+        }
+
+        let errors = match source_key {
+            Some(_) => Vec::new(),
+            None => vec![match mapping.record_type {
+                RecordType::Batches => "Missing batch number".to_string(),
+                RecordType::Sales => "Missing sale number".to_string(),
+                RecordType::Purchases => "Missing date, item, or quantity".to_string(),
+            }],
+        };
+
+        if let (RecordType::Sales, Some(sale_number)) = (mapping.record_type, &source_key) {
+            sales.insert(sale_number.clone(), records.len());
+        }
+
+        records.push(Record {
+            row_index,
+            source_key,
+            rows: vec![row],
+            errors,
+        });
+    }
+
+    records
+}
+
+/// Purchase sheets have no purchase number, so a row is identified by what was
+/// bought, when, and how much.
+fn purchase_fingerprint(row: &Map<String, Value>, mapping: &Mapping) -> Option<String> {
+    Some(format!(
+        "{}|{}|{}|{}",
+        text(row, mapping, "purchase_date")?,
+        text(row, mapping, "product")?.to_lowercase(),
+        text(row, mapping, "quantity")?,
+        text(row, mapping, "lot_number").unwrap_or_default()
+    ))
+}
+
+async fn import_record(
+    conn: &mut PgConnection,
+    mapping: &Mapping,
+    record: Record<'_>,
+) -> Result<LegacyImportOutcome, sqlx::Error> {
+    let record_type = mapping.record_type.name();
+
+    let Some(ref source_key) = record.source_key else {
+        return Ok(LegacyImportOutcome {
+            row_index: record.row_index as i32,
+            source_key: None,
+            status: "rejected".to_string(),
+            entity_id: None,
+            issues: record.errors,
+        });
+    };
+
+    let already_imported = sqlx::query_scalar!(
+        r#"
+        SELECT entity_id FROM legacy_import_rows
+        WHERE record_type = $1 AND source_key = $2 AND status = 'imported'
+        "#,
+        record_type,
+        source_key
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(entity_id) = already_imported {
+        return Ok(LegacyImportOutcome {
+            row_index: record.row_index as i32,
+            source_key: Some(source_key.clone()),
+            status: "skipped".to_string(),
+            entity_id,
+            issues: vec!["Already imported".to_string()],
+        });
+    }
+
+    let mut savepoint = conn.begin().await?;
+
+    let result = match mapping.record_type {
+        RecordType::Batches => import_batch(&mut savepoint, mapping, &record).await,
+        RecordType::Sales => import_sale(&mut savepoint, mapping, &record).await,
+        RecordType::Purchases => import_purchase(&mut savepoint, mapping, &record).await,
+    };
+
+    let (status, entity_id, issues) = match result {
+        Ok(Outcome::Imported {
+            entity_id,
+            warnings,
+        }) => {
+            savepoint.commit().await?;
+            ("imported", Some(entity_id), warnings)
+        }
+        Ok(Outcome::Rejected(errors)) => {
+            savepoint.rollback().await?;
+            ("rejected", None, errors)
+        }
+        // Values the sheet allowed but the schema doesn't (e.g. too many digits)
+        Err(sqlx::Error::Database(e)) => {
+            savepoint.rollback().await?;
+            ("rejected", None, vec![e.message().to_string()])
+        }
+        Err(e) => return Err(e),
+    };
+
+    let raw = match record.rows.as_slice() {
+        [row] => Value::Object((*row).clone()),
+        rows => Value::Array(
+            rows.iter()
+                .map(|row| Value::Object((*row).clone()))
+                .collect(),
+        ),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO legacy_import_rows (record_type, source_key, status, entity_id, issues, raw, imported_at)
+        VALUES ($1, $2, $3::varchar, $4, $5, $6, CASE WHEN $3::varchar = 'imported' THEN NOW() END)
+        ON CONFLICT (record_type, source_key) DO UPDATE
+        SET status = EXCLUDED.status,
+            entity_id = EXCLUDED.entity_id,
+            issues = EXCLUDED.issues,
+            raw = EXCLUDED.raw,
+            imported_at = EXCLUDED.imported_at,
+            updated_at = NOW()
+        "#,
+        record_type,
+        source_key,
+        status,
+        entity_id,
+        json!(issues),
+        raw
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(LegacyImportOutcome {
+        row_index: record.row_index as i32,
+        source_key: Some(source_key.clone()),
+        status: status.to_string(),
+        entity_id,
+        issues,
+    })
+}
+
+async fn import_batch(
+    conn: &mut PgConnection,
+    mapping: &Mapping,
+    record: &Record<'_>,
+) -> Result<Outcome, sqlx::Error> {
+    let mut row = Row::new(record.rows[0], mapping);
+    let mut warnings = Vec::new();
+
+    let batch_number = record.source_key.clone().unwrap_or_default();
+    let product = row.required_text("product");
+    let batch_size = row.decimal("batch_size");
+    let unit = row.required_text("unit");
+    let start_date = row.date("start_date");
+    let completion_date = row.date("completion_date");
+    let actual_yield = row.decimal("actual_yield");
+
+    let status = match row
+        .text("status")
+        .map(|s| s.to_lowercase().replace(' ', "_"))
+    {
+        Some(status) if ["completed", "failed", "in_progress"].contains(&status.as_str()) => status,
+        Some(status) => {
+            row.errors.push(format!(
+                "Unknown status '{}'; expected completed, failed, or in progress",
+                status
+            ));
+            status
+        }
+        None if completion_date.is_some() => "completed".to_string(),
+        None => {
+            warnings.push("No status or completion date; imported as in progress".to_string());
+            "in_progress".to_string()
+        }
+    };
+
+    let product_id = match product {
+        Some(ref product) => row.product(conn, product).await?,
+        None => None,
+    };
+
+    let existing = sqlx::query_scalar!(
+        "SELECT id FROM production_batches WHERE batch_number = $1",
+        batch_number
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if existing.is_some() {
+        row.errors
+            .push(format!("Batch number {} already exists", batch_number));
+    }
+
+    if batch_size
+        .as_ref()
+        .is_some_and(|size| *size <= BigDecimal::from(0))
+    {
+        row.errors
+            .push("Batch size must be greater than 0".to_string());
+    }
+
+    let (Some(product_id), Some(batch_size), Some(unit), Some(start_date), true) = (
+        product_id,
+        batch_size,
+        unit,
+        start_date,
+        row.errors.is_empty(),
+    ) else {
+        return Ok(Outcome::Rejected(row.errors));
+    };
+
+    if completion_date.is_some_and(|completed| completed < start_date) {
+        warnings.push("Completion date is before start date".to_string());
+    }
+
+    if status == "completed" && completion_date.is_none() {
+        warnings.push("Completed batch has no completion date".to_string());
+    }
+
+    if status == "completed" && actual_yield.is_none() {
+        warnings.push("Completed batch has no yield".to_string());
+    }
+
+    if actual_yield.as_ref().is_some_and(|y| *y > batch_size) {
+        warnings.push(format!(
+            "Yield {} is more than the batch size {}",
+            actual_yield.as_ref().unwrap(),
+            batch_size
+        ));
+    }
+
+    if start_date > Utc::now() {
+        warnings.push("Start date is in the future".to_string());
+    }
+
+    let yield_percentage = actual_yield.as_ref().map(|y| {
+        (y * BigDecimal::from(100) / &batch_size).with_scale_round(2, RoundingMode::HalfUp)
+    });
+
+    let production_time_hours = completion_date
+        .map(|completed| completed - start_date)
+        .filter(|duration| duration.num_hours() >= 0 && duration.num_hours() < 10_000)
+        .map(|duration| BigDecimal::from(duration.num_minutes()) / BigDecimal::from(60))
+        .map(|hours| hours.with_scale_round(2, RoundingMode::HalfUp));
+
+    let storage_location_id = match row.text("storage_location") {
+        Some(name) => Some(locations::resolve(&mut *conn, &name).await?),
+        None => None,
+    };
+
+    let batch_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO production_batches (
+            batch_number, product_inventory_id, batch_size, unit,
+            start_date, completion_date, production_date, status,
+            production_time_hours, yield_percentage, actual_yield,
+            storage_location_id, notes
+        ) VALUES ($1, $2, $3, $4, $5, $6, $5, $7, $8, $9, $10, $11, $12)
+        RETURNING id
+        "#,
+        batch_number,
+        product_id,
+        batch_size,
+        unit,
+        start_date,
+        completion_date,
+        status,
+        production_time_hours,
+        yield_percentage,
+        actual_yield,
+        storage_location_id,
+        import_note(row.text("notes"))
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Outcome::Imported {
+        entity_id: batch_id,
+        warnings,
+    })
+}
+
+async fn import_sale(
+    conn: &mut PgConnection,
+    mapping: &Mapping,
+    record: &Record<'_>,
+) -> Result<Outcome, sqlx::Error> {
+    let mut header = Row::new(record.rows[0], mapping);
+    let mut warnings = Vec::new();
+
+    let sale_number = record.source_key.clone().unwrap_or_default();
+    let sale_date = header.date("sale_date");
+    let tax_amount = header.decimal("tax_amount");
+    let discount_amount = header.decimal("discount_amount");
+    let sheet_total = header.decimal("total_amount");
+    let mut errors = std::mem::take(&mut header.errors);
+
+    // (product_id, quantity, unit_price)
+    let mut items = Vec::new();
+
+    for (line, &values) in record.rows.iter().enumerate() {
+        let mut row = Row::new(values, mapping);
+        let product = row.required_text("product");
+        let quantity = row.decimal("quantity");
+        let unit_price = row.decimal("unit_price");
+
+        let product_id = match product {
+            Some(ref product) => row.product(conn, product).await?,
+            None => None,
+        };
+
+        if line > 0
+            && row
+                .date("sale_date")
+                .is_some_and(|date| Some(date) != sale_date)
+        {
+            warnings.push(format!(
+                "Line {} has a different date than line 1",
+                line + 1
+            ));
+        }
+
+        if quantity.as_ref().is_some_and(|q| *q <= BigDecimal::from(0)) {
+            row.errors
+                .push("Quantity must be greater than 0".to_string());
+        }
+
+        if unit_price
+            .as_ref()
+            .is_some_and(|p| *p < BigDecimal::from(0))
+        {
+            row.errors.push("Unit price cannot be negative".to_string());
+        }
+
+        let prefix = if record.rows.len() > 1 {
+            format!("Line {}: ", line + 1)
+        } else {
+            String::new()
+        };
+        errors.extend(row.errors.drain(..).map(|e| format!("{}{}", prefix, e)));
+
+        if let (Some(product_id), Some(quantity), Some(unit_price)) =
+            (product_id, quantity, unit_price)
+        {
+            items.push((product_id, quantity, unit_price));
+        }
+    }
+
+    let existing = sqlx::query_scalar!("SELECT id FROM sales WHERE sale_number = $1", sale_number)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    if existing.is_some() {
+        errors.push(format!("Sale number {} already exists", sale_number));
+    }
+
+    let (Some(sale_date), true) = (sale_date, errors.is_empty()) else {
+        return Ok(Outcome::Rejected(errors));
+    };
+
+    let mut notes = header.text("notes");
+
+    let customer_id = match header.text("customer") {
+        Some(customer) => {
+            let customer_id = sqlx::query_scalar!(
+                "SELECT id FROM customers WHERE LOWER(name) = LOWER($1) ORDER BY is_active DESC LIMIT 1",
+                customer
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if customer_id.is_none() {
+                warnings.push(format!(
+                    "Customer '{}' not found; imported without a customer",
+                    customer
+                ));
+                notes = Some(match notes {
+                    Some(notes) => format!("Customer: {}. {}", customer, notes),
+                    None => format!("Customer: {}", customer),
+                });
+            }
+
+            customer_id
+        }
+        None => None,
+    };
+
+    let subtotal: BigDecimal = items.iter().map(|(_, q, p)| q * p).sum();
+    let tax_amount = tax_amount.unwrap_or_else(|| BigDecimal::from(0));
+    let discount_amount = discount_amount.unwrap_or_else(|| BigDecimal::from(0));
+    let computed_total = &subtotal + &tax_amount - &discount_amount;
+
+    // Keep what the sheet says was charged, but flag arithmetic that doesn't add up
+    let total_amount = match sheet_total {
+        Some(total) => {
+            if (&total - &computed_total).abs() > "0.01".parse::<BigDecimal>().unwrap() {
+                warnings.push(format!(
+                    "Sheet total {} doesn't match lines + tax - discount ({})",
+                    total,
+                    computed_total.with_scale_round(2, RoundingMode::HalfUp)
+                ));
+            }
+            total
+        }
+        None => computed_total,
+    };
+
+    if sale_date > Utc::now() {
+        warnings.push("Sale date is in the future".to_string());
+    }
+
+    let sale_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sales (
+            sale_number, customer_id, sale_date,
+            subtotal, tax_amount, discount_amount, total_amount,
+            payment_method, payment_status, notes
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'completed', $9)
+        RETURNING id
+        "#,
+        sale_number,
+        customer_id,
+        sale_date,
+        subtotal,
+        tax_amount,
+        discount_amount,
+        total_amount,
+        header.text("payment_method"),
+        import_note(notes)
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    for (product_id, quantity, unit_price) in items {
+        let line_total = &quantity * &unit_price;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO sale_items (sale_id, inventory_id, quantity, unit_price, line_total)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            sale_id,
+            product_id,
+            quantity,
+            unit_price,
+            line_total
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if discount_amount > BigDecimal::from(0) {
+        let discount = Discount::fixed(discount_amount, IMPORT_NOTE);
+        discounts::record(&mut *conn, sale_id, None, &[discount]).await?;
+    }
+
+    Ok(Outcome::Imported {
+        entity_id: sale_id,
+        warnings,
+    })
+}
+
+async fn import_purchase(
+    conn: &mut PgConnection,
+    mapping: &Mapping,
+    record: &Record<'_>,
+) -> Result<Outcome, sqlx::Error> {
+    let mut row = Row::new(record.rows[0], mapping);
+    let mut warnings = Vec::new();
+
+    let purchase_date = row.date("purchase_date");
+    let product = row.required_text("product");
+    let quantity = row.decimal("quantity");
+    let unit_cost = row.decimal("unit_cost");
+    let expiry_date = row.date("expiry_date").map(|date| date.date_naive());
+
+    let product_id = match product {
+        Some(ref product) => row.product(conn, product).await?,
+        None => None,
+    };
+
+    if quantity.as_ref().is_some_and(|q| *q <= BigDecimal::from(0)) {
+        row.errors
+            .push("Quantity must be greater than 0".to_string());
+    }
+
+    if unit_cost.as_ref().is_some_and(|c| *c < BigDecimal::from(0)) {
+        row.errors.push("Unit cost cannot be negative".to_string());
+    }
+
+    let (Some(purchase_date), Some(product_id), Some(quantity), Some(unit_cost), true) = (
+        purchase_date,
+        product_id,
+        quantity,
+        unit_cost,
+        row.errors.is_empty(),
+    ) else {
+        return Ok(Outcome::Rejected(row.errors));
+    };
+
+    let supplier_id = match row.text("supplier") {
+        Some(supplier) => {
+            let supplier_id = sqlx::query_scalar!(
+                "SELECT id FROM suppliers WHERE LOWER(name) = LOWER($1) LIMIT 1",
+                supplier
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if supplier_id.is_none() {
+                warnings.push(format!(
+                    "Supplier '{}' not found; imported without a supplier",
+                    supplier
+                ));
+            }
+
+            supplier_id
+        }
+        None => None,
+    };
+
+    if expiry_date.is_some_and(|expiry| expiry < purchase_date.date_naive()) {
+        warnings.push("Expiry date is before purchase date".to_string());
+    }
+
+    if purchase_date > Utc::now() {
+        warnings.push("Purchase date is in the future".to_string());
+    }
+
+    let lot_number = row.text("lot_number");
+
+    sqlx::query!(
+        r#"
+        INSERT INTO inventory_logs (
+            inventory_id, movement_type, quantity, unit_cost,
+            reason, batch_number, expiry_date, created_at
+        ) VALUES ($1, 'purchase', $2, $3, $4, $5, $6, $7)
+        "#,
+        product_id,
+        quantity,
+        unit_cost,
+        import_note(row.text("notes")),
+        lot_number,
+        expiry_date,
+        purchase_date
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Recorded as fully drawn down so the lot is traceable without counting as stock
+    let lot_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO inventory_lots (
+            inventory_id, lot_number, source_type, supplier_id,
+            initial_quantity, remaining_quantity, unit_cost,
+            received_date, expiry_date
+        ) VALUES ($1, $2, 'purchase', $3, $4, 0, $5, $6, $7)
+        RETURNING id
+        "#,
+        product_id,
+        lot_number,
+        supplier_id,
+        quantity,
+        unit_cost,
+        purchase_date,
+        expiry_date
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Outcome::Imported {
+        entity_id: lot_id,
+        warnings,
+    })
+}
+
+fn import_note(notes: Option<String>) -> String {
+    match notes {
+        Some(notes) => format!("{}. {}", IMPORT_NOTE, notes),
+        None => IMPORT_NOTE.to_string(),
+    }
+}
+
+/// Cell text for a field, with numbers and booleans stringified and blanks treated as missing.
+fn text(row: &Map<String, Value>, mapping: &Mapping, field: &str) -> Option<String> {
+    let column = mapping.columns.get(field)?;
+
+    match row.get(column)? {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Typed access to one sheet row, collecting errors as fields are read.
+struct Row<'a> {
+    values: &'a Map<String, Value>,
+    mapping: &'a Mapping,
+    errors: Vec<String>,
+}
+
+impl<'a> Row<'a> {
+    fn new(values: &'a Map<String, Value>, mapping: &'a Mapping) -> Self {
+        Self {
+            values,
+            mapping,
+            errors: Vec::new(),
+        }
+    }
+
+    fn text(&self, field: &str) -> Option<String> {
+        text(self.values, self.mapping, field)
+    }
+
+    /// Cell text, recording an error if a required field is blank.
+    fn required_text(&mut self, field: &str) -> Option<String> {
+        let value = self.text(field);
+
+        if value.is_none() && self.mapping.required(field) {
+            self.errors.push(format!(
+                "Missing {} (column '{}')",
+                field.replace('_', " "),
+                self.mapping.columns[field]
+            ));
+        }
+
+        value
+    }
+
+    /// Numeric cell; tolerates currency symbols and thousands separators.
+    fn decimal(&mut self, field: &str) -> Option<BigDecimal> {
+        let value = self.required_text(field)?;
+        let cleaned: String = value.chars().filter(|c| !matches!(c, '$' | ',')).collect();
+
+        match cleaned.parse::<BigDecimal>() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                self.errors
+                    .push(format!("Invalid {} '{}'", field.replace('_', " "), value));
+                None
+            }
+        }
+    }
+
+    /// Date cell as RFC 3339, YYYY-MM-DD, or M/D/YY(YY); plain dates are midnight UTC.
+    fn date(&mut self, field: &str) -> Option<DateTime<Utc>> {
+        let value = self.required_text(field)?;
+
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(&value) {
+            return Some(datetime.with_timezone(&Utc));
+        }
+
+        // Two-digit years first: %Y would read "24" as the year 24
+        let date = ["%Y-%m-%d", "%m/%d/%y", "%m/%d/%Y"]
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(&value, format).ok());
+
+        match date {
+            Some(date) => date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc()),
+            None => {
+                self.errors
+                    .push(format!("Invalid {} '{}'", field.replace('_', " "), value));
+                None
+            }
+        }
+    }
+
+    /// Inventory item with this name (case-insensitive), recording an error if none.
+    async fn product(
+        &mut self,
+        conn: &mut PgConnection,
+        name: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let id = sqlx::query_scalar!(
+            "SELECT id FROM inventory WHERE LOWER(name) = LOWER($1) ORDER BY is_active DESC LIMIT 1",
+            name
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if id.is_none() {
+            self.errors.push(format!("Unknown product '{}'", name));
+        }
+
+        Ok(id)
+    }
+}
@@ -0,0 +1,445 @@
+use bigdecimal::BigDecimal;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{
+    LibraryCategory, LibraryIngredient, LibraryRecipe, LibraryUnit, RecipeIngredientInput,
+    RecipeLibrary,
+};
+use crate::services::recipes;
+
+// The built-in library: common fermentation recipes plus category and unit
+// presets, shipped in the binary and copied into a deployment on request.
+
+/// Unit presets: (name, kind).
+pub const UNITS: &[(&str, &str)] = &[
+    ("grams", "mass"),
+    ("kilograms", "mass"),
+    ("milliliters", "volume"),
+    ("liters", "volume"),
+    ("each", "count"),
+    ("jars", "count"),
+];
+
+/// Kinds of unit.
+pub const UNIT_KINDS: &[&str] = &["mass", "volume", "count"];
+
+/// Category presets: (name, description).
+/// `finished_product` is the category the catalog and availability feed read.
+pub const CATEGORIES: &[(&str, &str)] = &[
+    ("Vegetable", "Cabbage, cucumbers, chiles and other produce"),
+    ("Herb", "Fresh and dried herbs"),
+    ("Spice", "Ground spices and chile flakes"),
+    ("Salt", "Salt for brines and dry-salting"),
+    ("Sweetener", "Sugar and honey"),
+    ("Dry Goods", "Tea and other shelf-stable ingredients"),
+    ("Culture", "Starter cultures and SCOBYs"),
+    ("Packaging", "Jars, lids and labels"),
+    ("finished_product", "Products made here and sold"),
+];
+
+struct RecipeEntry {
+    key: &'static str,
+    template_name: &'static str,
+    description: &'static str,
+    product_name: &'static str,
+    batch_size: &'static str,
+    unit: &'static str,
+    hours: &'static str,
+    /// Fermentation range in degrees Fahrenheit
+    temperature: (&'static str, &'static str),
+    /// (day, max pH)
+    ph_curve: &'static [(i32, f64)],
+    instructions: &'static str,
+    /// (name, category, quantity per batch, unit)
+    ingredients: &'static [(&'static str, &'static str, &'static str, &'static str)],
+}
+
+/// Recipe templates.
+const RECIPES: &[RecipeEntry] = &[
+    RecipeEntry {
+        key: "sauerkraut",
+        template_name: "Classic Sauerkraut",
+        description: "Cabbage dry-salted at 2% and fermented three weeks",
+        product_name: "Classic Sauerkraut",
+        batch_size: "5000",
+        unit: "grams",
+        hours: "504",
+        temperature: ("64", "72"),
+        ph_curve: &[(3, 4.6), (14, 3.8)],
+        instructions: "Core and shred the cabbage. Massage with the salt until it weeps, \
+            pack tightly under its own brine and weight it down. Taste from day 14.",
+        ingredients: &[
+            ("Cabbage", "Vegetable", "4900", "grams"),
+            ("Salt", "Salt", "100", "grams"),
+        ],
+    },
+    RecipeEntry {
+        key: "kimchi",
+        template_name: "Napa Cabbage Kimchi",
+        description: "Brined napa cabbage with radish, scallion and a gochugaru paste",
+        product_name: "Napa Cabbage Kimchi",
+        batch_size: "5000",
+        unit: "grams",
+        hours: "168",
+        temperature: ("60", "68"),
+        ph_curve: &[(2, 4.6), (5, 4.2)],
+        instructions: "Salt the quartered cabbage for 2 hours and rinse. Mix the garlic, \
+            ginger and gochugaru into a paste, toss everything together and pack. \
+            Move to the cooler once sour enough.",
+        ingredients: &[
+            ("Napa Cabbage", "Vegetable", "3900", "grams"),
+            ("Daikon Radish", "Vegetable", "500", "grams"),
+            ("Scallions", "Vegetable", "150", "grams"),
+            ("Garlic", "Vegetable", "100", "grams"),
+            ("Ginger", "Vegetable", "50", "grams"),
+            ("Gochugaru", "Spice", "200", "grams"),
+            ("Salt", "Salt", "100", "grams"),
+        ],
+    },
+    RecipeEntry {
+        key: "dill_pickles",
+        template_name: "Sour Dill Pickles",
+        description: "Whole cucumbers in a 3.5% garlic and dill brine",
+        product_name: "Sour Dill Pickles",
+        batch_size: "5000",
+        unit: "grams",
+        hours: "336",
+        temperature: ("65", "75"),
+        ph_curve: &[(3, 4.6), (10, 3.9)],
+        instructions: "Trim the blossom ends. Pack the cucumbers with dill and garlic and \
+            cover with brine. Skim daily; move to the cooler when sour through.",
+        ingredients: &[
+            ("Pickling Cucumbers", "Vegetable", "3000", "grams"),
+            ("Dill", "Herb", "60", "grams"),
+            ("Garlic", "Vegetable", "60", "grams"),
+            ("Salt", "Salt", "175", "grams"),
+        ],
+    },
+    RecipeEntry {
+        key: "hot_sauce",
+        template_name: "Fermented Fresno Hot Sauce",
+        description: "Fresno chile mash fermented two weeks, then blended",
+        product_name: "Fermented Fresno Hot Sauce",
+        batch_size: "2000",
+        unit: "grams",
+        hours: "336",
+        temperature: ("65", "75"),
+        ph_curve: &[(4, 4.6), (10, 4.0)],
+        instructions: "Stem and rough-chop the chiles with the garlic. Salt at 3%, pack \
+            into a crock and weight. Blend and strain when done.",
+        ingredients: &[
+            ("Fresno Chiles", "Vegetable", "1840", "grams"),
+            ("Garlic", "Vegetable", "100", "grams"),
+            ("Salt", "Salt", "60", "grams"),
+        ],
+    },
+    RecipeEntry {
+        key: "kombucha",
+        template_name: "Black Tea Kombucha",
+        description: "Sweet black tea fermented with a SCOBY and starter",
+        product_name: "Black Tea Kombucha",
+        batch_size: "4000",
+        unit: "milliliters",
+        hours: "240",
+        temperature: ("72", "80"),
+        ph_curve: &[(3, 4.2), (7, 3.5)],
+        instructions: "Brew the tea, dissolve the sugar and cool to room temperature. Add \
+            the starter and SCOBY, cover with cloth and taste from day 7.",
+        ingredients: &[
+            ("Black Tea", "Dry Goods", "24", "grams"),
+            ("Cane Sugar", "Sweetener", "300", "grams"),
+            ("Kombucha Starter", "Culture", "400", "milliliters"),
+        ],
+    },
+];
+
+/// The whole library, marking entries this deployment already has.
+pub async fn catalog(pool: &PgPool) -> Result<RecipeLibrary, sqlx::Error> {
+    let existing = sqlx::query!(
+        r#"
+        SELECT
+            ARRAY(SELECT LOWER(template_name) FROM recipe_templates) AS "recipes!",
+            ARRAY(SELECT LOWER(name) FROM inventory_categories) AS "categories!",
+            ARRAY(SELECT LOWER(name) FROM units) AS "units!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let recipes = RECIPES
+        .iter()
+        .map(|recipe| LibraryRecipe {
+            key: recipe.key.to_string(),
+            template_name: recipe.template_name.to_string(),
+            description: recipe.description.to_string(),
+            product_name: recipe.product_name.to_string(),
+            default_batch_size: decimal(recipe.batch_size),
+            default_unit: recipe.unit.to_string(),
+            estimated_duration_hours: decimal(recipe.hours),
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|(name, category, quantity, unit)| LibraryIngredient {
+                    name: name.to_string(),
+                    category: category.to_string(),
+                    quantity_per_batch: decimal(quantity),
+                    unit: unit.to_string(),
+                })
+                .collect(),
+            added: existing
+                .recipes
+                .contains(&recipe.template_name.to_lowercase()),
+        })
+        .collect();
+
+    let categories = CATEGORIES
+        .iter()
+        .map(|(name, description)| LibraryCategory {
+            name: name.to_string(),
+            description: description.to_string(),
+            added: existing.categories.contains(&name.to_lowercase()),
+        })
+        .collect();
+
+    let units = UNITS
+        .iter()
+        .map(|(name, kind)| LibraryUnit {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            added: existing.units.contains(&name.to_lowercase()),
+        })
+        .collect();
+
+    Ok(RecipeLibrary {
+        recipes,
+        categories,
+        units,
+    })
+}
+
+/// A library category preset by name.
+pub fn category(name: &str) -> Option<(&'static str, &'static str)> {
+    CATEGORIES
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .copied()
+}
+
+/// A library unit preset by name.
+pub fn unit(name: &str) -> Option<(&'static str, &'static str)> {
+    UNITS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+        .copied()
+}
+
+/// Add a category unless one with the name exists. True if added.
+pub async fn add_category(
+    conn: &mut PgConnection,
+    name: &str,
+    description: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO inventory_categories (name, description)
+        SELECT $1::varchar, $2
+        WHERE NOT EXISTS (SELECT 1 FROM inventory_categories WHERE LOWER(name) = LOWER($1))
+        "#,
+        name,
+        description
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Add a unit unless one with the name exists. True if added.
+pub async fn add_unit(
+    conn: &mut PgConnection,
+    name: &str,
+    kind: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO units (name, kind)
+        SELECT $1::varchar, $2
+        WHERE NOT EXISTS (SELECT 1 FROM units WHERE LOWER(name) = LOWER($1))
+        "#,
+        name,
+        kind
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// A library recipe copied into this deployment.
+pub struct AddedRecipe {
+    pub template_name: String,
+    /// Inventory items created for its product and ingredients
+    pub created_items: Vec<String>,
+}
+
+/// Copy a library recipe, with its product and ingredients. Each name in
+/// `mappings` (case-insensitive) uses that inventory item; other names use
+/// the active item of the same name, or a new one with no stock. Returns
+/// why it was skipped if the key is unknown or the recipe already exists.
+pub async fn add_recipe(
+    conn: &mut PgConnection,
+    key: &str,
+    mappings: &[(String, Uuid)],
+) -> Result<Result<AddedRecipe, String>, sqlx::Error> {
+    let Some(recipe) = RECIPES.iter().find(|recipe| recipe.key == key.trim()) else {
+        return Ok(Err(format!("No library recipe '{}'", key)));
+    };
+
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM recipe_templates WHERE LOWER(template_name) = LOWER($1)
+        ) AS "exists!"
+        "#,
+        recipe.template_name
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if exists {
+        return Ok(Err(format!("'{}' is already set up", recipe.template_name)));
+    }
+
+    let mut created_items = Vec::new();
+    let product_id = resolve_item(
+        conn,
+        mappings,
+        &mut created_items,
+        (recipe.product_name, "finished_product", recipe.unit),
+    )
+    .await?;
+
+    let mut ingredients = Vec::new();
+    for (name, category, quantity, unit) in recipe.ingredients {
+        ingredients.push(RecipeIngredientInput {
+            inventory_id: resolve_item(conn, mappings, &mut created_items, (name, category, unit))
+                .await?,
+            quantity_per_batch: decimal(quantity),
+            unit: Some(unit.to_string()),
+        });
+    }
+
+    if let Some(index) = (1..ingredients.len()).find(|&index| {
+        ingredients[..index]
+            .iter()
+            .any(|other| other.inventory_id == ingredients[index].inventory_id)
+    }) {
+        return Ok(Err(format!(
+            "'{}' maps more than one ingredient to the same item (at '{}')",
+            recipe.template_name, recipe.ingredients[index].0
+        )));
+    }
+
+    let ph_curve = json!(
+        recipe
+            .ph_curve
+            .iter()
+            .map(|(day, max_ph)| json!({ "day": day, "max_ph": max_ph }))
+            .collect::<Vec<_>>()
+    );
+
+    let recipe_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO recipe_templates (
+            product_inventory_id, template_name, description, default_batch_size,
+            default_unit, estimated_duration_hours, instructions, min_temperature,
+            max_temperature, target_ph_curve, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, true)
+        RETURNING id
+        "#,
+        product_id,
+        recipe.template_name,
+        recipe.description,
+        decimal(recipe.batch_size),
+        recipe.unit,
+        decimal(recipe.hours),
+        recipe.instructions,
+        decimal(recipe.temperature.0),
+        decimal(recipe.temperature.1),
+        ph_curve
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    recipes::replace(conn, recipe_id, &ingredients).await?;
+
+    Ok(Ok(AddedRecipe {
+        template_name: recipe.template_name.to_string(),
+        created_items,
+    }))
+}
+
+/// The inventory item a library name maps to, noting any item created.
+async fn resolve_item(
+    conn: &mut PgConnection,
+    mappings: &[(String, Uuid)],
+    created_items: &mut Vec<String>,
+    (name, category, unit): (&str, &str, &str),
+) -> Result<Uuid, sqlx::Error> {
+    if let Some((_, id)) = mappings
+        .iter()
+        .find(|(mapped, _)| mapped.eq_ignore_ascii_case(name))
+    {
+        return Ok(*id);
+    }
+
+    let (id, created) = find_or_add_item(conn, name, category, unit).await?;
+    if created {
+        created_items.push(name.to_string());
+    }
+
+    Ok(id)
+}
+
+/// An active inventory item by name (case-insensitive), added with no stock
+/// if there isn't one. True if it was added.
+async fn find_or_add_item(
+    conn: &mut PgConnection,
+    name: &str,
+    category: &str,
+    unit: &str,
+) -> Result<(Uuid, bool), sqlx::Error> {
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM inventory
+        WHERE LOWER(name) = LOWER($1) AND is_active = true
+        ORDER BY created_at
+        LIMIT 1
+        "#,
+        name
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        return Ok((id, false));
+    }
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO inventory (name, category, unit) VALUES ($1, $2, $3) RETURNING id",
+        name,
+        category,
+        unit
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((id, true))
+}
+
+fn decimal(value: &str) -> BigDecimal {
+    value.parse().unwrap_or_default()
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextExecute};
+use async_graphql::{Response, ServerError};
+
+/// Schema extension that gives up on a query or mutation still running
+/// after `GRAPHQL_TIMEOUT_SECS`, answering with an error. Dropping the
+/// operation drops its pending SQL; the reporting pool's `statement_timeout`
+/// is what stops a query's statement on the server. A mutation that times
+/// out rolls back, unless it had already committed.
+pub struct OperationTimeout(pub Duration);
+
+impl ExtensionFactory for OperationTimeout {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(OperationTimeoutExtension(self.0))
+    }
+}
+
+struct OperationTimeoutExtension(Duration);
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for OperationTimeoutExtension {
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        match tokio::time::timeout(self.0, next.run(ctx, operation_name)).await {
+            Ok(response) => response,
+            Err(_) => {
+                tracing::warn!(
+                    "⏱️ {} timed out after {}s",
+                    operation_name.unwrap_or("Operation"),
+                    self.0.as_secs()
+                );
+                Response::from_errors(vec![ServerError::new(
+                    format!("Operation timed out after {}s", self.0.as_secs()),
+                    None,
+                )])
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{BatchMoveTask, LocationStock, TransferStockInput};
+
+/// Find a location by name (ignoring case), creating it if it doesn't exist
+/// yet. Storage transition rules and legacy imports name locations as text.
+pub async fn resolve(conn: &mut PgConnection, name: &str) -> Result<Uuid, sqlx::Error> {
+    let name = name.trim();
+
+    let existing = sqlx::query_scalar!(
+        "SELECT id FROM locations WHERE LOWER(name) = LOWER($1)",
+        name
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    sqlx::query_scalar!(
+        "INSERT INTO locations (name) VALUES ($1) RETURNING id",
+        name
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+/// Record that a batch moved as its move task said: put the batch at
+/// `to_location` and close the task. Returns the batch number and the task.
+pub async fn record_move(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    batch_id: Uuid,
+    to_location: &str,
+    moved_at: DateTime<Utc>,
+    notes: Option<String>,
+) -> Result<(String, BatchMoveTask), sqlx::Error> {
+    let location_id = resolve(conn, to_location).await?;
+    let batch_number = sqlx::query_scalar!(
+        r#"
+        UPDATE production_batches
+        SET storage_location_id = $1,
+            last_moved_at = $2,
+            updated_at = NOW()
+        WHERE id = $3
+        RETURNING batch_number
+        "#,
+        location_id,
+        moved_at,
+        batch_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let task = sqlx::query_as!(
+        BatchMoveTask,
+        r#"
+        UPDATE batch_move_tasks
+        SET status = 'completed',
+            completed_at = $1,
+            to_location = $2,
+            notes = COALESCE($3, notes)
+        WHERE id = $4
+        RETURNING
+            id, batch_id, rule_id, from_location, to_location,
+            due_date, status, completed_at, notes, created_at
+        "#,
+        moved_at,
+        to_location,
+        notes,
+        task_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((batch_number, task))
+}
+
+/// Pick a batch's storage location from an ID or a name (an ID wins).
+/// Returns the reason if the ID doesn't name an active location.
+pub async fn for_batch(
+    conn: &mut PgConnection,
+    location_id: Option<Uuid>,
+    name: Option<&str>,
+) -> Result<Result<Option<Uuid>, String>, sqlx::Error> {
+    if let Some(location_id) = location_id {
+        let active = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM locations WHERE id = $1 AND is_active = true) AS \"exists!\"",
+            location_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !active {
+            return Ok(Err("Storage location not found or is inactive".to_string()));
+        }
+        return Ok(Ok(Some(location_id)));
+    }
+
+    match name.filter(|name| !name.trim().is_empty()) {
+        Some(name) => Ok(Ok(Some(resolve(&mut *conn, name).await?))),
+        None => Ok(Ok(None)),
+    }
+}
+
+/// Stock per location, optionally for one item or one location. Stock not in
+/// a located lot (including stock from before lot tracking) is reported as
+/// unassigned, with no location.
+pub async fn stock(
+    pool: &PgPool,
+    inventory_id: Option<Uuid>,
+    location_id: Option<Uuid>,
+) -> Result<Vec<LocationStock>, sqlx::Error> {
+    sqlx::query_as!(
+        LocationStock,
+        r#"
+        SELECT
+            l.location_id AS "location_id?", loc.name AS "location_name?",
+            i.id AS "inventory_id!", i.name AS "item_name!", i.unit AS "unit!",
+            SUM(l.remaining_quantity) AS "quantity!"
+        FROM inventory_lots l
+        JOIN inventory i ON i.id = l.inventory_id
+        JOIN locations loc ON loc.id = l.location_id
+        WHERE l.remaining_quantity > 0
+            AND ($1::uuid IS NULL OR l.inventory_id = $1)
+            AND ($2::uuid IS NULL OR l.location_id = $2)
+        GROUP BY l.location_id, loc.name, i.id, i.name, i.unit
+
+        UNION ALL
+
+        SELECT
+            NULL, NULL, i.id, i.name, i.unit,
+            i.current_stock - COALESCE(located.quantity, 0)
+        FROM inventory i
+        LEFT JOIN LATERAL (
+            SELECT SUM(remaining_quantity) AS quantity
+            FROM inventory_lots
+            WHERE inventory_id = i.id AND remaining_quantity > 0 AND location_id IS NOT NULL
+        ) located ON true
+        WHERE i.is_active = true
+            AND $2::uuid IS NULL
+            AND ($1::uuid IS NULL OR i.id = $1)
+            AND i.current_stock - COALESCE(located.quantity, 0) > 0
+
+        ORDER BY 4, 2 NULLS LAST
+        "#,
+        inventory_id,
+        location_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Move stock of an item from one location (or unassigned stock) to another
+/// by re-homing its open lots, first-expired-first-out. A lot that only
+/// partly moves is split, the moved part keeping its lot number and source.
+/// Unassigned stock with no lot gets a new 'transfer' lot at the destination.
+/// Returns a summary of the move, or the reason it can't be made.
+pub async fn transfer(
+    conn: &mut PgConnection,
+    input: &TransferStockInput,
+) -> Result<Result<String, String>, sqlx::Error> {
+    let zero = BigDecimal::from(0);
+
+    if input.quantity <= zero {
+        return Ok(Err("Quantity must be greater than 0".to_string()));
+    }
+
+    if input.from_location_id == Some(input.to_location_id) {
+        return Ok(Err(
+            "Source and destination are the same location".to_string()
+        ));
+    }
+
+    // Lock the item so concurrent moves and sales see a consistent picture
+    let item = sqlx::query!(
+        "SELECT name, unit, current_stock FROM inventory WHERE id = $1 AND is_active = true FOR UPDATE",
+        input.inventory_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(item) = item else {
+        return Ok(Err("Inventory item not found or is inactive".to_string()));
+    };
+
+    let to_name = sqlx::query_scalar!(
+        "SELECT name FROM locations WHERE id = $1 AND is_active = true",
+        input.to_location_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(to_name) = to_name else {
+        return Ok(Err(
+            "Destination location not found or is inactive".to_string()
+        ));
+    };
+
+    let from_name = match input.from_location_id {
+        Some(from_location_id) => {
+            let name =
+                sqlx::query_scalar!("SELECT name FROM locations WHERE id = $1", from_location_id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+
+            match name {
+                Some(name) => name,
+                None => return Ok(Err("Source location not found".to_string())),
+            }
+        }
+        None => "unassigned stock".to_string(),
+    };
+
+    let lots = sqlx::query!(
+        r#"
+        SELECT l.id, l.remaining_quantity
+        FROM inventory_lots l
+        JOIN inventory i ON i.id = l.inventory_id
+        WHERE l.inventory_id = $1
+            AND l.remaining_quantity > 0
+            AND l.location_id IS NOT DISTINCT FROM $2
+        ORDER BY
+            COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) NULLS LAST,
+            l.received_date
+        FOR UPDATE OF l
+        "#,
+        input.inventory_id,
+        input.from_location_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut available = lots
+        .iter()
+        .fold(zero.clone(), |sum, lot| sum + &lot.remaining_quantity);
+
+    // Unassigned stock also covers stock that has no lot at all
+    let untracked = if input.from_location_id.is_none() {
+        let tracked = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(remaining_quantity), 0) AS "tracked!"
+            FROM inventory_lots
+            WHERE inventory_id = $1 AND remaining_quantity > 0
+            "#,
+            input.inventory_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let untracked = &item.current_stock - tracked;
+        if untracked > zero {
+            untracked
+        } else {
+            zero.clone()
+        }
+    } else {
+        zero.clone()
+    };
+    available += &untracked;
+
+    if available < input.quantity {
+        return Ok(Err(format!(
+            "Only {} {} of {} in {}",
+            available, item.unit, item.name, from_name
+        )));
+    }
+
+    let mut outstanding = input.quantity.clone();
+
+    for lot in lots {
+        if outstanding <= zero {
+            break;
+        }
+
+        if lot.remaining_quantity <= outstanding {
+            sqlx::query!(
+                "UPDATE inventory_lots SET location_id = $1, updated_at = NOW() WHERE id = $2",
+                input.to_location_id,
+                lot.id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            outstanding -= &lot.remaining_quantity;
+            continue;
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_lots (
+                inventory_id, lot_number, source_type, supplier_id, production_batch_id,
+                parent_lot_id, location_id, initial_quantity, remaining_quantity, unit_cost,
+                received_date, expiry_date
+            )
+            SELECT
+                inventory_id, lot_number, source_type, supplier_id, production_batch_id,
+                parent_lot_id, $2, $3, $3, unit_cost, received_date, expiry_date
+            FROM inventory_lots
+            WHERE id = $1
+            "#,
+            lot.id,
+            input.to_location_id,
+            outstanding
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE inventory_lots
+            SET initial_quantity = initial_quantity - $1,
+                remaining_quantity = remaining_quantity - $1,
+                updated_at = NOW()
+            WHERE id = $2
+            "#,
+            outstanding,
+            lot.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        outstanding = zero.clone();
+    }
+
+    if outstanding > zero {
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_lots (
+                inventory_id, source_type, location_id, initial_quantity, remaining_quantity,
+                unit_cost
+            )
+            SELECT id, 'transfer', $2, $3, $3, cost_per_unit
+            FROM inventory
+            WHERE id = $1
+            "#,
+            input.inventory_id,
+            input.to_location_id,
+            outstanding
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let summary = format!(
+        "Moved {} {} of {} from {} to {}",
+        input.quantity, item.unit, item.name, from_name, to_name
+    );
+
+    // Total stock doesn't change, so the log entry moves nothing
+    sqlx::query!(
+        r#"
+        INSERT INTO inventory_logs (inventory_id, movement_type, quantity, reason)
+        VALUES ($1, 'transfer', 0, $2)
+        "#,
+        input.inventory_id,
+        match &input.notes {
+            Some(notes) => format!("{} ({})", summary, notes),
+            None => summary.clone(),
+        }
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(summary))
+}
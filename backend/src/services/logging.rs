@@ -0,0 +1,195 @@
+use std::io::IsTerminal;
+use std::time::{Duration, SystemTime};
+
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{Span as _, SpanKind, TraceContextExt, Tracer, TracerProvider};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::{EnvFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Log filter when `RUST_LOG` isn't set. Resolver spans are left out of the
+/// log; they go to the trace exporter.
+const DEFAULT_LOG_FILTER: &str = "info,async_graphql::graphql=warn";
+
+/// Service name traces are reported under unless `OTEL_SERVICE_NAME` is set.
+const SERVICE_NAME: &str = "frederick-ferments-api";
+
+/// Target sqlx logs each statement under, with its SQL and duration.
+const SQL_TARGET: &str = "sqlx::query";
+
+/// Logging and tracing for the server. Logs go to stdout, filtered by
+/// `RUST_LOG`, as JSON lines (`LOG_FORMAT=json`, the default when stdout
+/// isn't a terminal) or plain text (`LOG_FORMAT=text`). When `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set, spans for HTTP requests,
+/// GraphQL operations and resolvers, scheduled jobs and every SQL statement
+/// are exported over OTLP/HTTP (Jaeger, Tempo, an OpenTelemetry collector).
+pub struct Logging {
+    provider: Option<SdkTracerProvider>,
+}
+
+/// Install the global subscriber. Call once, first thing in `main`.
+pub fn init() -> anyhow::Result<Logging> {
+    let log_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let json = match std::env::var("LOG_FORMAT") {
+        Ok(format) => format.trim().eq_ignore_ascii_case("json"),
+        Err(_) => !std::io::stdout().is_terminal(),
+    };
+    let log = if json {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(std::io::stdout().is_terminal())
+            .boxed()
+    }
+    .with_filter(log_filter);
+
+    let exporting = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|var| std::env::var(var).is_ok_and(|value| !value.trim().is_empty()));
+
+    if !exporting {
+        tracing_subscriber::registry().with(log).init();
+        return Ok(Logging { provider: None });
+    }
+
+    // Endpoint, headers and timeout come from the standard OTEL_EXPORTER_OTLP_* vars
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+
+    let mut resource = Resource::builder();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name(SERVICE_NAME);
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+
+    let spans = tracing_opentelemetry::layer()
+        .with_tracer(tracer.clone())
+        .with_filter(Targets::new().with_default(Level::INFO));
+    // sqlx only reports statements at debug, which the log usually leaves out
+    let sql = SqlSpans { tracer }.with_filter(Targets::new().with_target(SQL_TARGET, Level::DEBUG));
+
+    tracing_subscriber::registry()
+        .with(log)
+        .with(spans)
+        .with(sql)
+        .init();
+
+    Ok(Logging {
+        provider: Some(provider),
+    })
+}
+
+impl Logging {
+    /// Send spans still waiting in the batch.
+    pub fn shutdown(self) {
+        let Some(provider) = self.provider else {
+            return;
+        };
+
+        if let Err(e) = provider.shutdown() {
+            tracing::error!("❌ Failed to flush traces: {}", e);
+        }
+    }
+}
+
+/// Turns each statement sqlx logs into a span under the resolver (or job)
+/// that ran it, so a slow operation's trace shows which queries took the time.
+/// sqlx reports a statement once it finishes, so the span is backdated by its
+/// duration. Statements outside any traced span are left out.
+struct SqlSpans {
+    tracer: SdkTracer,
+}
+
+impl<S> Layer<S> for SqlSpans
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let parent = tracing::Span::current().context();
+        if !parent.has_active_span() {
+            return;
+        }
+
+        let mut statement = SqlStatement::default();
+        event.record(&mut statement);
+
+        let end = SystemTime::now();
+        let start = end
+            .checked_sub(Duration::from_secs_f64(statement.elapsed_secs.max(0.0)))
+            .unwrap_or(end);
+        // sqlx leaves `db.statement` empty when the summary is the whole statement
+        let sql = match statement.sql.trim() {
+            "" => statement.summary.clone(),
+            sql => sql.to_string(),
+        };
+
+        let mut span = self
+            .tracer
+            .span_builder(statement.summary)
+            .with_kind(SpanKind::Client)
+            .with_start_time(start)
+            .with_attributes([
+                KeyValue::new("db.system", "postgresql"),
+                KeyValue::new("db.statement", sql),
+                KeyValue::new("db.rows_returned", statement.rows_returned as i64),
+                KeyValue::new("db.rows_affected", statement.rows_affected as i64),
+            ])
+            .start_with_context(&self.tracer, &parent);
+        span.end_with_timestamp(end);
+    }
+}
+
+/// Fields of a sqlx statement event.
+#[derive(Default)]
+struct SqlStatement {
+    summary: String,
+    sql: String,
+    rows_returned: u64,
+    rows_affected: u64,
+    elapsed_secs: f64,
+}
+
+impl Visit for SqlStatement {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.sql = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "rows_returned" => self.rows_returned = value,
+            "rows_affected" => self.rows_affected = value,
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed_secs = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
@@ -0,0 +1,272 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{ExpiringLot, InventoryAging};
+
+/// A quantity drawn from a single lot by `consume`.
+#[derive(Debug, Clone)]
+pub struct LotDraw {
+    pub lot_id: Uuid,
+    pub lot_number: Option<String>,
+    pub production_batch_id: Option<Uuid>,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity: BigDecimal,
+}
+
+/// Draw down open lots of an inventory item, first-expired-first-out.
+///
+/// Stock that predates lot tracking has no lots, so any quantity beyond what
+/// the open lots hold is simply left untracked (and absent from the draws).
+pub async fn consume(
+    conn: &mut PgConnection,
+    inventory_id: Uuid,
+    quantity: &BigDecimal,
+) -> Result<Vec<LotDraw>, sqlx::Error> {
+    let lots = sqlx::query!(
+        r#"
+        SELECT
+            l.id, l.lot_number, l.production_batch_id, l.remaining_quantity,
+            COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) as expiry_date
+        FROM inventory_lots l
+        JOIN inventory i ON i.id = l.inventory_id
+        WHERE l.inventory_id = $1 AND l.remaining_quantity > 0
+        ORDER BY
+            COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) NULLS LAST,
+            l.received_date
+        FOR UPDATE OF l
+        "#,
+        inventory_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let zero = BigDecimal::from(0);
+    let mut outstanding = quantity.clone();
+    let mut draws = Vec::new();
+
+    for lot in lots {
+        if outstanding <= zero {
+            break;
+        }
+
+        let take = if lot.remaining_quantity < outstanding {
+            lot.remaining_quantity
+        } else {
+            outstanding.clone()
+        };
+
+        sqlx::query!(
+            "UPDATE inventory_lots SET remaining_quantity = remaining_quantity - $1, updated_at = NOW() WHERE id = $2",
+            take,
+            lot.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        outstanding -= &take;
+        draws.push(LotDraw {
+            lot_id: lot.id,
+            lot_number: lot.lot_number,
+            production_batch_id: lot.production_batch_id,
+            expiry_date: lot.expiry_date,
+            quantity: take,
+        });
+    }
+
+    Ok(draws)
+}
+
+/// Draw a quantity from one specific lot, such as goods received and used in
+/// the same step. The caller makes sure the lot holds enough.
+pub async fn draw(
+    conn: &mut PgConnection,
+    lot_id: Uuid,
+    quantity: &BigDecimal,
+) -> Result<LotDraw, sqlx::Error> {
+    let lot = sqlx::query!(
+        r#"
+        UPDATE inventory_lots l
+        SET remaining_quantity = l.remaining_quantity - $1, updated_at = NOW()
+        FROM inventory i
+        WHERE l.id = $2 AND i.id = l.inventory_id
+        RETURNING
+            l.lot_number, l.production_batch_id,
+            COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) as expiry_date
+        "#,
+        quantity,
+        lot_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(LotDraw {
+        lot_id,
+        lot_number: lot.lot_number,
+        production_batch_id: lot.production_batch_id,
+        expiry_date: lot.expiry_date,
+        quantity: quantity.clone(),
+    })
+}
+
+/// Open lots whose effective expiry date falls within `within_days` of today,
+/// including lots that have already expired.
+pub async fn expiring(pool: &PgPool, within_days: i32) -> Result<Vec<ExpiringLot>, sqlx::Error> {
+    sqlx::query_as!(
+        ExpiringLot,
+        r#"
+        SELECT
+            l.id as "lot_id!",
+            l.inventory_id as "inventory_id!",
+            i.name as "item_name!",
+            l.lot_number,
+            l.remaining_quantity as "remaining_quantity!",
+            i.unit as "unit!",
+            e.expiry_date as "expiry_date!",
+            (e.expiry_date - CURRENT_DATE) as "days_until_expiry!",
+            i.storage_requirements
+        FROM inventory_lots l
+        JOIN inventory i ON i.id = l.inventory_id
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) AS expiry_date
+        ) e
+        WHERE l.remaining_quantity > 0
+            AND i.is_active = true
+            AND e.expiry_date IS NOT NULL
+            AND e.expiry_date <= CURRENT_DATE + $1::int
+        ORDER BY e.expiry_date, i.name
+        "#,
+        within_days
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Aging of on-hand stock per active product, with expiry and slow-mover
+/// signals. Outbound movements other than adjustments and waste count as use.
+pub async fn aging(
+    pool: &PgPool,
+    expiring_within_days: i32,
+    slow_after_days: i32,
+    category: Option<&str>,
+) -> Result<Vec<InventoryAging>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        WITH open_lots AS (
+            SELECT
+                l.inventory_id,
+                l.remaining_quantity AS quantity,
+                CURRENT_DATE - l.received_date::date AS age,
+                COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) AS expiry_date
+            FROM inventory_lots l
+            JOIN inventory i ON i.id = l.inventory_id
+            WHERE l.remaining_quantity > 0
+        ),
+        usage AS (
+            SELECT
+                inventory_id,
+                MAX(created_at) AS last_used_at,
+                SUM(-quantity) FILTER (
+                    WHERE created_at >= NOW() - make_interval(days => $2)
+                ) AS used_in_window
+            FROM inventory_logs
+            WHERE quantity < 0 AND movement_type NOT IN ('adjustment', 'waste')
+            GROUP BY inventory_id
+        )
+        SELECT
+            i.id, i.name, i.category, i.unit, i.current_stock,
+            COALESCE(SUM(l.quantity) FILTER (WHERE l.age <= 30), 0) AS "days_0_to_30!",
+            COALESCE(SUM(l.quantity) FILTER (WHERE l.age BETWEEN 31 AND 60), 0) AS "days_31_to_60!",
+            COALESCE(SUM(l.quantity) FILTER (WHERE l.age BETWEEN 61 AND 90), 0) AS "days_61_to_90!",
+            COALESCE(SUM(l.quantity) FILTER (WHERE l.age > 90), 0) AS "over_90_days!",
+            COALESCE(SUM(l.quantity), 0) AS "tracked!",
+            ROUND(SUM(l.quantity * l.age) / NULLIF(SUM(l.quantity), 0), 1) AS average_age_days,
+            COALESCE(
+                SUM(l.quantity) FILTER (WHERE l.expiry_date <= CURRENT_DATE + $1::int), 0
+            ) AS "expiring_soon!",
+            MIN(l.expiry_date) AS next_expiry_date,
+            u.last_used_at AS "last_used_at?",
+            COALESCE(u.used_in_window, 0) AS "used_in_window!"
+        FROM inventory i
+        LEFT JOIN open_lots l ON l.inventory_id = i.id
+        LEFT JOIN usage u ON u.inventory_id = i.id
+        WHERE i.is_active = true
+            AND i.current_stock > 0
+            AND ($3::varchar IS NULL OR i.category = $3)
+        GROUP BY i.id, u.last_used_at, u.used_in_window
+        ORDER BY average_age_days DESC NULLS LAST, i.name
+        "#,
+        expiring_within_days,
+        slow_after_days,
+        category
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let zero = BigDecimal::from(0);
+
+    Ok(rows
+        .into_iter()
+        .map(|row| InventoryAging {
+            untracked: if row.tracked < row.current_stock {
+                &row.current_stock - &row.tracked
+            } else {
+                zero.clone()
+            },
+            slow_mover: row.used_in_window <= zero,
+            inventory_id: row.id,
+            item_name: row.name,
+            category: row.category,
+            unit: row.unit,
+            current_stock: row.current_stock,
+            days_0_to_30: row.days_0_to_30,
+            days_31_to_60: row.days_31_to_60,
+            days_61_to_90: row.days_61_to_90,
+            over_90_days: row.over_90_days,
+            average_age_days: row.average_age_days,
+            expiring_soon: row.expiring_soon,
+            next_expiry_date: row.next_expiry_date,
+            last_used_at: row.last_used_at,
+            used_in_window: row.used_in_window,
+        })
+        .collect())
+}
+
+/// What a set of lot draws was used for.
+#[derive(Debug, Clone, Copy)]
+pub enum LotUsage {
+    /// Consumed as an ingredient of a production batch
+    ProductionBatch(Uuid),
+    /// Sold on a sale line
+    SaleItem(Uuid),
+}
+
+/// Record which lots were drawn for a batch or sale line so they can be traced.
+pub async fn record_usage(
+    conn: &mut PgConnection,
+    draws: &[LotDraw],
+    usage: LotUsage,
+) -> Result<(), sqlx::Error> {
+    let (production_batch_id, sale_item_id) = match usage {
+        LotUsage::ProductionBatch(batch_id) => (Some(batch_id), None),
+        LotUsage::SaleItem(sale_item_id) => (None, Some(sale_item_id)),
+    };
+
+    for draw in draws {
+        sqlx::query!(
+            r#"
+            INSERT INTO lot_consumptions (lot_id, quantity, production_batch_id, sale_item_id)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            draw.lot_id,
+            draw.quantity,
+            production_batch_id,
+            sale_item_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MarketProfitability, MarketSession, MarketStaffInput};
+
+/// A market session with its market's name.
+pub async fn session<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<MarketSession>, sqlx::Error> {
+    sqlx::query_as!(
+        MarketSession,
+        r#"
+        SELECT
+            s.id, s.market_id, m.name AS market_name, s.calendar_event_id, s.session_date,
+            s.booth, s.booth_fee, s.travel_cost, s.notes, s.created_at, s.updated_at
+        FROM market_sessions s
+        JOIN markets m ON m.id = s.market_id
+        WHERE s.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Replace a session's staff list. Users are named by their display name
+/// unless a name is given; helpers without an account need a name. Returns
+/// the reason when the list can't be used.
+pub async fn set_staff(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    staff: &[MarketStaffInput],
+) -> Result<Result<(), String>, sqlx::Error> {
+    let mut rows: Vec<(Option<Uuid>, String)> = Vec::new();
+
+    for member in staff {
+        let given = member
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+
+        let name = match member.user_id {
+            Some(user_id) => {
+                let user = sqlx::query!(
+                    "SELECT COALESCE(display_name, username) AS \"name!\", is_active FROM users WHERE id = $1",
+                    user_id
+                )
+                .fetch_optional(&mut *conn)
+                .await?;
+
+                match user {
+                    Some(user) if user.is_active => given.map_or(user.name, str::to_string),
+                    Some(user) => return Ok(Err(format!("{} is no longer active", user.name))),
+                    None => return Ok(Err(format!("User not found: {}", user_id))),
+                }
+            }
+            None => match given {
+                Some(name) => name.to_string(),
+                None => return Ok(Err("Staff without an account need a name".to_string())),
+            },
+        };
+
+        if rows
+            .iter()
+            .any(|(_, existing)| existing.eq_ignore_ascii_case(&name))
+        {
+            return Ok(Err(format!("{} is listed twice", name)));
+        }
+        rows.push((member.user_id, name));
+    }
+
+    sqlx::query!(
+        "DELETE FROM market_session_staff WHERE session_id = $1",
+        session_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for (user_id, name) in rows {
+        sqlx::query!(
+            "INSERT INTO market_session_staff (session_id, user_id, staff_name) VALUES ($1, $2, $3)",
+            session_id,
+            user_id,
+            name
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(Ok(()))
+}
+
+/// The business calendar's market day for a market on a date: an existing
+/// 'market' entry of the same name covering the date, or a new one-day entry
+/// that closes nothing.
+pub async fn calendar_event(
+    conn: &mut PgConnection,
+    market_name: &str,
+    date: NaiveDate,
+) -> Result<Uuid, sqlx::Error> {
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM business_calendar
+        WHERE kind = 'market' AND name = $1 AND $2 BETWEEN start_date AND end_date
+        ORDER BY start_date DESC
+        LIMIT 1
+        "#,
+        market_name,
+        date
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO business_calendar (
+            kind, name, start_date, end_date, blocks_production, blocks_deliveries
+        ) VALUES ('market', $1, $2, $2, false, false)
+        RETURNING id
+        "#,
+        market_name,
+        date
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+/// Takings and costs per market for sessions between two dates
+/// (inclusive), most profitable first. Markets without a session in the
+/// period are left out.
+pub async fn profitability(
+    pool: &PgPool,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Vec<MarketProfitability>, sqlx::Error> {
+    sqlx::query_as!(
+        MarketProfitability,
+        r#"
+        WITH sessions AS (
+            SELECT s.id, s.market_id, s.booth_fee, s.travel_cost
+            FROM market_sessions s
+            WHERE s.session_date BETWEEN $1 AND $2
+        ),
+        takings AS (
+            SELECT sa.market_session_id AS session_id,
+                COUNT(*) AS sales_count,
+                SUM(sa.total_amount) AS revenue,
+                SUM((
+                    SELECT COALESCE(SUM(si.quantity * COALESCE(i.cost_per_unit, 0)), 0)
+                    FROM sale_items si
+                    JOIN inventory i ON i.id = si.inventory_id
+                    WHERE si.sale_id = sa.id
+                )) AS cost_of_goods
+            FROM sales sa
+            WHERE sa.market_session_id IN (SELECT id FROM sessions)
+                AND sa.payment_status <> 'refunded'
+            GROUP BY sa.market_session_id
+        )
+        SELECT
+            m.id AS market_id, m.name AS market_name,
+            COUNT(*) AS "sessions!",
+            COALESCE(SUM(t.sales_count), 0)::bigint AS "sales_count!",
+            COALESCE(SUM(t.revenue), 0) AS "revenue!",
+            ROUND(COALESCE(SUM(t.cost_of_goods), 0), 2) AS "cost_of_goods!",
+            SUM(s.booth_fee) AS "booth_fees!",
+            SUM(s.travel_cost) AS "travel_costs!",
+            ROUND(
+                COALESCE(SUM(t.revenue), 0) - COALESCE(SUM(t.cost_of_goods), 0)
+                    - SUM(s.booth_fee) - SUM(s.travel_cost),
+                2
+            ) AS "profit!"
+        FROM sessions s
+        JOIN markets m ON m.id = s.market_id
+        LEFT JOIN takings t ON t.session_id = s.id
+        GROUP BY m.id
+        ORDER BY 9 DESC, m.name
+        "#,
+        start_date,
+        end_date
+    )
+    .fetch_all(pool)
+    .await
+}
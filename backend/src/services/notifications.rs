@@ -0,0 +1,276 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::NotificationSettings;
+use crate::services::http::{HttpClient, HttpError};
+use crate::services::secrets::{self, SecretStore};
+
+/// Alert kinds users can have texted to them: problems someone has to act on
+/// straight away (a batch out of its temperature range or behind its pH curve),
+/// and the owner's decision on a purchase order they placed.
+pub const SMS_ALERT_KINDS: &[&str] = &["reading_out_of_range", "purchase_order_decision"];
+
+/// Longest text sent; Twilio splits anything over 160 characters into segments.
+const MAX_SMS_LENGTH: usize = 320;
+
+/// An operational alert raised by a background job or resolver.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// Machine-readable alert type (e.g. "expiring_items")
+    pub kind: String,
+    /// Human-readable summary
+    pub message: String,
+    /// Structured details for webhook consumers
+    pub data: serde_json::Value,
+}
+
+/// Gateway that delivers text messages. Credentials are looked up on every
+/// send, so setting or rotating them takes effect without a restart.
+enum SmsProvider {
+    Twilio {
+        account_sid: String,
+        auth_token: String,
+        from: String,
+    },
+}
+
+impl SmsProvider {
+    async fn send(&self, http: &HttpClient, to: &str, body: &str) -> Result<(), HttpError> {
+        match self {
+            SmsProvider::Twilio {
+                account_sid,
+                auth_token,
+                from,
+            } => {
+                let url = format!(
+                    "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+                    account_sid
+                );
+                let request = http
+                    .client()
+                    .post(url)
+                    .basic_auth(account_sid, Some(auth_token))
+                    .form(&[("To", to), ("From", from.as_str()), ("Body", body)]);
+
+                http.execute("twilio", request).await.map(|_| ())
+            }
+        }
+    }
+}
+
+/// Delivers alerts to the server log and, when an alert webhook is configured
+/// (the `alert_webhook_url` secret or `ALERT_WEBHOOK_URL`), POSTs them as JSON
+/// to that webhook (Slack/Zapier/n8n style receivers). Alerts in
+/// `SMS_ALERT_KINDS` are also texted to users who chose them, once an SMS
+/// provider is configured.
+#[derive(Clone)]
+pub struct Notifier {
+    http: HttpClient,
+    secrets: SecretStore,
+    pool: PgPool,
+}
+
+impl Notifier {
+    pub fn new(http: HttpClient, secrets: SecretStore, pool: PgPool) -> Self {
+        Self {
+            http,
+            secrets,
+            pool,
+        }
+    }
+
+    pub async fn send(&self, alert: &Alert) {
+        tracing::warn!("🚨 [{}] {}", alert.kind, alert.message);
+
+        self.post_webhook(alert).await;
+
+        if SMS_ALERT_KINDS.contains(&alert.kind.as_str()) {
+            self.text_subscribers(alert).await;
+        }
+    }
+
+    /// Send an alert meant for one user: to the webhook as usual, and texted
+    /// only to that user if they chose its kind.
+    pub async fn send_to(&self, user_id: Uuid, alert: &Alert) {
+        tracing::warn!("🚨 [{}] {}", alert.kind, alert.message);
+
+        self.post_webhook(alert).await;
+
+        let phone = sqlx::query_scalar!(
+            r#"
+            SELECT phone AS "phone!"
+            FROM users
+            WHERE id = $1 AND is_active AND phone IS NOT NULL AND $2 = ANY(sms_alert_kinds)
+            "#,
+            user_id,
+            alert.kind
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match phone {
+            Ok(Some(phone)) => {
+                self.text(&phone, &alert.message).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("❌ Failed to look up SMS recipient: {}", e),
+        }
+    }
+
+    /// Text a single number (e.g. a customer's pickup reminder). Returns
+    /// whether the message was handed to the provider.
+    pub async fn text(&self, to: &str, body: &str) -> bool {
+        let Some(to) = normalize_phone(to) else {
+            return false;
+        };
+
+        let Some(provider) = self.sms_provider().await else {
+            return false;
+        };
+
+        match provider.send(&self.http, &to, &sms_body(body)).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("❌ Failed to send text to {}: {}", to, e);
+                false
+            }
+        }
+    }
+
+    /// A user's notification settings, or `None` if there's no such user.
+    pub async fn settings(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<NotificationSettings>, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT phone, sms_alert_kinds FROM users WHERE id = $1",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(NotificationSettings {
+            phone: row.phone,
+            sms_alert_kinds: row.sms_alert_kinds,
+            available_sms_alert_kinds: SMS_ALERT_KINDS.iter().map(|k| k.to_string()).collect(),
+            sms_configured: self.sms_provider().await.is_some(),
+        }))
+    }
+
+    async fn post_webhook(&self, alert: &Alert) {
+        let webhook_url = self
+            .secrets
+            .resolve(secrets::ALERT_WEBHOOK_URL, "ALERT_WEBHOOK_URL")
+            .await;
+
+        let Some(url) = webhook_url else {
+            return;
+        };
+
+        let request = self.http.client().post(&url).json(alert);
+
+        if let Err(e) = self.http.execute("alert_webhook", request).await {
+            tracing::error!(
+                "❌ Failed to deliver {} alert to webhook: {}",
+                alert.kind,
+                e
+            );
+        }
+    }
+
+    async fn text_subscribers(&self, alert: &Alert) {
+        let Some(provider) = self.sms_provider().await else {
+            return;
+        };
+
+        let phones = sqlx::query_scalar!(
+            r#"
+            SELECT phone AS "phone!"
+            FROM users
+            WHERE is_active AND phone IS NOT NULL AND $1 = ANY(sms_alert_kinds)
+            "#,
+            alert.kind
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        let phones = match phones {
+            Ok(phones) => phones,
+            Err(e) => {
+                tracing::error!("❌ Failed to look up SMS recipients: {}", e);
+                return;
+            }
+        };
+
+        let body = sms_body(&alert.message);
+        for phone in phones {
+            if let Err(e) = provider.send(&self.http, &phone, &body).await {
+                tracing::error!("❌ Failed to text {} alert to {}: {}", alert.kind, phone, e);
+            }
+        }
+    }
+
+    async fn sms_provider(&self) -> Option<SmsProvider> {
+        let account_sid = self
+            .secrets
+            .resolve(secrets::TWILIO_ACCOUNT_SID, "TWILIO_ACCOUNT_SID")
+            .await?;
+        let auth_token = self
+            .secrets
+            .resolve(secrets::TWILIO_AUTH_TOKEN, "TWILIO_AUTH_TOKEN")
+            .await?;
+        let from = self
+            .secrets
+            .resolve(secrets::TWILIO_FROM_NUMBER, "TWILIO_FROM_NUMBER")
+            .await?;
+
+        Some(SmsProvider::Twilio {
+            account_sid,
+            auth_token,
+            from,
+        })
+    }
+}
+
+/// Put a phone number in E.164 form (`+13015550123`). Ten-digit numbers are
+/// taken as US numbers; anything else needs its country code.
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let trimmed = phone.trim();
+    let digits: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+
+    let valid = trimmed
+        .chars()
+        .all(|c| c.is_ascii_digit() || " +-().".contains(c));
+    if !valid {
+        return None;
+    }
+
+    if trimmed.starts_with('+') {
+        return (8..=15)
+            .contains(&digits.len())
+            .then(|| format!("+{}", digits));
+    }
+
+    match digits.len() {
+        10 => Some(format!("+1{}", digits)),
+        11 if digits.starts_with('1') => Some(format!("+{}", digits)),
+        _ => None,
+    }
+}
+
+fn sms_body(message: &str) -> String {
+    let body = format!("Frederick Ferments: {}", message);
+
+    if body.chars().count() <= MAX_SMS_LENGTH {
+        return body;
+    }
+
+    let mut truncated: String = body.chars().take(MAX_SMS_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
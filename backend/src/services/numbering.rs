@@ -0,0 +1,391 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MutationError, Rejection};
+
+/// Batch numbers unless `BATCH_NUMBER_FORMAT` says otherwise.
+pub const DEFAULT_BATCH_FORMAT: &str = "BATCH-{date}-{seq}";
+
+/// Sale numbers unless `SALE_NUMBER_FORMAT` says otherwise.
+pub const DEFAULT_SALE_FORMAT: &str = "SALE-{date}-{seq}";
+
+/// Customer order numbers unless `ORDER_NUMBER_FORMAT` says otherwise.
+pub const DEFAULT_ORDER_FORMAT: &str = "ORD-{date}-{seq}";
+
+/// Digits the sequence is padded to when the format just says `{seq}`.
+const DEFAULT_SEQ_WIDTH: usize = 3;
+
+/// What a number is handed out for; each has its own counters.
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    Batch,
+    Sale,
+    Order,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Batch => "batch",
+            Kind::Sale => "sale",
+            Kind::Order => "order",
+        }
+    }
+}
+
+/// A number layout like `BATCH-{product}-{date}-{seq}`. Placeholders:
+/// `{date}` (YYYYMMDD), `{year}`, `{month}`, `{day}`, `{product}` (the
+/// product's code; batches only) and `{seq}`, the sequence, zero-padded to
+/// 3 digits or to N with `{seq:N}`. The sequence starts again at 1 whenever
+/// the rest of the number changes, so `{date}` numbers restart daily and a
+/// format without dates never does.
+#[derive(Debug, Clone)]
+pub struct NumberFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Date,
+    Year,
+    Month,
+    Day,
+    Product,
+    Seq(usize),
+}
+
+impl NumberFormat {
+    /// Check a format, explaining what's wrong with it. `{product}` is only
+    /// allowed when `products` is set.
+    pub fn parse(format: &str, products: bool) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = format;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                return Err(format!("{} has an unclosed {{", format));
+            };
+            let part = match &rest[start + 1..end] {
+                "date" => Part::Date,
+                "year" => Part::Year,
+                "month" => Part::Month,
+                "day" => Part::Day,
+                "product" if products => Part::Product,
+                "seq" => Part::Seq(DEFAULT_SEQ_WIDTH),
+                name => match name.strip_prefix("seq:").map(str::parse::<usize>) {
+                    Some(Ok(width @ 1..=9)) => Part::Seq(width),
+                    _ => {
+                        return Err(format!(
+                            "{} has an unknown placeholder {{{}}}",
+                            format, name
+                        ));
+                    }
+                },
+            };
+            parts.push(part);
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+
+        if parts
+            .iter()
+            .any(|part| matches!(part, Part::Text(text) if text.contains('}')))
+        {
+            return Err(format!("{} has a stray }}", format));
+        }
+        if parts
+            .iter()
+            .filter(|part| matches!(part, Part::Seq(_)))
+            .count()
+            != 1
+        {
+            return Err(format!("{} must contain {{seq}} exactly once", format));
+        }
+
+        Ok(NumberFormat { parts })
+    }
+
+    /// The number with `seq` in its place, or with `{seq}` left in when
+    /// None (the counter's scope).
+    fn render(&self, date: NaiveDate, product: &str, seq: Option<i64>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Date => date.format("%Y%m%d").to_string(),
+                Part::Year => date.format("%Y").to_string(),
+                Part::Month => date.format("%m").to_string(),
+                Part::Day => date.format("%d").to_string(),
+                Part::Product => product.to_string(),
+                Part::Seq(width) => match seq {
+                    Some(seq) => format!("{:0width$}", seq, width = width),
+                    None => "{seq}".to_string(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// The next number for a batch, sale or order on `date`. Counters are rows in
+/// `number_sequences`, locked until the caller's transaction ends, so two
+/// batches started at once get different numbers and a rolled-back one
+/// gives its number back. A scope's first number follows the highest one
+/// already used (e.g. by numbers made before counters existed), and numbers
+/// already taken (imported, or restored from a backup) are skipped.
+pub async fn next(
+    conn: &mut PgConnection,
+    kind: Kind,
+    format: &NumberFormat,
+    date: NaiveDate,
+    product: &str,
+) -> Result<String, sqlx::Error> {
+    let scope = format.render(date, product, None);
+
+    loop {
+        let seq = count(&mut *conn, kind, &scope).await?;
+        let number = format.render(date, product, Some(seq));
+
+        let taken = match kind {
+            Kind::Batch => {
+                sqlx::query_scalar!(
+                    r#"SELECT EXISTS(SELECT 1 FROM production_batches WHERE batch_number = $1) AS "taken!""#,
+                    number
+                )
+                .fetch_one(&mut *conn)
+                .await?
+            }
+            Kind::Sale => {
+                sqlx::query_scalar!(
+                    r#"SELECT EXISTS(SELECT 1 FROM sales WHERE sale_number = $1) AS "taken!""#,
+                    number
+                )
+                .fetch_one(&mut *conn)
+                .await?
+            }
+            Kind::Order => {
+                sqlx::query_scalar!(
+                    r#"SELECT EXISTS(SELECT 1 FROM customer_orders WHERE order_number = $1) AS "taken!""#,
+                    number
+                )
+                .fetch_one(&mut *conn)
+                .await?
+            }
+        };
+        if !taken {
+            return Ok(number);
+        }
+    }
+}
+
+/// Advance the scope's counter, starting it if it's new.
+async fn count(conn: &mut PgConnection, kind: Kind, scope: &str) -> Result<i64, sqlx::Error> {
+    let counted = sqlx::query_scalar!(
+        r#"
+        UPDATE number_sequences SET last_value = last_value + 1
+        WHERE kind = $1 AND scope = $2
+        RETURNING last_value
+        "#,
+        kind.as_str(),
+        scope
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(seq) = counted {
+        return Ok(seq);
+    }
+
+    let (prefix, suffix) = scope.split_once("{seq}").unwrap_or((scope, ""));
+    let pattern = format!("^{}([0-9]+){}$", escape(prefix), escape(suffix));
+    let highest = match kind {
+        Kind::Batch => {
+            sqlx::query_scalar!(
+                r#"
+                SELECT MAX(substring(batch_number FROM $1)::BIGINT)
+                FROM production_batches WHERE batch_number ~ $1
+                "#,
+                pattern
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+        Kind::Sale => {
+            sqlx::query_scalar!(
+                r#"
+                SELECT MAX(substring(sale_number FROM $1)::BIGINT)
+                FROM sales WHERE sale_number ~ $1
+                "#,
+                pattern
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+        Kind::Order => {
+            sqlx::query_scalar!(
+                r#"
+                SELECT MAX(substring(order_number FROM $1)::BIGINT)
+                FROM customer_orders WHERE order_number ~ $1
+                "#,
+                pattern
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    // Another transaction may have started the counter meanwhile
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO number_sequences (kind, scope, last_value)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (kind, scope) DO UPDATE
+            SET last_value = number_sequences.last_value + 1
+        RETURNING last_value
+        "#,
+        kind.as_str(),
+        scope,
+        highest.unwrap_or(0) + 1
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+/// A product code as it will be stored (trimmed, upper case), or why it
+/// can't be used: codes are 2 to 10 letters and digits, and no two items
+/// share one. `item_id` is the item being edited, if any.
+pub async fn product_code(
+    executor: impl PgExecutor<'_>,
+    code: &str,
+    item_id: Option<Uuid>,
+) -> Result<Result<String, Rejection>, sqlx::Error> {
+    let code = code.trim().to_uppercase();
+    if !(2..=10).contains(&code.len()) || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Ok(Err(format!(
+            "Product code {} must be 2 to 10 letters and digits",
+            code
+        )
+        .into()));
+    }
+
+    let taken_by = sqlx::query_scalar!(
+        "SELECT name FROM inventory WHERE product_code = $1 AND id IS DISTINCT FROM $2",
+        code,
+        item_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(match taken_by {
+        Some(name) => Err(Rejection::new(
+            format!("Product code {} is already used by {}", code, name),
+            MutationError::name_conflict("Product code", &code),
+        )),
+        None => Ok(code),
+    })
+}
+
+/// A code made from a product's name, for products without one and for
+/// `backfill-product-codes`: the initials of a name of several words
+/// ("Garlic Dill Sauerkraut" → GDS), else the first letter and the
+/// consonants after it ("Kraut" → KRT), 3 letters where the name allows.
+pub fn suggested_code(name: &str) -> String {
+    let words: Vec<Vec<char>> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_uppercase().chars().collect())
+        .collect();
+
+    let code: String = match words.as_slice() {
+        [] => return "ITEM".to_string(),
+        [word] => {
+            let consonants = word[1..]
+                .iter()
+                .filter(|c| !"AEIOU".contains(**c))
+                .chain(word[1..].iter().filter(|c| "AEIOU".contains(**c)));
+            word[..1].iter().chain(consonants).take(3).collect()
+        }
+        words => words.iter().take(4).map(|word| word[0]).collect(),
+    };
+
+    // Codes are at least 2 characters
+    if code.len() < 2 {
+        format!("{}X", code)
+    } else {
+        code
+    }
+}
+
+/// Give every product made here (finished products, and items recipes or
+/// batches make) that has no product code one made from its name, adding
+/// a digit where two would clash. Existing codes and batch numbers are left
+/// alone. Run as `backfill-product-codes`.
+pub async fn backfill_product_codes(pool: &PgPool) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let mut taken: HashSet<String> = sqlx::query_scalar!(
+        r#"SELECT product_code AS "product_code!" FROM inventory WHERE product_code IS NOT NULL"#
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let products = sqlx::query!(
+        r#"
+        SELECT i.id, i.name
+        FROM inventory i
+        WHERE i.product_code IS NULL AND i.is_active
+            AND (i.category = 'finished_product'
+                OR EXISTS (SELECT 1 FROM recipe_templates r WHERE r.product_inventory_id = i.id)
+                OR EXISTS (SELECT 1 FROM production_batches b WHERE b.product_inventory_id = i.id))
+        ORDER BY i.created_at, i.name
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for product in &products {
+        let base = suggested_code(&product.name);
+        let code = std::iter::once(base.clone())
+            .chain((2..).map(|n: u32| {
+                let n = n.to_string();
+                format!("{}{}", &base[..base.len().min(10 - n.len())], n)
+            }))
+            .find(|code| !taken.contains(code))
+            .unwrap_or(base);
+
+        sqlx::query!(
+            "UPDATE inventory SET product_code = $2, updated_at = NOW() WHERE id = $1",
+            product.id,
+            code
+        )
+        .execute(&mut *tx)
+        .await?;
+        println!("{}\t{}", code, product.name);
+        taken.insert(code);
+    }
+
+    tx.commit().await?;
+    eprintln!("🏷️  Gave {} products a product code", products.len());
+    Ok(())
+}
+
+/// `text` matched literally in a Postgres regular expression.
+fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| {
+            c.is_ascii_punctuation()
+                .then_some('\\')
+                .into_iter()
+                .chain([c])
+        })
+        .collect()
+}
@@ -0,0 +1,76 @@
+use sqlx::PgPool;
+
+use crate::models::{OnboardingStatus, OnboardingStep};
+
+/// Locations set up when the owner takes the defaults: (name, type, description).
+pub const DEFAULT_LOCATIONS: &[(&str, &str, &str)] = &[
+    (
+        "Walk-in Cooler",
+        "cold_storage",
+        "Finished product and slow ferments",
+    ),
+    (
+        "Dry Storage",
+        "dry_storage",
+        "Salt, spices, jars and dry goods",
+    ),
+];
+
+/// Setup steps and how far this deployment has got through them.
+pub async fn status(pool: &PgPool) -> Result<OnboardingStatus, sqlx::Error> {
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM locations WHERE is_active = true) AS "locations!",
+            (SELECT COUNT(*) FROM units WHERE is_active = true) AS "units!",
+            (SELECT COUNT(*) FROM inventory_categories WHERE is_active = true) AS "categories!",
+            (SELECT COUNT(*) FROM recipe_templates WHERE is_active = true) AS "recipes!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let step = |key: &str, title: &str, count: i64, hint: &str| OnboardingStep {
+        key: key.to_string(),
+        title: title.to_string(),
+        done: count > 0,
+        count,
+        hint: hint.to_string(),
+    };
+
+    let steps = vec![
+        step(
+            "locations",
+            "Add your storage locations",
+            counts.locations,
+            "Where stock and batches are kept. setUpLocations adds a walk-in cooler and dry storage, or your own list",
+        ),
+        step(
+            "units",
+            "Choose units of measure",
+            counts.units,
+            "Units offered when adding items and recipes. setUpUnits adds grams, liters, jars and the like",
+        ),
+        step(
+            "categories",
+            "Choose inventory categories",
+            counts.categories,
+            "How ingredients, packaging and products are grouped. setUpCategories adds the library set",
+        ),
+        step(
+            "recipes",
+            "Add your first recipes",
+            counts.recipes,
+            "Pick from the built-in library with addStarterRecipes or importFromLibrary, or write your own with createRecipeTemplate",
+        ),
+    ];
+
+    Ok(OnboardingStatus {
+        complete: steps.iter().all(|step| step.done),
+        next_step: steps
+            .iter()
+            .find(|step| !step.done)
+            .map(|step| step.key.clone()),
+        steps,
+    })
+}
@@ -0,0 +1,745 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::models::{
+    CreateOrderInput, CustomerOrder, CustomerOrderItem, MutationError, OrderItemInput, Rejection,
+    UpdateOrderInput,
+};
+use crate::services::config;
+use crate::services::events;
+use crate::services::invoices;
+use crate::services::numbering;
+use crate::services::pricing;
+use crate::services::sales::{self, SaleHeader, SaleLine};
+
+/// Order statuses still waiting on delivery.
+pub const OPEN: [&str; 2] = ["draft", "confirmed"];
+
+/// Sales channel fulfilled orders are sold through.
+pub const CHANNEL: &str = "order";
+
+struct LockedOrder {
+    order_number: String,
+    customer_id: Uuid,
+    status: String,
+    sale_id: Option<Uuid>,
+}
+
+/// What an order needs of one item, against what there is.
+struct Needed {
+    inventory_id: Uuid,
+    name: String,
+    quantity: BigDecimal,
+    current_stock: BigDecimal,
+    available_stock: BigDecimal,
+    is_active: bool,
+    discontinued: bool,
+}
+
+/// Take an order as a draft, numbered (`ORDER_NUMBER_FORMAT`) for the day
+/// it's taken.
+pub async fn create(
+    conn: &mut PgConnection,
+    input: &CreateOrderInput,
+) -> Result<Result<Uuid, Rejection>, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1) AS "exists!""#,
+        input.customer_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !exists {
+        return Ok(Err(Rejection::new(
+            "Customer not found".to_string(),
+            MutationError::not_found("Customer"),
+        )));
+    }
+
+    let lines = match lines(&mut *conn, input.customer_id, &input.items).await? {
+        Ok(lines) => lines,
+        Err(rejection) => return Ok(Err(rejection)),
+    };
+
+    let order_number = numbering::next(
+        &mut *conn,
+        numbering::Kind::Order,
+        &config::order_number_format(),
+        Utc::now().date_naive(),
+        "",
+    )
+    .await?;
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO customer_orders (order_number, customer_id, delivery_date, notes)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        order_number,
+        input.customer_id,
+        input.delivery_date,
+        input.notes
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    write_items(&mut *conn, id, &lines).await?;
+
+    Ok(Ok(id))
+}
+
+/// Edit a draft order: its delivery date, notes, and (replacing them) its
+/// items.
+pub async fn update(
+    conn: &mut PgConnection,
+    input: &UpdateOrderInput,
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let Some(order) = lock(&mut *conn, input.order_id).await? else {
+        return Ok(Err(not_found()));
+    };
+    if order.status != "draft" {
+        return Ok(Err(format!(
+            "Order {} is {}; only drafts can be changed",
+            order.order_number, order.status
+        )
+        .into()));
+    }
+
+    if let Some(items) = &input.items {
+        let lines = match lines(&mut *conn, order.customer_id, items).await? {
+            Ok(lines) => lines,
+            Err(rejection) => return Ok(Err(rejection)),
+        };
+        write_items(&mut *conn, input.order_id, &lines).await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE customer_orders
+        SET delivery_date = COALESCE($2, delivery_date),
+            notes = COALESCE($3, notes),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        input.order_id,
+        input.delivery_date,
+        input.notes
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+/// An order's lines with their prices settled: the price given, else the
+/// customer's (see `pricing::price_for`).
+async fn lines(
+    conn: &mut PgConnection,
+    customer_id: Uuid,
+    items: &[OrderItemInput],
+) -> Result<Result<Vec<SaleLine>, Rejection>, sqlx::Error> {
+    if items.is_empty() {
+        return Ok(Err("An order needs at least one item".to_string().into()));
+    }
+
+    let zero = BigDecimal::from(0);
+    let mut lines = Vec::new();
+    for item in items {
+        if item.quantity <= zero {
+            return Ok(Err("Quantity must be greater than 0".to_string().into()));
+        }
+        if item.unit_price.as_ref().is_some_and(|price| *price < zero) {
+            return Ok(Err("A unit price can't be negative".to_string().into()));
+        }
+
+        let inventory = sqlx::query!(
+            "SELECT name, is_active, discontinued_at FROM inventory WHERE id = $1",
+            item.inventory_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(inventory) = inventory else {
+            return Ok(Err(Rejection::new(
+                format!("Inventory item not found: {}", item.inventory_id),
+                MutationError::not_found("Inventory item"),
+            )));
+        };
+        if let Some(rejection) = not_sold(
+            &inventory.name,
+            inventory.is_active,
+            inventory.discontinued_at.is_some(),
+        ) {
+            return Ok(Err(rejection));
+        }
+        let name = inventory.name;
+
+        let unit_price = match &item.unit_price {
+            Some(unit_price) => unit_price.clone(),
+            None => {
+                let price =
+                    pricing::price_for(&mut *conn, Some(customer_id), item.inventory_id).await?;
+                let Some(unit_price) = price.unit_price else {
+                    return Ok(Err(format!(
+                        "{} has no price for this customer; give a unit price",
+                        name
+                    )
+                    .into()));
+                };
+                unit_price
+            }
+        };
+        lines.push(SaleLine {
+            inventory_id: item.inventory_id,
+            quantity: item.quantity.clone(),
+            unit_price,
+            discounts: Vec::new(),
+            notes: item.notes.clone(),
+        });
+    }
+
+    Ok(Ok(lines))
+}
+
+/// Why an item can't go on an order: it's been deactivated, or discontinued
+/// (its remaining stock sells through at the counter, but isn't promised out).
+fn not_sold(name: &str, is_active: bool, discontinued: bool) -> Option<Rejection> {
+    if !is_active {
+        Some(Rejection::new(
+            format!("{} is inactive", name),
+            MutationError::not_found("Inventory item"),
+        ))
+    } else if discontinued {
+        Some(format!("{} has been discontinued", name).into())
+    } else {
+        None
+    }
+}
+
+/// Replace an order's items.
+async fn write_items(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    lines: &[SaleLine],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM customer_order_items WHERE order_id = $1",
+        order_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for line in lines {
+        sqlx::query!(
+            r#"
+            INSERT INTO customer_order_items (order_id, inventory_id, quantity, unit_price, notes)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            order_id,
+            line.inventory_id,
+            line.quantity,
+            line.unit_price,
+            line.notes
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Confirm a draft order, reserving its stock. Refused when an item has
+/// been deactivated or discontinued since the order was taken, or has less
+/// available (on hand, less what's already reserved) than ordered.
+pub async fn confirm(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let Some(order) = lock(&mut *conn, order_id).await? else {
+        return Ok(Err(not_found()));
+    };
+    if order.status != "draft" {
+        return Ok(Err(format!(
+            "Order {} is {}; only drafts can be confirmed",
+            order.order_number, order.status
+        )
+        .into()));
+    }
+
+    let needed = needed(&mut *conn, order_id).await?;
+    if let Some(rejection) = needed
+        .iter()
+        .find_map(|item| not_sold(&item.name, item.is_active, item.discontinued))
+    {
+        return Ok(Err(rejection));
+    }
+    if let Some(short) = needed
+        .iter()
+        .find(|item| item.available_stock < item.quantity)
+    {
+        return Ok(Err(Rejection::new(
+            format!(
+                "Not enough {} for order {}: {} available, {} ordered",
+                short.name, order.order_number, short.available_stock, short.quantity
+            ),
+            MutationError::insufficient_stock(
+                short.inventory_id,
+                &short.name,
+                &short.quantity,
+                &short.available_stock,
+            ),
+        )));
+    }
+
+    for item in &needed {
+        sqlx::query!(
+            "UPDATE inventory SET reserved_stock = reserved_stock + $1, updated_at = $2 WHERE id = $3",
+            item.quantity,
+            now,
+            item.inventory_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE customer_orders
+        SET status = 'confirmed', confirmed_at = $2, updated_at = $2
+        WHERE id = $1
+        "#,
+        order_id,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+/// Fulfil a confirmed order: its reservation is released and it is sold to
+/// its customer at the order's prices, on the `order` channel. With a
+/// `payment_method` it was paid on delivery, except 'invoice', which bills
+/// the customer at once and leaves the order invoiced; without one the sale
+/// waits on payment. Refused when an item has less stock on record than
+/// ordered.
+pub async fn fulfill(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    payment_method: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let Some(order) = lock(&mut *conn, order_id).await? else {
+        return Ok(Err(not_found()));
+    };
+    if order.status != "confirmed" {
+        return Ok(Err(format!(
+            "Order {} is {}; only confirmed orders can be fulfilled",
+            order.order_number, order.status
+        )
+        .into()));
+    }
+
+    let needed = needed(&mut *conn, order_id).await?;
+    if let Some(short) = needed
+        .iter()
+        .find(|item| item.current_stock < item.quantity)
+    {
+        return Ok(Err(Rejection::new(
+            format!(
+                "Not enough {} on hand for order {}: {} on record, {} ordered",
+                short.name, order.order_number, short.current_stock, short.quantity
+            ),
+            MutationError::insufficient_stock(
+                short.inventory_id,
+                &short.name,
+                &short.quantity,
+                &short.current_stock,
+            ),
+        )));
+    }
+    release(&mut *conn, order_id, now).await?;
+
+    let lines: Vec<SaleLine> = sqlx::query!(
+        r#"
+        SELECT o.inventory_id, o.quantity, o.unit_price, o.notes
+        FROM customer_order_items o
+        JOIN inventory i ON i.id = o.inventory_id
+        WHERE o.order_id = $1
+        ORDER BY i.name, o.id
+        "#,
+        order_id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| SaleLine {
+        inventory_id: row.inventory_id,
+        quantity: row.quantity,
+        unit_price: row.unit_price,
+        discounts: Vec::new(),
+        notes: row.notes,
+    })
+    .collect();
+
+    let invoiced = payment_method == Some("invoice");
+    let total_amount: BigDecimal = lines.iter().map(SaleLine::total).sum();
+    let sale_number = sales::next_sale_number(&mut *conn, now).await?;
+    let sale_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sales (
+            sale_number, customer_id, sale_date, subtotal, total_amount,
+            payment_method, payment_status, notes, channel
+        ) VALUES ($1, $2, $3, $4, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+        sale_number,
+        order.customer_id,
+        now,
+        total_amount,
+        payment_method,
+        match payment_method {
+            Some(method) if method != "invoice" => "completed",
+            _ => "pending",
+        },
+        format!("Order {}", order.order_number),
+        CHANNEL
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let header = SaleHeader {
+        id: sale_id,
+        sale_number: &sale_number,
+        sale_date: now,
+    };
+    let mut sold = Vec::new();
+    for line in lines {
+        let (_, line) = sales::sell_item(&mut *conn, &header, line).await?;
+        sold.push(line);
+    }
+
+    events::publish(
+        &mut *conn,
+        "saleCreated",
+        json!({
+            "sale_id": sale_id,
+            "sale_number": sale_number,
+            "sale_date": now,
+            "customer_id": order.customer_id,
+            "channel": CHANNEL,
+            "market_session_id": null,
+            "subtotal": total_amount,
+            "tax_amount": BigDecimal::from(0),
+            "discount_amount": BigDecimal::from(0),
+            "total_amount": total_amount,
+            "payment_method": payment_method,
+            "order_number": order.order_number,
+            "items": sold,
+        }),
+    )
+    .await?;
+
+    if invoiced {
+        invoices::open(
+            &mut *conn,
+            sale_id,
+            order.customer_id,
+            &total_amount,
+            now.date_naive(),
+        )
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE customer_orders
+        SET status = $2, sale_id = $3, fulfilled_at = $4, updated_at = $4
+        WHERE id = $1
+        "#,
+        order_id,
+        if invoiced { "invoiced" } else { "fulfilled" },
+        sale_id,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+/// Bill a fulfilled order's customer for its sale, due after their payment
+/// terms (see `invoices::open`). Refused once the sale is paid.
+pub async fn invoice(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let Some(order) = lock(&mut *conn, order_id).await? else {
+        return Ok(Err(not_found()));
+    };
+    let (true, Some(sale_id)) = (order.status == "fulfilled", order.sale_id) else {
+        return Ok(Err(format!(
+            "Order {} is {}; only fulfilled orders can be invoiced",
+            order.order_number, order.status
+        )
+        .into()));
+    };
+
+    let sale = sqlx::query!(
+        r#"
+        UPDATE sales SET payment_method = 'invoice', updated_at = $2
+        WHERE id = $1 AND payment_status = 'pending'
+        RETURNING total_amount
+        "#,
+        sale_id,
+        now
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(sale) = sale else {
+        return Ok(Err(format!(
+            "The sale for order {} isn't waiting on payment",
+            order.order_number
+        )
+        .into()));
+    };
+
+    invoices::open(
+        &mut *conn,
+        sale_id,
+        order.customer_id,
+        &sale.total_amount,
+        now.date_naive(),
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE customer_orders SET status = 'invoiced', updated_at = $2 WHERE id = $1",
+        order_id,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+/// Cancel a draft or confirmed order, releasing a confirmed one's stock.
+pub async fn cancel(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    reason: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let Some(order) = lock(&mut *conn, order_id).await? else {
+        return Ok(Err(not_found()));
+    };
+    if !OPEN.contains(&order.status.as_str()) {
+        return Ok(Err(format!(
+            "Order {} is already {}",
+            order.order_number, order.status
+        )
+        .into()));
+    }
+
+    if order.status == "confirmed" {
+        release(&mut *conn, order_id, now).await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE customer_orders
+        SET status = 'cancelled',
+            notes = CASE
+                WHEN $2::text IS NULL THEN notes
+                ELSE concat_ws(E'\n', notes, 'Cancelled: ' || $2)
+            END,
+            cancelled_at = $3,
+            updated_at = $3
+        WHERE id = $1
+        "#,
+        order_id,
+        reason,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+fn not_found() -> Rejection {
+    Rejection::new(
+        "Order not found".to_string(),
+        MutationError::not_found("Order"),
+    )
+}
+
+/// The order, locked until the caller's transaction ends.
+async fn lock(conn: &mut PgConnection, order_id: Uuid) -> Result<Option<LockedOrder>, sqlx::Error> {
+    sqlx::query_as!(
+        LockedOrder,
+        r#"
+        SELECT order_number, customer_id, status, sale_id
+        FROM customer_orders
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        order_id
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+/// Each item the order takes, with its stock. The items' rows stay locked
+/// until the caller's transaction ends.
+async fn needed(conn: &mut PgConnection, order_id: Uuid) -> Result<Vec<Needed>, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        SELECT id FROM inventory
+        WHERE id IN (SELECT inventory_id FROM customer_order_items WHERE order_id = $1)
+        ORDER BY id
+        FOR UPDATE
+        "#,
+        order_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query_as!(
+        Needed,
+        r#"
+        SELECT
+            o.inventory_id, i.name, SUM(o.quantity) AS "quantity!",
+            i.current_stock, i.available_stock AS "available_stock!",
+            i.is_active, i.discontinued_at IS NOT NULL AS "discontinued!"
+        FROM customer_order_items o
+        JOIN inventory i ON i.id = o.inventory_id
+        WHERE o.order_id = $1
+        GROUP BY o.inventory_id, i.name, i.current_stock, i.available_stock, i.is_active,
+            i.discontinued_at
+        ORDER BY i.name
+        "#,
+        order_id
+    )
+    .fetch_all(conn)
+    .await
+}
+
+/// Take a confirmed order's quantities back off its items' reserved stock.
+async fn release(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE inventory i
+        SET reserved_stock = GREATEST(i.reserved_stock - o.quantity, 0), updated_at = $2
+        FROM (
+            SELECT inventory_id, SUM(quantity) AS quantity
+            FROM customer_order_items
+            WHERE order_id = $1
+            GROUP BY inventory_id
+        ) o
+        WHERE i.id = o.inventory_id
+        "#,
+        order_id,
+        now
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// An order by ID.
+pub async fn by_id(
+    executor: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Option<CustomerOrder>, sqlx::Error> {
+    sqlx::query_as!(
+        CustomerOrder,
+        r#"
+        SELECT
+            o.id, o.order_number, o.customer_id, c.name AS customer_name, o.delivery_date,
+            o.status,
+            COALESCE(
+                (SELECT SUM(ROUND(i.quantity * i.unit_price, 2))
+                 FROM customer_order_items i WHERE i.order_id = o.id),
+                0
+            ) AS "total_amount!",
+            o.sale_id, s.sale_number AS "sale_number?", o.notes,
+            o.confirmed_at, o.fulfilled_at, o.cancelled_at, o.created_at, o.updated_at
+        FROM customer_orders o
+        JOIN customers c ON c.id = o.customer_id
+        LEFT JOIN sales s ON s.id = o.sale_id
+        WHERE o.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Orders by delivery date, optionally with one of `statuses`, for one
+/// customer, or delivering between two dates (inclusive).
+pub async fn orders(
+    executor: impl PgExecutor<'_>,
+    statuses: Option<&[String]>,
+    customer_id: Option<Uuid>,
+    delivery_from: Option<NaiveDate>,
+    delivery_to: Option<NaiveDate>,
+) -> Result<Vec<CustomerOrder>, sqlx::Error> {
+    sqlx::query_as!(
+        CustomerOrder,
+        r#"
+        SELECT
+            o.id, o.order_number, o.customer_id, c.name AS customer_name, o.delivery_date,
+            o.status,
+            COALESCE(
+                (SELECT SUM(ROUND(i.quantity * i.unit_price, 2))
+                 FROM customer_order_items i WHERE i.order_id = o.id),
+                0
+            ) AS "total_amount!",
+            o.sale_id, s.sale_number AS "sale_number?", o.notes,
+            o.confirmed_at, o.fulfilled_at, o.cancelled_at, o.created_at, o.updated_at
+        FROM customer_orders o
+        JOIN customers c ON c.id = o.customer_id
+        LEFT JOIN sales s ON s.id = o.sale_id
+        WHERE ($1::varchar[] IS NULL OR o.status = ANY($1))
+            AND ($2::uuid IS NULL OR o.customer_id = $2)
+            AND ($3::date IS NULL OR o.delivery_date >= $3)
+            AND ($4::date IS NULL OR o.delivery_date <= $4)
+        ORDER BY o.delivery_date, c.name, o.order_number
+        "#,
+        statuses,
+        customer_id,
+        delivery_from,
+        delivery_to
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// An order's items, by name.
+pub async fn items(
+    executor: impl PgExecutor<'_>,
+    order_id: Uuid,
+) -> Result<Vec<CustomerOrderItem>, sqlx::Error> {
+    sqlx::query_as!(
+        CustomerOrderItem,
+        r#"
+        SELECT
+            o.id, o.inventory_id, i.name AS item_name, i.unit, o.quantity, o.unit_price,
+            ROUND(o.quantity * o.unit_price, 2) AS "line_total!", o.notes
+        FROM customer_order_items o
+        JOIN inventory i ON i.id = o.inventory_id
+        WHERE o.order_id = $1
+        ORDER BY i.name, o.id
+        "#,
+        order_id
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,145 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgPool;
+
+use crate::models::{OverheadLine, OverheadPool, OverheadRate};
+
+/// Ways an overhead pool can be spread over production.
+pub const BASES: &[&str] = &["per_batch", "per_liter", "per_labor_hour"];
+
+/// `quantity` of `unit` in liters, for volume units.
+pub fn liters(quantity: &BigDecimal, unit: &str) -> Option<BigDecimal> {
+    let per_unit = match unit.trim().to_lowercase().as_str() {
+        "l" | "liter" | "liters" | "litre" | "litres" => "1",
+        "ml" | "milliliter" | "milliliters" => "0.001",
+        "gal" | "gallon" | "gallons" => "3.785411784",
+        "qt" | "quart" | "quarts" => "0.946352946",
+        "pt" | "pint" | "pints" => "0.473176473",
+        _ => return None,
+    };
+
+    let per_unit: BigDecimal = per_unit.parse().ok()?;
+    Some(quantity * per_unit)
+}
+
+/// Monthly volume of a basis actually produced: completed batches over the
+/// last 90 days, divided by three. Batches in non-volume units don't count
+/// towards liters.
+pub async fn trailing_monthly_volume(
+    pool: &PgPool,
+    basis: &str,
+) -> Result<BigDecimal, sqlx::Error> {
+    let batches = sqlx::query!(
+        r#"
+        SELECT batch_size, unit, production_time_hours
+        FROM production_batches
+        WHERE status = 'completed'
+            AND completion_date >= NOW() - INTERVAL '90 days'
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total: BigDecimal = match basis {
+        "per_batch" => BigDecimal::from(batches.len() as i64),
+        "per_liter" => batches
+            .iter()
+            .filter_map(|batch| liters(&batch.batch_size, &batch.unit))
+            .sum(),
+        _ => batches
+            .iter()
+            .filter_map(|batch| batch.production_time_hours.clone())
+            .sum(),
+    };
+
+    Ok((total / BigDecimal::from(3)).with_scale_round(3, bigdecimal::RoundingMode::HalfUp))
+}
+
+/// A pool's rate per batch, liter or labor hour.
+pub async fn rate(pool: &PgPool, overhead: &OverheadPool) -> Result<OverheadRate, sqlx::Error> {
+    let (monthly_volume, volume_source) = match &overhead.expected_monthly_volume {
+        Some(volume) => (volume.clone(), "expected"),
+        None => (
+            trailing_monthly_volume(pool, &overhead.allocation_basis).await?,
+            "trailing_90_days",
+        ),
+    };
+
+    let rate = (monthly_volume > BigDecimal::from(0)).then(|| {
+        (&overhead.monthly_amount / &monthly_volume)
+            .with_scale_round(6, bigdecimal::RoundingMode::HalfUp)
+    });
+
+    Ok(OverheadRate {
+        monthly_volume,
+        volume_source: volume_source.to_string(),
+        rate,
+    })
+}
+
+/// Spread every active overhead pool over one batch of `batch_size` `unit`
+/// taking `labor_hours` of work.
+pub async fn allocate(
+    pool: &PgPool,
+    batch_size: &BigDecimal,
+    unit: Option<&str>,
+    labor_hours: Option<&BigDecimal>,
+) -> Result<Vec<OverheadLine>, sqlx::Error> {
+    let pools = sqlx::query_as!(
+        OverheadPool,
+        r#"
+        SELECT
+            id, name, monthly_amount, allocation_basis, expected_monthly_volume,
+            is_active, notes, created_at, updated_at
+        FROM overhead_pools
+        WHERE is_active = true
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut lines = Vec::new();
+
+    for overhead in pools {
+        let rate = rate(pool, &overhead).await?.rate;
+
+        let (quantity, missing) = match overhead.allocation_basis.as_str() {
+            "per_batch" => (Some(BigDecimal::from(1)), None),
+            "per_liter" => match unit.and_then(|unit| liters(batch_size, unit)) {
+                Some(liters) => (
+                    Some(liters.with_scale_round(3, bigdecimal::RoundingMode::HalfUp)),
+                    None,
+                ),
+                None => (None, Some("Batch unit is not a volume")),
+            },
+            _ => match labor_hours {
+                Some(hours) => (Some(hours.clone()), None),
+                None => (None, Some("No labor hours recorded for this recipe")),
+            },
+        };
+
+        let line_cost = match (&quantity, &rate) {
+            (Some(quantity), Some(rate)) => {
+                Some((quantity * rate).with_scale_round(2, bigdecimal::RoundingMode::HalfUp))
+            }
+            _ => None,
+        };
+        let note = match (missing, &rate) {
+            (Some(missing), _) => Some(missing.to_string()),
+            (None, None) => Some("No production volume to spread it over yet".to_string()),
+            (None, Some(_)) => None,
+        };
+
+        lines.push(OverheadLine {
+            overhead_pool_id: overhead.id,
+            name: overhead.name,
+            allocation_basis: overhead.allocation_basis,
+            quantity,
+            rate,
+            line_cost,
+            note,
+        });
+    }
+
+    Ok(lines)
+}
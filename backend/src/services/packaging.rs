@@ -0,0 +1,129 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::{MutationError, PackagingMaterialInput, Rejection};
+use crate::services::lots::{self, LotUsage};
+
+/// Check that packaging materials (jars, lids, labels) are listed once each,
+/// exist, and are in stock. Returns the reason if they can't be used.
+pub async fn validate_materials(
+    conn: &mut PgConnection,
+    materials: &[PackagingMaterialInput],
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    for (index, material) in materials.iter().enumerate() {
+        if material.quantity <= BigDecimal::from(0) {
+            return Ok(Err("All packaging quantities must be greater than 0"
+                .to_string()
+                .into()));
+        }
+
+        if materials[..index]
+            .iter()
+            .any(|m| m.inventory_id == material.inventory_id)
+        {
+            return Ok(Err(format!(
+                "Packaging item {} is listed more than once",
+                material.inventory_id
+            )
+            .into()));
+        }
+
+        let inv = sqlx::query!(
+            "SELECT name, current_stock FROM inventory WHERE id = $1 AND is_active = true",
+            material.inventory_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(inv) = inv else {
+            return Ok(Err(Rejection::new(
+                format!(
+                    "Packaging item with ID {} not found or is inactive",
+                    material.inventory_id
+                ),
+                MutationError::not_found("Packaging item"),
+            )));
+        };
+
+        if inv.current_stock < material.quantity {
+            return Ok(Err(Rejection::new(
+                format!(
+                    "Insufficient stock for {}: need {}, have {}",
+                    inv.name, material.quantity, inv.current_stock
+                ),
+                MutationError::insufficient_stock(
+                    material.inventory_id,
+                    &inv.name,
+                    &material.quantity,
+                    &inv.current_stock,
+                ),
+            )));
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+/// Consume validated packaging materials for a batch's output: record them
+/// on the batch, draw down stock and lots, and log the usage.
+pub async fn consume_materials(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    batch_number: &str,
+    materials: &[PackagingMaterialInput],
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    for material in materials {
+        let unit = sqlx::query_scalar!(
+            "SELECT unit FROM inventory WHERE id = $1",
+            material.inventory_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO production_batch_packaging (batch_id, inventory_id, quantity, unit)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            batch_id,
+            material.inventory_id,
+            material.quantity,
+            unit
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+            material.quantity,
+            now,
+            material.inventory_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let draws = lots::consume(&mut *conn, material.inventory_id, &material.quantity).await?;
+        lots::record_usage(&mut *conn, &draws, LotUsage::ProductionBatch(batch_id)).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_logs (
+                inventory_id, movement_type, quantity, reason, batch_number, created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            material.inventory_id,
+            "packaging_use",
+            -material.quantity.clone(), // Negative because it's consumption
+            format!("Packaged output of batch {}", batch_number),
+            batch_number,
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
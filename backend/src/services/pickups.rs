@@ -0,0 +1,132 @@
+use chrono::{NaiveDate, NaiveTime};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{PickupItem, PickupManifestEntry};
+use crate::services::calendar::{self, Purpose};
+
+/// What a booking can be set to once it's made.
+pub const OUTCOMES: [&str; 3] = ["picked_up", "no_show", "cancelled"];
+
+/// Check a window's times and capacity before saving it.
+pub fn validate_window(start: NaiveTime, end: NaiveTime, capacity: i32) -> Result<(), String> {
+    if end <= start {
+        return Err("End time must be after start time".to_string());
+    }
+
+    if capacity <= 0 {
+        return Err("Capacity must be greater than 0".to_string());
+    }
+
+    Ok(())
+}
+
+/// Why a day can't take pickups, if the business calendar closes it to deliveries.
+pub async fn closure<'e>(
+    executor: impl PgExecutor<'e>,
+    date: NaiveDate,
+) -> Result<Option<String>, sqlx::Error> {
+    let closed = calendar::closures(executor, date, Purpose::Delivery).await?;
+
+    if closed.is_empty() {
+        return Ok(None);
+    }
+
+    let names: Vec<&str> = closed.iter().map(|event| event.name.as_str()).collect();
+
+    Ok(Some(format!(
+        "{} is closed for pickups ({})",
+        date,
+        names.join(", ")
+    )))
+}
+
+/// How many pickups a customer has missed.
+pub async fn no_show_count<'e>(
+    executor: impl PgExecutor<'e>,
+    customer_id: Uuid,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM pickup_bookings
+        WHERE customer_id = $1 AND status = 'no_show'
+        "#,
+        customer_id
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Every order booked for pickup on a day, by window, with what to hand over.
+pub async fn manifest(
+    pool: &PgPool,
+    date: NaiveDate,
+) -> Result<Vec<PickupManifestEntry>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            b.id, b.status, w.start_time, w.end_time, b.sale_id, s.sale_number,
+            b.customer_id, c.name as "customer_name?", c.phone, c.email,
+            s.total_amount, s.payment_status, b.reminded_at, b.notes,
+            (SELECT COUNT(*) FROM pickup_bookings n
+             WHERE n.customer_id = b.customer_id AND n.status = 'no_show'
+                AND n.id <> b.id) AS "no_show_count!"
+        FROM pickup_bookings b
+        JOIN pickup_windows w ON w.id = b.pickup_window_id
+        JOIN sales s ON s.id = b.sale_id
+        LEFT JOIN customers c ON c.id = b.customer_id
+        WHERE w.pickup_date = $1 AND b.status <> 'cancelled'
+        ORDER BY w.start_time, c.name, s.sale_number
+        "#,
+        date
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let sale_ids: Vec<Uuid> = rows.iter().map(|row| row.sale_id).collect();
+
+    let items = sqlx::query!(
+        r#"
+        SELECT si.sale_id, si.inventory_id, i.name, si.quantity, i.unit
+        FROM sale_items si
+        JOIN inventory i ON i.id = si.inventory_id
+        WHERE si.sale_id = ANY($1)
+        ORDER BY i.name
+        "#,
+        &sale_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PickupManifestEntry {
+            items: items
+                .iter()
+                .filter(|item| item.sale_id == row.sale_id)
+                .map(|item| PickupItem {
+                    inventory_id: item.inventory_id,
+                    name: item.name.clone(),
+                    quantity: item.quantity.clone(),
+                    unit: item.unit.clone(),
+                })
+                .collect(),
+            booking_id: row.id,
+            status: row.status,
+            start_time: row.start_time,
+            end_time: row.end_time,
+            sale_id: row.sale_id,
+            sale_number: row.sale_number,
+            customer_id: row.customer_id,
+            customer_name: row.customer_name,
+            phone: row.phone,
+            email: row.email,
+            total_amount: row.total_amount,
+            payment_status: row.payment_status,
+            no_show_count: row.no_show_count,
+            reminded_at: row.reminded_at,
+            notes: row.notes,
+        })
+        .collect())
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MaterialRequirement, PlanAllocation, PlanCapacity};
+use crate::services::recipes;
+
+/// Link a newly started batch to the plan entry it came from. Returns the
+/// reason if the plan can't be started.
+pub async fn mark_started(
+    conn: &mut PgConnection,
+    planned_batch_id: Uuid,
+    batch_id: Uuid,
+) -> Result<Result<(), String>, sqlx::Error> {
+    let status = sqlx::query_scalar!(
+        "SELECT status FROM planned_batches WHERE id = $1 FOR UPDATE",
+        planned_batch_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match status.as_deref() {
+        None => return Ok(Err("Planned batch not found".to_string())),
+        Some("planned") => {}
+        Some(status) => {
+            return Ok(Err(format!(
+                "Cannot start a planned batch with status '{}'",
+                status
+            )));
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE planned_batches
+        SET status = 'started', production_batch_id = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        planned_batch_id,
+        batch_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(()))
+}
+
+/// Ingredient demand of the batches planned between `from` and `to`
+/// (inclusive), netted against available stock and against open purchase
+/// orders expected by `to` (or with no expected date). Shortfalls come first.
+pub async fn requirements(
+    pool: &PgPool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<MaterialRequirement>, sqlx::Error> {
+    let planned = sqlx::query!(
+        r#"
+        SELECT p.recipe_template_id, p.batch_size, p.target_date, r.default_batch_size
+        FROM planned_batches p
+        JOIN recipe_templates r ON r.id = p.recipe_template_id
+        WHERE p.status = 'planned' AND p.target_date BETWEEN $1 AND $2
+        ORDER BY p.target_date
+        "#,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut ingredients_by_recipe = HashMap::new();
+    let mut requirements: Vec<MaterialRequirement> = Vec::new();
+
+    for plan in planned {
+        if let Entry::Vacant(entry) = ingredients_by_recipe.entry(plan.recipe_template_id) {
+            entry.insert(recipes::ingredients(pool, plan.recipe_template_id).await?);
+        }
+
+        let factor = recipes::scale(plan.default_batch_size.as_ref(), &plan.batch_size);
+
+        for ingredient in &ingredients_by_recipe[&plan.recipe_template_id] {
+            let quantity = &ingredient.quantity_per_batch * &factor;
+
+            // Planned batches are in date order, so the first one seen is the earliest
+            match requirements
+                .iter_mut()
+                .find(|r| r.inventory_id == ingredient.inventory_id)
+            {
+                Some(requirement) => {
+                    requirement.required += quantity;
+                    requirement.planned_batches += 1;
+                }
+                None => requirements.push(MaterialRequirement {
+                    inventory_id: ingredient.inventory_id,
+                    item_name: ingredient.ingredient_name.clone(),
+                    unit: ingredient.unit.clone(),
+                    required: quantity,
+                    available: BigDecimal::from(0),
+                    on_order: BigDecimal::from(0),
+                    shortfall: BigDecimal::from(0),
+                    first_needed: plan.target_date,
+                    planned_batches: 1,
+                }),
+            }
+        }
+    }
+
+    let ids: Vec<Uuid> = requirements.iter().map(|r| r.inventory_id).collect();
+    let supply = sqlx::query!(
+        r#"
+        SELECT
+            i.id,
+            GREATEST(i.available_stock, 0) AS "available!",
+            COALESCE((
+                SELECT SUM(GREATEST(p.quantity - p.received_quantity, 0))
+                FROM purchase_order_items p
+                JOIN purchase_orders o ON o.id = p.purchase_order_id
+                WHERE p.inventory_id = i.id
+                    AND o.status = 'open'
+                    AND (o.expected_date IS NULL OR o.expected_date <= $2)
+            ), 0) AS "on_order!"
+        FROM inventory i
+        WHERE i.id = ANY($1)
+        "#,
+        &ids,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let zero = BigDecimal::from(0);
+
+    for requirement in &mut requirements {
+        if let Some(row) = supply.iter().find(|row| row.id == requirement.inventory_id) {
+            requirement.available = row.available.clone();
+            requirement.on_order = row.on_order.clone();
+        }
+
+        requirement.required = requirement
+            .required
+            .with_scale_round(3, bigdecimal::RoundingMode::HalfUp);
+        let covered = &requirement.available + &requirement.on_order;
+        requirement.shortfall = if covered < requirement.required {
+            &requirement.required - &covered
+        } else {
+            zero.clone()
+        };
+    }
+
+    requirements.sort_by(|a, b| {
+        (b.shortfall > zero)
+            .cmp(&(a.shortfall > zero))
+            .then(a.first_needed.cmp(&b.first_needed))
+            .then(a.item_name.cmp(&b.item_name))
+    });
+
+    Ok(requirements)
+}
+
+/// Expected output and allocations of the planned batches that can still take
+/// allocations: those still planned, and started ones whose batch is in
+/// progress. Optionally one plan, or those with target dates in a range.
+pub async fn capacity(
+    conn: &mut PgConnection,
+    planned_batch_id: Option<Uuid>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<PlanCapacity>, sqlx::Error> {
+    let plans = sqlx::query!(
+        r#"
+        SELECT
+            p.id, r.template_name, r.product_inventory_id,
+            i.name AS "product_name?", i.unit AS "product_unit?",
+            p.target_date, p.status, p.batch_size,
+            ROUND(COALESCE(y.yield_percent, 100), 2) AS "yield_percent!",
+            COALESCE(a.allocated, 0) AS "allocated!"
+        FROM planned_batches p
+        JOIN recipe_templates r ON r.id = p.recipe_template_id
+        LEFT JOIN inventory i ON i.id = r.product_inventory_id
+        LEFT JOIN production_batches b ON b.id = p.production_batch_id
+        LEFT JOIN LATERAL (
+            SELECT AVG(pb.yield_percentage) AS yield_percent
+            FROM production_batches pb
+            WHERE pb.recipe_template_id = p.recipe_template_id
+                AND pb.status = 'completed'
+                AND pb.yield_percentage IS NOT NULL
+        ) y ON true
+        LEFT JOIN LATERAL (
+            SELECT SUM(quantity) AS allocated
+            FROM plan_allocations
+            WHERE planned_batch_id = p.id AND status = 'soft'
+        ) a ON true
+        WHERE (p.status = 'planned' OR (p.status = 'started' AND b.status = 'in_progress'))
+            AND ($1::uuid IS NULL OR p.id = $1)
+            AND ($2::date IS NULL OR p.target_date >= $2)
+            AND ($3::date IS NULL OR p.target_date <= $3)
+        ORDER BY p.target_date, r.template_name
+        "#,
+        planned_batch_id,
+        from,
+        to
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let ids: Vec<Uuid> = plans.iter().map(|plan| plan.id).collect();
+    let allocations = sqlx::query_as!(
+        PlanAllocation,
+        r#"
+        SELECT
+            a.id, a.planned_batch_id, a.inventory_id, a.customer_id,
+            c.name AS "customer_name?", a.quantity, a.due_date, a.status,
+            a.reserved_quantity, a.reserved_at, a.notes, a.created_at, a.updated_at
+        FROM plan_allocations a
+        LEFT JOIN customers c ON c.id = a.customer_id
+        WHERE a.planned_batch_id = ANY($1) AND a.status = 'soft'
+        ORDER BY a.due_date NULLS LAST, a.created_at
+        "#,
+        &ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(plans
+        .into_iter()
+        .map(|plan| {
+            let expected_yield = (&plan.batch_size * &plan.yield_percent / BigDecimal::from(100))
+                .with_scale_round(3, bigdecimal::RoundingMode::HalfUp);
+
+            PlanCapacity {
+                planned_batch_id: plan.id,
+                recipe_name: plan.template_name,
+                product_inventory_id: plan.product_inventory_id,
+                product_name: plan.product_name,
+                product_unit: plan.product_unit,
+                target_date: plan.target_date,
+                status: plan.status,
+                batch_size: plan.batch_size,
+                yield_percent: plan.yield_percent,
+                unallocated: &expected_yield - &plan.allocated,
+                expected_yield,
+                allocated: plan.allocated,
+                allocations: allocations
+                    .iter()
+                    .filter(|a| a.planned_batch_id == plan.id)
+                    .cloned()
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+/// Turn the soft allocations of the plan a completed batch was started from
+/// into reserved stock of `product_id`, earliest due first, up to the
+/// batch's yield. Returns how many were reserved and how many of those got
+/// less than promised.
+pub async fn reserve_allocations(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    product_id: Uuid,
+    actual_yield: &BigDecimal,
+    now: DateTime<Utc>,
+) -> Result<(usize, usize), sqlx::Error> {
+    let allocations = sqlx::query!(
+        r#"
+        SELECT a.id, a.quantity
+        FROM plan_allocations a
+        JOIN planned_batches p ON p.id = a.planned_batch_id
+        WHERE p.production_batch_id = $1 AND a.status = 'soft' AND a.inventory_id = $2
+        ORDER BY a.due_date NULLS LAST, a.created_at
+        FOR UPDATE OF a
+        "#,
+        batch_id,
+        product_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let zero = BigDecimal::from(0);
+    let mut remaining = actual_yield.clone();
+    let mut total = zero.clone();
+    let mut short = 0;
+
+    for allocation in &allocations {
+        let reserved = if remaining < allocation.quantity {
+            short += 1;
+            remaining.clone().max(zero.clone())
+        } else {
+            allocation.quantity.clone()
+        };
+        remaining -= &reserved;
+        total += &reserved;
+
+        sqlx::query!(
+            r#"
+            UPDATE plan_allocations
+            SET status = 'reserved', reserved_quantity = $2, reserved_at = $3, updated_at = $3
+            WHERE id = $1
+            "#,
+            allocation.id,
+            reserved,
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    if total > zero {
+        sqlx::query!(
+            "UPDATE inventory SET reserved_stock = reserved_stock + $1, updated_at = $2 WHERE id = $3",
+            total,
+            now,
+            product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok((allocations.len(), short))
+}
+
+/// Cancel the soft allocations of a plan that is itself being cancelled.
+pub async fn cancel_allocations(
+    conn: &mut PgConnection,
+    planned_batch_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let cancelled = sqlx::query!(
+        r#"
+        UPDATE plan_allocations
+        SET status = 'cancelled', updated_at = NOW()
+        WHERE planned_batch_id = $1 AND status = 'soft'
+        "#,
+        planned_batch_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(cancelled.rows_affected())
+}
@@ -0,0 +1,203 @@
+use bigdecimal::BigDecimal;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::models::{CustomerPrice, PriceListItem, ResolvedPrice};
+
+/// Price list for anonymous sales, customers without a type, and customers
+/// whose type has no price for an item.
+pub const RETAIL: &str = "retail";
+
+/// A customer type as price lists store it (trimmed, lower case), matching
+/// `customers.customer_type` however that was typed.
+pub fn customer_type(customer_type: &str) -> Result<String, String> {
+    let customer_type = customer_type.trim().to_lowercase();
+    if customer_type.is_empty() {
+        return Err("Customer type is required".to_string());
+    }
+    if customer_type.len() > 50 {
+        return Err("Customer type must be at most 50 characters".to_string());
+    }
+    Ok(customer_type)
+}
+
+/// The price an item sells for to a customer (None for an anonymous sale):
+/// the customer's own price, else their type's price list, else the retail
+/// list. `unit_price` is None when none of these has the item.
+pub async fn price_for(
+    executor: impl PgExecutor<'_>,
+    customer_id: Option<Uuid>,
+    inventory_id: Uuid,
+) -> Result<ResolvedPrice, sqlx::Error> {
+    let price = sqlx::query!(
+        r#"
+        SELECT unit_price AS "unit_price!", source AS "source!", price_list
+        FROM (
+            SELECT unit_price, 'customer' AS source, NULL::VARCHAR AS price_list, 1 AS rank
+            FROM customer_prices
+            WHERE customer_id = $1 AND inventory_id = $2
+            UNION ALL
+            SELECT p.unit_price, 'price_list', p.customer_type, 2
+            FROM price_list_items p
+            JOIN customers c ON LOWER(TRIM(c.customer_type)) = p.customer_type
+            WHERE c.id = $1 AND p.inventory_id = $2
+            UNION ALL
+            SELECT unit_price, 'price_list', customer_type, 3
+            FROM price_list_items
+            WHERE customer_type = $3 AND inventory_id = $2
+        ) prices
+        ORDER BY rank
+        LIMIT 1
+        "#,
+        customer_id,
+        inventory_id,
+        RETAIL
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(match price {
+        Some(price) => ResolvedPrice {
+            inventory_id,
+            customer_id,
+            unit_price: Some(price.unit_price),
+            source: Some(price.source),
+            price_list: price.price_list,
+        },
+        None => ResolvedPrice {
+            inventory_id,
+            customer_id,
+            unit_price: None,
+            source: None,
+            price_list: None,
+        },
+    })
+}
+
+/// Prices on the price lists, one customer type's or all of them.
+pub async fn price_list(
+    executor: impl PgExecutor<'_>,
+    customer_type: Option<&str>,
+) -> Result<Vec<PriceListItem>, sqlx::Error> {
+    sqlx::query_as!(
+        PriceListItem,
+        r#"
+        SELECT p.id, p.customer_type, p.inventory_id, i.name AS item_name,
+            p.unit_price, p.created_at, p.updated_at
+        FROM price_list_items p
+        JOIN inventory i ON i.id = p.inventory_id
+        WHERE $1::VARCHAR IS NULL OR p.customer_type = $1
+        ORDER BY p.customer_type, i.name
+        "#,
+        customer_type
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Prices agreed with a customer.
+pub async fn customer_prices(
+    executor: impl PgExecutor<'_>,
+    customer_id: Uuid,
+) -> Result<Vec<CustomerPrice>, sqlx::Error> {
+    sqlx::query_as!(
+        CustomerPrice,
+        r#"
+        SELECT p.id, p.customer_id, p.inventory_id, i.name AS item_name,
+            p.unit_price, p.created_at, p.updated_at
+        FROM customer_prices p
+        JOIN inventory i ON i.id = p.inventory_id
+        WHERE p.customer_id = $1
+        ORDER BY i.name
+        "#,
+        customer_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Set an item's price for a customer type, or remove it when `unit_price`
+/// is None. Returns the price as set.
+pub async fn set_list_price(
+    executor: impl PgExecutor<'_>,
+    customer_type: &str,
+    inventory_id: Uuid,
+    unit_price: Option<&BigDecimal>,
+) -> Result<Option<PriceListItem>, sqlx::Error> {
+    let Some(unit_price) = unit_price else {
+        sqlx::query!(
+            "DELETE FROM price_list_items WHERE customer_type = $1 AND inventory_id = $2",
+            customer_type,
+            inventory_id
+        )
+        .execute(executor)
+        .await?;
+        return Ok(None);
+    };
+
+    sqlx::query_as!(
+        PriceListItem,
+        r#"
+        WITH saved AS (
+            INSERT INTO price_list_items (customer_type, inventory_id, unit_price)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (customer_type, inventory_id) DO UPDATE
+                SET unit_price = EXCLUDED.unit_price, updated_at = NOW()
+            RETURNING *
+        )
+        SELECT s.id, s.customer_type, s.inventory_id, i.name AS item_name,
+            s.unit_price, s.created_at, s.updated_at
+        FROM saved s
+        JOIN inventory i ON i.id = s.inventory_id
+        "#,
+        customer_type,
+        inventory_id,
+        unit_price
+    )
+    .fetch_one(executor)
+    .await
+    .map(Some)
+}
+
+/// Set an item's price for one customer, or remove it when `unit_price` is
+/// None. Returns the price as set.
+pub async fn set_customer_price(
+    executor: impl PgExecutor<'_>,
+    customer_id: Uuid,
+    inventory_id: Uuid,
+    unit_price: Option<&BigDecimal>,
+) -> Result<Option<CustomerPrice>, sqlx::Error> {
+    let Some(unit_price) = unit_price else {
+        sqlx::query!(
+            "DELETE FROM customer_prices WHERE customer_id = $1 AND inventory_id = $2",
+            customer_id,
+            inventory_id
+        )
+        .execute(executor)
+        .await?;
+        return Ok(None);
+    };
+
+    sqlx::query_as!(
+        CustomerPrice,
+        r#"
+        WITH saved AS (
+            INSERT INTO customer_prices (customer_id, inventory_id, unit_price)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (customer_id, inventory_id) DO UPDATE
+                SET unit_price = EXCLUDED.unit_price, updated_at = NOW()
+            RETURNING *
+        )
+        SELECT s.id, s.customer_id, s.inventory_id, i.name AS item_name,
+            s.unit_price, s.created_at, s.updated_at
+        FROM saved s
+        JOIN inventory i ON i.id = s.inventory_id
+        "#,
+        customer_id,
+        inventory_id,
+        unit_price
+    )
+    .fetch_one(executor)
+    .await
+    .map(Some)
+}
@@ -0,0 +1,291 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::{MutationError, PurchaseOrderItemInput, Rejection, SupplierContractLine};
+
+/// Next purchase order number for the day (PO-YYYYMMDD-NNN).
+pub async fn next_number(
+    conn: &mut PgConnection,
+    now: DateTime<Utc>,
+) -> Result<String, sqlx::Error> {
+    let date_str = now.format("%Y%m%d").to_string();
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM purchase_orders WHERE po_number LIKE $1"#,
+        format!("PO-{}-%", date_str)
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(format!("PO-{}-{:03}", date_str, count + 1))
+}
+
+/// Whether an order for `total` placed by someone with `role` has to wait for
+/// the owner's approval: it does when the total is over the role's limit, when
+/// the role has no limit, or when a line has no unit cost (`priced` false) so
+/// the total can't be trusted. The owner's own orders never call this.
+pub async fn needs_approval(
+    conn: &mut PgConnection,
+    role: &str,
+    total: &BigDecimal,
+    priced: bool,
+) -> Result<bool, sqlx::Error> {
+    if !priced {
+        return Ok(true);
+    }
+
+    let limit = sqlx::query_scalar!(
+        "SELECT max_amount FROM purchase_approval_limits WHERE role = $1",
+        role
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(limit.is_none_or(|max_amount| *total > max_amount))
+}
+
+/// Count a received quantity against the order's line for the item, if it has
+/// one. Returns whether the order had a line for it.
+pub async fn receive(
+    conn: &mut PgConnection,
+    purchase_order_id: Uuid,
+    inventory_id: Uuid,
+    quantity: &BigDecimal,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE purchase_order_items
+        SET received_quantity = received_quantity + $3
+        WHERE purchase_order_id = $1 AND inventory_id = $2
+        "#,
+        purchase_order_id,
+        inventory_id,
+        quantity
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark an open order received once every line has arrived in full. Returns
+/// whether it was closed.
+pub async fn close_if_received(
+    conn: &mut PgConnection,
+    purchase_order_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE purchase_orders
+        SET status = 'received', updated_at = NOW()
+        WHERE id = $1
+            AND status = 'open'
+            AND NOT EXISTS (
+                SELECT 1 FROM purchase_order_items
+                WHERE purchase_order_id = $1 AND received_quantity < quantity
+            )
+        "#,
+        purchase_order_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Move the inventory items that default to a supplier being removed over to
+/// `reassign_to`. Refused if items still default to it and no reassignment
+/// was given, or if the reassignment target isn't another active supplier.
+/// Returns how many items moved.
+pub async fn reassign_default_items(
+    conn: &mut PgConnection,
+    supplier_id: Uuid,
+    supplier_name: &str,
+    reassign_to: Option<Uuid>,
+) -> Result<Result<u64, Rejection>, sqlx::Error> {
+    if let Some(target) = reassign_to {
+        if target == supplier_id {
+            return Ok(Err("Reassign items to a different supplier"
+                .to_string()
+                .into()));
+        }
+
+        let active = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM suppliers WHERE id = $1 AND is_active) AS \"active!\"",
+            target
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !active {
+            return Ok(Err(Rejection::new(
+                "Supplier to reassign items to not found or is inactive".to_string(),
+                MutationError::not_found("Supplier"),
+            )));
+        }
+    }
+
+    let items = sqlx::query_scalar!(
+        "SELECT name FROM inventory WHERE default_supplier_id = $1 ORDER BY name",
+        supplier_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let Some(target) = reassign_to else {
+        if items.is_empty() {
+            return Ok(Ok(0));
+        }
+
+        let mut names = items.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+        if items.len() > 5 {
+            names.push_str(&format!(" and {} more", items.len() - 5));
+        }
+
+        return Ok(Err(format!(
+            "{} item(s) still default to {} ({}); pass reassignTo to move them",
+            items.len(),
+            supplier_name,
+            names
+        )
+        .into()));
+    };
+
+    let moved = sqlx::query!(
+        r#"
+        UPDATE inventory
+        SET default_supplier_id = $2, updated_at = NOW()
+        WHERE default_supplier_id = $1
+        "#,
+        supplier_id,
+        target
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Ok(moved.rows_affected()))
+}
+
+/// Each item on a contract with how much has been ordered against it. Lines
+/// on cancelled or rejected orders count only what was received.
+pub async fn contract_lines(
+    conn: &mut PgConnection,
+    contract_id: Uuid,
+) -> Result<Vec<SupplierContractLine>, sqlx::Error> {
+    sqlx::query_as!(
+        SupplierContractLine,
+        r#"
+        SELECT
+            ci.id, ci.inventory_id, i.name AS item_name, i.unit,
+            ci.committed_quantity, ci.agreed_price,
+            drawn.ordered AS "ordered_quantity!",
+            drawn.received AS "received_quantity!",
+            GREATEST(ci.committed_quantity - drawn.ordered, 0) AS "remaining_quantity!",
+            GREATEST(ci.committed_quantity - drawn.ordered, 0) * ci.agreed_price AS "remaining_value!"
+        FROM supplier_contract_items ci
+        JOIN inventory i ON i.id = ci.inventory_id
+        CROSS JOIN LATERAL (
+            SELECT
+                COALESCE(SUM(CASE WHEN po.status IN ('cancelled', 'rejected')
+                    THEN poi.received_quantity ELSE poi.quantity END), 0) AS ordered,
+                COALESCE(SUM(poi.received_quantity), 0) AS received
+            FROM purchase_orders po
+            JOIN purchase_order_items poi ON poi.purchase_order_id = po.id
+            WHERE po.contract_id = ci.contract_id AND poi.inventory_id = ci.inventory_id
+        ) drawn
+        WHERE ci.contract_id = $1
+        ORDER BY i.name
+        "#,
+        contract_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+/// Check an order against the contract it draws down, and price its lines
+/// for contract items that have no unit cost. The contract must be active,
+/// with the same supplier, and protecting prices on `today`. Contract lines
+/// can't cost more than the agreed price or go past what is left of the
+/// commitment; items not on the contract are ordered as usual. The contract
+/// is locked until the order commits, so two orders can't both take the rest.
+pub async fn draw_down_contract(
+    conn: &mut PgConnection,
+    contract_id: Uuid,
+    supplier_id: Uuid,
+    today: NaiveDate,
+    items: &mut [PurchaseOrderItemInput],
+) -> Result<Result<(), Rejection>, sqlx::Error> {
+    let contract = sqlx::query!(
+        r#"
+        SELECT contract_number, supplier_id, status, start_date, end_date
+        FROM supplier_contracts
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        contract_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(contract) = contract else {
+        return Ok(Err(Rejection::new(
+            "Supplier contract not found".to_string(),
+            MutationError::not_found("Supplier contract"),
+        )));
+    };
+    if contract.supplier_id != supplier_id {
+        return Ok(Err(format!(
+            "Contract {} is with a different supplier",
+            contract.contract_number
+        )
+        .into()));
+    }
+    if contract.status != "active" {
+        return Ok(Err(format!(
+            "Contract {} is {}",
+            contract.contract_number, contract.status
+        )
+        .into()));
+    }
+    if today < contract.start_date || today > contract.end_date {
+        return Ok(Err(format!(
+            "Contract {} only covers orders from {} to {}",
+            contract.contract_number, contract.start_date, contract.end_date
+        )
+        .into()));
+    }
+
+    let lines = contract_lines(conn, contract_id).await?;
+    for item in items.iter_mut() {
+        let Some(line) = lines
+            .iter()
+            .find(|line| line.inventory_id == item.inventory_id)
+        else {
+            continue;
+        };
+
+        match &item.unit_cost {
+            Some(cost) if *cost > line.agreed_price => {
+                return Ok(Err(format!(
+                    "{} is ${} a unit on contract {}, not ${}",
+                    line.item_name, line.agreed_price, contract.contract_number, cost
+                )
+                .into()));
+            }
+            Some(_) => {}
+            None => item.unit_cost = Some(line.agreed_price.clone()),
+        }
+
+        if item.quantity > line.remaining_quantity {
+            return Ok(Err(format!(
+                "Contract {} has {} {} of {} left to order",
+                contract.contract_number, line.remaining_quantity, line.unit, line.item_name
+            )
+            .into()));
+        }
+    }
+
+    Ok(Ok(()))
+}
@@ -0,0 +1,425 @@
+// QR codes for internal labels: byte mode, error correction level M,
+// versions 1-6 (21x21 to 41x41 modules). That covers up to 106 bytes, plenty
+// for lot codes, and avoids the version information blocks of version 7+.
+
+/// Data codewords per block, number of blocks, and error correction
+/// codewords per block for versions 1-6 at level M.
+const VERSIONS: [(usize, usize, usize); 6] = [
+    (16, 1, 10),
+    (28, 1, 16),
+    (44, 1, 26),
+    (32, 2, 18),
+    (43, 2, 24),
+    (27, 4, 16),
+];
+
+/// Modules of white border around the symbol, as the spec requires.
+const QUIET_ZONE: usize = 4;
+
+/// An encoded symbol: `size` x `size` modules, `true` for dark.
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode text, or `None` if it is longer than 106 bytes.
+    pub fn encode(text: &str) -> Option<QrCode> {
+        let bytes = text.as_bytes();
+        let version = (1..=VERSIONS.len()).find(|&version| {
+            let (data, blocks, _) = VERSIONS[version - 1];
+            4 + 8 + bytes.len() * 8 <= data * blocks * 8
+        })?;
+
+        let codewords = codewords(version, bytes);
+        let mut qr = Canvas::new(version);
+        qr.draw_function_patterns();
+        qr.draw_data(&codewords);
+
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut candidate = qr.clone();
+                candidate.apply_mask(mask);
+                candidate.draw_format(mask);
+                candidate.penalty()
+            })
+            .unwrap_or(0);
+        qr.apply_mask(mask);
+        qr.draw_format(mask);
+
+        Some(QrCode {
+            size: qr.size,
+            modules: qr.modules,
+        })
+    }
+
+    /// The symbol as a standalone SVG, one unit per module, quiet zone
+    /// included. Scale it with CSS or the `width`/`height` of an `<img>`.
+    pub fn svg(&self) -> String {
+        let full = self.size + QUIET_ZONE * 2;
+        let mut path = String::new();
+
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.modules[y * self.size + x] {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE));
+                }
+            }
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {full} {full}\" shape-rendering=\"crispEdges\">\
+             <rect width=\"{full}\" height=\"{full}\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>"
+        )
+    }
+
+    /// The symbol as PDF drawing operators: a `width`-point square (quiet
+    /// zone included) with its lower-left corner at (`left`, `bottom`).
+    pub fn pdf(&self, left: f64, bottom: f64, width: f64) -> String {
+        let module = width / (self.size + QUIET_ZONE * 2) as f64;
+        let mut ops = String::from("0 g\n");
+
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.modules[y * self.size + x] {
+                    ops.push_str(&format!(
+                        "{:.2} {:.2} {:.2} {:.2} re\n",
+                        left + (x + QUIET_ZONE) as f64 * module,
+                        bottom + width - (y + QUIET_ZONE + 1) as f64 * module,
+                        module,
+                        module
+                    ));
+                }
+            }
+        }
+
+        ops.push_str("f\n");
+        ops
+    }
+}
+
+/// Data and error correction codewords in placement order.
+fn codewords(version: usize, bytes: &[u8]) -> Vec<u8> {
+    let (data_per_block, blocks, ec_per_block) = VERSIONS[version - 1];
+    let capacity = data_per_block * blocks * 8;
+
+    // Byte mode indicator, 8-bit character count, then the bytes
+    let mut bits = Vec::with_capacity(capacity);
+    push_bits(&mut bits, 0b0100, 4);
+    push_bits(&mut bits, bytes.len() as u32, 8);
+    for &byte in bytes {
+        push_bits(&mut bits, u32::from(byte), 8);
+    }
+
+    // Terminator, then pad to a whole byte and fill with the pad codewords
+    let terminator = (capacity - bits.len()).min(4);
+    push_bits(&mut bits, 0, terminator);
+    let to_byte = (8 - bits.len() % 8) % 8;
+    push_bits(&mut bits, 0, to_byte);
+
+    let mut data: Vec<u8> = bits
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0u8, |byte, &bit| (byte << 1) | u8::from(bit))
+        })
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if data.len() >= data_per_block * blocks {
+            break;
+        }
+        data.push(pad);
+    }
+
+    let generator = rs_generator(ec_per_block);
+    let data_blocks: Vec<&[u8]> = data.chunks(data_per_block).collect();
+    let ec_blocks: Vec<Vec<u8>> = data_blocks
+        .iter()
+        .map(|block| rs_remainder(block, &generator))
+        .collect();
+
+    // Interleave: the first codeword of every block, then the second, ...
+    let mut out = Vec::with_capacity((data_per_block + ec_per_block) * blocks);
+    for i in 0..data_per_block {
+        out.extend(data_blocks.iter().map(|block| block[i]));
+    }
+    for i in 0..ec_per_block {
+        out.extend(ec_blocks.iter().map(|block| block[i]));
+    }
+    out
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: usize) {
+    for i in (0..count).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+/// Multiply in GF(256) with the QR polynomial x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut a = u16::from(a);
+    let mut b = b;
+    let mut product = 0u16;
+
+    while b > 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        a <<= 1;
+        if a & 0x100 != 0 {
+            a ^= 0x11D;
+        }
+        b >>= 1;
+    }
+
+    product as u8
+}
+
+/// Reed-Solomon generator polynomial of the given degree, highest power first.
+fn rs_generator(degree: usize) -> Vec<u8> {
+    let mut generator = vec![1u8];
+    let mut root = 1u8;
+
+    for _ in 0..degree {
+        let mut next = vec![0u8; generator.len() + 1];
+        for (i, &coefficient) in generator.iter().enumerate() {
+            next[i] ^= coefficient;
+            next[i + 1] ^= gf_mul(coefficient, root);
+        }
+        generator = next;
+        root = gf_mul(root, 2);
+    }
+
+    generator
+}
+
+/// Error correction codewords for one block.
+fn rs_remainder(data: &[u8], generator: &[u8]) -> Vec<u8> {
+    let degree = generator.len() - 1;
+    let mut remainder = vec![0u8; degree];
+
+    for &byte in data {
+        let factor = byte ^ remainder[0];
+        remainder.remove(0);
+        remainder.push(0);
+        for (slot, &coefficient) in remainder.iter_mut().zip(&generator[1..]) {
+            *slot ^= gf_mul(coefficient, factor);
+        }
+    }
+
+    remainder
+}
+
+/// A symbol being drawn. `function` marks finder, timing, alignment and
+/// format modules, which data and masks leave alone.
+#[derive(Clone)]
+struct Canvas {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Canvas {
+    fn new(version: usize) -> Self {
+        let size = 17 + 4 * version;
+        Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+
+        // Timing patterns
+        for i in 0..size {
+            self.set(6, i, i % 2 == 0);
+            self.set(i, 6, i % 2 == 0);
+        }
+
+        // Finder patterns with their separators
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if x < 0 || y < 0 || x >= size as i32 || y >= size as i32 {
+                        continue;
+                    }
+                    let distance = dx.abs().max(dy.abs());
+                    self.set(x as usize, y as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+
+        // Versions 2-6 have a single alignment pattern, bottom right
+        if self.version >= 2 {
+            let center = size - 7;
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    let (x, y) = (center as i32 + dx, center as i32 + dy);
+                    self.set(x as usize, y as usize, dx.abs().max(dy.abs()) != 1);
+                }
+            }
+        }
+
+        // Reserve the format areas (drawn once the mask is chosen)
+        self.draw_format(0);
+    }
+
+    /// Place codewords in the two-column zigzag from the bottom right.
+    fn draw_data(&mut self, codewords: &[u8]) {
+        let size = self.size as i32;
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for column in 0..2 {
+                    let x = (right - column) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        size - 1 - vertical
+                    } else {
+                        vertical
+                    } as usize;
+
+                    if !self.function[y * self.size + x] && i < total_bits {
+                        self.modules[y * self.size + x] =
+                            (codewords[i / 8] >> (7 - i % 8)) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                if invert && !self.function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// Both copies of the 15-bit format information (level M, this mask),
+    /// plus the dark module.
+    fn draw_format(&mut self, mask: u8) {
+        let data = u32::from(mask); // Level M is 00
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set(8, i, bit(i));
+        }
+        self.set(8, 7, bit(6));
+        self.set(8, 8, bit(7));
+        self.set(7, 8, bit(8));
+        for i in 9..15 {
+            self.set(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set(8, size - 15 + i, bit(i));
+        }
+        self.set(8, size - 8, true);
+    }
+
+    /// The spec's mask penalty: long runs, 2x2 blocks, finder-like patterns
+    /// and an uneven dark/light balance all score against a mask.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+
+        let lines = (0..size).flat_map(|a| {
+            [
+                (0..size).map(|b| self.get(b, a)).collect::<Vec<_>>(),
+                (0..size).map(|b| self.get(a, b)).collect::<Vec<_>>(),
+            ]
+        });
+        const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+
+        for line in lines {
+            let mut run = 1;
+            for i in 1..=line.len() {
+                if i < line.len() && line[i] == line[i - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+
+            for start in 0..=line.len() - FINDER.len() {
+                if line[start..start + FINDER.len()] != FINDER {
+                    continue;
+                }
+                let light = |from: usize, to: usize| (from..to).all(|i| !line[i]);
+                let before = start >= 4 && light(start - 4, start);
+                let after = start + 11 <= line.len() && light(start + 7, start + 11);
+                if before || after {
+                    penalty += 40;
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.get(x, y);
+                if self.get(x + 1, y) == color
+                    && self.get(x, y + 1) == color
+                    && self.get(x + 1, y + 1) == color
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let dark = self.modules.iter().filter(|&&dark| dark).count();
+        let total = size * size;
+        let percent = dark * 100 / total;
+        penalty += percent.abs_diff(50) / 5 * 10;
+
+        penalty
+    }
+}
@@ -0,0 +1,252 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextPrepareRequest,
+};
+use async_graphql::parser::types::{ExecutableDocument, OperationType};
+use async_graphql::{
+    ErrorExtensionValues, Request, Response, ServerError, ServerResult, Variables,
+};
+use axum::http::HeaderMap;
+
+use crate::services::auth::Identity;
+use crate::services::config::Config;
+
+/// Error code on operations turned away for going over budget.
+pub const RATE_LIMITED: &str = "RATE_LIMITED";
+
+/// Buckets kept at most; full (idle) ones are dropped first.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Address a request came from, added to the GraphQL request's data.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub IpAddr);
+
+/// Per-client budgets for queries and mutations
+/// (`RATE_LIMIT_QUERIES_PER_MINUTE`, `RATE_LIMIT_MUTATIONS_PER_MINUTE`),
+/// kept as token buckets in memory. A client is its API key, else its user,
+/// else its address, so staff sharing the shop's address don't share a
+/// budget once signed in. A client can spend a minute's budget at once and
+/// it refills steadily.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<(String, bool), Bucket>>>,
+    queries_per_minute: Option<u32>,
+    mutations_per_minute: Option<u32>,
+    trust_proxy_headers: bool,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// The budget it refills to, at that many a minute
+    per_minute: f64,
+}
+
+impl Bucket {
+    /// Tokens it holds at `now`, refilled since it was last spent from.
+    fn level(&self, now: Instant) -> f64 {
+        let refilled = now.duration_since(self.updated).as_secs_f64() * self.per_minute / 60.0;
+        (self.tokens + refilled).min(self.per_minute)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            buckets: Arc::default(),
+            queries_per_minute: config.queries_per_minute,
+            mutations_per_minute: config.mutations_per_minute,
+            trust_proxy_headers: config.trust_proxy_headers,
+        }
+    }
+
+    /// The connecting address, or with `TRUST_PROXY_HEADERS` the first
+    /// address in `X-Forwarded-For`.
+    pub fn client_addr(&self, peer: IpAddr, headers: &HeaderMap) -> ClientAddr {
+        let forwarded = self
+            .trust_proxy_headers
+            .then(|| headers.get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|first| first.trim().parse().ok());
+
+        ClientAddr(forwarded.unwrap_or(peer))
+    }
+
+    /// Spend one operation from the client's budget, or return how many
+    /// seconds until one is available.
+    fn take(&self, client: &str, mutation: bool) -> Result<(), u64> {
+        let per_minute = if mutation {
+            self.mutations_per_minute
+        } else {
+            self.queries_per_minute
+        };
+        let Some(per_minute) = per_minute.map(f64::from) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+
+        // Make room before adding a client: a full bucket is the same as
+        // none, and past that the one idle longest goes
+        let key = (client.to_string(), mutation);
+        if !buckets.contains_key(&key) && buckets.len() >= MAX_BUCKETS {
+            buckets.retain(|_, bucket| bucket.level(now) < bucket.per_minute);
+            if buckets.len() >= MAX_BUCKETS {
+                let idlest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(key, _)| key.clone());
+                if let Some(idlest) = idlest {
+                    buckets.remove(&idlest);
+                }
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: per_minute,
+            updated: now,
+            per_minute,
+        });
+        bucket.tokens = bucket.level(now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) * 60.0 / per_minute).ceil() as u64)
+        }
+    }
+}
+
+/// Seconds to wait before retrying, when the response was turned away by
+/// `RateLimit`.
+pub fn retry_after(response: &Response) -> Option<u64> {
+    response.errors.iter().find_map(|error| {
+        let extensions = error.extensions.as_ref()?;
+        if extensions.get("code") != Some(&async_graphql::Value::from(RATE_LIMITED)) {
+            return None;
+        }
+        match extensions.get("retryAfter") {
+            Some(async_graphql::Value::Number(secs)) => secs.as_u64(),
+            _ => Some(1),
+        }
+    })
+}
+
+/// Schema extension that charges each query and mutation to its client's
+/// `RateLimiter` budget, rejecting it before it runs when the budget is
+/// spent. The error has code `RATE_LIMITED` and `retryAfter` (seconds);
+/// the HTTP handler answers it with 429 and `Retry-After`.
+pub struct RateLimit;
+
+impl ExtensionFactory for RateLimit {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RateLimitExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct RateLimitExtension {
+    client: Mutex<Option<String>>,
+    operation_name: Mutex<Option<String>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RateLimitExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if let Ok(mut name) = self.operation_name.lock() {
+            name.clone_from(&request.operation_name);
+        }
+        // Request data isn't in the context until execution starts
+        let identity = request
+            .data
+            .get(&TypeId::of::<Identity>())
+            .and_then(|data| data.downcast_ref::<Identity>());
+        let addr = request
+            .data
+            .get(&TypeId::of::<ClientAddr>())
+            .and_then(|data| data.downcast_ref::<ClientAddr>());
+        if let Ok(mut client) = self.client.lock() {
+            *client = match (identity, addr) {
+                (Some(identity), _) => Some(match identity.api_key_id {
+                    Some(key) => format!("key:{}", key),
+                    None => format!("user:{}", identity.user_id),
+                }),
+                (None, Some(ClientAddr(addr))) => Some(format!("ip:{}", addr)),
+                (None, None) => None,
+            };
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let (Some(limiter), Some(client)) = (
+            ctx.data_opt::<RateLimiter>(),
+            self.client.lock().ok().and_then(|client| client.clone()),
+        ) else {
+            return Ok(document);
+        };
+        let operation_name = self
+            .operation_name
+            .lock()
+            .ok()
+            .and_then(|name| name.clone());
+        let operation_type = document
+            .operations
+            .iter()
+            .find(|(name, _)| {
+                operation_name.is_none() || name.map(|n| n.as_str()) == operation_name.as_deref()
+            })
+            .map(|(_, operation)| operation.node.ty);
+
+        let mutation = match operation_type {
+            Some(OperationType::Query) => false,
+            Some(OperationType::Mutation) => true,
+            // Subscriptions hold one connection; unknown operations fail validation
+            _ => return Ok(document),
+        };
+
+        if let Err(retry_after) = limiter.take(&client, mutation) {
+            tracing::warn!("🚦 Rate limited {} ({}s)", client, retry_after);
+            let mut extensions = ErrorExtensionValues::default();
+            extensions.set("code", RATE_LIMITED);
+            extensions.set("retryAfter", retry_after);
+            let mut error = ServerError::new(
+                format!(
+                    "Too many {}; try again in {}s",
+                    if mutation { "mutations" } else { "queries" },
+                    retry_after
+                ),
+                None,
+            );
+            error.extensions = Some(extensions);
+            return Err(error);
+        }
+
+        Ok(document)
+    }
+}
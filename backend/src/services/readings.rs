@@ -0,0 +1,261 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{BatchAlert, BatchReading};
+
+/// Fermentation temperature range (°F) used when a batch's recipe doesn't set one.
+const DEFAULT_MIN_TEMPERATURE: i32 = 55;
+const DEFAULT_MAX_TEMPERATURE: i32 = 80;
+
+/// pH checkpoint used when a batch's recipe has no target pH curve: a ferment
+/// should be safely acidified (pH 4.6 or below) within a week.
+const DEFAULT_PH_CHECKPOINT: (i32, &str) = (7, "4.6");
+
+/// One point on a recipe's target pH curve.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhCheckpoint {
+    /// Days after the batch start date
+    pub day: i32,
+    /// pH the batch should be at or below by that day
+    pub max_ph: BigDecimal,
+}
+
+/// Parse and validate a recipe's `target_ph_curve` JSON.
+pub fn parse_ph_curve(curve: &serde_json::Value) -> Result<Vec<PhCheckpoint>, String> {
+    let mut points: Vec<PhCheckpoint> = serde_json::from_value(curve.clone()).map_err(|_| {
+        "Target pH curve must be an array of {\"day\": number, \"max_ph\": number}".to_string()
+    })?;
+
+    if points.iter().any(|p| p.day < 0) {
+        return Err("Target pH curve days cannot be negative".to_string());
+    }
+
+    if points
+        .iter()
+        .any(|p| p.max_ph < BigDecimal::from(0) || p.max_ph > BigDecimal::from(14))
+    {
+        return Err("Target pH values must be between 0 and 14".to_string());
+    }
+
+    points.sort_by_key(|p| p.day);
+    Ok(points)
+}
+
+/// A reading to be recorded, from the GraphQL mutation or the sensor ingest endpoint.
+pub struct NewReading {
+    pub recorded_at: Option<DateTime<Utc>>,
+    pub ph: Option<BigDecimal>,
+    pub temperature: Option<BigDecimal>,
+    pub salinity_percent: Option<BigDecimal>,
+    pub brix: Option<BigDecimal>,
+    pub observations: Option<String>,
+    /// 'manual' or 'sensor'
+    pub source: &'static str,
+    /// Name of the probe that sent the reading (sensor readings only)
+    pub device_name: Option<String>,
+}
+
+/// Check a reading for missing or out-of-range values.
+pub fn validate(reading: &NewReading) -> Result<(), String> {
+    let zero = BigDecimal::from(0);
+
+    if reading.ph.is_none()
+        && reading.temperature.is_none()
+        && reading.salinity_percent.is_none()
+        && reading.brix.is_none()
+        && reading
+            .observations
+            .as_deref()
+            .is_none_or(|o| o.trim().is_empty())
+    {
+        return Err("Reading must include at least one measurement or observation".to_string());
+    }
+
+    if reading
+        .ph
+        .as_ref()
+        .is_some_and(|ph| *ph < zero || *ph > BigDecimal::from(14))
+    {
+        return Err("pH must be between 0 and 14".to_string());
+    }
+
+    if reading
+        .salinity_percent
+        .as_ref()
+        .is_some_and(|s| *s < zero || *s > BigDecimal::from(100))
+    {
+        return Err("Salinity must be between 0 and 100 percent".to_string());
+    }
+
+    if reading.brix.as_ref().is_some_and(|b| *b < zero) {
+        return Err("Brix cannot be negative".to_string());
+    }
+
+    Ok(())
+}
+
+/// Insert a validated reading for a batch.
+pub async fn insert(
+    pool: &PgPool,
+    batch_id: Uuid,
+    reading: NewReading,
+) -> Result<BatchReading, sqlx::Error> {
+    sqlx::query_as!(
+        BatchReading,
+        r#"
+        INSERT INTO batch_readings (
+            batch_id, recorded_at, ph, temperature, salinity_percent, brix,
+            observations, source, device_name
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING
+            id, batch_id, recorded_at, ph, temperature, salinity_percent,
+            brix, observations, source, device_name, created_at
+        "#,
+        batch_id,
+        reading.recorded_at.unwrap_or_else(Utc::now),
+        reading.ph,
+        reading.temperature,
+        reading.salinity_percent,
+        reading.brix,
+        reading.observations,
+        reading.source,
+        reading.device_name
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Check a recipe's temperature range and pH curve before saving it.
+pub fn validate_thresholds(
+    min_temperature: Option<&BigDecimal>,
+    max_temperature: Option<&BigDecimal>,
+    target_ph_curve: Option<&serde_json::Value>,
+) -> Result<(), String> {
+    if let (Some(min), Some(max)) = (min_temperature, max_temperature)
+        && min > max
+    {
+        return Err("Minimum temperature cannot be above maximum temperature".to_string());
+    }
+
+    if let Some(curve) = target_ph_curve {
+        parse_ph_curve(curve)?;
+    }
+
+    Ok(())
+}
+
+/// Compare a reading against its batch recipe's thresholds and persist an
+/// alert for every limit it crosses.
+///
+/// Recipes without thresholds fall back to the default temperature range and
+/// pH checkpoint.
+pub async fn evaluate(
+    pool: &PgPool,
+    reading: &BatchReading,
+) -> Result<Vec<BatchAlert>, sqlx::Error> {
+    let batch = sqlx::query!(
+        r#"
+        SELECT pb.batch_number, pb.start_date, rt.min_temperature, rt.max_temperature, rt.target_ph_curve
+        FROM production_batches pb
+        LEFT JOIN recipe_templates rt ON rt.id = pb.recipe_template_id
+        WHERE pb.id = $1
+        "#,
+        reading.batch_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // (alert_type, message, measured_value, threshold)
+    let mut violations = Vec::new();
+
+    if let Some(ref temperature) = reading.temperature {
+        let min = batch
+            .min_temperature
+            .unwrap_or_else(|| BigDecimal::from(DEFAULT_MIN_TEMPERATURE));
+        let max = batch
+            .max_temperature
+            .unwrap_or_else(|| BigDecimal::from(DEFAULT_MAX_TEMPERATURE));
+
+        if *temperature < min {
+            violations.push((
+                "temperature_low",
+                format!("temperature {}°F is below {}°F", temperature, min),
+                temperature.clone(),
+                min,
+            ));
+        } else if *temperature > max {
+            violations.push((
+                "temperature_high",
+                format!("temperature {}°F is above {}°F", temperature, max),
+                temperature.clone(),
+                max,
+            ));
+        }
+    }
+
+    if let Some(ref ph) = reading.ph {
+        let days_fermenting = (reading.recorded_at - batch.start_date).num_days();
+
+        let curve = batch
+            .target_ph_curve
+            .as_ref()
+            .and_then(|curve| parse_ph_curve(curve).ok())
+            .filter(|points| !points.is_empty())
+            .unwrap_or_else(|| {
+                vec![PhCheckpoint {
+                    day: DEFAULT_PH_CHECKPOINT.0,
+                    max_ph: DEFAULT_PH_CHECKPOINT.1.parse().expect("valid pH"),
+                }]
+            });
+
+        // Latest checkpoint the batch has already reached
+        let checkpoint = curve
+            .iter()
+            .rev()
+            .find(|point| i64::from(point.day) <= days_fermenting);
+
+        if let Some(checkpoint) = checkpoint
+            && *ph > checkpoint.max_ph
+        {
+            violations.push((
+                "ph_high",
+                format!(
+                    "pH {} is above the day-{} target of {} after {} days",
+                    ph, checkpoint.day, checkpoint.max_ph, days_fermenting
+                ),
+                ph.clone(),
+                checkpoint.max_ph.clone(),
+            ));
+        }
+    }
+
+    let mut alerts = Vec::with_capacity(violations.len());
+
+    for (alert_type, message, measured_value, threshold) in violations {
+        let alert = sqlx::query_as!(
+            BatchAlert,
+            r#"
+            INSERT INTO batch_alerts (batch_id, reading_id, alert_type, message, measured_value, threshold)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id, batch_id, reading_id, alert_type, message, measured_value,
+                threshold, acknowledged_at, created_at
+            "#,
+            reading.batch_id,
+            reading.id,
+            alert_type,
+            format!("Batch {}: {}", batch.batch_number, message),
+            measured_value,
+            threshold
+        )
+        .fetch_one(pool)
+        .await?;
+
+        alerts.push(alert);
+    }
+
+    Ok(alerts)
+}
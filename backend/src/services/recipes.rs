@@ -0,0 +1,400 @@
+use bigdecimal::BigDecimal;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{RecipeCost, RecipeCostLine, RecipeIngredient, RecipeIngredientInput};
+use crate::services::overhead;
+
+/// Read ingredients from a legacy JSONB ingredient template, either
+/// `{"ingredients": [...]}` or a bare array of
+/// `{"inventory_id", "quantity_per_batch", "unit"}` objects.
+pub fn parse_legacy(template: &Value) -> Result<Vec<RecipeIngredientInput>, String> {
+    let entries = match template {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("ingredients") {
+            Some(Value::Array(entries)) => entries,
+            None => return Ok(vec![]),
+            Some(_) => return Err("ingredient_template.ingredients must be an array".to_string()),
+        },
+        Value::Null => return Ok(vec![]),
+        _ => return Err("ingredient_template must be an object or array".to_string()),
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let inventory_id = entry
+                .get("inventory_id")
+                .and_then(Value::as_str)
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .ok_or_else(|| format!("Ingredient {} has no valid inventory_id", index + 1))?;
+
+            let quantity_per_batch = entry
+                .get("quantity_per_batch")
+                .filter(|quantity| quantity.is_number())
+                .and_then(|quantity| quantity.to_string().parse::<BigDecimal>().ok())
+                .ok_or_else(|| {
+                    format!("Ingredient {} has no numeric quantity_per_batch", index + 1)
+                })?;
+
+            Ok(RecipeIngredientInput {
+                inventory_id,
+                quantity_per_batch,
+                unit: entry
+                    .get("unit")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// A recipe's ingredients for one default-size batch, in listing order.
+/// Recipes saved before ingredients had their own table are read from the
+/// legacy JSONB (either `{"ingredients": [...]}` or a bare array).
+pub async fn ingredients(
+    pool: &PgPool,
+    recipe_template_id: Uuid,
+) -> Result<Vec<RecipeIngredient>, sqlx::Error> {
+    let ingredients = sqlx::query_as!(
+        RecipeIngredient,
+        r#"
+        SELECT r.inventory_id, i.name AS ingredient_name, r.quantity_per_batch, r.unit
+        FROM recipe_ingredients r
+        JOIN inventory i ON i.id = r.inventory_id
+        WHERE r.recipe_template_id = $1
+        ORDER BY r.position
+        "#,
+        recipe_template_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !ingredients.is_empty() {
+        return Ok(ingredients);
+    }
+
+    sqlx::query_as!(
+        RecipeIngredient,
+        r#"
+        SELECT
+            i.id AS inventory_id, i.name AS ingredient_name,
+            (e.value->>'quantity_per_batch')::DECIMAL AS "quantity_per_batch!",
+            COALESCE(e.value->>'unit', i.unit) AS "unit!"
+        FROM recipe_templates r
+        CROSS JOIN LATERAL jsonb_array_elements(
+            CASE jsonb_typeof(r.ingredient_template)
+                WHEN 'array' THEN r.ingredient_template
+                WHEN 'object' THEN COALESCE(r.ingredient_template->'ingredients', '[]'::jsonb)
+                ELSE '[]'::jsonb
+            END
+        ) WITH ORDINALITY AS e(value, ordinality)
+        JOIN inventory i ON i.id::text = e.value->>'inventory_id'
+        WHERE r.id = $1
+            AND (e.value->>'quantity_per_batch') ~ '^[0-9]*\.?[0-9]+$'
+        ORDER BY e.ordinality
+        "#,
+        recipe_template_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// How much to multiply a recipe's per-batch quantities by for a batch of
+/// `batch_size`. Recipes without a default batch size are taken as written.
+pub fn scale(default_batch_size: Option<&BigDecimal>, batch_size: &BigDecimal) -> BigDecimal {
+    match default_batch_size {
+        Some(default) if *default > BigDecimal::from(0) => batch_size / default,
+        _ => BigDecimal::from(1),
+    }
+}
+
+/// Price a recipe's ingredients for a batch (default: the recipe's default
+/// batch size) at 'current' (latest purchase) or 'average' (open lots) costs,
+/// add each active overhead pool's share, and compare the full cost per unit
+/// against a sale price, falling back to the product's average price over the
+/// last 90 days of sales. Returns the reason if the recipe can't be costed.
+pub async fn cost(
+    pool: &PgPool,
+    recipe_id: Uuid,
+    batch_size: Option<BigDecimal>,
+    cost_basis: &str,
+    sale_price: Option<BigDecimal>,
+) -> Result<Result<RecipeCost, String>, sqlx::Error> {
+    let zero = BigDecimal::from(0);
+
+    if cost_basis != "current" && cost_basis != "average" {
+        return Ok(Err(
+            "Cost basis must be one of: current, average".to_string()
+        ));
+    }
+
+    let recipe = sqlx::query!(
+        r#"
+        SELECT template_name, product_inventory_id, default_batch_size, default_unit
+        FROM recipe_templates
+        WHERE id = $1
+        "#,
+        recipe_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(recipe) = recipe else {
+        return Ok(Err("Recipe template not found".to_string()));
+    };
+
+    let Some(batch_size) = batch_size.or(recipe.default_batch_size.clone()) else {
+        return Ok(Err(
+            "Batch size is required for recipes without a default batch size".to_string(),
+        ));
+    };
+    if batch_size <= zero {
+        return Ok(Err("Batch size must be greater than 0".to_string()));
+    }
+
+    let factor = scale(recipe.default_batch_size.as_ref(), &batch_size);
+
+    let mut lines = Vec::new();
+    let mut uncosted = Vec::new();
+    let mut total_cost = zero.clone();
+
+    for ingredient in ingredients(pool, recipe_id).await? {
+        let costs = sqlx::query!(
+            r#"
+            SELECT
+                i.cost_per_unit,
+                (SELECT SUM(l.remaining_quantity * l.unit_cost) / NULLIF(SUM(l.remaining_quantity), 0)
+                 FROM inventory_lots l
+                 WHERE l.inventory_id = i.id AND l.remaining_quantity > 0
+                     AND l.unit_cost IS NOT NULL) AS average_cost
+            FROM inventory i
+            WHERE i.id = $1
+            "#,
+            ingredient.inventory_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let unit_cost = match cost_basis {
+            "average" => costs.average_cost.or(costs.cost_per_unit),
+            _ => costs.cost_per_unit,
+        }
+        .map(|cost| cost.with_scale_round(6, bigdecimal::RoundingMode::HalfUp));
+
+        let quantity = (&ingredient.quantity_per_batch * &factor)
+            .with_scale_round(3, bigdecimal::RoundingMode::HalfUp);
+        let line_cost = unit_cost
+            .as_ref()
+            .map(|cost| (&quantity * cost).with_scale_round(2, bigdecimal::RoundingMode::HalfUp));
+
+        match &line_cost {
+            Some(cost) => total_cost += cost,
+            None => uncosted.push(ingredient.ingredient_name.clone()),
+        }
+
+        lines.push(RecipeCostLine {
+            inventory_id: ingredient.inventory_id,
+            ingredient_name: ingredient.ingredient_name,
+            quantity,
+            unit: ingredient.unit,
+            unit_cost,
+            line_cost,
+        });
+    }
+
+    let cost_per_unit =
+        (&total_cost / &batch_size).with_scale_round(6, bigdecimal::RoundingMode::HalfUp);
+
+    // Overhead, with labor taken from how long the recipe's batches have taken
+    let labor_hours = sqlx::query_scalar!(
+        r#"
+        SELECT AVG(production_time_hours)
+        FROM production_batches
+        WHERE recipe_template_id = $1 AND status = 'completed'
+            AND production_time_hours IS NOT NULL
+        "#,
+        recipe_id
+    )
+    .fetch_one(pool)
+    .await?
+    .map(|hours| hours.with_scale_round(2, bigdecimal::RoundingMode::HalfUp));
+
+    let overhead_lines = overhead::allocate(
+        pool,
+        &batch_size,
+        recipe.default_unit.as_deref(),
+        labor_hours.as_ref(),
+    )
+    .await?;
+    let overhead_cost: BigDecimal = overhead_lines
+        .iter()
+        .filter_map(|line| line.line_cost.clone())
+        .sum();
+    let full_cost_per_unit = ((&total_cost + &overhead_cost) / &batch_size)
+        .with_scale_round(6, bigdecimal::RoundingMode::HalfUp);
+
+    // Price to compare against: given, or what the product has actually sold for
+    let (sale_price, price_source) = match (sale_price, recipe.product_inventory_id) {
+        (Some(price), _) => (Some(price), Some("given".to_string())),
+        (None, Some(product_id)) => {
+            let average = sqlx::query_scalar!(
+                r#"
+                SELECT SUM(si.line_total - si.discount_amount) / NULLIF(SUM(si.quantity), 0)
+                FROM sale_items si
+                JOIN sales s ON s.id = si.sale_id
+                WHERE si.inventory_id = $1
+                    AND s.sale_date >= NOW() - INTERVAL '90 days'
+                    AND s.payment_status <> 'refunded'
+                "#,
+                product_id
+            )
+            .fetch_one(pool)
+            .await?;
+
+            match average {
+                Some(price) => (
+                    Some(price.with_scale_round(6, bigdecimal::RoundingMode::HalfUp)),
+                    Some("recent_sales".to_string()),
+                ),
+                None => (None, None),
+            }
+        }
+        (None, None) => (None, None),
+    };
+
+    let margin_per_unit = sale_price.as_ref().map(|price| price - &full_cost_per_unit);
+    let margin_percent = match (&sale_price, &margin_per_unit) {
+        (Some(price), Some(margin)) if *price > zero => Some(
+            (margin * BigDecimal::from(100) / price)
+                .with_scale_round(1, bigdecimal::RoundingMode::HalfUp),
+        ),
+        _ => None,
+    };
+
+    Ok(Ok(RecipeCost {
+        recipe_template_id: recipe_id,
+        template_name: recipe.template_name,
+        product_inventory_id: recipe.product_inventory_id,
+        batch_size,
+        unit: recipe.default_unit,
+        cost_basis: cost_basis.to_string(),
+        lines,
+        total_cost,
+        cost_per_unit,
+        uncosted,
+        labor_hours,
+        overhead_lines,
+        overhead_cost,
+        full_cost_per_unit,
+        sale_price,
+        price_source,
+        margin_per_unit,
+        margin_percent,
+    }))
+}
+
+/// Check that ingredients are listed once each, name active inventory items,
+/// and have positive quantities. Returns the reason if they can't be saved.
+pub async fn validate(
+    conn: &mut PgConnection,
+    ingredients: &[RecipeIngredientInput],
+) -> Result<Result<(), String>, sqlx::Error> {
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if ingredient.quantity_per_batch <= BigDecimal::from(0) {
+            return Ok(Err(
+                "All ingredient quantities must be greater than 0".to_string()
+            ));
+        }
+
+        if ingredients[..index]
+            .iter()
+            .any(|i| i.inventory_id == ingredient.inventory_id)
+        {
+            return Ok(Err(format!(
+                "Ingredient {} is listed more than once",
+                ingredient.inventory_id
+            )));
+        }
+
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+            ingredient.inventory_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !exists {
+            return Ok(Err(format!(
+                "Ingredient with ID {} not found or is inactive",
+                ingredient.inventory_id
+            )));
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+/// Replace a recipe's ingredient rows with a validated list. The legacy JSONB
+/// is rewritten to match so older clients keep seeing the same ingredients;
+/// the new template is returned.
+pub async fn replace(
+    conn: &mut PgConnection,
+    recipe_template_id: Uuid,
+    ingredients: &[RecipeIngredientInput],
+) -> Result<Option<Value>, sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM recipe_ingredients WHERE recipe_template_id = $1",
+        recipe_template_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for (position, ingredient) in (1..).zip(ingredients) {
+        sqlx::query!(
+            r#"
+            INSERT INTO recipe_ingredients (
+                recipe_template_id, inventory_id, quantity_per_batch, unit, position
+            )
+            SELECT $1, id, $3, COALESCE($4, unit), $5
+            FROM inventory
+            WHERE id = $2
+            "#,
+            recipe_template_id,
+            ingredient.inventory_id,
+            ingredient.quantity_per_batch,
+            ingredient.unit,
+            position
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query_scalar!(
+        r#"
+        UPDATE recipe_templates
+        SET ingredient_template = jsonb_build_object(
+            'ingredients',
+            COALESCE((
+                SELECT jsonb_agg(
+                    jsonb_build_object(
+                        'inventory_id', inventory_id,
+                        'quantity_per_batch', quantity_per_batch,
+                        'unit', unit
+                    )
+                    ORDER BY position
+                )
+                FROM recipe_ingredients
+                WHERE recipe_template_id = $1
+            ), '[]'::jsonb)
+        )
+        WHERE id = $1
+        RETURNING ingredient_template
+        "#,
+        recipe_template_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
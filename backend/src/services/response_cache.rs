@@ -0,0 +1,286 @@
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextParseQuery, NextPrepareRequest,
+};
+use async_graphql::parser::types::{ExecutableDocument, OperationType, Selection};
+use async_graphql::{Request, Response, ServerResult, Value, Variables};
+
+use crate::services::auth::Identity;
+use crate::services::config::Config;
+use crate::services::versions::ApiVersion;
+
+/// Entries kept before expired ones are dropped.
+const MAX_ENTRIES: usize = 1_000;
+
+/// Mutations that can't change a cached result, so don't clear the cache.
+/// Token refreshes in particular come from every open screen.
+const QUIET_MUTATIONS: [&str; 5] = [
+    "login",
+    "refreshToken",
+    "logout",
+    "submitFeedback",
+    "updateNotificationSettings",
+];
+
+/// Results of hot, read-only queries (`RESPONSE_CACHE_QUERIES`) kept in
+/// memory for `RESPONSE_CACHE_TTL_SECS`, so the kiosk display polling
+/// `inventoryItems` doesn't reach the database every time. Results are kept
+/// per client, API version, query text and variables. Every writer clears
+/// the cache with `invalidate`: mutations outside `QUIET_MUTATIONS`, action
+/// link confirmations, probe readings, Stripe webhooks and the Shopify and
+/// Square imports. Changes made by scheduled jobs (expiry alerts, storage
+/// moves, ...) show once entries expire.
+#[derive(Clone)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+    /// Bumped on every clear, so a query that started before a mutation
+    /// doesn't store what it read after the clear.
+    generation: Arc<AtomicU64>,
+    ttl: Option<Duration>,
+    queries: Arc<HashSet<String>>,
+}
+
+struct Entry {
+    data: Value,
+    expires: Instant,
+}
+
+impl ResponseCache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            entries: Arc::default(),
+            generation: Arc::default(),
+            ttl: config.response_cache_ttl,
+            queries: Arc::new(config.response_cache_queries.iter().cloned().collect()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|entry| entry.expires > Instant::now())
+            .map(|entry| entry.data.clone())
+    }
+
+    fn put(&self, key: String, data: Value, generation: u64) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+
+        let now = Instant::now();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= MAX_ENTRIES {
+                return;
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                data,
+                expires: now + ttl,
+            },
+        );
+    }
+
+    /// Drop every cached result. Anything that writes data a cached query
+    /// may have read calls this once the write is committed.
+    pub fn invalidate(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            entries.clear();
+        }
+    }
+}
+
+/// Schema extension that answers cacheable queries from `ResponseCache` and
+/// clears it after mutations. Only operations whose top-level fields are all
+/// cacheable are cached, and only results without errors. Sign-in and rate
+/// limits still apply to cached answers.
+pub struct CacheQueries;
+
+impl ExtensionFactory for CacheQueries {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(CacheQueriesExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct CacheQueriesExtension {
+    client: Mutex<String>,
+    operation_name: Mutex<Option<String>>,
+    plan: Mutex<Plan>,
+}
+
+#[derive(Default)]
+enum Plan {
+    #[default]
+    Pass,
+    Cache {
+        key: String,
+        generation: u64,
+    },
+    Invalidate,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for CacheQueriesExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if let Ok(mut name) = self.operation_name.lock() {
+            name.clone_from(&request.operation_name);
+        }
+        // Request data isn't in the context until execution starts
+        let identity = request
+            .data
+            .get(&TypeId::of::<Identity>())
+            .and_then(|data| data.downcast_ref::<Identity>());
+        if let Ok(mut client) = self.client.lock() {
+            *client = match identity {
+                Some(Identity {
+                    api_key_id: Some(key),
+                    ..
+                }) => format!("key:{}", key),
+                Some(identity) => format!("user:{}", identity.user_id),
+                None => "anonymous".to_string(),
+            };
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let Some(cache) = ctx.data_opt::<ResponseCache>() else {
+            return Ok(document);
+        };
+        let operation_name = self
+            .operation_name
+            .lock()
+            .ok()
+            .and_then(|name| name.clone());
+        let Some(operation) = document
+            .operations
+            .iter()
+            .find(|(name, _)| {
+                operation_name.is_none() || name.map(|n| n.as_str()) == operation_name.as_deref()
+            })
+            .map(|(_, operation)| operation)
+        else {
+            return Ok(document);
+        };
+
+        // Fragments at the top level aren't worth following; such
+        // operations just aren't cached
+        let fields: Option<Vec<&str>> = operation
+            .node
+            .selection_set
+            .node
+            .items
+            .iter()
+            .map(|selection| match &selection.node {
+                Selection::Field(field) => Some(field.node.name.node.as_str()),
+                _ => None,
+            })
+            .filter(|field| *field != Some("__typename"))
+            .collect();
+
+        let plan = match (operation.node.ty, fields) {
+            (OperationType::Mutation, Some(fields))
+                if fields.iter().all(|field| QUIET_MUTATIONS.contains(field)) =>
+            {
+                Plan::Pass
+            }
+            (OperationType::Mutation, _) => Plan::Invalidate,
+            (OperationType::Query, Some(fields))
+                if cache.ttl.is_some()
+                    && !fields.is_empty()
+                    && fields.iter().all(|field| cache.queries.contains(*field)) =>
+            {
+                let version = ctx
+                    .data_opt::<ApiVersion>()
+                    .map(|version| version.as_str())
+                    .unwrap_or_default();
+                let client = self
+                    .client
+                    .lock()
+                    .map(|client| client.clone())
+                    .unwrap_or_default();
+                Plan::Cache {
+                    key: format!(
+                        "{}\n{}\n{}\n{}\n{}",
+                        version,
+                        client,
+                        operation_name.unwrap_or_default(),
+                        serde_json::to_string(variables).unwrap_or_default(),
+                        query
+                    ),
+                    generation: cache.generation.load(Ordering::SeqCst),
+                }
+            }
+            _ => Plan::Pass,
+        };
+        if let Ok(mut current) = self.plan.lock() {
+            *current = plan;
+        }
+
+        Ok(document)
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let plan = self
+            .plan
+            .lock()
+            .map(|mut plan| std::mem::take(&mut *plan))
+            .unwrap_or_default();
+        let Some(cache) = ctx.data_opt::<ResponseCache>() else {
+            return next.run(ctx, operation_name).await;
+        };
+
+        match plan {
+            Plan::Pass => next.run(ctx, operation_name).await,
+            Plan::Cache { key, generation } => {
+                if let Some(data) = cache.get(&key) {
+                    return Response::new(data);
+                }
+                let response = next.run(ctx, operation_name).await;
+                if response.errors.is_empty() {
+                    cache.put(key, response.data.clone(), generation);
+                }
+                response
+            }
+            Plan::Invalidate => {
+                let response = next.run(ctx, operation_name).await;
+                cache.invalidate();
+                response
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{Customer, InventoryItem, Sale, SaleItem, SaleWithItems};
+use crate::services::config;
+use crate::services::discounts::{self, Discount};
+use crate::services::events;
+use crate::services::invoices;
+use crate::services::lots::{self, LotUsage};
+use crate::services::numbering;
+
+/// A sale whose header row is written, ready for its items.
+pub struct SaleHeader<'a> {
+    pub id: Uuid,
+    pub sale_number: &'a str,
+    pub sale_date: DateTime<Utc>,
+}
+
+/// A line of a sale with its price settled.
+pub struct SaleLine {
+    pub inventory_id: Uuid,
+    pub quantity: BigDecimal,
+    pub unit_price: BigDecimal,
+    /// Discounts on this line only (see `discounts::apply`)
+    pub discounts: Vec<Discount>,
+    pub notes: Option<String>,
+}
+
+impl SaleLine {
+    /// Quantity times unit price.
+    pub fn total(&self) -> BigDecimal {
+        &self.quantity * &self.unit_price
+    }
+
+    /// The line's total less its discounts.
+    pub fn net_total(&self) -> BigDecimal {
+        self.total() - self.discount_amount()
+    }
+
+    pub fn discount_amount(&self) -> BigDecimal {
+        self.discounts.iter().map(|discount| &discount.amount).sum()
+    }
+}
+
+/// Next sale number (`SALE_NUMBER_FORMAT`) for the sale's day.
+pub async fn next_sale_number(
+    conn: &mut PgConnection,
+    sale_date: DateTime<Utc>,
+) -> Result<String, sqlx::Error> {
+    numbering::next(
+        conn,
+        numbering::Kind::Sale,
+        &config::sale_number_format(),
+        sale_date.date_naive(),
+        "",
+    )
+    .await
+}
+
+/// A sale with its items, named, and its customer; None if there's no such
+/// sale.
+pub async fn with_items(
+    pool: &PgPool,
+    sale_id: Uuid,
+) -> Result<Option<SaleWithItems>, sqlx::Error> {
+    // Fetch the sale
+    let sale = sqlx::query_as!(
+        Sale,
+        r#"
+        SELECT
+            id, sale_number, customer_id, sale_date,
+            subtotal, tax_amount, discount_amount, total_amount,
+            payment_method, payment_status, notes, channel, stock_check, oversold,
+            stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+        FROM sales
+        WHERE id = $1
+        "#,
+        sale_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(sale) = sale else {
+        return Ok(None);
+    };
+
+    // Fetch sale items
+    let items = sqlx::query_as!(
+        SaleItem,
+        r#"
+        SELECT
+            s.id, s.sale_id, s.inventory_id, i.name AS item_name, i.unit, s.quantity,
+            s.unit_price, s.line_total, s.discount_amount, s.notes
+        FROM sale_items s
+        JOIN inventory i ON i.id = s.inventory_id
+        WHERE s.sale_id = $1
+        ORDER BY i.name
+        "#,
+        sale_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Fetch customer if exists
+    let customer = if let Some(customer_id) = sale.customer_id {
+        sqlx::query_as!(
+            Customer,
+            r#"
+            SELECT
+                id, name, email, phone,
+                street_address, city, state, zip_code, country,
+                latitude, longitude,
+                customer_type,
+                tax_exempt as "tax_exempt!",
+                sms_opt_in,
+                payment_terms_days,
+                notes,
+                is_active as "is_active!",
+                created_at, updated_at
+            FROM customers
+            WHERE id = $1
+            "#,
+            customer_id
+        )
+        .fetch_optional(pool)
+        .await?
+    } else {
+        None
+    };
+
+    let discounts = discounts::for_sale(pool, sale_id).await?;
+    let invoice = invoices::for_sale(pool, sale_id).await?;
+
+    Ok(Some(SaleWithItems {
+        sale,
+        items,
+        customer,
+        discounts,
+        invoice,
+    }))
+}
+
+/// The stock check policy for a sales channel; channels without one are strict.
+pub async fn stock_check(conn: &mut PgConnection, channel: &str) -> Result<String, sqlx::Error> {
+    let stock_check = sqlx::query_scalar!(
+        "SELECT stock_check FROM sale_channel_policies WHERE channel = $1",
+        channel
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(stock_check.unwrap_or_else(|| "strict".to_string()))
+}
+
+/// Add an item to a sale: the sale line, the stock it takes (from the item and
+/// its open lots, oldest first) and the `inventory_logs` entry. Returns the
+/// item as it now stands and the line as published in `saleCreated`.
+pub async fn sell_item(
+    conn: &mut PgConnection,
+    sale: &SaleHeader<'_>,
+    item: SaleLine,
+) -> Result<(InventoryItem, serde_json::Value), sqlx::Error> {
+    let line_total = item.total();
+    let discount_amount = item.discount_amount();
+
+    let sale_item_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sale_items (
+            sale_id, inventory_id, quantity, unit_price, line_total, discount_amount, notes
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        sale.id,
+        item.inventory_id,
+        item.quantity,
+        item.unit_price,
+        line_total,
+        discount_amount,
+        item.notes
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    discounts::record(&mut *conn, sale.id, Some(sale_item_id), &item.discounts).await?;
+
+    // Update inventory (decrement stock)
+    let updated_item = sqlx::query_as!(
+        InventoryItem,
+        r#"
+        UPDATE inventory
+        SET
+            current_stock = current_stock - $1,
+            updated_at = $2
+        WHERE id = $3
+        RETURNING
+            id,
+            name,
+            category,
+            unit,
+            current_stock as "current_stock!: BigDecimal",
+            reserved_stock as "reserved_stock!: BigDecimal",
+            available_stock as "available_stock!: BigDecimal",
+            reorder_point as "reorder_point!: BigDecimal",
+            cost_per_unit as "cost_per_unit?: BigDecimal",
+            default_supplier_id,
+            shelf_life_days,
+            storage_requirements,
+            product_code,
+            is_active,
+            version,
+            created_at,
+            updated_at
+        "#,
+        item.quantity,
+        sale.sale_date,
+        item.inventory_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // Draw the sold quantity from the item's open lots
+    let draws = lots::consume(&mut *conn, item.inventory_id, &item.quantity).await?;
+    lots::record_usage(&mut *conn, &draws, LotUsage::SaleItem(sale_item_id)).await?;
+
+    let sold = serde_json::json!({
+        "inventory_id": item.inventory_id,
+        "name": updated_item.name,
+        "unit": updated_item.unit,
+        "quantity": item.quantity,
+        "unit_price": item.unit_price,
+        "line_total": line_total,
+        "discount_amount": discount_amount,
+    });
+
+    // Log the sale in inventory_logs
+    sqlx::query!(
+        r#"
+        INSERT INTO inventory_logs (
+            inventory_id, movement_type, quantity, unit_price, reason, created_at
+        ) VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        item.inventory_id,
+        "sale",
+        -item.quantity, // Negative for stock reduction
+        item.unit_price,
+        format!("Sale {}", sale.sale_number),
+        sale.sale_date
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok((updated_item, sold))
+}
+
+/// A sale already rung up on another system (Square, Shopify) to record here.
+pub struct ImportedSale<'a> {
+    pub channel: &'a str,
+    pub sale_date: DateTime<Utc>,
+    pub items: Vec<SaleLine>,
+    pub tax_amount: BigDecimal,
+    pub discount_amount: BigDecimal,
+    /// What the other system charged; defaults to subtotal + tax - discount
+    pub total_amount: Option<BigDecimal>,
+    pub payment_method: Option<String>,
+    pub market_session_id: Option<Uuid>,
+    pub notes: String,
+    /// Key and value naming the source order in `saleCreated` (e.g. "square_order_id")
+    pub source: (&'static str, String),
+}
+
+/// An item an imported sale sold more of than was available.
+pub struct Oversold {
+    pub inventory_id: Uuid,
+    pub message: String,
+}
+
+/// Record an imported sale, draw its stock and publish `saleCreated`. The
+/// sale already happened, so an item with less stock available than it sold
+/// (stock confirmed orders reserved doesn't count) never blocks it: under a strict policy the sale is queued for stock review.
+pub async fn record_imported(
+    conn: &mut PgConnection,
+    sale: ImportedSale<'_>,
+) -> Result<(Uuid, Vec<Oversold>), sqlx::Error> {
+    let stock_check = match stock_check(&mut *conn, sale.channel).await?.as_str() {
+        "strict" => "review".to_string(),
+        policy => policy.to_string(),
+    };
+
+    let mut oversold = Vec::new();
+    for item in &sale.items {
+        let inventory = sqlx::query!(
+            r#"SELECT name, available_stock AS "available_stock!" FROM inventory WHERE id = $1 FOR UPDATE"#,
+            item.inventory_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if inventory.available_stock < item.quantity {
+            oversold.push(Oversold {
+                inventory_id: item.inventory_id,
+                message: format!(
+                    "{}: sold {} with {} available",
+                    inventory.name, item.quantity, inventory.available_stock
+                ),
+            });
+        }
+    }
+    let needs_review = !oversold.is_empty() && stock_check == "review";
+
+    let subtotal: BigDecimal = sale.items.iter().map(SaleLine::total).sum();
+    let total_amount = sale
+        .total_amount
+        .unwrap_or_else(|| &subtotal + &sale.tax_amount - &sale.discount_amount);
+
+    let sale_number = next_sale_number(&mut *conn, sale.sale_date).await?;
+
+    let sale_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sales (
+            sale_number, sale_date,
+            subtotal, tax_amount, discount_amount, total_amount,
+            payment_method, payment_status, notes,
+            channel, stock_check, oversold, stock_review_status, market_session_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, 'completed', $8, $9, $10, $11, $12, $13)
+        RETURNING id
+        "#,
+        sale_number,
+        sale.sale_date,
+        subtotal,
+        sale.tax_amount,
+        sale.discount_amount,
+        total_amount,
+        sale.payment_method,
+        sale.notes,
+        sale.channel,
+        stock_check,
+        !oversold.is_empty(),
+        needs_review.then_some("pending"),
+        sale.market_session_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let header = SaleHeader {
+        id: sale_id,
+        sale_number: &sale_number,
+        sale_date: sale.sale_date,
+    };
+    let mut sold = Vec::new();
+    for item in sale.items {
+        let (_, line) = sell_item(&mut *conn, &header, item).await?;
+        sold.push(line);
+    }
+    if sale.discount_amount > BigDecimal::from(0) {
+        let discount = Discount::fixed(
+            sale.discount_amount.clone(),
+            &format!("Imported from {}", sale.channel),
+        );
+        discounts::record(&mut *conn, sale_id, None, &[discount]).await?;
+    }
+
+    let (source_key, source_id) = sale.source;
+    let mut event = json!({
+        "sale_id": sale_id,
+        "sale_number": sale_number,
+        "sale_date": sale.sale_date,
+        "customer_id": null,
+        "channel": sale.channel,
+        "market_session_id": sale.market_session_id,
+        "subtotal": subtotal,
+        "tax_amount": sale.tax_amount,
+        "discount_amount": sale.discount_amount,
+        "total_amount": total_amount,
+        "payment_method": sale.payment_method,
+        "items": sold,
+    });
+    event[source_key] = json!(source_id);
+
+    events::publish(&mut *conn, "saleCreated", event).await?;
+
+    Ok((sale_id, oversold))
+}
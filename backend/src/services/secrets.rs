@@ -0,0 +1,264 @@
+use std::fmt;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::IntegrationSecret;
+
+/// Secret holding the alert webhook URL (falls back to `ALERT_WEBHOOK_URL`).
+pub const ALERT_WEBHOOK_URL: &str = "alert_webhook_url";
+/// Secret holding the sensor ingest device tokens (falls back to `INGEST_DEVICE_TOKENS`).
+pub const INGEST_DEVICE_TOKENS: &str = "ingest_device_tokens";
+/// Stripe secret API key used to create payment links (falls back to `STRIPE_SECRET_KEY`).
+pub const STRIPE_SECRET_KEY: &str = "stripe_secret_key";
+/// Stripe webhook endpoint signing secret (falls back to `STRIPE_WEBHOOK_SECRET`).
+pub const STRIPE_WEBHOOK_SECRET: &str = "stripe_webhook_secret";
+/// Shopify app API secret used to sign webhooks (falls back to `SHOPIFY_WEBHOOK_SECRET`).
+pub const SHOPIFY_WEBHOOK_SECRET: &str = "shopify_webhook_secret";
+/// Shopify store domain, e.g. my-shop.myshopify.com (falls back to `SHOPIFY_SHOP_DOMAIN`).
+pub const SHOPIFY_SHOP_DOMAIN: &str = "shopify_shop_domain";
+/// Shopify Admin API access token used to sync stock and orders (falls back to `SHOPIFY_ACCESS_TOKEN`).
+pub const SHOPIFY_ACCESS_TOKEN: &str = "shopify_access_token";
+/// Square webhook subscription signature key (falls back to `SQUARE_WEBHOOK_SIGNATURE_KEY`).
+pub const SQUARE_WEBHOOK_SIGNATURE_KEY: &str = "square_webhook_signature_key";
+/// Public URL Square posts to; part of its signed payload (falls back to `SQUARE_WEBHOOK_URL`).
+pub const SQUARE_WEBHOOK_URL: &str = "square_webhook_url";
+/// Square access token used to pull orders (falls back to `SQUARE_ACCESS_TOKEN`).
+pub const SQUARE_ACCESS_TOKEN: &str = "square_access_token";
+/// Twilio account SID for text messages (falls back to `TWILIO_ACCOUNT_SID`).
+pub const TWILIO_ACCOUNT_SID: &str = "twilio_account_sid";
+/// Twilio auth token (falls back to `TWILIO_AUTH_TOKEN`).
+pub const TWILIO_AUTH_TOKEN: &str = "twilio_auth_token";
+/// Number texts are sent from, in E.164 form (falls back to `TWILIO_FROM_NUMBER`).
+pub const TWILIO_FROM_NUMBER: &str = "twilio_from_number";
+
+/// Error from reading or writing an integration secret.
+#[derive(Debug)]
+pub enum SecretError {
+    /// `SECRETS_ENCRYPTION_KEY` is missing or malformed
+    NotConfigured,
+    /// Stored ciphertext could not be decrypted (wrong key or corrupted row)
+    Decrypt(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::NotConfigured => write!(
+                f,
+                "Secret storage is disabled: SECRETS_ENCRYPTION_KEY is not set to a 64-character hex key"
+            ),
+            SecretError::Decrypt(name) => write!(f, "Failed to decrypt secret '{}'", name),
+            SecretError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+impl From<sqlx::Error> for SecretError {
+    fn from(e: sqlx::Error) -> Self {
+        SecretError::Database(e)
+    }
+}
+
+/// Integration secrets stored in the database, encrypted with AES-256-GCM.
+///
+/// The encryption key comes from `SECRETS_ENCRYPTION_KEY` (32 bytes as hex) and
+/// never touches the database. Integrations look secrets up through `resolve`,
+/// which falls back to the matching environment variable so existing
+/// env-based deployments keep working.
+#[derive(Clone)]
+pub struct SecretStore {
+    pool: PgPool,
+    cipher: Option<Arc<Aes256Gcm>>,
+}
+
+impl SecretStore {
+    pub fn from_env(pool: PgPool) -> Self {
+        let cipher = std::env::var("SECRETS_ENCRYPTION_KEY")
+            .ok()
+            .and_then(|hex| decode_hex(hex.trim()))
+            .filter(|key| key.len() == 32)
+            .map(|key| Arc::new(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))));
+
+        if cipher.is_none() {
+            tracing::info!(
+                "🔐 SECRETS_ENCRYPTION_KEY not set; integration secrets come from env vars only"
+            );
+        }
+
+        Self { pool, cipher }
+    }
+
+    /// Look up a secret, falling back to an environment variable.
+    pub async fn resolve(&self, name: &str, env_var: &str) -> Option<String> {
+        match self.get(name).await {
+            Ok(Some(value)) => return Some(value),
+            Ok(None) | Err(SecretError::NotConfigured) => {}
+            Err(e) => tracing::error!("❌ {}", e),
+        }
+
+        std::env::var(env_var)
+            .ok()
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Decrypted value of a secret, if it exists.
+    pub async fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        let cipher = self.cipher.as_ref().ok_or(SecretError::NotConfigured)?;
+
+        let row = sqlx::query!(
+            "SELECT ciphertext, nonce FROM integration_secrets WHERE name = $1",
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&row.nonce), row.ciphertext.as_slice())
+            .map_err(|_| SecretError::Decrypt(name.to_string()))?;
+
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| SecretError::Decrypt(name.to_string()))
+    }
+
+    /// Create or replace a secret. Replacing an existing value bumps its version.
+    pub async fn set(
+        &self,
+        name: &str,
+        value: &str,
+        description: Option<&str>,
+        rotate: bool,
+    ) -> Result<IntegrationSecret, SecretError> {
+        let (ciphertext, nonce) = self.encrypt(value)?;
+        let hint = hint(value);
+
+        let row = sqlx::query_as!(
+            SecretRow,
+            r#"
+            INSERT INTO integration_secrets (name, description, ciphertext, nonce, hint)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (name) DO UPDATE
+            SET description = COALESCE(EXCLUDED.description, integration_secrets.description),
+                ciphertext = EXCLUDED.ciphertext,
+                nonce = EXCLUDED.nonce,
+                hint = EXCLUDED.hint,
+                version = integration_secrets.version + 1,
+                rotated_at = CASE WHEN $6 THEN NOW() ELSE integration_secrets.rotated_at END,
+                updated_at = NOW()
+            RETURNING id, name, description, hint, version, rotated_at, created_at, updated_at
+            "#,
+            name,
+            description,
+            ciphertext,
+            nonce,
+            hint,
+            rotate
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.into())
+    }
+
+    /// All secrets, masked.
+    pub async fn list(&self) -> Result<Vec<IntegrationSecret>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            SecretRow,
+            r#"
+            SELECT id, name, description, hint, version, rotated_at, created_at, updated_at
+            FROM integration_secrets
+            ORDER BY name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Whether a secret with this name exists.
+    pub async fn exists(&self, name: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM integration_secrets WHERE name = $1) as "exists!""#,
+            name
+        )
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Delete a secret. Returns false if it didn't exist.
+    pub async fn delete(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!("DELETE FROM integration_secrets WHERE name = $1", name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn encrypt(&self, value: &str) -> Result<(Vec<u8>, Vec<u8>), SecretError> {
+        let cipher = self.cipher.as_ref().ok_or(SecretError::NotConfigured)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, value.as_bytes())
+            .expect("AES-GCM encryption does not fail for in-memory buffers");
+
+        Ok((ciphertext, nonce.to_vec()))
+    }
+}
+
+/// Last four characters of a value long enough that showing them reveals little.
+fn hint(value: &str) -> Option<String> {
+    let chars: Vec<char> = value.chars().collect();
+    (chars.len() >= 12).then(|| chars[chars.len() - 4..].iter().collect())
+}
+
+/// Secret metadata as stored; never includes the ciphertext.
+struct SecretRow {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    hint: Option<String>,
+    version: i32,
+    rotated_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<SecretRow> for IntegrationSecret {
+    fn from(row: SecretRow) -> Self {
+        IntegrationSecret {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            masked_value: format!("••••{}", row.hint.unwrap_or_default()),
+            version: row.version,
+            rotated_at: row.rotated_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
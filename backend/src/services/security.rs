@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::services::config::Config;
+
+/// Response headers browsers may read cross-origin: CSV and PDF file names,
+/// change export paging and API version sunset notices.
+const EXPOSED_HEADERS: [&str; 6] = [
+    "content-disposition",
+    "x-has-more",
+    "x-next-cursor",
+    "deprecation",
+    "sunset",
+    "link",
+];
+
+/// Which browser origins may call the API, with which methods and request
+/// headers (`CORS_ORIGINS`, `CORS_METHODS`, `CORS_HEADERS`). Tokens travel
+/// in the `Authorization` header, so cookies and other credentials are never
+/// allowed cross-origin.
+pub fn cors(config: &Config) -> CorsLayer {
+    let origins = match &config.cors_origins {
+        Some(origins) => AllowOrigin::list(origins.clone()),
+        None => AllowOrigin::any(),
+    };
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(config.cors_methods.clone())
+        .allow_headers(config.cors_headers.clone())
+        .expose_headers(EXPOSED_HEADERS.map(HeaderName::from_static))
+}
+
+/// Headers added to every response that doesn't set its own, or None when
+/// `SECURITY_HEADERS_ENABLED=false`. HSTS is only sent when `PUBLIC_URL` is
+/// https, so a LAN install on plain http isn't locked out.
+pub fn headers(config: &Config) -> Option<Arc<HeaderMap>> {
+    if !config.security_headers {
+        return None;
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    // Action link pages carry their token in the URL
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        config.content_security_policy.clone(),
+    );
+    if config.https {
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000; includeSubDomains"),
+        );
+    }
+
+    Some(Arc::new(headers))
+}
+
+/// Middleware that adds the `headers` to each response.
+pub async fn add_headers(
+    State(headers): State<Arc<HeaderMap>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+
+    for (name, value) in headers.iter() {
+        if !response.headers().contains_key(name) {
+            response.headers_mut().insert(name, value.clone());
+        }
+    }
+
+    response
+}
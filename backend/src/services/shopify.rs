@@ -0,0 +1,852 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive, Zero};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{
+    ShopifyOrder, ShopifyProduct, ShopifySyncConflict, ShopifyVariant, UnmappedShopifyVariant,
+};
+use crate::services::http::{HttpClient, HttpError};
+use crate::services::sales::{self, ImportedSale, SaleLine};
+use crate::services::secrets::{self, SecretStore};
+
+/// Admin API version the requests are written against.
+const API_VERSION: &str = "2025-01";
+
+/// How far back the first sync looks for orders.
+const FIRST_SYNC_DAYS: i64 = 7;
+
+/// Later syncs ask for orders updated since this long before the newest
+/// order already pulled. Known orders are skipped.
+const OVERLAP_MINUTES: i64 = 60;
+
+/// Shopify reads stock levels for at most this many inventory items per request.
+const ITEMS_PER_LEVELS_REQUEST: usize = 50;
+
+/// Channel Shopify sales are recorded under; its policy decides how an
+/// oversold line is treated.
+pub const CHANNEL: &str = "shopify";
+
+/// What one sync did.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// New orders pulled from Shopify
+    pub fetched: i32,
+    pub imported: i32,
+    pub unmapped: i32,
+    pub skipped: i32,
+    /// Stock levels changed on Shopify
+    pub pushed: i32,
+    pub conflicts: i32,
+}
+
+/// Why a sync didn't reach Shopify.
+#[derive(Debug)]
+pub enum SyncError {
+    /// The shop domain or access token isn't set
+    NotConnected,
+    Failed(String),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::NotConnected => write!(
+                f,
+                "Shopify is not connected; set the shopify_shop_domain and shopify_access_token secrets"
+            ),
+            SyncError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(e: sqlx::Error) -> Self {
+        SyncError::Failed(e.to_string())
+    }
+}
+
+/// A connected shop's Admin API.
+struct Shop<'a> {
+    http: &'a HttpClient,
+    base: String,
+    token: String,
+}
+
+impl<'a> Shop<'a> {
+    async fn connect(http: &'a HttpClient, secrets: &SecretStore) -> Result<Self, SyncError> {
+        let domain = secrets
+            .resolve(secrets::SHOPIFY_SHOP_DOMAIN, "SHOPIFY_SHOP_DOMAIN")
+            .await
+            .ok_or(SyncError::NotConnected)?;
+        let token = secrets
+            .resolve(secrets::SHOPIFY_ACCESS_TOKEN, "SHOPIFY_ACCESS_TOKEN")
+            .await
+            .ok_or(SyncError::NotConnected)?;
+
+        // "my-shop", "my-shop.myshopify.com" or a full URL
+        let domain = domain.trim().trim_end_matches('/');
+        let origin = if domain.starts_with("http://") || domain.starts_with("https://") {
+            domain.to_string()
+        } else if domain.contains('.') {
+            format!("https://{}", domain)
+        } else {
+            format!("https://{}.myshopify.com", domain)
+        };
+
+        Ok(Self {
+            http,
+            base: format!("{}/admin/api/{}", origin, API_VERSION),
+            token,
+        })
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.http
+            .client()
+            .get(url)
+            .header("X-Shopify-Access-Token", &self.token)
+    }
+
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.http
+            .client()
+            .post(url)
+            .header("X-Shopify-Access-Token", &self.token)
+    }
+
+    async fn get_json(&self, path: &str, what: &str) -> Result<serde_json::Value, String> {
+        let request = self.get(&format!("{}{}", self.base, path));
+        self.http
+            .execute("shopify", request)
+            .await
+            .map_err(|e| format!("Couldn't fetch Shopify {}: {}", what, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Couldn't read Shopify {}: {}", what, e))
+    }
+}
+
+#[derive(Deserialize)]
+struct Order {
+    id: i64,
+    name: Option<String>,
+    processed_at: Option<DateTime<Utc>>,
+    cancelled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    line_items: Vec<LineItem>,
+    total_tax: Option<String>,
+    total_discounts: Option<String>,
+    #[serde(default)]
+    payment_gateway_names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LineItem {
+    name: Option<String>,
+    variant_id: Option<i64>,
+    quantity: i64,
+    price: Option<String>,
+}
+
+/// Shopify money fields are decimal strings ("12.50").
+fn amount(value: Option<&String>) -> BigDecimal {
+    value
+        .and_then(|value| value.parse::<BigDecimal>().ok())
+        .unwrap_or_else(BigDecimal::zero)
+        .with_scale(2)
+}
+
+/// A variant as Shopify describes it when it's mapped.
+#[derive(Deserialize)]
+pub struct Variant {
+    pub title: Option<String>,
+    pub inventory_item_id: i64,
+}
+
+/// Look up a variant, for the inventory item its stock level is set on.
+pub async fn variant(
+    http: &HttpClient,
+    secrets: &SecretStore,
+    variant_id: i64,
+) -> Result<Option<Variant>, SyncError> {
+    let shop = Shop::connect(http, secrets).await?;
+
+    let request = shop.get(&format!("{}/variants/{}.json", shop.base, variant_id));
+    let response = match shop.http.execute("shopify", request).await {
+        Ok(response) => response,
+        Err(HttpError::Request(e)) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(SyncError::Failed(format!(
+                "Couldn't fetch Shopify variant: {}",
+                e
+            )));
+        }
+    };
+
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| SyncError::Failed(format!("Couldn't read Shopify variant: {}", e)))?;
+
+    serde_json::from_value(body["variant"].clone())
+        .map(Some)
+        .map_err(|e| SyncError::Failed(format!("Couldn't read Shopify variant: {}", e)))
+}
+
+/// Pull orders paid since the last sync and import the ones that aren't
+/// sales yet, then push available stock for every synced variant. Fails when
+/// Shopify isn't connected or can't be reached.
+pub async fn sync(
+    pool: &PgPool,
+    http: &HttpClient,
+    secrets: &SecretStore,
+) -> Result<SyncSummary, SyncError> {
+    let shop = Shop::connect(http, secrets).await?;
+
+    let newest = sqlx::query_scalar!("SELECT MAX(processed_at) FROM shopify_orders")
+        .fetch_one(pool)
+        .await?;
+    let since = match newest {
+        Some(newest) => newest - Duration::minutes(OVERLAP_MINUTES),
+        None => Utc::now() - Duration::days(FIRST_SYNC_DAYS),
+    };
+
+    let orders = fetch_orders(&shop, since)
+        .await
+        .map_err(SyncError::Failed)?;
+
+    let mut fetched = 0;
+    for raw in orders {
+        // Only paid orders are sales; unpaid ones come back once they're paid
+        let paid = matches!(
+            raw["financial_status"].as_str(),
+            Some("paid" | "partially_refunded" | "refunded")
+        );
+        if !paid {
+            continue;
+        }
+        let Ok(order) = serde_json::from_value::<Order>(raw.clone()) else {
+            tracing::warn!("⚠️  Skipped a Shopify order that couldn't be read");
+            continue;
+        };
+        let Some(processed_at) = order.processed_at else {
+            continue;
+        };
+
+        let stored = sqlx::query!(
+            r#"
+            INSERT INTO shopify_orders (order_id, order_name, processed_at, status, raw)
+            VALUES ($1, $2, $3, 'pending', $4)
+            ON CONFLICT (order_id) DO NOTHING
+            "#,
+            order.id,
+            order.name,
+            processed_at,
+            raw
+        )
+        .execute(pool)
+        .await?;
+
+        fetched += stored.rows_affected() as i32;
+    }
+
+    let mut summary = import_waiting(pool).await?;
+    summary.fetched = fetched;
+
+    let (pushed, drifted) = push_stock(pool, &shop).await.map_err(SyncError::Failed)?;
+    summary.pushed = pushed;
+    summary.conflicts += drifted;
+
+    Ok(summary)
+}
+
+/// Import pulled orders that aren't sales yet: new ones and ones waiting
+/// for a mapping. Each order is its own transaction.
+pub async fn import_waiting(pool: &PgPool) -> Result<SyncSummary, sqlx::Error> {
+    let waiting = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM shopify_orders
+        WHERE status IN ('pending', 'unmapped')
+        ORDER BY processed_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = SyncSummary::default();
+
+    for id in waiting {
+        let mut tx = pool.begin().await?;
+        let imported = import_order(&mut tx, id).await?;
+        tx.commit().await?;
+
+        let Some((status, oversold)) = imported else {
+            continue;
+        };
+        match status {
+            "imported" => summary.imported += 1,
+            "unmapped" => summary.unmapped += 1,
+            "skipped" => summary.skipped += 1,
+            _ => {}
+        }
+        summary.conflicts += oversold;
+    }
+
+    Ok(summary)
+}
+
+/// Turn one pulled order into a sale, or record why it can't be one yet.
+/// Returns the order's new status and the oversold conflicts it logged, or
+/// None if another import got to it first.
+async fn import_order(
+    conn: &mut PgConnection,
+    id: Uuid,
+) -> Result<Option<(&'static str, i32)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT processed_at, raw FROM shopify_orders
+        WHERE id = $1 AND status IN ('pending', 'unmapped')
+        FOR UPDATE SKIP LOCKED
+        "#,
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let order = match serde_json::from_value::<Order>(row.raw) {
+        Ok(order) => order,
+        Err(e) => {
+            let issues = vec![format!("Couldn't read the order: {}", e)];
+            finish(conn, id, "skipped", None, &[], &issues).await?;
+            return Ok(Some(("skipped", 0)));
+        }
+    };
+
+    if order.cancelled_at.is_some() {
+        let issues = vec!["Cancelled in Shopify".to_string()];
+        finish(conn, id, "skipped", None, &[], &issues).await?;
+        return Ok(Some(("skipped", 0)));
+    }
+
+    let variant_ids: Vec<i64> = order
+        .line_items
+        .iter()
+        .filter_map(|line| line.variant_id)
+        .collect();
+    let products: HashMap<i64, (Uuid, Uuid, BigDecimal)> = sqlx::query!(
+        r#"
+        SELECT id, variant_id, inventory_id, quantity_per_unit
+        FROM shopify_products
+        WHERE variant_id = ANY($1)
+        "#,
+        &variant_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|p| (p.variant_id, (p.id, p.inventory_id, p.quantity_per_unit)))
+    .collect();
+
+    let mut items = Vec::new();
+    let mut sold_by: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut unmapped: Vec<ShopifyVariant> = Vec::new();
+    let mut issues = Vec::new();
+
+    for line in &order.line_items {
+        let label = line
+            .name
+            .clone()
+            .unwrap_or_else(|| "Unnamed item".to_string());
+
+        let Some(variant_id) = line.variant_id else {
+            issues.push(format!("'{}' isn't a product variant; left out", label));
+            continue;
+        };
+
+        let Some((product_id, inventory_id, per_unit)) = products.get(&variant_id) else {
+            if !unmapped.iter().any(|item| item.variant_id == variant_id) {
+                unmapped.push(ShopifyVariant {
+                    variant_id,
+                    name: line.name.clone(),
+                });
+            }
+            continue;
+        };
+
+        if line.quantity <= 0 {
+            issues.push(format!(
+                "'{}' has quantity {}; left out",
+                label, line.quantity
+            ));
+            continue;
+        }
+
+        let unit_price =
+            (amount(line.price.as_ref()) / per_unit).with_scale_round(2, RoundingMode::HalfUp);
+
+        sold_by.entry(*inventory_id).or_insert(*product_id);
+        items.push(SaleLine {
+            inventory_id: *inventory_id,
+            quantity: BigDecimal::from(line.quantity) * per_unit,
+            unit_price,
+            discounts: Vec::new(),
+            notes: Some(label),
+        });
+    }
+
+    if !unmapped.is_empty() {
+        finish(conn, id, "unmapped", None, &unmapped, &issues).await?;
+        return Ok(Some(("unmapped", 0)));
+    }
+
+    if items.is_empty() {
+        issues.push("Nothing stocked was sold".to_string());
+        finish(conn, id, "skipped", None, &[], &issues).await?;
+        return Ok(Some(("skipped", 0)));
+    }
+
+    let order_name = order.name.clone().unwrap_or_else(|| order.id.to_string());
+    let payment_method = match order.payment_gateway_names.as_slice() {
+        [] => None,
+        [gateway] => Some(gateway.to_lowercase()),
+        _ => Some("split".to_string()),
+    };
+
+    let sale = ImportedSale {
+        channel: CHANNEL,
+        sale_date: row.processed_at,
+        items,
+        tax_amount: amount(order.total_tax.as_ref()),
+        discount_amount: amount(order.total_discounts.as_ref()),
+        // Shipping isn't stock, so the total is the goods, tax and discounts
+        total_amount: None,
+        payment_method,
+        market_session_id: None,
+        notes: format!("Shopify order {}", order_name),
+        source: ("shopify_order_id", order.id.to_string()),
+    };
+
+    let (sale_id, oversold) = sales::record_imported(conn, sale).await?;
+
+    for line in &oversold {
+        sqlx::query!(
+            r#"
+            INSERT INTO shopify_sync_conflicts
+                (shopify_product_id, shopify_order_id, kind, message)
+            VALUES ($1, $2, 'oversold', $3)
+            "#,
+            sold_by.get(&line.inventory_id),
+            id,
+            format!("Shopify order {} oversold {}", order_name, line.message)
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    issues.extend(oversold.iter().map(|line| line.message.clone()));
+
+    finish(conn, id, "imported", Some(sale_id), &[], &issues).await?;
+
+    Ok(Some(("imported", oversold.len() as i32)))
+}
+
+async fn finish(
+    conn: &mut PgConnection,
+    id: Uuid,
+    status: &str,
+    sale_id: Option<Uuid>,
+    unmapped: &[ShopifyVariant],
+    issues: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE shopify_orders
+        SET status = $2, sale_id = $3, unmapped = $4, issues = $5, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status,
+        sale_id,
+        Json(unmapped) as _,
+        Json(issues) as _
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Orders updated since `since`, paid or not, in the order Shopify pages them.
+async fn fetch_orders(
+    shop: &Shop<'_>,
+    since: DateTime<Utc>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut orders = Vec::new();
+    let mut url = format!(
+        "{}/orders.json?status=any&limit=250&updated_at_min={}",
+        shop.base,
+        since.to_rfc3339()
+    );
+
+    loop {
+        let response = shop
+            .http
+            .execute("shopify", shop.get(&url))
+            .await
+            .map_err(|e| format!("Couldn't fetch Shopify orders: {}", e))?;
+
+        // Pages are linked from the Link header: <url>; rel="next"
+        let next = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(|link| {
+                link.split(',').find_map(|part| {
+                    let (target, rel) = part.split_once(';')?;
+                    rel.contains("rel=\"next\"").then(|| {
+                        target
+                            .trim()
+                            .trim_start_matches('<')
+                            .trim_end_matches('>')
+                            .to_string()
+                    })
+                })
+            });
+
+        let page: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Couldn't read Shopify orders: {}", e))?;
+
+        if let Some(found) = page["orders"].as_array() {
+            orders.extend(found.iter().cloned());
+        }
+
+        match next {
+            Some(next) => url = next,
+            None => break,
+        }
+    }
+
+    Ok(orders)
+}
+
+/// Set each synced variant's Shopify stock to what's available here, in
+/// Shopify units, less what pulled orders that aren't sales yet will take.
+///
+/// A level that isn't what the sync last set means Shopify's stock changed
+/// outside the sync. That is logged as a conflict. The sync then applies its
+/// own change on top of Shopify's level, never going above what's here, so an
+/// order Shopify took that hasn't been pulled yet isn't sold twice.
+/// Returns the levels changed and the conflicts logged.
+async fn push_stock(pool: &PgPool, shop: &Shop<'_>) -> Result<(i32, i32), String> {
+    let products = sqlx::query!(
+        r#"
+        SELECT p.id, p.inventory_item_id, p.quantity_per_unit, p.last_pushed_quantity,
+            COALESCE(p.shopify_name, i.name) AS "name!",
+            i.is_active,
+            i.available_stock AS "available_stock!",
+            (
+                SELECT COALESCE(SUM((line->>'quantity')::numeric), 0)
+                FROM shopify_orders o, jsonb_array_elements(o.raw->'line_items') AS line
+                WHERE o.status IN ('pending', 'unmapped')
+                    AND line->>'variant_id' = p.variant_id::text
+            ) AS "held!"
+        FROM shopify_products p
+        JOIN inventory i ON i.id = p.inventory_id
+        WHERE p.sync_enabled
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if products.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let location_id = location_id(shop).await?;
+
+    let item_ids: Vec<i64> = products.iter().map(|p| p.inventory_item_id).collect();
+    let mut levels: HashMap<i64, Option<i64>> = HashMap::new();
+    for chunk in item_ids.chunks(ITEMS_PER_LEVELS_REQUEST) {
+        let ids: Vec<String> = chunk.iter().map(i64::to_string).collect();
+        let page = shop
+            .get_json(
+                &format!(
+                    "/inventory_levels.json?inventory_item_ids={}&location_ids={}&limit=250",
+                    ids.join(","),
+                    location_id
+                ),
+                "stock levels",
+            )
+            .await?;
+
+        for level in page["inventory_levels"].as_array().into_iter().flatten() {
+            if let Some(item_id) = level["inventory_item_id"].as_i64() {
+                levels.insert(item_id, level["available"].as_i64());
+            }
+        }
+    }
+
+    let mut pushed = 0;
+    let mut conflicts = 0;
+
+    for product in products {
+        let target = if product.is_active {
+            let units = (&product.available_stock / &product.quantity_per_unit)
+                .with_scale_round(0, RoundingMode::Floor)
+                .to_i64()
+                .unwrap_or(0);
+            let held = product.held.to_i64().unwrap_or(0);
+            (units - held).max(0)
+        } else {
+            0
+        };
+
+        let on_shopify = levels.get(&product.inventory_item_id).copied().flatten();
+        let expected = product.last_pushed_quantity.map(i64::from);
+
+        let quantity = match (on_shopify, expected) {
+            (Some(on_shopify), Some(expected)) if on_shopify != expected => {
+                let quantity = (on_shopify + target - expected).clamp(0, target);
+                sqlx::query!(
+                    r#"
+                    INSERT INTO shopify_sync_conflicts
+                        (shopify_product_id, kind, expected_quantity, shopify_quantity,
+                         pushed_quantity, message)
+                    VALUES ($1, 'stock_drift', $2, $3, $4, $5)
+                    "#,
+                    product.id,
+                    expected as i32,
+                    on_shopify as i32,
+                    quantity as i32,
+                    format!(
+                        "Shopify had {} of {} where the sync last set {}",
+                        on_shopify, product.name, expected
+                    )
+                )
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+                conflicts += 1;
+                quantity
+            }
+            _ => target,
+        };
+
+        if on_shopify != Some(quantity) {
+            let request = shop
+                .post(&format!("{}/inventory_levels/set.json", shop.base))
+                .json(&json!({
+                    "location_id": location_id,
+                    "inventory_item_id": product.inventory_item_id,
+                    "available": quantity,
+                }));
+            if let Err(e) = shop.http.execute("shopify", request).await {
+                tracing::error!("❌ Couldn't set Shopify stock for {}: {}", product.name, e);
+                continue;
+            }
+            pushed += 1;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE shopify_products
+            SET last_pushed_quantity = $2, last_pushed_at = NOW()
+            WHERE id = $1
+            "#,
+            product.id,
+            quantity as i32
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok((pushed, conflicts))
+}
+
+/// The Shopify location stock is set at: `SHOPIFY_LOCATION_ID` if set,
+/// otherwise the shop's first active location.
+async fn location_id(shop: &Shop<'_>) -> Result<i64, String> {
+    if let Ok(id) = std::env::var("SHOPIFY_LOCATION_ID") {
+        return id
+            .trim()
+            .parse()
+            .map_err(|_| format!("SHOPIFY_LOCATION_ID '{}' isn't a location ID", id));
+    }
+
+    let locations = shop.get_json("/locations.json", "locations").await?;
+
+    locations["locations"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|location| location["active"].as_bool() != Some(false))
+        .find_map(|location| location["id"].as_i64())
+        .ok_or_else(|| "The Shopify store has no active location".to_string())
+}
+
+/// A mapped variant by ID.
+pub async fn product<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<ShopifyProduct>, sqlx::Error> {
+    sqlx::query_as!(
+        ShopifyProduct,
+        r#"
+        SELECT p.id, p.variant_id, p.inventory_item_id, p.shopify_name, p.inventory_id,
+            i.name AS inventory_name, p.quantity_per_unit, p.sync_enabled,
+            p.last_pushed_quantity, p.last_pushed_at, p.created_at, p.updated_at
+        FROM shopify_products p
+        JOIN inventory i ON i.id = p.inventory_id
+        WHERE p.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Every mapped variant, by Shopify name.
+pub async fn products<'e>(
+    executor: impl PgExecutor<'e>,
+) -> Result<Vec<ShopifyProduct>, sqlx::Error> {
+    sqlx::query_as!(
+        ShopifyProduct,
+        r#"
+        SELECT p.id, p.variant_id, p.inventory_item_id, p.shopify_name, p.inventory_id,
+            i.name AS inventory_name, p.quantity_per_unit, p.sync_enabled,
+            p.last_pushed_quantity, p.last_pushed_at, p.created_at, p.updated_at
+        FROM shopify_products p
+        JOIN inventory i ON i.id = p.inventory_id
+        ORDER BY p.shopify_name NULLS LAST, p.variant_id
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Pulled orders, newest first, optionally only those with `status`.
+pub async fn orders<'e>(
+    executor: impl PgExecutor<'e>,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<ShopifyOrder>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT o.id, o.order_id, o.order_name, o.processed_at, o.status, o.sale_id,
+            s.sale_number AS "sale_number?",
+            o.unmapped AS "unmapped: Json<Vec<ShopifyVariant>>",
+            o.issues AS "issues: Json<Vec<String>>",
+            o.created_at, o.updated_at
+        FROM shopify_orders o
+        LEFT JOIN sales s ON s.id = o.sale_id
+        WHERE ($1::varchar IS NULL OR o.status = $1)
+        ORDER BY o.processed_at DESC
+        LIMIT $2
+        "#,
+        status,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ShopifyOrder {
+            id: row.id,
+            order_id: row.order_id,
+            order_name: row.order_name,
+            processed_at: row.processed_at,
+            status: row.status,
+            sale_id: row.sale_id,
+            sale_number: row.sale_number,
+            unmapped: row.unmapped.0,
+            issues: row.issues.0,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+        .collect())
+}
+
+/// Variants holding orders back, most waiting orders first.
+pub async fn unmapped_variants<'e>(
+    executor: impl PgExecutor<'e>,
+) -> Result<Vec<UnmappedShopifyVariant>, sqlx::Error> {
+    sqlx::query_as!(
+        UnmappedShopifyVariant,
+        r#"
+        SELECT
+            (item->>'variant_id')::bigint AS "variant_id!",
+            (array_agg(item->>'name' ORDER BY o.processed_at DESC))[1] AS name,
+            COUNT(*) AS "waiting_orders!",
+            MIN(o.processed_at) AS "oldest_order_at!"
+        FROM shopify_orders o, jsonb_array_elements(o.unmapped) AS item
+        WHERE o.status = 'unmapped'
+        GROUP BY (item->>'variant_id')::bigint
+        ORDER BY COUNT(*) DESC, MIN(o.processed_at)
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// A sync conflict by ID.
+pub async fn conflict<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<ShopifySyncConflict>, sqlx::Error> {
+    sqlx::query_as!(
+        ShopifySyncConflict,
+        r#"
+        SELECT c.id, c.shopify_product_id, p.shopify_name AS "shopify_name?",
+            c.shopify_order_id, c.kind, c.expected_quantity, c.shopify_quantity,
+            c.pushed_quantity, c.message, c.resolved_at, c.resolution_note, c.created_at
+        FROM shopify_sync_conflicts c
+        LEFT JOIN shopify_products p ON p.id = c.shopify_product_id
+        WHERE c.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Sync conflicts, newest first; resolved ones only when asked for.
+pub async fn conflicts<'e>(
+    executor: impl PgExecutor<'e>,
+    include_resolved: bool,
+    limit: i64,
+) -> Result<Vec<ShopifySyncConflict>, sqlx::Error> {
+    sqlx::query_as!(
+        ShopifySyncConflict,
+        r#"
+        SELECT c.id, c.shopify_product_id, p.shopify_name AS "shopify_name?",
+            c.shopify_order_id, c.kind, c.expected_quantity, c.shopify_quantity,
+            c.pushed_quantity, c.message, c.resolved_at, c.resolution_note, c.created_at
+        FROM shopify_sync_conflicts c
+        LEFT JOIN shopify_products p ON p.id = c.shopify_product_id
+        WHERE $1 OR c.resolved_at IS NULL
+        ORDER BY c.created_at DESC
+        LIMIT $2
+        "#,
+        include_resolved,
+        limit
+    )
+    .fetch_all(executor)
+    .await
+}
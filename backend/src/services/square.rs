@@ -0,0 +1,585 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{SquareCatalogItem, SquareItemMapping, SquareOrder, UnmappedSquareItem};
+use crate::services::http::HttpClient;
+use crate::services::sales::{self, ImportedSale, SaleLine};
+use crate::services::secrets::{self, SecretStore};
+
+/// Square's production API; `SQUARE_API_URL` points elsewhere (e.g. the
+/// sandbox, https://connect.squareupsandbox.com).
+const DEFAULT_API_URL: &str = "https://connect.squareup.com";
+
+/// Square API version the requests are written against.
+const SQUARE_VERSION: &str = "2025-01-23";
+
+/// How far back the first import looks.
+const FIRST_IMPORT_DAYS: i64 = 7;
+
+/// Later imports start this long before the newest order already pulled, so
+/// orders Square closes out of order aren't missed. Known orders are skipped.
+const OVERLAP_MINUTES: i64 = 60;
+
+/// Square searches at most this many locations per request.
+const LOCATIONS_PER_SEARCH: usize = 10;
+
+/// Channel Square sales are recorded under; its policy decides how an
+/// oversold line is treated.
+pub const CHANNEL: &str = "square";
+
+/// Orders handled by one import.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// New orders pulled from Square
+    pub fetched: i32,
+    pub imported: i32,
+    pub unmapped: i32,
+    pub skipped: i32,
+}
+
+/// Why an import didn't reach Square.
+#[derive(Debug)]
+pub enum SyncError {
+    /// No access token is set
+    NotConnected,
+    Failed(String),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::NotConnected => write!(
+                f,
+                "Square is not connected; set the square_access_token secret"
+            ),
+            SyncError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(e: sqlx::Error) -> Self {
+        SyncError::Failed(e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct Order {
+    id: String,
+    location_id: Option<String>,
+    closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    line_items: Vec<LineItem>,
+    total_tax_money: Option<Money>,
+    total_discount_money: Option<Money>,
+    total_money: Option<Money>,
+    #[serde(default)]
+    tenders: Vec<Tender>,
+}
+
+#[derive(Deserialize)]
+struct LineItem {
+    name: Option<String>,
+    variation_name: Option<String>,
+    catalog_object_id: Option<String>,
+    quantity: String,
+    base_price_money: Option<Money>,
+}
+
+impl LineItem {
+    fn label(&self) -> Option<String> {
+        match (&self.name, &self.variation_name) {
+            (Some(name), Some(variation)) if !variation.is_empty() => {
+                Some(format!("{}, {}", name, variation))
+            }
+            (Some(name), _) => Some(name.clone()),
+            (None, variation) => variation.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Money {
+    /// Smallest currency unit (cents)
+    amount: i64,
+}
+
+impl Money {
+    fn dollars(money: Option<&Money>) -> BigDecimal {
+        let cents = money.map_or(0, |money| money.amount);
+        (BigDecimal::from(cents) / BigDecimal::from(100)).with_scale(2)
+    }
+}
+
+#[derive(Deserialize)]
+struct Tender {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Pull orders completed since the last import, then import every order
+/// that isn't a sale yet. Fails when Square isn't connected or can't be
+/// reached.
+pub async fn sync(
+    pool: &PgPool,
+    http: &HttpClient,
+    secrets: &SecretStore,
+) -> Result<ImportSummary, SyncError> {
+    let token = secrets
+        .resolve(secrets::SQUARE_ACCESS_TOKEN, "SQUARE_ACCESS_TOKEN")
+        .await
+        .ok_or(SyncError::NotConnected)?;
+
+    let newest = sqlx::query_scalar!("SELECT MAX(closed_at) FROM square_orders")
+        .fetch_one(pool)
+        .await?;
+    let since = match newest {
+        Some(newest) => newest - Duration::minutes(OVERLAP_MINUTES),
+        None => Utc::now() - Duration::days(FIRST_IMPORT_DAYS),
+    };
+
+    let orders = fetch_orders(http, &token, since)
+        .await
+        .map_err(SyncError::Failed)?;
+
+    let mut fetched = 0;
+    for raw in orders {
+        let Ok(order) = serde_json::from_value::<Order>(raw.clone()) else {
+            tracing::warn!("⚠️  Skipped a Square order that couldn't be read");
+            continue;
+        };
+        let Some(closed_at) = order.closed_at else {
+            continue;
+        };
+
+        let stored = sqlx::query!(
+            r#"
+            INSERT INTO square_orders (order_id, location_id, closed_at, status, raw)
+            VALUES ($1, $2, $3, 'pending', $4)
+            ON CONFLICT (order_id) DO NOTHING
+            "#,
+            order.id,
+            order.location_id,
+            closed_at,
+            raw
+        )
+        .execute(pool)
+        .await?;
+
+        fetched += stored.rows_affected() as i32;
+    }
+
+    let mut summary = import_waiting(pool).await?;
+    summary.fetched = fetched;
+    Ok(summary)
+}
+
+/// Import pulled orders that aren't sales yet: new ones and ones waiting
+/// for a mapping. Each order is its own transaction.
+pub async fn import_waiting(pool: &PgPool) -> Result<ImportSummary, sqlx::Error> {
+    let waiting = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM square_orders
+        WHERE status IN ('pending', 'unmapped')
+        ORDER BY closed_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut summary = ImportSummary::default();
+
+    for id in waiting {
+        let mut tx = pool.begin().await?;
+        let status = import_order(&mut tx, id).await?;
+        tx.commit().await?;
+
+        match status {
+            Some("imported") => summary.imported += 1,
+            Some("unmapped") => summary.unmapped += 1,
+            Some("skipped") => summary.skipped += 1,
+            _ => {}
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Turn one pulled order into a sale, or record why it can't be one yet.
+/// Returns the order's new status, or None if another import got to it first.
+async fn import_order(
+    conn: &mut PgConnection,
+    id: Uuid,
+) -> Result<Option<&'static str>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT closed_at, raw FROM square_orders
+        WHERE id = $1 AND status IN ('pending', 'unmapped')
+        FOR UPDATE SKIP LOCKED
+        "#,
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let order = match serde_json::from_value::<Order>(row.raw) {
+        Ok(order) => order,
+        Err(e) => {
+            let issues = vec![format!("Couldn't read the order: {}", e)];
+            finish(conn, id, "skipped", None, &[], &issues).await?;
+            return Ok(Some("skipped"));
+        }
+    };
+
+    let variations: Vec<String> = order
+        .line_items
+        .iter()
+        .filter_map(|line| line.catalog_object_id.clone())
+        .collect();
+    let mappings: HashMap<String, (Uuid, BigDecimal)> = sqlx::query!(
+        r#"
+        SELECT catalog_object_id, inventory_id, quantity_per_unit
+        FROM square_item_mappings
+        WHERE catalog_object_id = ANY($1)
+        "#,
+        &variations
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|m| (m.catalog_object_id, (m.inventory_id, m.quantity_per_unit)))
+    .collect();
+
+    let mut items = Vec::new();
+    let mut unmapped: Vec<SquareCatalogItem> = Vec::new();
+    let mut issues = Vec::new();
+
+    for line in &order.line_items {
+        let label = line.label().unwrap_or_else(|| "Unnamed item".to_string());
+
+        let Some(catalog_object_id) = &line.catalog_object_id else {
+            issues.push(format!("'{}' isn't a catalog item; left out", label));
+            continue;
+        };
+
+        let Some((inventory_id, per_unit)) = mappings.get(catalog_object_id) else {
+            if !unmapped
+                .iter()
+                .any(|item| &item.catalog_object_id == catalog_object_id)
+            {
+                unmapped.push(SquareCatalogItem {
+                    catalog_object_id: catalog_object_id.clone(),
+                    name: line.label(),
+                });
+            }
+            continue;
+        };
+
+        let quantity = match line.quantity.parse::<BigDecimal>() {
+            Ok(quantity) if quantity > BigDecimal::from(0) => quantity,
+            _ => {
+                issues.push(format!(
+                    "'{}' has quantity '{}'; left out",
+                    label, line.quantity
+                ));
+                continue;
+            }
+        };
+
+        let unit_price = (Money::dollars(line.base_price_money.as_ref()) / per_unit)
+            .with_scale_round(2, RoundingMode::HalfUp);
+
+        items.push(SaleLine {
+            inventory_id: *inventory_id,
+            quantity: quantity * per_unit,
+            unit_price,
+            discounts: Vec::new(),
+            notes: Some(label),
+        });
+    }
+
+    if !unmapped.is_empty() {
+        finish(conn, id, "unmapped", None, &unmapped, &issues).await?;
+        return Ok(Some("unmapped"));
+    }
+
+    if items.is_empty() {
+        issues.push("Nothing stocked was sold".to_string());
+        finish(conn, id, "skipped", None, &[], &issues).await?;
+        return Ok(Some("skipped"));
+    }
+
+    let sale_id = record_sale(conn, &order, row.closed_at, items, &mut issues).await?;
+    finish(conn, id, "imported", Some(sale_id), &[], &issues).await?;
+
+    Ok(Some("imported"))
+}
+
+/// Create the sale for an order whose lines are all mapped. Oversold lines
+/// are noted in `issues`.
+async fn record_sale(
+    conn: &mut PgConnection,
+    order: &Order,
+    sale_date: DateTime<Utc>,
+    items: Vec<SaleLine>,
+    issues: &mut Vec<String>,
+) -> Result<Uuid, sqlx::Error> {
+    // A market day's sales count toward its session when there's only one
+    let sessions = sqlx::query_scalar!(
+        "SELECT id FROM market_sessions WHERE session_date = $1",
+        sale_date.date_naive()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let market_session_id = match sessions.as_slice() {
+        [session_id] => Some(*session_id),
+        _ => None,
+    };
+
+    let payment_method = match order.tenders.as_slice() {
+        [] => None,
+        [tender] => Some(tender.kind.to_lowercase()),
+        _ => Some("split".to_string()),
+    };
+
+    let sale = ImportedSale {
+        channel: CHANNEL,
+        sale_date,
+        items,
+        tax_amount: Money::dollars(order.total_tax_money.as_ref()),
+        discount_amount: Money::dollars(order.total_discount_money.as_ref()),
+        // What Square charged, even where line prices were rounded per stock unit
+        total_amount: order
+            .total_money
+            .as_ref()
+            .map(|total| Money::dollars(Some(total))),
+        payment_method,
+        market_session_id,
+        notes: format!("Square order {}", order.id),
+        source: ("square_order_id", order.id.clone()),
+    };
+
+    let (sale_id, oversold) = sales::record_imported(conn, sale).await?;
+    issues.extend(oversold.into_iter().map(|line| line.message));
+
+    Ok(sale_id)
+}
+
+async fn finish(
+    conn: &mut PgConnection,
+    id: Uuid,
+    status: &str,
+    sale_id: Option<Uuid>,
+    unmapped: &[SquareCatalogItem],
+    issues: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE square_orders
+        SET status = $2, sale_id = $3, unmapped = $4, issues = $5, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status,
+        sale_id,
+        Json(unmapped) as _,
+        Json(issues) as _
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Completed orders closed since `since` at every active location, oldest first.
+async fn fetch_orders(
+    http: &HttpClient,
+    token: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let base = std::env::var("SQUARE_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+    let base = base.trim_end_matches('/');
+
+    let request = http
+        .client()
+        .get(format!("{}/v2/locations", base))
+        .bearer_auth(token)
+        .header("Square-Version", SQUARE_VERSION);
+    let locations: serde_json::Value = http
+        .execute("square", request)
+        .await
+        .map_err(|e| format!("Couldn't list Square locations: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Couldn't read Square locations: {}", e))?;
+
+    let location_ids: Vec<&str> = locations["locations"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|location| location["status"].as_str() != Some("INACTIVE"))
+        .filter_map(|location| location["id"].as_str())
+        .collect();
+
+    let mut orders = Vec::new();
+
+    for chunk in location_ids.chunks(LOCATIONS_PER_SEARCH) {
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let body = json!({
+                "location_ids": chunk,
+                "cursor": cursor,
+                "limit": 500,
+                "query": {
+                    "filter": {
+                        "state_filter": { "states": ["COMPLETED"] },
+                        "date_time_filter": {
+                            "closed_at": { "start_at": since.to_rfc3339() }
+                        }
+                    },
+                    "sort": { "sort_field": "CLOSED_AT", "sort_order": "ASC" }
+                }
+            });
+            let request = http
+                .client()
+                .post(format!("{}/v2/orders/search", base))
+                .bearer_auth(token)
+                .header("Square-Version", SQUARE_VERSION)
+                .json(&body);
+            let page: serde_json::Value = http
+                .execute("square", request)
+                .await
+                .map_err(|e| format!("Couldn't search Square orders: {}", e))?
+                .json()
+                .await
+                .map_err(|e| format!("Couldn't read Square orders: {}", e))?;
+
+            if let Some(found) = page["orders"].as_array() {
+                orders.extend(found.iter().cloned());
+            }
+
+            cursor = page["cursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+    }
+
+    Ok(orders)
+}
+
+/// A mapping by ID.
+pub async fn mapping<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<SquareItemMapping>, sqlx::Error> {
+    sqlx::query_as!(
+        SquareItemMapping,
+        r#"
+        SELECT m.id, m.catalog_object_id, m.square_name, m.inventory_id,
+            i.name AS inventory_name, m.quantity_per_unit, m.created_at, m.updated_at
+        FROM square_item_mappings m
+        JOIN inventory i ON i.id = m.inventory_id
+        WHERE m.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Every mapping, by Square name.
+pub async fn mappings<'e>(
+    executor: impl PgExecutor<'e>,
+) -> Result<Vec<SquareItemMapping>, sqlx::Error> {
+    sqlx::query_as!(
+        SquareItemMapping,
+        r#"
+        SELECT m.id, m.catalog_object_id, m.square_name, m.inventory_id,
+            i.name AS inventory_name, m.quantity_per_unit, m.created_at, m.updated_at
+        FROM square_item_mappings m
+        JOIN inventory i ON i.id = m.inventory_id
+        ORDER BY m.square_name NULLS LAST, m.catalog_object_id
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Pulled orders, newest first, optionally only those with `status`.
+pub async fn orders<'e>(
+    executor: impl PgExecutor<'e>,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<SquareOrder>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT o.id, o.order_id, o.location_id, o.closed_at, o.status, o.sale_id,
+            s.sale_number AS "sale_number?",
+            o.unmapped AS "unmapped: Json<Vec<SquareCatalogItem>>",
+            o.issues AS "issues: Json<Vec<String>>",
+            o.created_at, o.updated_at
+        FROM square_orders o
+        LEFT JOIN sales s ON s.id = o.sale_id
+        WHERE ($1::varchar IS NULL OR o.status = $1)
+        ORDER BY o.closed_at DESC
+        LIMIT $2
+        "#,
+        status,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SquareOrder {
+            id: row.id,
+            order_id: row.order_id,
+            location_id: row.location_id,
+            closed_at: row.closed_at,
+            status: row.status,
+            sale_id: row.sale_id,
+            sale_number: row.sale_number,
+            unmapped: row.unmapped.0,
+            issues: row.issues.0,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+        .collect())
+}
+
+/// Variations holding orders back, most waiting orders first.
+pub async fn unmapped_items<'e>(
+    executor: impl PgExecutor<'e>,
+) -> Result<Vec<UnmappedSquareItem>, sqlx::Error> {
+    sqlx::query_as!(
+        UnmappedSquareItem,
+        r#"
+        SELECT
+            item->>'catalog_object_id' AS "catalog_object_id!",
+            (array_agg(item->>'name' ORDER BY o.closed_at DESC))[1] AS name,
+            COUNT(*) AS "waiting_orders!",
+            MIN(o.closed_at) AS "oldest_order_at!"
+        FROM square_orders o, jsonb_array_elements(o.unmapped) AS item
+        WHERE o.status = 'unmapped'
+        GROUP BY item->>'catalog_object_id'
+        ORDER BY COUNT(*) DESC, MIN(o.closed_at)
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
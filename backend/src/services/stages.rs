@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::BatchStage;
+
+/// Outcome of moving a batch to its next stage.
+pub enum Advance {
+    Entered(BatchStage),
+    /// The batch can't move on; the message says why
+    Refused(String),
+}
+
+/// Put a new batch into the first stage of its recipe, if the recipe has stages.
+pub async fn enter_first(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    recipe_template_id: Option<Uuid>,
+    at: DateTime<Utc>,
+) -> Result<Option<BatchStage>, sqlx::Error> {
+    let Some(recipe_template_id) = recipe_template_id else {
+        return Ok(None);
+    };
+
+    enter(conn, batch_id, recipe_template_id, 1, at, None).await
+}
+
+/// Close the batch's current stage and enter the next one in its recipe.
+///
+/// A batch whose recipe gained stages after it started enters the first one.
+pub async fn advance(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    recipe_template_id: Option<Uuid>,
+    at: DateTime<Utc>,
+    notes: Option<String>,
+) -> Result<Advance, sqlx::Error> {
+    let Some(recipe_template_id) = recipe_template_id else {
+        return Ok(Advance::Refused(
+            "Batch has no recipe, so it has no stages".to_string(),
+        ));
+    };
+
+    let current = sqlx::query!(
+        "SELECT position, name FROM batch_stages WHERE batch_id = $1 AND completed_at IS NULL",
+        batch_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let next_position = current.as_ref().map_or(1, |stage| stage.position + 1);
+
+    let has_next = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM recipe_stages WHERE recipe_template_id = $1 AND position = $2)",
+        recipe_template_id,
+        next_position
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .unwrap_or(false);
+
+    if !has_next {
+        return Ok(Advance::Refused(match current {
+            Some(stage) => format!(
+                "Batch is in its final stage ({}); complete the batch instead",
+                stage.name
+            ),
+            None => "Batch's recipe has no stages".to_string(),
+        }));
+    }
+
+    close_current(conn, batch_id, at).await?;
+
+    let stage = enter(conn, batch_id, recipe_template_id, next_position, at, notes)
+        .await?
+        .expect("next recipe stage exists");
+
+    Ok(Advance::Entered(stage))
+}
+
+/// Mark the batch's current stage finished, returning it.
+pub async fn close_current(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    at: DateTime<Utc>,
+) -> Result<Option<BatchStage>, sqlx::Error> {
+    sqlx::query_as!(
+        BatchStage,
+        r#"
+        UPDATE batch_stages
+        SET completed_at = GREATEST($2, started_at)
+        WHERE batch_id = $1 AND completed_at IS NULL
+        RETURNING
+            id, batch_id, position, name, expected_days, started_at,
+            completed_at, notes, created_at
+        "#,
+        batch_id,
+        at
+    )
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+async fn enter(
+    conn: &mut PgConnection,
+    batch_id: Uuid,
+    recipe_template_id: Uuid,
+    position: i32,
+    at: DateTime<Utc>,
+    notes: Option<String>,
+) -> Result<Option<BatchStage>, sqlx::Error> {
+    sqlx::query_as!(
+        BatchStage,
+        r#"
+        INSERT INTO batch_stages (batch_id, position, name, expected_days, started_at, notes)
+        SELECT $1, position, name, expected_days, $4, COALESCE($5, notes)
+        FROM recipe_stages
+        WHERE recipe_template_id = $2 AND position = $3
+        RETURNING
+            id, batch_id, position, name, expected_days, started_at,
+            completed_at, notes, created_at
+        "#,
+        batch_id,
+        recipe_template_id,
+        position,
+        at,
+        notes
+    )
+    .fetch_optional(&mut *conn)
+    .await
+}
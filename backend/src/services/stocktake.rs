@@ -0,0 +1,239 @@
+use std::io::Read;
+
+use async_graphql::UploadValue;
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::ReconciliationSuggestion;
+
+/// Largest photo accepted as count evidence.
+const MAX_PHOTO_BYTES: usize = 10 * 1024 * 1024;
+
+/// How far back before a count to look for the cause of a variance.
+const LOOKBACK_DAYS: i32 = 30;
+/// Two identical entries this close together look like a double entry.
+const DOUBLE_ENTRY_MINUTES: i32 = 15;
+
+/// A photo read from an upload and ready to store.
+pub struct Photo {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Read an uploaded photo, rejecting non-images and oversized files.
+pub fn read_photo(upload: UploadValue) -> Result<Photo, String> {
+    let filename = upload.filename.clone();
+    let content_type = upload
+        .content_type
+        .clone()
+        .filter(|content_type| content_type.starts_with("image/"))
+        .ok_or_else(|| format!("{} is not an image", filename))?;
+
+    let mut data = Vec::new();
+    upload
+        .into_read()
+        .take(MAX_PHOTO_BYTES as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+
+    if data.is_empty() {
+        return Err(format!("{} is empty", filename));
+    }
+
+    if data.len() > MAX_PHOTO_BYTES {
+        return Err(format!(
+            "{} is larger than {} MB",
+            filename,
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        ));
+    }
+
+    Ok(Photo {
+        filename,
+        content_type,
+        data,
+    })
+}
+
+/// Likely causes of a count variance, most likely first.
+///
+/// Scans the item's logs and lots over the 30 days before the count:
+/// - double entry: an entry repeated with the same type and quantity within
+///   15 minutes, whose reversal moves stock the way the variance does
+/// - missing waste (stock short): a lot that expired before the count but
+///   still has stock on record
+/// - unlogged sample (stock short): a shortfall no bigger than a typical
+///   sale, most likely when the item has been selling
+///
+/// Likelihood is highest when the correction matches the variance exactly.
+pub async fn suggestions(
+    conn: &mut PgConnection,
+    inventory_id: Uuid,
+    variance: &BigDecimal,
+    counted_at: DateTime<Utc>,
+) -> Result<Vec<ReconciliationSuggestion>, sqlx::Error> {
+    let zero = BigDecimal::from(0);
+    let mut suggestions = Vec::new();
+
+    if *variance == zero {
+        return Ok(suggestions);
+    }
+
+    let duplicates = sqlx::query!(
+        r#"
+        SELECT l.id, l.movement_type, l.quantity, l.created_at, p.gap_seconds AS "gap_seconds!"
+        FROM inventory_logs l
+        JOIN LATERAL (
+            SELECT EXTRACT(EPOCH FROM l.created_at - o.created_at)::float8 AS gap_seconds
+            FROM inventory_logs o
+            WHERE o.inventory_id = l.inventory_id
+                AND o.movement_type = l.movement_type
+                AND o.quantity = l.quantity
+                AND o.created_at >= l.created_at - make_interval(mins => $4)
+                AND (o.created_at < l.created_at OR (o.created_at = l.created_at AND o.id < l.id))
+            ORDER BY o.created_at DESC
+            LIMIT 1
+        ) p ON true
+        WHERE l.inventory_id = $1
+            AND l.created_at <= $2
+            AND l.created_at >= $2 - make_interval(days => $3)
+            AND l.movement_type NOT IN ('adjustment', 'transfer')
+            AND l.quantity <> 0
+        ORDER BY l.created_at DESC
+        "#,
+        inventory_id,
+        counted_at,
+        LOOKBACK_DAYS,
+        DOUBLE_ENTRY_MINUTES
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for duplicate in duplicates {
+        let reversal = -duplicate.quantity.clone();
+        if reversal.is_positive() != variance.is_positive() {
+            continue;
+        }
+
+        let weight = if duplicate.gap_seconds <= 120.0 {
+            "0.9"
+        } else {
+            "0.7"
+        };
+        suggestions.push(ReconciliationSuggestion {
+            key: format!("double_entry:{}", duplicate.id),
+            cause: "double_entry".to_string(),
+            likelihood: likelihood(variance, &reversal, weight),
+            explanation: format!(
+                "{} of {} on {} was entered twice, {} min apart",
+                duplicate.movement_type,
+                duplicate.quantity.abs(),
+                duplicate.created_at.format("%Y-%m-%d %H:%M"),
+                (duplicate.gap_seconds / 60.0).ceil()
+            ),
+            movement_type: "adjustment".to_string(),
+            quantity: reversal,
+            related_log_id: Some(duplicate.id),
+            lot_id: None,
+        });
+    }
+
+    if variance.is_negative() {
+        let expired = sqlx::query!(
+            r#"
+            SELECT
+                l.id, l.lot_number, l.remaining_quantity,
+                COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) AS "expiry_date!"
+            FROM inventory_lots l
+            JOIN inventory i ON i.id = l.inventory_id
+            WHERE l.inventory_id = $1 AND l.remaining_quantity > 0
+                AND COALESCE(l.expiry_date, l.received_date::date + i.shelf_life_days) < $2::date
+            ORDER BY 4
+            "#,
+            inventory_id,
+            counted_at.date_naive()
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for lot in expired {
+            let (written_off, weight) = if lot.remaining_quantity > variance.abs() {
+                (variance.clone(), "0.6")
+            } else {
+                (-lot.remaining_quantity.clone(), "0.85")
+            };
+
+            suggestions.push(ReconciliationSuggestion {
+                key: format!("missing_waste:{}", lot.id),
+                cause: "missing_waste".to_string(),
+                // A lot that covers the shortfall exactly is the strongest sign;
+                // a bigger one may only have been partly thrown out
+                likelihood: likelihood(variance, &written_off, weight),
+                explanation: format!(
+                    "Lot {} expired on {} with {} still on record; it may have been thrown out without a waste entry",
+                    lot.lot_number.as_deref().unwrap_or("(unnumbered)"),
+                    lot.expiry_date,
+                    lot.remaining_quantity
+                ),
+                movement_type: "waste".to_string(),
+                quantity: written_off,
+                related_log_id: None,
+                lot_id: Some(lot.id),
+            });
+        }
+
+        let sales = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!", AVG(-quantity) AS typical_sale
+            FROM inventory_logs
+            WHERE inventory_id = $1 AND movement_type = 'sale' AND quantity < 0
+                AND created_at <= $2
+                AND created_at >= $2 - make_interval(days => $3)
+            "#,
+            inventory_id,
+            counted_at,
+            LOOKBACK_DAYS
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let shortfall = variance.abs();
+        let weight = match sales.typical_sale {
+            Some(typical) if shortfall <= typical => "0.6",
+            Some(typical) if shortfall <= &typical * BigDecimal::from(2) => "0.3",
+            Some(_) => "0.1",
+            None => "0.2",
+        };
+
+        suggestions.push(ReconciliationSuggestion {
+            key: "unlogged_sample".to_string(),
+            cause: "unlogged_sample".to_string(),
+            likelihood: weight.parse::<BigDecimal>().unwrap_or_default().with_scale(2),
+            explanation: format!(
+                "{} short after {} sale(s) in the last {} days; samples or tastings may not have been logged",
+                shortfall, sales.count, LOOKBACK_DAYS
+            ),
+            movement_type: "sample".to_string(),
+            quantity: variance.clone(),
+            related_log_id: None,
+            lot_id: None,
+        });
+    }
+
+    suggestions.sort_by(|a, b| b.likelihood.cmp(&a.likelihood));
+    Ok(suggestions)
+}
+
+/// `weight` scaled by how closely `correction` matches `variance`: full
+/// weight for an exact match, falling to zero as they differ by the whole
+/// variance.
+fn likelihood(variance: &BigDecimal, correction: &BigDecimal, weight: &str) -> BigDecimal {
+    let weight: BigDecimal = weight.parse().unwrap_or_default();
+    let miss = (variance - correction).abs() / variance.abs();
+    let closeness = (BigDecimal::from(1) - miss).max(BigDecimal::from(0));
+
+    (weight * closeness).with_scale_round(2, bigdecimal::RoundingMode::HalfUp)
+}
@@ -0,0 +1,357 @@
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::models::{MutationError, PaymentLink, Rejection};
+use crate::services::http::HttpClient;
+use crate::services::invoices;
+use crate::services::secrets::{self, SecretStore};
+
+/// Stripe's API; `STRIPE_API_URL` points elsewhere (e.g. stripe-mock).
+const DEFAULT_API_URL: &str = "https://api.stripe.com";
+
+/// Currency payment links charge in.
+const CURRENCY: &str = "usd";
+
+/// Create a payment link for a pending sale's total, or what's left on its
+/// invoice. A sale keeps one active link: asking again returns it, unless
+/// the amount changed since, in which case the old link is deactivated and a
+/// new one made.
+pub async fn create_payment_link(
+    pool: &PgPool,
+    http: &HttpClient,
+    secrets: &SecretStore,
+    sale_id: Uuid,
+) -> Result<Result<PaymentLink, Rejection>, sqlx::Error> {
+    // An invoice that's been partly paid only needs the rest
+    let sale = sqlx::query!(
+        r#"
+        SELECT
+            s.sale_number, s.payment_status,
+            COALESCE(i.amount - i.amount_paid, s.total_amount) AS "amount_due!"
+        FROM sales s
+        LEFT JOIN invoices i ON i.sale_id = s.id
+        WHERE s.id = $1
+        "#,
+        sale_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(sale) = sale else {
+        return Ok(Err(Rejection::new(
+            "Sale not found".to_string(),
+            MutationError::not_found("Sale"),
+        )));
+    };
+    if sale.payment_status != "pending" {
+        return Ok(Err(format!(
+            "Sale {} is {}; only pending sales take a payment link",
+            sale.sale_number, sale.payment_status
+        )
+        .into()));
+    }
+
+    let amount = sale.amount_due.with_scale_round(2, RoundingMode::HalfUp);
+    let cents = (&amount * BigDecimal::from(100)).to_i64().unwrap_or(0);
+    if cents <= 0 {
+        return Ok(Err(
+            format!("Sale {} has nothing to pay", sale.sale_number).into()
+        ));
+    }
+
+    let active = sqlx::query!(
+        r#"
+        SELECT id, stripe_payment_link_id, amount FROM stripe_payment_links
+        WHERE sale_id = $1 AND status = 'active'
+        "#,
+        sale_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if let Some(current) = active.iter().find(|link| link.amount == amount) {
+        return Ok(payment_link(pool, current.id)
+            .await?
+            .ok_or_else(|| "Payment link not found".to_string().into()));
+    }
+
+    let Some(key) = secrets
+        .resolve(secrets::STRIPE_SECRET_KEY, "STRIPE_SECRET_KEY")
+        .await
+    else {
+        return Ok(Err(
+            "Stripe is not connected; set the stripe_secret_key secret"
+                .to_string()
+                .into(),
+        ));
+    };
+    let base = std::env::var("STRIPE_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+    let base = base.trim_end_matches('/');
+
+    // The old total's link mustn't stay payable
+    for old in &active {
+        let request = http
+            .client()
+            .post(format!(
+                "{}/v1/payment_links/{}",
+                base, old.stripe_payment_link_id
+            ))
+            .bearer_auth(&key)
+            .form(&[("active", "false")]);
+        if let Err(e) = http.execute("stripe", request).await {
+            return Ok(Err(format!(
+                "Couldn't deactivate the earlier payment link: {}",
+                e
+            )
+            .into()));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE stripe_payment_links
+            SET status = 'deactivated', updated_at = NOW()
+            WHERE id = $1
+            "#,
+            old.id
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    // Retried calls reuse the key, so a timeout that did reach Stripe
+    // doesn't leave a second price or link behind
+    let attempt = Uuid::new_v4();
+    let sale_ref = sale_id.to_string();
+    let product_name = format!("Frederick Ferments sale {}", sale.sale_number);
+
+    let request = http
+        .client()
+        .post(format!("{}/v1/prices", base))
+        .bearer_auth(&key)
+        .header("Idempotency-Key", format!("{}-price", attempt))
+        .form(&[
+            ("currency", CURRENCY),
+            ("unit_amount", &cents.to_string()),
+            ("product_data[name]", &product_name),
+            ("metadata[sale_id]", &sale_ref),
+        ]);
+    let price = match call(http, request).await {
+        Ok(price) => price,
+        Err(e) => {
+            return Ok(Err(
+                format!("Couldn't create the Stripe price: {}", e).into()
+            ));
+        }
+    };
+    let Some(price_id) = price["id"].as_str() else {
+        return Ok(Err("Stripe returned a price without an ID"
+            .to_string()
+            .into()));
+    };
+
+    let request = http
+        .client()
+        .post(format!("{}/v1/payment_links", base))
+        .bearer_auth(&key)
+        .header("Idempotency-Key", format!("{}-link", attempt))
+        .form(&[
+            ("line_items[0][price]", price_id),
+            ("line_items[0][quantity]", "1"),
+            // One payment settles the sale; later visits can't pay twice
+            ("restrictions[completed_sessions][limit]", "1"),
+            ("metadata[sale_id]", &sale_ref),
+            ("payment_intent_data[metadata][sale_id]", &sale_ref),
+        ]);
+    let link = match call(http, request).await {
+        Ok(link) => link,
+        Err(e) => {
+            return Ok(Err(format!(
+                "Couldn't create the Stripe payment link: {}",
+                e
+            )
+            .into()));
+        }
+    };
+    let (Some(link_id), Some(url)) = (link["id"].as_str(), link["url"].as_str()) else {
+        return Ok(Err("Stripe returned a payment link without an ID or URL"
+            .to_string()
+            .into()));
+    };
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO stripe_payment_links (sale_id, stripe_payment_link_id, url, amount, currency)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        sale_id,
+        link_id,
+        url,
+        amount,
+        CURRENCY
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(payment_link(pool, id)
+        .await?
+        .ok_or_else(|| "Payment link not found".to_string().into()))
+}
+
+async fn call(http: &HttpClient, request: reqwest::RequestBuilder) -> Result<Value, String> {
+    http.execute("stripe", request)
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Apply a verified Stripe event. A paid checkout session for one of our
+/// links marks the link paid and its sale's payment completed, or records a
+/// payment against the sale's invoice; a failed
+/// delayed payment (e.g. ACH) marks the link failed so a new one can be sent.
+/// Returns what changed, or None for events that don't concern a link.
+pub async fn apply_event(pool: &PgPool, payload: &[u8]) -> Result<Option<String>, sqlx::Error> {
+    let event: Value = serde_json::from_slice(payload).unwrap_or_default();
+    let session = &event["data"]["object"];
+
+    let Some(link_id) = session["payment_link"].as_str() else {
+        return Ok(None);
+    };
+    let session_id = session["id"].as_str();
+
+    let paid = match event["type"].as_str() {
+        // A delayed payment completes its session unpaid and clears later
+        Some("checkout.session.completed") if session["payment_status"] == "paid" => true,
+        Some("checkout.session.async_payment_succeeded") => true,
+        Some("checkout.session.async_payment_failed") => false,
+        _ => return Ok(None),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let link = sqlx::query!(
+        r#"
+        UPDATE stripe_payment_links
+        SET status = $2::varchar,
+            checkout_session_id = COALESCE($3, checkout_session_id),
+            paid_at = CASE WHEN $2::varchar = 'paid' THEN NOW() END,
+            updated_at = NOW()
+        WHERE stripe_payment_link_id = $1 AND status <> 'paid'
+        RETURNING sale_id, amount
+        "#,
+        link_id,
+        if paid { "paid" } else { "failed" },
+        session_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(link) = link else {
+        return Ok(None);
+    };
+
+    let invoice = invoices::for_sale(&mut *tx, link.sale_id).await?;
+
+    let message = if let (true, Some(invoice)) = (paid, invoice) {
+        // Paid against the invoice, which completes the sale once settled
+        let paid_on = Utc::now().date_naive();
+        match invoices::record_payment(
+            &mut tx,
+            invoice.id,
+            &link.amount,
+            paid_on,
+            Some("stripe"),
+            session_id,
+            None,
+        )
+        .await?
+        {
+            Ok(_) => format!(
+                "Invoice for sale {} paid {} through its payment link",
+                invoice.sale_number, link.amount
+            ),
+            Err(rejection) => format!(
+                "Payment link {} paid, but it couldn't be recorded: {}",
+                link_id, rejection.message
+            ),
+        }
+    } else if paid {
+        let sale = sqlx::query!(
+            r#"
+            UPDATE sales
+            SET payment_status = 'completed', updated_at = NOW()
+            WHERE id = $1 AND payment_status = 'pending'
+            RETURNING sale_number
+            "#,
+            link.sale_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match sale {
+            Some(sale) => format!("Sale {} paid through its payment link", sale.sale_number),
+            None => format!(
+                "Payment link {} paid, but its sale wasn't pending; left as it was",
+                link_id
+            ),
+        }
+    } else {
+        format!("Payment through link {} failed", link_id)
+    };
+
+    tx.commit().await?;
+
+    Ok(Some(message))
+}
+
+/// A payment link by ID.
+pub async fn payment_link<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<PaymentLink>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentLink,
+        r#"
+        SELECT l.id, l.sale_id, s.sale_number, l.stripe_payment_link_id, l.url, l.amount,
+            l.currency, l.status, l.checkout_session_id, l.paid_at, l.created_at, l.updated_at
+        FROM stripe_payment_links l
+        JOIN sales s ON s.id = l.sale_id
+        WHERE l.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Payment links, newest first, optionally for one sale or with one status.
+pub async fn payment_links<'e>(
+    executor: impl PgExecutor<'e>,
+    sale_id: Option<Uuid>,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<PaymentLink>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentLink,
+        r#"
+        SELECT l.id, l.sale_id, s.sale_number, l.stripe_payment_link_id, l.url, l.amount,
+            l.currency, l.status, l.checkout_session_id, l.paid_at, l.created_at, l.updated_at
+        FROM stripe_payment_links l
+        JOIN sales s ON s.id = l.sale_id
+        WHERE ($1::uuid IS NULL OR l.sale_id = $1)
+            AND ($2::varchar IS NULL OR l.status = $2)
+        ORDER BY l.created_at DESC
+        LIMIT $3
+        "#,
+        sale_id,
+        status,
+        limit
+    )
+    .fetch_all(executor)
+    .await
+}
@@ -0,0 +1,57 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Create the pending tasks that recipe task templates call for on
+/// in-progress batches, optionally limited to one batch or one template.
+/// Existing occurrences are left untouched, so this is safe to run repeatedly.
+///
+/// Due dates that fall on a production closure in the business calendar are
+/// pushed to the next open day.
+pub async fn generate(
+    conn: &mut PgConnection,
+    batch_id: Option<Uuid>,
+    template_id: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO batch_tasks (batch_id, template_id, day_offset, task_type, description, due_date)
+        SELECT
+            b.id, t.id, day, t.task_type, t.description,
+            due.at + make_interval(
+                days => COALESCE(next_open_day(due.at::date, 'production'), due.at::date) - due.at::date
+            )
+        FROM production_batches b
+        JOIN recipe_task_templates t ON t.recipe_template_id = b.recipe_template_id
+        CROSS JOIN LATERAL generate_series(
+            t.start_day,
+            COALESCE(t.repeat_until_day, t.start_day),
+            COALESCE(t.repeat_every_days, 1)
+        ) AS day
+        CROSS JOIN LATERAL (
+            SELECT b.start_date + make_interval(days => day) AS at
+        ) due
+        WHERE b.status = 'in_progress'
+            AND ($1::uuid IS NULL OR b.id = $1)
+            AND ($2::uuid IS NULL OR t.id = $2)
+        ON CONFLICT (batch_id, template_id, day_offset) DO NOTHING
+        "#,
+        batch_id,
+        template_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Cancel a batch's outstanding tasks once it is finished.
+pub async fn cancel_pending(conn: &mut PgConnection, batch_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE batch_tasks SET status = 'cancelled' WHERE batch_id = $1 AND status = 'pending'",
+        batch_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,554 @@
+use std::any::TypeId;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextPrepareRequest, NextRequest,
+};
+use async_graphql::parser::types::{ExecutableDocument, OperationType, Selection, SelectionSet};
+use async_graphql::{PathSegment, Request, Response, ServerResult, Value, Variables};
+use opentelemetry::trace::TraceContextExt;
+use sqlx::PgPool;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+use crate::jobs::api_usage::{ApiUsage, Call};
+use crate::services::auth::Identity;
+use crate::services::versions::{ApiVersion, DeprecationLog};
+
+/// Schema extension that gives every request a trace ID (returned in the
+/// response's `extensions.traceId`) and records resolver, parse and
+/// validation errors in `api_errors`, scrubbed of anything that looks
+/// personal. Staff attach the trace ID to `submitFeedback`. When traces are
+/// exported, it is the request's OpenTelemetry trace ID (the same 32 hex
+/// digits, with dashes), so a reported error leads to its trace.
+pub struct ErrorTelemetry;
+
+impl ExtensionFactory for ErrorTelemetry {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ErrorTelemetryExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct ErrorTelemetryExtension {
+    operation_name: Mutex<Option<String>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for ErrorTelemetryExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let span = tracing::Span::current()
+            .context()
+            .span()
+            .span_context()
+            .clone();
+        let trace_id = if span.is_valid() {
+            Uuid::from_bytes(span.trace_id().to_bytes())
+        } else {
+            Uuid::new_v4()
+        };
+        let response = next.run(ctx).await;
+
+        if response.is_err()
+            && let Some(pool) = ctx.data_opt::<PgPool>()
+        {
+            let operation_name = self
+                .operation_name
+                .lock()
+                .ok()
+                .and_then(|name| name.clone());
+            let errors = response
+                .errors
+                .iter()
+                .map(|error| (path(&error.path), scrub(&error.message)))
+                .collect();
+
+            // Don't hold up the response on the insert
+            tokio::spawn(record(pool.clone(), trace_id, operation_name, errors));
+        }
+
+        response.extension("traceId", Value::String(trace_id.to_string()))
+    }
+
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if let Ok(mut name) = self.operation_name.lock() {
+            name.clone_from(&request.operation_name);
+        }
+        next.run(ctx, request).await
+    }
+}
+
+/// Schema extension that tallies every request by client (user or API key)
+/// and operation in `ApiUsage`: calls, errors and latency, for the
+/// `apiUsage` query. Unnamed operations are labelled by their top-level
+/// fields.
+pub struct UsageTelemetry;
+
+impl ExtensionFactory for UsageTelemetry {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(UsageTelemetryExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct UsageTelemetryExtension {
+    identity: Mutex<Option<Identity>>,
+    deprecations: Mutex<Option<DeprecationLog>>,
+    operation_name: Mutex<Option<String>>,
+    operation: Mutex<Option<(&'static str, String)>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for UsageTelemetryExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let started = Instant::now();
+        let response = next.run(ctx).await;
+
+        if let Some(usage) = ctx.data_opt::<ApiUsage>() {
+            let (operation_type, operation) = self
+                .operation
+                .lock()
+                .ok()
+                .and_then(|operation| operation.clone())
+                .unwrap_or(("invalid", "(unparsed)".to_string()));
+
+            let identity = self
+                .identity
+                .lock()
+                .ok()
+                .and_then(|identity| identity.clone());
+            let deprecated = self
+                .deprecations
+                .lock()
+                .ok()
+                .and_then(|log| log.clone())
+                .is_some_and(|log| !log.is_empty());
+            usage.record(Call {
+                identity: identity.as_ref(),
+                api_version: ctx
+                    .data_opt::<ApiVersion>()
+                    .copied()
+                    .unwrap_or(ApiVersion::V1),
+                operation_type,
+                operation,
+                elapsed: started.elapsed(),
+                failed: response.is_err(),
+                deprecated,
+            });
+        }
+
+        response
+    }
+
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if let Ok(mut name) = self.operation_name.lock() {
+            name.clone_from(&request.operation_name);
+        }
+        // Request data isn't in the context until execution starts
+        if let Ok(mut identity) = self.identity.lock() {
+            *identity = request
+                .data
+                .get(&TypeId::of::<Identity>())
+                .and_then(|data| data.downcast_ref::<Identity>())
+                .cloned();
+        }
+        // Filled in by `Versioning`, which runs first
+        if let Ok(mut deprecations) = self.deprecations.lock() {
+            *deprecations = request
+                .data
+                .get(&TypeId::of::<DeprecationLog>())
+                .and_then(|data| data.downcast_ref::<DeprecationLog>())
+                .cloned();
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let operation_name = self
+            .operation_name
+            .lock()
+            .ok()
+            .and_then(|name| name.clone());
+        if let Ok(mut operation) = self.operation.lock() {
+            *operation = label(&document, operation_name.as_deref());
+        }
+
+        Ok(document)
+    }
+}
+
+/// Schema extension that logs one line per GraphQL request: the operation,
+/// who sent it, its variables (credentials redacted, strings scrubbed as for
+/// `api_errors`), how long it took and whether it failed. Registered ahead
+/// of `ErrorTelemetry` so the line carries the request's trace ID.
+pub struct RequestLog;
+
+impl ExtensionFactory for RequestLog {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestLogExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct RequestLogExtension {
+    user: Mutex<Option<String>>,
+    variables: Mutex<Option<serde_json::Value>>,
+    secret_variables: Mutex<HashSet<String>>,
+    operation_name: Mutex<Option<String>>,
+    operation: Mutex<Option<(&'static str, String)>>,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RequestLogExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let started = Instant::now();
+        let response = next.run(ctx).await;
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (operation_type, operation) = self
+            .operation
+            .lock()
+            .ok()
+            .and_then(|operation| operation.clone())
+            .unwrap_or(("invalid", "(unparsed)".to_string()));
+        let user = self.user.lock().ok().and_then(|user| user.clone());
+        let mut variables = self
+            .variables
+            .lock()
+            .ok()
+            .and_then(|variables| variables.clone())
+            .unwrap_or_default();
+        if let (serde_json::Value::Object(fields), Ok(secrets)) =
+            (&mut variables, self.secret_variables.lock())
+        {
+            for (name, value) in fields.iter_mut() {
+                if secrets.contains(name) {
+                    *value = serde_json::Value::from("[redacted]");
+                }
+            }
+        }
+        let trace_id = match response.extensions.get("traceId") {
+            Some(Value::String(trace_id)) => Some(trace_id.clone()),
+            _ => None,
+        };
+
+        if response.is_err() {
+            let errors = response
+                .errors
+                .iter()
+                .map(|error| scrub(&error.message))
+                .collect::<Vec<_>>()
+                .join("; ");
+            tracing::warn!(
+                operation_type,
+                operation,
+                user,
+                %variables,
+                duration_ms,
+                outcome = "error",
+                errors,
+                trace_id,
+                "GraphQL request failed"
+            );
+        } else {
+            tracing::info!(
+                operation_type,
+                operation,
+                user,
+                %variables,
+                duration_ms,
+                outcome = "ok",
+                trace_id,
+                "GraphQL request"
+            );
+        }
+
+        response
+    }
+
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if let Ok(mut name) = self.operation_name.lock() {
+            name.clone_from(&request.operation_name);
+        }
+        if let Ok(mut variables) = self.variables.lock() {
+            *variables = request
+                .variables
+                .clone()
+                .into_value()
+                .into_json()
+                .ok()
+                .map(redact);
+        }
+        if let Ok(mut user) = self.user.lock() {
+            *user = request
+                .data
+                .get(&TypeId::of::<Identity>())
+                .and_then(|data| data.downcast_ref::<Identity>())
+                .map(|identity| identity.username.clone());
+        }
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let operation_name = self
+            .operation_name
+            .lock()
+            .ok()
+            .and_then(|name| name.clone());
+        if let Ok(mut operation) = self.operation.lock() {
+            *operation = label(&document, operation_name.as_deref());
+        }
+        if let Ok(mut secrets) = self.secret_variables.lock() {
+            *secrets = secret_variables(&document);
+        }
+
+        Ok(document)
+    }
+}
+
+/// Argument, input field and variable names whose values are never logged.
+const SECRET_FIELDS: &[&str] = &[
+    "password",
+    "token",
+    "secret",
+    "apikey",
+    "signature",
+    "authorization",
+];
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_lowercase().replace('_', "");
+    SECRET_FIELDS.iter().any(|secret| name.contains(secret))
+}
+
+/// Request variables fit for the log: values under credential-like names
+/// are replaced with "[redacted]" and other strings are scrubbed.
+fn redact(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .map(|(name, value)| {
+                if is_secret(&name) {
+                    (name, serde_json::Value::from("[redacted]"))
+                } else {
+                    (name, redact(value))
+                }
+            })
+            .collect(),
+        serde_json::Value::Array(items) => items.into_iter().map(redact).collect(),
+        serde_json::Value::String(text) => serde_json::Value::String(scrub(&text)),
+        other => other,
+    }
+}
+
+/// Variables passed to credential-like arguments or input fields, whatever
+/// they're called (e.g. `$p` in `login(input: { password: $p })`).
+fn secret_variables(document: &ExecutableDocument) -> HashSet<String> {
+    let mut secrets = HashSet::new();
+
+    for (_, operation) in document.operations.iter() {
+        selection_secrets(&operation.node.selection_set.node, &mut secrets);
+    }
+    for fragment in document.fragments.values() {
+        selection_secrets(&fragment.node.selection_set.node, &mut secrets);
+    }
+
+    secrets
+}
+
+fn selection_secrets(selection_set: &SelectionSet, secrets: &mut HashSet<String>) {
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                for (name, value) in &field.node.arguments {
+                    // Stand each variable in as an enum so the value can be walked
+                    let Ok(value) = value
+                        .node
+                        .clone()
+                        .into_const_with(|variable| Ok::<_, Infallible>(Value::Enum(variable)));
+                    argument_secrets(&name.node, &value, false, secrets);
+                }
+                selection_secrets(&field.node.selection_set.node, secrets);
+            }
+            Selection::InlineFragment(fragment) => {
+                selection_secrets(&fragment.node.selection_set.node, secrets);
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+fn argument_secrets(name: &str, value: &Value, secret: bool, secrets: &mut HashSet<String>) {
+    let secret = secret || is_secret(name);
+
+    match value {
+        Value::Enum(variable) if secret => {
+            secrets.insert(variable.to_string());
+        }
+        Value::List(items) => {
+            for item in items {
+                argument_secrets(name, item, secret, secrets);
+            }
+        }
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                argument_secrets(field, value, secret, secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The operation that will run and what to call it: its name, else its
+/// top-level fields (e.g. "inventoryItems, suppliers").
+fn label(
+    document: &ExecutableDocument,
+    operation_name: Option<&str>,
+) -> Option<(&'static str, String)> {
+    let (name, operation) = document
+        .operations
+        .iter()
+        .find(|(name, _)| operation_name.is_none() || name.map(|n| n.as_str()) == operation_name)?;
+
+    let operation_type = match operation.node.ty {
+        OperationType::Query => "query",
+        OperationType::Mutation => "mutation",
+        OperationType::Subscription => "subscription",
+    };
+
+    let label = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let fields: Vec<&str> = operation
+                .node
+                .selection_set
+                .node
+                .items
+                .iter()
+                .filter_map(|selection| match &selection.node {
+                    Selection::Field(field) => Some(field.node.name.node.as_str()),
+                    _ => None,
+                })
+                .collect();
+
+            if fields.is_empty() {
+                "(anonymous)".to_string()
+            } else {
+                fields.join(", ")
+            }
+        }
+    };
+
+    Some((operation_type, label))
+}
+
+async fn record(
+    pool: PgPool,
+    trace_id: Uuid,
+    operation_name: Option<String>,
+    errors: Vec<(Option<String>, String)>,
+) {
+    for (path, message) in errors {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO api_errors (trace_id, operation_name, path, message)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            trace_id,
+            operation_name,
+            path,
+            message
+        )
+        .execute(&pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!("❌ Failed to record API error {}: {}", trace_id, e);
+            return;
+        }
+    }
+}
+
+/// A field path like `createSale.items.0`, or None for request-level errors.
+fn path(segments: &[PathSegment]) -> Option<String> {
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some(
+        segments
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Field(name) => name.clone(),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("."),
+    )
+}
+
+/// Blank out words that could identify someone or leak a credential: email
+/// addresses, long digit runs (phone and card numbers), and long opaque
+/// tokens.
+pub fn scrub(message: &str) -> String {
+    message
+        .split(' ')
+        .map(|word| {
+            let digits = word.chars().filter(char::is_ascii_digit).count();
+            let opaque = word.len() >= 32
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '='));
+
+            if word.contains('@') && word.contains('.') {
+                "[email]"
+            } else if digits >= 7 && !is_uuid(word) {
+                "[number]"
+            } else if opaque && !is_uuid(word) {
+                "[token]"
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// IDs are safe to keep and are what makes an error traceable.
+fn is_uuid(word: &str) -> bool {
+    Uuid::parse_str(word.trim_matches(|c: char| !c.is_ascii_alphanumeric())).is_ok()
+}
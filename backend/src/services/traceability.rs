@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    BackwardTrace, BatchTrace, ForwardTrace, InventoryLot, ProductionBatch,
+    ProductionBatchIngredient, PublicBatchTrace, Sale, TracePageField, TracedBatchUse, TracedLot,
+    TracedSaleLine,
+};
+use crate::services::{config, labels};
+
+/// Fields of the public trace page the owner can hide, with what they show.
+/// All are shown until hidden.
+pub const TRACE_PAGE_FIELDS: &[(&str, &str)] = &[
+    ("production_date", "Date the batch was finished"),
+    ("best_by", "Best-by date of the batch's jars"),
+    ("ingredients", "Ingredient names (no quantities)"),
+    ("suppliers", "Farms and suppliers whose lots went in"),
+    ("origins", "Towns those suppliers are in"),
+];
+
+async fn batch_by_id(pool: &PgPool, id: Uuid) -> Result<Option<ProductionBatch>, sqlx::Error> {
+    sqlx::query_as!(
+        ProductionBatch,
+        r#"
+        SELECT
+            id, batch_number, product_inventory_id, recipe_template_id,
+            batch_size, unit, start_date, estimated_completion_date,
+            completion_date, production_date, status,
+            production_time_hours, yield_percentage, actual_yield,
+            quality_notes, storage_location_id,
+            (SELECT name FROM locations WHERE id = storage_location_id) AS storage_location,
+            vessel_id, last_moved_at, notes,
+            created_at, updated_at
+        FROM production_batches
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+async fn batch_id_by_number(
+    pool: &PgPool,
+    batch_number: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT id FROM production_batches WHERE batch_number = $1",
+        batch_number
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Lots drawn as ingredients by a production batch.
+async fn lots_used_by_batch(pool: &PgPool, batch_id: Uuid) -> Result<Vec<TracedLot>, sqlx::Error> {
+    sqlx::query_as!(
+        TracedLot,
+        r#"
+        SELECT
+            l.id as lot_id, l.inventory_id, i.name as item_name, l.lot_number, l.source_type,
+            l.supplier_id, s.name as "supplier_name?", l.production_batch_id,
+            pb.batch_number as "batch_number?", l.received_date, l.expiry_date,
+            c.quantity
+        FROM lot_consumptions c
+        JOIN inventory_lots l ON l.id = c.lot_id
+        JOIN inventory i ON i.id = l.inventory_id
+        LEFT JOIN suppliers s ON s.id = l.supplier_id
+        LEFT JOIN production_batches pb ON pb.id = l.production_batch_id
+        WHERE c.production_batch_id = $1
+        ORDER BY i.name, l.received_date
+        "#,
+        batch_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Everything that went into a batch, following produced ingredients back to
+/// the batches that made them.
+async fn trace_batches_backward(
+    pool: &PgPool,
+    start: Vec<Uuid>,
+) -> Result<Vec<BatchTrace>, sqlx::Error> {
+    let mut visited = HashSet::new();
+    let mut queue = start;
+    let mut traces = Vec::new();
+
+    while let Some(batch_id) = queue.pop() {
+        if !visited.insert(batch_id) {
+            continue;
+        }
+
+        let Some(batch) = batch_by_id(pool, batch_id).await? else {
+            continue;
+        };
+
+        let ingredients = sqlx::query_as!(
+            ProductionBatchIngredient,
+            r#"
+            SELECT id, batch_id, ingredient_inventory_id, quantity_used, unit, notes
+            FROM production_batch_ingredients
+            WHERE batch_id = $1
+            "#,
+            batch_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let ingredient_lots = lots_used_by_batch(pool, batch_id).await?;
+        queue.extend(
+            ingredient_lots
+                .iter()
+                .filter_map(|lot| lot.production_batch_id),
+        );
+
+        traces.push(BatchTrace {
+            batch,
+            ingredients,
+            ingredient_lots,
+        });
+    }
+
+    Ok(traces)
+}
+
+/// Trace a sale back to the lots, batches, and ingredient lots it came from.
+pub async fn backward_from_sale(
+    pool: &PgPool,
+    sale_id: Uuid,
+) -> Result<Option<BackwardTrace>, sqlx::Error> {
+    let sale = sqlx::query_as!(
+        Sale,
+        r#"
+        SELECT
+            id, sale_number, customer_id, sale_date,
+            subtotal, tax_amount, discount_amount, total_amount,
+            payment_method, payment_status, notes, channel, stock_check, oversold,
+            stock_review_status, stock_review_note, market_session_id, created_at, updated_at
+        FROM sales
+        WHERE id = $1
+        "#,
+        sale_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(sale) = sale else {
+        return Ok(None);
+    };
+
+    let sale_lots = sqlx::query_as!(
+        TracedLot,
+        r#"
+        SELECT
+            l.id as lot_id, l.inventory_id, i.name as item_name, l.lot_number, l.source_type,
+            l.supplier_id, s.name as "supplier_name?", l.production_batch_id,
+            pb.batch_number as "batch_number?", l.received_date, l.expiry_date,
+            c.quantity
+        FROM lot_consumptions c
+        JOIN sale_items si ON si.id = c.sale_item_id
+        JOIN inventory_lots l ON l.id = c.lot_id
+        JOIN inventory i ON i.id = l.inventory_id
+        LEFT JOIN suppliers s ON s.id = l.supplier_id
+        LEFT JOIN production_batches pb ON pb.id = l.production_batch_id
+        WHERE si.sale_id = $1
+        ORDER BY i.name, l.received_date
+        "#,
+        sale_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let batch_ids = sale_lots
+        .iter()
+        .filter_map(|lot| lot.production_batch_id)
+        .collect();
+    let batches = trace_batches_backward(pool, batch_ids).await?;
+
+    Ok(Some(BackwardTrace {
+        sale: Some(sale),
+        sale_lots,
+        batches,
+    }))
+}
+
+/// Trace a batch back to its ingredient lots (and the batches behind them).
+pub async fn backward_from_batch(
+    pool: &PgPool,
+    batch_number: &str,
+) -> Result<Option<BackwardTrace>, sqlx::Error> {
+    let Some(batch_id) = batch_id_by_number(pool, batch_number).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(BackwardTrace {
+        sale: None,
+        sale_lots: Vec::new(),
+        batches: trace_batches_backward(pool, vec![batch_id]).await?,
+    }))
+}
+
+/// Trace a batch forward to every lot, sale, and downstream batch its output reached.
+pub async fn forward_from_batch(
+    pool: &PgPool,
+    batch_number: &str,
+) -> Result<Option<ForwardTrace>, sqlx::Error> {
+    let Some(batch_id) = batch_id_by_number(pool, batch_number).await? else {
+        return Ok(None);
+    };
+    let Some(batch) = batch_by_id(pool, batch_id).await? else {
+        return Ok(None);
+    };
+
+    let mut visited = HashSet::new();
+    let mut queue = vec![batch_id];
+    let mut lots = Vec::new();
+    let mut sales = Vec::new();
+    let mut downstream_batches = Vec::new();
+
+    while let Some(current) = queue.pop() {
+        if !visited.insert(current) {
+            continue;
+        }
+
+        // Produced lots plus packaged lots filled from them
+        let batch_lots = sqlx::query_as!(
+            InventoryLot,
+            r#"
+            SELECT
+                id, inventory_id, lot_number, code, source_type, supplier_id,
+                production_batch_id, parent_lot_id, location_id, initial_quantity, remaining_quantity,
+                unit_cost, received_date, expiry_date, created_at, updated_at
+            FROM inventory_lots
+            WHERE production_batch_id = $1
+            ORDER BY received_date
+            "#,
+            current
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let lot_ids: Vec<Uuid> = batch_lots.iter().map(|lot| lot.id).collect();
+
+        let lot_sales = sqlx::query_as!(
+            TracedSaleLine,
+            r#"
+            SELECT
+                sa.id as sale_id, sa.sale_number, sa.sale_date,
+                sa.customer_id, cu.name as "customer_name?",
+                cu.email as "customer_email?", cu.phone as "customer_phone?",
+                si.id as sale_item_id, si.inventory_id, i.name as item_name,
+                l.id as lot_id, l.lot_number, pb.batch_number as "batch_number?",
+                c.quantity
+            FROM lot_consumptions c
+            JOIN sale_items si ON si.id = c.sale_item_id
+            JOIN sales sa ON sa.id = si.sale_id
+            JOIN inventory i ON i.id = si.inventory_id
+            JOIN inventory_lots l ON l.id = c.lot_id
+            LEFT JOIN customers cu ON cu.id = sa.customer_id
+            LEFT JOIN production_batches pb ON pb.id = l.production_batch_id
+            WHERE c.lot_id = ANY($1)
+            ORDER BY sa.sale_date
+            "#,
+            &lot_ids
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let lot_batch_uses = sqlx::query_as!(
+            TracedBatchUse,
+            r#"
+            SELECT
+                pb.id as batch_id, pb.batch_number, pb.status,
+                l.id as lot_id, l.lot_number, c.quantity
+            FROM lot_consumptions c
+            JOIN production_batches pb ON pb.id = c.production_batch_id
+            JOIN inventory_lots l ON l.id = c.lot_id
+            WHERE c.lot_id = ANY($1)
+            ORDER BY pb.start_date
+            "#,
+            &lot_ids
+        )
+        .fetch_all(pool)
+        .await?;
+
+        queue.extend(lot_batch_uses.iter().map(|used| used.batch_id));
+        lots.extend(batch_lots);
+        sales.extend(lot_sales);
+        downstream_batches.extend(lot_batch_uses);
+    }
+
+    Ok(Some(ForwardTrace {
+        batch,
+        lots,
+        sales,
+        downstream_batches,
+    }))
+}
+
+/// Address the QR code on a batch's jars opens. `PUBLIC_URL` is where
+/// customers reach this server (default `http://localhost:4000`).
+pub fn trace_url(batch_number: &str) -> String {
+    format!("{}/trace/{}", config::public_url(), batch_number)
+}
+
+/// Every trace page field and whether it's shown.
+pub async fn trace_page_fields(pool: &PgPool) -> Result<Vec<TracePageField>, sqlx::Error> {
+    let (fields, descriptions): (Vec<String>, Vec<String>) = TRACE_PAGE_FIELDS
+        .iter()
+        .map(|(field, description)| (field.to_string(), description.to_string()))
+        .unzip();
+
+    sqlx::query_as!(
+        TracePageField,
+        r#"
+        SELECT f.field AS "field!", f.description AS "description!",
+            COALESCE(t.visible, true) AS "visible!"
+        FROM unnest($1::text[], $2::text[]) WITH ORDINALITY AS f(field, description, position)
+        LEFT JOIN trace_page_fields t ON t.field = f.field
+        ORDER BY f.position
+        "#,
+        &fields,
+        &descriptions
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// What the public trace page shows for a finished batch, or `None` when
+/// there's no such batch to show (unknown, unfinished, or a development
+/// trial). Suppliers are followed back through produced ingredients.
+pub async fn public_trace(
+    pool: &PgPool,
+    batch_number: &str,
+) -> Result<Option<PublicBatchTrace>, sqlx::Error> {
+    let batch_id = sqlx::query_scalar!(
+        "SELECT id FROM production_batches WHERE batch_number = $1 AND development_id IS NULL",
+        batch_number
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(batch_id) = batch_id else {
+        return Ok(None);
+    };
+    let Some(label) = labels::batch_label(pool, batch_id).await? else {
+        return Ok(None);
+    };
+    if label.status != "completed" {
+        return Ok(None);
+    }
+
+    let fields = trace_page_fields(pool).await?;
+    let shown = |name: &str| fields.iter().any(|f| f.field == name && f.visible);
+
+    let ingredients = if shown("ingredients") {
+        Some(
+            sqlx::query_scalar!(
+                r#"
+                SELECT DISTINCT i.name
+                FROM production_batch_ingredients bi
+                JOIN inventory i ON i.id = bi.ingredient_inventory_id
+                WHERE bi.batch_id = $1
+                ORDER BY i.name
+                "#,
+                batch_id
+            )
+            .fetch_all(pool)
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let (suppliers, origins) = if shown("suppliers") || shown("origins") {
+        let rows = sqlx::query!(
+            r#"
+            WITH RECURSIVE chain AS (
+                SELECT $1::uuid AS batch_id
+                UNION
+                SELECT l.production_batch_id
+                FROM chain
+                JOIN lot_consumptions c ON c.production_batch_id = chain.batch_id
+                JOIN inventory_lots l ON l.id = c.lot_id
+                WHERE l.production_batch_id IS NOT NULL
+            )
+            SELECT DISTINCT s.name, NULLIF(CONCAT_WS(', ', s.city, s.state), '') AS origin
+            FROM chain
+            JOIN lot_consumptions c ON c.production_batch_id = chain.batch_id
+            JOIN inventory_lots l ON l.id = c.lot_id
+            JOIN suppliers s ON s.id = l.supplier_id
+            ORDER BY s.name
+            "#,
+            batch_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut origins: Vec<String> = rows.iter().filter_map(|r| r.origin.clone()).collect();
+        origins.sort();
+        origins.dedup();
+        (
+            shown("suppliers").then(|| rows.into_iter().map(|r| r.name).collect()),
+            shown("origins").then_some(origins),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(Some(PublicBatchTrace {
+        product_name: label.product_name,
+        batch_number: label.batch_number,
+        production_date: shown("production_date").then_some(label.produced_on),
+        best_by: label.best_by.filter(|_| shown("best_by")),
+        ingredients,
+        suppliers,
+        origins,
+    }))
+}
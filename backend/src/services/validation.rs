@@ -0,0 +1,160 @@
+use anyhow::bail;
+use sqlx::PgPool;
+
+use crate::models::DataIssue;
+
+/// Run `validate-data` from the command line: print every issue found and
+/// fail when there are any, so imports and migrations can be gated on it.
+pub async fn run(pool: &PgPool) -> anyhow::Result<()> {
+    let issues = scan(pool).await?;
+    if issues.is_empty() {
+        eprintln!("✅ No data issues found");
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!(
+            "{}\t{} {}\t{}\t→ {}",
+            issue.check, issue.entity, issue.entity_id, issue.description, issue.fix
+        );
+    }
+    bail!("{} data issues found", issues.len())
+}
+
+/// Look for rows that break rules the schema doesn't enforce: batches of
+/// inactive products, recipes calling for inactive ingredients, negative
+/// stock, lots holding more than is in stock, batch logs pointing at no
+/// batch, and sales with no (or mismatched) items. Ordered by check.
+pub async fn scan(pool: &PgPool) -> Result<Vec<DataIssue>, sqlx::Error> {
+    let mut issues = sqlx::query_as!(
+        DataIssue,
+        r#"
+        SELECT 'batch_of_inactive_product' AS "check!", 'production_batches' AS "entity!",
+            b.id AS entity_id,
+            'Batch ' || b.batch_number || ' is in progress but ' || i.name || ' is inactive'
+                AS "description!",
+            'Reactivate the product or fail the batch' AS "fix!"
+        FROM production_batches b
+        JOIN inventory i ON i.id = b.product_inventory_id
+        WHERE b.status = 'in_progress' AND NOT i.is_active
+        ORDER BY b.batch_number
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'inactive_recipe_ingredient' AS "check!", 'recipe_templates' AS "entity!",
+                t.id AS entity_id,
+                'Recipe ' || t.template_name || ' calls for ' || i.name || ', which is inactive'
+                    AS "description!",
+                'Swap the ingredient in the recipe or reactivate it' AS "fix!"
+            FROM recipe_ingredients ri
+            JOIN recipe_templates t ON t.id = ri.recipe_template_id
+            JOIN inventory i ON i.id = ri.inventory_id
+            WHERE COALESCE(t.is_active, true) AND NOT i.is_active
+            ORDER BY t.template_name, i.name
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'negative_stock' AS "check!", 'inventory' AS "entity!", id AS entity_id,
+                name || ' has ' || current_stock || ' ' || unit || ' in stock' AS "description!",
+                'Count it to record the real level' AS "fix!"
+            FROM inventory
+            WHERE current_stock < 0
+            ORDER BY name
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'lots_exceed_stock' AS "check!", 'inventory' AS "entity!", i.id AS entity_id,
+                i.name || ' has ' || SUM(l.remaining_quantity) || ' ' || i.unit
+                    || ' in open lots but ' || i.current_stock || ' in stock' AS "description!",
+                'Count it so its lots and stock agree' AS "fix!"
+            FROM inventory i
+            JOIN inventory_lots l ON l.inventory_id = i.id AND l.remaining_quantity > 0
+            GROUP BY i.id
+            HAVING SUM(l.remaining_quantity) > GREATEST(i.current_stock, 0)
+            ORDER BY i.name
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'orphaned_log' AS "check!", 'inventory_logs' AS "entity!", l.id AS entity_id,
+                'A ' || l.movement_type || ' log for ' || i.name || ' names batch '
+                    || l.batch_number || ', which doesn''t exist' AS "description!",
+                'Correct the batch number or delete the log' AS "fix!"
+            FROM inventory_logs l
+            JOIN inventory i ON i.id = l.inventory_id
+            WHERE l.movement_type IN ('production_use', 'production_output', 'packaging_use')
+                AND l.batch_number IS NOT NULL
+                AND NOT EXISTS (
+                    SELECT 1 FROM production_batches b WHERE b.batch_number = l.batch_number
+                )
+            ORDER BY l.created_at
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'sale_without_items' AS "check!", 'sales' AS "entity!", s.id AS entity_id,
+                'Sale ' || s.sale_number || ' has no items' AS "description!",
+                'Re-enter its items or delete the sale' AS "fix!"
+            FROM sales s
+            WHERE NOT EXISTS (SELECT 1 FROM sale_items si WHERE si.sale_id = s.id)
+            ORDER BY s.sale_date
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    issues.extend(
+        sqlx::query_as!(
+            DataIssue,
+            r#"
+            SELECT 'sale_subtotal_mismatch' AS "check!", 'sales' AS "entity!", s.id AS entity_id,
+                'Sale ' || s.sale_number || ' has a subtotal of ' || s.subtotal
+                    || ' but its items add up to ' || SUM(si.line_total) AS "description!",
+                'Set the subtotal and total from its items' AS "fix!"
+            FROM sales s
+            JOIN sale_items si ON si.sale_id = s.id
+            GROUP BY s.id
+            -- Lines are rounded to the cent one by one; the subtotal isn't
+            HAVING ABS(SUM(si.line_total) - s.subtotal) > 0.01 * COUNT(*)
+            ORDER BY s.sale_date
+            "#
+        )
+        .fetch_all(pool)
+        .await?,
+    );
+
+    Ok(issues)
+}
@@ -0,0 +1,125 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::{InventoryValuation, InventoryValuationLine};
+
+/// Fill in unit costs on log entries written before every movement was
+/// valued. Sales used to carry their sale price as `unit_cost`; it moves to
+/// `unit_price`. Entries without a cost then take their lot's cost (matched
+/// by lot number), or else the item's latest costed receipt at or before the
+/// movement. Entries with neither stay unvalued. Safe to run repeatedly.
+/// Run as `backfill-log-costs`.
+pub async fn backfill_log_costs(pool: &PgPool) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE inventory_logs
+        SET unit_price = unit_cost, unit_cost = NULL
+        WHERE movement_type = 'sale' AND unit_price IS NULL AND unit_cost IS NOT NULL
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let valued = sqlx::query!(
+        r#"
+        UPDATE inventory_logs l
+        SET unit_cost = derived.unit_cost
+        FROM (
+            SELECT
+                l.id,
+                COALESCE(
+                    (
+                        SELECT lot.unit_cost
+                        FROM inventory_lots lot
+                        WHERE lot.inventory_id = l.inventory_id
+                            AND lot.lot_number = l.batch_number
+                            AND lot.unit_cost IS NOT NULL
+                        ORDER BY lot.received_date DESC
+                        LIMIT 1
+                    ),
+                    (
+                        SELECT r.unit_cost
+                        FROM inventory_logs r
+                        WHERE r.inventory_id = l.inventory_id
+                            AND r.movement_type IN ('purchase', 'kit_assembly')
+                            AND r.unit_cost IS NOT NULL
+                            AND r.created_at <= l.created_at
+                        ORDER BY r.created_at DESC
+                        LIMIT 1
+                    )
+                ) AS unit_cost
+            FROM inventory_logs l
+            WHERE l.unit_cost IS NULL
+        ) derived
+        WHERE l.id = derived.id AND derived.unit_cost IS NOT NULL
+        "#
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+    eprintln!("💰 Valued {} earlier stock movement(s)", valued);
+    Ok(())
+}
+
+/// Stock and its value at `as_of`, optionally for one category. Items that
+/// had nothing on hand are left out.
+pub async fn as_of(
+    pool: &PgPool,
+    as_of: DateTime<Utc>,
+    category: Option<&str>,
+) -> Result<InventoryValuation, sqlx::Error> {
+    let lines = sqlx::query_as!(
+        InventoryValuationLine,
+        r#"
+        SELECT
+            inventory_id AS "inventory_id!", item_name AS "item_name!",
+            category AS "category!", unit AS "unit!", quantity AS "quantity!",
+            unit_cost, ROUND(quantity * unit_cost, 2) AS value
+        FROM (
+            SELECT
+                i.id AS inventory_id, i.name AS item_name, i.category, i.unit,
+                i.current_stock - COALESCE((
+                    SELECT SUM(l.quantity)
+                    FROM inventory_logs l
+                    WHERE l.inventory_id = i.id AND l.created_at > $1
+                ), 0) AS quantity,
+                (
+                    SELECT l.unit_cost
+                    FROM inventory_logs l
+                    WHERE l.inventory_id = i.id
+                        AND l.created_at <= $1
+                        AND l.unit_cost IS NOT NULL
+                    ORDER BY l.created_at DESC
+                    LIMIT 1
+                ) AS unit_cost
+            FROM inventory i
+            WHERE i.created_at <= $1
+                AND ($2::varchar IS NULL OR i.category = $2)
+        ) stock
+        WHERE quantity <> 0
+        ORDER BY category, item_name
+        "#,
+        as_of,
+        category
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total_value = lines
+        .iter()
+        .filter_map(|line| line.value.as_ref())
+        .fold(BigDecimal::from(0), |total, value| total + value);
+    let unvalued_items = lines.iter().filter(|line| line.value.is_none()).count() as i32;
+
+    Ok(InventoryValuation {
+        as_of,
+        total_value,
+        unvalued_items,
+        lines,
+    })
+}
@@ -0,0 +1,463 @@
+use std::collections::HashSet;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::SupplierCatalogChange;
+
+/// Price rise (percent) that alerts when an import doesn't set one.
+pub const DEFAULT_ALERT_THRESHOLD_PERCENT: i32 = 5;
+
+/// (field, default column header, required)
+const FIELDS: &[(&str, &str, bool)] = &[
+    ("sku", "SKU", true),
+    ("description", "Description", true),
+    ("pack_size", "Pack", false),
+    ("price", "Price", true),
+    ("available", "Available", false),
+];
+
+/// Where each field is in the file's rows.
+pub struct Columns {
+    sku: usize,
+    description: usize,
+    pack_size: Option<usize>,
+    price: usize,
+    available: Option<usize>,
+}
+
+/// A catalog line read from the file.
+pub struct CatalogRow {
+    pub sku: String,
+    pub description: String,
+    pub pack_size: Option<String>,
+    pub price: BigDecimal,
+    pub is_available: bool,
+}
+
+/// Split CSV text into records. Handles quoted fields with embedded commas,
+/// quotes ("") and line breaks, CRLF line endings and a leading BOM. Blank
+/// lines are dropped.
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.trim().is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("The file ends inside a quoted field".to_string());
+    }
+
+    record.push(field);
+    if record.iter().any(|f| !f.trim().is_empty()) {
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Find each field's column in the header row, using the mapping's header
+/// where one is given. Headers match case-insensitively.
+pub fn columns(header: &[String], mapping: Option<&Value>) -> Result<Columns, String> {
+    let mapping = match mapping {
+        None => None,
+        Some(Value::Object(mapping)) => Some(mapping),
+        Some(_) => {
+            return Err("Mapping must be an object of {\"field\": \"Column Header\"}".to_string());
+        }
+    };
+
+    if let Some(unknown) = mapping
+        .into_iter()
+        .flat_map(|m| m.keys())
+        .find(|key| !FIELDS.iter().any(|(field, _, _)| field == key))
+    {
+        return Err(format!(
+            "Unknown field '{}' in mapping; expected one of: {}",
+            unknown,
+            FIELDS
+                .iter()
+                .map(|(field, _, _)| *field)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let mut found = Vec::with_capacity(FIELDS.len());
+    for (field, default_header, required) in FIELDS {
+        let wanted = mapping
+            .and_then(|m| m.get(*field))
+            .and_then(Value::as_str)
+            .unwrap_or(default_header);
+
+        let index = header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(wanted.trim()));
+
+        if index.is_none() && *required {
+            return Err(format!("No '{}' column for {}", wanted, field));
+        }
+        found.push(index);
+    }
+
+    Ok(Columns {
+        sku: found[0].unwrap_or_default(),
+        description: found[1].unwrap_or_default(),
+        pack_size: found[2],
+        price: found[3].unwrap_or_default(),
+        available: found[4],
+    })
+}
+
+/// Read catalog lines from the records after the header. Returns the lines,
+/// a problem for each row that couldn't be read, and every SKU in the file
+/// (including unreadable rows, so they aren't taken as removed).
+pub fn rows(
+    records: &[Vec<String>],
+    columns: &Columns,
+) -> (Vec<CatalogRow>, Vec<String>, HashSet<String>) {
+    let mut lines = Vec::new();
+    let mut problems = Vec::new();
+    let mut skus = HashSet::new();
+
+    for (index, record) in records.iter().enumerate() {
+        // Row 1 is the header
+        let row_number = index + 2;
+        let cell = |column: usize| record.get(column).map(|c| c.trim()).unwrap_or("");
+
+        let sku = cell(columns.sku);
+        if sku.is_empty() {
+            problems.push(format!("Row {}: no SKU", row_number));
+            continue;
+        }
+        if !skus.insert(sku.to_string()) {
+            problems.push(format!(
+                "Row {}: SKU {} is listed more than once",
+                row_number, sku
+            ));
+            continue;
+        }
+
+        let description = cell(columns.description);
+        if description.is_empty() {
+            problems.push(format!(
+                "Row {}: SKU {} has no description",
+                row_number, sku
+            ));
+            continue;
+        }
+
+        let Some(price) = parse_price(cell(columns.price)) else {
+            problems.push(format!(
+                "Row {}: SKU {} has price '{}', which isn't a number",
+                row_number,
+                sku,
+                cell(columns.price)
+            ));
+            continue;
+        };
+
+        let is_available = match columns.available.map(cell) {
+            None => true,
+            Some(value) => match parse_available(value) {
+                Some(is_available) => is_available,
+                None => {
+                    problems.push(format!(
+                        "Row {}: SKU {} has availability '{}'; use yes/no",
+                        row_number, sku, value
+                    ));
+                    continue;
+                }
+            },
+        };
+
+        lines.push(CatalogRow {
+            sku: sku.to_string(),
+            description: description.to_string(),
+            pack_size: columns
+                .pack_size
+                .map(cell)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+            price,
+            is_available,
+        });
+    }
+
+    (lines, problems, skus)
+}
+
+fn parse_price(value: &str) -> Option<BigDecimal> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+
+    cleaned
+        .parse::<BigDecimal>()
+        .ok()
+        .filter(|price| *price >= BigDecimal::from(0))
+}
+
+fn parse_available(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "" | "y" | "yes" | "true" | "1" | "available" | "in stock" => Some(true),
+        "n" | "no" | "false" | "0" | "unavailable" | "out of stock" | "discontinued" => Some(false),
+        _ => None,
+    }
+}
+
+/// Percent change from `old` to `new`, to 2 places. None when there was no
+/// old price to compare with.
+fn percent_change(old: &BigDecimal, new: &BigDecimal) -> Option<BigDecimal> {
+    if *old == BigDecimal::from(0) {
+        return None;
+    }
+
+    Some(((new - old) * BigDecimal::from(100) / old).with_scale_round(2, RoundingMode::HalfUp))
+}
+
+/// Bring a supplier's catalog in line with the file and record what changed
+/// against `import_id`. New SKUs are linked to the inventory item with the
+/// same name when exactly one exists; SKUs missing from the file are marked
+/// removed.
+pub async fn apply(
+    conn: &mut PgConnection,
+    supplier_id: Uuid,
+    import_id: Uuid,
+    lines: &[CatalogRow],
+    skus: &HashSet<String>,
+    now: DateTime<Utc>,
+) -> Result<Vec<SupplierCatalogChange>, sqlx::Error> {
+    let mut changes = Vec::new();
+
+    for line in lines {
+        let existing = sqlx::query!(
+            r#"
+            SELECT id, description, pack_size, price, is_available, inventory_id, removed_at
+            FROM supplier_catalog_items
+            WHERE supplier_id = $1 AND sku = $2
+            FOR UPDATE
+            "#,
+            supplier_id,
+            line.sku
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(existing) = existing else {
+            let matches = sqlx::query_scalar!(
+                "SELECT id FROM inventory WHERE LOWER(name) = LOWER($1) AND is_active = true",
+                line.description
+            )
+            .fetch_all(&mut *conn)
+            .await?;
+            let inventory_id = match matches.as_slice() {
+                [id] => Some(*id),
+                _ => None,
+            };
+
+            let id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO supplier_catalog_items
+                    (supplier_id, sku, description, pack_size, price, is_available, inventory_id,
+                     created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+                RETURNING id
+                "#,
+                supplier_id,
+                line.sku,
+                line.description,
+                line.pack_size,
+                line.price,
+                line.is_available,
+                inventory_id,
+                now
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+
+            changes.push(change(id, "added", line, inventory_id, None, None));
+            continue;
+        };
+
+        let price_changed = existing.price != line.price;
+        let availability_changed = existing.is_available != line.is_available;
+        let returned = existing.removed_at.is_some();
+        let details_changed =
+            existing.description != line.description || existing.pack_size != line.pack_size;
+
+        if !(price_changed || availability_changed || returned || details_changed) {
+            continue;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE supplier_catalog_items
+            SET description = $2, pack_size = $3, price = $4, is_available = $5,
+                removed_at = NULL, updated_at = $6
+            WHERE id = $1
+            "#,
+            existing.id,
+            line.description,
+            line.pack_size,
+            line.price,
+            line.is_available,
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if returned {
+            changes.push(change(
+                existing.id,
+                "added",
+                line,
+                existing.inventory_id,
+                None,
+                None,
+            ));
+        } else if price_changed || availability_changed {
+            changes.push(change(
+                existing.id,
+                "changed",
+                line,
+                existing.inventory_id,
+                Some(existing.price),
+                Some(existing.is_available),
+            ));
+        }
+    }
+
+    let listed: Vec<String> = skus.iter().cloned().collect();
+    let removed = sqlx::query!(
+        r#"
+        UPDATE supplier_catalog_items
+        SET removed_at = $3, updated_at = $3
+        WHERE supplier_id = $1 AND removed_at IS NULL AND NOT (sku = ANY($2))
+        RETURNING id, sku, description, price, is_available, inventory_id
+        "#,
+        supplier_id,
+        &listed,
+        now
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for item in removed {
+        changes.push(SupplierCatalogChange {
+            id: Uuid::new_v4(),
+            catalog_item_id: item.id,
+            change_type: "removed".to_string(),
+            sku: item.sku,
+            description: item.description,
+            inventory_id: item.inventory_id,
+            old_price: Some(item.price),
+            new_price: None,
+            price_change_percent: None,
+            was_available: Some(item.is_available),
+            is_available: None,
+        });
+    }
+
+    for change in &mut changes {
+        change.id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO supplier_catalog_changes
+                (import_id, catalog_item_id, change_type, sku, description, old_price, new_price,
+                 price_change_percent, was_available, is_available)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id
+            "#,
+            import_id,
+            change.catalog_item_id,
+            change.change_type,
+            change.sku,
+            change.description,
+            change.old_price,
+            change.new_price,
+            change.price_change_percent,
+            change.was_available,
+            change.is_available
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+    }
+
+    Ok(changes)
+}
+
+fn change(
+    catalog_item_id: Uuid,
+    change_type: &str,
+    line: &CatalogRow,
+    inventory_id: Option<Uuid>,
+    old_price: Option<BigDecimal>,
+    was_available: Option<bool>,
+) -> SupplierCatalogChange {
+    SupplierCatalogChange {
+        id: Uuid::nil(),
+        catalog_item_id,
+        change_type: change_type.to_string(),
+        sku: line.sku.clone(),
+        description: line.description.clone(),
+        inventory_id,
+        price_change_percent: old_price
+            .as_ref()
+            .and_then(|old| percent_change(old, &line.price)),
+        old_price,
+        new_price: Some(line.price.clone()),
+        was_available,
+        is_available: Some(line.is_available),
+    }
+}
+
+/// Price rises at or above the threshold on items we buy.
+pub fn increases<'a>(
+    changes: &'a [SupplierCatalogChange],
+    threshold: &BigDecimal,
+) -> Vec<&'a SupplierCatalogChange> {
+    changes
+        .iter()
+        .filter(|c| c.inventory_id.is_some())
+        .filter(|c| {
+            c.price_change_percent
+                .as_ref()
+                .is_some_and(|p| p >= threshold)
+        })
+        .collect()
+}
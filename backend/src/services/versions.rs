@@ -0,0 +1,444 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextRequest, NextResolve,
+    ResolveInfo,
+};
+use async_graphql::{
+    Context, ObjectType, Request, Response, Schema, ServerError, ServerResult, SubscriptionType,
+    Value,
+};
+use axum::http::{HeaderMap, HeaderValue};
+use chrono::{NaiveDate, NaiveTime};
+
+/// A version of the public GraphQL API. Both are served from the same
+/// resolvers; v2 leaves out everything `DEPRECATIONS` removes in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    /// Where the version is served.
+    pub fn path(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/graphql",
+            ApiVersion::V2 => "/graphql/v2",
+        }
+    }
+
+    /// Subscription endpoint for the version.
+    pub fn ws_path(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/ws",
+            ApiVersion::V2 => "/ws/v2",
+        }
+    }
+
+    /// When the version was deprecated, and when it stops being served.
+    /// Clients of a deprecated version get `Deprecation`, `Sunset` and
+    /// successor `Link` headers on every response.
+    pub fn timeline(self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        match self {
+            ApiVersion::V1 => (
+                NaiveDate::from_ymd_opt(2026, 11, 1),
+                NaiveDate::from_ymd_opt(2027, 5, 1),
+            ),
+            ApiVersion::V2 => (None, None),
+        }
+    }
+
+    /// The version that replaces this one, if any.
+    pub fn successor(self) -> Option<ApiVersion> {
+        match self {
+            ApiVersion::V1 => Some(ApiVersion::V2),
+            ApiVersion::V2 => None,
+        }
+    }
+}
+
+/// A field or input field on its way out: still answered by the versions
+/// before `removed_in`, gone from that version on.
+pub struct Deprecation {
+    /// "Type.field" as in the schema (e.g. "RecipeTemplate.ingredientTemplate")
+    pub field: &'static str,
+    /// What to use instead
+    pub reason: &'static str,
+    pub removed_in: ApiVersion,
+}
+
+/// Every breaking change between versions. A field listed here also needs
+/// `#[graphql(deprecation = "...", visible = "crate::services::versions::before_v2")]`
+/// so it is marked deprecated in v1 and hidden from v2's schema. Input
+/// fields are checked by their resolvers with `deprecated_input`.
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        field: "RecipeTemplate.ingredientTemplate",
+        reason: "Use ingredients",
+        removed_in: ApiVersion::V2,
+    },
+    Deprecation {
+        field: "CreateRecipeTemplateInput.ingredientTemplate",
+        reason: "Use ingredients",
+        removed_in: ApiVersion::V2,
+    },
+    Deprecation {
+        field: "UpdateRecipeTemplateInput.ingredientTemplate",
+        reason: "Use ingredients",
+        removed_in: ApiVersion::V2,
+    },
+    Deprecation {
+        field: "CreateSaleInput.discountAmount",
+        reason: "Use discounts",
+        removed_in: ApiVersion::V2,
+    },
+];
+
+/// Fields `version` no longer has.
+pub fn removed_in(version: ApiVersion) -> impl Iterator<Item = &'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .filter(move |deprecation| deprecation.removed_in <= version)
+}
+
+/// Schema visibility for fields removed in v2.
+pub fn before_v2(ctx: &Context<'_>) -> bool {
+    ctx.data_opt::<ApiVersion>()
+        .is_none_or(|version| *version < ApiVersion::V2)
+}
+
+/// Deprecated fields a request used, collected while it runs.
+#[derive(Clone, Default)]
+pub struct DeprecationLog(Arc<Mutex<BTreeSet<&'static str>>>);
+
+impl DeprecationLog {
+    fn note(&self, deprecation: &'static Deprecation) {
+        if let Ok(mut used) = self.0.lock() {
+            used.insert(deprecation.field);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().map_or(true, |used| used.is_empty())
+    }
+
+    fn fields(&self) -> Vec<&'static str> {
+        self.0
+            .lock()
+            .map(|used| used.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Check a deprecated input field the caller gave: an error in versions
+/// that removed it, noted for the deprecation report otherwise.
+pub fn deprecated_input(ctx: &Context<'_>, field: &str) -> async_graphql::Result<()> {
+    let Some(deprecation) = DEPRECATIONS.iter().find(|d| d.field == field) else {
+        return Ok(());
+    };
+
+    let version = ctx
+        .data_opt::<ApiVersion>()
+        .copied()
+        .unwrap_or(ApiVersion::V1);
+    if version >= deprecation.removed_in {
+        return Err(removed_error(deprecation, version).into());
+    }
+
+    if let Some(log) = ctx.data_opt::<DeprecationLog>() {
+        log.note(deprecation);
+    }
+    Ok(())
+}
+
+fn removed_error(deprecation: &Deprecation, version: ApiVersion) -> String {
+    format!(
+        "{} is not available in {}: {}",
+        deprecation.field,
+        version.as_str(),
+        deprecation.reason
+    )
+}
+
+/// `Deprecation`, `Sunset` and `Link` response headers (RFC 9745, RFC 8594)
+/// for clients of a deprecated version.
+pub fn headers(version: ApiVersion) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let (deprecated_on, sunset_on) = version.timeline();
+
+    if let Some(date) = deprecated_on {
+        let timestamp = date.and_time(NaiveTime::MIN).and_utc().timestamp();
+        if let Ok(value) = HeaderValue::from_str(&format!("@{}", timestamp)) {
+            headers.insert("deprecation", value);
+        }
+    }
+    if let Some(date) = sunset_on {
+        let http_date = date
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        if let Ok(value) = HeaderValue::from_str(&http_date) {
+            headers.insert("sunset", value);
+        }
+    }
+    if deprecated_on.is_some()
+        && let Some(successor) = version.successor()
+        && let Ok(value) = HeaderValue::from_str(&format!(
+            "<{}>; rel=\"successor-version\"",
+            successor.path()
+        ))
+    {
+        headers.insert("link", value);
+    }
+
+    headers
+}
+
+/// Schema extension that enforces `DEPRECATIONS` for the schema's
+/// `ApiVersion`. Removed fields fail; deprecated ones still answer and are
+/// listed in the response's `extensions.deprecations`, and the usage
+/// extension counts the call against them.
+pub struct Versioning;
+
+impl ExtensionFactory for Versioning {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(VersioningExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct VersioningExtension {
+    log: DeprecationLog,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for VersioningExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let response = next.run(ctx).await;
+
+        let used = self.log.fields();
+        if used.is_empty() {
+            return response;
+        }
+
+        let deprecations = used
+            .into_iter()
+            .filter_map(|field| DEPRECATIONS.iter().find(|d| d.field == field))
+            .map(|deprecation| {
+                let mut entry = async_graphql::indexmap::IndexMap::new();
+                entry.insert(
+                    async_graphql::Name::new("field"),
+                    Value::String(deprecation.field.to_string()),
+                );
+                entry.insert(
+                    async_graphql::Name::new("reason"),
+                    Value::String(deprecation.reason.to_string()),
+                );
+                entry.insert(
+                    async_graphql::Name::new("removedIn"),
+                    Value::String(deprecation.removed_in.as_str().to_string()),
+                );
+                Value::Object(entry)
+            })
+            .collect();
+
+        response.extension("deprecations", Value::List(deprecations))
+    }
+
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        // Resolvers note deprecated input fields in the same log
+        next.run(ctx, request.data(self.log.clone())).await
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        let deprecation = DEPRECATIONS.iter().find(|d| {
+            d.field
+                .split_once('.')
+                .is_some_and(|(ty, field)| field == info.name && ty == info.parent_type)
+        });
+
+        if let Some(deprecation) = deprecation {
+            let version = ctx
+                .data_opt::<ApiVersion>()
+                .copied()
+                .unwrap_or(ApiVersion::V1);
+            if version >= deprecation.removed_in {
+                return Err(ServerError::new(removed_error(deprecation, version), None));
+            }
+            self.log.note(deprecation);
+        }
+
+        next.run(ctx, info).await
+    }
+}
+
+/// Every type the schema shows a request for `version`, with the names of
+/// its fields, input fields and enum values.
+async fn visible<Query, Mutation, Subscription>(
+    schema: &Schema<Query, Mutation, Subscription>,
+    version: ApiVersion,
+) -> BTreeMap<String, BTreeSet<String>>
+where
+    Query: ObjectType + 'static,
+    Mutation: ObjectType + 'static,
+    Subscription: SubscriptionType + 'static,
+{
+    let response = schema
+        .execute(
+            Request::new(
+                "{ __schema { types { name \
+                    fields(includeDeprecated: true) { name } \
+                    inputFields(includeDeprecated: true) { name } \
+                    enumValues(includeDeprecated: true) { name } } } }",
+            )
+            .data(version),
+        )
+        .await;
+    let types = response.data.into_json().unwrap_or_default()["__schema"]["types"].take();
+
+    let name = |value: &serde_json::Value| value["name"].as_str().unwrap_or_default().to_string();
+    types
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|ty| {
+            let members = ["fields", "inputFields", "enumValues"]
+                .iter()
+                .filter_map(|kind| ty[kind].as_array())
+                .flatten()
+                .map(name)
+                .collect();
+            (name(ty), members)
+        })
+        .collect()
+}
+
+/// The SDL of `schema` as `version`'s clients see it. `Schema::sdl` ignores
+/// `visible`, so the types, fields, input fields and enum values that
+/// introspection shows v1 but not `version` are cut out of it. The schema
+/// is built without an `ApiVersion`; each introspection supplies one.
+pub async fn sdl<Query, Mutation, Subscription>(
+    schema: &Schema<Query, Mutation, Subscription>,
+    version: ApiVersion,
+) -> String
+where
+    Query: ObjectType + 'static,
+    Mutation: ObjectType + 'static,
+    Subscription: SubscriptionType + 'static,
+{
+    let sdl = schema.sdl();
+    if version == ApiVersion::V1 {
+        return sdl;
+    }
+
+    // Type name to its hidden members, or `None` when the whole type is
+    let shown = visible(schema, version).await;
+    let hidden: BTreeMap<String, Option<BTreeSet<String>>> = visible(schema, ApiVersion::V1)
+        .await
+        .into_iter()
+        .filter_map(|(ty, members)| match shown.get(&ty) {
+            None => Some((ty, None)),
+            Some(kept) => {
+                let gone: BTreeSet<String> = members.difference(kept).cloned().collect();
+                (!gone.is_empty()).then_some((ty, Some(gone)))
+            }
+        })
+        .collect();
+
+    let lines: Vec<&str> = sdl.lines().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].is_empty() {
+            out.push(lines[i]);
+            i += 1;
+            continue;
+        }
+
+        // One definition: its description, header and any `{ ... }` body
+        let start = i;
+        i = skip_description(&lines, i, "");
+        let header = i;
+        if lines[i].ends_with('{') || lines[i].ends_with('(') {
+            while i + 1 < lines.len() && !lines[i].starts_with(['}', ')']) {
+                i += 1;
+            }
+        }
+        i += 1;
+
+        let name = lines[header]
+            .split([' ', '{', '('])
+            .nth(1)
+            .unwrap_or_default();
+        match hidden.get(name) {
+            // Gone, with the blank line after it
+            Some(None) => {
+                if lines.get(i).is_some_and(|line| line.is_empty()) {
+                    i += 1;
+                }
+            }
+            Some(Some(members)) => {
+                out.extend(&lines[start..=header]);
+                let mut j = header + 1;
+                while j < i - 1 {
+                    let member = j;
+                    j = skip_description(&lines, j, "\t");
+                    let field = lines[j];
+                    if field.ends_with('(') {
+                        while !lines[j].starts_with("\t)") {
+                            j += 1;
+                        }
+                    }
+                    j += 1;
+
+                    let name = field.trim_start().split(['(', ':', ' ']).next();
+                    if !name.is_some_and(|name| members.contains(name)) {
+                        out.extend(&lines[member..j]);
+                    }
+                }
+                out.push(lines[i - 1]);
+            }
+            None => out.extend(&lines[start..i]),
+        }
+    }
+
+    let mut sdl = out.join("\n");
+    sdl.push('\n');
+    sdl
+}
+
+/// The line after the `"""` description starting at `lines[i]` (indented by
+/// `indent`), or `i` when there's none.
+fn skip_description(lines: &[&str], mut i: usize, indent: &str) -> usize {
+    let quotes = format!("{}\"\"\"", indent);
+    if lines[i] != quotes {
+        return i;
+    }
+    i += 1;
+    while i < lines.len() && lines[i] != quotes {
+        i += 1;
+    }
+    i + 1
+}
@@ -0,0 +1,91 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Outcome of trying to put a batch into a vessel.
+pub enum Claim {
+    Claimed {
+        vessel_name: String,
+    },
+    /// The vessel can't take the batch; the message says why
+    Unavailable(String),
+}
+
+/// Put a batch into a vessel and mark the vessel in use.
+///
+/// The vessel row is locked so two batches can't claim it at once; the
+/// partial unique index on production_batches backs this up.
+pub async fn claim(
+    conn: &mut PgConnection,
+    vessel_id: Uuid,
+    batch_id: Uuid,
+) -> Result<Claim, sqlx::Error> {
+    let vessel = sqlx::query!(
+        "SELECT name, status FROM vessels WHERE id = $1 AND is_active = true FOR UPDATE",
+        vessel_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(vessel) = vessel else {
+        return Ok(Claim::Unavailable(
+            "Vessel not found or is inactive".to_string(),
+        ));
+    };
+
+    if vessel.status != "available" {
+        let occupant = sqlx::query_scalar!(
+            "SELECT batch_number FROM production_batches WHERE vessel_id = $1 AND status = 'in_progress'",
+            vessel_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        return Ok(Claim::Unavailable(match occupant {
+            Some(batch_number) => format!(
+                "Vessel {} is occupied by batch {}",
+                vessel.name, batch_number
+            ),
+            None => format!(
+                "Vessel {} is not available ({})",
+                vessel.name,
+                vessel.status.replace('_', " ")
+            ),
+        }));
+    }
+
+    sqlx::query!(
+        "UPDATE production_batches SET vessel_id = $1, updated_at = NOW() WHERE id = $2",
+        vessel_id,
+        batch_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE vessels SET status = 'in_use', updated_at = NOW() WHERE id = $1",
+        vessel_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Claim::Claimed {
+        vessel_name: vessel.name,
+    })
+}
+
+/// Free the vessel a batch is in; it needs cleaning before the next batch.
+pub async fn release(conn: &mut PgConnection, batch_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE vessels
+        SET status = 'cleaning', updated_at = NOW()
+        WHERE status = 'in_use'
+            AND id = (SELECT vessel_id FROM production_batches WHERE id = $1)
+        "#,
+        batch_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
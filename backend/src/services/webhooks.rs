@@ -0,0 +1,90 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::models::{WebhookDelivery, WebhookSubscription};
+use crate::services::events;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shortest signing secret accepted from a caller.
+pub const MIN_SECRET_LENGTH: usize = 16;
+
+/// Integration secret holding a subscription's signing secret.
+pub fn secret_name(subscription_id: Uuid) -> String {
+    format!("webhook_signing_{}", subscription_id.simple())
+}
+
+/// A new random signing secret.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Hex HMAC-SHA256 of `"{timestamp}.{body}"`, sent as
+/// `X-Webhook-Signature: sha256=<hex>`.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Why a URL can't take deliveries, if it can't.
+pub fn check_url(url: &str) -> Option<String> {
+    match reqwest::Url::parse(url.trim()) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => None,
+        Ok(_) => Some("Webhook URL must be http or https".to_string()),
+        Err(e) => Some(format!("Invalid webhook URL: {}", e)),
+    }
+}
+
+/// Why an event list can't be used, if it can't.
+pub fn check_event_types(event_types: &[String]) -> Option<String> {
+    if event_types.is_empty() {
+        return Some("Choose at least one event".to_string());
+    }
+    events::validate_event_types(event_types).err()
+}
+
+/// A subscription by ID.
+pub async fn subscription<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<WebhookSubscription>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+        SELECT id, url, event_types, description, is_active, created_at, updated_at
+        FROM webhook_subscriptions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// A delivery by ID.
+pub async fn delivery<'e>(
+    executor: impl PgExecutor<'e>,
+    id: Uuid,
+) -> Result<Option<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT d.id, d.subscription_id, e.event_type, e.payload, d.status, d.attempts,
+            d.next_attempt_at, d.last_status_code, d.last_error, d.delivered_at, d.created_at
+        FROM webhook_deliveries d
+        JOIN domain_events e ON e.id = d.event_id
+        WHERE d.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
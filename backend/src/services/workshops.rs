@@ -0,0 +1,164 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::models::{MutationError, Rejection, WorkshopKitItemInput};
+use crate::services::lots;
+
+pub const STATUSES: [&str; 3] = ["scheduled", "completed", "cancelled"];
+
+/// Check that kit items are listed once each, exist, and have positive
+/// quantities. Returns the reason if the kit can't be used.
+pub async fn validate_kit(
+    conn: &mut PgConnection,
+    kit: &[WorkshopKitItemInput],
+) -> Result<Result<(), String>, sqlx::Error> {
+    for (index, item) in kit.iter().enumerate() {
+        if item.quantity_per_attendee <= BigDecimal::from(0) {
+            return Ok(Err("All kit quantities must be greater than 0".to_string()));
+        }
+
+        if kit[..index]
+            .iter()
+            .any(|k| k.inventory_id == item.inventory_id)
+        {
+            return Ok(Err(format!(
+                "Kit item {} is listed more than once",
+                item.inventory_id
+            )));
+        }
+
+        let exists = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM inventory WHERE id = $1 AND is_active = true) AS \"exists!\"",
+            item.inventory_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !exists {
+            return Ok(Err(format!(
+                "Kit item with ID {} not found or is inactive",
+                item.inventory_id
+            )));
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+/// Replace a workshop's materials kit.
+pub async fn replace_kit(
+    conn: &mut PgConnection,
+    workshop_id: Uuid,
+    kit: &[WorkshopKitItemInput],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM workshop_kit_items WHERE workshop_id = $1",
+        workshop_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for item in kit {
+        sqlx::query!(
+            r#"
+            INSERT INTO workshop_kit_items (workshop_id, inventory_id, quantity_per_attendee)
+            VALUES ($1, $2, $3)
+            "#,
+            workshop_id,
+            item.inventory_id,
+            item.quantity_per_attendee
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Draw one attendee's kit from inventory: check every item is in stock, then
+/// draw down stock and lots and log the usage. Returns the reason if an item
+/// is short, in which case nothing is consumed.
+pub async fn issue_kit(
+    conn: &mut PgConnection,
+    workshop_id: Uuid,
+    label: &str,
+    now: DateTime<Utc>,
+) -> Result<Result<usize, Rejection>, sqlx::Error> {
+    let items = sqlx::query!(
+        r#"
+        SELECT k.inventory_id, k.quantity_per_attendee, i.name, i.current_stock
+        FROM workshop_kit_items k
+        JOIN inventory i ON i.id = k.inventory_id
+        WHERE k.workshop_id = $1
+        FOR UPDATE OF i
+        "#,
+        workshop_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if let Some(short) = items
+        .iter()
+        .find(|item| item.current_stock < item.quantity_per_attendee)
+    {
+        return Ok(Err(Rejection::new(
+            format!(
+                "Insufficient stock for kit item {}: need {}, have {}",
+                short.name, short.quantity_per_attendee, short.current_stock
+            ),
+            MutationError::insufficient_stock(
+                short.inventory_id,
+                &short.name,
+                &short.quantity_per_attendee,
+                &short.current_stock,
+            ),
+        )));
+    }
+
+    for item in &items {
+        sqlx::query!(
+            "UPDATE inventory SET current_stock = current_stock - $1, updated_at = $2 WHERE id = $3",
+            item.quantity_per_attendee,
+            now,
+            item.inventory_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        lots::consume(&mut *conn, item.inventory_id, &item.quantity_per_attendee).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO inventory_logs (inventory_id, movement_type, quantity, reason, created_at)
+            VALUES ($1, 'workshop_kit', $2, $3, $4)
+            "#,
+            item.inventory_id,
+            -item.quantity_per_attendee.clone(), // Negative because it's consumption
+            label,
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(Ok(items.len()))
+}
+
+/// Next ticket number for the day (TKT-YYYYMMDD-NNN).
+pub async fn next_ticket_number(
+    conn: &mut PgConnection,
+    now: DateTime<Utc>,
+) -> Result<String, sqlx::Error> {
+    let date_str = now.format("%Y%m%d").to_string();
+
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM workshop_attendees WHERE ticket_number LIKE $1"#,
+        format!("TKT-{}-%", date_str)
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(format!("TKT-{}-{:03}", date_str, count + 1))
+}
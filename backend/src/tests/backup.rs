@@ -0,0 +1,51 @@
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::TestApp;
+use crate::services::backup;
+
+/// A restore brings back the number counters along with the rows they
+/// numbered.
+#[sqlx::test(migrations = false)]
+async fn restore_keeps_number_counters(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    app.mutate(
+        "createSale",
+        "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message } }",
+        json!({ "input": { "items": [{ "inventoryId": kraut, "quantity": "1", "unitPrice": "10.00" }] } }),
+    )
+    .await;
+    let counters = || async {
+        sqlx::query_as::<_, (String, String, i64)>(
+            "SELECT kind, scope, last_value FROM number_sequences ORDER BY kind, scope",
+        )
+        .fetch_all(&app.pool)
+        .await
+        .expect("counters are read")
+    };
+    let before = counters().await;
+    assert!(!before.is_empty());
+
+    let mut archive = Vec::new();
+    backup::backup(&app.pool, &mut archive)
+        .await
+        .expect("backup is written");
+    // Restored into a fresh database
+    sqlx::query("DELETE FROM number_sequences")
+        .execute(&app.pool)
+        .await
+        .expect("counters are cleared");
+    backup::restore(&app.pool, String::from_utf8(archive).unwrap())
+        .await
+        .expect("archive restores");
+
+    assert_eq!(counters().await, before);
+}
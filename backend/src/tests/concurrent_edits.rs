@@ -0,0 +1,52 @@
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::TestApp;
+
+const UPDATE_ITEM: &str = "mutation($input: UpdateInventoryItemInput!) { updateInventoryItem(input: $input) { success message conflict item { version } } }";
+
+/// Stock moving while an item is open for editing doesn't make the edit
+/// stale; someone else saving an edit first does.
+#[sqlx::test(migrations = false)]
+async fn only_edits_make_an_edit_stale(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let version: i32 = sqlx::query_scalar!("SELECT version FROM inventory WHERE id = $1", kraut)
+        .fetch_one(&app.pool)
+        .await
+        .expect("item exists");
+
+    // Sold while the edit form was open
+    app.mutate(
+        "createSale",
+        "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message } }",
+        json!({ "input": { "items": [{ "inventoryId": kraut, "quantity": "2", "unitPrice": "10.00" }] } }),
+    )
+    .await;
+
+    let saved = app
+        .mutate(
+            "updateInventoryItem",
+            UPDATE_ITEM,
+            json!({ "input": { "id": kraut, "reorderPoint": "4", "expectedVersion": version } }),
+        )
+        .await;
+    assert_eq!(saved["item"]["version"], json!(version + 1));
+
+    // A second form loaded before that save
+    let stale = app
+        .execute(
+            UPDATE_ITEM,
+            json!({ "input": { "id": kraut, "reorderPoint": "6", "expectedVersion": version } }),
+        )
+        .await;
+    assert_eq!(stale["updateInventoryItem"]["success"], json!(false));
+    assert_eq!(stale["updateInventoryItem"]["conflict"], json!(true));
+}
@@ -0,0 +1,82 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::{TestApp, id};
+
+/// Discontinuing a product flags the open customer orders still promising
+/// it; fulfilled and cancelled orders are left out.
+#[sqlx::test(migrations = false)]
+async fn discontinuation_flags_open_orders(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let customer = app
+        .mutate(
+            "createCustomer",
+            "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }",
+            json!({ "input": { "name": "Test Bistro" } }),
+        )
+        .await;
+    let bistro = id(&customer["customer"]["id"]);
+
+    let friday = Utc::now().date_naive() + Duration::days(3);
+    let mut orders = Vec::new();
+    for _ in 0..3 {
+        let order = app
+            .mutate(
+                "createOrder",
+                "mutation($input: CreateOrderInput!) { createOrder(input: $input) { success message order { id orderNumber } } }",
+                json!({ "input": {
+                    "customerId": bistro,
+                    "deliveryDate": friday,
+                    "items": [{ "inventoryId": kraut, "quantity": "2", "unitPrice": "9.00" }],
+                }}),
+            )
+            .await;
+        orders.push(order["order"].clone());
+    }
+    app.mutate(
+        "confirmOrder",
+        "mutation($id: UUID!) { confirmOrder(input: { orderId: $id }) { success message } }",
+        json!({ "id": orders[0]["id"] }),
+    )
+    .await;
+    app.mutate(
+        "cancelOrder",
+        "mutation($id: UUID!) { cancelOrder(input: { orderId: $id }) { success message } }",
+        json!({ "id": orders[2]["id"] }),
+    )
+    .await;
+
+    let discontinued = app
+        .mutate(
+            "discontinueProduct",
+            "mutation($id: UUID!) { discontinueProduct(input: { inventoryId: $id }) { success message report { orders } } }",
+            json!({ "id": kraut }),
+        )
+        .await;
+    let open: Vec<String> = orders[..2]
+        .iter()
+        .map(|order| {
+            format!(
+                "{} for Test Bistro on {}",
+                order["orderNumber"].as_str().unwrap(),
+                friday
+            )
+        })
+        .collect();
+    assert_eq!(discontinued["report"]["orders"], json!(open));
+    assert!(
+        discontinued["message"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("On open orders: {}", open.join(", ")))
+    );
+}
@@ -0,0 +1,123 @@
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+use super::support::{TestApp, dec};
+
+const CREATE_SALE: &str =
+    "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleId } }";
+
+/// Line discounts come off their line, whole-sale discounts and promo codes
+/// off what's left; the sale keeps each one and their total.
+#[sqlx::test(migrations = false)]
+async fn discounts_are_applied_and_recorded(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let kimchi = app
+        .inventory_item("Test Kimchi")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    app.mutate(
+        "createPromoCode",
+        "mutation($input: CreatePromoCodeInput!) { createPromoCode(input: $input) { success message } }",
+        json!({ "input": {
+            "code": "market5",
+            "discountType": "fixed",
+            "value": "5.00",
+            "maxUses": 1,
+        }}),
+    )
+    .await;
+
+    // Kraut 2 × 10.00 less 10% = 18.00, kimchi 1 × 12.00; 30.00 less 2.00
+    // by hand is 28.00, less 5.00 for the code is 23.00
+    let sale = app
+        .mutate(
+            "createSale",
+            CREATE_SALE,
+            json!({ "input": {
+                "items": [
+                    {
+                        "inventoryId": kraut,
+                        "quantity": "2",
+                        "unitPrice": "10.00",
+                        "discount": { "discountType": "percentage", "value": "10" },
+                    },
+                    { "inventoryId": kimchi, "quantity": "1", "unitPrice": "12.00" },
+                ],
+                "discounts": [{ "discountType": "fixed", "value": "2.00", "reason": "regular" }],
+                "promoCode": " Market5 ",
+            }}),
+        )
+        .await;
+
+    let details = app
+        .execute(
+            "query($id: UUID!) { sale(id: $id) { sale { subtotal discountAmount totalAmount } discounts { amount promoCode saleItemId } } }",
+            json!({ "id": sale["saleId"] }),
+        )
+        .await["sale"]
+        .clone();
+    let amount = |value: &Value| value.as_str().map(dec);
+    assert_eq!(amount(&details["sale"]["subtotal"]), Some(dec("32")));
+    assert_eq!(amount(&details["sale"]["discountAmount"]), Some(dec("9")));
+    assert_eq!(amount(&details["sale"]["totalAmount"]), Some(dec("23")));
+    let discounts = details["discounts"].as_array().expect("discounts");
+    assert_eq!(discounts.len(), 3);
+    assert!(discounts[0]["saleItemId"].is_string());
+    assert_eq!(discounts[2]["promoCode"], json!("MARKET5"));
+
+    // The code's one use is gone, and a discount can't exceed the sale
+    for input in [
+        json!({
+            "items": [{ "inventoryId": kimchi, "quantity": "1", "unitPrice": "12.00" }],
+            "promoCode": "MARKET5",
+        }),
+        json!({
+            "items": [{ "inventoryId": kimchi, "quantity": "1", "unitPrice": "12.00" }],
+            "discounts": [{ "discountType": "fixed", "value": "15.00" }],
+        }),
+    ] {
+        let result = app.execute(CREATE_SALE, json!({ "input": input })).await;
+        assert_eq!(result["createSale"]["success"], json!(false));
+    }
+    assert_eq!(app.stock(kimchi).await, dec("9"));
+
+    let today = chrono::Utc::now().date_naive();
+    let summary = app
+        .execute(
+            "query($start: NaiveDate!, $end: NaiveDate!) { revenueSummary(startDate: $start, endDate: $end) { discounts } discountSummary(startDate: $start, endDate: $end) { promoCode scope total } }",
+            json!({ "start": today, "end": today }),
+        )
+        .await;
+    assert_eq!(
+        amount(&summary["revenueSummary"]["discounts"]),
+        Some(dec("9"))
+    );
+    assert_eq!(
+        summary["discountSummary"]
+            .as_array()
+            .expect("summary")
+            .iter()
+            .map(|line| (
+                line["promoCode"].clone(),
+                line["scope"].clone(),
+                amount(&line["total"])
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (json!("MARKET5"), json!("order"), Some(dec("5"))),
+            (json!(null), json!("order"), Some(dec("2"))),
+            (json!(null), json!("line"), Some(dec("2"))),
+        ]
+    );
+}
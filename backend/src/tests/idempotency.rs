@@ -0,0 +1,51 @@
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::support::{TestApp, dec};
+use crate::services::auth::Identity;
+
+const CREATE_SALE: &str = "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleNumber } }";
+
+/// A retry with the same key and input replays the sale. The same key with
+/// other input, or from another caller, never gets that sale back.
+#[sqlx::test(migrations = false)]
+async fn idempotency_keys_belong_to_one_request(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let sale = |quantity: &str| {
+        json!({ "input": {
+            "idempotencyKey": "checkout-1",
+            "items": [{ "inventoryId": kraut, "quantity": quantity, "unitPrice": "10.00" }],
+        }})
+    };
+
+    let first = app.mutate("createSale", CREATE_SALE, sale("1")).await;
+    let retry = app.mutate("createSale", CREATE_SALE, sale("1")).await;
+    assert_eq!(retry["saleNumber"], first["saleNumber"]);
+
+    let changed = app.execute(CREATE_SALE, sale("2")).await;
+    assert_eq!(
+        changed["createSale"]["message"],
+        json!("Idempotency key checkout-1 was already used for a different createSale")
+    );
+
+    // A till signed in with its own API key, reusing the key
+    let till = Identity {
+        api_key_id: Some(Uuid::new_v4()),
+        ..app.owner.clone()
+    };
+    let other = app.execute_as(&till, CREATE_SALE, sale("1")).await;
+    assert!(other.errors.is_empty(), "{:?}", other.errors);
+    let other = other.data.into_json().unwrap();
+    assert_eq!(other["createSale"]["success"], json!(true));
+    assert_ne!(other["createSale"]["saleNumber"], first["saleNumber"]);
+    assert_eq!(app.stock(kraut).await, dec("8"));
+}
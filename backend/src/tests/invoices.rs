@@ -0,0 +1,138 @@
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+use super::support::{TestApp, dec, id};
+
+const CREATE_SALE: &str =
+    "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleId } }";
+const RECORD_PAYMENT: &str = "mutation($input: RecordPaymentInput!) { recordPayment(input: $input) { success message invoice { status balance paidOn } } }";
+const SALE: &str = "query($id: UUID!) { sale(id: $id) { sale { paymentStatus } invoice { id issuedOn dueOn amount status } } }";
+
+/// Invoiced sales fall due after the customer's terms, take part payments
+/// until they're settled, and show in the aging by how old they are.
+#[sqlx::test(migrations = false)]
+async fn invoices_are_paid_off_and_aged(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("20")
+        .create()
+        .await;
+    let customer = app
+        .mutate(
+            "createCustomer",
+            "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }",
+            json!({ "input": { "name": "Test Deli", "paymentTermsDays": 30 } }),
+        )
+        .await;
+    let deli = id(&customer["customer"]["id"]);
+
+    // Sold 45 days ago on net 30 (15 days overdue), and again today
+    let today = Utc::now().date_naive();
+    let mut invoices = Vec::new();
+    for (days_ago, quantity) in [(45, "10"), (0, "2")] {
+        let sale = app
+            .mutate(
+                "createSale",
+                CREATE_SALE,
+                json!({ "input": {
+                    "customerId": deli,
+                    "saleDate": Utc::now() - Duration::days(days_ago),
+                    "items": [{ "inventoryId": kraut, "quantity": quantity, "unitPrice": "10.00" }],
+                    "paymentMethod": "invoice",
+                }}),
+            )
+            .await;
+        let details = app.execute(SALE, json!({ "id": sale["saleId"] })).await["sale"].clone();
+        assert_eq!(details["sale"]["paymentStatus"], json!("pending"));
+        assert_eq!(details["invoice"]["status"], json!("open"));
+        let issued_on = today - Duration::days(days_ago);
+        assert_eq!(details["invoice"]["issuedOn"], json!(issued_on));
+        assert_eq!(
+            details["invoice"]["dueOn"],
+            json!(issued_on + Duration::days(30))
+        );
+        invoices.push((sale["saleId"].clone(), details["invoice"]["id"].clone()));
+    }
+
+    // Nobody to bill
+    let result = app
+        .execute(
+            CREATE_SALE,
+            json!({ "input": {
+                "items": [{ "inventoryId": kraut, "quantity": "1", "unitPrice": "10.00" }],
+                "paymentMethod": "invoice",
+            }}),
+        )
+        .await;
+    assert_eq!(result["createSale"]["success"], json!(false));
+
+    let amount = |value: &Value| value.as_str().map(dec);
+    let aging = app
+        .execute(
+            "{ arAging { days0To30 days31To60 total overdue customers { customerName invoices } } }",
+            json!({}),
+        )
+        .await["arAging"]
+        .clone();
+    assert_eq!(amount(&aging["days0To30"]), Some(dec("20")));
+    assert_eq!(amount(&aging["days31To60"]), Some(dec("100")));
+    assert_eq!(amount(&aging["total"]), Some(dec("120")));
+    assert_eq!(amount(&aging["overdue"]), Some(dec("100")));
+    assert_eq!(
+        aging["customers"],
+        json!([{ "customerName": "Test Deli", "invoices": 2 }])
+    );
+
+    // The old invoice in two parts; it can't be overpaid
+    let (sale_id, invoice_id) = invoices[0].clone();
+    let result = app
+        .execute(
+            RECORD_PAYMENT,
+            json!({ "input": { "invoiceId": invoice_id, "amount": "150.00" } }),
+        )
+        .await;
+    assert_eq!(result["recordPayment"]["success"], json!(false));
+
+    let part = app
+        .mutate(
+            "recordPayment",
+            RECORD_PAYMENT,
+            json!({ "input": {
+                "invoiceId": invoice_id,
+                "amount": "40.00",
+                "paymentMethod": "check",
+                "reference": "1042",
+            }}),
+        )
+        .await;
+    assert_eq!(part["invoice"]["status"], json!("partially_paid"));
+    assert_eq!(amount(&part["invoice"]["balance"]), Some(dec("60")));
+
+    let rest = app
+        .mutate(
+            "recordPayment",
+            RECORD_PAYMENT,
+            json!({ "input": { "invoiceId": invoice_id, "amount": "60.00" } }),
+        )
+        .await;
+    assert_eq!(rest["invoice"]["status"], json!("paid"));
+    assert_eq!(rest["invoice"]["paidOn"], json!(today));
+    let details = app.execute(SALE, json!({ "id": sale_id })).await["sale"].clone();
+    assert_eq!(details["sale"]["paymentStatus"], json!("completed"));
+
+    // Yesterday's aging still has the old invoice owing in full
+    let aging = app
+        .execute(
+            "query($asOf: NaiveDate) { now: arAging { total overdue } then: arAging(asOf: $asOf) { total } }",
+            json!({ "asOf": today - Duration::days(1) }),
+        )
+        .await;
+    assert_eq!(amount(&aging["now"]["total"]), Some(dec("20")));
+    assert_eq!(amount(&aging["now"]["overdue"]), Some(dec("0")));
+    assert_eq!(amount(&aging["then"]["total"]), Some(dec("100")));
+}
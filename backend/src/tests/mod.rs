@@ -0,0 +1,16 @@
+//! End-to-end tests of the GraphQL mutations. Each test gets its own
+//! database on the `DATABASE_URL` server (which needs rights to create
+//! databases), loaded from `init.sql`.
+
+mod backup;
+mod concurrent_edits;
+mod discontinuation;
+mod discounts;
+mod idempotency;
+mod invoices;
+mod numbering;
+mod orders;
+mod pricing;
+mod production_flow;
+mod support;
+mod webhooks;
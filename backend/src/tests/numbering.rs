@@ -0,0 +1,72 @@
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::TestApp;
+
+const CREATE_SALE: &str = "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleNumber } }";
+
+/// Sales made at the same moment each get their own number, in sequence.
+#[sqlx::test(migrations = false)]
+async fn concurrent_sales_get_distinct_numbers(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let sale = json!({ "input": {
+        "items": [{ "inventoryId": kraut, "quantity": "1", "unitPrice": "12.00" }],
+    }});
+
+    let (a, b, c, d) = tokio::join!(
+        app.mutate("createSale", CREATE_SALE, sale.clone()),
+        app.mutate("createSale", CREATE_SALE, sale.clone()),
+        app.mutate("createSale", CREATE_SALE, sale.clone()),
+        app.mutate("createSale", CREATE_SALE, sale.clone()),
+    );
+
+    let mut numbers: Vec<String> = [a, b, c, d]
+        .iter()
+        .map(|result| {
+            result["saleNumber"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    numbers.sort();
+    let today = chrono::Utc::now().format("%Y%m%d");
+    assert_eq!(
+        numbers,
+        (1..=4)
+            .map(|seq| format!("SALE-{}-{:03}", today, seq))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// A product code is stored upper case and can't be given to two items.
+#[sqlx::test(migrations = false)]
+async fn product_codes_are_unique(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    app.inventory_item("Test Kimchi")
+        .product_code("kim")
+        .create()
+        .await;
+
+    let result = app
+        .execute(
+            "mutation($input: CreateInventoryItemInput!) { createInventoryItem(input: $input) { success message } }",
+            json!({ "input": {
+                "name": "Test Kimchi Jar",
+                "category": "finished_product",
+                "unit": "jars",
+                "productCode": "KIM",
+            }}),
+        )
+        .await;
+    assert_eq!(result["createInventoryItem"]["success"], json!(false));
+}
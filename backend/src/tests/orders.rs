@@ -0,0 +1,298 @@
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::support::{TestApp, dec, id};
+
+const ORDER_FIELDS: &str =
+    "order { id orderNumber status totalAmount saleId items { quantity unitPrice } }";
+
+async fn reserved(app: &TestApp, inventory_id: Uuid) -> BigDecimal {
+    sqlx::query_scalar("SELECT reserved_stock FROM inventory WHERE id = $1")
+        .bind(inventory_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("item exists")
+}
+
+/// An order is taken as a draft, holds its stock once confirmed, and is sold
+/// and then invoiced when it's delivered.
+#[sqlx::test(migrations = false)]
+async fn orders_reserve_stock_until_fulfilled(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let customer = app
+        .mutate(
+            "createCustomer",
+            "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }",
+            json!({ "input": { "name": "Test Bistro", "paymentTermsDays": 15 } }),
+        )
+        .await;
+    let bistro = id(&customer["customer"]["id"]);
+    app.mutate(
+        "setCustomerPrice",
+        "mutation($input: SetCustomerPriceInput!) { setCustomerPrice(input: $input) { success message } }",
+        json!({ "input": { "customerId": bistro, "inventoryId": kraut, "unitPrice": "9.00" } }),
+    )
+    .await;
+
+    // Ordered Tuesday for Friday, then upped before confirming
+    let friday = Utc::now().date_naive() + Duration::days(3);
+    let mut orders = Vec::new();
+    for quantity in ["6", "4"] {
+        let order = app
+            .mutate(
+                "createOrder",
+                &format!(
+                    "mutation($input: CreateOrderInput!) {{ createOrder(input: $input) {{ success message {} }} }}",
+                    ORDER_FIELDS
+                ),
+                json!({ "input": {
+                    "customerId": bistro,
+                    "deliveryDate": friday,
+                    "items": [{ "inventoryId": kraut, "quantity": quantity }],
+                }}),
+            )
+            .await;
+        assert_eq!(order["order"]["status"], json!("draft"));
+        orders.push(order["order"]["id"].clone());
+    }
+    let (first, second) = (orders[0].clone(), orders[1].clone());
+    let updated = app
+        .mutate(
+            "updateOrder",
+            &format!(
+                "mutation($input: UpdateOrderInput!) {{ updateOrder(input: $input) {{ success message {} }} }}",
+                ORDER_FIELDS
+            ),
+            json!({ "input": {
+                "orderId": first,
+                "items": [{ "inventoryId": kraut, "quantity": "8" }],
+            }}),
+        )
+        .await;
+    assert_eq!(
+        updated["order"]["totalAmount"].as_str().map(dec),
+        Some(dec("72"))
+    );
+
+    let confirm = "mutation($id: UUID!) { confirmOrder(input: { orderId: $id }) { success message order { status } } }";
+    app.mutate("confirmOrder", confirm, json!({ "id": first }))
+        .await;
+    assert_eq!(reserved(&app, kraut).await, dec("8"));
+
+    // Only 2 left unreserved
+    let result = app.execute(confirm, json!({ "id": second })).await;
+    assert_eq!(result["confirmOrder"]["success"], json!(false));
+
+    let open = app
+        .execute(
+            "query($from: NaiveDate) { openOrders(deliveryFrom: $from) { id status } }",
+            json!({ "from": friday }),
+        )
+        .await;
+    assert_eq!(
+        open["openOrders"],
+        json!([
+            { "id": first, "status": "confirmed" },
+            { "id": second, "status": "draft" },
+        ])
+    );
+    app.mutate(
+        "cancelOrder",
+        "mutation($id: UUID!) { cancelOrder(input: { orderId: $id, reason: \"Closed for a private event\" }) { success message } }",
+        json!({ "id": second }),
+    )
+    .await;
+
+    // Delivered: sold out of stock, the reservation let go
+    let fulfill = format!(
+        "mutation($id: UUID!) {{ fulfillOrder(input: {{ orderId: $id }}) {{ success message {} }} }}",
+        ORDER_FIELDS
+    );
+    let fulfilled = app
+        .mutate("fulfillOrder", &fulfill, json!({ "id": first }))
+        .await;
+    assert_eq!(fulfilled["order"]["status"], json!("fulfilled"));
+    assert_eq!(app.stock(kraut).await, dec("2"));
+    assert_eq!(reserved(&app, kraut).await, dec("0"));
+    let result = app.execute(&fulfill, json!({ "id": first })).await;
+    assert_eq!(result["fulfillOrder"]["success"], json!(false));
+
+    let invoiced = app
+        .mutate(
+            "invoiceOrder",
+            "mutation($id: UUID!) { invoiceOrder(input: { orderId: $id }) { success message order { status } } }",
+            json!({ "id": first }),
+        )
+        .await;
+    assert_eq!(invoiced["order"]["status"], json!("invoiced"));
+
+    let sale = app
+        .execute(
+            "query($id: UUID!) { sale(id: $id) { sale { paymentMethod paymentStatus totalAmount } invoice { amount dueOn } } }",
+            json!({ "id": fulfilled["order"]["saleId"] }),
+        )
+        .await["sale"]
+        .clone();
+    assert_eq!(sale["sale"]["paymentMethod"], json!("invoice"));
+    assert_eq!(sale["sale"]["paymentStatus"], json!("pending"));
+    assert_eq!(sale["invoice"]["amount"].as_str().map(dec), Some(dec("72")));
+    assert_eq!(
+        sale["invoice"]["dueOn"],
+        json!(Utc::now().date_naive() + Duration::days(15))
+    );
+}
+
+/// Discontinued and inactive items can't be ordered, and an order taken
+/// before its item was discontinued can't be confirmed.
+#[sqlx::test(migrations = false)]
+async fn orders_refuse_items_no_longer_sold(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let old_kraut = app
+        .inventory_item("Old Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let customer = app
+        .mutate(
+            "createCustomer",
+            "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }",
+            json!({ "input": { "name": "Test Bistro" } }),
+        )
+        .await;
+    let bistro = id(&customer["customer"]["id"]);
+
+    let create = format!(
+        "mutation($input: CreateOrderInput!) {{ createOrder(input: $input) {{ success message {} }} }}",
+        ORDER_FIELDS
+    );
+    let order_for = |inventory_id: Uuid| {
+        json!({ "input": {
+            "customerId": bistro,
+            "deliveryDate": Utc::now().date_naive() + Duration::days(3),
+            "items": [{ "inventoryId": inventory_id, "quantity": "2", "unitPrice": "9.00" }],
+        }})
+    };
+    let order = app.mutate("createOrder", &create, order_for(kraut)).await;
+
+    app.mutate(
+        "discontinueProduct",
+        "mutation($id: UUID!) { discontinueProduct(input: { inventoryId: $id }) { success message } }",
+        json!({ "id": kraut }),
+    )
+    .await;
+    app.mutate(
+        "updateInventoryItem",
+        "mutation($id: UUID!) { updateInventoryItem(input: { id: $id, isActive: false }) { success message } }",
+        json!({ "id": old_kraut }),
+    )
+    .await;
+
+    for (inventory_id, message) in [
+        (kraut, "Test Kraut has been discontinued"),
+        (old_kraut, "Old Kraut is inactive"),
+    ] {
+        let result = app.execute(&create, order_for(inventory_id)).await;
+        assert_eq!(result["createOrder"]["message"], json!(message));
+    }
+
+    let result = app
+        .execute(
+            "mutation($id: UUID!) { confirmOrder(input: { orderId: $id }) { success message } }",
+            json!({ "id": order["order"]["id"] }),
+        )
+        .await;
+    assert_eq!(
+        result["confirmOrder"]["message"],
+        json!("Test Kraut has been discontinued")
+    );
+    assert_eq!(reserved(&app, kraut).await, dec("0"));
+}
+
+/// Stock a confirmed order reserved can't be sold to someone else, so the
+/// order can still be fulfilled.
+#[sqlx::test(migrations = false)]
+async fn sales_leave_reserved_stock_alone(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let customer = app
+        .mutate(
+            "createCustomer",
+            "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }",
+            json!({ "input": { "name": "Test Bistro" } }),
+        )
+        .await;
+    let order = app
+        .mutate(
+            "createOrder",
+            &format!(
+                "mutation($input: CreateOrderInput!) {{ createOrder(input: $input) {{ success message {} }} }}",
+                ORDER_FIELDS
+            ),
+            json!({ "input": {
+                "customerId": customer["customer"]["id"],
+                "deliveryDate": Utc::now().date_naive() + Duration::days(3),
+                "items": [{ "inventoryId": kraut, "quantity": "10", "unitPrice": "9.00" }],
+            }}),
+        )
+        .await;
+    let order_id = order["order"]["id"].clone();
+    app.mutate(
+        "confirmOrder",
+        "mutation($id: UUID!) { confirmOrder(input: { orderId: $id }) { success message } }",
+        json!({ "id": order_id }),
+    )
+    .await;
+
+    // A walk-in wants a jar of the reserved stock
+    let result = app
+        .execute(
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message } }",
+            json!({ "input": { "items": [{ "inventoryId": kraut, "quantity": "1", "unitPrice": "10.00" }] } }),
+        )
+        .await;
+    assert_eq!(result["createSale"]["success"], json!(false));
+    assert!(
+        result["createSale"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Insufficient stock")
+    );
+    assert_eq!(app.stock(kraut).await, dec("10"));
+
+    app.mutate(
+        "fulfillOrder",
+        "mutation($id: UUID!) { fulfillOrder(input: { orderId: $id }) { success message } }",
+        json!({ "id": order_id }),
+    )
+    .await;
+    assert_eq!(app.stock(kraut).await, dec("0"));
+    assert_eq!(reserved(&app, kraut).await, dec("0"));
+}
@@ -0,0 +1,119 @@
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::{TestApp, dec, id};
+
+const SET_LIST_PRICE: &str =
+    "mutation($input: SetListPriceInput!) { setListPrice(input: $input) { success message } }";
+const SET_CUSTOMER_PRICE: &str = "mutation($input: SetCustomerPriceInput!) { setCustomerPrice(input: $input) { success message } }";
+const CREATE_CUSTOMER: &str = "mutation($input: CreateCustomerInput!) { createCustomer(input: $input) { success message customer { id } } }";
+const PRICE_FOR: &str = "query($customerId: UUID, $inventoryId: UUID!) { priceFor(customerId: $customerId, inventoryId: $inventoryId) { unitPrice source priceList } }";
+
+/// Items sold without a price take the customer's own price, else their
+/// type's list price, else the retail price; with none of these the sale is
+/// refused.
+#[sqlx::test(migrations = false)]
+async fn sales_default_to_the_customers_price(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    for (customer_type, unit_price) in [("retail", "12.00"), ("Wholesale", "9.00")] {
+        app.mutate(
+            "setListPrice",
+            SET_LIST_PRICE,
+            json!({ "input": {
+                "customerType": customer_type,
+                "inventoryId": kraut,
+                "unitPrice": unit_price,
+            }}),
+        )
+        .await;
+    }
+
+    let mut customers = Vec::new();
+    for name in ["Test Deli", "Test Bistro"] {
+        let result = app
+            .mutate(
+                "createCustomer",
+                CREATE_CUSTOMER,
+                json!({ "input": { "name": name, "customerType": "wholesale" } }),
+            )
+            .await;
+        customers.push(id(&result["customer"]["id"]));
+    }
+    let (deli, bistro) = (customers[0], customers[1]);
+    app.mutate(
+        "setCustomerPrice",
+        SET_CUSTOMER_PRICE,
+        json!({ "input": { "customerId": bistro, "inventoryId": kraut, "unitPrice": "8.50" } }),
+    )
+    .await;
+
+    for (customer, unit_price, source, price_list) in [
+        (None, "12.00", "price_list", json!("retail")),
+        (Some(deli), "9.00", "price_list", json!("wholesale")),
+        (Some(bistro), "8.50", "customer", json!(null)),
+    ] {
+        let price = app
+            .execute(
+                PRICE_FOR,
+                json!({ "customerId": customer, "inventoryId": kraut }),
+            )
+            .await["priceFor"]
+            .clone();
+        assert_eq!(price["unitPrice"].as_str().map(dec), Some(dec(unit_price)));
+        assert_eq!(price["source"], json!(source));
+        assert_eq!(price["priceList"], price_list);
+    }
+
+    let sale = app
+        .mutate(
+            "createSale",
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleId } }",
+            json!({ "input": {
+                "customerId": bistro,
+                "items": [{ "inventoryId": kraut, "quantity": "2" }],
+            }}),
+        )
+        .await;
+    let details = app
+        .execute(
+            "query($id: UUID!) { sale(id: $id) { sale { subtotal } items { unitPrice } } }",
+            json!({ "id": sale["saleId"] }),
+        )
+        .await["sale"]
+        .clone();
+    assert_eq!(
+        details["items"][0]["unitPrice"].as_str().map(dec),
+        Some(dec("8.50"))
+    );
+    assert_eq!(
+        details["sale"]["subtotal"].as_str().map(dec),
+        Some(dec("17.00"))
+    );
+
+    let unpriced = app
+        .inventory_item("Test Kimchi")
+        .category("finished_product")
+        .unit("jars")
+        .stock("10")
+        .create()
+        .await;
+    let result = app
+        .execute(
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message } }",
+            json!({ "input": {
+                "customerId": deli,
+                "items": [{ "inventoryId": unpriced, "quantity": "1" }],
+            }}),
+        )
+        .await;
+    assert_eq!(result["createSale"]["success"], json!(false));
+    assert_eq!(app.stock(unpriced).await, dec("10"));
+}
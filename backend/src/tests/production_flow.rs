@@ -0,0 +1,188 @@
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::{TestApp, dec, id};
+
+/// Buy ingredients, make a batch from a recipe, complete it and sell some
+/// of it, checking stock at each step.
+#[sqlx::test(migrations = false)]
+async fn purchase_produce_complete_sell(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let supplier = app
+        .supplier("Test Farm")
+        .email("farm@example.com")
+        .create()
+        .await;
+    let cabbage = app
+        .inventory_item("Test Cabbage")
+        .category("Vegetable")
+        .cost("0.004")
+        .supplier(supplier)
+        .create()
+        .await;
+    let salt = app
+        .inventory_item("Test Salt")
+        .category("mineral")
+        .supplier(supplier)
+        .create()
+        .await;
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .create()
+        .await;
+
+    // Purchase
+    app.mutate(
+        "createPurchase",
+        "mutation($input: CreatePurchaseInput!) { createPurchase(input: $input) { success message } }",
+        json!({ "input": {
+            "supplierId": supplier,
+            "items": [
+                { "inventoryId": cabbage, "quantity": "2000", "unitCost": "0.005" },
+                { "inventoryId": salt, "quantity": "500", "unitCost": "0.002" },
+            ],
+        }}),
+    )
+    .await;
+    assert_eq!(app.stock(cabbage).await, dec("2000"));
+    assert_eq!(app.stock(salt).await, dec("500"));
+
+    // Produce from a recipe
+    let recipe = app
+        .recipe("Test Kraut", kraut)
+        .batch_size("8", "jars")
+        .ingredient(cabbage, "1500")
+        .ingredient(salt, "30")
+        .create()
+        .await;
+    let batch = app
+        .mutate(
+            "createProductionBatch",
+            "mutation($input: CreateProductionBatchInput!) { createProductionBatch(input: $input) { success message batchId } }",
+            json!({ "input": {
+                "productInventoryId": kraut,
+                "recipeTemplateId": recipe,
+                "batchSize": "8",
+                "unit": "jars",
+                "ingredients": [
+                    { "inventoryId": cabbage, "quantityUsed": "1500" },
+                    { "inventoryId": salt, "quantityUsed": "30" },
+                ],
+            }}),
+        )
+        .await;
+    let batch = id(&batch["batchId"]);
+    assert_eq!(app.stock(cabbage).await, dec("500"));
+    assert_eq!(app.stock(salt).await, dec("470"));
+    assert_eq!(app.stock(kraut).await, dec("0"));
+
+    // Complete
+    app.mutate(
+        "completeProductionBatch",
+        "mutation($input: CompleteProductionBatchInput!) { completeProductionBatch(input: $input) { success message } }",
+        json!({ "input": { "batchId": batch, "actualYield": "8" } }),
+    )
+    .await;
+    assert_eq!(app.stock(kraut).await, dec("8"));
+    let status = sqlx::query_scalar!("SELECT status FROM production_batches WHERE id = $1", batch)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "completed");
+
+    // Sell
+    let sale = app
+        .mutate(
+            "createSale",
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleId } }",
+            json!({ "input": {
+                "items": [{ "inventoryId": kraut, "quantity": "3", "unitPrice": "12.00" }],
+                "paymentMethod": "cash",
+            }}),
+        )
+        .await;
+    let sale = id(&sale["saleId"]);
+    assert_eq!(app.stock(kraut).await, dec("5"));
+    let total = sqlx::query_scalar!("SELECT total_amount FROM sales WHERE id = $1", sale)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(total, dec("36"));
+}
+
+/// A sale for more than is on hand is turned away and leaves stock alone.
+#[sqlx::test(migrations = false)]
+async fn sale_beyond_stock_is_rejected(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("2")
+        .create()
+        .await;
+
+    let result = app
+        .execute(
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message } }",
+            json!({ "input": {
+                "items": [{ "inventoryId": kraut, "quantity": "3", "unitPrice": "12.00" }],
+            }}),
+        )
+        .await;
+    assert_eq!(result["createSale"]["success"], json!(false));
+    assert_eq!(app.stock(kraut).await, dec("2"));
+}
+
+/// A sale can be found again by its ID or its printed number, with its
+/// items named.
+#[sqlx::test(migrations = false)]
+async fn sale_is_found_by_id_and_number(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+
+    let kraut = app
+        .inventory_item("Test Kraut")
+        .category("finished_product")
+        .unit("jars")
+        .stock("5")
+        .create()
+        .await;
+    let sale = app
+        .mutate(
+            "createSale",
+            "mutation($input: CreateSaleInput!) { createSale(input: $input) { success message saleId saleNumber } }",
+            json!({ "input": {
+                "items": [{ "inventoryId": kraut, "quantity": "2", "unitPrice": "12.00" }],
+            }}),
+        )
+        .await;
+    let sale_number = sale["saleNumber"].as_str().unwrap_or_default();
+
+    let found = app
+        .execute(
+            "query($id: UUID!, $number: String!) {
+                byId: sale(id: $id) { sale { saleNumber } items { itemName unit quantity } }
+                byNumber: saleByNumber(saleNumber: $number) { sale { id } }
+            }",
+            json!({ "id": id(&sale["saleId"]), "number": format!(" {} ", sale_number.to_lowercase()) }),
+        )
+        .await;
+    assert_eq!(found["byId"]["sale"]["saleNumber"], json!(sale_number));
+    assert_eq!(
+        found["byId"]["items"],
+        json!([{ "itemName": "Test Kraut", "unit": "jars", "quantity": "2" }])
+    );
+    assert_eq!(found["byNumber"]["sale"]["id"], sale["saleId"]);
+
+    let missing = app
+        .execute(
+            "{ saleByNumber(saleNumber: \"SALE-19000101-001\") { sale { id } } }",
+            json!({}),
+        )
+        .await;
+    assert_eq!(missing["saleByNumber"], json!(null));
+}
@@ -0,0 +1,291 @@
+use std::str::FromStr;
+
+use async_graphql::{Request, Variables};
+use bigdecimal::BigDecimal;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::auth::{AuthConfig, Identity};
+use crate::services::config::Config;
+use crate::services::database::ReportingPool;
+use crate::services::rate_limit::RateLimiter;
+use crate::services::versions::ApiVersion;
+use crate::{ApiSchema, SchemaData, build_schema, jobs, services};
+
+/// The schema the server runs, over a test's own database, acting as a
+/// signed-in owner. `#[sqlx::test(migrations = false)]` creates the
+/// database (from `DATABASE_URL`) and drops it when the test passes, so
+/// tests can't see each other's rows.
+pub struct TestApp {
+    pub pool: PgPool,
+    pub owner: Identity,
+    schema: ApiSchema,
+}
+
+impl TestApp {
+    /// Load `init.sql` (schema and sample data) into the test database and
+    /// build the v1 schema over it. No background jobs run.
+    pub async fn new(pool: PgPool) -> Self {
+        sqlx::raw_sql(include_str!("../../../init.sql"))
+            .execute(&pool)
+            .await
+            .expect("init.sql loads");
+
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (username, password_hash, role) VALUES ('test-owner', '', 'owner') RETURNING id"
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("owner is created");
+        let owner = Identity {
+            user_id,
+            username: "test-owner".to_string(),
+            role: "owner".to_string(),
+            api_key_id: None,
+        };
+
+        let config = Config::from_env().expect("test configuration is valid");
+        let http = services::http::HttpClient::new();
+        let secrets = services::secrets::SecretStore::from_env(pool.clone());
+        let notifier =
+            services::notifications::Notifier::new(http.clone(), secrets.clone(), pool.clone());
+        let (reading_queue, _, batch_alerts) = jobs::reading_alerts::channels();
+        let scheduler = jobs::scheduler::Scheduler::from_env(
+            pool.clone(),
+            notifier.clone(),
+            batch_alerts.clone(),
+        );
+
+        let data = SchemaData {
+            auth: AuthConfig::new(&config),
+            pool: pool.clone(),
+            reporting: ReportingPool::from(pool.clone()),
+            notifier,
+            http,
+            secrets,
+            reading_queue,
+            batch_alerts,
+            availability: jobs::availability::AvailabilityFeed::new(),
+            api_usage: jobs::api_usage::ApiUsage::new(),
+            rate_limiter: RateLimiter::new(&config),
+            response_cache: services::response_cache::ResponseCache::new(&config),
+            scheduler,
+        };
+
+        TestApp {
+            schema: build_schema(ApiVersion::V1, &config, &data),
+            pool,
+            owner,
+        }
+    }
+
+    /// Run an operation as the owner, failing the test on any GraphQL error.
+    pub async fn execute(&self, query: &str, variables: Value) -> Value {
+        let response = self.execute_as(&self.owner, query, variables).await;
+
+        assert!(
+            response.errors.is_empty(),
+            "{} failed: {:?}",
+            query,
+            response.errors
+        );
+        response.data.into_json().expect("response is JSON")
+    }
+
+    /// Run an operation as someone else, returning the response as it is.
+    pub async fn execute_as(
+        &self,
+        identity: &Identity,
+        query: &str,
+        variables: Value,
+    ) -> async_graphql::Response {
+        let request = Request::new(query)
+            .variables(Variables::from_json(variables))
+            .data(identity.clone());
+        services::audit::acting_as(Some(identity.clone()), self.schema.execute(request)).await
+    }
+
+    /// The owner, signed in with the read_only role instead.
+    pub fn read_only(&self) -> Identity {
+        Identity {
+            role: "read_only".to_string(),
+            ..self.owner.clone()
+        }
+    }
+
+    /// Run a mutation, failing the test unless its result reports success.
+    /// Returns the result object.
+    pub async fn mutate(&self, field: &str, query: &str, variables: Value) -> Value {
+        let result = self.execute(query, variables).await[field].clone();
+        assert_eq!(
+            result["success"],
+            json!(true),
+            "{} didn't succeed: {}",
+            field,
+            result
+        );
+        result
+    }
+
+    /// An item's stock on hand.
+    pub async fn stock(&self, inventory_id: Uuid) -> BigDecimal {
+        sqlx::query_scalar!(
+            "SELECT current_stock FROM inventory WHERE id = $1",
+            inventory_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .expect("item exists")
+    }
+
+    pub fn supplier(&self, name: &str) -> SupplierBuilder<'_> {
+        SupplierBuilder {
+            app: self,
+            input: json!({ "name": name }),
+        }
+    }
+
+    pub fn inventory_item(&self, name: &str) -> InventoryBuilder<'_> {
+        InventoryBuilder {
+            app: self,
+            input: json!({ "name": name, "category": "ingredient", "unit": "grams" }),
+        }
+    }
+
+    pub fn recipe(&self, name: &str, product_inventory_id: Uuid) -> RecipeBuilder<'_> {
+        RecipeBuilder {
+            app: self,
+            input: json!({
+                "templateName": name,
+                "productInventoryId": product_inventory_id,
+                "ingredients": [],
+            }),
+        }
+    }
+}
+
+/// A decimal from its text, e.g. `dec("1.50")`.
+pub fn dec(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).expect("valid decimal")
+}
+
+/// Reads an ID out of a result.
+pub fn id(value: &Value) -> Uuid {
+    value
+        .as_str()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| panic!("{} is not an ID", value))
+}
+
+/// Builds a supplier through `createSupplier`.
+pub struct SupplierBuilder<'a> {
+    app: &'a TestApp,
+    input: Value,
+}
+
+impl SupplierBuilder<'_> {
+    pub fn email(mut self, email: &str) -> Self {
+        self.input["contactEmail"] = json!(email);
+        self
+    }
+
+    pub async fn create(self) -> Uuid {
+        let result = self
+            .app
+            .mutate(
+                "createSupplier",
+                "mutation($input: CreateSupplierInput!) { createSupplier(input: $input) { success message supplier { id } } }",
+                json!({ "input": self.input }),
+            )
+            .await;
+        id(&result["supplier"]["id"])
+    }
+}
+
+/// Builds an inventory item through `createInventoryItem`: an ingredient
+/// counted in grams with no stock unless told otherwise.
+pub struct InventoryBuilder<'a> {
+    app: &'a TestApp,
+    input: Value,
+}
+
+impl InventoryBuilder<'_> {
+    pub fn category(mut self, category: &str) -> Self {
+        self.input["category"] = json!(category);
+        self
+    }
+
+    pub fn unit(mut self, unit: &str) -> Self {
+        self.input["unit"] = json!(unit);
+        self
+    }
+
+    pub fn stock(mut self, stock: &str) -> Self {
+        self.input["currentStock"] = json!(stock);
+        self
+    }
+
+    pub fn cost(mut self, cost_per_unit: &str) -> Self {
+        self.input["costPerUnit"] = json!(cost_per_unit);
+        self
+    }
+
+    pub fn product_code(mut self, code: &str) -> Self {
+        self.input["productCode"] = json!(code);
+        self
+    }
+
+    pub fn supplier(mut self, supplier_id: Uuid) -> Self {
+        self.input["defaultSupplierId"] = json!(supplier_id);
+        self
+    }
+
+    pub async fn create(self) -> Uuid {
+        let result = self
+            .app
+            .mutate(
+                "createInventoryItem",
+                "mutation($input: CreateInventoryItemInput!) { createInventoryItem(input: $input) { success message item { id } } }",
+                json!({ "input": self.input }),
+            )
+            .await;
+        id(&result["item"]["id"])
+    }
+}
+
+/// Builds a recipe through `createRecipeTemplate`.
+pub struct RecipeBuilder<'a> {
+    app: &'a TestApp,
+    input: Value,
+}
+
+impl RecipeBuilder<'_> {
+    pub fn batch_size(mut self, size: &str, unit: &str) -> Self {
+        self.input["defaultBatchSize"] = json!(size);
+        self.input["defaultUnit"] = json!(unit);
+        self
+    }
+
+    pub fn ingredient(mut self, inventory_id: Uuid, quantity_per_batch: &str) -> Self {
+        if let Some(ingredients) = self.input["ingredients"].as_array_mut() {
+            ingredients.push(json!({
+                "inventoryId": inventory_id,
+                "quantityPerBatch": quantity_per_batch,
+            }));
+        }
+        self
+    }
+
+    pub async fn create(self) -> Uuid {
+        let result = self
+            .app
+            .mutate(
+                "createRecipeTemplate",
+                "mutation($input: CreateRecipeTemplateInput!) { createRecipeTemplate(input: $input) { success message recipe { id } } }",
+                json!({ "input": self.input }),
+            )
+            .await;
+        id(&result["recipe"]["id"])
+    }
+}
@@ -0,0 +1,65 @@
+use axum::body::Bytes;
+use axum::extract::{Extension, Path};
+use axum::http::HeaderMap;
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::support::TestApp;
+use crate::jobs::shopify_sync::ShopifySync;
+use crate::jobs::square_import::SquareImport;
+use crate::routes::webhooks;
+use crate::services::config::Config;
+use crate::services::response_cache::ResponseCache;
+use crate::services::secrets::SecretStore;
+
+/// Archived inbound deliveries carry customer details, so only the owner
+/// can read them.
+#[sqlx::test(migrations = false)]
+async fn webhook_events_are_owner_only(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let query = "{ webhookEvents { id provider payload } }";
+
+    let refused = app.execute_as(&app.read_only(), query, json!({})).await;
+    assert_eq!(refused.errors.len(), 1);
+    assert_eq!(
+        refused.errors[0].message,
+        "The read_only role can't do this"
+    );
+
+    app.execute(query, json!({})).await;
+}
+
+/// A delivery that fails verification is archived without its signature
+/// and with only the start of its body.
+#[sqlx::test(migrations = false)]
+async fn rejected_webhooks_keep_only_metadata(pool: PgPool) {
+    let app = TestApp::new(pool).await;
+    let config = Config::from_env().expect("test configuration is valid");
+
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", "application/json".parse().unwrap());
+    headers.insert("stripe-signature", "t=1,v1=forged".parse().unwrap());
+    let response = webhooks::receive_webhook(
+        Extension(app.pool.clone()),
+        Extension(SecretStore::from_env(app.pool.clone())),
+        Extension(SquareImport::new()),
+        Extension(ShopifySync::new()),
+        Extension(ResponseCache::new(&config)),
+        Path("stripe".to_string()),
+        headers,
+        Bytes::from("x".repeat(100_000)),
+    )
+    .await;
+    assert!(response.status().is_client_error() || response.status().is_server_error());
+
+    let archived = sqlx::query!("SELECT status, headers, payload FROM webhook_events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("delivery is archived");
+    assert_eq!(archived.status, "rejected");
+    assert_eq!(
+        archived.headers,
+        json!({ "content-type": "application/json" })
+    );
+    assert_eq!(archived.payload.len(), 512);
+}
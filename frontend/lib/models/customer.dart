@@ -13,6 +13,7 @@ class Customer {
   final double? longitude;
   final String? customerType;
   final bool taxExempt;
+  final bool smsOptIn;
   final String? notes;
   final bool isActive;
   final DateTime createdAt;
@@ -32,6 +33,7 @@ class Customer {
     this.longitude,
     this.customerType,
     required this.taxExempt,
+    this.smsOptIn = false,
     this.notes,
     required this.isActive,
     required this.createdAt,
@@ -62,6 +64,7 @@ class Customer {
       longitude: parseLat(json['longitude']),
       customerType: json['customerType'] as String?,
       taxExempt: json['taxExempt'] as bool? ?? false,
+      smsOptIn: json['smsOptIn'] as bool? ?? false,
       notes: json['notes'] as String?,
       isActive: json['isActive'] as bool? ?? true,
       createdAt: DateTime.parse(json['createdAt'] as String),
@@ -85,6 +88,7 @@ class Customer {
       'longitude': longitude,
       'customerType': customerType,
       'taxExempt': taxExempt,
+      'smsOptIn': smsOptIn,
       'notes': notes,
       'isActive': isActive,
       'createdAt': createdAt.toIso8601String(),
@@ -145,6 +149,7 @@ class CreateCustomerInput {
   final double? longitude;
   final String? customerType;
   final bool? taxExempt;
+  final bool? smsOptIn;
   final String? notes;
 
   CreateCustomerInput({
@@ -160,6 +165,7 @@ class CreateCustomerInput {
     this.longitude,
     this.customerType,
     this.taxExempt,
+    this.smsOptIn,
     this.notes,
   });
 
@@ -177,6 +183,7 @@ class CreateCustomerInput {
       if (longitude != null) 'longitude': longitude,
       if (customerType != null) 'customerType': customerType,
       if (taxExempt != null) 'taxExempt': taxExempt,
+      if (smsOptIn != null) 'smsOptIn': smsOptIn,
       if (notes != null) 'notes': notes,
     };
   }
@@ -197,6 +204,7 @@ class UpdateCustomerInput {
   final double? longitude;
   final String? customerType;
   final bool? taxExempt;
+  final bool? smsOptIn;
   final String? notes;
   final bool? isActive;
 
@@ -214,6 +222,7 @@ class UpdateCustomerInput {
     this.longitude,
     this.customerType,
     this.taxExempt,
+    this.smsOptIn,
     this.notes,
     this.isActive,
   });
@@ -233,6 +242,7 @@ class UpdateCustomerInput {
       if (longitude != null) 'longitude': longitude,
       if (customerType != null) 'customerType': customerType,
       if (taxExempt != null) 'taxExempt': taxExempt,
+      if (smsOptIn != null) 'smsOptIn': smsOptIn,
       if (notes != null) 'notes': notes,
       if (isActive != null) 'isActive': isActive,
     };
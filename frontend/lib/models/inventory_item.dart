@@ -18,6 +18,7 @@ class InventoryItem {
     this.shelfLifeDays,
     this.storageRequirements,
     required this.isActive,
+    required this.version,
     required this.createdAt,
     required this.updatedAt,
   });
@@ -40,6 +41,7 @@ class InventoryItem {
       shelfLifeDays: json['shelfLifeDays'] as int?,
       storageRequirements: json['storageRequirements'] as String?,
       isActive: json['isActive'] as bool,
+      version: json['version'] as int,
       createdAt: DateTime.parse(json['createdAt'] as String),
       updatedAt: DateTime.parse(json['updatedAt'] as String),
     );
@@ -101,6 +103,11 @@ class InventoryItem {
   /// Soft delete flag - false indicates item is archived.
   final bool isActive;
 
+  /// Edit counter, bumped by each edit but not by stock movements.
+  ///
+  /// Sent back as `expectedVersion` so a stale edit is refused.
+  final int version;
+
   /// Timestamp when item was created.
   final DateTime createdAt;
 
@@ -8,6 +8,7 @@ class CreatePurchaseInput {
     required this.items,
     this.purchaseDate,
     this.notes,
+    this.idempotencyKey,
   });
 
   /// Converts to GraphQL mutation variables format.
@@ -18,6 +19,7 @@ class CreatePurchaseInput {
       if (purchaseDate != null)
         'purchaseDate': purchaseDate!.toUtc().toIso8601String(),
       if (notes != null) 'notes': notes,
+      if (idempotencyKey != null) 'idempotencyKey': idempotencyKey,
     };
   }
 
@@ -32,6 +34,9 @@ class CreatePurchaseInput {
 
   /// Optional notes about the purchase.
   final String? notes;
+
+  /// Client-generated key so a retried submit records the purchase only once.
+  final String? idempotencyKey;
 }
 
 /// Represents a single item in a purchase order.
@@ -217,6 +222,7 @@ class UpdateInventoryItemInput {
     this.shelfLifeDays,
     this.storageRequirements,
     this.isActive,
+    this.expectedVersion,
   });
 
   /// Converts to GraphQL mutation variables format.
@@ -234,6 +240,7 @@ class UpdateInventoryItemInput {
       if (shelfLifeDays != null) 'shelfLifeDays': shelfLifeDays,
       if (storageRequirements != null) 'storageRequirements': storageRequirements,
       if (isActive != null) 'isActive': isActive,
+      if (expectedVersion != null) 'expectedVersion': expectedVersion,
     };
   }
 
@@ -249,6 +256,10 @@ class UpdateInventoryItemInput {
   final int? shelfLifeDays;
   final String? storageRequirements;
   final bool? isActive;
+
+  /// The item's version as loaded; the server refuses the update if
+  /// someone else has edited it since.
+  final int? expectedVersion;
 }
 
 /// Result from create/update inventory item mutations.
@@ -10,6 +10,7 @@ class RecipeTemplate {
   final Map<String, dynamic>? ingredientTemplate;
   final String? instructions;
   final bool isActive;
+  final int version;
   final DateTime createdAt;
   final DateTime updatedAt;
 
@@ -24,6 +25,7 @@ class RecipeTemplate {
     this.ingredientTemplate,
     this.instructions,
     required this.isActive,
+    required this.version,
     required this.createdAt,
     required this.updatedAt,
   });
@@ -44,6 +46,7 @@ class RecipeTemplate {
       ingredientTemplate: json['ingredientTemplate'] as Map<String, dynamic>?,
       instructions: json['instructions'] as String?,
       isActive: json['isActive'] as bool,
+      version: json['version'] as int,
       createdAt: DateTime.parse(json['createdAt'] as String),
       updatedAt: DateTime.parse(json['updatedAt'] as String),
     );
@@ -61,6 +64,7 @@ class RecipeTemplate {
       'ingredientTemplate': ingredientTemplate,
       'instructions': instructions,
       'isActive': isActive,
+      'version': version,
       'createdAt': createdAt.toIso8601String(),
       'updatedAt': updatedAt.toIso8601String(),
     };
@@ -130,6 +134,7 @@ class CreateRecipeTemplateInput {
     this.estimatedDurationHours,
     this.ingredients,
     this.instructions,
+    this.expectedUpdatedAt,
   });
 
   Map<String, dynamic> toJson() {
@@ -163,6 +168,10 @@ class UpdateRecipeTemplateInput {
   final List<IngredientTemplateItem>? ingredients;
   final String? instructions;
 
+  /// The recipe's version as loaded; the server refuses the update if
+  /// someone else has edited it since.
+  final int? expectedVersion;
+
   UpdateRecipeTemplateInput({
     required this.id,
     this.productInventoryId,
@@ -173,6 +182,7 @@ class UpdateRecipeTemplateInput {
     this.estimatedDurationHours,
     this.ingredients,
     this.instructions,
+    this.expectedVersion,
   });
 
   Map<String, dynamic> toJson() {
@@ -191,6 +201,7 @@ class UpdateRecipeTemplateInput {
         'estimatedDurationHours': estimatedDurationHours,
       if (ingredientTemplate != null) 'ingredientTemplate': ingredientTemplate,
       if (instructions != null) 'instructions': instructions,
+      if (expectedVersion != null) 'expectedVersion': expectedVersion,
     };
   }
 }
@@ -178,6 +178,9 @@ class CreateSaleInput {
   final String? paymentStatus;
   final String? notes;
 
+  /// Client-generated key so a retried submit records the sale only once.
+  final String? idempotencyKey;
+
   CreateSaleInput({
     this.customerId,
     this.saleDate,
@@ -187,6 +190,7 @@ class CreateSaleInput {
     this.paymentMethod,
     this.paymentStatus,
     this.notes,
+    this.idempotencyKey,
   });
 
   /// Calculates the subtotal from all items.
@@ -209,6 +213,7 @@ class CreateSaleInput {
       if (paymentMethod != null) 'paymentMethod': paymentMethod,
       if (paymentStatus != null) 'paymentStatus': paymentStatus,
       if (notes != null) 'notes': notes,
+      if (idempotencyKey != null) 'idempotencyKey': idempotencyKey,
     };
   }
 }
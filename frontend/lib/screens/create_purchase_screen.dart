@@ -1,5 +1,6 @@
 import 'package:flutter/material.dart';
 import 'package:flutter_riverpod/flutter_riverpod.dart';
+import 'package:uuid/uuid.dart';
 
 import '../models/inventory_item.dart';
 import '../models/purchase.dart';
@@ -30,6 +31,9 @@ class _CreatePurchaseScreenState extends ConsumerState<CreatePurchaseScreen> {
 
   bool _isSubmitting = false;
 
+  /// Reused for every submit from this screen so a retry can't double-record.
+  final _idempotencyKey = const Uuid().v4();
+
   @override
   void dispose() {
     _notesController.dispose();
@@ -88,6 +92,7 @@ class _CreatePurchaseScreenState extends ConsumerState<CreatePurchaseScreen> {
             .toList(),
         purchaseDate: _purchaseDate,
         notes: _notesController.text.isEmpty ? null : _notesController.text,
+        idempotencyKey: _idempotencyKey,
       );
 
       final service = ref.read(graphqlServiceProvider.notifier);
@@ -1,6 +1,7 @@
 import 'package:flutter/material.dart';
 import 'package:flutter/services.dart';
 import 'package:flutter_riverpod/flutter_riverpod.dart';
+import 'package:uuid/uuid.dart';
 import '../models/customer.dart';
 import '../models/inventory_item.dart';
 import '../models/sale.dart';
@@ -26,6 +27,9 @@ class _CreateSaleScreenState extends ConsumerState<CreateSaleScreen> {
   final List<SaleItemInput> _saleItems = [];
   bool _isSubmitting = false;
 
+  /// Reused for every submit from this screen so a retry can't double-record.
+  final _idempotencyKey = const Uuid().v4();
+
   @override
   void dispose() {
     _notesController.dispose();
@@ -101,6 +105,7 @@ class _CreateSaleScreenState extends ConsumerState<CreateSaleScreen> {
         paymentMethod: _paymentMethod,
         notes: _notesController.text.trim().isEmpty ? null : _notesController.text.trim(),
         items: _saleItems,
+        idempotencyKey: _idempotencyKey,
       );
 
       final result = await graphqlService.createSale(input);
@@ -28,6 +28,7 @@ class _CustomerFormScreenState extends ConsumerState<CustomerFormScreen> {
 
   String _customerType = 'retail';
   bool _taxExempt = false;
+  bool _smsOptIn = false;
   bool _isActive = true;
   bool _isSubmitting = false;
 
@@ -51,6 +52,7 @@ class _CustomerFormScreenState extends ConsumerState<CustomerFormScreen> {
     if (customer != null) {
       _customerType = customer.customerType ?? 'retail';
       _taxExempt = customer.taxExempt;
+      _smsOptIn = customer.smsOptIn;
       _isActive = customer.isActive;
     }
   }
@@ -98,6 +100,7 @@ class _CustomerFormScreenState extends ConsumerState<CustomerFormScreen> {
           longitude: _longitudeController.text.trim().isEmpty ? null : double.tryParse(_longitudeController.text.trim()),
           customerType: _customerType,
           taxExempt: _taxExempt,
+          smsOptIn: _smsOptIn,
           notes: _notesController.text.trim().isEmpty ? null : _notesController.text.trim(),
         );
 
@@ -129,6 +132,7 @@ class _CustomerFormScreenState extends ConsumerState<CustomerFormScreen> {
           longitude: _longitudeController.text.trim().isEmpty ? null : double.tryParse(_longitudeController.text.trim()),
           customerType: _customerType,
           taxExempt: _taxExempt,
+          smsOptIn: _smsOptIn,
           notes: _notesController.text.trim().isEmpty ? null : _notesController.text.trim(),
           isActive: _isActive,
         );
@@ -394,6 +398,17 @@ class _CustomerFormScreenState extends ConsumerState<CustomerFormScreen> {
               },
             ),
 
+            SwitchListTile(
+              title: const Text('Text Pickup Reminders'),
+              subtitle: const Text('Customer agreed to reminder texts'),
+              value: _smsOptIn,
+              onChanged: (value) {
+                setState(() {
+                  _smsOptIn = value;
+                });
+              },
+            ),
+
             if (isEditing)
               SwitchListTile(
                 title: const Text('Active'),
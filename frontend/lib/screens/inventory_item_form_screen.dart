@@ -380,6 +380,7 @@ class _InventoryItemFormScreenState
             storageRequirements: _storageRequirementsController.text.isEmpty
                 ? null
                 : _storageRequirementsController.text,
+            expectedVersion: widget.item!.version,
           ),
         );
       } else {
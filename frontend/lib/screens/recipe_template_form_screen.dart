@@ -225,6 +225,7 @@ class _RecipeTemplateFormScreenState
             instructions: _instructionsController.text.trim().isEmpty
                 ? null
                 : _instructionsController.text.trim(),
+            expectedVersion: widget.recipe!.version,
           ),
         );
       } else {
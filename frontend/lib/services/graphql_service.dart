@@ -76,6 +76,7 @@ class GraphqlService extends _$GraphqlService {
         shelfLifeDays
         storageRequirements
         isActive
+        version
         createdAt
         updatedAt
       }
@@ -169,6 +170,7 @@ class GraphqlService extends _$GraphqlService {
           shelfLifeDays
           storageRequirements
           isActive
+          version
           createdAt
           updatedAt
         }
@@ -195,6 +197,7 @@ class GraphqlService extends _$GraphqlService {
           shelfLifeDays
           storageRequirements
           isActive
+          version
           createdAt
           updatedAt
         }
@@ -385,6 +388,7 @@ class GraphqlService extends _$GraphqlService {
           ingredientTemplate
           instructions
           isActive
+          version
           createdAt
           updatedAt
         }
@@ -408,6 +412,7 @@ class GraphqlService extends _$GraphqlService {
           ingredientTemplate
           instructions
           isActive
+          version
           createdAt
           updatedAt
         }
@@ -1205,6 +1210,7 @@ class GraphqlService extends _$GraphqlService {
           ingredientTemplate
           instructions
           isActive
+          version
           createdAt
           updatedAt
         }
@@ -1264,6 +1270,7 @@ class GraphqlService extends _$GraphqlService {
           longitude
           customerType
           taxExempt
+          smsOptIn
           notes
           isActive
           createdAt
@@ -1322,6 +1329,7 @@ class GraphqlService extends _$GraphqlService {
             longitude
             customerType
             taxExempt
+            smsOptIn
             notes
             isActive
             createdAt
@@ -1389,6 +1397,7 @@ class GraphqlService extends _$GraphqlService {
             longitude
             customerType
             taxExempt
+            smsOptIn
             notes
             isActive
             createdAt
@@ -1546,6 +1555,7 @@ class GraphqlService extends _$GraphqlService {
             country
             customerType
             taxExempt
+            smsOptIn
           }
         }
       }
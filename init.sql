@@ -16,6 +16,7 @@ CREATE TABLE suppliers (
     latitude DECIMAL(10, 8),
     longitude DECIMAL(11, 8),
     notes TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true, -- Inactive suppliers can't take new orders or be item defaults
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -34,24 +35,223 @@ CREATE TABLE inventory (
     default_supplier_id UUID REFERENCES suppliers(id),
     shelf_life_days INTEGER,
     storage_requirements TEXT,
+    product_code VARCHAR(10) UNIQUE, -- Short code for batch numbers, e.g. 'KIM' ({product} in BATCH_NUMBER_FORMAT)
     is_active BOOLEAN NOT NULL DEFAULT true,
+    discontinued_at TIMESTAMPTZ, -- Off the catalog and no new batches; remaining stock sells through
+    discontinued_reason TEXT,
+    version INTEGER NOT NULL DEFAULT 1, -- Bumped by edits only (not stock movements); checked against expectedVersion
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+-- Units of measure offered when adding items and recipes (inventory.unit stays free text)
+CREATE TABLE units (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50) NOT NULL UNIQUE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('mass', 'volume', 'count')),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Inventory categories offered when adding items (inventory.category stays free text)
+CREATE TABLE inventory_categories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Storage locations (walk-in cooler, dry storage, farmers-market van) that lots and batches sit in
+CREATE TABLE locations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    location_type VARCHAR(50) NOT NULL DEFAULT 'storage', -- 'cold_storage', 'dry_storage', 'vehicle', 'storage'
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Product translations (customer-facing catalog and label text per locale, e.g. 'es')
+CREATE TABLE product_translations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    locale VARCHAR(10) NOT NULL,
+    name VARCHAR NOT NULL,
+    description TEXT,
+    label_text TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(inventory_id, locale)
+);
+
 -- Inventory logs table for tracking movements
 CREATE TABLE inventory_logs (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     inventory_id UUID NOT NULL REFERENCES inventory(id),
     movement_type VARCHAR NOT NULL, -- 'purchase', 'sale', 'adjustment', 'waste'
     quantity DECIMAL NOT NULL,
-    unit_cost DECIMAL,
+    unit_cost DECIMAL, -- What a unit was worth when it moved; filled from inventory.cost_per_unit when not given
+    value DECIMAL GENERATED ALWAYS AS (quantity * unit_cost) STORED, -- Signed, like quantity
+    unit_price DECIMAL, -- Sale price per unit, for sales
     reason TEXT,
     batch_number VARCHAR,
     expiry_date DATE,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+-- Inventory lots table (received or produced quantities with their own expiry)
+-- Created before production_batches; the batch reference is added below
+CREATE TABLE inventory_lots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    lot_number VARCHAR(100),
+    code VARCHAR(20) UNIQUE, -- Internal QR code (FFL-XXXXXXXXXX), given to received and produced lots
+    source_type VARCHAR(50) NOT NULL, -- 'purchase', 'production', 'packaging', 'assembly', 'transfer'
+    supplier_id UUID REFERENCES suppliers(id),
+    production_batch_id UUID,
+    parent_lot_id UUID REFERENCES inventory_lots(id) ON DELETE SET NULL, -- Bulk lot a packaged lot came from
+    location_id UUID REFERENCES locations(id), -- Where the lot is stored (NULL = unassigned)
+    initial_quantity DECIMAL NOT NULL,
+    remaining_quantity DECIMAL NOT NULL,
+    unit_cost DECIMAL,
+    received_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expiry_date DATE, -- Explicit expiry; falls back to received_date + shelf_life_days
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Supplier contracts (blanket orders): committed volumes at agreed prices for a period.
+-- Purchase orders placed against a contract draw it down
+CREATE TABLE supplier_contracts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    contract_number VARCHAR(100) NOT NULL UNIQUE, -- Our or the supplier's reference
+    supplier_id UUID NOT NULL REFERENCES suppliers(id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL, -- Prices are protected through this day
+    status VARCHAR(20) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'closed')),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (end_date >= start_date)
+);
+
+CREATE TABLE supplier_contract_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    contract_id UUID NOT NULL REFERENCES supplier_contracts(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    committed_quantity DECIMAL(12,3) NOT NULL CHECK (committed_quantity > 0),
+    agreed_price DECIMAL(12,4) NOT NULL CHECK (agreed_price >= 0), -- Per unit
+    UNIQUE (contract_id, inventory_id)
+);
+
+-- Purchase orders (placed with a supplier; stock arrives when receipts are recorded against them)
+CREATE TABLE purchase_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    po_number VARCHAR(100) NOT NULL UNIQUE, -- Format: PO-YYYYMMDD-NNN
+    supplier_id UUID NOT NULL REFERENCES suppliers(id),
+    status VARCHAR(50) NOT NULL DEFAULT 'open', -- 'pending_approval', 'open', 'received', 'rejected', 'cancelled'
+    order_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expected_date DATE,
+    notes TEXT,
+    total_amount DECIMAL(12,2) NOT NULL DEFAULT 0, -- Sum of the lines with a unit cost
+    requested_by VARCHAR(100), -- Username of who placed it
+    requested_by_user_id UUID, -- References users; the constraint is added below
+    decided_by VARCHAR(100), -- Owner who approved or rejected it
+    decided_at TIMESTAMPTZ,
+    decision_note TEXT,
+    contract_id UUID REFERENCES supplier_contracts(id), -- Contract the order draws down
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Purchase order lines (received_quantity counts up as receipts come in)
+CREATE TABLE purchase_order_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    purchase_order_id UUID NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0),
+    unit_cost DECIMAL,
+    received_quantity DECIMAL(10,3) NOT NULL DEFAULT 0,
+    UNIQUE (purchase_order_id, inventory_id)
+);
+
+-- Supplier price catalogs, kept current by importing the vendor's CSV
+CREATE TABLE supplier_catalog_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    supplier_id UUID NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+    sku VARCHAR(100) NOT NULL,
+    description VARCHAR(255) NOT NULL,
+    pack_size VARCHAR(100), -- as the vendor lists it, e.g. '6 x 32 oz'
+    price DECIMAL(12,4) NOT NULL CHECK (price >= 0),
+    is_available BOOLEAN NOT NULL DEFAULT true,
+    inventory_id UUID REFERENCES inventory(id) ON DELETE SET NULL, -- our item it's bought as
+    removed_at TIMESTAMPTZ, -- missing from the vendor's latest catalog
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (supplier_id, sku)
+);
+
+-- One import of a supplier's catalog file, with counts of what changed
+CREATE TABLE supplier_catalog_imports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    supplier_id UUID NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+    file_name VARCHAR(255),
+    row_count INTEGER NOT NULL,
+    added INTEGER NOT NULL DEFAULT 0,
+    changed INTEGER NOT NULL DEFAULT 0,
+    removed INTEGER NOT NULL DEFAULT 0,
+    alert_threshold_percent DECIMAL(6,2) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- What each import changed against the catalog before it
+CREATE TABLE supplier_catalog_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    import_id UUID NOT NULL REFERENCES supplier_catalog_imports(id) ON DELETE CASCADE,
+    catalog_item_id UUID NOT NULL REFERENCES supplier_catalog_items(id) ON DELETE CASCADE,
+    change_type VARCHAR(20) NOT NULL CHECK (change_type IN ('added', 'changed', 'removed')),
+    sku VARCHAR(100) NOT NULL,
+    description VARCHAR(255) NOT NULL,
+    old_price DECIMAL(12,4),
+    new_price DECIMAL(12,4),
+    price_change_percent DECIMAL(8,2),
+    was_available BOOLEAN,
+    is_available BOOLEAN
+);
+
+-- Quick stock counts (e.g. at the market) held for back-office approval before stock changes
+CREATE TABLE stock_counts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    counted_quantity DECIMAL(10,3) NOT NULL CHECK (counted_quantity >= 0),
+    expected_quantity DECIMAL(10,3) NOT NULL, -- current_stock when the count was submitted
+    variance DECIMAL(10,3) GENERATED ALWAYS AS (counted_quantity - expected_quantity) STORED,
+    unit VARCHAR(50) NOT NULL,
+    counted_by VARCHAR(255) NOT NULL,
+    location VARCHAR(100), -- Where the count was taken (e.g. "Saturday market")
+    notes TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'approved', 'rejected'
+    counted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by VARCHAR(255),
+    reviewed_at TIMESTAMPTZ,
+    review_notes TEXT,
+    applied_adjustment DECIMAL(10,3), -- Stock change made on approval
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Photo evidence for quick counts (kept out of the change log)
+CREATE TABLE stock_count_photos (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    stock_count_id UUID NOT NULL REFERENCES stock_counts(id) ON DELETE CASCADE,
+    filename VARCHAR(255) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    data BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
 -- Recipe templates table (must be created before production_batches due to foreign key)
 CREATE TABLE recipe_templates (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -61,13 +261,115 @@ CREATE TABLE recipe_templates (
     default_batch_size DECIMAL(10,3),
     default_unit VARCHAR(50),
     estimated_duration_hours DECIMAL(6,2),
-    ingredient_template JSONB,
+    ingredient_template JSONB, -- Legacy; superseded by recipe_ingredients
     instructions TEXT,
+    min_temperature DECIMAL(5,2), -- Fermentation range in degrees Fahrenheit
+    max_temperature DECIMAL(5,2),
+    target_ph_curve JSONB, -- [{"day": 3, "max_ph": 4.6}, ...]: pH should be at or below max_ph by that day
     is_active BOOLEAN DEFAULT true,
+    version INTEGER NOT NULL DEFAULT 1, -- Bumped by edits; checked against expectedVersion
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Recipe ingredients (typed rows replacing the legacy recipe_templates.ingredient_template JSONB)
+CREATE TABLE recipe_ingredients (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL REFERENCES recipe_templates(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity_per_batch DECIMAL(10,3) NOT NULL CHECK (quantity_per_batch > 0), -- For default_batch_size
+    unit VARCHAR(50) NOT NULL,
+    position INTEGER NOT NULL, -- Listing order
+    UNIQUE (recipe_template_id, inventory_id)
+);
+
+-- Overhead pools (rent, utilities, insurance) spread over production. The rate is the monthly
+-- amount over the monthly volume of the allocation basis: budgeted, or the last 90 days' actuals
+CREATE TABLE overhead_pools (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    monthly_amount DECIMAL(12,2) NOT NULL CHECK (monthly_amount >= 0),
+    allocation_basis VARCHAR(20) NOT NULL
+        CHECK (allocation_basis IN ('per_batch', 'per_liter', 'per_labor_hour')),
+    expected_monthly_volume DECIMAL(12,3) CHECK (expected_monthly_volume > 0), -- Batches, liters or labor hours
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Recipe cost watches (alert when ingredient costs push a recipe past its cost or margin limits)
+CREATE TABLE recipe_cost_watches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL UNIQUE REFERENCES recipe_templates(id) ON DELETE CASCADE,
+    max_unit_cost DECIMAL,
+    min_margin_percent DECIMAL(5,1),
+    sale_price DECIMAL, -- Price to measure margin against; falls back to recent sales
+    is_alerting BOOLEAN NOT NULL DEFAULT false, -- Whether the last check found a limit crossed
+    last_unit_cost DECIMAL,
+    last_margin_percent DECIMAL(5,1),
+    last_checked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (max_unit_cost IS NOT NULL OR min_margin_percent IS NOT NULL)
+);
+
+-- Product development pipeline (concept -> trials -> costing -> compliance -> launched)
+CREATE TABLE product_developments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    stage VARCHAR(50) NOT NULL DEFAULT 'concept', -- 'concept', 'trials', 'costing', 'compliance', 'launched', 'shelved'
+    recipe_template_id UUID REFERENCES recipe_templates(id) ON DELETE SET NULL, -- Candidate recipe under trial
+    product_inventory_id UUID REFERENCES inventory(id), -- Product it launches as
+    target_launch_date DATE,
+    costed_unit_cost DECIMAL, -- Ingredient cost per unit when it passed the costing gate
+    label_approved_at TIMESTAMPTZ, -- Label and compliance review signed off
+    launched_at TIMESTAMPTZ,
+    notes TEXT,
+    stage_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
 
+-- Stage changes of a product in development (the gate history)
+CREATE TABLE product_development_stages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    development_id UUID NOT NULL REFERENCES product_developments(id) ON DELETE CASCADE,
+    from_stage VARCHAR(50),
+    to_stage VARCHAR(50) NOT NULL,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Vessels (crocks, fermenters, brite tanks) that production batches ferment in
+CREATE TABLE vessels (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    vessel_type VARCHAR(50) NOT NULL, -- 'crock', 'fermenter', 'brite_tank', 'jar', etc.
+    capacity DECIMAL(10,3) NOT NULL CHECK (capacity > 0),
+    capacity_unit VARCHAR(50) NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'available', -- 'available', 'in_use', 'cleaning', 'out_of_service'
+    location VARCHAR(100),
+    notes TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Sanitation log (cleanings of vessels, kept for health inspections)
+CREATE TABLE sanitation_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    vessel_id UUID NOT NULL REFERENCES vessels(id),
+    chemical VARCHAR(100) NOT NULL, -- Sanitizer used (e.g. 'Star San', 'chlorine', 'quaternary ammonium')
+    concentration DECIMAL(10,3) NOT NULL CHECK (concentration >= 0),
+    concentration_unit VARCHAR(20) NOT NULL DEFAULT 'ppm',
+    operator VARCHAR(100) NOT NULL, -- Who did the cleaning
+    cleaned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
 -- Production batches table
 CREATE TABLE production_batches (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -85,7 +387,10 @@ CREATE TABLE production_batches (
     yield_percentage DECIMAL(5,2),
     actual_yield DECIMAL(10,3),
     quality_notes TEXT,
-    storage_location VARCHAR(100),
+    storage_location_id UUID REFERENCES locations(id),
+    vessel_id UUID REFERENCES vessels(id),
+    development_id UUID REFERENCES product_developments(id), -- Trial batch of a product in development
+    last_moved_at TIMESTAMPTZ,
     notes TEXT,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
@@ -101,6 +406,278 @@ CREATE TABLE production_batch_ingredients (
     notes TEXT
 );
 
+-- Packaging materials (jars, lids, labels) used to package a batch's output (at completion or when split into packs)
+CREATE TABLE production_batch_packaging (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0),
+    unit VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Everything a completed batch produced: the batch product plus any co-products (e.g. brine)
+CREATE TABLE production_batch_outputs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity >= 0),
+    unit VARCHAR(50) NOT NULL,
+    is_primary BOOLEAN NOT NULL DEFAULT false, -- The batch's product_inventory_id
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, inventory_id)
+);
+
+ALTER TABLE inventory_lots
+    ADD CONSTRAINT inventory_lots_production_batch_id_fkey
+    FOREIGN KEY (production_batch_id) REFERENCES production_batches(id) ON DELETE SET NULL;
+
+-- Pack sizes (packaged SKUs of a bulk product, e.g. 16oz and 32oz jars of kraut)
+-- Each pack size is its own inventory item so packaged stock is tracked separately from bulk
+CREATE TABLE pack_sizes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bulk_inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    packaged_inventory_id UUID NOT NULL UNIQUE REFERENCES inventory(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    bulk_quantity_per_pack DECIMAL NOT NULL CHECK (bulk_quantity_per_pack > 0), -- In the bulk item's unit
+    label_inventory_id UUID REFERENCES inventory(id) ON DELETE SET NULL, -- Label stock used by label runs
+    labels_per_pack INTEGER NOT NULL DEFAULT 1 CHECK (labels_per_pack > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (bulk_inventory_id <> packaged_inventory_id)
+);
+
+-- Label print runs planned before a packaging day (kept out of the change log;
+-- the label stock they consume is logged against each batch as packaging)
+CREATE TABLE label_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    locale VARCHAR(10) NOT NULL,
+    label_count INTEGER NOT NULL,
+    notes TEXT,
+    pdf BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Kit components (bill of materials for an assembled kit, e.g. workshop kits and gift boxes)
+-- The kit itself is an inventory item; assembling kits consumes components and adds kit stock
+CREATE TABLE kit_components (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kit_inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    component_inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0), -- Per kit
+    UNIQUE (kit_inventory_id, component_inventory_id),
+    CHECK (kit_inventory_id <> component_inventory_id)
+);
+
+-- Kit assembly runs (components consumed, kits added to stock, and what each kit cost)
+CREATE TABLE kit_assemblies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    assembly_number VARCHAR(100) NOT NULL UNIQUE,
+    kit_inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0),
+    unit_cost DECIMAL NOT NULL,
+    total_cost DECIMAL NOT NULL,
+    notes TEXT,
+    assembled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Storage transition rules (per recipe: move batches between storage areas after N days)
+CREATE TABLE storage_transition_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL REFERENCES recipe_templates(id) ON DELETE CASCADE,
+    from_location VARCHAR(100),
+    to_location VARCHAR(100) NOT NULL,
+    after_days INTEGER NOT NULL CHECK (after_days >= 0),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Recipe stages (ordered aging/maturation stages, e.g. koji -> primary -> aging for miso)
+CREATE TABLE recipe_stages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL REFERENCES recipe_templates(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL CHECK (position > 0), -- 1 = first stage
+    name VARCHAR(100) NOT NULL,
+    expected_days INTEGER CHECK (expected_days >= 0),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (recipe_template_id, position)
+);
+
+-- Stages a batch has entered (the row with no completed_at is the current stage)
+CREATE TABLE batch_stages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name VARCHAR(100) NOT NULL, -- Copied from the recipe so later recipe edits don't rewrite history
+    expected_days INTEGER,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, position)
+);
+
+-- Batch move tasks (generated by the scheduler from storage transition rules)
+CREATE TABLE batch_move_tasks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    rule_id UUID REFERENCES storage_transition_rules(id) ON DELETE SET NULL,
+    from_location VARCHAR(100),
+    to_location VARCHAR(100) NOT NULL,
+    due_date TIMESTAMPTZ NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending', -- 'pending', 'completed', 'cancelled'
+    completed_at TIMESTAMPTZ,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, rule_id)
+);
+
+-- Recipe task templates (recurring chores per recipe: burp jars, check brine, transfer)
+CREATE TABLE recipe_task_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL REFERENCES recipe_templates(id) ON DELETE CASCADE,
+    task_type VARCHAR(50) NOT NULL, -- 'burp', 'check_brine', 'transfer', etc.
+    description TEXT NOT NULL,
+    start_day INTEGER NOT NULL CHECK (start_day >= 0), -- Days after the batch start date
+    repeat_every_days INTEGER CHECK (repeat_every_days > 0), -- NULL for a one-off task
+    repeat_until_day INTEGER, -- Last day a repeating task is due
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((repeat_every_days IS NULL) = (repeat_until_day IS NULL)),
+    CHECK (repeat_until_day >= start_day)
+);
+
+-- Batch tasks (generated from recipe task templates when a batch starts)
+CREATE TABLE batch_tasks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    template_id UUID REFERENCES recipe_task_templates(id) ON DELETE SET NULL,
+    day_offset INTEGER NOT NULL, -- Day after the batch start the task was generated for
+    task_type VARCHAR(50) NOT NULL,
+    description TEXT NOT NULL,
+    due_date TIMESTAMPTZ NOT NULL, -- Pushed past production closures in the business calendar
+    status VARCHAR(50) NOT NULL DEFAULT 'pending', -- 'pending', 'completed', 'cancelled'
+    completed_at TIMESTAMPTZ,
+    completed_by VARCHAR(255),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, template_id, day_offset)
+);
+
+-- Planned batches (draft production schedule; marked started when a batch is created from one)
+CREATE TABLE planned_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipe_template_id UUID NOT NULL REFERENCES recipe_templates(id),
+    batch_size DECIMAL(10,3) NOT NULL CHECK (batch_size > 0),
+    target_date DATE NOT NULL,
+    status VARCHAR(50) NOT NULL DEFAULT 'planned', -- 'planned', 'started', 'cancelled'
+    production_batch_id UUID REFERENCES production_batches(id) ON DELETE SET NULL,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Batch readings (fermentation monitoring log: pH, temperature, brine strength, observations)
+CREATE TABLE batch_readings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ph DECIMAL(4,2) CHECK (ph >= 0 AND ph <= 14),
+    temperature DECIMAL(5,2), -- Degrees Fahrenheit
+    salinity_percent DECIMAL(5,2) CHECK (salinity_percent >= 0 AND salinity_percent <= 100),
+    brix DECIMAL(5,2) CHECK (brix >= 0),
+    observations TEXT,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual', -- 'manual', 'sensor'
+    device_name VARCHAR(100), -- Probe that sent the reading (sensor readings only)
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Batch alerts (readings that fell outside the recipe's temperature range or pH curve)
+CREATE TABLE batch_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    reading_id UUID REFERENCES batch_readings(id) ON DELETE SET NULL,
+    alert_type VARCHAR(50) NOT NULL, -- 'temperature_low', 'temperature_high', 'ph_high', 'overdue'
+    message TEXT NOT NULL,
+    measured_value DECIMAL(6,2) NOT NULL, -- Reading value, or days overdue
+    threshold DECIMAL(6,2) NOT NULL,
+    acknowledged_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Quality checks (HACCP checkpoint records on production batches)
+CREATE TABLE quality_checks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    check_type VARCHAR(50) NOT NULL, -- 'ph', 'temperature', 'salt_concentration', 'visual', 'label_review', etc.
+    control_point VARCHAR(100), -- HACCP plan reference (e.g. 'CCP-1 Acidification')
+    passed BOOLEAN NOT NULL,
+    measured_value DECIMAL(10,3),
+    unit VARCHAR(20),
+    critical_limit VARCHAR(100), -- Limit the value was checked against (e.g. 'pH <= 4.6')
+    corrective_action TEXT, -- Required when the check failed
+    signed_by VARCHAR(100) NOT NULL,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (passed OR corrective_action IS NOT NULL)
+);
+
+-- Business calendar (market days, holidays, production blackouts) consulted when scheduling
+CREATE TABLE business_calendar (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('market', 'holiday', 'blackout')),
+    name VARCHAR(255) NOT NULL, -- e.g. "Great Frederick Fair"
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    blocks_production BOOLEAN NOT NULL, -- Nothing should be made, moved, or finished
+    blocks_deliveries BOOLEAN NOT NULL, -- No deliveries or order pickups
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (end_date >= start_date)
+);
+
+-- Farmers markets we sell at, with the usual booth fee and cost of getting there
+CREATE TABLE markets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE, -- e.g. "Frederick Saturday Market"
+    location TEXT,
+    default_booth_fee DECIMAL(10,2) NOT NULL DEFAULT 0 CHECK (default_booth_fee >= 0),
+    default_travel_cost DECIMAL(10,2) NOT NULL DEFAULT 0 CHECK (default_travel_cost >= 0), -- Mileage, parking, tolls
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- A day we attend a market, shown on the business calendar as a market day
+CREATE TABLE market_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    market_id UUID NOT NULL REFERENCES markets(id),
+    calendar_event_id UUID REFERENCES business_calendar(id) ON DELETE SET NULL,
+    session_date DATE NOT NULL,
+    booth VARCHAR(50), -- Booth or stall assigned by the market (e.g. "B12")
+    booth_fee DECIMAL(10,2) NOT NULL CHECK (booth_fee >= 0),
+    travel_cost DECIMAL(10,2) NOT NULL CHECK (travel_cost >= 0),
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (market_id, session_date)
+);
+
+-- Who works the booth at a market session (app users or helpers without an account)
+CREATE TABLE market_session_staff (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES market_sessions(id) ON DELETE CASCADE,
+    user_id UUID, -- References users(id); constraint added after the users table
+    staff_name VARCHAR(255) NOT NULL,
+    UNIQUE (session_id, staff_name)
+);
+
 -- Customers table
 CREATE TABLE customers (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -116,6 +693,8 @@ CREATE TABLE customers (
     longitude DECIMAL(11, 8),
     customer_type VARCHAR(50) DEFAULT 'retail',
     tax_exempt BOOLEAN DEFAULT false,
+    sms_opt_in BOOLEAN NOT NULL DEFAULT false, -- agreed to texted pickup reminders
+    payment_terms_days INTEGER NOT NULL DEFAULT 0 CHECK (payment_terms_days >= 0), -- invoices due after (net 15, net 30); 0 is on receipt
     notes TEXT,
     is_active BOOLEAN NOT NULL DEFAULT true,
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
@@ -135,6 +714,13 @@ CREATE TABLE sales (
     payment_method VARCHAR(50),
     payment_status VARCHAR(50) NOT NULL DEFAULT 'completed',
     notes TEXT,
+    channel VARCHAR(50) NOT NULL DEFAULT 'in_person', -- Where the sale was made; picks the stock check policy
+    stock_check VARCHAR(20) NOT NULL DEFAULT 'strict'
+        CHECK (stock_check IN ('strict', 'allow_negative', 'review')), -- Policy the sale was made under
+    oversold BOOLEAN NOT NULL DEFAULT false, -- Sold more than was on record
+    stock_review_status VARCHAR(20) CHECK (stock_review_status IN ('pending', 'resolved')),
+    stock_review_note TEXT,
+    market_session_id UUID REFERENCES market_sessions(id) ON DELETE SET NULL, -- Market day the sale was made at
     created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -147,33 +733,909 @@ CREATE TABLE sale_items (
     quantity DECIMAL(10,3) NOT NULL,
     unit_price DECIMAL(10,2) NOT NULL,
     line_total DECIMAL(10,2) NOT NULL,
+    -- Taken off this line by its own discounts (see sale_discounts); line_total
+    -- is before discounts
+    discount_amount DECIMAL(10,2) NOT NULL DEFAULT 0,
     notes TEXT
 );
 
+-- Promo codes customers give at checkout. A code with an inventory_id takes
+-- its discount off the lines of that item, otherwise off the whole sale.
+CREATE TABLE promo_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code VARCHAR(50) NOT NULL UNIQUE, -- stored upper case
+    description TEXT,
+    discount_type VARCHAR(20) NOT NULL CHECK (discount_type IN ('percentage', 'fixed')),
+    value DECIMAL(10,2) NOT NULL CHECK (value > 0),
+    inventory_id UUID REFERENCES inventory(id) ON DELETE CASCADE,
+    starts_on DATE,
+    ends_on DATE,
+    max_uses INTEGER CHECK (max_uses > 0),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (discount_type <> 'percentage' OR value <= 100),
+    CHECK (ends_on IS NULL OR starts_on IS NULL OR ends_on >= starts_on)
+);
+
+-- Each discount a sale got: on one line (sale_item_id) or the whole sale,
+-- given by hand or through a promo code. sales.discount_amount is their total.
+CREATE TABLE sale_discounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sale_id UUID NOT NULL REFERENCES sales(id) ON DELETE CASCADE,
+    sale_item_id UUID REFERENCES sale_items(id) ON DELETE CASCADE,
+    promo_code_id UUID REFERENCES promo_codes(id),
+    discount_type VARCHAR(20) NOT NULL CHECK (discount_type IN ('percentage', 'fixed')),
+    value DECIMAL(10,2) NOT NULL, -- percent off, or amount off
+    amount DECIMAL(10,2) NOT NULL CHECK (amount >= 0), -- what it took off
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Invoices for sales paid later (payment_method 'invoice'), one per sale,
+-- due after the customer's payment terms. status follows the payments:
+-- 'open', 'partially_paid', 'paid', or 'void' once the sale is refunded.
+CREATE TABLE invoices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sale_id UUID NOT NULL UNIQUE REFERENCES sales(id) ON DELETE CASCADE,
+    customer_id UUID NOT NULL REFERENCES customers(id),
+    issued_on DATE NOT NULL,
+    due_on DATE NOT NULL,
+    amount DECIMAL(10,2) NOT NULL CHECK (amount >= 0), -- the sale's total
+    amount_paid DECIMAL(10,2) NOT NULL DEFAULT 0,
+    status VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'partially_paid', 'paid', 'void')),
+    paid_on DATE, -- when the last of it was paid
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (due_on >= issued_on),
+    CHECK (amount_paid >= 0 AND amount_paid <= amount)
+);
+
+-- Payments against an invoice; several part payments can settle one
+CREATE TABLE invoice_payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    invoice_id UUID NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    amount DECIMAL(10,2) NOT NULL CHECK (amount > 0),
+    paid_on DATE NOT NULL,
+    payment_method VARCHAR(50), -- 'check', 'cash', 'transfer', 'stripe', etc.
+    reference VARCHAR(255), -- check number, Stripe checkout session, ...
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Orders customers place ahead of delivery (restaurants order Tuesday for
+-- Friday). draft -> confirmed, which reserves the stock -> fulfilled, which
+-- turns the order into a sale and releases the reservation -> invoiced.
+-- Drafts and confirmed orders can be cancelled.
+CREATE TABLE customer_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_number VARCHAR(100) NOT NULL UNIQUE, -- ORDER_NUMBER_FORMAT, default ORD-YYYYMMDD-NNN
+    customer_id UUID NOT NULL REFERENCES customers(id),
+    delivery_date DATE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft'
+        CHECK (status IN ('draft', 'confirmed', 'fulfilled', 'invoiced', 'cancelled')),
+    sale_id UUID REFERENCES sales(id) ON DELETE SET NULL, -- The sale it was fulfilled as
+    notes TEXT,
+    confirmed_at TIMESTAMPTZ,
+    fulfilled_at TIMESTAMPTZ,
+    cancelled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE customer_order_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES customer_orders(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0),
+    unit_price DECIMAL(10,2) NOT NULL CHECK (unit_price >= 0), -- settled when the order is taken
+    notes TEXT
+);
+
+-- How createSale treats a sale of more than is on record, per sales channel.
+-- 'strict' refuses it, 'allow_negative' lets stock go negative with a warning,
+-- and 'review' does the same but queues the sale for review. Channels
+-- without a row are strict.
+CREATE TABLE sale_channel_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel VARCHAR(50) NOT NULL UNIQUE,
+    stock_check VARCHAR(20) NOT NULL CHECK (stock_check IN ('strict', 'allow_negative', 'review')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Price lists: what an item sells for to each type of customer. 'retail' is
+-- the fallback for customers whose type has no price and for anonymous sales.
+CREATE TABLE price_list_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_type VARCHAR(50) NOT NULL, -- 'retail', 'wholesale', 'restaurant', etc.
+    inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    unit_price DECIMAL(10,2) NOT NULL CHECK (unit_price >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (customer_type, inventory_id)
+);
+
+-- Prices agreed with one customer, ahead of their type's price list
+CREATE TABLE customer_prices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID NOT NULL REFERENCES customers(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    unit_price DECIMAL(10,2) NOT NULL CHECK (unit_price >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (customer_id, inventory_id)
+);
+
+-- Pickup windows (time slots for collecting orders at the shop, e.g. CSA pickups)
+CREATE TABLE pickup_windows (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pickup_date DATE NOT NULL,
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    capacity INTEGER NOT NULL CHECK (capacity > 0), -- Orders that can be booked into the window
+    notes TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (end_time > start_time)
+);
+
+-- Pickup bookings (one per order; no-shows are counted per customer)
+CREATE TABLE pickup_bookings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pickup_window_id UUID NOT NULL REFERENCES pickup_windows(id) ON DELETE CASCADE,
+    sale_id UUID NOT NULL UNIQUE REFERENCES sales(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'booked'
+        CHECK (status IN ('booked', 'picked_up', 'no_show', 'cancelled')),
+    reminded_at TIMESTAMPTZ, -- When the reminder alert went out
+    picked_up_at TIMESTAMPTZ,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Plan allocations (future output of a planned batch promised to a made-to-order
+-- wholesale customer; soft until the batch completes, then reserved stock)
+CREATE TABLE plan_allocations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    planned_batch_id UUID NOT NULL REFERENCES planned_batches(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id), -- The recipe's product
+    customer_id UUID REFERENCES customers(id),
+    quantity DECIMAL(10,3) NOT NULL CHECK (quantity > 0), -- In the product's unit
+    due_date DATE,
+    status VARCHAR(20) NOT NULL DEFAULT 'soft'
+        CHECK (status IN ('soft', 'reserved', 'released', 'cancelled')),
+    reserved_quantity DECIMAL(10,3), -- Added to reserved_stock on completion (less if the yield fell short)
+    reserved_at TIMESTAMPTZ,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Idempotency keys (the result of a retried createSale/createPurchase is replayed, not re-run)
+CREATE TABLE idempotency_keys (
+    operation VARCHAR(50) NOT NULL, -- Mutation name, e.g. 'createSale'
+    caller VARCHAR(100) NOT NULL,   -- 'key:<api key id>', 'user:<user id>' or 'anonymous'
+    key VARCHAR(255) NOT NULL,      -- Supplied by the client, e.g. a UUID per checkout
+    request_hash VARCHAR(64) NOT NULL, -- SHA-256 of the input; a retry with other input is refused
+    result JSONB,                   -- Set in the same transaction that claims the key
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (operation, caller, key)
+);
+
+-- Lot consumptions (which lots were drawn by production batches and sale lines, for traceability)
+CREATE TABLE lot_consumptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    lot_id UUID NOT NULL REFERENCES inventory_lots(id) ON DELETE CASCADE,
+    quantity DECIMAL NOT NULL,
+    production_batch_id UUID REFERENCES production_batches(id) ON DELETE CASCADE, -- Used as a batch ingredient
+    sale_item_id UUID REFERENCES sale_items(id) ON DELETE CASCADE,                 -- Sold on a sale line
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (production_batch_id IS NOT NULL OR sale_item_id IS NOT NULL)
+);
+
+-- Workshops (fermentation classes and other ticketed events)
+CREATE TABLE workshops (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title VARCHAR NOT NULL,
+    description TEXT,
+    starts_at TIMESTAMPTZ NOT NULL,
+    duration_minutes INTEGER CHECK (duration_minutes > 0),
+    location VARCHAR,
+    capacity INTEGER NOT NULL CHECK (capacity > 0),
+    ticket_price DECIMAL(10,2) NOT NULL CHECK (ticket_price >= 0),
+    status VARCHAR(50) NOT NULL DEFAULT 'scheduled', -- 'scheduled', 'completed', 'cancelled'
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Materials kit handed to each workshop attendee (consumed from inventory at check-in)
+CREATE TABLE workshop_kit_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    workshop_id UUID NOT NULL REFERENCES workshops(id) ON DELETE CASCADE,
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity_per_attendee DECIMAL(10,3) NOT NULL CHECK (quantity_per_attendee > 0),
+    UNIQUE (workshop_id, inventory_id)
+);
+
+-- Workshop attendees (one ticket each; ticket sales are revenue of their own, not inventory sales)
+CREATE TABLE workshop_attendees (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    workshop_id UUID NOT NULL REFERENCES workshops(id) ON DELETE CASCADE,
+    ticket_number VARCHAR(100) NOT NULL UNIQUE,
+    customer_id UUID REFERENCES customers(id),
+    attendee_name VARCHAR NOT NULL,
+    email VARCHAR,
+    phone VARCHAR,
+    price_paid DECIMAL(10,2) NOT NULL CHECK (price_paid >= 0),
+    payment_method VARCHAR(50),
+    status VARCHAR(50) NOT NULL DEFAULT 'registered', -- 'registered', 'checked_in', 'cancelled'
+    purchased_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    checked_in_at TIMESTAMPTZ,
+    cancelled_at TIMESTAMPTZ,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Customer complaints (quality investigations tied back to lots and batches)
+CREATE TABLE complaints (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    customer_id UUID REFERENCES customers(id),
+    sale_id UUID REFERENCES sales(id),
+    inventory_id UUID REFERENCES inventory(id),
+    lot_id UUID REFERENCES inventory_lots(id) ON DELETE SET NULL,
+    production_batch_id UUID REFERENCES production_batches(id) ON DELETE SET NULL,
+    description TEXT NOT NULL,
+    severity VARCHAR(50) NOT NULL DEFAULT 'medium', -- 'low', 'medium', 'high'
+    status VARCHAR(50) NOT NULL DEFAULT 'open',     -- 'open', 'resolved'
+    disposition VARCHAR(50),                        -- 'refund', 'replace', 'no_action'
+    refund_amount DECIMAL(10,2),
+    replacement_quantity DECIMAL(10,3),
+    resolution_notes TEXT,
+    reported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Reviews of sibling lots triggered by a complaint
+CREATE TABLE complaint_lot_reviews (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    complaint_id UUID NOT NULL REFERENCES complaints(id) ON DELETE CASCADE,
+    lot_id UUID NOT NULL REFERENCES inventory_lots(id) ON DELETE CASCADE,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending', -- 'pending', 'cleared', 'quarantined', 'discarded'
+    notes TEXT,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (complaint_id, lot_id)
+);
+
+-- Fields the owner has shown or hidden on the public batch trace page
+-- (/trace/{batch_number}); fields without a row are shown
+CREATE TABLE trace_page_fields (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    field VARCHAR(50) NOT NULL UNIQUE
+        CHECK (field IN ('production_date', 'best_by', 'ingredients', 'suppliers', 'origins')),
+    visible BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Announcements shown in the app ("what's new", planned downtime, notices)
+CREATE TABLE announcements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    kind VARCHAR(50) NOT NULL DEFAULT 'notice', -- 'whats_new', 'downtime', 'notice'
+    publish_at TIMESTAMPTZ NOT NULL DEFAULT NOW(), -- Shown from
+    expires_at TIMESTAMPTZ, -- Shown until (NULL = until removed)
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (expires_at IS NULL OR expires_at > publish_at)
+);
+
+-- GraphQL errors captured by the telemetry extension (messages scrubbed of emails, numbers, tokens)
+CREATE TABLE api_errors (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    trace_id UUID NOT NULL, -- Returned to the client in the response's extensions.traceId
+    operation_name VARCHAR(255),
+    path TEXT, -- Field path of the failing resolver (e.g. 'createSale.items.0')
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- GraphQL calls per client, operation and hour, for finding clients that
+-- hammer the API. Tallied in memory and added in every minute.
+CREATE TABLE api_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bucket TIMESTAMPTZ NOT NULL, -- Start of the hour
+    client_id VARCHAR(64) NOT NULL, -- 'user:<id>', 'api_key:<id>' or 'anonymous'
+    client VARCHAR(255) NOT NULL, -- Username or API key name when last seen
+    user_id UUID,
+    api_key_id UUID,
+    api_version VARCHAR(10) NOT NULL DEFAULT 'v1', -- 'v1' (/graphql) or 'v2' (/graphql/v2)
+    operation_type VARCHAR(20) NOT NULL, -- 'query', 'mutation', 'subscription' or 'invalid'
+    operation VARCHAR(255) NOT NULL, -- Operation name, else its top-level fields
+    calls BIGINT NOT NULL DEFAULT 0,
+    errors BIGINT NOT NULL DEFAULT 0,
+    deprecated_calls BIGINT NOT NULL DEFAULT 0, -- Calls that used a deprecated field
+    total_ms BIGINT NOT NULL DEFAULT 0,
+    max_ms INTEGER NOT NULL DEFAULT 0,
+    last_called_at TIMESTAMPTZ NOT NULL,
+    UNIQUE(bucket, client_id, api_version, operation_type, operation)
+);
+
+-- Feedback from app users ("this button didn't work"), with the trace of the failing request
+CREATE TABLE user_feedback (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    trace_id UUID,
+    message TEXT NOT NULL,
+    page VARCHAR(255), -- Screen or route the user was on
+    reported_by VARCHAR(100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- App users. Passwords are stored as argon2 hashes; usernames are lowercase
+CREATE TABLE users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    username VARCHAR(100) NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    display_name VARCHAR(255),
+    role VARCHAR(20) NOT NULL DEFAULT 'read_only'
+        CHECK (role IN ('owner', 'production', 'sales', 'read_only')),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    phone VARCHAR(20), -- E.164, for texted alerts
+    sms_alert_kinds TEXT[] NOT NULL DEFAULT '{}', -- alert kinds texted to phone
+    last_login_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE purchase_orders
+    ADD CONSTRAINT purchase_orders_requested_by_user_id_fkey
+    FOREIGN KEY (requested_by_user_id) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE market_session_staff
+    ADD CONSTRAINT market_session_staff_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL;
+
+-- A user's saved dashboard layout: widgets in display order, each with its filters
+CREATE TABLE dashboards (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    widgets JSONB NOT NULL DEFAULT '[]', -- [{kind, title, width, filters}]
+    is_default BOOLEAN NOT NULL DEFAULT false, -- opened first; at most one per user
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, name)
+);
+
+-- Largest purchase order a role can place without owner approval (no row: every order needs approval)
+CREATE TABLE purchase_approval_limits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    role VARCHAR(20) NOT NULL UNIQUE CHECK (role IN ('production', 'sales')),
+    max_amount DECIMAL(12,2) NOT NULL CHECK (max_amount >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Refresh tokens (SHA-256 of the token, never the token itself). Rotated on every refresh
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- API keys for devices and scripts (SHA-256 of the key, never the key itself)
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(12) NOT NULL, -- Start of the key, so it can be recognised in lists
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    scope VARCHAR(20) NOT NULL CHECK (scope IN ('read_only', 'ingest', 'full')),
+    created_by UUID NOT NULL REFERENCES users(id),
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Action links: short-lived, single-use links (shown as QR codes) that let a
+-- staff member confirm one batch transition from a phone without signing in.
+-- Only the SHA-256 of the token is stored.
+CREATE TABLE action_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    action VARCHAR(20) NOT NULL CHECK (action IN ('advance_stage', 'complete_move')),
+    batch_id UUID NOT NULL REFERENCES production_batches(id) ON DELETE CASCADE,
+    move_task_id UUID REFERENCES batch_move_tasks(id) ON DELETE CASCADE,
+    from_position INTEGER, -- Stage the batch was in when the link was made, so a stale link is refused
+    description TEXT NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE, -- Who confirms; the transition is recorded as theirs
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((action = 'complete_move') = (move_task_id IS NOT NULL))
+);
+
+-- Integration secrets (API keys, passwords, signing secrets) encrypted at rest with AES-256-GCM
+CREATE TABLE integration_secrets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    ciphertext BYTEA NOT NULL,
+    nonce BYTEA NOT NULL,
+    hint VARCHAR(4), -- Last characters of the value, shown in masked reads
+    version INTEGER NOT NULL DEFAULT 1,
+    rotated_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Outbound webhooks: URLs told about domain events (Zapier, n8n). Each one's
+-- signing secret is the integration secret webhook_signing_<id as 32 hex digits>
+CREATE TABLE webhook_subscriptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    url TEXT NOT NULL,
+    event_types TEXT[] NOT NULL CHECK (cardinality(event_types) > 0), -- 'batchCompleted', 'saleCreated', 'lowStock', 'purchaseReceived'
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Domain events (transactional outbox): written in the same transaction as the change they
+-- describe, kept for replay through the events query, and fanned out to webhook_deliveries
+CREATE TABLE domain_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type VARCHAR(50) NOT NULL, -- 'batchCompleted', 'saleCreated', 'lowStock', 'purchaseReceived'
+    payload JSONB NOT NULL,
+    txid BIGINT NOT NULL DEFAULT pg_current_xact_id()::text::bigint, -- Writing transaction; orders the replay cursor
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One event queued for one subscription; the dispatcher posts it and retries with backoff
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    subscription_id UUID NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+    event_id BIGINT NOT NULL REFERENCES domain_events(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_status_code INTEGER, -- HTTP status of the last attempt (NULL when it got no response)
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    failed_at TIMESTAMPTZ, -- When the last attempt gave up
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Items the lowStock event has fired for, cleared once stock is back above the reorder point
+CREATE TABLE low_stock_flags (
+    inventory_id UUID PRIMARY KEY REFERENCES inventory(id) ON DELETE CASCADE,
+    flagged_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE webhook_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    provider VARCHAR(20) NOT NULL, -- 'stripe', 'shopify', 'square'
+    event_id VARCHAR(255), -- Provider's event/delivery ID, used for replay protection
+    event_type VARCHAR(100),
+    status VARCHAR(20) NOT NULL, -- 'received', 'duplicate', 'rejected', 'failed' (verified but couldn't be applied; the provider retries)
+    error TEXT, -- Why the delivery was rejected or failed
+    headers JSONB NOT NULL DEFAULT '{}',
+    payload TEXT NOT NULL, -- Raw request body, archived for debugging
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Stripe payment links sent to collect a sale's payment. Paying one marks
+-- the sale completed (checkout.session.* webhooks).
+CREATE TABLE stripe_payment_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sale_id UUID NOT NULL REFERENCES sales(id) ON DELETE CASCADE,
+    stripe_payment_link_id VARCHAR(255) NOT NULL UNIQUE, -- plink_...
+    url TEXT NOT NULL,
+    amount DECIMAL(10,2) NOT NULL, -- Sale total the link charges
+    currency VARCHAR(3) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'paid', 'failed', 'deactivated')),
+    checkout_session_id VARCHAR(255), -- Checkout session that paid it
+    paid_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Background jobs the scheduler runs (expiry scan, low-stock check, ...),
+-- one row per job with its schedule and latest outcome
+CREATE TABLE scheduled_jobs (
+    name VARCHAR(50) PRIMARY KEY,
+    description TEXT NOT NULL,
+    schedule VARCHAR(100), -- Cron expression in UTC; NULL when the job is turned off
+    next_run_at TIMESTAMPTZ,
+    running_since TIMESTAMPTZ, -- Set while a run is in progress, so only one server runs it
+    last_started_at TIMESTAMPTZ,
+    last_finished_at TIMESTAMPTZ,
+    last_status VARCHAR(20) CHECK (last_status IN ('succeeded', 'failed')),
+    last_message TEXT,
+    last_trigger VARCHAR(20) CHECK (last_trigger IN ('schedule', 'manual')),
+    run_count INTEGER NOT NULL DEFAULT 0,
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Square catalog item variations and the inventory item each one sells.
+-- Square orders with an unmapped line wait until the variation is mapped.
+CREATE TABLE square_item_mappings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    catalog_object_id VARCHAR(64) NOT NULL UNIQUE, -- Square item variation ID
+    square_name VARCHAR(255), -- Item and variation name as Square showed them
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity_per_unit DECIMAL(10,3) NOT NULL DEFAULT 1 CHECK (quantity_per_unit > 0), -- Stock units one Square unit takes (e.g. 6 for a six-pack)
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Completed Square orders pulled by the import, one row per order
+CREATE TABLE square_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id VARCHAR(64) NOT NULL UNIQUE, -- Square order ID
+    location_id VARCHAR(64),
+    closed_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('pending', 'imported', 'unmapped', 'skipped')),
+    sale_id UUID REFERENCES sales(id) ON DELETE SET NULL, -- Sale created for the order
+    unmapped JSONB NOT NULL DEFAULT '[]', -- Variations still to map: [{catalog_object_id, name}]
+    issues JSONB NOT NULL DEFAULT '[]', -- Lines left out (e.g. custom amounts) and other notes
+    raw JSONB NOT NULL, -- Order as Square returned it
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Shopify product variants and the inventory item each one sells. Available
+-- stock is pushed to Shopify for variants with sync_enabled.
+CREATE TABLE shopify_products (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    variant_id BIGINT NOT NULL UNIQUE, -- Shopify variant ID
+    inventory_item_id BIGINT NOT NULL, -- Shopify inventory item ID stock levels are set on
+    shopify_name VARCHAR(255), -- Product and variant title as Shopify showed them
+    inventory_id UUID NOT NULL REFERENCES inventory(id),
+    quantity_per_unit DECIMAL(10,3) NOT NULL DEFAULT 1 CHECK (quantity_per_unit > 0), -- Stock units one Shopify unit takes
+    sync_enabled BOOLEAN NOT NULL DEFAULT true,
+    last_pushed_quantity INTEGER, -- What Shopify was last set to; a different level next sync is drift
+    last_pushed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Paid Shopify orders pulled by the sync, one row per order
+CREATE TABLE shopify_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id BIGINT NOT NULL UNIQUE, -- Shopify order ID
+    order_name VARCHAR(50), -- e.g. #1001
+    processed_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('pending', 'imported', 'unmapped', 'skipped')),
+    sale_id UUID REFERENCES sales(id) ON DELETE SET NULL, -- Sale created for the order
+    unmapped JSONB NOT NULL DEFAULT '[]', -- Variants still to map: [{variant_id, name}]
+    issues JSONB NOT NULL DEFAULT '[]', -- Lines left out (e.g. custom items) and other notes
+    raw JSONB NOT NULL, -- Order as Shopify returned it
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Times Shopify and the stock on record disagreed, kept until someone looks
+CREATE TABLE shopify_sync_conflicts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    shopify_product_id UUID REFERENCES shopify_products(id) ON DELETE SET NULL,
+    shopify_order_id UUID REFERENCES shopify_orders(id) ON DELETE SET NULL, -- Order that oversold
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('stock_drift', 'oversold')),
+    expected_quantity INTEGER, -- What the sync last set on Shopify
+    shopify_quantity INTEGER, -- What Shopify had instead
+    pushed_quantity INTEGER, -- What the sync set it to
+    message TEXT NOT NULL,
+    resolved_at TIMESTAMPTZ,
+    resolution_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Rows imported from the legacy spreadsheets (one per source record; re-running an import skips 'imported' rows)
+CREATE TABLE legacy_import_rows (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    record_type VARCHAR(20) NOT NULL, -- 'batches', 'sales', 'purchases'
+    source_key VARCHAR(255) NOT NULL, -- Original batch/sale number, or a purchase row fingerprint
+    status VARCHAR(20) NOT NULL, -- 'imported', 'rejected'
+    entity_id UUID, -- Created batch, sale, or purchase lot
+    issues JSONB NOT NULL DEFAULT '[]', -- Inconsistencies found (warnings if imported, errors if rejected)
+    raw JSONB NOT NULL, -- Source row(s) as exported from the sheet
+    imported_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (record_type, source_key)
+);
+
+-- Change log for incremental warehouse sync (written by the record_change trigger below)
+CREATE TABLE change_log (
+    id BIGSERIAL PRIMARY KEY,
+    entity VARCHAR(50) NOT NULL, -- Source table
+    entity_id UUID NOT NULL,
+    operation VARCHAR(10) NOT NULL, -- 'insert', 'update', 'delete'
+    row_data JSONB, -- Row after the change; NULL for deletes
+    txid BIGINT NOT NULL DEFAULT pg_current_xact_id()::text::bigint, -- Writing transaction; orders the sync cursor
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Who changed what (written by the record_audit trigger below): one row per row changed on an
+-- exported table by a signed-in user or API key
+CREATE TABLE mutation_audit (
+    id BIGSERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id), -- For API keys, the owner who created the key
+    actor VARCHAR(150) NOT NULL, -- Username, or '<key name> (API key)'
+    entity VARCHAR(50) NOT NULL, -- Source table
+    entity_id UUID NOT NULL,
+    operation VARCHAR(10) NOT NULL, -- 'insert', 'update', 'delete'
+    diff JSONB NOT NULL, -- {"column": {"old": ..., "new": ...}} for updates; the row for inserts and deletes
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Last sequence number handed out for batch, sale and order numbers, per numbering scope: the
+-- formatted number without its sequence (e.g. 'BATCH-20250101-'), so counters restart with each
+-- day, month or product the format includes. The row stays locked until the number's row commits.
+CREATE TABLE number_sequences (
+    kind VARCHAR(20) NOT NULL, -- 'batch', 'sale', 'order'
+    scope VARCHAR(100) NOT NULL,
+    last_value BIGINT NOT NULL,
+    PRIMARY KEY (kind, scope)
+);
+
 -- Create indexes for better performance
 CREATE INDEX idx_inventory_active ON inventory(is_active);
 CREATE INDEX idx_inventory_category ON inventory(category);
 CREATE INDEX idx_inventory_supplier ON inventory(default_supplier_id);
 CREATE INDEX idx_inventory_logs_item ON inventory_logs(inventory_id);
 CREATE INDEX idx_inventory_logs_date ON inventory_logs(created_at);
+CREATE INDEX idx_product_translations_locale ON product_translations(locale);
+CREATE INDEX idx_inventory_lots_item ON inventory_lots(inventory_id);
+CREATE INDEX idx_inventory_lots_open ON inventory_lots(inventory_id, expiry_date) WHERE remaining_quantity > 0;
+CREATE INDEX idx_inventory_lots_batch ON inventory_lots(production_batch_id);
 CREATE INDEX idx_production_batches_date ON production_batches(production_date DESC);
 CREATE INDEX idx_production_batches_start_date ON production_batches(start_date DESC);
 CREATE INDEX idx_production_batches_product ON production_batches(product_inventory_id);
 CREATE INDEX idx_production_batches_status ON production_batches(status);
 CREATE INDEX idx_production_batches_status_active ON production_batches(status, start_date DESC)
     WHERE status IN ('in_progress');
+-- A vessel can hold only one in-progress batch at a time
+CREATE UNIQUE INDEX idx_production_batches_vessel_active ON production_batches(vessel_id)
+    WHERE status = 'in_progress' AND vessel_id IS NOT NULL;
 CREATE INDEX idx_production_batch_ingredients_batch ON production_batch_ingredients(batch_id);
 CREATE INDEX idx_production_batch_ingredients_ingredient ON production_batch_ingredients(ingredient_inventory_id);
+CREATE INDEX idx_production_batch_packaging_batch ON production_batch_packaging(batch_id);
+CREATE INDEX idx_production_batch_outputs_inventory ON production_batch_outputs(inventory_id);
 CREATE INDEX idx_recipe_templates_product ON recipe_templates(product_inventory_id) WHERE product_inventory_id IS NOT NULL;
 CREATE INDEX idx_recipe_templates_active ON recipe_templates(is_active) WHERE is_active = true;
+CREATE INDEX idx_pack_sizes_bulk ON pack_sizes(bulk_inventory_id);
+CREATE INDEX idx_storage_transition_rules_recipe ON storage_transition_rules(recipe_template_id);
+CREATE UNIQUE INDEX idx_batch_stages_current ON batch_stages(batch_id) WHERE completed_at IS NULL;
+CREATE INDEX idx_recipe_task_templates_recipe ON recipe_task_templates(recipe_template_id);
+CREATE INDEX idx_batch_tasks_due ON batch_tasks(due_date) WHERE status = 'pending';
+CREATE INDEX idx_batch_move_tasks_batch ON batch_move_tasks(batch_id);
+CREATE INDEX idx_batch_move_tasks_pending ON batch_move_tasks(due_date) WHERE status = 'pending';
+CREATE INDEX idx_batch_readings_batch ON batch_readings(batch_id, recorded_at DESC);
+CREATE INDEX idx_batch_alerts_batch ON batch_alerts(batch_id, created_at DESC);
+CREATE INDEX idx_quality_checks_batch ON quality_checks(batch_id, checked_at);
+CREATE INDEX idx_batch_alerts_open ON batch_alerts(created_at DESC) WHERE acknowledged_at IS NULL;
+CREATE INDEX idx_lot_consumptions_lot ON lot_consumptions(lot_id);
+CREATE INDEX idx_lot_consumptions_batch ON lot_consumptions(production_batch_id);
+CREATE INDEX idx_lot_consumptions_sale_item ON lot_consumptions(sale_item_id);
+CREATE INDEX idx_complaints_status ON complaints(status);
+CREATE INDEX idx_complaints_batch ON complaints(production_batch_id);
+CREATE INDEX idx_complaint_lot_reviews_complaint ON complaint_lot_reviews(complaint_id);
+CREATE UNIQUE INDEX idx_webhook_events_delivery ON webhook_events(provider, event_id) WHERE status = 'received';
+CREATE INDEX idx_legacy_import_rows_status ON legacy_import_rows(record_type, status);
+CREATE INDEX idx_webhook_events_received ON webhook_events(received_at);
+CREATE INDEX idx_stripe_payment_links_sale ON stripe_payment_links(sale_id);
+CREATE INDEX idx_square_orders_status ON square_orders(status, closed_at);
+CREATE INDEX idx_shopify_orders_status ON shopify_orders(status, processed_at);
+CREATE INDEX idx_shopify_sync_conflicts_open ON shopify_sync_conflicts(created_at) WHERE resolved_at IS NULL;
+CREATE INDEX idx_vessels_status ON vessels(status) WHERE is_active = true;
+CREATE INDEX idx_sanitation_logs_vessel ON sanitation_logs(vessel_id, cleaned_at);
+CREATE INDEX idx_stock_counts_pending ON stock_counts(counted_at) WHERE status = 'pending';
+CREATE INDEX idx_stock_count_photos_count ON stock_count_photos(stock_count_id);
+CREATE INDEX idx_business_calendar_dates ON business_calendar(start_date, end_date);
+CREATE INDEX idx_market_sessions_date ON market_sessions(session_date);
+CREATE INDEX idx_market_session_staff_session ON market_session_staff(session_id);
 CREATE INDEX idx_customers_name ON customers(name);
 CREATE INDEX idx_customers_email ON customers(email);
 CREATE INDEX idx_customers_active ON customers(is_active);
 CREATE INDEX idx_sales_date ON sales(sale_date DESC);
 CREATE INDEX idx_sales_customer ON sales(customer_id);
 CREATE INDEX idx_sales_status ON sales(payment_status);
+CREATE INDEX idx_sales_stock_review ON sales(stock_review_status) WHERE stock_review_status IS NOT NULL;
+CREATE INDEX idx_sales_market_session ON sales(market_session_id) WHERE market_session_id IS NOT NULL;
 CREATE INDEX idx_sale_items_sale ON sale_items(sale_id);
 CREATE INDEX idx_sale_items_inventory ON sale_items(inventory_id);
+CREATE INDEX idx_sale_discounts_sale ON sale_discounts(sale_id);
+CREATE INDEX idx_sale_discounts_promo_code ON sale_discounts(promo_code_id);
+CREATE INDEX idx_invoices_customer ON invoices(customer_id);
+CREATE INDEX idx_invoices_open ON invoices(due_on) WHERE status IN ('open', 'partially_paid');
+CREATE INDEX idx_invoice_payments_invoice ON invoice_payments(invoice_id);
+CREATE INDEX idx_customer_orders_open ON customer_orders(delivery_date) WHERE status IN ('draft', 'confirmed');
+CREATE INDEX idx_customer_orders_customer ON customer_orders(customer_id);
+CREATE INDEX idx_customer_order_items_order ON customer_order_items(order_id);
+CREATE INDEX idx_kit_assemblies_kit ON kit_assemblies(kit_inventory_id, assembled_at DESC);
+CREATE INDEX idx_recipe_ingredients_inventory ON recipe_ingredients(inventory_id);
+CREATE INDEX idx_workshops_starts_at ON workshops(starts_at);
+CREATE INDEX idx_workshop_attendees_workshop ON workshop_attendees(workshop_id);
+CREATE INDEX idx_workshop_attendees_purchased ON workshop_attendees(purchased_at);
+CREATE INDEX idx_purchase_orders_open ON purchase_orders(expected_date) WHERE status = 'open';
+CREATE INDEX idx_purchase_order_items_inventory ON purchase_order_items(inventory_id);
+CREATE INDEX idx_purchase_orders_contract ON purchase_orders(contract_id) WHERE contract_id IS NOT NULL;
+CREATE INDEX idx_supplier_contracts_supplier ON supplier_contracts(supplier_id, status);
+CREATE INDEX idx_planned_batches_target ON planned_batches(target_date) WHERE status = 'planned';
+CREATE INDEX idx_api_errors_trace ON api_errors(trace_id);
+CREATE INDEX idx_api_errors_created ON api_errors(created_at);
+CREATE INDEX idx_refresh_tokens_user ON refresh_tokens(user_id);
+CREATE INDEX idx_action_links_batch ON action_links(batch_id);
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_webhook_deliveries_subscription ON webhook_deliveries(subscription_id, created_at DESC);
+CREATE INDEX idx_mutation_audit_entity ON mutation_audit(entity_id, changed_at);
+CREATE INDEX idx_mutation_audit_user ON mutation_audit(user_id, changed_at);
+CREATE INDEX idx_pickup_windows_date ON pickup_windows(pickup_date);
+CREATE INDEX idx_pickup_bookings_window ON pickup_bookings(pickup_window_id);
+CREATE INDEX idx_pickup_bookings_customer ON pickup_bookings(customer_id, status);
+CREATE INDEX idx_supplier_catalog_items_inventory ON supplier_catalog_items(inventory_id);
+CREATE INDEX idx_supplier_catalog_imports_supplier ON supplier_catalog_imports(supplier_id, created_at DESC);
+CREATE INDEX idx_supplier_catalog_changes_import ON supplier_catalog_changes(import_id);
+CREATE INDEX idx_api_usage_client ON api_usage(client_id, bucket);
+CREATE INDEX idx_plan_allocations_plan ON plan_allocations(planned_batch_id, status);
+CREATE INDEX idx_purchase_orders_status ON purchase_orders(status);
+CREATE INDEX idx_change_log_cursor ON change_log(txid, id);
+CREATE INDEX idx_domain_events_cursor ON domain_events(txid, id);
+CREATE UNIQUE INDEX idx_dashboards_default ON dashboards(user_id) WHERE is_default;
+
+-- ============================================================================
+-- Business Calendar
+-- ============================================================================
+
+-- First day on or after from_date that no calendar entry closes for the
+-- purpose ('production' or 'delivery'); NULL if closed for more than a year
+CREATE FUNCTION next_open_day(from_date DATE, purpose TEXT) RETURNS DATE AS $$
+    SELECT MIN(day)::date
+    FROM generate_series(from_date, from_date + 366, INTERVAL '1 day') AS day
+    WHERE NOT EXISTS (
+        SELECT 1
+        FROM business_calendar c
+        WHERE day::date BETWEEN c.start_date AND c.end_date
+          AND CASE purpose
+                  WHEN 'production' THEN c.blocks_production
+                  WHEN 'delivery' THEN c.blocks_deliveries
+                  ELSE false
+              END
+    );
+$$ LANGUAGE sql STABLE;
+
+-- ============================================================================
+-- Stock Valuation
+-- ============================================================================
+
+-- Value each stock movement as it is written: an entry without a unit cost
+-- takes the item's cost_per_unit at that moment, so valuations can be
+-- replayed later without guessing what stock cost back then
+CREATE FUNCTION value_inventory_log() RETURNS trigger AS $$
+BEGIN
+    IF NEW.unit_cost IS NULL THEN
+        SELECT cost_per_unit INTO NEW.unit_cost FROM inventory WHERE id = NEW.inventory_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER inventory_logs_value BEFORE INSERT ON inventory_logs
+    FOR EACH ROW EXECUTE FUNCTION value_inventory_log();
+
+-- ============================================================================
+-- Change Tracking
+-- ============================================================================
+
+-- Log every insert, update, and delete on exported tables to change_log
+CREATE FUNCTION record_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO change_log (entity, entity_id, operation)
+        VALUES (TG_TABLE_NAME, OLD.id, 'delete');
+        RETURN OLD;
+    END IF;
+
+    INSERT INTO change_log (entity, entity_id, operation, row_data)
+    VALUES (TG_TABLE_NAME, NEW.id, lower(TG_OP), to_jsonb(NEW));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Record changes made by a signed-in user or API key to mutation_audit. The app tags each
+-- write-pool connection with app.actor_id / app.actor as it hands it out (services/audit.rs);
+-- background jobs, probes and webhooks write untagged and aren't audited
+CREATE FUNCTION record_audit() RETURNS trigger AS $$
+DECLARE
+    actor_id TEXT := current_setting('app.actor_id', true);
+    row_id UUID;
+    diff JSONB;
+BEGIN
+    IF COALESCE(actor_id, '') = '' THEN
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'INSERT' THEN
+        row_id := NEW.id;
+        diff := to_jsonb(NEW);
+    ELSIF TG_OP = 'DELETE' THEN
+        row_id := OLD.id;
+        diff := to_jsonb(OLD);
+    ELSE
+        row_id := NEW.id;
+        SELECT jsonb_object_agg(n.key, jsonb_build_object('old', o.value, 'new', n.value))
+        INTO diff
+        FROM jsonb_each(to_jsonb(NEW)) n
+        JOIN jsonb_each(to_jsonb(OLD)) o USING (key)
+        WHERE n.value IS DISTINCT FROM o.value AND n.key <> 'updated_at';
+
+        IF diff IS NULL THEN
+            RETURN NULL;
+        END IF;
+    END IF;
+
+    INSERT INTO mutation_audit (user_id, actor, entity, entity_id, operation, diff)
+    VALUES (
+        actor_id::uuid, current_setting('app.actor', true), TG_TABLE_NAME, row_id, lower(TG_OP), diff
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Keep in sync with TRACKED_ENTITIES in backend/src/services/changes.rs.
+-- Secrets, webhook payloads, count photos, label PDFs, idempotency keys, import bookkeeping,
+-- and catalog change lines are deliberately not exported.
+DO $$
+DECLARE
+    tracked TEXT;
+BEGIN
+    FOREACH tracked IN ARRAY ARRAY[
+        'suppliers', 'units', 'inventory_categories', 'locations', 'inventory',
+        'inventory_logs', 'inventory_lots', 'supplier_contracts', 'supplier_contract_items',
+        'purchase_orders', 'purchase_order_items', 'purchase_approval_limits',
+        'supplier_catalog_items', 'supplier_catalog_imports', 'stock_counts',
+        'product_translations', 'recipe_templates', 'recipe_ingredients', 'recipe_cost_watches',
+        'overhead_pools', 'product_developments', 'product_development_stages', 'vessels',
+        'sanitation_logs', 'production_batches', 'production_batch_ingredients',
+        'production_batch_packaging', 'production_batch_outputs', 'pack_sizes',
+        'kit_components', 'kit_assemblies', 'storage_transition_rules', 'recipe_stages',
+        'batch_stages', 'recipe_task_templates', 'batch_tasks', 'planned_batches',
+        'plan_allocations', 'batch_move_tasks', 'batch_readings', 'batch_alerts',
+        'quality_checks', 'business_calendar', 'markets', 'market_sessions',
+        'market_session_staff', 'customers', 'sales', 'sale_items', 'promo_codes',
+        'sale_discounts', 'invoices', 'invoice_payments', 'customer_orders',
+        'customer_order_items', 'sale_channel_policies', 'price_list_items', 'customer_prices',
+        'square_item_mappings', 'shopify_products', 'pickup_windows', 'pickup_bookings',
+        'lot_consumptions', 'workshops', 'workshop_kit_items', 'workshop_attendees',
+        'complaints', 'complaint_lot_reviews', 'trace_page_fields'
+    ] LOOP
+        EXECUTE format(
+            'CREATE TRIGGER %I AFTER INSERT OR UPDATE OR DELETE ON %I
+             FOR EACH ROW EXECUTE FUNCTION record_change()',
+            tracked || '_change_log', tracked
+        );
+        EXECUTE format(
+            'CREATE TRIGGER %I AFTER INSERT OR UPDATE OR DELETE ON %I
+             FOR EACH ROW EXECUTE FUNCTION record_audit()',
+            tracked || '_audit', tracked
+        );
+    END LOOP;
+END $$;
 
 -- ============================================================================
 -- Sample Test Data
@@ -185,6 +1647,11 @@ INSERT INTO suppliers (id, name, contact_email, contact_phone, street_address, c
 ('f0444eba-2b22-4ece-8303-f29764611009', 'Home Garden', '', '', '8 Fairview Ave', 'Frederick', 'MD', '21701', 'USA', 39.416313, -77.431301, '', NOW(), NOW());
 
 -- Insert inventory items
+INSERT INTO locations (name, location_type, description) VALUES
+('Walk-in Cooler', 'cold_storage', 'Finished product and fermenting crocks'),
+('Dry Storage', 'dry_storage', 'Salt, spices, jars and lids'),
+('Farmers Market Van', 'vehicle', 'Stock loaded for market days');
+
 INSERT INTO inventory (id, name, category, unit, current_stock, reserved_stock, reorder_point, cost_per_unit, default_supplier_id, shelf_life_days, storage_requirements, is_active, created_at, updated_at) VALUES
 ('fc8bc8a3-c8cf-4f05-b141-732aef691d63', 'Cabbage', 'Vegetable', 'grams', 0, 0, 100, NULL, '75bf2f4b-d30d-4258-8b62-2937525a714f', 14, 'Store in fridge', true, NOW(), NOW()),
 ('201721bd-29a3-4fd9-9b55-528e85838eba', 'Salt', 'minerial', 'grams', 0, 0, 20, NULL, '75bf2f4b-d30d-4258-8b62-2937525a714f', NULL, 'Keep dry', true, NOW(), NOW()),
@@ -292,4 +1759,40 @@ Completion:
 When fermentation is complete, seal jar tightly
 Transfer to refrigerator for long-term storage
 Will keep for several months refrigerated',
-true, NOW(), NOW());
\ No newline at end of file
+true, NOW(), NOW());
+
+-- Move JSONB ingredient templates into recipe_ingredients (safe to re-run on existing databases;
+-- entries naming unknown inventory items or without a positive quantity are skipped)
+INSERT INTO recipe_ingredients (recipe_template_id, inventory_id, quantity_per_batch, unit, position)
+SELECT r.id, i.id, (e.value->>'quantity_per_batch')::DECIMAL, COALESCE(e.value->>'unit', i.unit), e.ordinality
+FROM recipe_templates r
+CROSS JOIN LATERAL jsonb_array_elements(
+    CASE jsonb_typeof(r.ingredient_template)
+        WHEN 'array' THEN r.ingredient_template
+        WHEN 'object' THEN COALESCE(r.ingredient_template->'ingredients', '[]'::jsonb)
+        ELSE '[]'::jsonb
+    END
+) WITH ORDINALITY AS e(value, ordinality)
+JOIN inventory i ON i.id::text = e.value->>'inventory_id'
+WHERE (e.value->>'quantity_per_batch') ~ '^[0-9]*\.?[0-9]+$'
+    AND (e.value->>'quantity_per_batch')::DECIMAL > 0
+ON CONFLICT (recipe_template_id, inventory_id) DO NOTHING;
+-- Least-privilege roles for the API: ff_app reads and writes rows (DATABASE_URL) and
+-- ff_reporting only reads (DATABASE_READONLY_URL). Neither can change the schema; migrations
+-- run as the owner. Give them passwords per environment: ALTER ROLE ff_app PASSWORD '...';
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'ff_app') THEN
+        CREATE ROLE ff_app LOGIN;
+    END IF;
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'ff_reporting') THEN
+        CREATE ROLE ff_reporting LOGIN;
+    END IF;
+    EXECUTE format('GRANT CONNECT ON DATABASE %I TO ff_app, ff_reporting', current_database());
+END $$;
+
+GRANT USAGE ON SCHEMA public TO ff_app, ff_reporting;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO ff_app;
+GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO ff_app;
+GRANT SELECT ON ALL TABLES IN SCHEMA public TO ff_reporting;
+REVOKE SELECT ON users, refresh_tokens, api_keys, action_links FROM ff_reporting; -- credentials stay with the app role